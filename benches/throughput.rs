@@ -0,0 +1,67 @@
+//! Parse throughput benchmarks
+//!
+//! Each `benches/fixtures/*` corpus is repeated up to roughly 1 KB, 10 KB
+//! and 100 KB and parsed with the default options. Criterion reports the
+//! throughput in bytes per second, so regressions in the regex pipeline
+//! show up as a drop in MB/s:
+//!
+//! ```sh
+//! cargo bench-umd
+//! cargo bench-umd -- tables/large   # a single corpus and size
+//! ```
+
+use std::fs;
+use std::hint::black_box;
+use std::path::Path;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use umd::parse_with_frontmatter_opts;
+use umd::parser::ParserOptions;
+
+/// Fixture documents, one per kind of content
+const CORPORA: &[(&str, &str)] = &[
+    ("plain", "plain.md"),
+    ("plugins", "plugins.umd"),
+    ("tables", "tables.umd"),
+    ("cjk", "cjk.umd"),
+];
+
+/// Target document sizes in bytes
+const SIZES: &[(&str, usize)] = &[("small", 1_024), ("medium", 10_240), ("large", 102_400)];
+
+fn load_fixture(file: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("benches/fixtures")
+        .join(file);
+    fs::read_to_string(&path).unwrap_or_else(|error| panic!("{}: {}", path.display(), error))
+}
+
+/// Repeat the fixture until the document reaches `size` bytes
+fn scale(fixture: &str, size: usize) -> String {
+    let copies = size.div_ceil(fixture.len()).max(1);
+    let mut document = String::with_capacity(copies * (fixture.len() + 1));
+    for _ in 0..copies {
+        document.push_str(fixture);
+        document.push('\n');
+    }
+    document
+}
+
+fn bench_throughput(c: &mut Criterion) {
+    let options = ParserOptions::default();
+    for (corpus, file) in CORPORA {
+        let fixture = load_fixture(file);
+        let mut group = c.benchmark_group(*corpus);
+        for (label, size) in SIZES {
+            let document = scale(&fixture, *size);
+            group.throughput(Throughput::Bytes(document.len() as u64));
+            group.bench_with_input(BenchmarkId::from_parameter(label), &document, |b, input| {
+                b.iter(|| parse_with_frontmatter_opts(black_box(input), &options))
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_throughput);
+criterion_main!(benches);
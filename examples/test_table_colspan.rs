@@ -25,7 +25,7 @@ fn main() {
     let rowspan_basic = r#"| Header1 | Header2 |
 |---------|---------|
 | Cell1   | Cell2   |
-| |^      | Cell4   |"#;
+|^        | Cell4   |"#;
     println!("Example 3: Basic rowspan");
     println!("Input:\n{}\n", rowspan_basic);
     println!("Output:\n{}\n", parse(rowspan_basic));
@@ -35,7 +35,7 @@ fn main() {
     let combined = r#"| Header1 |> | Header3 |
 |---------|--|---------|
 | Span2x2 |> | Cell3   |
-| |^      |^ | Cell4   |
+|^        |^ | Cell4   |
 | Cell5   | Cell6 | Cell7 |"#;
     println!("Example 4: Combined colspan and rowspan");
     println!("Input:\n{}\n", combined);
@@ -0,0 +1,14 @@
+//! Fuzz target for the full parse pipeline
+//!
+//! Run with `cargo +nightly fuzz run parse` from the repository root.
+//! Any panic is a bug: `parse()` must accept arbitrary input.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(input) = std::str::from_utf8(data) {
+        let _ = umd::parse(input);
+    }
+});
@@ -0,0 +1,36 @@
+/*
+ * C ABI of the umd library, built with `cargo build --release --features ffi`
+ * (target/release/libumd.so, libumd.dylib or umd.dll).
+ *
+ * All strings are NUL-terminated UTF-8. See src/ffi.rs for details.
+ */
+
+#ifndef UMD_H
+#define UMD_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * Parse Universal Markdown and return HTML.
+ *
+ * options_json: NULL, or options as JSON with the keys of the WASM API
+ * (e.g. {"baseUrl":"/app"}). Invalid JSON falls back to the defaults.
+ *
+ * Returns a string to be released with umd_free(), or NULL when input is
+ * NULL or not UTF-8, or when the parser failed internally.
+ */
+char *umd_parse(const char *input, const char *options_json);
+
+/* Release a string returned by umd_parse(). NULL is ignored. */
+void umd_free(char *value);
+
+/* Library version, e.g. "0.1.1". Static, do not release. */
+const char *umd_version(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* UMD_H */
@@ -0,0 +1,193 @@
+//! `umd` command line tool
+//!
+//! Renders a Universal Markdown file, or stdin, to HTML on stdout, so build
+//! scripts can use the parser without a wrapper program. Built with the
+//! `cli` feature:
+//!
+//! ```sh
+//! cargo install umd --features cli
+//! umd page.umd --base-url /docs --toc > page.html
+//! umd fmt page.umd > formatted.umd
+//! ```
+
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+use std::{env, fs};
+
+use umd::extensions::toc::{apply_toc, render_toc_placeholder};
+use umd::parser::ParserOptions;
+use umd::{options_from_json, parse_with_frontmatter_opts};
+
+const USAGE: &str = "\
+Usage: umd [OPTIONS] [FILE]
+       umd fmt [FILE]
+
+Render Universal Markdown to HTML on stdout. Reads stdin when FILE is
+omitted or `-`. `umd fmt` prints the source in canonical formatting
+instead.
+
+Options:
+      --base-url <URL>       Prefix absolute links and media paths with URL
+      --options-file <FILE>  Parser options as JSON (the keys of the WASM API)
+      --toc                  Insert a table of contents before the body
+      --frontmatter-json     Print the frontmatter scalars as a JSON object
+                             instead of the HTML
+  -h, --help                 Print this help
+  -V, --version              Print the version
+";
+
+/// Parsed command line
+#[derive(Debug, Default)]
+struct Args {
+    input: Option<String>,
+    base_url: Option<String>,
+    options_file: Option<String>,
+    toc: bool,
+    frontmatter_json: bool,
+}
+
+enum Command {
+    Render(Args),
+    Format(Option<String>),
+    Help,
+    Version,
+}
+
+fn parse_args(raw: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut raw = raw.peekable();
+    if raw.next_if(|arg| arg == "fmt").is_some() {
+        return parse_format_args(raw);
+    }
+    let mut args = Args::default();
+    while let Some(arg) = raw.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value)),
+            _ => (arg.clone(), None),
+        };
+        let mut value = |name: &str| {
+            inline_value
+                .map(str::to_string)
+                .or_else(|| raw.next())
+                .ok_or_else(|| format!("{} requires a value", name))
+        };
+        match flag.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "--base-url" => args.base_url = Some(value("--base-url")?),
+            "--options-file" => args.options_file = Some(value("--options-file")?),
+            "--toc" => args.toc = true,
+            "--frontmatter-json" => args.frontmatter_json = true,
+            "-" => args.input = None,
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+            _ if args.input.is_some() => return Err(format!("unexpected argument: {}", arg)),
+            _ => args.input = Some(arg),
+        }
+    }
+    Ok(Command::Render(args))
+}
+
+fn parse_format_args(raw: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut input = None;
+    for arg in raw {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-" => input = None,
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+            _ if input.is_some() => return Err(format!("unexpected argument: {}", arg)),
+            _ => input = Some(arg),
+        }
+    }
+    Ok(Command::Format(input))
+}
+
+fn read_input(input: &Option<String>) -> Result<String, String> {
+    match input {
+        Some(path) => fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e)),
+        None => {
+            let mut input = String::new();
+            io::stdin()
+                .read_to_string(&mut input)
+                .map_err(|e| format!("stdin: {}", e))?;
+            Ok(input)
+        }
+    }
+}
+
+fn render(args: &Args) -> Result<String, String> {
+    let input = read_input(&args.input)?;
+
+    let mut options = match &args.options_file {
+        Some(path) => {
+            let json = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+            options_from_json(&json).map_err(|e| format!("{}: {}", path, e))?
+        }
+        None => ParserOptions::default(),
+    };
+    if let Some(base_url) = &args.base_url {
+        options.base_url = Some(base_url.clone());
+    }
+
+    let result = parse_with_frontmatter_opts(&input, &options);
+    if args.frontmatter_json {
+        let scalars: serde_json::Map<String, serde_json::Value> = result
+            .frontmatter
+            .map(|frontmatter| frontmatter.scalars())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(key, value)| (key, serde_json::Value::String(value)))
+            .collect();
+        return Ok(format!("{}\n", serde_json::Value::Object(scalars)));
+    }
+
+    let mut html = String::new();
+    if let Some(css) = &result.color_palette {
+        html.push_str(&format!(
+            "<style class=\"umd-color-palette\">\n{}</style>\n",
+            css
+        ));
+    }
+    if args.toc {
+        let body = format!("{}\n{}", render_toc_placeholder(""), result.html);
+        html.push_str(&apply_toc(&body, &options.locale));
+    } else {
+        html.push_str(&result.html);
+    }
+    if let Some(footnotes) = &result.footnotes {
+        html.push('\n');
+        html.push_str(footnotes);
+    }
+    if !html.ends_with('\n') {
+        html.push('\n');
+    }
+    Ok(html)
+}
+
+fn main() -> ExitCode {
+    let output = match parse_args(env::args().skip(1)) {
+        Ok(Command::Render(args)) => render(&args),
+        Ok(Command::Format(input)) => read_input(&input).map(|input| umd::format(&input)),
+        Ok(Command::Help) => {
+            print!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Ok(Command::Version) => {
+            println!("umd {}", env!("CARGO_PKG_VERSION"));
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("umd: {}\n\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    match output {
+        Ok(output) => {
+            // A closed pipe (`umd page.umd | head`) is not an error
+            let _ = io::stdout().lock().write_all(output.as_bytes());
+            ExitCode::SUCCESS
+        }
+        Err(message) => {
+            eprintln!("umd: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
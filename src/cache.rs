@@ -0,0 +1,258 @@
+//! Output caching by content and options hash
+//!
+//! Hosts that render the same page over and over can set
+//! [`ParserOptions::cache`](crate::parser::ParserOptions::cache) to skip the
+//! whole pipeline for input they have already rendered with the same
+//! options. [`LruCache`] keeps the most recently used results in memory;
+//! other stores (Redis, a disk cache, ...) implement [`Cache`].
+//!
+//! Keys are stable within one build of the library, so a shared store must
+//! be flushed when the library is upgraded. With [`IdStrategy::Random`]
+//! (the default) a cached result repeats the generated IDs of the first
+//! render.
+//!
+//! [`IdStrategy::Random`]: crate::parser::IdStrategy::Random
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Mutex;
+
+use crate::ParseResult;
+use crate::parser::ParserOptions;
+
+/// Cache key of one rendered document
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CacheKey {
+    /// 128-bit hash of the input text
+    pub content_hash: u128,
+    /// Hash of every option that affects the output
+    pub options_hash: u64,
+}
+
+impl CacheKey {
+    /// Key of `input` rendered with `options`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use umd::cache::CacheKey;
+    /// use umd::parser::ParserOptions;
+    ///
+    /// let options = ParserOptions::default();
+    /// assert_eq!(CacheKey::new("# A", &options), CacheKey::new("# A", &options));
+    /// assert_ne!(CacheKey::new("# A", &options), CacheKey::new("# B", &options));
+    /// ```
+    pub fn new(input: &str, options: &ParserOptions) -> Self {
+        Self {
+            content_hash: content_hash(input),
+            options_hash: options_hash(options),
+        }
+    }
+}
+
+/// Store of rendered documents consulted by the parse functions
+///
+/// `get` and `put` take `&self` so one cache can be shared between threads
+/// through an `Arc`; implementations use interior mutability.
+pub trait Cache: Send + Sync + fmt::Debug {
+    /// Rendered result for `key`, if cached
+    fn get(&self, key: &CacheKey) -> Option<ParseResult>;
+
+    /// Store the rendered result for `key`
+    fn put(&self, key: CacheKey, result: &ParseResult);
+}
+
+/// In-memory cache evicting the least recently used document
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use umd::cache::LruCache;
+/// use umd::parse_with_frontmatter_opts;
+/// use umd::parser::ParserOptions;
+///
+/// let cache = Arc::new(LruCache::new(100));
+/// let mut options = ParserOptions::default();
+/// options.cache = Some(cache.clone());
+///
+/// let first = parse_with_frontmatter_opts("# Title", &options);
+/// let second = parse_with_frontmatter_opts("# Title", &options);
+/// assert_eq!(first.html, second.html);
+/// assert_eq!(cache.len(), 1);
+/// ```
+#[derive(Debug)]
+pub struct LruCache {
+    capacity: usize,
+    state: Mutex<LruState>,
+}
+
+#[derive(Debug, Default)]
+struct LruState {
+    /// Cached results with the tick of their last use
+    entries: HashMap<CacheKey, (ParseResult, u64)>,
+    /// Keys ordered by the tick of their last use
+    recency: BTreeMap<u64, CacheKey>,
+    tick: u64,
+}
+
+impl LruState {
+    fn touch(&mut self, key: CacheKey, last_used: u64) -> u64 {
+        self.recency.remove(&last_used);
+        self.tick += 1;
+        self.recency.insert(self.tick, key);
+        self.tick
+    }
+}
+
+impl LruCache {
+    /// Create a cache holding at most `capacity` documents
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Mutex::new(LruState::default()),
+        }
+    }
+
+    /// Maximum number of cached documents
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of cached documents
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Whether no document is cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove every cached document
+    pub fn clear(&self) {
+        let mut state = self.lock();
+        state.entries.clear();
+        state.recency.clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LruState> {
+        // A key left in `recency` by a panic is skipped on eviction
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Cache for LruCache {
+    fn get(&self, key: &CacheKey) -> Option<ParseResult> {
+        let mut state = self.lock();
+        let last_used = state.entries.get(key)?.1;
+        let tick = state.touch(*key, last_used);
+        let entry = state.entries.get_mut(key)?;
+        entry.1 = tick;
+        Some(entry.0.clone())
+    }
+
+    fn put(&self, key: CacheKey, result: &ParseResult) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut state = self.lock();
+        let last_used = state.entries.get(&key).map_or(0, |entry| entry.1);
+        let tick = state.touch(key, last_used);
+        state.entries.insert(key, (result.clone(), tick));
+        while state.entries.len() > self.capacity {
+            let Some((_, oldest)) = state.recency.pop_first() else {
+                break;
+            };
+            state.entries.remove(&oldest);
+        }
+    }
+}
+
+/// 128-bit hash from two independently seeded SipHash passes
+fn content_hash(input: &str) -> u128 {
+    let half = |seed: u8| {
+        let mut hasher = DefaultHasher::new();
+        seed.hash(&mut hasher);
+        input.hash(&mut hasher);
+        hasher.finish()
+    };
+    (u128::from(half(0)) << 64) | u128::from(half(1))
+}
+
+/// Hash of the options, excluding the cache itself
+fn options_hash(options: &ParserOptions) -> u64 {
+    let mut keyed = options.clone();
+    keyed.cache = None;
+    let mut hasher = DefaultHasher::new();
+    // Not every option type implements `Hash`; the debug output covers all
+    // fields. Maps built separately may list their entries in another order,
+    // which only costs a cache miss.
+    format!("{:?}", keyed).hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(html: &str) -> ParseResult {
+        ParseResult {
+            html: html.to_string(),
+            frontmatter: None,
+            footnotes: None,
+            diagnostics: Vec::new(),
+            color_palette: None,
+            first_image: None,
+            sections: Vec::new(),
+        }
+    }
+
+    fn key(input: &str) -> CacheKey {
+        CacheKey::new(input, &ParserOptions::default())
+    }
+
+    #[test]
+    fn test_options_change_the_key() {
+        let mut options = ParserOptions::default();
+        let plain = CacheKey::new("x", &options);
+        options.base_url = Some("/app".to_string());
+        let rebased = CacheKey::new("x", &options);
+        assert_eq!(plain.content_hash, rebased.content_hash);
+        assert_ne!(plain.options_hash, rebased.options_hash);
+    }
+
+    #[test]
+    fn test_lru_evicts_least_recently_used() {
+        let cache = LruCache::new(2);
+        cache.put(key("a"), &result("A"));
+        cache.put(key("b"), &result("B"));
+        assert_eq!(cache.get(&key("a")).unwrap().html, "A");
+        cache.put(key("c"), &result("C"));
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&key("b")).is_none());
+        assert!(cache.get(&key("a")).is_some());
+        assert!(cache.get(&key("c")).is_some());
+    }
+
+    #[test]
+    fn test_lru_put_replaces_entry() {
+        let cache = LruCache::new(2);
+        cache.put(key("a"), &result("A"));
+        cache.put(key("a"), &result("A2"));
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(&key("a")).unwrap().html, "A2");
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_zero_capacity_stores_nothing() {
+        let cache = LruCache::new(0);
+        cache.put(key("a"), &result("A"));
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,166 @@
+//! CommonMark / GFM specification compliance
+//!
+//! Runs spec examples in the format of the official `spec.json`
+//! (<https://spec.commonmark.org/>) through the parser and reports which
+//! examples pass. HTML is compared after a light normalization (attribute
+//! order, self-closing slashes, trailing whitespace), similar to the
+//! normalization of the reference spec runner.
+
+use std::collections::BTreeMap;
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use serde::Deserialize;
+
+use crate::parser::{Dialect, ParserOptions};
+
+/// Regex to detect an opening or self-closing tag with its attributes
+static START_TAG: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"<([a-zA-Z][a-zA-Z0-9-]*)((?:\s+[^\s"'=<>/]+(?:="[^"]*")?)*)\s*/?>"#).unwrap()
+});
+
+/// Regex to split tag attributes
+static ATTRIBUTE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"[^\s"'=<>/]+(?:="[^"]*")?"#).unwrap());
+
+/// Single spec example
+#[derive(Debug, Clone, Deserialize)]
+pub struct SpecExample {
+    /// Markdown input
+    pub markdown: String,
+    /// Expected HTML
+    pub html: String,
+    /// Example number
+    pub example: u32,
+    /// Spec section (e.g. `Tabs`, `Emphasis and strong emphasis`)
+    pub section: String,
+}
+
+/// Failed spec example
+#[derive(Debug, Clone)]
+pub struct SpecFailure {
+    /// Example number
+    pub example: u32,
+    /// Spec section
+    pub section: String,
+    /// Markdown input
+    pub markdown: String,
+    /// Expected HTML
+    pub expected: String,
+    /// HTML produced by the parser
+    pub actual: String,
+}
+
+/// Result of running spec examples
+#[derive(Debug, Clone, Default)]
+pub struct ComplianceReport {
+    /// Number of examples run
+    pub total: usize,
+    /// Number of passing examples
+    pub passed: usize,
+    /// Passed / total examples per section
+    pub sections: BTreeMap<String, (usize, usize)>,
+    /// Failing examples, in spec order
+    pub failures: Vec<SpecFailure>,
+}
+
+impl ComplianceReport {
+    /// Share of passing examples (0.0 - 1.0)
+    pub fn pass_rate(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        self.passed as f64 / self.total as f64
+    }
+}
+
+/// Run spec examples through the parser
+///
+/// # Arguments
+///
+/// * `spec_json` - Examples in the format of the official `spec.json`
+/// * `dialect` - Dialect to parse with (`CommonMark` for `spec.json`,
+///   `Gfm` for the GFM extension examples)
+///
+/// # Returns
+///
+/// Compliance report, or the JSON error when `spec_json` is malformed
+///
+/// # Examples
+///
+/// ```
+/// use umd::compliance::run_spec;
+/// use umd::parser::Dialect;
+///
+/// let spec = r#"[{"markdown": "*foo*\n", "html": "<p><em>foo</em></p>\n", "example": 1, "section": "Emphasis"}]"#;
+/// let report = run_spec(spec, Dialect::CommonMark).unwrap();
+/// assert_eq!(report.passed, 1);
+/// ```
+pub fn run_spec(spec_json: &str, dialect: Dialect) -> Result<ComplianceReport, serde_json::Error> {
+    let examples: Vec<SpecExample> = serde_json::from_str(spec_json)?;
+    let options = ParserOptions {
+        dialect,
+        ..ParserOptions::default()
+    };
+
+    let mut report = ComplianceReport::default();
+    for example in examples {
+        let actual = crate::parse_with_frontmatter_opts(&example.markdown, &options).html;
+        let passed = normalize_html(&actual) == normalize_html(&example.html);
+
+        report.total += 1;
+        let section = report.sections.entry(example.section.clone()).or_default();
+        section.1 += 1;
+        if passed {
+            report.passed += 1;
+            section.0 += 1;
+        } else {
+            report.failures.push(SpecFailure {
+                example: example.example,
+                section: example.section,
+                markdown: example.markdown,
+                expected: example.html,
+                actual,
+            });
+        }
+    }
+    Ok(report)
+}
+
+/// Normalize HTML for comparison
+///
+/// Sorts attributes, drops self-closing slashes, trailing whitespace and
+/// blank lines.
+fn normalize_html(html: &str) -> String {
+    let html = START_TAG.replace_all(html, |caps: &Captures| {
+        let mut attributes: Vec<&str> = ATTRIBUTE
+            .find_iter(&caps[2])
+            .map(|attribute| attribute.as_str())
+            .collect();
+        attributes.sort_unstable();
+        let mut tag = format!("<{}", caps[1].to_ascii_lowercase());
+        for attribute in attributes {
+            tag.push(' ');
+            tag.push_str(attribute);
+        }
+        tag.push('>');
+        tag
+    });
+    html.lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_html() {
+        assert_eq!(
+            normalize_html("<p>\n<input type=\"checkbox\" disabled=\"\" /> a<br />\n</p>\n"),
+            "<p>\n<input disabled=\"\" type=\"checkbox\"> a<br>\n</p>"
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//! Diagnostics reported during parsing
+//!
+//! The parser never fails on malformed input; problems that authors may want
+//! to fix (duplicate IDs, unresolved references, ...) are collected as
+//! [`Diagnostic`] values and returned in [`crate::ParseResult::diagnostics`].
+
+use std::fmt;
+
+/// Diagnostic severity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Informational note; output is unaffected
+    Info,
+    /// Output was adjusted or may not be what the author intended
+    Warning,
+    /// Content could not be rendered as written
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A single diagnostic message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Severity level
+    pub severity: Severity,
+    /// Stable machine-readable code (e.g. `duplicate-id`)
+    pub code: &'static str,
+    /// Human-readable description
+    pub message: String,
+    /// 1-based source line, when known
+    pub line: Option<usize>,
+}
+
+impl Diagnostic {
+    /// Create an informational diagnostic without line information
+    pub fn info(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Info,
+            code,
+            message: message.into(),
+            line: None,
+        }
+    }
+
+    /// Create a warning diagnostic without line information
+    pub fn warning(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            code,
+            message: message.into(),
+            line: None,
+        }
+    }
+
+    /// Create an error diagnostic without line information
+    pub fn error(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            code,
+            message: message.into(),
+            line: None,
+        }
+    }
+
+    /// Attach a 1-based source line
+    pub fn with_line(mut self, line: usize) -> Self {
+        self.line = Some(line);
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(
+                f,
+                "{}[{}] line {}: {}",
+                self.severity, self.code, line, self.message
+            ),
+            None => write!(f, "{}[{}]: {}", self.severity, self.code, self.message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let diagnostic = Diagnostic::warning("duplicate-id", "ID `h-a` renamed");
+        assert_eq!(
+            diagnostic.to_string(),
+            "warning[duplicate-id]: ID `h-a` renamed"
+        );
+        assert_eq!(
+            diagnostic.with_line(3).to_string(),
+            "warning[duplicate-id] line 3: ID `h-a` renamed"
+        );
+    }
+}
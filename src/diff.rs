@@ -0,0 +1,242 @@
+//! Block-level diff of two documents
+//!
+//! Wiki history views compare revisions block by block rather than line by
+//! line: both sources are rendered, split into their top-level blocks
+//! (headings, paragraphs, lists, tables, plugin output, ...) and matched
+//! with a longest common subsequence over the rendered HTML. A reworded
+//! paragraph is reported once as [`BlockChange::Changed`] with the HTML of
+//! both revisions, however many source lines it spans.
+//!
+//! Generated IDs are derived from the content
+//! ([`IdStrategy::ContentHash`]), and `id` attributes and in-page links are
+//! ignored when blocks are compared, so a block whose heading anchor was
+//! renumbered by an insertion above it still counts as unchanged. The
+//! footnotes section is compared as the last block.
+
+use std::borrow::Cow;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::parser::{IdStrategy, ParserOptions};
+use crate::sections::top_level_blocks;
+
+/// `id` attributes and in-page link targets, which depend on the position
+/// of the block in the document
+static POSITIONAL_ATTRIBUTE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r##"\s(?:id="[^"]*"|href="#[^"]*")"##).unwrap());
+
+/// How a block differs between the old and the new revision
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockChange {
+    /// Same rendered HTML in both revisions, apart from generated IDs
+    Unchanged,
+    /// Replaced by a different block at the same position
+    Changed,
+    /// Only in the new revision
+    Inserted,
+    /// Only in the old revision
+    Removed,
+}
+
+/// A top-level block of either revision
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockDiff {
+    /// Kind of change
+    pub change: BlockChange,
+    /// Rendered block in the old revision (`None` when inserted)
+    pub old_html: Option<String>,
+    /// Rendered block in the new revision (`None` when removed)
+    pub new_html: Option<String>,
+}
+
+impl BlockDiff {
+    fn new(change: BlockChange, old_html: Option<&str>, new_html: Option<&str>) -> Self {
+        Self {
+            change,
+            old_html: old_html.map(str::to_string),
+            new_html: new_html.map(str::to_string),
+        }
+    }
+}
+
+/// Compare two documents block by block with custom parser options
+///
+/// Returns every block of both revisions in document order, unchanged ones
+/// included, so a history view can render the whole page with the changes
+/// highlighted. Removed and inserted blocks between the same unchanged
+/// neighbours are paired up as [`BlockChange::Changed`].
+///
+/// # Arguments
+///
+/// * `old_src` - The previous Universal Markdown source text
+/// * `new_src` - The current Universal Markdown source text
+/// * `options` - Parser configuration options (`ids` is ignored)
+///
+/// # Returns
+///
+/// Block differences in document order
+pub fn diff_with_options(old_src: &str, new_src: &str, options: &ParserOptions) -> Vec<BlockDiff> {
+    let options = ParserOptions {
+        ids: IdStrategy::ContentHash,
+        ..options.clone()
+    };
+    let old = crate::parse_with_frontmatter_opts(old_src, &options);
+    let new = crate::parse_with_frontmatter_opts(new_src, &options);
+    let blocks = |html: &str, footnotes: Option<&str>| {
+        let mut blocks = top_level_blocks(html);
+        blocks.extend(
+            footnotes
+                .map(str::trim)
+                .filter(|footnotes| !footnotes.is_empty()),
+        );
+        blocks.into_iter().map(str::to_string).collect::<Vec<_>>()
+    };
+    let old_blocks = blocks(&old.html, old.footnotes.as_deref());
+    let new_blocks = blocks(&new.html, new.footnotes.as_deref());
+    diff_blocks(&old_blocks, &new_blocks)
+}
+
+/// Match two block sequences and pair up the unmatched runs
+fn diff_blocks(old_html: &[String], new_html: &[String]) -> Vec<BlockDiff> {
+    let old: Vec<Cow<str>> = old_html.iter().map(|html| comparison_key(html)).collect();
+    let new: Vec<Cow<str>> = new_html.iter().map(|html| comparison_key(html)).collect();
+
+    // Common leading and trailing blocks keep the table small for edits
+    // that touch one part of a long page
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    // lengths[i][j]: longest common subsequence of old_middle[i..] and new_middle[j..]
+    let width = new_middle.len() + 1;
+    let mut lengths = vec![0u32; (old_middle.len() + 1) * width];
+    for i in (0..old_middle.len()).rev() {
+        for j in (0..new_middle.len()).rev() {
+            lengths[i * width + j] = if old_middle[i] == new_middle[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let unchanged = |i: usize, j: usize| {
+        BlockDiff::new(
+            BlockChange::Unchanged,
+            Some(&old_html[i]),
+            Some(&new_html[j]),
+        )
+    };
+    let mut diffs: Vec<BlockDiff> = (0..prefix).map(|i| unchanged(i, i)).collect();
+    let mut removed: Vec<&str> = Vec::new();
+    let mut inserted: Vec<&str> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old_middle.len() || j < new_middle.len() {
+        if i < old_middle.len() && j < new_middle.len() && old_middle[i] == new_middle[j] {
+            flush_run(&mut diffs, &mut removed, &mut inserted);
+            diffs.push(unchanged(prefix + i, prefix + j));
+            i += 1;
+            j += 1;
+        } else if j == new_middle.len()
+            || (i < old_middle.len() && lengths[(i + 1) * width + j] >= lengths[i * width + j + 1])
+        {
+            removed.push(&old_html[prefix + i]);
+            i += 1;
+        } else {
+            inserted.push(&new_html[prefix + j]);
+            j += 1;
+        }
+    }
+    flush_run(&mut diffs, &mut removed, &mut inserted);
+    diffs.extend((0..suffix).map(|k| unchanged(old.len() - suffix + k, new.len() - suffix + k)));
+    diffs
+}
+
+/// Block HTML without its positional attributes
+fn comparison_key(html: &str) -> Cow<'_, str> {
+    POSITIONAL_ATTRIBUTE.replace_all(html, "")
+}
+
+/// Emit a run of removed and inserted blocks, pairing them in order
+fn flush_run(diffs: &mut Vec<BlockDiff>, removed: &mut Vec<&str>, inserted: &mut Vec<&str>) {
+    let paired = removed.len().min(inserted.len());
+    for (old, new) in removed.iter().zip(inserted.iter()) {
+        diffs.push(BlockDiff::new(BlockChange::Changed, Some(old), Some(new)));
+    }
+    for old in &removed[paired..] {
+        diffs.push(BlockDiff::new(BlockChange::Removed, Some(old), None));
+    }
+    for new in &inserted[paired..] {
+        diffs.push(BlockDiff::new(BlockChange::Inserted, None, Some(new)));
+    }
+    removed.clear();
+    inserted.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocks(items: &[&str]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    #[test]
+    fn test_diff_blocks() {
+        let diffs = diff_blocks(
+            &blocks(&["a", "b", "c", "d"]),
+            &blocks(&["a", "x", "c", "y", "d", "e"]),
+        );
+        let changes: Vec<_> = diffs.iter().map(|diff| diff.change).collect();
+        assert_eq!(
+            changes,
+            [
+                BlockChange::Unchanged,
+                BlockChange::Changed,
+                BlockChange::Unchanged,
+                BlockChange::Inserted,
+                BlockChange::Unchanged,
+                BlockChange::Inserted,
+            ]
+        );
+        assert_eq!(diffs[1].old_html.as_deref(), Some("b"));
+        assert_eq!(diffs[1].new_html.as_deref(), Some("x"));
+        assert_eq!(diffs[3].old_html, None);
+    }
+
+    #[test]
+    fn test_removed_blocks() {
+        let diffs = diff_blocks(&blocks(&["a", "b", "c"]), &blocks(&["c"]));
+        assert_eq!(diffs[0].change, BlockChange::Removed);
+        assert_eq!(diffs[1].change, BlockChange::Removed);
+        assert_eq!(diffs[2].change, BlockChange::Unchanged);
+        assert!(diff_blocks(&[], &[]).is_empty());
+    }
+
+    #[test]
+    fn test_renumbered_ids_are_unchanged() {
+        let diffs = diff_with_options(
+            "# One\n\n## Two\n",
+            "# Zero\n\n# One\n\n## Two\n",
+            &ParserOptions::default(),
+        );
+        let changes: Vec<_> = diffs.iter().map(|diff| diff.change).collect();
+        assert_eq!(
+            changes,
+            [
+                BlockChange::Inserted,
+                BlockChange::Unchanged,
+                BlockChange::Unchanged
+            ]
+        );
+        assert!(diffs[1].old_html.as_deref().unwrap().contains("id=\"h-1\""));
+        assert!(diffs[1].new_html.as_deref().unwrap().contains("id=\"h-2\""));
+    }
+}
@@ -0,0 +1,283 @@
+//! Accessibility audit
+//!
+//! Opt-in pass (see [`ParserOptions::a11y_audit`](crate::parser::ParserOptions::a11y_audit))
+//! that inspects the rendered HTML and reports common accessibility problems
+//! as diagnostics. The output HTML is never modified.
+//!
+//! | Code | Problem |
+//! |------|---------|
+//! | `a11y-img-alt` | Image without alternative text |
+//! | `a11y-heading-skip` | Heading level jumps by more than one (`h1` → `h3`) |
+//! | `a11y-link-text` | Link text that does not describe the target ("here", "こちら", ...) |
+//! | `a11y-table-header` | Table without header cells |
+//! | `a11y-color-only` | Text distinguished only by color (reported as info) |
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::diagnostics::Diagnostic;
+
+/// Link texts that carry no information out of context
+const NON_DESCRIPTIVE_LINK_TEXTS: &[&str] = &[
+    "here",
+    "click here",
+    "this",
+    "link",
+    "more",
+    "read more",
+    "ここ",
+    "こちら",
+    "ここをクリック",
+    "こちらをクリック",
+    "リンク",
+    "詳細",
+    "詳しくはこちら",
+];
+
+/// Bootstrap `text-*` utilities that are not colors
+const NON_COLOR_TEXT_UTILITIES: &[&str] = &[
+    "start",
+    "end",
+    "center",
+    "left",
+    "right",
+    "justify",
+    "truncate",
+    "wrap",
+    "nowrap",
+    "break",
+    "lowercase",
+    "uppercase",
+    "capitalize",
+    "reset",
+];
+
+/// Regex to remove plugin templates and SVG, whose content is not rendered as-is
+static OPAQUE_ELEMENT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?s)<template\b.*?</template>|<svg\b.*?</svg>").unwrap());
+
+static IMG_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<img\b[^>]*>").unwrap());
+
+static ALT_ATTR: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\salt="([^"]*)""#).unwrap());
+
+static SRC_ATTR: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\ssrc="([^"]*)""#).unwrap());
+
+static HEADING_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<h([1-6])\b").unwrap());
+
+static LINK: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(?s)<a\b([^>]*)>(.*?)</a>"#).unwrap());
+
+static TABLE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<table\b.*?</table>").unwrap());
+
+static COLORED_SPAN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?s)<span\b([^>]*)>(.*?)</span>"#).unwrap());
+
+static COLORED_PARAGRAPH: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?s)<p\b([^>]*)>(.*?)</p>"#).unwrap());
+
+static CLASS_ATTR: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\sclass="([^"]*)""#).unwrap());
+
+static STYLE_ATTR: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\sstyle="([^"]*)""#).unwrap());
+
+static EMPHASIS_TAG: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"<(?:strong|em|b|i|u|mark)\b").unwrap());
+
+static TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]+>").unwrap());
+
+fn text_content(html: &str) -> String {
+    let text = TAG.replace_all(html, "");
+    html_escape::decode_html_entities(text.trim()).into_owned()
+}
+
+fn check_images(html: &str, diagnostics: &mut Vec<Diagnostic>) {
+    for tag in IMG_TAG.find_iter(html) {
+        let tag = tag.as_str();
+        let has_alt = ALT_ATTR
+            .captures(tag)
+            .is_some_and(|caps| !caps[1].trim().is_empty());
+        if !has_alt {
+            let src = SRC_ATTR
+                .captures(tag)
+                .map_or(String::new(), |caps| caps[1].to_string());
+            diagnostics.push(Diagnostic::warning(
+                "a11y-img-alt",
+                format!("Image `{}` has no alternative text", src),
+            ));
+        }
+    }
+}
+
+fn check_heading_levels(html: &str, diagnostics: &mut Vec<Diagnostic>) {
+    let mut previous: Option<u8> = None;
+    for caps in HEADING_TAG.captures_iter(html) {
+        let level: u8 = caps[1].parse().unwrap_or(1);
+        if let Some(prev) = previous
+            && level > prev + 1
+        {
+            diagnostics.push(Diagnostic::warning(
+                "a11y-heading-skip",
+                format!("Heading level jumps from h{} to h{}", prev, level),
+            ));
+        }
+        previous = Some(level);
+    }
+}
+
+fn check_link_texts(html: &str, diagnostics: &mut Vec<Diagnostic>) {
+    for caps in LINK.captures_iter(html) {
+        let attrs = &caps[1];
+        // Heading anchors and footnote references are labelled by their context
+        if attrs.contains("aria-hidden=\"true\"")
+            || attrs.contains("aria-label=")
+            || attrs.contains("data-footnote-ref")
+            || attrs.contains("data-footnote-backref")
+        {
+            continue;
+        }
+        let inner = &caps[2];
+        let text = text_content(inner);
+        let normalized = text.to_lowercase();
+        if text.is_empty() && !IMG_TAG.is_match(inner) {
+            diagnostics.push(Diagnostic::warning("a11y-link-text", "Link has no text"));
+        } else if NON_DESCRIPTIVE_LINK_TEXTS.contains(&normalized.as_str()) {
+            diagnostics.push(Diagnostic::warning(
+                "a11y-link-text",
+                format!("Link text `{}` does not describe the link target", text),
+            ));
+        }
+    }
+}
+
+fn check_tables(html: &str, diagnostics: &mut Vec<Diagnostic>) {
+    for table in TABLE.find_iter(html) {
+        if !table.as_str().contains("<th") {
+            diagnostics.push(Diagnostic::warning(
+                "a11y-table-header",
+                "Table has no header cells",
+            ));
+        }
+    }
+}
+
+fn is_color_decoration(attrs: &str) -> bool {
+    let color_class = CLASS_ATTR.captures(attrs).is_some_and(|caps| {
+        caps[1].split_whitespace().any(|class| {
+            class
+                .strip_prefix("text-")
+                .is_some_and(|rest| !NON_COLOR_TEXT_UTILITIES.contains(&rest))
+        })
+    });
+    let color_style = STYLE_ATTR.captures(attrs).is_some_and(|caps| {
+        caps[1]
+            .split(';')
+            .any(|decl| decl.trim().starts_with("color:"))
+    });
+    color_class || color_style
+}
+
+fn check_color_only(html: &str, diagnostics: &mut Vec<Diagnostic>) {
+    for regex in [&*COLORED_SPAN, &*COLORED_PARAGRAPH] {
+        for caps in regex.captures_iter(html) {
+            let inner = &caps[2];
+            if is_color_decoration(&caps[1]) && !EMPHASIS_TAG.is_match(inner) {
+                diagnostics.push(Diagnostic::info(
+                    "a11y-color-only",
+                    format!(
+                        "`{}` is distinguished by color only; add emphasis or wording",
+                        text_content(inner)
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+/// Audit rendered HTML for accessibility problems
+///
+/// # Arguments
+///
+/// * `html` - Rendered HTML (code sections may be protected)
+///
+/// # Returns
+///
+/// Diagnostics in the order the checks run (images, headings, links,
+/// tables, colors)
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::accessibility::audit;
+///
+/// let diagnostics = audit(r#"<h1>A</h1><h3>B</h3><p><a href="/x">here</a></p>"#);
+/// let codes: Vec<_> = diagnostics.iter().map(|d| d.code).collect();
+/// assert_eq!(codes, ["a11y-heading-skip", "a11y-link-text"]);
+/// ```
+pub fn audit(html: &str) -> Vec<Diagnostic> {
+    let html = OPAQUE_ELEMENT.replace_all(html, "");
+    let mut diagnostics = Vec::new();
+
+    check_images(&html, &mut diagnostics);
+    check_heading_levels(&html, &mut diagnostics);
+    check_link_texts(&html, &mut diagnostics);
+    check_tables(&html, &mut diagnostics);
+    check_color_only(&html, &mut diagnostics);
+
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Severity;
+
+    fn codes(html: &str) -> Vec<&'static str> {
+        audit(html).iter().map(|d| d.code).collect()
+    }
+
+    #[test]
+    fn test_image_alt() {
+        assert_eq!(codes(r#"<img src="a.png" alt="" />"#), ["a11y-img-alt"]);
+        assert_eq!(codes(r#"<img src="a.png" />"#), ["a11y-img-alt"]);
+        assert!(codes(r#"<img src="a.png" alt="Chart" />"#).is_empty());
+    }
+
+    #[test]
+    fn test_heading_levels() {
+        assert!(codes("<h2>A</h2><h3>B</h3><h2>C</h2><h1>D</h1>").is_empty());
+        assert_eq!(codes("<h2>A</h2><h4>B</h4>"), ["a11y-heading-skip"]);
+    }
+
+    #[test]
+    fn test_link_texts() {
+        let html = r##"<h1><a href="#h-1" aria-hidden="true" class="anchor" id="h-1"></a>T</h1><a href="/a">こちら</a> <a href="/b">Click <em>here</em></a> <a href="/c">Install guide</a> <a href="/d"></a>"##;
+        assert_eq!(
+            codes(html),
+            ["a11y-link-text", "a11y-link-text", "a11y-link-text"]
+        );
+    }
+
+    #[test]
+    fn test_table_headers() {
+        assert_eq!(
+            codes("<table><tr><td>a</td></tr></table>"),
+            ["a11y-table-header"]
+        );
+        assert!(codes("<table><thead><tr><th>a</th></tr></thead></table>").is_empty());
+    }
+
+    #[test]
+    fn test_color_only() {
+        let diagnostics =
+            audit(r#"<span class="text-danger">warn</span> <p style="color: red">x</p>"#);
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics.iter().all(|d| d.severity == Severity::Info));
+
+        let html = r#"<span class="text-danger"><strong>warn</strong></span> <p class="text-center" style="background-color: red">x</p>"#;
+        assert!(codes(html).is_empty());
+    }
+
+    #[test]
+    fn test_plugins_ignored() {
+        let html = r#"<template class="umd-plugin"><a href="/x">here</a><img src="a"></template>"#;
+        assert!(codes(html).is_empty());
+    }
+}
@@ -0,0 +1,140 @@
+//! Heading anchor rendering styles
+//!
+//! The heading pass always emits an empty `<a class="anchor">` before the
+//! heading text, and ID deduplication, cross-references and ARIA labelling
+//! work on that form. This pass runs after them and rewrites the anchor into
+//! the style selected by [`AnchorOptions`].
+
+use std::borrow::Cow;
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use crate::html_builder::text_escape;
+use crate::parser::{AnchorOptions, AnchorStyle};
+
+/// Regex to detect a heading that starts with its (empty) anchor link
+static HEADING_WITH_ANCHOR: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r##"(?s)<h([1-6])([^>]*)><a href="#([^"]*)"([^>]*)></a>(.*?)</h[1-6]>"##).unwrap()
+});
+
+/// Regex to detect ARIA attributes that only make sense on an empty anchor
+static ANCHOR_ARIA: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"\s(?:aria-hidden|aria-label)="[^"]*""#).unwrap());
+
+/// Rewrite heading anchors into the configured style
+///
+/// With [`AnchorStyle::Wrap`], headings that already contain a link fall
+/// back to [`AnchorStyle::IdOnly`], since links cannot be nested.
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::anchors::apply_anchor_style;
+/// use umd::parser::{AnchorOptions, AnchorStyle};
+///
+/// let html = r##"<h2><a href="#h-1" aria-hidden="true" class="anchor" id="h-1"></a>Setup</h2>"##;
+/// let options = AnchorOptions {
+///     style: AnchorStyle::IdOnly,
+///     ..AnchorOptions::default()
+/// };
+/// assert_eq!(apply_anchor_style(html, &options), r#"<h2 id="h-1">Setup</h2>"#);
+/// ```
+pub fn apply_anchor_style<'a>(html: &'a str, options: &AnchorOptions) -> Cow<'a, str> {
+    if options.style == AnchorStyle::Empty {
+        return Cow::Borrowed(html);
+    }
+
+    HEADING_WITH_ANCHOR.replace_all(html, |caps: &Captures| {
+        let attrs = &caps[4];
+        if !attrs.contains("class=\"anchor\"") {
+            return caps[0].to_string();
+        }
+        let (level, heading_attrs, href, title) = (&caps[1], &caps[2], &caps[3], &caps[5]);
+
+        let style = match options.style {
+            AnchorStyle::Wrap if title.contains("<a ") => AnchorStyle::IdOnly,
+            style => style,
+        };
+        match style {
+            AnchorStyle::Empty => caps[0].to_string(),
+            AnchorStyle::Permalink => format!(
+                "<h{}{}>{} <a href=\"#{}\"{}>{}</a></h{}>",
+                level,
+                heading_attrs,
+                title,
+                href,
+                attrs,
+                text_escape(&options.symbol),
+                level
+            ),
+            AnchorStyle::Wrap => format!(
+                "<h{}{}><a href=\"#{}\"{}>{}</a></h{}>",
+                level,
+                heading_attrs,
+                href,
+                ANCHOR_ARIA.replace_all(attrs, ""),
+                title,
+                level
+            ),
+            AnchorStyle::IdOnly => {
+                format!(
+                    "<h{}{} id=\"{}\">{}</h{}>",
+                    level, heading_attrs, href, title, level
+                )
+            }
+            AnchorStyle::None => {
+                format!("<h{}{}>{}</h{}>", level, heading_attrs, title, level)
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADING: &str = r##"<h2 class="text-center"><a href="#h-1" class="anchor" id="h-1" aria-label="Link to section: Setup"></a>Setup <em>now</em></h2>"##;
+
+    fn render(style: AnchorStyle) -> String {
+        let options = AnchorOptions {
+            style,
+            ..AnchorOptions::default()
+        };
+        apply_anchor_style(HEADING, &options).into_owned()
+    }
+
+    #[test]
+    fn test_anchor_styles() {
+        assert_eq!(render(AnchorStyle::Empty), HEADING);
+        assert_eq!(
+            render(AnchorStyle::Permalink),
+            r##"<h2 class="text-center">Setup <em>now</em> <a href="#h-1" class="anchor" id="h-1" aria-label="Link to section: Setup">#</a></h2>"##
+        );
+        assert_eq!(
+            render(AnchorStyle::Wrap),
+            r##"<h2 class="text-center"><a href="#h-1" class="anchor" id="h-1">Setup <em>now</em></a></h2>"##
+        );
+        assert_eq!(
+            render(AnchorStyle::IdOnly),
+            r#"<h2 class="text-center" id="h-1">Setup <em>now</em></h2>"#
+        );
+        assert_eq!(
+            render(AnchorStyle::None),
+            r#"<h2 class="text-center">Setup <em>now</em></h2>"#
+        );
+    }
+
+    #[test]
+    fn test_wrap_falls_back_for_linked_headings() {
+        let html = r##"<h2><a href="#h-1" aria-hidden="true" class="anchor" id="h-1"></a>See <a href="/x">x</a></h2>"##;
+        let options = AnchorOptions {
+            style: AnchorStyle::Wrap,
+            ..AnchorOptions::default()
+        };
+        assert_eq!(
+            apply_anchor_style(html, &options),
+            r#"<h2 id="h-1">See <a href="/x">x</a></h2>"#
+        );
+    }
+}
@@ -0,0 +1,219 @@
+//! ARIA attributes for interactive and navigational output
+//!
+//! Applied once the document structure is final (heading IDs deduplicated,
+//! footnotes rendered):
+//!
+//! - spoilers: content is wrapped in `<span class="spoiler-content" id="umd-spoiler-N">`
+//!   and the toggle gets `aria-controls`
+//! - GFM alerts: optional `aria-live` politeness
+//! - heading anchors: `aria-hidden` is replaced by an `aria-label` naming the
+//!   section, so the focusable link is announced
+//! - footnote back-references: configurable `aria-label`
+
+use std::borrow::Cow;
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use crate::html_builder::attr_escape;
+use crate::parser::AriaOptions;
+
+/// Opening tag emitted for spoilers by the inline decoration pass
+const SPOILER_OPEN: &str =
+    r#"<span class="spoiler" role="button" tabindex="0" aria-expanded="false">"#;
+
+/// Regex to detect heading anchors together with the heading text
+static HEADING_ANCHOR: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r##"(?s)<a href="#([^"]+)" aria-hidden="true" class="anchor" id="([^"]+)"></a>(.*?)(</h[1-6]>)"##)
+        .unwrap()
+});
+
+/// Regex to detect comrak's footnote back-reference labels
+static FOOTNOTE_BACKREF_LABEL: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(data-footnote-backref[^>]*?) aria-label="[^"]*""#).unwrap());
+
+static ALERT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"<div class="alert ([^"]*)" role="alert">"#).unwrap());
+
+static TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]+>").unwrap());
+
+/// Plain text of heading content: tags removed, whitespace collapsed
+fn heading_text(html: &str) -> String {
+    TAG.replace_all(html, "")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Find the `</span>` closing the span whose content starts at `start`
+fn find_span_end(html: &str, start: usize) -> Option<usize> {
+    let mut depth = 1usize;
+    let mut pos = start;
+    while let Some(offset) = html[pos..].find('<') {
+        let at = pos + offset;
+        let rest = &html[at..];
+        if rest.starts_with("</span>") {
+            depth -= 1;
+            if depth == 0 {
+                return Some(at);
+            }
+        } else if rest.starts_with("<span") {
+            depth += 1;
+        }
+        pos = at + 1;
+    }
+    None
+}
+
+fn link_spoilers(html: &str) -> Cow<'_, str> {
+    if !html.contains(SPOILER_OPEN) {
+        return Cow::Borrowed(html);
+    }
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    let mut count = 0;
+
+    while let Some(open) = rest.find(SPOILER_OPEN) {
+        let content_start = open + SPOILER_OPEN.len();
+        let Some(content_end) = find_span_end(rest, content_start) else {
+            break;
+        };
+        count += 1;
+        let id = format!("umd-spoiler-{}", count);
+        output.push_str(&rest[..open]);
+        output.push_str(&format!(
+            "<span class=\"spoiler\" role=\"button\" tabindex=\"0\" aria-expanded=\"false\" aria-controls=\"{}\"><span class=\"spoiler-content\" id=\"{}\">{}</span>",
+            id,
+            id,
+            &rest[content_start..content_end]
+        ));
+        rest = &rest[content_end..];
+    }
+    output.push_str(rest);
+    Cow::Owned(output)
+}
+
+/// Apply the ARIA layer
+///
+/// # Arguments
+///
+/// * `html` - Rendered HTML
+/// * `options` - ARIA configuration
+///
+/// # Returns
+///
+/// HTML with ARIA attributes completed
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::aria::apply_aria;
+/// use umd::parser::AriaOptions;
+///
+/// let html = r##"<h2><a href="#h-1" aria-hidden="true" class="anchor" id="h-1"></a>Setup</h2>"##;
+/// assert_eq!(
+///     apply_aria(html, &AriaOptions::default()),
+///     r##"<h2><a href="#h-1" class="anchor" id="h-1" aria-label="Link to section: Setup"></a>Setup</h2>"##
+/// );
+/// ```
+pub fn apply_aria<'a>(html: &'a str, options: &AriaOptions) -> Cow<'a, str> {
+    let mut result = link_spoilers(html);
+
+    if let Some(template) = &options.heading_anchor_label
+        && let Cow::Owned(replaced) = HEADING_ANCHOR.replace_all(&result, |caps: &Captures| {
+            let title = heading_text(&caps[3]);
+            let label = template.replace("{title}", &title);
+            format!(
+                "<a href=\"#{}\" class=\"anchor\" id=\"{}\" aria-label=\"{}\"></a>{}{}",
+                &caps[1],
+                &caps[2],
+                attr_escape(&label),
+                &caps[3],
+                &caps[4]
+            )
+        })
+    {
+        result = Cow::Owned(replaced);
+    }
+
+    if let Some(label) = &options.footnote_backref_label {
+        let label = attr_escape(label);
+        if let Cow::Owned(replaced) = FOOTNOTE_BACKREF_LABEL
+            .replace_all(&result, |caps: &Captures| {
+                format!("{} aria-label=\"{}\"", &caps[1], label)
+            })
+        {
+            result = Cow::Owned(replaced);
+        }
+    }
+
+    if let Some(live) = options
+        .alert_live
+        .as_deref()
+        .filter(|value| matches!(*value, "off" | "polite" | "assertive"))
+        && let Cow::Owned(replaced) = ALERT.replace_all(&result, |caps: &Captures| {
+            format!(
+                "<div class=\"alert {}\" role=\"alert\" aria-live=\"{}\">",
+                &caps[1], live
+            )
+        })
+    {
+        result = Cow::Owned(replaced);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spoilers_get_controls() {
+        let html = format!(
+            "<p>{}a <span class=\"x\">b</span></span> {}c</span></p>",
+            SPOILER_OPEN, SPOILER_OPEN
+        );
+        let output = apply_aria(&html, &AriaOptions::default());
+        assert!(output.contains(r#"aria-controls="umd-spoiler-1"><span class="spoiler-content" id="umd-spoiler-1">a <span class="x">b</span></span></span>"#));
+        assert!(output.contains(r#"aria-controls="umd-spoiler-2"><span class="spoiler-content" id="umd-spoiler-2">c</span></span></p>"#));
+    }
+
+    #[test]
+    fn test_heading_anchor_label_can_be_disabled() {
+        let html = r##"<h2><a href="#h-1" aria-hidden="true" class="anchor" id="h-1"></a>A &quot;B&quot; <em>C</em></h2>"##;
+        let output = apply_aria(html, &AriaOptions::default());
+        assert!(output.contains(r#"aria-label="Link to section: A &quot;B&quot; C""#));
+
+        let options = AriaOptions {
+            heading_anchor_label: None,
+            ..Default::default()
+        };
+        assert_eq!(apply_aria(html, &options), html);
+    }
+
+    #[test]
+    fn test_footnote_backref_label() {
+        let html = r##"<a href="#fnref-1" class="footnote-backref" data-footnote-backref data-footnote-backref-idx="1" aria-label="Back to reference 1">↩</a>"##;
+        let output = apply_aria(html, &AriaOptions::default());
+        assert!(output.contains(r#"data-footnote-backref-idx="1" aria-label="Back to content">"#));
+    }
+
+    #[test]
+    fn test_alert_live() {
+        let html = r#"<div class="alert alert-info" role="alert">x</div>"#;
+        assert_eq!(apply_aria(html, &AriaOptions::default()), html);
+
+        let options = AriaOptions {
+            alert_live: Some("polite".to_string()),
+            ..Default::default()
+        };
+        assert!(apply_aria(html, &options).contains(r#"role="alert" aria-live="polite">"#));
+
+        let options = AriaOptions {
+            alert_live: Some("\"><script>".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(apply_aria(html, &options), html);
+    }
+}
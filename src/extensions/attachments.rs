@@ -0,0 +1,123 @@
+//! Attachment links (`&attach(file.pdf){label};`)
+//!
+//! Opt-in: attachments are linked only when
+//! [`ParserOptions::attachment_url`](crate::parser::ParserOptions::attachment_url)
+//! is set; otherwise `&attach` is an ordinary plugin. The file name is
+//! percent-encoded into the `{name}` placeholder of the URL template and the
+//! link uses the classes of media download links. Its
+//! `<span class="download-size" data-size="">` is left empty for the host
+//! to fill with the human-readable file size.
+
+use crate::html_builder::{attr_escape, text_escape};
+use crate::parser::{Icons, Locale};
+
+/// Placeholder for the file name in the URL template
+pub const NAME_PLACEHOLDER: &str = "{name}";
+
+/// Render an attachment link
+///
+/// `label` defaults to the file name. Returns `None` for names that are
+/// empty or contain a path (`/`, `\`, `..`).
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::attachments::render_attachment_link;
+/// use umd::parser::{Icons, Locale};
+///
+/// let icons = Icons {
+///     download: String::new(),
+///     ..Icons::default()
+/// };
+/// let html = render_attachment_link("spec v2.pdf", "", "/files/{name}", &icons, &Locale::en());
+/// assert_eq!(
+///     html.as_deref(),
+///     Some(concat!(
+///         r#"<a href="/files/spec%20v2.pdf" download class="download-link attachment" "#,
+///         r#"data-attachment="spec v2.pdf" aria-label="Download spec v2.pdf">"#,
+///         r#"spec v2.pdf <span class="download-size" data-size=""></span></a>"#
+///     ))
+/// );
+/// ```
+pub fn render_attachment_link(
+    name: &str,
+    label: &str,
+    template: &str,
+    icons: &Icons,
+    locale: &Locale,
+) -> Option<String> {
+    let name = name.trim();
+    if name.is_empty() || name.contains(['/', '\\']) || name == ".." {
+        return None;
+    }
+    let label = match label.trim() {
+        "" => name,
+        label => label,
+    };
+    let url = if template.contains(NAME_PLACEHOLDER) {
+        template.replace(NAME_PLACEHOLDER, &encode_file_name(name))
+    } else {
+        format!("{}{}", template, encode_file_name(name))
+    };
+    let icon = if icons.download.is_empty() {
+        String::new()
+    } else {
+        format!("{} ", icons.download)
+    };
+
+    Some(format!(
+        "<a href=\"{}\" download class=\"download-link attachment\" data-attachment=\"{}\" aria-label=\"{}\">{}{} <span class=\"download-size\" data-size=\"\"></span></a>",
+        attr_escape(&url),
+        attr_escape(name),
+        attr_escape(&locale.download.replace("{name}", label)),
+        icon,
+        text_escape(label)
+    ))
+}
+
+/// Percent-encode a file name for use in a URL path segment or query value
+fn encode_file_name(name: &str) -> String {
+    let mut encoded = String::with_capacity(name.len());
+    for byte in name.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attachment_link() {
+        let icons = Icons::default();
+        let locale = Locale::en();
+        let html = render_attachment_link(
+            "報告書&.pdf",
+            "<Report>",
+            "https://wiki.example.com/?attach={name}&page=Top",
+            &icons,
+            &locale,
+        )
+        .unwrap();
+        assert!(html.starts_with(
+            r#"<a href="https://wiki.example.com/?attach=%E5%A0%B1%E5%91%8A%E6%9B%B8%26.pdf&amp;page=Top" download class="download-link attachment" data-attachment="報告書&amp;.pdf" aria-label="Download &lt;Report&gt;">"#
+        ));
+        assert!(html.contains(&format!("{} &lt;Report&gt; <span", icons.download)));
+
+        assert!(
+            render_attachment_link("../secret.txt", "", "/f/{name}", &icons, &locale).is_none()
+        );
+        assert!(render_attachment_link(" ", "", "/f/{name}", &icons, &locale).is_none());
+        assert!(
+            render_attachment_link("a.zip", "", "/files/", &icons, &locale)
+                .unwrap()
+                .contains(r#"href="/files/a.zip""#)
+        );
+    }
+}
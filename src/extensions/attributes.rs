@@ -0,0 +1,258 @@
+//! Attribute blocks (`{#id .class key=value}`)
+//!
+//! Shared parser for the attribute blocks written after links, list items,
+//! headings, paragraphs, images and fenced code blocks. `#id` and `.class`
+//! tokens are limited to ASCII letters, digits, `-` and `_`; `key=value`
+//! pairs are checked against [`sanitize_attribute`], so event handlers,
+//! `style`, `href` and other attributes outside the whitelist are rejected.
+
+use crate::html_builder::attr_escape;
+use crate::sanitizer::sanitize_attribute;
+
+/// Parsed attribute block
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Attributes {
+    /// `#id` (the last one wins)
+    pub id: Option<String>,
+    /// `.class` tokens in order of appearance
+    pub classes: Vec<String>,
+    /// Whitelisted `key=value` pairs (unescaped values)
+    pub pairs: Vec<(String, String)>,
+}
+
+impl Attributes {
+    /// Whether the block sets nothing
+    pub fn is_empty(&self) -> bool {
+        self.id.is_none() && self.classes.is_empty() && self.pairs.is_empty()
+    }
+
+    /// Merge into the attribute text of a start tag (`href="/a" class="x"`)
+    ///
+    /// An existing `id` or key wins; classes are appended to an existing
+    /// `class` attribute without duplicates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use umd::extensions::attributes::parse_attributes;
+    ///
+    /// let mut attrs = String::from(r#" class="table""#);
+    /// parse_attributes(".table .table-sm title=Sales").unwrap().merge_into(&mut attrs);
+    /// assert_eq!(attrs, r#" class="table table-sm" title="Sales""#);
+    /// ```
+    pub fn merge_into(&self, attrs: &mut String) {
+        if let Some(id) = &self.id
+            && find_attribute(attrs, "id").is_none()
+        {
+            attrs.push_str(&format!(" id=\"{}\"", id));
+        }
+
+        if !self.classes.is_empty() {
+            match find_attribute(attrs, "class") {
+                Some((start, end)) => {
+                    let mut class_list: Vec<&str> = attrs[start..end].split_whitespace().collect();
+                    for class_name in &self.classes {
+                        if !class_list.contains(&class_name.as_str()) {
+                            class_list.push(class_name);
+                        }
+                    }
+                    let merged = class_list.join(" ");
+                    attrs.replace_range(start..end, &merged);
+                }
+                None => attrs.push_str(&format!(" class=\"{}\"", self.classes.join(" "))),
+            }
+        }
+
+        for (key, value) in &self.pairs {
+            if find_attribute(attrs, key).is_none() {
+                attrs.push_str(&format!(" {}=\"{}\"", key, attr_escape(value)));
+            }
+        }
+    }
+}
+
+/// Parse an attribute block body (without the braces)
+///
+/// Every token must be `#id`, `.class` or a whitelisted `key=value` pair
+/// (values may be double- or single-quoted, `&quot;` counts as a quote);
+/// otherwise `None` is returned and the block is left as text.
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::attributes::parse_attributes;
+///
+/// let attributes = parse_attributes(r#"#intro .lead data-level="2 b""#).unwrap();
+/// assert_eq!(attributes.id.as_deref(), Some("intro"));
+/// assert_eq!(attributes.classes, ["lead"]);
+/// assert_eq!(attributes.pairs, [("data-level".to_string(), "2 b".to_string())]);
+///
+/// assert!(parse_attributes("onclick=alert(1)").is_none());
+/// assert!(parse_attributes("plain words").is_none());
+/// ```
+pub fn parse_attributes(spec: &str) -> Option<Attributes> {
+    let spec = spec.replace("&quot;", "\"").replace("&#39;", "'");
+    let mut attributes = Attributes::default();
+    let mut rest = spec.trim();
+
+    while !rest.is_empty() {
+        if let Some(token) = rest.strip_prefix('#') {
+            let end = token.find(char::is_whitespace).unwrap_or(token.len());
+            attributes.id = Some(valid_token(&token[..end])?.to_string());
+            rest = token[end..].trim_start();
+        } else if let Some(token) = rest.strip_prefix('.') {
+            let end = token.find(char::is_whitespace).unwrap_or(token.len());
+            attributes
+                .classes
+                .push(valid_token(&token[..end])?.to_string());
+            rest = token[end..].trim_start();
+        } else {
+            let (key, after_key) = rest.split_once('=')?;
+            let key = key.to_ascii_lowercase();
+            let (value, after_value) = match after_key.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let quoted = &after_key[1..];
+                    let end = quoted.find(quote)?;
+                    (&quoted[..end], &quoted[end + 1..])
+                }
+                _ => after_key
+                    .split_once(char::is_whitespace)
+                    .unwrap_or((after_key, "")),
+            };
+            let value = sanitize_attribute(&key, value)?;
+            attributes.pairs.push((key, value.into_owned()));
+            rest = after_value.trim_start();
+        }
+    }
+
+    (!attributes.is_empty()).then_some(attributes)
+}
+
+/// Parse the link attribute form, which also accepts bare tokens
+/// (`{docs-link btn btn-primary}`: the first bare token is the ID, the
+/// others are classes)
+///
+/// Invalid tokens are skipped.
+pub fn parse_link_attributes(spec: &str) -> Attributes {
+    let mut attributes = Attributes::default();
+
+    for token in spec.split_whitespace() {
+        if let Some(stripped) = token.strip_prefix('#') {
+            if let Some(id) = valid_token(stripped) {
+                attributes.id = Some(id.to_string());
+            }
+        } else if let Some(stripped) = token.strip_prefix('.') {
+            if let Some(class_name) = valid_token(stripped) {
+                attributes.classes.push(class_name.to_string());
+            }
+        } else if let Some(token) = valid_token(token) {
+            if attributes.id.is_none() {
+                attributes.id = Some(token.to_string());
+            } else {
+                attributes.classes.push(token.to_string());
+            }
+        }
+    }
+
+    attributes
+}
+
+/// Split a trailing attribute block off `text` (`Title {.lead}`)
+///
+/// The block must be preceded by whitespace and parse with
+/// [`parse_attributes`].
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::attributes::split_trailing_attributes;
+///
+/// let (text, attributes) = split_trailing_attributes("Item {.active} ").unwrap();
+/// assert_eq!(text, "Item");
+/// assert_eq!(attributes.classes, ["active"]);
+/// assert!(split_trailing_attributes("f(x) {not attributes}").is_none());
+/// ```
+pub fn split_trailing_attributes(text: &str) -> Option<(&str, Attributes)> {
+    let trimmed = text.trim_end();
+    let body = trimmed.strip_suffix('}')?;
+    let open = body.rfind('{')?;
+    if body[open + 1..].contains('\n') {
+        return None;
+    }
+    let before = &body[..open];
+    if !before.ends_with([' ', '\t', '\n']) {
+        return None;
+    }
+    let attributes = parse_attributes(&body[open + 1..])?;
+    Some((before.trim_end(), attributes))
+}
+
+fn valid_token(token: &str) -> Option<&str> {
+    (!token.is_empty()
+        && token
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_'))
+    .then_some(token)
+}
+
+/// Byte range of the value of double-quoted attribute `name` in `attrs`
+pub(crate) fn find_attribute(attrs: &str, name: &str) -> Option<(usize, usize)> {
+    let pattern = format!("{}=\"", name);
+    let mut from = 0;
+    while let Some(found) = attrs[from..].find(&pattern) {
+        let start = from + found;
+        let boundary = attrs[..start]
+            .chars()
+            .next_back()
+            .is_none_or(char::is_whitespace);
+        let value_start = start + pattern.len();
+        let value_end = value_start + attrs[value_start..].find('"')?;
+        if boundary {
+            return Some((value_start, value_end));
+        }
+        from = value_end;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_attributes_rejects_unsafe_pairs() {
+        for spec in [
+            "style=color:red",
+            "href=javascript:alert(1)",
+            "onmouseover=x",
+            "data-x=\"unterminated",
+            "#bad!id",
+            ".",
+            "",
+        ] {
+            assert_eq!(parse_attributes(spec), None, "{}", spec);
+        }
+        let attributes = parse_attributes("dir=RTL lang=en-US role=note aria-label='a b'").unwrap();
+        assert_eq!(
+            attributes.pairs,
+            [
+                ("dir".to_string(), "rtl".to_string()),
+                ("lang".to_string(), "en-US".to_string()),
+                ("role".to_string(), "note".to_string()),
+                ("aria-label".to_string(), "a b".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_into_escapes_values() {
+        let mut attrs = String::from(r#" data-class="x" id="keep""#);
+        parse_attributes(r#"#other .y title="a<b>&amp;""#)
+            .unwrap()
+            .merge_into(&mut attrs);
+        assert_eq!(
+            attrs,
+            r#" data-class="x" id="keep" class="y" title="a&lt;b&gt;&amp;""#
+        );
+    }
+}
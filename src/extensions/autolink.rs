@@ -0,0 +1,273 @@
+//! Autolinking for bare domains and mail addresses
+//!
+//! comrak's GFM autolink extension already links `www.` hosts, `http(s)://`
+//! URLs and mail addresses. This module complements it:
+//!
+//! - bare domains such as `example.com/docs` are linked (restricted to a
+//!   curated list of top-level domains to avoid matching file names like
+//!   `main.rs` or `README.md`)
+//! - autolinks are filtered through the allow/deny lists in
+//!   [`AutolinkOptions`](crate::parser::AutolinkOptions); rejected links are
+//!   rendered as plain text
+//! - GFM autolinks containing `&` are un-double-escaped (the sanitizer has
+//!   already turned `&` into `&amp;` before comrak sees the URL)
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use super::conflict_resolver::extract_http_host;
+use crate::parser::AutolinkOptions;
+
+/// Top-level domains recognized for bare-domain autolinking
+const BARE_DOMAIN_TLDS: &str = "com|org|net|edu|gov|mil|int|info|biz|io|dev|app|ai|co|me|tv|xyz|site|online|tech|blog|page|cloud|jp|cn|kr|tw|hk|uk|de|fr|it|es|nl|be|ch|at|se|no|fi|dk|pl|cz|eu|us|ca|au|nz|ru|br|mx|ar|in|id|th|vn|sg";
+
+/// Regex to detect bare domains (optionally followed by a path)
+static BARE_DOMAIN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(
+        r"(?P<pre>^|[\s(（「『、。,])(?P<domain>(?:[a-z0-9](?:[a-z0-9-]{{0,61}}[a-z0-9])?\.)+(?:{}))(?P<path>/[^\s<>()（）「」]*[^\s<>()（）「」.,;:!?。、])?",
+        BARE_DOMAIN_TLDS
+    ))
+    .unwrap()
+});
+
+/// Regex to detect autolinks generated by comrak (link text equals the URL)
+static GFM_AUTOLINK: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"<a href="(?P<href>(?:https?://|mailto:)[^"]*)">(?P<text>[^<]*)</a>"#).unwrap()
+});
+
+/// Regex to tokenize HTML into tags and text
+static HTML_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]+>").unwrap());
+
+/// Elements whose text content must not be autolinked
+const SKIP_ELEMENTS: &[&str] = &["a", "code", "pre", "template", "script", "style", "svg"];
+
+/// Check whether a host passes the allow/deny lists
+///
+/// Entries match the host itself and any subdomain (`example.com` matches
+/// `docs.example.com`). The deny list wins over the allow list; an empty
+/// allow list allows every host.
+pub fn is_host_allowed(host: &str, options: &AutolinkOptions) -> bool {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    let matches = |entry: &String| {
+        let entry = entry.trim().trim_start_matches("*.").to_ascii_lowercase();
+        !entry.is_empty() && (host == entry || host.ends_with(&format!(".{}", entry)))
+    };
+
+    if options.deny.iter().any(matches) {
+        return false;
+    }
+    options.allow.is_empty() || options.allow.iter().any(matches)
+}
+
+fn autolink_host(href: &str) -> Option<String> {
+    if let Some(address) = href.strip_prefix("mailto:") {
+        return address.rsplit_once('@').map(|(_, host)| host.to_string());
+    }
+    extract_http_host(href).map(str::to_string)
+}
+
+/// Whether an `<a>` element was produced by autolinking (text mirrors the URL)
+fn is_autolink(href: &str, text: &str) -> bool {
+    href == text
+        || href.strip_prefix("mailto:") == Some(text)
+        || href.strip_prefix("http://") == Some(text)
+        || href.strip_prefix("https://") == Some(text)
+}
+
+fn normalize_gfm_autolinks(html: &str, options: &AutolinkOptions) -> String {
+    GFM_AUTOLINK
+        .replace_all(html, |caps: &Captures| {
+            let href = &caps["href"];
+            let text = &caps["text"];
+            if !is_autolink(href, text) {
+                return caps[0].to_string();
+            }
+
+            let href = href.replace("&amp;amp;", "&amp;");
+            let text = text.replace("&amp;amp;", "&amp;");
+            match autolink_host(&html_escape::decode_html_entities(&href)) {
+                Some(host) if !is_host_allowed(&host, options) => text,
+                _ => format!("<a href=\"{}\">{}</a>", href, text),
+            }
+        })
+        .to_string()
+}
+
+fn link_bare_domains(text: &str, options: &AutolinkOptions) -> String {
+    BARE_DOMAIN
+        .replace_all(text, |caps: &Captures| {
+            let domain = &caps["domain"];
+            let path = caps.name("path").map_or("", |m| m.as_str());
+
+            // The TLD must end the host: reject `example.community`, `a.com.x`
+            let end = caps.get(0).map_or(0, |m| m.end());
+            let mut following = text[end..].chars();
+            let continues_host = match following.next() {
+                Some(c) if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '@' => true,
+                Some('.') => following.next().is_some_and(|c| c.is_ascii_alphanumeric()),
+                _ => false,
+            };
+            if continues_host || !is_host_allowed(domain, options) {
+                return caps[0].to_string();
+            }
+            format!(
+                "{}<a href=\"https://{}{}\">{}{}</a>",
+                &caps["pre"], domain, path, domain, path
+            )
+        })
+        .to_string()
+}
+
+/// Apply a transformation to text nodes outside links, code and plugins
+pub(crate) fn map_text_nodes(html: &str, mut f: impl FnMut(&str) -> String) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut skip_depth = 0usize;
+    let mut last_end = 0;
+
+    for tag in HTML_TAG.find_iter(html) {
+        let text = &html[last_end..tag.start()];
+        if skip_depth == 0 && !text.is_empty() {
+            output.push_str(&f(text));
+        } else {
+            output.push_str(text);
+        }
+        output.push_str(tag.as_str());
+        last_end = tag.end();
+
+        let inner = tag.as_str().trim_start_matches('<');
+        let (closing, inner) = match inner.strip_prefix('/') {
+            Some(rest) => (true, rest),
+            None => (false, inner),
+        };
+        let name: String = inner
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        if SKIP_ELEMENTS.contains(&name.as_str()) && !tag.as_str().ends_with("/>") {
+            if closing {
+                skip_depth = skip_depth.saturating_sub(1);
+            } else {
+                skip_depth += 1;
+            }
+        }
+    }
+
+    let tail = &html[last_end..];
+    if skip_depth == 0 {
+        output.push_str(&f(tail));
+    } else {
+        output.push_str(tail);
+    }
+    output
+}
+
+/// Apply autolink post-processing
+///
+/// # Arguments
+///
+/// * `html` - The HTML to process (code sections should be protected)
+/// * `options` - Autolink configuration
+///
+/// # Returns
+///
+/// HTML with bare domains linked and autolinks filtered
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::autolink::apply_autolinks;
+/// use umd::parser::AutolinkOptions;
+///
+/// let output = apply_autolinks("<p>See example.com/docs.</p>", &AutolinkOptions::default());
+/// assert_eq!(
+///     output,
+///     r#"<p>See <a href="https://example.com/docs">example.com/docs</a>.</p>"#
+/// );
+/// ```
+pub fn apply_autolinks(html: &str, options: &AutolinkOptions) -> String {
+    let result = normalize_gfm_autolinks(html, options);
+    if !options.bare_domains {
+        return result;
+    }
+    map_text_nodes(&result, |text| link_bare_domains(text, options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bare_domain_linked() {
+        let output = apply_autolinks(
+            "<p>Visit example.com today</p>",
+            &AutolinkOptions::default(),
+        );
+        assert!(output.contains(r#"<a href="https://example.com">example.com</a> today"#));
+    }
+
+    #[test]
+    fn test_file_names_not_linked() {
+        let html = "<p>Edit main.rs and README.md or config.toml</p>";
+        assert_eq!(apply_autolinks(html, &AutolinkOptions::default()), html);
+    }
+
+    #[test]
+    fn test_existing_links_and_code_untouched() {
+        let html = r#"<p><a href="/x">example.com</a> <code>example.com</code></p>"#;
+        assert_eq!(apply_autolinks(html, &AutolinkOptions::default()), html);
+    }
+
+    #[test]
+    fn test_bare_domains_can_be_disabled() {
+        let options = AutolinkOptions {
+            bare_domains: false,
+            ..Default::default()
+        };
+        let html = "<p>example.com</p>";
+        assert_eq!(apply_autolinks(html, &options), html);
+    }
+
+    #[test]
+    fn test_deny_list_unlinks_gfm_autolinks() {
+        let options = AutolinkOptions {
+            deny: vec!["evil.example".to_string()],
+            ..Default::default()
+        };
+        let html = r#"<p><a href="http://www.evil.example">www.evil.example</a> <a href="mailto:a@evil.example">a@evil.example</a></p>"#;
+        assert_eq!(
+            apply_autolinks(html, &options),
+            "<p>www.evil.example a@evil.example</p>"
+        );
+    }
+
+    #[test]
+    fn test_allow_list_restricts_links() {
+        let options = AutolinkOptions {
+            allow: vec!["example.com".to_string()],
+            ..Default::default()
+        };
+        let output = apply_autolinks("<p>docs.example.com other.org</p>", &options);
+        assert!(output.contains(r#"href="https://docs.example.com""#));
+        assert!(!output.contains("https://other.org"));
+    }
+
+    #[test]
+    fn test_gfm_autolink_ampersand_unescaped() {
+        let html = r#"<a href="https://example.com/?a=1&amp;amp;b=2">https://example.com/?a=1&amp;amp;b=2</a>"#;
+        assert_eq!(
+            apply_autolinks(html, &AutolinkOptions::default()),
+            r#"<a href="https://example.com/?a=1&amp;b=2">https://example.com/?a=1&amp;b=2</a>"#
+        );
+    }
+
+    #[test]
+    fn test_explicit_links_not_filtered() {
+        let options = AutolinkOptions {
+            deny: vec!["example.com".to_string()],
+            ..Default::default()
+        };
+        let html = r#"<a href="https://example.com">Example</a>"#;
+        assert_eq!(apply_autolinks(html, &options), html);
+    }
+}
@@ -1,43 +1,64 @@
 //! Block decoration syntax for LukiWiki with Bootstrap 5 class support
 //!
 //! Provides line-prefix decorations with compound syntax support:
-//! - COLOR(fg,bg): Bootstrap color classes or inherit
-//! - SIZE(value): Bootstrap fs-* classes or inline rem
+//! - COLOR(fg,bg,opacity): Bootstrap color classes or inherit
+//! - SIZE(value): Bootstrap fs-* classes, size keywords or inline rem
 //! - TRUNCATE: Bootstrap text-truncate class
 //! - JUSTIFY/RIGHT/CENTER/LEFT: Bootstrap text alignment classes
+//! - LANG(code): `lang` attribute (block counterpart of `&lang(code){...};`)
+//! - RTL/LTR: `dir` attribute; RTL paragraphs are right-aligned (`text-end`)
+//!   unless an explicit alignment is given
+//! - BDI: `dir="auto"` (direction taken from the content, like `&bdi(...);`)
 //!
 //! Multiple prefixes can be combined:
 //! - SIZE(1.5): COLOR(primary): CENTER: Text
 //! - TRUNCATE: RIGHT: Text
 
+use std::borrow::Cow;
+
 use once_cell::sync::Lazy;
 use regex::Regex;
 
+use super::class_map::CLASS_ATTR;
+use super::color_utilities::{gradient_classes, opacity_class};
+use super::font_size::{FontSize, map_font_size};
+use crate::diagnostics::Diagnostic;
+use crate::html_builder::attr_escape;
+use crate::sanitizer::{sanitize_css_color, split_args};
+
 /// Block decoration attributes
 #[derive(Default, Debug)]
 struct BlockDecoration {
     // Color classes or inline styles
     fg_color: Option<String>,
     bg_color: Option<String>,
+    // Opacity class for the color
+    opacity: Option<String>,
     // Font size class or inline style
-    font_size: Option<String>,
+    font_size: Option<FontSize>,
     // Text alignment class
     text_align: Option<String>,
     // Truncate flag
     truncate: bool,
     // Vertical alignment (for table cells)
     vertical_align: Option<String>,
+    // Language tag for the `lang` attribute
+    lang: Option<String>,
+    // Text direction for the `dir` attribute
+    dir: Option<&'static str>,
 }
 
 impl BlockDecoration {
-    /// Convert to HTML class and style attributes
-    fn to_html_attrs(&self) -> (Option<String>, Option<String>) {
+    /// Collect the classes and inline style declarations
+    fn classes_and_styles(&self) -> (Vec<String>, Vec<String>) {
         let mut classes = Vec::new();
         let mut styles = Vec::new();
 
-        // Text alignment
+        // Text alignment (RTL blocks default to the end edge)
         if let Some(ref align) = self.text_align {
             classes.push(align.clone());
+        } else if self.dir == Some("rtl") {
+            classes.push("text-end".to_string());
         }
 
         // Truncate
@@ -51,17 +72,15 @@ impl BlockDecoration {
         }
 
         // Font size (class or inline)
-        if let Some(ref size) = self.font_size {
-            if size.starts_with("fs-") {
-                classes.push(size.clone());
-            } else {
-                styles.push(format!("font-size: {}", size));
-            }
+        match self.font_size {
+            Some(FontSize::Class(ref class)) => classes.push(class.clone()),
+            Some(FontSize::Style(ref size)) => styles.push(format!("font-size: {}", size)),
+            None => {}
         }
 
-        // Foreground color (class or inline)
+        // Foreground color (class or inline; gradients are background classes)
         if let Some(ref fg) = self.fg_color {
-            if fg.starts_with("text-") {
+            if fg.starts_with("text-") || fg.starts_with("bg-") {
                 classes.push(fg.clone());
             } else {
                 styles.push(format!("color: {}", fg));
@@ -77,16 +96,27 @@ impl BlockDecoration {
             }
         }
 
+        if let Some(ref opacity) = self.opacity {
+            classes.push(opacity.clone());
+        }
+
+        (classes, styles)
+    }
+
+    /// Convert to HTML class and style attributes
+    fn to_html_attrs(&self) -> (Option<String>, Option<String>) {
+        let (classes, styles) = self.classes_and_styles();
+
         let class_attr = if classes.is_empty() {
             None
         } else {
-            Some(format!("class=\"{}\"", classes.join(" ")))
+            Some(format!("class=\"{}\"", attr_escape(&classes.join(" "))))
         };
 
         let style_attr = if styles.is_empty() {
             None
         } else {
-            Some(format!("style=\"{}\"", styles.join("; ")))
+            Some(format!("style=\"{}\"", attr_escape(&styles.join("; "))))
         };
 
         (class_attr, style_attr)
@@ -97,20 +127,32 @@ impl BlockDecoration {
 #[allow(dead_code)]
 static COMPOUND_PREFIX: Lazy<Regex> = Lazy::new(|| {
     Regex::new(
-        r"(?m)^(?:(?:SIZE\(([^)]+)\)|COLOR\(([^,)]*?)(?:,([^)]*?))?\)|(TRUNCATE)|(TOP|MIDDLE|BOTTOM|BASELINE)|(JUSTIFY|RIGHT|CENTER|LEFT)):\s*)+(.+)$"
+        r"(?m)^(?:(?:SIZE\(([^)]+)\)|COLOR\(((?:[^()]|\([^()]*\))*)\)|(TRUNCATE)|(TOP|MIDDLE|BOTTOM|BASELINE)|(JUSTIFY|RIGHT|CENTER|LEFT)|LANG\(([^)]+)\)|(RTL|LTR|BDI)):\s*)+(.+)$"
     )
     .unwrap()
 });
 
 // Individual pattern extractors
 static SIZE_EXTRACT: Lazy<Regex> = Lazy::new(|| Regex::new(r"SIZE\(([^)]+)\):").unwrap());
+// Arguments may contain color functions: COLOR(rgb(0 0 0),hsl(0 0% 90%)):
 static COLOR_EXTRACT: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"COLOR\(([^,)]*?)(?:,([^)]*?))?\):").unwrap());
+    Lazy::new(|| Regex::new(r"COLOR\(((?:[^()]|\([^()]*\))*)\):").unwrap());
 static TRUNCATE_EXTRACT: Lazy<Regex> = Lazy::new(|| Regex::new(r"(TRUNCATE):").unwrap());
 static VALIGN_EXTRACT: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"(TOP|MIDDLE|BOTTOM|BASELINE):").unwrap());
 static ALIGN_EXTRACT: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"(JUSTIFY|RIGHT|CENTER|LEFT):").unwrap());
+static DIR_EXTRACT: Lazy<Regex> = Lazy::new(|| Regex::new(r"(RTL|LTR|BDI):").unwrap());
+// BCP 47 style language tag (e.g. ja, en-US, zh-Hant-TW)
+static LANG_EXTRACT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"LANG\(([A-Za-z]{1,8}(?:-[A-Za-z0-9]{1,8})*)\):").unwrap());
+// Leading prefix chain; order-independent prefixes are only taken from it
+static PREFIX_CHAIN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^(?:(?:SIZE\([^)]+\)|COLOR\((?:[^()]|\([^()]*\))*\)|TRUNCATE|TOP|MIDDLE|BOTTOM|BASELINE|JUSTIFY|RIGHT|CENTER|LEFT|LANG\([^)]+\)|RTL|LTR|BDI):\s*)*",
+    )
+    .unwrap()
+});
 
 // Block placement pattern for tables and plugins (must start on new line)
 static BLOCK_PLACEMENT: Lazy<Regex> = Lazy::new(|| {
@@ -120,24 +162,41 @@ static BLOCK_PLACEMENT: Lazy<Regex> = Lazy::new(|| {
     .unwrap()
 });
 
-/// Map font size value to Bootstrap class or inline style
-fn map_font_size(value: &str) -> String {
-    // Check if value has unit (rem, em, px, etc.)
-    if value.contains("rem") || value.contains("em") || value.contains("px") {
-        return value.to_string(); // Return as inline style
-    }
+// Placement prefix in its own paragraph before a media element
+static MEDIA_BLOCK_PLACEMENT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?s)<p>\s*(LEFT|CENTER|RIGHT|JUSTIFY):\s*\n\s*(<picture[\s\S]*?</picture>|<video[\s\S]*?</video>|<audio[\s\S]*?</audio>|<a href="[^"]+" download class="download-link[^"]*"[^>]*>[\s\S]*?</a>)\s*</p>"#,
+    )
+    .unwrap()
+});
 
-    // Map to Bootstrap fs-* classes (unitless values)
-    match value {
-        "2.5" => "fs-1".to_string(),       // 2.5rem
-        "2" | "2.0" => "fs-2".to_string(), // 2rem
-        "1.75" => "fs-3".to_string(),      // 1.75rem
-        "1.5" => "fs-4".to_string(),       // 1.5rem
-        "1.25" => "fs-5".to_string(),      // 1.25rem
-        "0.875" => "fs-6".to_string(),     // 0.875rem
-        _ => format!("{}rem", value),      // Custom value as inline style
-    }
-}
+// Float prefix before a media element, optionally followed by the text
+// that wraps around it in the same paragraph
+static FLOAT_MEDIA_PLACEMENT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?s)<p>\s*FLOAT-(LEFT|RIGHT):\s*\n\s*(<picture[\s\S]*?</picture>|<video[\s\S]*?</video>|<audio[\s\S]*?</audio>)\s*(.*?)</p>"#,
+    )
+    .unwrap()
+});
+
+// Placement prefix sharing a paragraph with a rendered table or plugin
+static PARAGRAPH_BLOCK_PLACEMENT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?s)<p>\s*(LEFT|CENTER|RIGHT|JUSTIFY):\s*\n\s*(<(?:table|template)\b[^>]*>[\s\S]*?</(?:table|template)>)\s*</p>"#,
+    )
+    .unwrap()
+});
+
+// Placement prefix paragraph directly before a rendered table or plugin
+static PRECEDING_BLOCK_PLACEMENT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?s)<p>\s*(LEFT|CENTER|RIGHT|JUSTIFY):\s*</p>\s*(<(?:table|template)\b[^>]*>[\s\S]*?</(?:table|template)>)"#,
+    )
+    .unwrap()
+});
+
+static PLACEMENT_CLASS_ATTR: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"class=\"([^\"]*)\""#).unwrap());
 
 /// Map color value to Bootstrap class or inline style
 fn map_color(value: &str, is_background: bool) -> Option<String> {
@@ -146,6 +205,10 @@ fn map_color(value: &str, is_background: bool) -> Option<String> {
         return None;
     }
 
+    if let Some(classes) = gradient_classes(trimmed) {
+        return Some(classes);
+    }
+
     // Bootstrap theme colors
     let bootstrap_colors = [
         // Theme colors
@@ -220,22 +283,8 @@ fn map_color(value: &str, is_background: bool) -> Option<String> {
         }
     }
 
-    // Check if it's a HEX color (#RRGGBB or #RGB)
-    if trimmed.starts_with('#') && (trimmed.len() == 4 || trimmed.len() == 7) {
-        // Basic validation: check if all characters after # are hex digits
-        if trimmed[1..].chars().all(|c| c.is_ascii_hexdigit()) {
-            return Some(trimmed.to_string());
-        }
-    }
-
-    // Future: Support rgb() and hsl() formats
-    // if trimmed.starts_with("rgb(") || trimmed.starts_with("rgba(") ||
-    //    trimmed.starts_with("hsl(") || trimmed.starts_with("hsla(") {
-    //     return Some(trimmed.to_string());
-    // }
-
-    // Invalid color specification (e.g., HTML color names are not supported)
-    None
+    // HEX, rgb()/hsl() or CSS named color as inline style
+    sanitize_css_color(trimmed)
 }
 
 /// Map alignment to Bootstrap class
@@ -260,31 +309,70 @@ fn map_vertical_align(value: &str) -> String {
     }
 }
 
+/// Byte length of the leading prefix chain of a line
+fn prefix_chain_end(line: &str) -> usize {
+    PREFIX_CHAIN.find(line).map_or(0, |m| m.end())
+}
+
 /// Parse all prefixes from a line and extract decoration attributes
 fn parse_prefixes(line: &str) -> (BlockDecoration, String) {
     let mut decoration = BlockDecoration::default();
     let mut remaining = line;
 
+    // Extract LANG (removed in place so it may appear anywhere in the chain)
+    let without_lang;
+    if let Some(caps) = LANG_EXTRACT.captures(remaining)
+        && caps.get(0).unwrap().end() <= prefix_chain_end(remaining)
+    {
+        decoration.lang = Some(caps[1].to_string());
+        without_lang = LANG_EXTRACT.replacen(remaining, 1, "").into_owned();
+        remaining = &without_lang;
+    }
+
+    // Extract direction (also order-independent)
+    let without_dir;
+    if let Some(caps) = DIR_EXTRACT.captures(remaining)
+        && caps.get(0).unwrap().end() <= prefix_chain_end(remaining)
+    {
+        decoration.dir = match &caps[1] {
+            "RTL" => Some("rtl"),
+            "LTR" => Some("ltr"),
+            _ => Some("auto"),
+        };
+        without_dir = DIR_EXTRACT.replacen(remaining, 1, "").into_owned();
+        remaining = &without_dir;
+    }
+
     // Extract SIZE
     if let Some(caps) = SIZE_EXTRACT.captures(remaining) {
         let value = caps.get(1).map_or("", |m| m.as_str());
-        decoration.font_size = Some(map_font_size(value));
+        decoration.font_size = map_font_size(value);
         remaining = &remaining[caps.get(0).unwrap().end()..];
     }
 
     // Extract COLOR
     if let Some(caps) = COLOR_EXTRACT.captures(remaining) {
-        let fg = caps.get(1).map_or("", |m| m.as_str());
-        let bg = caps.get(2).map_or("", |m| m.as_str());
-        decoration.fg_color = map_color(fg, false);
-        decoration.bg_color = map_color(bg, true);
+        let args = split_args(caps.get(1).map_or("", |m| m.as_str()));
+        decoration.fg_color = map_color(args[0], false);
+        decoration.bg_color = args.get(1).and_then(|bg| map_color(bg, true));
+        if let Some(opacity) = args.get(2) {
+            let color_classes: Vec<String> = [&decoration.fg_color, &decoration.bg_color]
+                .into_iter()
+                .flatten()
+                .filter(|color| color.starts_with("text-") || color.starts_with("bg-"))
+                .cloned()
+                .collect();
+            decoration.opacity = opacity_class(opacity, &color_classes);
+        }
         remaining = &remaining[caps.get(0).unwrap().end()..];
     }
 
     // Extract TRUNCATE
+    let without_truncate;
     if TRUNCATE_EXTRACT.is_match(remaining) {
         decoration.truncate = true;
-        remaining = TRUNCATE_EXTRACT.replace(remaining, "").to_string().leak();
+        without_truncate = TRUNCATE_EXTRACT.replace(remaining, "").into_owned();
+        remaining = &without_truncate;
     }
 
     // Extract vertical alignment
@@ -313,23 +401,15 @@ fn parse_prefixes(line: &str) -> (BlockDecoration, String) {
 /// # Returns
 ///
 /// HTML with block decorations applied
-pub fn apply_block_decorations(html: &str) -> String {
+pub fn apply_block_decorations(html: &str) -> Cow<'_, str> {
+    if !html.contains('\r') && !html.lines().any(has_decoration_prefix) {
+        return Cow::Borrowed(html.trim_end());
+    }
+
     let mut result = String::new();
 
     for line in html.lines() {
-        // Check if line starts with any decoration prefix
-        if line.starts_with("SIZE(")
-            || line.starts_with("COLOR(")
-            || line.starts_with("TRUNCATE:")
-            || line.starts_with("TOP:")
-            || line.starts_with("MIDDLE:")
-            || line.starts_with("BOTTOM:")
-            || line.starts_with("BASELINE:")
-            || line.starts_with("JUSTIFY:")
-            || line.starts_with("RIGHT:")
-            || line.starts_with("CENTER:")
-            || line.starts_with("LEFT:")
-        {
+        if has_decoration_prefix(line) {
             let (decoration, content) = parse_prefixes(line);
             let (class_attr, style_attr) = decoration.to_html_attrs();
 
@@ -340,6 +420,12 @@ pub fn apply_block_decorations(html: &str) -> String {
             if let Some(style) = style_attr {
                 attrs.push(style);
             }
+            if let Some(lang) = decoration.lang {
+                attrs.push(format!("lang=\"{}\"", lang));
+            }
+            if let Some(dir) = decoration.dir {
+                attrs.push(format!("dir=\"{}\"", dir));
+            }
 
             if attrs.is_empty() {
                 result.push_str(&format!("<p>{}</p>\n", content));
@@ -352,13 +438,145 @@ pub fn apply_block_decorations(html: &str) -> String {
         }
     }
 
-    result.trim_end().to_string()
+    result.truncate(result.trim_end().len());
+    Cow::Owned(result)
+}
+
+/// Report floated media that no `@clear()` follows
+///
+/// Every block after a float wraps around it, so a float without a later
+/// clearfix can reach into unrelated sections or the page footer.
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::block_decorations::float_diagnostics;
+///
+/// let html = "<figure class=\"float-start me-3\">\n<picture></picture>\n</figure>\n<p>Text</p>";
+/// assert_eq!(float_diagnostics(html)[0].code, "float-without-clear");
+/// assert!(float_diagnostics(&format!("{}\n<div class=\"clearfix\"></div>", html)).is_empty());
+/// ```
+pub fn float_diagnostics(html: &str) -> Vec<Diagnostic> {
+    html.match_indices("<figure class=\"float-")
+        .filter(|(index, _)| !html[*index..].contains("<div class=\"clearfix\"></div>"))
+        .map(|_| {
+            Diagnostic::info(
+                "float-without-clear",
+                "Floated media is not followed by `@clear()`; all following blocks wrap around it",
+            )
+        })
+        .collect()
+}
+
+/// Whether a line starts with any block decoration prefix
+fn has_decoration_prefix(line: &str) -> bool {
+    const PREFIXES: [&str; 15] = [
+        "SIZE(",
+        "COLOR(",
+        "TRUNCATE:",
+        "TOP:",
+        "MIDDLE:",
+        "BOTTOM:",
+        "BASELINE:",
+        "JUSTIFY:",
+        "RIGHT:",
+        "CENTER:",
+        "LEFT:",
+        "LANG(",
+        "RTL:",
+        "LTR:",
+        "BDI:",
+    ];
+    PREFIXES.iter().any(|prefix| line.starts_with(prefix))
+}
+
+/// Regex to find the style attribute of an opening tag
+static STYLE_ATTR: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\sstyle="([^"]*)""#).unwrap());
+
+/// Add an attribute to an opening tag
+fn insert_attr(tag: &str, name: &str, value: &str) -> String {
+    let end = tag.len() - if tag.ends_with("/>") { 2 } else { 1 };
+    format!("{} {}=\"{}\"{}", &tag[..end], name, value, &tag[end..])
+}
+
+/// Extend an existing attribute of an opening tag, or add it
+fn merge_attr(tag: &str, pattern: &Regex, name: &str, value: &str, separator: &str) -> String {
+    match pattern.captures(tag) {
+        Some(caps) => {
+            let whole = caps.get(0).unwrap();
+            format!(
+                "{} {}=\"{}{}{}\"{}",
+                &tag[..whole.start()],
+                name,
+                &caps[1],
+                separator,
+                value,
+                &tag[whole.end()..]
+            )
+        }
+        None => insert_attr(tag, name, value),
+    }
+}
+
+/// Apply a prefix-only decoration line to the following block
+///
+/// `CENTER:` on its own line followed by `## Title` centers the heading
+/// instead of producing an empty decorated paragraph. Used for headings,
+/// lists, blockquotes and tables.
+///
+/// # Arguments
+///
+/// * `prefixes` - Decoration prefixes (e.g. `COLOR(red): CENTER:`)
+/// * `tag` - Opening tag of the decorated block (e.g. `<h2>`, `<ul>`)
+///
+/// # Returns
+///
+/// Opening tag with the decoration classes, styles, `lang` and `dir` merged in
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::block_decorations::decorate_block_tag;
+///
+/// assert_eq!(decorate_block_tag("CENTER:", "<h2>"), r#"<h2 class="text-center">"#);
+/// assert_eq!(
+///     decorate_block_tag("COLOR(red): LANG(en):", r#"<table class="table">"#),
+///     r#"<table class="table text-red" lang="en">"#
+/// );
+/// ```
+pub fn decorate_block_tag(prefixes: &str, tag: &str) -> String {
+    let (decoration, _) = parse_prefixes(prefixes.trim());
+    let (classes, styles) = decoration.classes_and_styles();
+    let mut tag = tag.to_string();
+
+    if !classes.is_empty() {
+        let classes = attr_escape(&classes.join(" ")).into_owned();
+        tag = merge_attr(&tag, &CLASS_ATTR, "class", &classes, " ");
+    }
+    if !styles.is_empty() {
+        let styles = attr_escape(&styles.join("; ")).into_owned();
+        tag = merge_attr(&tag, &STYLE_ATTR, "style", &styles, "; ");
+    }
+    if let Some(lang) = decoration.lang
+        && !tag.contains(" lang=")
+    {
+        tag = insert_attr(&tag, "lang", &lang);
+    }
+    if let Some(dir) = decoration.dir
+        && !tag.contains(" dir=")
+    {
+        tag = insert_attr(&tag, "dir", dir);
+    }
+
+    tag
 }
 
 /// Apply block placement prefixes to tables and block plugins
 ///
 /// Handles LEFT:/CENTER:/RIGHT:/JUSTIFY: prefixes followed by newline
-/// for UMD tables and block plugins (@function).
+/// for UMD tables, block plugins (@function) and media. FLOAT-LEFT: and
+/// FLOAT-RIGHT: float media (`float-start me-3` / `float-end ms-3`) so the
+/// following text wraps around it until `@clear()`.
 ///
 /// # Arguments
 ///
@@ -367,11 +585,9 @@ pub fn apply_block_decorations(html: &str) -> String {
 /// # Returns
 ///
 /// HTML with block placement applied (Bootstrap utility classes)
-pub fn apply_block_placement(html: &str) -> String {
+pub fn apply_block_placement(html: &str) -> Cow<'_, str> {
     fn merge_class_attr(tag_html: &str, extra_classes: &str) -> String {
-        let class_re = Regex::new(r#"class=\"([^\"]*)\""#).unwrap();
-
-        if let Some(caps) = class_re.captures(tag_html) {
+        if let Some(caps) = PLACEMENT_CLASS_ATTR.captures(tag_html) {
             let existing = caps.get(1).map_or("", |m| m.as_str());
             let mut merged: Vec<String> = if existing.trim().is_empty() {
                 Vec::new()
@@ -385,11 +601,11 @@ pub fn apply_block_placement(html: &str) -> String {
                 }
             }
 
-            class_re
-                .replace(tag_html, format!(r#"class=\"{}\""#, merged.join(" ")))
+            PLACEMENT_CLASS_ATTR
+                .replace(tag_html, format!(r#"class="{}""#, merged.join(" ")))
                 .to_string()
         } else {
-            tag_html.replacen('>', &format!(r#" class=\"{}\">"#, extra_classes), 1)
+            tag_html.replacen('>', &format!(r#" class="{}">"#, extra_classes), 1)
         }
     }
 
@@ -403,13 +619,30 @@ pub fn apply_block_placement(html: &str) -> String {
         }
     }
 
-    let media_block_placement = Regex::new(
-        r#"(?s)<p>\s*(LEFT|CENTER|RIGHT|JUSTIFY):\s*\n\s*(<picture[\s\S]*?</picture>|<video[\s\S]*?</video>|<audio[\s\S]*?</audio>|<a href="[^"]+" download class="download-link[^"]*"[^>]*>[\s\S]*?</a>)\s*</p>"#,
-    )
-    .unwrap();
+    if !["LEFT:", "CENTER:", "RIGHT:", "JUSTIFY:"]
+        .iter()
+        .any(|prefix| html.contains(prefix))
+    {
+        return Cow::Borrowed(html);
+    }
+
+    let with_float_placement = FLOAT_MEDIA_PLACEMENT.replace_all(html, |caps: &regex::Captures| {
+        let float_class = match &caps[1] {
+            "LEFT" => "float-start me-3",
+            _ => "float-end ms-3",
+        };
+        let figure = format!(
+            "<figure class=\"{}\">\n{}\n</figure>",
+            float_class, &caps[2]
+        );
+        match caps[3].trim() {
+            "" => figure,
+            text => format!("{}\n<p>{}</p>", figure, text),
+        }
+    });
 
-    let with_media_placement = media_block_placement
-        .replace_all(html, |caps: &regex::Captures| {
+    let with_media_placement = MEDIA_BLOCK_PLACEMENT
+        .replace_all(&with_float_placement, |caps: &regex::Captures| {
             let placement = &caps[1];
             let media = &caps[2];
 
@@ -429,12 +662,7 @@ pub fn apply_block_placement(html: &str) -> String {
         })
         .to_string();
 
-    let table_and_plugin_placement_in_paragraph = Regex::new(
-        r#"(?s)<p>\s*(LEFT|CENTER|RIGHT|JUSTIFY):\s*\n\s*(<(?:table|template)\b[^>]*>[\s\S]*?</(?:table|template)>)\s*</p>"#,
-    )
-    .unwrap();
-
-    let with_table_and_plugin_placement_in_paragraph = table_and_plugin_placement_in_paragraph
+    let with_table_and_plugin_placement_in_paragraph = PARAGRAPH_BLOCK_PLACEMENT
         .replace_all(&with_media_placement, |caps: &regex::Captures| {
             let placement = &caps[1];
             let block = &caps[2];
@@ -452,12 +680,7 @@ pub fn apply_block_placement(html: &str) -> String {
         })
         .to_string();
 
-    let table_and_plugin_placement = Regex::new(
-        r#"(?s)<p>\s*(LEFT|CENTER|RIGHT|JUSTIFY):\s*</p>\s*(<(?:table|template)\b[^>]*>[\s\S]*?</(?:table|template)>)"#,
-    )
-    .unwrap();
-
-    let with_table_and_plugin_placement = table_and_plugin_placement
+    let with_table_and_plugin_placement = PRECEDING_BLOCK_PLACEMENT
         .replace_all(
             &with_table_and_plugin_placement_in_paragraph,
             |caps: &regex::Captures| {
@@ -499,7 +722,8 @@ pub fn apply_block_placement(html: &str) -> String {
                 }
             },
         )
-        .to_string()
+        .into_owned()
+        .into()
 }
 
 #[cfg(test)]
@@ -574,6 +798,75 @@ mod tests {
         assert!(output.contains("text-end"));
     }
 
+    #[test]
+    fn test_lang_prefix() {
+        let output = apply_block_decorations("LANG(ja): 日本語の段落");
+        assert_eq!(output, "<p lang=\"ja\">日本語の段落</p>");
+    }
+
+    #[test]
+    fn test_lang_in_compound_prefix() {
+        let output = apply_block_decorations("COLOR(primary): LANG(en-US): CENTER: Hello");
+        assert_eq!(
+            output,
+            "<p class=\"text-center text-primary\" lang=\"en-US\">Hello</p>"
+        );
+    }
+
+    #[test]
+    fn test_lang_rejects_invalid_tag() {
+        let output = apply_block_decorations("LANG(\"x\"): text");
+        assert!(!output.contains("lang="));
+    }
+
+    #[test]
+    fn test_lang_in_body_text_is_kept() {
+        let output = apply_block_decorations("CENTER: Write LANG(en): and LANG(ja): too");
+        assert_eq!(
+            output,
+            "<p class=\"text-center\">Write LANG(en): and LANG(ja): too</p>"
+        );
+    }
+
+    #[test]
+    fn test_rtl_prefix_defaults_to_end_alignment() {
+        let output = apply_block_decorations("RTL: مرحبا بالعالم");
+        assert_eq!(
+            output,
+            "<p class=\"text-end\" dir=\"rtl\">مرحبا بالعالم</p>"
+        );
+    }
+
+    #[test]
+    fn test_rtl_prefix_keeps_explicit_alignment() {
+        let output = apply_block_decorations("CENTER: LANG(he): RTL: שלום");
+        assert_eq!(
+            output,
+            "<p class=\"text-center\" lang=\"he\" dir=\"rtl\">שלום</p>"
+        );
+    }
+
+    #[test]
+    fn test_direction_in_body_text_is_kept() {
+        let output = apply_block_decorations("CENTER: Use the RTL: prefix");
+        assert_eq!(output, "<p class=\"text-center\">Use the RTL: prefix</p>");
+
+        let output = apply_block_decorations("COLOR(blue): Mark LTR: or RTL: text");
+        assert_eq!(output, "<p class=\"text-blue\">Mark LTR: or RTL: text</p>");
+    }
+
+    #[test]
+    fn test_ltr_and_bdi_prefixes() {
+        assert_eq!(
+            apply_block_decorations("LTR: Hello"),
+            "<p dir=\"ltr\">Hello</p>"
+        );
+        assert_eq!(
+            apply_block_decorations("BDI: مرحبا Hello"),
+            "<p dir=\"auto\">مرحبا Hello</p>"
+        );
+    }
+
     #[test]
     fn test_block_placement_left() {
         let input = "LEFT:\n|Header|\n|Cell|";
@@ -611,6 +904,21 @@ mod tests {
         assert!(output.contains("@youtube"));
     }
 
+    #[test]
+    fn test_float_media() {
+        let input = "<p>FLOAT-LEFT:\n<picture>\n  <img src=\"a.png\" alt=\"a\" />\n</picture>\nText wraps</p>";
+        assert_eq!(
+            apply_block_placement(input),
+            "<figure class=\"float-start me-3\">\n<picture>\n  <img src=\"a.png\" alt=\"a\" />\n</picture>\n</figure>\n<p>Text wraps</p>"
+        );
+
+        let input = "<p>FLOAT-RIGHT:\n<video controls></video></p>";
+        assert_eq!(
+            apply_block_placement(input),
+            "<figure class=\"float-end ms-3\">\n<video controls></video>\n</figure>"
+        );
+    }
+
     #[test]
     fn test_block_placement_right_media() {
         let input = r#"<p>RIGHT:
@@ -622,4 +930,19 @@ mod tests {
         assert!(output.contains("<picture>"));
         assert!(!output.contains("RIGHT:"));
     }
+
+    #[test]
+    fn test_decorate_block_tag_merges_attributes() {
+        assert_eq!(
+            decorate_block_tag(
+                "SIZE(3rem): RTL:",
+                r#"<blockquote class="umd-blockquote" style="margin: 0">"#
+            ),
+            r#"<blockquote class="umd-blockquote text-end" style="margin: 0; font-size: 3rem" dir="rtl">"#
+        );
+        assert_eq!(
+            decorate_block_tag("LANG(en):", r#"<ol lang="ja">"#),
+            r#"<ol lang="ja">"#
+        );
+    }
 }
@@ -0,0 +1,130 @@
+//! Class name remapping
+//!
+//! Rewrites the `class` attributes of the final HTML according to
+//! [`ClassOptions`], so embedders with a different design system
+//! (Tailwind, a custom stylesheet, ...) do not have to post-process output.
+//!
+//! - names found in `map` are replaced by the mapped value (which may hold
+//!   several classes, or be empty to drop the class)
+//! - every other name gets `prefix` prepended
+//! - `language-*` and `syntect-*` code classes are kept as-is, since they
+//!   are consumed by highlighter stylesheets
+//!
+//! A `class` attribute left empty is removed.
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use crate::parser::ClassOptions;
+
+/// Regex to detect opening tags
+pub(super) static TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[a-zA-Z][^<>]*>").unwrap());
+
+/// Regex to detect the class attribute inside a tag
+pub(super) static CLASS_ATTR: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"\sclass="([^"]*)""#).unwrap());
+
+fn map_classes(classes: &str, options: &ClassOptions) -> String {
+    let mut mapped = Vec::new();
+    for class in classes.split_whitespace() {
+        if let Some(replacement) = options.map.get(class) {
+            mapped.extend(replacement.split_whitespace().map(str::to_string));
+        } else if class.starts_with("language-") || class.starts_with("syntect-") {
+            mapped.push(class.to_string());
+        } else {
+            let prefix = options.prefix.as_deref().unwrap_or("");
+            mapped.push(format!("{}{}", prefix, class));
+        }
+    }
+    mapped.join(" ")
+}
+
+/// Apply the class map to rendered HTML
+///
+/// # Arguments
+///
+/// * `html` - Rendered HTML
+/// * `options` - Class prefix and mapping
+///
+/// # Returns
+///
+/// HTML with class names rewritten
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::class_map::apply_class_map;
+/// use umd::parser::ClassOptions;
+///
+/// let mut options = ClassOptions::default();
+/// options.prefix = Some("umd-".to_string());
+/// options.map.insert("table".to_string(), "min-w-full".to_string());
+///
+/// let html = r#"<table class="table umd-table"><tr><td class="text-center">x</td></tr></table>"#;
+/// assert_eq!(
+///     apply_class_map(html, &options),
+///     r#"<table class="min-w-full umd-umd-table"><tr><td class="umd-text-center">x</td></tr></table>"#
+/// );
+/// ```
+pub fn apply_class_map(html: &str, options: &ClassOptions) -> String {
+    if options.is_identity() {
+        return html.to_string();
+    }
+
+    TAG.replace_all(html, |tag: &Captures| {
+        CLASS_ATTR
+            .replace(&tag[0], |attr: &Captures| {
+                let classes = map_classes(&attr[1], options);
+                if classes.is_empty() {
+                    String::new()
+                } else {
+                    format!(" class=\"{}\"", classes)
+                }
+            })
+            .into_owned()
+    })
+    .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identity_is_noop() {
+        let html = r#"<span class="spoiler">x</span>"#;
+        assert_eq!(apply_class_map(html, &ClassOptions::default()), html);
+    }
+
+    #[test]
+    fn test_map_to_multiple_and_empty() {
+        let mut options = ClassOptions::default();
+        options
+            .map
+            .insert("badge".to_string(), "rounded px-2".to_string());
+        options.map.insert("bg-primary".to_string(), String::new());
+        assert_eq!(
+            apply_class_map(r#"<span class="badge bg-primary">a</span>"#, &options),
+            r#"<span class="rounded px-2">a</span>"#
+        );
+
+        options.map.insert("badge".to_string(), String::new());
+        assert_eq!(
+            apply_class_map(
+                r#"<span class="badge bg-primary" id="b">a</span>"#,
+                &options
+            ),
+            r#"<span id="b">a</span>"#
+        );
+    }
+
+    #[test]
+    fn test_code_classes_and_text_untouched() {
+        let options = ClassOptions {
+            prefix: Some("x-".to_string()),
+            ..Default::default()
+        };
+        let html = r#"<pre><code class="language-rust syntect-highlight">class="a"</code></pre>"#;
+        assert_eq!(apply_class_map(html, &options), html);
+    }
+}
@@ -5,6 +5,8 @@
 //! - Mermaid diagrams: Diagram rendering from Markdown fence blocks with SVG generation
 //! - File name support: Code blocks with associated file names
 
+use std::borrow::Cow;
+
 use once_cell::sync::Lazy;
 use regex::Regex;
 #[cfg(not(target_arch = "wasm32"))]
@@ -13,7 +15,10 @@ use syntect::html::{ClassStyle, ClassedHTMLGenerator};
 use syntect::parsing::SyntaxSet;
 #[cfg(not(target_arch = "wasm32"))]
 use syntect::util::LinesWithEndings;
-use uuid::Uuid;
+
+use super::attributes::{Attributes, parse_attributes, split_trailing_attributes};
+use super::ids::IdGenerator;
+use crate::parser::{Locale, ParserOptions};
 
 static MERMAID_BLOCK_RE: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r#"(?s)<pre><code[^>]*class=\"language-mermaid\"[^>]*>(.*?)</code></pre>"#)
@@ -55,24 +60,39 @@ static SYNTAX_SET: Lazy<SyntaxSet> = Lazy::new(SyntaxSet::load_defaults_newlines
 /// Language-specific: `<pre><code class="language-rust">content</code></pre>` (unchanged)
 ///
 /// Mermaid diagram: `<figure class="code-block code-block-mermaid mermaid-diagram">SVG content</figure>`
-pub fn process_code_blocks(html: &str) -> String {
+pub fn process_code_blocks(html: &str) -> Cow<'_, str> {
+    process_code_blocks_with_options(html, &ParserOptions::default())
+}
+
+/// Process code blocks with the Mermaid diagram ID strategy and the
+/// fallback caption of `options`
+pub fn process_code_blocks_with_options<'a>(
+    html: &'a str,
+    options: &ParserOptions,
+) -> Cow<'a, str> {
     // First handle Mermaid diagrams if present
-    let html = process_mermaid_blocks(html);
+    let html = process_mermaid_blocks(html, options);
 
     // Then process regular code blocks with syntax highlighting
-    process_syntax_highlighted_blocks(&html)
+    if let Cow::Owned(highlighted) = process_syntax_highlighted_blocks(&html) {
+        return Cow::Owned(highlighted);
+    }
+    html
 }
 
 /// Process Mermaid diagram blocks
 ///
 /// Converts `<code class="language-mermaid">` blocks into SVG diagrams with Bootstrap styling
 /// comrak outputs: `<pre><code class="language-mermaid">...</code></pre>`
-fn process_mermaid_blocks(html: &str) -> String {
+fn process_mermaid_blocks<'a>(html: &'a str, options: &ParserOptions) -> Cow<'a, str> {
     // Check if mermaid is present (but not already wrapped)
     if !html.contains("language-mermaid") || html.contains("mermaid-diagram") {
-        return html.to_string();
+        return Cow::Borrowed(html);
     }
 
+    let mut ids = IdGenerator::new(options.ids);
+    let locale: &Locale = &options.locale;
+
     MERMAID_BLOCK_RE
         .replace_all(html, |caps: &regex::Captures| {
             let code = &caps[1];
@@ -81,10 +101,9 @@ fn process_mermaid_blocks(html: &str) -> String {
 
             match render_mermaid_as_svg(code_text) {
                 Ok(svg) => {
-                    let diagram_id = Uuid::new_v4().to_string();
                     format!(
                         "<figure class=\"code-block code-block-mermaid mermaid-diagram\" id=\"mermaid-{}\" data-mermaid-source=\"{}\">{}</figure>",
-                        &diagram_id[..8],
+                        ids.token(code_text, 8),
                         html_escape::encode_double_quoted_attribute(code_text),
                         svg
                     )
@@ -92,14 +111,14 @@ fn process_mermaid_blocks(html: &str) -> String {
                 Err(error) => {
                     let escaped_error = html_escape::encode_double_quoted_attribute(&error);
                     format!(
-                        "<figure class=\"code-block code-block-mermaid mermaid-diagram\"><pre class=\"mermaid-error\" data-error=\"{}\"><code class=\"language-mermaid\">{}</code></pre></figure>",
+                        "<figure class=\"code-block code-block-mermaid mermaid-diagram\"><pre class=\"mermaid-error\" data-error=\"{}\"><code class=\"language-mermaid\">{}</code></pre><figcaption class=\"mermaid-error-message\">{}</figcaption></figure>",
                         escaped_error,
-                        code
+                        code,
+                        html_escape::encode_text(&locale.mermaid_error)
                     )
                 }
             }
         })
-        .to_string()
 }
 
 /// Process syntax highlighting for code blocks
@@ -118,7 +137,7 @@ fn process_mermaid_blocks(html: &str) -> String {
 /// 2. Plain text with title: parse from fence info in data attributes
 /// 3. Language-only: `<pre><code class="language-rust">...</code></pre>`
 /// 4. Language+Title: add figcaption wrapper with title
-fn process_syntax_highlighted_blocks(html: &str) -> String {
+fn process_syntax_highlighted_blocks(html: &str) -> Cow<'_, str> {
     CODE_BLOCK_RE
         .replace_all(html, |caps: &regex::Captures| {
             let attrs = caps.name("attrs").map(|m| m.as_str()).unwrap_or("");
@@ -129,21 +148,30 @@ fn process_syntax_highlighted_blocks(html: &str) -> String {
                 return caps[0].to_string();
             }
 
-            let filename = extract_attribute(attrs, "data-meta")
+            let meta = extract_attribute(attrs, "data-meta")
                 .map(|value| decode_html_entities(&value))
-                .and_then(|meta| extract_filename_from_meta(&meta));
+                .unwrap_or_default();
+            let (meta, block) = split_meta_attributes(&meta);
+            let filename = extract_filename_from_meta(meta);
+            let mut pre_attrs = String::new();
+            if let Some(block) = block {
+                block.merge_into(&mut pre_attrs);
+            }
 
             let rendered_block = if let Some(lang) = language.as_deref() {
                 let decoded = decode_html_entities(code);
                 match highlight_code_with_syntect(lang, &decoded) {
                     Some(highlighted) => format!(
-                        "<pre><code class=\"language-{} syntect-highlight\" data-highlighted=\"true\">{}</code></pre>",
-                        lang, highlighted
+                        "<pre{}><code class=\"language-{} syntect-highlight\" data-highlighted=\"true\">{}</code></pre>",
+                        pre_attrs, lang, highlighted
+                    ),
+                    None => format!(
+                        "<pre{}><code class=\"language-{}\">{}</code></pre>",
+                        pre_attrs, lang, code
                     ),
-                    None => format!("<pre><code class=\"language-{}\">{}</code></pre>", lang, code),
                 }
             } else {
-                format!("<pre>{}</pre>", code)
+                format!("<pre{}>{}</pre>", pre_attrs, code)
             };
 
             if let Some(filename) = filename {
@@ -157,7 +185,6 @@ fn process_syntax_highlighted_blocks(html: &str) -> String {
                 rendered_block
             }
         })
-        .to_string()
 }
 
 fn extract_attribute(attrs: &str, name: &str) -> Option<String> {
@@ -186,6 +213,25 @@ fn extract_language_from_attrs(attrs: &str) -> Option<String> {
     None
 }
 
+/// Split a `{#id .class key=value}` block off the fence info meta
+///
+/// The block applies to the `<pre>` element; `rust {.numbered}` and
+/// `rust:main.rs {#listing-1}` both leave it at the end of the meta.
+fn split_meta_attributes(meta: &str) -> (&str, Option<Attributes>) {
+    let trimmed = meta.trim();
+    if let Some(spec) = trimmed
+        .strip_prefix('{')
+        .and_then(|rest| rest.strip_suffix('}'))
+        && let Some(block) = parse_attributes(spec)
+    {
+        return ("", Some(block));
+    }
+    match split_trailing_attributes(trimmed) {
+        Some((rest, block)) => (rest, Some(block)),
+        None => (meta, None),
+    }
+}
+
 fn extract_filename_from_meta(meta: &str) -> Option<String> {
     let marker = "umd-filename:";
     let index = meta.find(marker)?;
@@ -265,22 +311,6 @@ fn inject_bootstrap_colors(svg: &str) -> String {
     // as they represent structural elements, not semantic colors
 }
 
-/// Simple hash function for generating diagram IDs
-/// Uses a lightweight FNV-1a algorithm
-/// Note: Currently replaced with UUID for unique ID generation in render_mermaid_as_svg
-#[allow(dead_code)]
-fn simple_hash(data: &str) -> u64 {
-    const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
-    const FNV_PRIME: u64 = 0x100000001b3;
-
-    let mut hash = FNV_OFFSET_BASIS;
-    for byte in data.bytes() {
-        hash ^= byte as u64;
-        hash = hash.wrapping_mul(FNV_PRIME);
-    }
-    hash
-}
-
 /// Basic HTML entity decoder for common entities
 fn decode_html_entities(s: &str) -> String {
     s.replace("&lt;", "<")
@@ -423,13 +453,6 @@ mod tests {
         assert!(result.contains("content"));
     }
 
-    #[test]
-    fn test_simple_hash_consistency() {
-        let hash1 = simple_hash("test");
-        let hash2 = simple_hash("test");
-        assert_eq!(hash1, hash2);
-    }
-
     #[test]
     fn test_decoded_html_entities() {
         let encoded = "&lt;div&gt; &amp; &quot;test&quot;";
@@ -447,6 +470,18 @@ mod tests {
         assert!(result.contains("language-rust"));
     }
 
+    #[test]
+    fn test_code_block_attribute_block() {
+        let html = "<pre><code class=\"language-umd-nolang\" data-meta=\"umd-filename:a.txt {#listing .numbered data-start=&quot;3&quot;}\">x</code></pre>";
+        let result = process_code_blocks(html);
+        assert!(result.contains("<span class=\"filename\">a.txt</span>"));
+        assert!(result.contains("<pre id=\"listing\" class=\"numbered\" data-start=\"3\">x</pre>"));
+
+        let html =
+            "<pre><code class=\"language-umd-nolang\" data-meta=\"{onclick=x}\">x</code></pre>";
+        assert_eq!(process_code_blocks(html), "<pre>x</pre>");
+    }
+
     #[test]
     fn test_code_block_with_filename_without_language() {
         let html = "<pre><code class=\"language-umd-nolang\" data-meta=\"umd-filename:config.yml\">key: value</code></pre>";
@@ -0,0 +1,285 @@
+//! Dark-mode aware colors
+//!
+//! With [`ColorMode::Adaptive`](crate::parser::ColorMode::Adaptive):
+//!
+//! - HEX colors in decoration styles (`COLOR(#f00)`, `&color(#f00){...};`,
+//!   table cells) are replaced by CSS custom properties
+//!   (`color: var(--umd-color-ff0000)`) and a palette stylesheet is generated
+//!   that defines a lightness-inverted alternate for dark mode
+//! - Bootstrap theme color classes switch to their adaptive variants
+//!   (`text-primary` → `text-primary-emphasis`,
+//!   `bg-warning` → `bg-warning-subtle`); badges are left alone since their
+//!   `bg-*` class already pairs with a contrasting text color
+//!
+//! Code, inline code color samples, SVG and plugin templates are not touched.
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+/// Regex to detect regions whose colors must stay as authored
+static PROTECTED_REGION: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?s)<pre\b.*?</pre>|<code\b.*?</code>|<svg\b.*?</svg>|<template\b.*?</template>")
+        .unwrap()
+});
+
+static TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[a-zA-Z][^<>]*>").unwrap());
+
+static STYLE_ATTR: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\sstyle="([^"]*)""#).unwrap());
+
+static CLASS_ATTR: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\sclass="([^"]*)""#).unwrap());
+
+/// Regex to detect HEX color declarations inside a style attribute
+static HEX_DECLARATION: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?P<prop>(?:^|[;\s])(?:background-)?color:\s*)#(?P<hex>[0-9a-fA-F]{3,8})\b")
+        .unwrap()
+});
+
+/// Theme colors with `-emphasis` / `-subtle` variants
+const THEME_COLORS: &[&str] = &[
+    "primary",
+    "secondary",
+    "success",
+    "danger",
+    "warning",
+    "info",
+    "light",
+    "dark",
+];
+
+/// Parse `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa` into RGBA channels
+fn parse_hex(hex: &str) -> Option<[u8; 4]> {
+    let expanded: String = match hex.len() {
+        3 | 4 => hex.chars().flat_map(|c| [c, c]).collect(),
+        6 | 8 => hex.to_string(),
+        _ => return None,
+    };
+    let channel = |i: usize| u8::from_str_radix(&expanded[i * 2..i * 2 + 2], 16).ok();
+    Some([
+        channel(0)?,
+        channel(1)?,
+        channel(2)?,
+        if expanded.len() == 8 {
+            channel(3)?
+        } else {
+            255
+        },
+    ])
+}
+
+/// Dark-mode alternate of a color: same hue and saturation, inverted lightness
+fn dark_alternate([r, g, b, a]: [u8; 4]) -> String {
+    let (r, g, b) = (r as f64 / 255.0, g as f64 / 255.0, b as f64 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max + min) / 2.0;
+    let delta = max - min;
+
+    let (hue, saturation) = if delta == 0.0 {
+        (0.0, 0.0)
+    } else {
+        let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+        let hue = if max == r {
+            ((g - b) / delta).rem_euclid(6.0)
+        } else if max == g {
+            (b - r) / delta + 2.0
+        } else {
+            (r - g) / delta + 4.0
+        };
+        (hue * 60.0, saturation)
+    };
+
+    let lightness = 1.0 - lightness;
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let x = chroma * (1.0 - ((hue / 60.0).rem_euclid(2.0) - 1.0).abs());
+    let m = lightness - chroma / 2.0;
+    let (r, g, b) = match (hue / 60.0) as u8 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let to_u8 = |v: f64| ((v + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+
+    if a == 255 {
+        format!("#{:02x}{:02x}{:02x}", to_u8(r), to_u8(g), to_u8(b))
+    } else {
+        format!("#{:02x}{:02x}{:02x}{:02x}", to_u8(r), to_u8(g), to_u8(b), a)
+    }
+}
+
+/// Colors used by the document as (variable suffix, dark alternate)
+///
+/// Variables are named after the normalized light color
+/// (`--umd-color-ff0000`), so palettes of several pages never conflict.
+#[derive(Default)]
+struct Palette {
+    colors: Vec<(String, String)>,
+}
+
+impl Palette {
+    /// Variable reference for a color, registering it on first use
+    fn variable(&mut self, hex: &str) -> Option<String> {
+        let rgba = parse_hex(hex)?;
+        let name = match rgba {
+            [r, g, b, 255] => format!("{:02x}{:02x}{:02x}", r, g, b),
+            [r, g, b, a] => format!("{:02x}{:02x}{:02x}{:02x}", r, g, b, a),
+        };
+        if !self.colors.iter().any(|(existing, _)| *existing == name) {
+            self.colors.push((name.clone(), dark_alternate(rgba)));
+        }
+        Some(format!("var(--umd-color-{})", name))
+    }
+
+    fn stylesheet(&self) -> Option<String> {
+        if self.colors.is_empty() {
+            return None;
+        }
+        let declarations = |dark: bool| {
+            self.colors
+                .iter()
+                .map(|(name, dark_color)| {
+                    if dark {
+                        format!("  --umd-color-{}: {};\n", name, dark_color)
+                    } else {
+                        format!("  --umd-color-{}: #{};\n", name, name)
+                    }
+                })
+                .collect::<String>()
+        };
+        Some(format!(
+            ":root {{\n{}}}\n@media (prefers-color-scheme: dark) {{\n:root {{\n{}}}\n}}\n[data-bs-theme=\"dark\"] {{\n{}}}\n",
+            declarations(false),
+            declarations(true),
+            declarations(true)
+        ))
+    }
+}
+
+fn adaptive_classes(classes: &str) -> String {
+    if classes.split_whitespace().any(|class| class == "badge") {
+        return classes.to_string();
+    }
+    classes
+        .split_whitespace()
+        .map(|class| {
+            let adaptive = class
+                .strip_prefix("text-")
+                .filter(|color| THEME_COLORS.contains(color))
+                .map(|color| format!("text-{}-emphasis", color))
+                .or_else(|| {
+                    class
+                        .strip_prefix("bg-")
+                        .filter(|color| THEME_COLORS.contains(color))
+                        .map(|color| format!("bg-{}-subtle", color))
+                });
+            adaptive.unwrap_or_else(|| class.to_string())
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn adapt_tag(tag: &str, palette: &mut Palette) -> String {
+    // Inline code color samples show the authored color
+    if tag.contains("class=\"inline-code-color\"") {
+        return tag.to_string();
+    }
+    let tag = STYLE_ATTR.replace(tag, |caps: &Captures| {
+        let style = HEX_DECLARATION.replace_all(&caps[1], |decl: &Captures| {
+            match palette.variable(&decl["hex"]) {
+                Some(variable) => format!("{}{}", &decl["prop"], variable),
+                None => decl[0].to_string(),
+            }
+        });
+        format!(" style=\"{}\"", style)
+    });
+    CLASS_ATTR
+        .replace(&tag, |caps: &Captures| {
+            format!(" class=\"{}\"", adaptive_classes(&caps[1]))
+        })
+        .into_owned()
+}
+
+fn adapt_segment(segment: &str, palette: &mut Palette) -> String {
+    TAG.replace_all(segment, |caps: &Captures| adapt_tag(&caps[0], palette))
+        .into_owned()
+}
+
+/// Apply adaptive colors
+///
+/// # Arguments
+///
+/// * `html` - Rendered HTML
+///
+/// # Returns
+///
+/// Tuple of (HTML using palette variables and adaptive classes, palette CSS
+/// if any HEX color was found)
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::color_mode::apply_adaptive_colors;
+///
+/// let (html, css) = apply_adaptive_colors(r#"<span style="color: #000">a</span>"#);
+/// assert_eq!(html, r#"<span style="color: var(--umd-color-000000)">a</span>"#);
+/// assert!(css.unwrap().contains("--umd-color-000000: #ffffff;"));
+/// ```
+pub fn apply_adaptive_colors(html: &str) -> (String, Option<String>) {
+    let mut palette = Palette::default();
+    let mut output = String::with_capacity(html.len());
+    let mut last = 0;
+
+    for region in PROTECTED_REGION.find_iter(html) {
+        output.push_str(&adapt_segment(&html[last..region.start()], &mut palette));
+        output.push_str(region.as_str());
+        last = region.end();
+    }
+    output.push_str(&adapt_segment(&html[last..], &mut palette));
+
+    (output, palette.stylesheet())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dark_alternate() {
+        assert_eq!(dark_alternate([0, 0, 0, 255]), "#ffffff");
+        assert_eq!(dark_alternate([255, 0, 0, 255]), "#ff0000");
+        assert_eq!(dark_alternate([0x80, 0, 0, 0x80]), "#ff7f7f80");
+    }
+
+    #[test]
+    fn test_palette_deduplicates_colors() {
+        let html = r#"<p style="color: #F00; background-color: #ffeecc">a</p><span style="color: #f00">b</span>"#;
+        let (output, css) = apply_adaptive_colors(html);
+        assert_eq!(
+            output,
+            r#"<p style="color: var(--umd-color-ff0000); background-color: var(--umd-color-ffeecc)">a</p><span style="color: var(--umd-color-ff0000)">b</span>"#
+        );
+        let css = css.unwrap();
+        assert_eq!(css.matches("--umd-color-ff0000:").count(), 3);
+        assert!(css.contains("--umd-color-ffeecc: #332200;"));
+        assert!(css.contains("@media (prefers-color-scheme: dark)"));
+    }
+
+    #[test]
+    fn test_theme_classes() {
+        let html = r#"<span class="text-primary bg-warning">a</span><span class="badge bg-primary">b</span><span class="text-red">c</span>"#;
+        let (output, css) = apply_adaptive_colors(html);
+        assert_eq!(
+            output,
+            r#"<span class="text-primary-emphasis bg-warning-subtle">a</span><span class="badge bg-primary">b</span><span class="text-red">c</span>"#
+        );
+        assert!(css.is_none());
+    }
+
+    #[test]
+    fn test_code_untouched() {
+        let html = r#"<code><span class="inline-code-color" style="background-color: #ff0000;"></span>#ff0000</code><span class="inline-code-color" style="background-color: #ff0000;"></span>"#;
+        assert_eq!(apply_adaptive_colors(html).0, html);
+    }
+}
@@ -0,0 +1,85 @@
+//! Bootstrap 5.3 color utilities shared by `COLOR()`, `&color()` and table cells
+//!
+//! - `gradient-{color}` → `bg-{color} bg-gradient` (in either argument position,
+//!   since a gradient is always a background)
+//! - a third argument sets the opacity: `COLOR(primary,,50):` →
+//!   `text-primary text-opacity-50`. It applies to the foreground color class,
+//!   or to the background class when only a background is given.
+
+/// Colors with `bg-*` utilities usable under `bg-gradient`
+const GRADIENT_COLORS: &[&str] = &[
+    "primary",
+    "secondary",
+    "success",
+    "danger",
+    "warning",
+    "info",
+    "light",
+    "dark",
+    "blue",
+    "indigo",
+    "purple",
+    "pink",
+    "red",
+    "orange",
+    "yellow",
+    "green",
+    "teal",
+    "cyan",
+];
+
+/// Opacity levels provided by `text-opacity-*` / `bg-opacity-*`
+const OPACITY_LEVELS: &[&str] = &["10", "25", "50", "75", "100"];
+
+/// Map `gradient-{color}` to gradient background classes
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::color_utilities::gradient_classes;
+///
+/// assert_eq!(gradient_classes("gradient-primary").as_deref(), Some("bg-primary bg-gradient"));
+/// assert_eq!(gradient_classes("gradient-#fff"), None);
+/// ```
+pub fn gradient_classes(value: &str) -> Option<String> {
+    let color = value.trim().strip_prefix("gradient-")?;
+    GRADIENT_COLORS
+        .contains(&color)
+        .then(|| format!("bg-{} bg-gradient", color))
+}
+
+/// Map the opacity argument to an opacity class
+///
+/// # Arguments
+///
+/// * `value` - Opacity argument (`10`, `25`, `50`, `75` or `100`)
+/// * `classes` - Color classes produced for the decoration
+///
+/// # Returns
+///
+/// `text-opacity-N` when a `text-*` class is present, otherwise
+/// `bg-opacity-N` when a `bg-*` class is present
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::color_utilities::opacity_class;
+///
+/// let classes = vec!["text-primary".to_string()];
+/// assert_eq!(opacity_class("50", &classes).as_deref(), Some("text-opacity-50"));
+/// assert_eq!(opacity_class("33", &classes), None);
+/// ```
+pub fn opacity_class(value: &str, classes: &[String]) -> Option<String> {
+    let value = value.trim();
+    if !OPACITY_LEVELS.contains(&value) {
+        return None;
+    }
+    let has = |prefix: &str| classes.iter().any(|class| class.starts_with(prefix));
+    if has("text-") {
+        Some(format!("text-opacity-{}", value))
+    } else if has("bg-") {
+        Some(format!("bg-opacity-{}", value))
+    } else {
+        None
+    }
+}
@@ -5,10 +5,23 @@
 
 use once_cell::sync::Lazy;
 use regex::{Captures, Regex};
-use std::collections::HashMap;
-
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+use super::attachments;
+use super::attributes;
+use super::color_utilities::{gradient_classes, opacity_class};
+use super::cross_reference;
+use super::embeds;
+use super::font_size::{FontSize, map_font_size};
+use super::ids::IdGenerator;
 use super::plugin_markers;
 use super::preprocessor;
+use super::toc;
+use crate::diagnostics::Diagnostic;
+use crate::html_builder::{attr_escape, text_escape, url_attr};
+use crate::parser::{Locale, ParserOptions, PluginContent, Profile};
+use crate::sanitizer::{sanitize_css_color, split_args};
 
 thread_local! {
     static MATH_CONVERTER: std::cell::RefCell<Option<math_core::LatexToMathML>> =
@@ -17,22 +30,6 @@ thread_local! {
         );
 }
 
-/// Escape HTML special characters
-///
-/// # Arguments
-///
-/// * `input` - Text to escape
-///
-/// # Returns
-///
-/// HTML-escaped string
-fn escape_html_text(input: &str) -> String {
-    input
-        .replace('&', "&amp;")
-        .replace('<', "&lt;")
-        .replace('>', "&gt;")
-}
-
 /// Parse comma-separated args into a vector
 ///
 /// # Arguments
@@ -62,11 +59,75 @@ fn render_args_as_data(args: &str) -> String {
     parse_args(args)
         .iter()
         .enumerate()
-        .map(|(i, arg)| format!("<data value=\"{}\">{}</data>", i, escape_html_text(arg)))
+        .map(|(i, arg)| format!("<data value=\"{}\">{}</data>", i, text_escape(arg)))
         .collect::<Vec<_>>()
         .join("")
 }
 
+/// Render a host plugin as `<template class="umd-plugin umd-plugin-{name}">`
+///
+/// With `PluginContent::Encoded` the template is empty: every argument is
+/// base64-encoded into the comma-separated `data-umd-args` and the content
+/// into `data-umd-content`, so no markup or entity reaches the DOM even when
+/// a frontend copies the template's text into `innerHTML`. Both hold the
+/// text an escaped template would read as, with character references
+/// decoded.
+fn render_plugin_template(
+    function: &str,
+    args: &str,
+    content: &str,
+    mode: PluginContent,
+) -> String {
+    use base64::{Engine as _, engine::general_purpose};
+
+    match mode {
+        PluginContent::Escaped => format!(
+            "<template class=\"umd-plugin umd-plugin-{}\">{}{}</template>",
+            function,
+            render_args_as_data(args),
+            text_escape(content)
+        ),
+        PluginContent::Encoded => {
+            let mut attrs = String::new();
+            let args = parse_args(args);
+            if !args.is_empty() {
+                let encoded: Vec<String> = args
+                    .iter()
+                    .map(|arg| {
+                        general_purpose::STANDARD
+                            .encode(html_escape::decode_html_entities(arg).as_bytes())
+                    })
+                    .collect();
+                attrs.push_str(&format!(" data-umd-args=\"{}\"", encoded.join(",")));
+            }
+            if !content.is_empty() {
+                attrs.push_str(&format!(
+                    " data-umd-content=\"{}\"",
+                    general_purpose::STANDARD
+                        .encode(html_escape::decode_html_entities(content).as_bytes())
+                ));
+            }
+            format!(
+                "<template class=\"umd-plugin umd-plugin-{}\"{}></template>",
+                function, attrs
+            )
+        }
+    }
+}
+
+/// Render a plugin that `allowed_plugins` / `denied_plugins` rejects as its
+/// escaped source text
+///
+/// `&` is written as `&#38;` so the inline decoration pass, which also reads
+/// comrak's `&amp;name(...)`, leaves the text alone.
+fn render_denied_plugin(function: &str, source: &str) -> String {
+    format!(
+        "<span class=\"umd-plugin-denied\" data-plugin=\"{}\">{}</span>",
+        function,
+        text_escape(source).replace("&amp;", "&#38;")
+    )
+}
+
 fn map_table_plugin_option_to_class(option: &str) -> Option<&'static str> {
     match option {
         "striped" => Some("table-striped"),
@@ -84,9 +145,7 @@ fn merge_class_attr(existing_attrs: &str, add_classes: &[String]) -> String {
         return existing_attrs.to_string();
     }
 
-    let class_pattern = Regex::new(r#"class=\"([^\"]*)\""#).unwrap();
-
-    if let Some(class_caps) = class_pattern.captures(existing_attrs) {
+    if let Some(class_caps) = CLASS_ATTRIBUTE.captures(existing_attrs) {
         let existing_classes = class_caps.get(1).map_or("", |m| m.as_str());
         let mut class_list: Vec<String> = existing_classes
             .split_whitespace()
@@ -99,11 +158,11 @@ fn merge_class_attr(existing_attrs: &str, add_classes: &[String]) -> String {
             }
         }
 
-        let merged = format!(r#"class=\"{}\""#, class_list.join(" "));
-        class_pattern.replace(existing_attrs, merged).to_string()
+        let merged = format!(r#"class="{}""#, class_list.join(" "));
+        CLASS_ATTRIBUTE.replace(existing_attrs, merged).to_string()
     } else {
         let mut attrs = existing_attrs.to_string();
-        attrs.push_str(&format!(r#" class=\"{}\""#, add_classes.join(" ")));
+        attrs.push_str(&format!(r#" class="{}""#, add_classes.join(" ")));
         attrs
     }
 }
@@ -123,13 +182,10 @@ fn process_table_plugin(function_args: &str, content: &str) -> String {
         }
     }
 
-    let table_pattern = Regex::new(r"(?s)<table[^>]*>.*?</table>").unwrap();
-    let open_table_pattern = Regex::new(r"<table([^>]*)>").unwrap();
-
-    if let Some(table_match) = table_pattern.find(&rendered_content) {
+    if let Some(table_match) = TABLE_ELEMENT.find(&rendered_content) {
         let table_html = table_match.as_str();
 
-        let table_with_classes = open_table_pattern
+        let table_with_classes = TABLE_OPEN_TAG
             .replace(table_html, |caps: &Captures| {
                 let existing_attrs = caps.get(1).map_or("", |m| m.as_str());
                 let merged_attrs = merge_class_attr(existing_attrs, &table_classes);
@@ -161,31 +217,14 @@ fn process_table_plugin(function_args: &str, content: &str) -> String {
     }
 }
 
-/// Map font size value to Bootstrap class or inline style
-fn map_font_size_value(value: &str) -> (bool, String) {
-    // Check if value has unit (rem, em, px, etc.)
-    if value.contains("rem") || value.contains("em") || value.contains("px") {
-        return (false, value.to_string()); // Return as inline style
-    }
-
-    // Map to Bootstrap fs-* classes (unitless values)
-    let class = match value {
-        "2.5" => "fs-1",
-        "2" | "2.0" => "fs-2",
-        "1.75" => "fs-3",
-        "1.5" => "fs-4",
-        "1.25" => "fs-5",
-        "0.875" => "fs-6",
-        _ => return (false, format!("{}rem", value)), // Custom value as inline style
-    };
-
-    (true, class.to_string())
-}
-
 /// Map color value to Bootstrap class or inline style
 fn map_color_value(value: &str, is_background: bool) -> Option<(bool, String)> {
     let trimmed = value.trim();
 
+    if let Some(classes) = gradient_classes(trimmed) {
+        return Some((true, classes));
+    }
+
     // Bootstrap theme colors (14) + custom colors (10)
     let bootstrap_colors = [
         // Theme colors
@@ -223,17 +262,8 @@ fn map_color_value(value: &str, is_background: bool) -> Option<(bool, String)> {
         }
     }
 
-    // Validate HEX color format (#RGB or #RRGGBB)
-    // TODO: Future support for rgb() and hsl() formats
-    if trimmed.starts_with('#') && (trimmed.len() == 4 || trimmed.len() == 7) {
-        // Validate all characters after # are hex digits
-        if trimmed[1..].chars().all(|c| c.is_ascii_hexdigit()) {
-            return Some((false, trimmed.to_string()));
-        }
-    }
-
-    // Invalid color - reject
-    None
+    // HEX, rgb()/hsl() or CSS named color as inline style; anything else is rejected
+    sanitize_css_color(trimmed).map(|color| (false, color))
 }
 
 // Patterns that need special handling
@@ -241,7 +271,50 @@ fn map_color_value(value: &str, is_background: bool) -> Option<(bool, String)> {
 /// Regex to detect UMD blockquote: > ... <
 static UMD_BLOCKQUOTE: Lazy<Regex> = Lazy::new(|| {
     // Match single line > content < pattern
-    Regex::new(r"(?m)^>\s*(.+?)\s*<\s*$").unwrap()
+    Regex::new(r"(?m)^>[ \t]*(.+?)[ \t]*<[ \t]*$").unwrap()
+});
+
+/// Regex to detect the opening line of a multi-line UMD blockquote: `> ...`
+static UMD_BLOCKQUOTE_OPEN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^>[ \t]*(.*?)[ \t]*$").unwrap());
+
+/// Regex to detect blockquote opening and closing tags
+static BLOCKQUOTE_TAG: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"<blockquote\b[^>]*>|</blockquote>").unwrap());
+
+/// Regex to detect Markdown images in `@gallery` content: `![alt](url "title")`
+static GALLERY_IMAGE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"!\[([^\]]*)\]\(\s*([^\s)]+)(?:\s+"([^"]*)")?\s*\)"#).unwrap());
+
+/// Marker paragraphs around the content of a multi-line UMD blockquote
+const UMD_BLOCKQUOTE_START: &str = "{{UMD_BLOCKQUOTE_START}}";
+const UMD_BLOCKQUOTE_END: &str = "{{UMD_BLOCKQUOTE_END}}";
+
+/// Regex to detect a line consisting only of block decoration prefixes
+static PREFIX_ONLY_LINE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^(?:(?:SIZE\([^)]+\)|COLOR\((?:[^()\n]|\([^()\n]*\))*\)|LANG\([^)]*\)|RTL|LTR|BDI|TRUNCATE|TOP|MIDDLE|BOTTOM|BASELINE|JUSTIFY|RIGHT|CENTER|LEFT):[ \t]*)+$",
+    )
+    .unwrap()
+});
+
+/// Regex to detect the first line of a heading, list, blockquote or table
+static DECORATABLE_BLOCK_START: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^ {0,3}(?:#{1,6}(?:[ \t]|$)|[-*+][ \t]|\d{1,9}[.)][ \t]|>|\||\{\{UMD_BLOCKQUOTE:)")
+        .unwrap()
+});
+
+/// Regex to detect a decoration marker paragraph followed by its target block
+static BLOCK_TARGET: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"<p>\{\{BLOCK_TARGET_B64:([A-Za-z0-9+/=]+):BLOCK_TARGET_B64\}\}</p>\s*(?:<p>(<blockquote\b[^>]*>)(.*?</blockquote>)</p>|(<(?:h[1-6]|ul|ol|blockquote|table)\b[^>]*>))",
+    )
+    .unwrap()
+});
+
+/// Regex to detect a decoration marker whose target block was not found
+static ORPHAN_BLOCK_TARGET: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\{\{BLOCK_TARGET_B64:([A-Za-z0-9+/=]+):BLOCK_TARGET_B64\}\}").unwrap()
 });
 
 /// Regex to detect Markdown-style emphasis that might conflict with UMD
@@ -249,15 +322,224 @@ static UMD_BLOCKQUOTE: Lazy<Regex> = Lazy::new(|| {
 static TRIPLE_STAR_EMPHASIS: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"\*\*\*([^*]+)\*\*\*").unwrap());
 
-/// Regex to detect custom header ID syntax: # Header {#custom-id}
+/// Regex to detect a heading attribute block: # Header {#custom-id .class}
 static CUSTOM_HEADER_ID: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"(?m)^(#{1,6})\s+(.+?)\s+\{#([a-zA-Z0-9_-]+)\}\s*$").unwrap());
+    Lazy::new(|| Regex::new(r"(?m)^(#{1,6})\s+(.+?)\s+\{([^{}]+)\}\s*$").unwrap());
+
+/// Regex to detect ATX headings (used to count headings in document order)
+static ATX_HEADING: Lazy<Regex> = Lazy::new(|| Regex::new(r"^ {0,3}#{1,6}(?:[ \t]|$)").unwrap());
+
+/// Regex to detect the anchor element inserted into headings by `postprocess_conflicts`
+static HEADING_ANCHOR: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r##"<a href="#([^"]+)" aria-hidden="true" class="anchor" id="([^"]+)"></a>"##)
+        .unwrap()
+});
+
+/// Regex to detect headings as rendered by comrak (content may hold inline markup)
+static HEADING_ELEMENT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?s)<h([1-6])>(.*?)</h([1-6])>").unwrap());
+
+/// Regex to detect the start of a heading up to its anchor
+static HEADING_START: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r##"<h([1-6])[^>]*><a href="#[^"]*" aria-hidden="true" class="anchor" id="[^"]*"></a>"##,
+    )
+    .unwrap()
+});
+
+/// Regex to detect a plugin rejected by the plugin allow/deny lists
+static DENIED_PLUGIN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"<span class="umd-plugin-denied" data-plugin="(\w+)">"#).unwrap());
+
+/// Regex to detect a rendered definition list, with the paragraph comrak
+/// wrapped it in
+static DEFINITION_LIST: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?s)(<p>\s*)?\{\{DEFINITION_LIST:(default|horizontal):DEFINITION_LIST\}\}(.*?)\{\{/DEFINITION_LIST\}\}(\s*</p>)?",
+    )
+    .unwrap()
+});
+
+/// Regex to detect one term and its definition inside a definition list
+static DEFINITION_ITEM: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?s)\{\{DEFINITION_TERM:(.*?):DEFINITION_TERM\}\}\{\{DEFINITION_DESC:(.*?):DEFINITION_DESC\}\}",
+    )
+    .unwrap()
+});
+
+/// Regex to detect opening and closing heading tags
+static HEADING_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<(/?)h([1-6])\b").unwrap());
+
+/// Regex to collect `id` attributes from rendered HTML
+static ELEMENT_ID: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\sid="([^"]+)""#).unwrap());
+
+/// `class="..."` attribute of a tag
+static CLASS_ATTRIBUTE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"class=\"([^\"]*)\""#).unwrap());
+
+/// Whole `<table>` element
+static TABLE_ELEMENT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?s)<table[^>]*>.*?</table>").unwrap());
+
+/// Opening `<table>` tag with its attributes
+static TABLE_OPEN_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<table([^>]*)>").unwrap());
+
+/// Line starting with block decoration prefixes (`COLOR(red): CENTER: ...`)
+static BLOCK_DECORATION_PREFIX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?m)^((?:(?:SIZE\([^)]+\)|COLOR\((?:[^()\n]|\([^()\n]*\))*\)|LANG\([^)]*\)|RTL|LTR|BDI|TRUNCATE|TOP|MIDDLE|BOTTOM|BASELINE|JUSTIFY|RIGHT|CENTER|LEFT):[ \t]*)+.*)$",
+    )
+    .unwrap()
+});
+
+/// Markdown link `[text](url)` in plugin content
+static MARKDOWN_LINK: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[([^\]]+)\]\(([^)]+)\)").unwrap());
+
+/// Link followed by an attribute spec (`[text](url){#id .class}`)
+static LINK_WITH_ATTRIBUTE_SPEC: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?s)<a\s+([^>]*\bhref=\"[^\"]+\"[^>]*)>(.*?)</a>\s*\{([^}]+)\}"#).unwrap()
+});
+
+/// Start tag of an element that takes a trailing attribute block
+static ATTRIBUTE_TARGET: Lazy<Regex> = Lazy::new(|| Regex::new(r"<(li|p)(\s[^>]*)?>").unwrap());
+
+/// Attribute block paragraph directly before a block (`{.list-group}` + `- item`)
+static BLOCK_ATTRIBUTE_LINE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"<p>\{([^{}\n]+)\}</p>\n<(ul|ol|blockquote|pre|table)((?:\s[^>]*)?)>").unwrap()
+});
+
+/// `<a>` element with a double- or single-quoted `href`
+static LINK_ELEMENT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?s)<a\s+([^>]*\bhref=(?:\"([^\"]+)\"|'([^']+)')[^>]*)>(.*?)</a>"#).unwrap()
+});
+
+/// `class='...'` attribute of a tag
+static CLASS_ATTRIBUTE_SINGLE_QUOTED: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"class='([^']*)'").unwrap());
+
+/// Single-line UMD blockquote marker
+static UMD_BLOCKQUOTE_MARKER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{\{UMD_BLOCKQUOTE:(.+?):UMD_BLOCKQUOTE\}\}").unwrap());
+
+/// Protected block decoration, with the `<p>` comrak wrapped it in
+static BLOCK_DECORATION_MARKER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(<p>)?\{\{BLOCK_DECORATION_B64:([A-Za-z0-9+/=]+):BLOCK_DECORATION_B64\}\}(</p>)?")
+        .unwrap()
+});
+
+/// Protected `&function(args){content};` plugin
+static INLINE_PLUGIN_MARKER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\{\{INLINE_PLUGIN:(\w+):([\s\S]*?):([\s\S]*?):INLINE_PLUGIN\}\}").unwrap()
+});
+
+/// Protected `&function(args);` plugin
+static INLINE_PLUGIN_ARGSONLY_MARKER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\{\{INLINE_PLUGIN_ARGSONLY:(\w+):([\s\S]*?):INLINE_PLUGIN_ARGSONLY\}\}").unwrap()
+});
+
+/// Protected `&function;` plugin
+static INLINE_PLUGIN_NOARGS_MARKER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{\{INLINE_PLUGIN_NOARGS:(\w+):INLINE_PLUGIN_NOARGS\}\}").unwrap());
+
+/// Protected `@function(args){{content}}` plugin
+static BLOCK_PLUGIN_MARKER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\{\{BLOCK_PLUGIN:(\w+):([\s\S]*?):([\s\S]*?):BLOCK_PLUGIN\}\}").unwrap()
+});
+
+/// Protected `@function(args)` plugin
+static BLOCK_PLUGIN_ARGSONLY_MARKER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\{\{BLOCK_PLUGIN_ARGSONLY:(\w+):([\s\S]*?):BLOCK_PLUGIN_ARGSONLY\}\}").unwrap()
+});
+
+/// Plugin template wrapped in a paragraph
+static WRAPPED_PLUGIN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"<p>\s*(<template class="umd-plugin[^"]*"[^>]*>.*?</template>)\s*</p>"#).unwrap()
+});
+
+/// Verse block wrapped in a paragraph
+static WRAPPED_VERSE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?s)<p>\s*(<p class="verse">.*?</p>)\s*</p>"#).unwrap());
+
+/// Gallery wrapped in a paragraph
+static WRAPPED_GALLERY: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?s)<p>\s*(<div class="umd-gallery [^"]*"[^>]*>.*?</div>\n</div>)\s*</p>"#)
+        .unwrap()
+});
+
+/// Table of contents placeholder wrapped in a paragraph
+static WRAPPED_TOC: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"<p>\s*(<nav class="umd-toc"[^>]*></nav>)\s*</p>"#).unwrap());
+
+/// Clearfix block wrapped in a paragraph
+static WRAPPED_CLEARFIX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"<p>\s*(<div class="clearfix"></div>)\s*</p>"#).unwrap());
+
+/// Block QR code wrapped in a paragraph
+static WRAPPED_QR: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"<p>\s*(<div class="umd-qr"><svg [^>]*>.*?</svg></div>)\s*</p>"#).unwrap()
+});
+
+/// Video or map embed wrapped in a paragraph
+static WRAPPED_EMBED: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"<p>\s*(<div class="ratio ratio-\w+ umd-embed [^"]*"><iframe [^>]*></iframe></div>)\s*</p>"#,
+    )
+    .unwrap()
+});
+
+/// Task list checkbox followed by the indeterminate marker
+static TASK_INDETERMINATE_CHECKBOX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"<input([^>]*\btype=\"checkbox\"[^>]*)/?>\s*\{\{TASK_INDETERMINATE\}\}"#).unwrap()
+});
+
+/// GFM alert blockquote (`> [!NOTE]`)
+static GFM_ALERT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?s)<blockquote class="blockquote">\s*<p>\[!(NOTE|TIP|IMPORTANT|WARNING|CAUTION)\]\s*(.*?)</p>(.*?)</blockquote>"#,
+    )
+    .unwrap()
+});
+
+/// `<td>` cell of a GFM table
+static TABLE_DATA_CELL: Lazy<Regex> = Lazy::new(|| Regex::new(r"<td([^>]*)>(.*?)</td>").unwrap());
+
+/// `<th>` cell of a GFM table
+static TABLE_HEADER_CELL: Lazy<Regex> = Lazy::new(|| Regex::new(r"<th([^>]*)>(.*?)</th>").unwrap());
+
+/// Root-relative `href`/`src`/`srcset` value in double quotes
+static ROOT_PATH_ATTRIBUTE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"((?:href|src|srcset)\s*=\s*)"(/[^"]*)""#).unwrap());
+
+/// Root-relative `href`/`src`/`srcset` value in single quotes
+static ROOT_PATH_ATTRIBUTE_SINGLE_QUOTED: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"((?:href|src|srcset)\s*=\s*)'(/[^']*)'").unwrap());
+
+/// Decoration that only sets the placement of the following block
+static PLACEMENT_ONLY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(LEFT|CENTER|RIGHT|JUSTIFY):\s*$").unwrap());
+
+/// Plugin markers whose quotes comrak escaped
+static PLUGIN_MARKERS: Lazy<Vec<Regex>> = Lazy::new(|| {
+    [
+        "INLINE_PLUGIN",
+        "BLOCK_PLUGIN",
+        "BLOCK_PLUGIN_ARGSONLY",
+        "INLINE_PLUGIN_ARGSONLY",
+        "INLINE_PLUGIN_NOARGS",
+    ]
+    .iter()
+    .map(|name| Regex::new(&format!(r"\{{\{{{name}:([^\}}]+):{name}\}}\}}")).unwrap())
+    .collect()
+});
 
 /// Store custom header IDs and UMD tables during preprocessing
 #[derive(Debug, Clone)]
 pub struct HeaderIdMap {
     /// Maps heading number (1-based) to custom ID
     pub ids: HashMap<usize, String>,
+    /// Maps heading number (1-based) to the classes and `key=value` pairs
+    /// of its attribute block
+    pub attributes: HashMap<usize, attributes::Attributes>,
     /// Maps table markers to HTML content
     pub tables: Vec<(String, String)>,
 }
@@ -266,6 +548,7 @@ impl HeaderIdMap {
     pub fn new() -> Self {
         Self {
             ids: HashMap::new(),
+            attributes: HashMap::new(),
             tables: Vec::new(),
         }
     }
@@ -296,57 +579,102 @@ impl HeaderIdMap {
 pub fn preprocess_conflicts(input: &str) -> (String, HeaderIdMap) {
     // Step 1: Remove comments before any other processing
     let mut result = preprocessor::remove_comments(input);
+    if let Cow::Owned(removed) = preprocessor::remove_comment_plugins(&result) {
+        result = removed;
+    }
 
     let mut header_map = HeaderIdMap::new();
     let mut heading_counter = 0;
 
     // Extract custom header IDs: # Header {#custom-id}
-    result = CUSTOM_HEADER_ID
-        .replace_all(&result, |caps: &Captures| {
-            heading_counter += 1;
-            let hashes = &caps[1];
-            let title = &caps[2];
-            let custom_id = &caps[3];
+    // Every ATX heading is counted (not only those with a custom ID) so the
+    // counter matches the heading order seen in post-processing.
+    let mut lines = Vec::new();
+    let mut fence: Option<(char, usize)> = None;
+    for line in result.lines() {
+        let trimmed = line.trim_start();
+        let fence_char = trimmed.chars().next().filter(|c| *c == '`' || *c == '~');
+        if let Some(ch) = fence_char {
+            let run = trimmed.chars().take_while(|c| *c == ch).count();
+            if run >= 3 {
+                match fence {
+                    None => fence = Some((ch, run)),
+                    Some((open_ch, open_len))
+                        if open_ch == ch && run >= open_len && trimmed[run..].trim().is_empty() =>
+                    {
+                        fence = None
+                    }
+                    _ => {}
+                }
+                lines.push(line.to_string());
+                continue;
+            }
+        }
 
-            // Store the custom ID for this heading
-            header_map
-                .ids
-                .insert(heading_counter, custom_id.to_string());
+        if fence.is_none() && ATX_HEADING.is_match(line) {
+            heading_counter += 1;
+            if let Some(caps) = CUSTOM_HEADER_ID.captures(line)
+                && let Some(mut block) = attributes::parse_attributes(&caps[3])
+            {
+                // Store the custom ID and other attributes for this heading
+                if let Some(id) = block.id.take() {
+                    header_map.ids.insert(heading_counter, id);
+                }
+                if !block.is_empty() {
+                    header_map.attributes.insert(heading_counter, block);
+                }
 
-            // Return the heading without the {#id} part
-            format!("{} {}", hashes, title)
-        })
-        .to_string();
+                // Keep the heading without the attribute block
+                lines.push(format!("{} {}", &caps[1], &caps[2]));
+                continue;
+            }
+        }
+        lines.push(line.to_string());
+    }
+    let had_trailing_newline = result.ends_with('\n');
+    result = lines.join("\n");
+    if had_trailing_newline {
+        result.push('\n');
+    }
 
     // Handle UMD blockquotes: > ... <
     // Use a safe marker that won't be affected by HTML escaping
-    result = UMD_BLOCKQUOTE
-        .replace_all(&result, |caps: &Captures| {
-            let content = &caps[1];
-            format!("{{{{UMD_BLOCKQUOTE:{}:UMD_BLOCKQUOTE}}}}", content)
-        })
-        .to_string();
+    if let Cow::Owned(replaced) = UMD_BLOCKQUOTE.replace_all(&result, |caps: &Captures| {
+        let content = &caps[1];
+        format!("{{{{UMD_BLOCKQUOTE:{}:UMD_BLOCKQUOTE}}}}", content)
+    }) {
+        result = replaced;
+    }
+
+    // Prefix-only lines decorate the following heading, list, blockquote or table
+    result = protect_block_targets(&result);
 
-    // Protect UMD block decorations (COLOR, SIZE, TRUNCATE, alignment, vertical alignment)
+    // Multi-line UMD blockquotes: `> ...` up to a line containing only `<`
+    result = protect_multiline_blockquotes(&result);
+
+    // Protect UMD block decorations (COLOR, SIZE, TRUNCATE, alignment, vertical alignment, LANG, direction)
     // These will be applied in post-processing.
-    let block_decoration_prefix = Regex::new(
-        r"(?m)^((?:(?:SIZE\([^)]+\)|COLOR\([^)]*\)|TRUNCATE|TOP|MIDDLE|BOTTOM|BASELINE|JUSTIFY|RIGHT|CENTER|LEFT):[ \t]*)+.*)$",
-    )
-    .unwrap();
-    result = block_decoration_prefix
-        .replace_all(&result, |caps: &Captures| {
-            use base64::{Engine as _, engine::general_purpose};
-            let encoded = general_purpose::STANDARD.encode(caps[1].as_bytes());
-            format!(
-                "{{{{BLOCK_DECORATION_B64:{}:BLOCK_DECORATION_B64}}}}",
-                encoded
-            )
-        })
-        .to_string();
+    if let Cow::Owned(replaced) = BLOCK_DECORATION_PREFIX.replace_all(&result, |caps: &Captures| {
+        use base64::{Engine as _, engine::general_purpose};
+        let encoded = general_purpose::STANDARD.encode(caps[1].as_bytes());
+        format!(
+            "{{{{BLOCK_DECORATION_B64:{}:BLOCK_DECORATION_B64}}}}",
+            encoded
+        )
+    }) {
+        result = replaced;
+    }
+
+    // `@@colwidths(...)` table directives would otherwise be taken for plugins
+    result = crate::extensions::table::umd::protect_colwidths(&result);
 
     // Protect inline and block plugin syntax
-    result = plugin_markers::protect_inline_plugins(&result);
-    result = plugin_markers::protect_block_plugins(&result);
+    if let Cow::Owned(protected) = plugin_markers::protect_inline_plugins(&result) {
+        result = protected;
+    }
+    if let Cow::Owned(protected) = plugin_markers::protect_block_plugins(&result) {
+        result = protected;
+    }
 
     // Extract and protect UMD tables (before definition lists)
     let (result, table_map) = crate::extensions::table::umd::extract_umd_tables(&result);
@@ -358,9 +686,230 @@ pub fn preprocess_conflicts(input: &str) -> (String, HeaderIdMap) {
     (result, header_map)
 }
 
+/// Shift heading levels down by `offset` and clamp them to `max_level`
+///
+/// Both the opening and closing tags are rewritten, so attributes and the
+/// anchor inside the heading are kept. Levels stay within `1..=6`.
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::conflict_resolver::adjust_heading_levels;
+///
+/// let html = adjust_heading_levels("<h1>A</h1><h5 class=\"x\">B</h5>", 1, 5);
+/// assert_eq!(html, "<h2>A</h2><h5 class=\"x\">B</h5>");
+/// ```
+pub fn adjust_heading_levels(html: &str, offset: u8, max_level: u8) -> Cow<'_, str> {
+    let max_level = max_level.clamp(1, 6);
+    if offset == 0 && max_level == 6 {
+        return Cow::Borrowed(html);
+    }
+    HEADING_TAG.replace_all(html, |caps: &Captures| {
+        let level: u8 = caps[2].parse().unwrap_or(1);
+        format!(
+            "<{}h{}",
+            &caps[1],
+            level.saturating_add(offset).min(max_level)
+        )
+    })
+}
+
+/// Prefix headings with hierarchical section numbers
+///
+/// Numbers are plain text in a `<span class="umd-heading-number">`, so they
+/// also appear in search tokens, TOC entries and copied text. The shallowest
+/// heading level of the document is numbered `1.`, deeper ones `1.1`,
+/// `1.1.1`, ...; a skipped level (h2 → h4) only adds one number.
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::conflict_resolver::number_headings;
+///
+/// let anchor = |id: &str| format!(r##"<a href="#{id}" aria-hidden="true" class="anchor" id="{id}"></a>"##);
+/// let html = format!("<h2>{}A</h2><h3>{}B</h3>", anchor("h-1"), anchor("h-2"));
+/// let output = number_headings(&html);
+/// assert!(output.contains(r#"<span class="umd-heading-number">1.</span> A</h2>"#));
+/// assert!(output.contains(r#"<span class="umd-heading-number">1.1</span> B</h3>"#));
+/// ```
+pub fn number_headings(html: &str) -> String {
+    // Levels of the enclosing headings, and the count at each depth
+    let mut levels: Vec<u8> = Vec::new();
+    let mut counters: Vec<usize> = Vec::new();
+
+    HEADING_START
+        .replace_all(html, |caps: &Captures| {
+            let level: u8 = caps[1].parse().unwrap_or(1);
+            while levels.last().is_some_and(|last| *last > level) {
+                levels.pop();
+                counters.pop();
+            }
+            match levels.last() {
+                Some(last) if *last == level => {}
+                _ => {
+                    levels.push(level);
+                    counters.push(0);
+                }
+            }
+            if let Some(count) = counters.last_mut() {
+                *count += 1;
+            }
+
+            let number = if counters.len() == 1 {
+                format!("{}.", counters[0])
+            } else {
+                counters
+                    .iter()
+                    .map(usize::to_string)
+                    .collect::<Vec<_>>()
+                    .join(".")
+            };
+            format!(
+                "{}<span class=\"umd-heading-number\">{}</span> ",
+                &caps[0], number
+            )
+        })
+        .to_string()
+}
+
+/// Replace prefix-only decoration lines that precede a heading, list,
+/// blockquote or table with a marker paragraph
+///
+/// The decoration is applied to the block's opening tag in post-processing.
+/// Placement-only prefixes before a table (`CENTER:\n| ... |`) are left to
+/// `apply_block_placement`, which positions the table itself.
+fn protect_block_targets(input: &str) -> String {
+    use base64::{Engine as _, engine::general_purpose};
+
+    let lines: Vec<&str> = input.lines().collect();
+    let mut output = Vec::with_capacity(lines.len());
+    let mut fence: Option<(char, usize)> = None;
+
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        if let Some(ch) = trimmed.chars().next().filter(|c| *c == '`' || *c == '~') {
+            let run = trimmed.chars().take_while(|c| *c == ch).count();
+            if run >= 3 {
+                match fence {
+                    None => fence = Some((ch, run)),
+                    Some((open_ch, open_len))
+                        if open_ch == ch && run >= open_len && trimmed[run..].trim().is_empty() =>
+                    {
+                        fence = None
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let starts_block = i == 0 || lines[i - 1].trim().is_empty();
+        let next = lines.get(i + 1).copied().unwrap_or("");
+        let placement_table = next.trim_start().starts_with('|')
+            && matches!(line.trim(), "LEFT:" | "CENTER:" | "RIGHT:" | "JUSTIFY:");
+        if fence.is_none()
+            && starts_block
+            && PREFIX_ONLY_LINE.is_match(line.trim_end())
+            && DECORATABLE_BLOCK_START.is_match(next)
+            && !placement_table
+        {
+            let encoded = general_purpose::STANDARD.encode(line.trim().as_bytes());
+            output.push(format!(
+                "{{{{BLOCK_TARGET_B64:{}:BLOCK_TARGET_B64}}}}\n",
+                encoded
+            ));
+        } else {
+            output.push(line.to_string());
+        }
+    }
+
+    let mut result = output.join("\n");
+    if input.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+/// Wrap the content of multi-line UMD blockquotes in marker paragraphs
+///
+/// A block starting with a `>` line and ending with a line containing only
+/// `<` becomes a UMD blockquote; its content (including the text after the
+/// opening `>`) is parsed as Markdown. The multi-line form does not nest.
+fn protect_multiline_blockquotes(input: &str) -> String {
+    if !input.contains('>') || !input.contains('<') {
+        return input.to_string();
+    }
+
+    let lines: Vec<&str> = input.split('\n').collect();
+    let mut output: Vec<&str> = Vec::with_capacity(lines.len());
+    let mut i = 0;
+    while i < lines.len() {
+        let starts_block = i == 0 || lines[i - 1].trim().is_empty();
+        let close = lines[i + 1..]
+            .iter()
+            .position(|line| line.trim() == "<")
+            .map(|offset| i + 1 + offset);
+        if let (true, Some(caps), Some(close)) =
+            (starts_block, UMD_BLOCKQUOTE_OPEN.captures(lines[i]), close)
+        {
+            output.extend(["", UMD_BLOCKQUOTE_START, ""]);
+            if let Some(first) = caps.get(1).filter(|m| !m.as_str().is_empty()) {
+                output.push(first.as_str());
+            }
+            output.extend(&lines[i + 1..close]);
+            output.extend(["", UMD_BLOCKQUOTE_END, ""]);
+            i = close + 1;
+        } else {
+            output.push(lines[i]);
+            i += 1;
+        }
+    }
+    output.join("\n")
+}
+
+/// Apply decoration markers to their target blocks
+fn apply_block_targets(html: &str) -> Cow<'_, str> {
+    use crate::extensions::block_decorations;
+    use base64::{Engine as _, engine::general_purpose};
+
+    let decode = |encoded: &str| {
+        general_purpose::STANDARD
+            .decode(encoded.as_bytes())
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .unwrap_or_default()
+    };
+
+    let result = BLOCK_TARGET.replace_all(html, |caps: &Captures| {
+        let prefixes = decode(&caps[1]);
+        match caps.get(2) {
+            // UMD blockquotes are rendered inside a paragraph: unwrap them
+            Some(tag) => format!(
+                "{}{}",
+                block_decorations::decorate_block_tag(&prefixes, tag.as_str()),
+                &caps[3]
+            ),
+            None => block_decorations::decorate_block_tag(&prefixes, &caps[4]),
+        }
+    });
+    // Restore the prefixes as text if the block was not rendered as expected
+    if !ORPHAN_BLOCK_TARGET.is_match(&result) {
+        return result;
+    }
+    ORPHAN_BLOCK_TARGET
+        .replace_all(&result, |caps: &Captures| {
+            text_escape(&decode(&caps[1])).into_owned()
+        })
+        .into_owned()
+        .into()
+}
+
 /// Convert inline decoration function to HTML
 /// Returns None if not a decoration function
 fn convert_inline_decoration_to_html(function: &str, args: &str, content: &str) -> Option<String> {
+    // Content comes from the base64-encoded plugin marker and bypassed the sanitizer
+    let raw_content = content;
+    let escaped = text_escape(content);
+    let content = escaped.as_ref();
     match function {
         // Simple wrapper tags without content
         "dfn" => Some(format!("<dfn>{}</dfn>", content)),
@@ -378,36 +927,65 @@ fn convert_inline_decoration_to_html(function: &str, args: &str, content: &str)
             // &ruby(reading){text}; → <ruby>text<rp>(</rp><rt>reading</rt><rp>)</rp></ruby>
             Some(format!(
                 "<ruby>{}<rp>(</rp><rt>{}</rt><rp>)</rp></ruby>",
-                content, args
+                content,
+                text_escape(args)
             ))
         }
         "time" => {
             // &time(datetime){text}; → <time datetime="datetime">text</time>
-            Some(format!("<time datetime=\"{}\">{}</time>", args, content))
+            Some(format!(
+                "<time datetime=\"{}\">{}</time>",
+                attr_escape(args),
+                content
+            ))
         }
         "data" => {
             // &data(value){text}; → <data value="value">text</data>
-            Some(format!("<data value=\"{}\">{}</data>", args, content))
+            Some(format!(
+                "<data value=\"{}\">{}</data>",
+                attr_escape(args),
+                content
+            ))
         }
         "bdo" => {
             // &bdo(dir){text}; → <bdo dir="dir">text</bdo>
-            Some(format!("<bdo dir=\"{}\">{}</bdo>", args, content))
+            // `bdo` requires an explicit direction; anything else is isolated as <bdi>
+            match crate::sanitizer::sanitize_dir(args) {
+                Some(dir @ ("ltr" | "rtl")) => {
+                    Some(format!("<bdo dir=\"{}\">{}</bdo>", dir, content))
+                }
+                _ => Some(format!("<bdi>{}</bdi>", content)),
+            }
         }
         "lang" => {
             // &lang(locale){text}; → <span lang="locale">text</span>
-            Some(format!("<span lang=\"{}\">{}</span>", args, content))
+            Some(format!(
+                "<span lang=\"{}\">{}</span>",
+                attr_escape(args.trim()),
+                content
+            ))
         }
         "abbr" => {
             // &abbr(text){description}; → <abbr title="description">text</abbr>
-            Some(format!("<abbr title=\"{}\">{}</abbr>", content, args))
+            Some(format!(
+                "<abbr title=\"{}\">{}</abbr>",
+                attr_escape(raw_content),
+                text_escape(args)
+            ))
         }
         "sup" => {
             // &sup(text); → <sup>text</sup>
-            Some(format!("<sup>{}</sup>", args))
+            Some(format!("<sup>{}</sup>", text_escape(args)))
         }
         "sub" => {
             // &sub(text); → <sub>text</sub>
-            Some(format!("<sub>{}</sub>", args))
+            Some(format!("<sub>{}</sub>", text_escape(args)))
+        }
+        "ref" => {
+            // &ref(id){text}; → <a href="#h-id" class="umd-ref">text</a>
+            let id = args.trim();
+            cross_reference::is_valid_ref_id(id)
+                .then(|| cross_reference::render_ref_link(id, content))
         }
         "badge" => {
             // &badge(type){content}; → <span class="badge bg-type">content</span>
@@ -418,15 +996,17 @@ fn convert_inline_decoration_to_html(function: &str, args: &str, content: &str)
             } else {
                 format!("badge bg-{}", args)
             };
+            let badge_class = attr_escape(&badge_class);
 
             // Check if content contains a Markdown link: [text](url)
-            let link_regex = Regex::new(r"\[([^\]]+)\]\(([^)]+)\)").unwrap();
-            if let Some(link_caps) = link_regex.captures(content) {
+            if let Some(link_caps) = MARKDOWN_LINK.captures(raw_content) {
                 let text = link_caps.get(1).map_or("", |m| m.as_str());
                 let url = link_caps.get(2).map_or("", |m| m.as_str());
                 Some(format!(
                     "<a href=\"{}\" class=\"{}\">{}</a>",
-                    url, badge_class, text
+                    url_attr(url),
+                    badge_class,
+                    text_escape(text)
                 ))
             } else {
                 Some(format!(
@@ -436,10 +1016,11 @@ fn convert_inline_decoration_to_html(function: &str, args: &str, content: &str)
             }
         }
         "color" => {
-            // &color(fg,bg){text}; with Bootstrap support
-            let parts: Vec<&str> = args.split(',').collect();
-            let fg = parts.get(0).map_or("", |m| m.trim());
-            let bg = parts.get(1).map_or("", |m| m.trim());
+            // &color(fg,bg,opacity){text}; with Bootstrap support
+            let parts = split_args(args);
+            let fg = parts[0];
+            let bg = parts.get(1).copied().unwrap_or("");
+            let opacity = parts.get(2).copied().unwrap_or("");
 
             let mut classes = Vec::new();
             let mut styles = Vec::new();
@@ -464,29 +1045,35 @@ fn convert_inline_decoration_to_html(function: &str, args: &str, content: &str)
                 }
             }
 
+            classes.extend(opacity_class(opacity, &classes));
+
             if classes.is_empty() && styles.is_empty() {
                 Some(content.to_string())
             } else {
                 let mut attrs = Vec::new();
                 if !classes.is_empty() {
-                    attrs.push(format!("class=\"{}\"", classes.join(" ")));
+                    attrs.push(format!("class=\"{}\"", attr_escape(&classes.join(" "))));
                 }
                 if !styles.is_empty() {
-                    attrs.push(format!("style=\"{}\"", styles.join("; ")));
+                    attrs.push(format!("style=\"{}\"", attr_escape(&styles.join("; "))));
                 }
                 Some(format!("<span {}>{}</span>", attrs.join(" "), content))
             }
         }
         "size" => {
             // &size(value){text}; with Bootstrap support
-            let (is_class, value) = map_font_size_value(args);
-            if is_class {
-                Some(format!("<span class=\"{}\">{}</span>", value, content))
-            } else {
-                Some(format!(
+            match map_font_size(args) {
+                Some(FontSize::Class(class)) => Some(format!(
+                    "<span class=\"{}\">{}</span>",
+                    attr_escape(&class),
+                    content
+                )),
+                Some(FontSize::Style(size)) => Some(format!(
                     "<span style=\"font-size: {}\">{}</span>",
-                    value, content
-                ))
+                    attr_escape(&size),
+                    content
+                )),
+                None => Some(content.to_string()),
             }
         }
         _ => None,
@@ -496,9 +1083,14 @@ fn convert_inline_decoration_to_html(function: &str, args: &str, content: &str)
 /// Convert args-only inline decoration function to HTML
 fn convert_inline_decoration_argsonly_to_html(function: &str, args: &str) -> Option<String> {
     match function {
-        "sup" => Some(format!("<sup>{}</sup>", args)),
-        "sub" => Some(format!("<sub>{}</sub>", args)),
+        "sup" => Some(format!("<sup>{}</sup>", text_escape(args))),
+        "sub" => Some(format!("<sub>{}</sub>", text_escape(args))),
         "math" => render_math_html(args, false),
+        "ref" => {
+            // &ref(id); → link text is resolved from the target heading
+            let id = args.trim();
+            cross_reference::is_valid_ref_id(id).then(|| cross_reference::render_ref_link(id, ""))
+        }
         _ => None,
     }
 }
@@ -535,112 +1127,262 @@ fn render_math_html(formula: &str, block_display: bool) -> Option<String> {
         Some(mathml) => Some(mathml),
         None => Some(format!(
             "<span class=\"umd-math-error\" data-math-source=\"{}\">{}</span>",
-            escape_html_text(formula),
-            escape_html_text(formula)
+            attr_escape(formula),
+            text_escape(formula)
         )),
     }
 }
 
-fn render_popover_html(trigger_text: &str, raw_content: &str) -> String {
-    let popover_id = format!("umd-popover-{}", uuid::Uuid::new_v4().simple());
-    let content_html = crate::parse(raw_content);
+/// Parse the Markdown content of a plugin (popover, verse) with the ID
+/// strategy and UI strings of the document; footnotes are kept inside
+fn parse_nested_content(raw_content: &str, ids: &IdGenerator, locale: &Locale) -> String {
+    let options = ParserOptions {
+        ids: ids.strategy(),
+        locale: locale.clone(),
+        ..ParserOptions::default()
+    };
+    let content = crate::parse_with_frontmatter_opts(raw_content, &options);
+    match content.footnotes {
+        Some(footnotes) => format!("{}\n{}", content.html, footnotes),
+        None => content.html,
+    }
+}
+
+fn render_popover_html(
+    trigger_text: &str,
+    raw_content: &str,
+    ids: &mut IdGenerator,
+    locale: &Locale,
+) -> String {
+    let popover_id = format!(
+        "umd-popover-{}",
+        ids.token(&format!("{}\n{}", trigger_text, raw_content), 32)
+    );
+    let content_html = parse_nested_content(raw_content, ids, locale);
     format!(
         "<button command=\"show-popover\" commandfor=\"{}\">{}</button><div id=\"{}\" popover>{}</div>",
         popover_id,
-        escape_html_text(trigger_text.trim()),
+        text_escape(trigger_text.trim()),
         popover_id,
         content_html
     )
 }
 
-fn is_valid_link_attr_token(token: &str) -> bool {
-    !token.is_empty()
-        && token
-            .chars()
-            .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_')
-}
-
-fn parse_link_attribute_spec(spec: &str) -> (Option<String>, Vec<String>) {
-    let mut id = None;
-    let mut classes = Vec::new();
-
-    for raw_token in spec.split_whitespace() {
-        let token = raw_token.trim();
-        if token.is_empty() {
-            continue;
-        }
-
-        if let Some(stripped) = token.strip_prefix('#') {
-            if is_valid_link_attr_token(stripped) {
-                id = Some(stripped.to_string());
+/// Render `@verse{{ ... }}` as `<p class="verse">` paragraphs
+///
+/// Line breaks become `<br />` and leading spaces `&nbsp;`; blank lines
+/// separate stanzas. Inline syntax is parsed, block syntax is not.
+fn render_verse_html(raw_content: &str, ids: &IdGenerator, locale: &Locale) -> String {
+    let lines: Vec<&str> = raw_content.lines().map(str::trim_end).collect();
+    let start = lines.iter().position(|line| !line.is_empty());
+    let end = lines.iter().rposition(|line| !line.is_empty());
+    let (Some(start), Some(end)) = (start, end) else {
+        return String::new();
+    };
+    let lines = &lines[start..=end];
+
+    let mut source = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        if !line.is_empty() {
+            let text = line.trim_start();
+            let indent: usize = line[..line.len() - text.len()]
+                .chars()
+                .map(|ch| if ch == '\t' { 4 } else { 1 })
+                .sum();
+            source.push_str(&"&nbsp;".repeat(indent));
+            // Keep list, heading and quote markers as text
+            let digits = text.bytes().take_while(u8::is_ascii_digit).count();
+            match text.as_bytes().get(digits) {
+                Some(b'.' | b')') if digits > 0 => {
+                    source.push_str(&text[..digits]);
+                    source.push('\\');
+                    source.push_str(&text[digits..]);
+                }
+                Some(&ch) if digits == 0 && is_block_marker(text, ch) => {
+                    source.push('\\');
+                    source.push_str(text);
+                }
+                _ => source.push_str(text),
             }
-            continue;
-        }
-
-        if let Some(stripped) = token.strip_prefix('.') {
-            if is_valid_link_attr_token(stripped) {
-                classes.push(stripped.to_string());
+            // Hard line break unless the stanza ends here
+            if lines.get(i + 1).is_some_and(|next| !next.is_empty()) {
+                source.push('\\');
             }
-            continue;
         }
+        source.push('\n');
+    }
 
-        if id.is_none() {
-            if is_valid_link_attr_token(token) {
-                id = Some(token.to_string());
-            }
-        } else if is_valid_link_attr_token(token) {
-            classes.push(token.to_string());
+    parse_nested_content(&source, ids, locale).replace("<p>", "<p class=\"verse\">")
+}
+
+/// Render `@gallery(cols){{ ![a](1.jpg) ... }}` as a Bootstrap grid
+///
+/// Each image links to itself; the links share a `data-gallery` group for
+/// lightbox scripts. `cols` (1-6, default 3) is the column count from the
+/// `md` breakpoint up.
+fn render_gallery_html(args: &str, raw_content: &str, ids: &mut IdGenerator) -> String {
+    let cols = args
+        .trim()
+        .parse::<u8>()
+        .ok()
+        .filter(|cols| (1..=6).contains(cols))
+        .unwrap_or(3);
+    let group = format!("gallery-{}", ids.token(raw_content, 8));
+
+    let items: String = GALLERY_IMAGE
+        .captures_iter(raw_content)
+        .map(|caps| {
+            let url = url_attr(&caps[2]);
+            let alt = attr_escape(&caps[1]);
+            let title = caps
+                .get(3)
+                .map(|title| format!(" title=\"{}\"", attr_escape(title.as_str())))
+                .unwrap_or_default();
+            format!(
+                "<div class=\"col\"><a href=\"{}\" data-gallery=\"{}\"{}><img src=\"{}\" alt=\"{}\" loading=\"lazy\" class=\"img-fluid\" /></a></div>",
+                url, group, title, url, alt
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        "<div class=\"umd-gallery row row-cols-1 row-cols-md-{} g-2\" id=\"{}\">\n{}\n</div>",
+        cols, group, items
+    )
+}
+
+/// Whether a line starting with `first` would open a block (heading, quote,
+/// table, fence, definition list, list item or thematic break)
+fn is_block_marker(line: &str, first: u8) -> bool {
+    match first {
+        b'#' | b'>' | b'|' | b':' | b'`' | b'~' | b'=' => true,
+        b'-' | b'+' | b'*' | b'_' => {
+            line.as_bytes().get(1).is_none_or(u8::is_ascii_whitespace)
+                || line.bytes().all(|b| b == first || b == b' ')
         }
+        _ => false,
     }
-
-    (id, classes)
 }
 
-fn apply_custom_link_attributes(html: &str) -> String {
-    let link_pattern =
-        Regex::new(r#"(?s)<a\s+([^>]*\bhref=\"[^\"]+\"[^>]*)>(.*?)</a>\s*\{([^}]+)\}"#).unwrap();
-    let class_pattern = Regex::new(r#"class=\"([^\"]*)\""#).unwrap();
-    let id_pattern = Regex::new(r#"\bid=\"[^\"]*\""#).unwrap();
+fn apply_custom_link_attributes(html: &str) -> Cow<'_, str> {
+    LINK_WITH_ATTRIBUTE_SPEC.replace_all(html, |caps: &Captures| {
+        let spec = &caps[3];
+        let mut attrs = caps[1].to_string();
+        attributes::parse_attributes(spec)
+            .unwrap_or_else(|| attributes::parse_link_attributes(spec))
+            .merge_into(&mut attrs);
+        format!("<a {}>{}</a>", attrs, &caps[2])
+    })
+}
 
-    link_pattern
-        .replace_all(html, |caps: &Captures| {
-            let mut attrs = caps[1].to_string();
-            let content = &caps[2];
-            let spec = &caps[3];
+/// Apply attribute blocks (`{#id .class key=value}`, see [`attributes`]):
+///
+/// - at the end of a paragraph or of a list item's first line, on the
+///   `<p>` / `<li>` (a block on a later line of a list item stays text);
+/// - as a paragraph of its own directly before a list, blockquote, code
+///   block or table, on that block.
+///
+/// Blocks that do not parse are left as text.
+fn apply_attribute_blocks(html: &str) -> Cow<'_, str> {
+    if !html.contains('{') {
+        return Cow::Borrowed(html);
+    }
 
-            let (id, classes) = parse_link_attribute_spec(spec);
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    let mut changed = false;
 
-            if let Some(id_value) = id {
-                if !id_pattern.is_match(&attrs) {
-                    attrs.push_str(&format!(" id=\"{}\"", id_value));
-                }
+    while let Some(caps) = ATTRIBUTE_TARGET.captures(rest) {
+        let tag = caps.get(0).unwrap();
+        let body = &rest[tag.end()..];
+        // Loose list items start with a paragraph; the block goes on the `<li>`
+        let lead = if &caps[1] == "li" && body.starts_with("\n<p>") {
+            4
+        } else {
+            0
+        };
+        let stops: &[&str] = if &caps[1] == "p" || lead > 0 {
+            &["</p>"]
+        } else {
+            &[
+                "</li>",
+                "<li",
+                "<ul",
+                "<ol",
+                "<p",
+                "<pre",
+                "<table",
+                "<blockquote",
+                "<div",
+                "<figure",
+            ]
+        };
+        let line = &body[lead..];
+        // First stop tag; a `find` per stop would scan to the end of the
+        // document for every item
+        let line = &line[..line
+            .match_indices('<')
+            .map(|(pos, _)| pos)
+            .find(|&pos| stops.iter().any(|stop| line[pos..].starts_with(stop)))
+            .unwrap_or(line.len())];
+
+        // A block on a line of its own in a list item is a lazy continuation
+        // (`- b\n{.list-group}` after a list), not the item's attributes
+        let own_line = &caps[1] == "li"
+            && line
+                .trim_end()
+                .rsplit_once('\n')
+                .is_some_and(|(_, last)| last.trim_start().starts_with('{'));
+
+        output.push_str(&rest[..tag.start()]);
+        match attributes::split_trailing_attributes(line).filter(|_| !own_line) {
+            Some((text, block)) => {
+                let mut attrs = caps.get(2).map_or("", |m| m.as_str()).to_string();
+                block.merge_into(&mut attrs);
+                output.push_str(&format!("<{}{}>", &caps[1], attrs));
+                output.push_str(&body[..lead]);
+                output.push_str(text);
+                output.push_str(&line[line.trim_end().len()..]);
+                rest = &body[lead + line.len()..];
+                changed = true;
             }
-
-            if !classes.is_empty() {
-                if let Some(class_caps) = class_pattern.captures(&attrs) {
-                    let existing = class_caps.get(1).map_or("", |m| m.as_str());
-                    let mut class_list: Vec<String> =
-                        existing.split_whitespace().map(|s| s.to_string()).collect();
-                    for class_name in classes {
-                        if !class_list.iter().any(|c| c == &class_name) {
-                            class_list.push(class_name);
-                        }
-                    }
-                    let merged = class_list.join(" ");
-                    attrs = class_pattern
-                        .replace(&attrs, format!("class=\"{}\"", merged))
-                        .to_string();
-                } else {
-                    attrs.push_str(&format!(" class=\"{}\"", classes.join(" ")));
-                }
+            None if own_line => {
+                // Also keep the loose item's paragraph from taking the block
+                output.push_str(tag.as_str());
+                output.push_str(&body[..lead + line.len()]);
+                rest = &body[lead + line.len()..];
+            }
+            None => {
+                output.push_str(tag.as_str());
+                rest = body;
             }
+        }
+    }
 
-            format!("<a {}>{}</a>", attrs, content)
-        })
-        .to_string()
+    let result = if changed {
+        output.push_str(rest);
+        Cow::Owned(output)
+    } else {
+        Cow::Borrowed(html)
+    };
+
+    match BLOCK_ATTRIBUTE_LINE.replace_all(&result, |caps: &Captures| {
+        match attributes::parse_attributes(&caps[1]) {
+            Some(block) => {
+                let mut attrs = caps[3].to_string();
+                block.merge_into(&mut attrs);
+                format!("<{}{}>", &caps[2], attrs)
+            }
+            None => caps[0].to_string(),
+        }
+    }) {
+        Cow::Owned(replaced) => Cow::Owned(replaced),
+        Cow::Borrowed(_) => result,
+    }
 }
 
-fn extract_http_host(href: &str) -> Option<&str> {
+pub(crate) fn extract_http_host(href: &str) -> Option<&str> {
     let rest = if let Some(stripped) = href.strip_prefix("http://") {
         stripped
     } else if let Some(stripped) = href.strip_prefix("https://") {
@@ -691,13 +1433,8 @@ fn should_warn_idn_link(href: &str) -> bool {
     extract_http_host(href).is_some_and(is_idn_host)
 }
 
-fn apply_idn_link_warnings(html: &str) -> String {
-    let link_pattern =
-        Regex::new(r#"(?s)<a\s+([^>]*\bhref=(?:\"([^\"]+)\"|'([^']+)')[^>]*)>(.*?)</a>"#).unwrap();
-    let class_double_pattern = Regex::new(r#"class=\"([^\"]*)\""#).unwrap();
-    let class_single_pattern = Regex::new(r"class='([^']*)'").unwrap();
-
-    link_pattern
+fn apply_idn_link_warnings<'a>(html: &'a str, locale: &Locale) -> Cow<'a, str> {
+    LINK_ELEMENT
         .replace_all(html, |caps: &Captures| {
             let mut attrs = caps[1].to_string();
             let href = caps.get(2).or_else(|| caps.get(3)).map_or("", |m| m.as_str());
@@ -707,7 +1444,7 @@ fn apply_idn_link_warnings(html: &str) -> String {
                 return caps[0].to_string();
             }
 
-            if let Some(class_caps) = class_double_pattern.captures(&attrs) {
+            if let Some(class_caps) = CLASS_ATTRIBUTE.captures(&attrs) {
                 let existing = class_caps.get(1).map_or("", |m| m.as_str());
                 let mut class_list: Vec<String> =
                     existing.split_whitespace().map(|s| s.to_string()).collect();
@@ -715,10 +1452,10 @@ fn apply_idn_link_warnings(html: &str) -> String {
                     class_list.push("umd-idn-warning-link".to_string());
                 }
                 let merged = class_list.join(" ");
-                attrs = class_double_pattern
+                attrs = CLASS_ATTRIBUTE
                     .replace(&attrs, format!("class=\"{}\"", merged))
                     .to_string();
-            } else if let Some(class_caps) = class_single_pattern.captures(&attrs) {
+            } else if let Some(class_caps) = CLASS_ATTRIBUTE_SINGLE_QUOTED.captures(&attrs) {
                 let existing = class_caps.get(1).map_or("", |m| m.as_str());
                 let mut class_list: Vec<String> =
                     existing.split_whitespace().map(|s| s.to_string()).collect();
@@ -726,7 +1463,7 @@ fn apply_idn_link_warnings(html: &str) -> String {
                     class_list.push("umd-idn-warning-link".to_string());
                 }
                 let merged = class_list.join(" ");
-                attrs = class_single_pattern
+                attrs = CLASS_ATTRIBUTE_SINGLE_QUOTED
                     .replace(&attrs, format!("class='{}'", merged))
                     .to_string();
             } else {
@@ -738,196 +1475,306 @@ fn apply_idn_link_warnings(html: &str) -> String {
             }
 
             if !content.contains("umd-idn-warning-icon") {
-                content.push_str(
-                    " <span class=\"umd-idn-warning-icon\" role=\"img\" aria-label=\"Internationalized domain warning\">&#9888;</span>",
-                );
+                content.push_str(&format!(
+                    " <span class=\"umd-idn-warning-icon\" role=\"img\" aria-label=\"{}\">&#9888;</span>",
+                    attr_escape(&locale.idn_warning)
+                ));
             }
 
             format!("<a {}>{}</a>", attrs, content)
         })
-        .to_string()
 }
 
 pub fn postprocess_conflicts(html: &str, header_map: &HeaderIdMap) -> String {
-    use crate::extensions::block_decorations;
+    postprocess_conflicts_with_options(html, header_map, &ParserOptions::default())
+}
+
+/// [`postprocess_conflicts`] with the popover ID strategy and the UI strings
+/// of `options`
+pub fn postprocess_conflicts_with_options(
+    html: &str,
+    header_map: &HeaderIdMap,
+    options: &ParserOptions,
+) -> String {
+    postprocess_conflicts_with_syntax(html, header_map, options, true)
+}
+
+/// [`postprocess_conflicts_with_options`], skipping the UMD-only restoration
+/// passes unless `umd_syntax` (see [`has_umd_syntax`])
+///
+/// [`has_umd_syntax`]: crate::extensions::detect::has_umd_syntax
+pub(crate) fn postprocess_conflicts_with_syntax(
+    html: &str,
+    header_map: &HeaderIdMap,
+    options: &ParserOptions,
+    umd_syntax: bool,
+) -> String {
+    let mut ids = IdGenerator::new(options.ids);
+    let locale = &options.locale;
 
     // First, unescape quotes within markers to allow proper JSON parsing
     // comrak escapes quotes in JSON within markers, so we need to restore them
     // but ONLY within marker boundaries to avoid XSS
-    let result = html.to_string();
-
-    // Helper function to unescape quotes only within markers
-    let unescape_marker_quotes = |input: &str| -> String {
-        let marker_patterns = vec![
-            (
-                r"\{\{DEFINITION_LIST:([^\}]+):DEFINITION_LIST\}\}",
-                "{{DEFINITION_LIST:",
-            ),
-            (
-                r"\{\{INLINE_PLUGIN:([^\}]+):INLINE_PLUGIN\}\}",
-                "{{INLINE_PLUGIN:",
-            ),
-            (
-                r"\{\{BLOCK_PLUGIN:([^\}]+):BLOCK_PLUGIN\}\}",
-                "{{BLOCK_PLUGIN:",
-            ),
-            (
-                r"\{\{BLOCK_PLUGIN_ARGSONLY:([^\}]+):BLOCK_PLUGIN_ARGSONLY\}\}",
-                "{{BLOCK_PLUGIN_ARGSONLY:",
-            ),
-            (
-                r"\{\{INLINE_PLUGIN_ARGSONLY:([^\}]+):INLINE_PLUGIN_ARGSONLY\}\}",
-                "{{INLINE_PLUGIN_ARGSONLY:",
-            ),
-            (
-                r"\{\{INLINE_PLUGIN_NOARGS:([^\}]+):INLINE_PLUGIN_NOARGS\}\}",
-                "{{INLINE_PLUGIN_NOARGS:",
-            ),
-        ];
-
-        let mut result = input.to_string();
-        for (pattern, _marker_start) in marker_patterns {
-            let re = Regex::new(pattern).unwrap();
-            result = re
-                .replace_all(&result, |caps: &Captures| {
-                    let content = &caps[0];
-                    content.replace("&quot;", "\"")
-                })
-                .to_string();
+    let mut result = html.to_string();
+    if result.contains("&quot;") {
+        for marker in PLUGIN_MARKERS.iter() {
+            if let Cow::Owned(unescaped) =
+                marker.replace_all(&result, |caps: &Captures| caps[0].replace("&quot;", "\""))
+            {
+                result = unescaped;
+            }
         }
-        result
-    };
-
-    let mut result = unescape_marker_quotes(&result);
+    }
 
     // Add header IDs: <h1>Title</h1> -> <h1><a href="#id" id="id"></a>Title</h1>
+    // Every heading is numbered, including those with inline markup, so the
+    // counter matches the one used for custom IDs in preprocessing
     let mut heading_counter = 0;
-    let header_regex = Regex::new(r"<h([1-6])>([^<]+)</h([1-6])>").unwrap();
-    result = header_regex
-        .replace_all(&result, |caps: &Captures| {
-            heading_counter += 1;
-            let level = &caps[1];
-            let title = &caps[2];
-            let close_level = &caps[3];
+    if let Cow::Owned(replaced) = HEADING_ELEMENT.replace_all(&result, |caps: &Captures| {
+        heading_counter += 1;
+        let level = &caps[1];
+        let title = &caps[2];
+        let close_level = &caps[3];
+
+        let id = if let Some(custom_id) = header_map.ids.get(&heading_counter) {
+            // Add 'h-' prefix to custom IDs to avoid conflicts with system IDs
+            format!("h-{}", custom_id)
+        } else {
+            // Auto-numbered IDs also use 'h-' prefix for consistency
+            format!("h-{}", heading_counter)
+        };
 
-            let id = if let Some(custom_id) = header_map.ids.get(&heading_counter) {
-                // Add 'h-' prefix to custom IDs to avoid conflicts with system IDs
-                format!("h-{}", custom_id)
-            } else {
-                // Auto-numbered IDs also use 'h-' prefix for consistency
-                format!("h-{}", heading_counter)
-            };
+        let mut attrs = String::new();
+        if let Some(block) = header_map.attributes.get(&heading_counter) {
+            block.merge_into(&mut attrs);
+        }
 
-            format!(
-                "<h{}><a href=\"#{}\" aria-hidden=\"true\" class=\"anchor\" id=\"{}\"></a>{}</h{}>",
-                level, id, id, title, close_level
-            )
-        })
-        .to_string();
+        format!(
+            "<h{}{}><a href=\"#{}\" aria-hidden=\"true\" class=\"anchor\" id=\"{}\"></a>{}</h{}>",
+            level, attrs, id, id, title, close_level
+        )
+    }) {
+        result = replaced;
+    }
+
+    if let Cow::Owned(replaced) =
+        adjust_heading_levels(&result, options.heading_offset, options.max_heading_level)
+    {
+        result = replaced;
+    }
+    if options.number_headings {
+        result = number_headings(&result);
+    }
+
+    // Tables first, so the plugins and decorations in their cells are
+    // restored with the rest of the document
+    result = restore_umd_tables(result, header_map);
+    if umd_syntax {
+        result = restore_umd_syntax(result, options, &mut ids);
+    }
+
+    // Add a visual warning marker for external links that use IDN or punycode hosts.
+    if let Cow::Owned(replaced) = apply_idn_link_warnings(&result, locale) {
+        result = replaced;
+    }
+
+    // Apply indeterminate task list markers before other HTML transforms
+    if umd_syntax && let Cow::Owned(replaced) = apply_tasklist_indeterminate(&result) {
+        result = replaced;
+    }
+
+    // Apply Bootstrap default classes, GFM alerts, and table cell alignment
+    result = apply_bootstrap_enhancements(&result, options);
+
+    // Decorate headings, lists, blockquotes and tables once their tags are final
+    if umd_syntax && let Cow::Owned(replaced) = apply_block_targets(&result) {
+        result = replaced;
+    }
+
+    // Attribute blocks on paragraphs, list items and blocks: `{#id .class}`
+    if umd_syntax && let Cow::Owned(replaced) = apply_attribute_blocks(&result) {
+        result = replaced;
+    }
+
+    result
+}
+
+/// Replace the UMD table markers left by [`preprocess_conflicts`] with the
+/// rendered tables
+fn restore_umd_tables(html: String, header_map: &HeaderIdMap) -> String {
+    let mut result = html;
+    // comrak wraps markers in <p> tags and strips newlines; in list items the
+    // marker is the last line of the item's text, after which the paragraph
+    // of a loose item is closed
+    for (marker, html) in &header_map.tables {
+        let marker_text = marker.trim();
+        let comrak_marker = format!("<p>{}</p>", marker_text);
+        result = result.replace(&comrak_marker, html);
+        let trailing_marker = format!("\n{}</p>", marker_text);
+        result = result.replace(&trailing_marker, &format!("</p>\n{}", html));
+        result = result.replace(marker_text, html);
+    }
+    result
+}
+
+/// Restore the UMD syntax protected by [`preprocess_conflicts`]: blockquotes,
+/// block decorations, plugins, definition lists and custom link attributes
+fn restore_umd_syntax(html: String, options: &ParserOptions, ids: &mut IdGenerator) -> String {
+    use crate::extensions::block_decorations;
+
+    let locale = &options.locale;
+    let mut result = html;
+
+    // Restore multi-line UMD blockquotes (their content is already Markdown)
+    result = result
+        .replace(
+            &format!("<p>{}</p>", UMD_BLOCKQUOTE_START),
+            "<blockquote class=\"umd-blockquote\">",
+        )
+        .replace(&format!("<p>{}</p>", UMD_BLOCKQUOTE_END), "</blockquote>");
 
     // Restore UMD blockquotes
-    let umd_blockquote_marker = Regex::new(r"\{\{UMD_BLOCKQUOTE:(.+?):UMD_BLOCKQUOTE\}\}").unwrap();
 
-    result = umd_blockquote_marker
-        .replace_all(&result, |caps: &Captures| {
-            let content = &caps[1];
-            format!(
-                "<blockquote class=\"umd-blockquote\">{}</blockquote>",
-                content
-            )
-        })
-        .to_string();
+    if let Cow::Owned(replaced) = UMD_BLOCKQUOTE_MARKER.replace_all(&result, |caps: &Captures| {
+        let content = &caps[1];
+        format!(
+            "<blockquote class=\"umd-blockquote\">{}</blockquote>",
+            content
+        )
+    }) {
+        result = replaced;
+    }
 
     // Restore and apply block decorations
-    let block_decoration_marker =
-        Regex::new(r"\{\{BLOCK_DECORATION_B64:([A-Za-z0-9+/=]+):BLOCK_DECORATION_B64\}\}").unwrap();
+    // The decorated paragraph replaces comrak's <p> wrapper instead of nesting in it
+
+    if let Cow::Owned(replaced) = BLOCK_DECORATION_MARKER.replace_all(&result, |caps: &Captures| {
+        use base64::{Engine as _, engine::general_purpose};
+        let encoded = &caps[2];
+        let open = caps.get(1).map_or("", |m| m.as_str());
+        let close = caps.get(3).map_or("", |m| m.as_str());
+        let decoration = general_purpose::STANDARD
+            .decode(encoded.as_bytes())
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .unwrap_or_else(|| encoded.to_string());
+        // Multiline decorations (e.g., RIGHT:\n<media>) and standalone block placement
+        // prefixes are handled later by apply_block_placement.
+        let placement_only = PLACEMENT_ONLY.is_match(decoration.trim());
+
+        if decoration.contains('\n') || placement_only || open.is_empty() {
+            return format!("{}{}{}", open, decoration, close);
+        }
 
-    result = block_decoration_marker
-        .replace_all(&result, |caps: &Captures| {
-            use base64::{Engine as _, engine::general_purpose};
-            let encoded = &caps[1];
-            let decoration = general_purpose::STANDARD
-                .decode(encoded.as_bytes())
-                .ok()
-                .and_then(|bytes| String::from_utf8(bytes).ok())
-                .unwrap_or_else(|| encoded.to_string());
-            // Multiline decorations (e.g., RIGHT:\n<media>) and standalone block placement
-            // prefixes are handled later by apply_block_placement.
-            let placement_only = Regex::new(r"^(LEFT|CENTER|RIGHT|JUSTIFY):\s*$")
-                .unwrap()
-                .is_match(decoration.trim());
-
-            if decoration.contains('\n') || placement_only {
-                decoration
+        let decorated = block_decorations::apply_block_decorations(&decoration);
+        if close.is_empty() {
+            // The paragraph continues on the following lines: keep it open
+            decorated
+                .strip_suffix("</p>")
+                .unwrap_or(&decorated)
+                .to_string()
+        } else {
+            decorated.into_owned()
+        }
+    }) {
+        result = replaced;
+    }
+
+    // Restore inline plugins
+    if let Cow::Owned(replaced) = INLINE_PLUGIN_MARKER.replace_all(&result, |caps: &Captures| {
+        use base64::{Engine as _, engine::general_purpose};
+        let function = &caps[1];
+        let args = &caps[2];
+        let encoded_content = &caps[3];
+
+        // Decode base64 to get original content
+        let content = general_purpose::STANDARD
+            .decode(encoded_content.as_bytes())
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .unwrap_or_else(|| encoded_content.to_string());
+
+        if !options.is_plugin_allowed(function) {
+            let source = if args.is_empty() {
+                format!("&{}{{{}}};", function, content)
             } else {
-                block_decorations::apply_block_decorations(&decoration)
+                format!("&{}({}){{{}}};", function, args, content)
+            };
+            return render_denied_plugin(function, &source);
+        }
+
+        if function == "math" {
+            let formula = if content.trim().is_empty() {
+                args
+            } else {
+                &content
+            };
+            if let Some(mathml) = render_math_html(formula, false) {
+                return mathml;
             }
-        })
-        .to_string();
+        }
 
-    // Restore inline plugins
-    let inline_plugin_marker =
-        Regex::new(r"\{\{INLINE_PLUGIN:(\w+):([\s\S]*?):([\s\S]*?):INLINE_PLUGIN\}\}").unwrap();
-    result = inline_plugin_marker
-        .replace_all(&result, |caps: &Captures| {
+        if function == "popover" {
+            return render_popover_html(args, &content, ids, locale);
+        }
+
+        if function == "attach"
+            && let Some(template) = &options.attachment_url
+            && let Some(html) = attachments::render_attachment_link(
+                args,
+                &content,
+                template,
+                &options.icons,
+                locale,
+            )
+        {
+            return html;
+        }
+
+        // Try to convert as inline decoration function
+        if let Some(html) = convert_inline_decoration_to_html(function, args, &content) {
+            return html;
+        }
+
+        // Otherwise, convert to plugin <template>
+        render_plugin_template(function, args, &content, options.plugin_content)
+    }) {
+        result = replaced;
+    }
+
+    // Restore inline plugins (args only)
+    if let Cow::Owned(replaced) =
+        INLINE_PLUGIN_ARGSONLY_MARKER.replace_all(&result, |caps: &Captures| {
             use base64::{Engine as _, engine::general_purpose};
             let function = &caps[1];
-            let args = &caps[2];
-            let encoded_content = &caps[3];
+            let encoded_args = &caps[2];
 
-            // Decode base64 to get original content
-            let content = general_purpose::STANDARD
-                .decode(encoded_content.as_bytes())
+            // Decode base64 to get original args
+            let args = general_purpose::STANDARD
+                .decode(encoded_args.as_bytes())
                 .ok()
                 .and_then(|bytes| String::from_utf8(bytes).ok())
-                .unwrap_or_else(|| encoded_content.to_string());
-
-            if function == "math" {
-                let formula = if content.trim().is_empty() {
-                    args
-                } else {
-                    &content
-                };
-                if let Some(mathml) = render_math_html(formula, false) {
-                    return mathml;
-                }
-            }
+                .unwrap_or_else(|| encoded_args.to_string());
+            let args = args.as_str();
 
-            if function == "popover" {
-                return render_popover_html(args, &content);
+            if !options.is_plugin_allowed(function) {
+                return render_denied_plugin(function, &format!("&{}({});", function, args));
             }
 
-            // Try to convert as inline decoration function
-            if let Some(html) = convert_inline_decoration_to_html(function, args, &content) {
+            if function == "attach"
+                && let Some(template) = &options.attachment_url
+                && let Some(html) =
+                    attachments::render_attachment_link(args, "", template, &options.icons, locale)
+            {
                 return html;
             }
 
-            // Otherwise, convert to plugin <template>
-            let args_html = render_args_as_data(args);
-            let escaped_content = escape_html_text(&content);
-
-            if escaped_content.is_empty() {
-                format!(
-                    "<template class=\"umd-plugin umd-plugin-{}\">{}</template>",
-                    function, args_html
-                )
-            } else {
-                format!(
-                    "<template class=\"umd-plugin umd-plugin-{}\">{}{}</template>",
-                    function, args_html, escaped_content
-                )
+            #[cfg(feature = "qr")]
+            if function == "qr"
+                && let Some(html) = super::qr::render_qr_code(args, false, locale)
+            {
+                return html;
             }
-        })
-        .to_string();
-
-    // Restore inline plugins (args only)
-    let inline_plugin_argsonly_marker =
-        Regex::new(r"\{\{INLINE_PLUGIN_ARGSONLY:(\w+):([\s\S]*?):INLINE_PLUGIN_ARGSONLY\}\}")
-            .unwrap();
-    result = inline_plugin_argsonly_marker
-        .replace_all(&result, |caps: &Captures| {
-            let function = &caps[1];
-            let args = &caps[2];
 
             // Try to convert as inline decoration function
             if let Some(html) = convert_inline_decoration_argsonly_to_html(function, args) {
@@ -935,93 +1782,95 @@ pub fn postprocess_conflicts(html: &str, header_map: &HeaderIdMap) -> String {
             }
 
             // Otherwise, convert to plugin <template>
-            let args_html = render_args_as_data(args);
-            format!(
-                "<template class=\"umd-plugin umd-plugin-{}\">{}</template>",
-                function, args_html
-            )
+            render_plugin_template(function, args, "", options.plugin_content)
         })
-        .to_string();
+    {
+        result = replaced;
+    }
 
     // Restore inline plugins (no args)
-    let inline_plugin_noargs_marker =
-        Regex::new(r"\{\{INLINE_PLUGIN_NOARGS:(\w+):INLINE_PLUGIN_NOARGS\}\}").unwrap();
-    result = inline_plugin_noargs_marker
-        .replace_all(&result, |caps: &Captures| {
+    if let Cow::Owned(replaced) =
+        INLINE_PLUGIN_NOARGS_MARKER.replace_all(&result, |caps: &Captures| {
             let function = &caps[1];
 
+            if !options.is_plugin_allowed(function) {
+                return render_denied_plugin(function, &format!("&{};", function));
+            }
+
             // Try to convert as inline decoration function
             if let Some(html) = convert_inline_decoration_noargs_to_html(function) {
                 return html;
             }
 
             // Otherwise, convert to plugin <template>
-            format!(
-                "<template class=\"umd-plugin umd-plugin-{}\"></template>",
-                function
-            )
+            render_plugin_template(function, "", "", options.plugin_content)
         })
-        .to_string();
-
-    // Restore block plugins
-    let block_plugin_marker =
-        Regex::new(r"\{\{BLOCK_PLUGIN:(\w+):([\s\S]*?):([\s\S]*?):BLOCK_PLUGIN\}\}").unwrap();
-    result = block_plugin_marker
-        .replace_all(&result, |caps: &Captures| {
-            use base64::{Engine as _, engine::general_purpose};
-            let function = &caps[1];
-            let args = &caps[2];
-            let encoded_content = &caps[3];
+    {
+        result = replaced;
+    }
 
-            // Decode base64 to get original content
-            let content = general_purpose::STANDARD
-                .decode(encoded_content.as_bytes())
-                .ok()
-                .and_then(|bytes| String::from_utf8(bytes).ok())
-                .unwrap_or_else(|| encoded_content.to_string());
+    // Restore block plugins
+    if let Cow::Owned(replaced) = BLOCK_PLUGIN_MARKER.replace_all(&result, |caps: &Captures| {
+        use base64::{Engine as _, engine::general_purpose};
+        let function = &caps[1];
+        let args = &caps[2];
+        let encoded_content = &caps[3];
+
+        // Decode base64 to get original content
+        let content = general_purpose::STANDARD
+            .decode(encoded_content.as_bytes())
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .unwrap_or_else(|| encoded_content.to_string());
+
+        if !options.is_plugin_allowed(function) {
+            let source = if args.is_empty() || content.contains('\n') {
+                format!("@{}({}){{{{{}}}}}", function, args, content)
+            } else {
+                format!("@{}({}){{{}}}", function, args, content)
+            };
+            return render_denied_plugin(function, &source);
+        }
 
-            if function == "table" {
-                return process_table_plugin(args, &content);
-            }
+        if function == "table" {
+            return process_table_plugin(args, &content);
+        }
 
-            if function == "math" {
-                let formula = if content.trim().is_empty() {
-                    args
-                } else {
-                    &content
-                };
-                if let Some(mathml) = render_math_html(formula, true) {
-                    return mathml;
-                }
-            }
+        if function == "verse" {
+            return render_verse_html(&content, ids, locale);
+        }
 
-            if function == "popover" {
-                return render_popover_html(args, &content);
-            }
+        if function == "toc" {
+            return toc::render_toc_placeholder(args);
+        }
 
-            let args_html = render_args_as_data(args);
-            let escaped_content = escape_html_text(&content);
+        if function == "gallery" {
+            return render_gallery_html(args, &content, ids);
+        }
 
-            if escaped_content.is_empty() {
-                format!(
-                    "<template class=\"umd-plugin umd-plugin-{}\">{}</template>",
-                    function, args_html
-                )
+        if function == "math" {
+            let formula = if content.trim().is_empty() {
+                args
             } else {
-                format!(
-                    "<template class=\"umd-plugin umd-plugin-{}\">{}{}</template>",
-                    function, args_html, escaped_content
-                )
+                &content
+            };
+            if let Some(mathml) = render_math_html(formula, true) {
+                return mathml;
             }
-        })
-        .to_string();
+        }
+
+        if function == "popover" {
+            return render_popover_html(args, &content, ids, locale);
+        }
+
+        render_plugin_template(function, args, &content, options.plugin_content)
+    }) {
+        result = replaced;
+    }
 
     // Restore block plugins (args only, no content)
-    let block_plugin_argsonly_marker =
-        Regex::new(r"\{\{BLOCK_PLUGIN_ARGSONLY:(\w+):([\s\S]*?):BLOCK_PLUGIN_ARGSONLY\}\}")
-            .unwrap();
-    result = block_plugin_argsonly_marker
-        .replace_all(&result, |caps: &Captures| {
+    if let Cow::Owned(replaced) =
+        BLOCK_PLUGIN_ARGSONLY_MARKER.replace_all(&result, |caps: &Captures| {
             use base64::{Engine as _, engine::general_purpose};
             let function = &caps[1];
             let encoded_args = &caps[2];
@@ -1033,94 +1882,142 @@ pub fn postprocess_conflicts(html: &str, header_map: &HeaderIdMap) -> String {
                 .and_then(|bytes| String::from_utf8(bytes).ok())
                 .unwrap_or_else(|| encoded_args.to_string());
 
+            if !options.is_plugin_allowed(function) {
+                return render_denied_plugin(function, &format!("@{}({})", function, args));
+            }
+
+            // Mail cannot play embeds either
+            let print = options.print_mode || options.profile == Profile::EmailSafe;
+            if options.video_embeds
+                && let Some(html) = embeds::render_video_embed(function, &args, locale, print)
+            {
+                return html;
+            }
+
+            if function == "map"
+                && let Some(html) =
+                    embeds::render_map_embed(&args, &options.map_embed, locale, print)
+            {
+                return html;
+            }
+
+            #[cfg(feature = "qr")]
+            if function == "qr"
+                && let Some(html) = super::qr::render_qr_code(&args, true, locale)
+            {
+                return html;
+            }
+
             if function == "clear" && args.trim().is_empty() {
                 return "<div class=\"clearfix\"></div>".to_string();
             }
 
+            if function == "toc" {
+                return toc::render_toc_placeholder(&args);
+            }
+
             if function == "math" {
                 if let Some(mathml) = render_math_html(&args, true) {
                     return mathml;
                 }
             }
 
-            let args_html = render_args_as_data(&args);
-            format!(
-                "<template class=\"umd-plugin umd-plugin-{}\">{}</template>",
-                function, args_html
-            )
+            render_plugin_template(function, &args, "", options.plugin_content)
         })
-        .to_string();
+    {
+        result = replaced;
+    }
 
     // Remove wrapping <p> tags around template plugins
-    let wrapped_plugin =
-        Regex::new(r#"<p>\s*(<template class="umd-plugin[^"]*"[^>]*>.*?</template>)\s*</p>"#)
-            .unwrap();
-    result = wrapped_plugin.replace_all(&result, "$1").to_string();
+    if let Cow::Owned(replaced) = WRAPPED_PLUGIN.replace_all(&result, "$1") {
+        result = replaced;
+    }
 
-    // Remove wrapping <p> tags around clearfix blocks
-    let wrapped_clearfix = Regex::new(r#"<p>\s*(<div class="clearfix"></div>)\s*</p>"#).unwrap();
-    result = wrapped_clearfix.replace_all(&result, "$1").to_string();
+    // Remove wrapping <p> tags around verse blocks (rendered as paragraphs)
+    if let Cow::Owned(replaced) = WRAPPED_VERSE.replace_all(&result, "$1") {
+        result = replaced;
+    }
 
-    // Restore definition lists
-    let definition_list_marker =
-        Regex::new(r"\{\{DEFINITION_LIST:([\s\S]*?):DEFINITION_LIST\}\}").unwrap();
-    result = definition_list_marker
-        .replace_all(&result, |caps: &Captures| {
-            let items_json = &caps[1];
+    // Remove wrapping <p> tags around galleries
+    if let Cow::Owned(replaced) = WRAPPED_GALLERY.replace_all(&result, "$1") {
+        result = replaced;
+    }
 
-            // Parse JSON to get items
-            let items: Vec<(String, String)> = serde_json::from_str(items_json).unwrap_or_default();
+    // Remove wrapping <p> tags around table of contents placeholders
+    if let Cow::Owned(replaced) = WRAPPED_TOC.replace_all(&result, "$1") {
+        result = replaced;
+    }
 
-            if items.is_empty() {
-                return String::new();
-            }
+    // Remove wrapping <p> tags around clearfix blocks
+    if let Cow::Owned(replaced) = WRAPPED_CLEARFIX.replace_all(&result, "$1") {
+        result = replaced;
+    }
 
-            let mut dl_html = String::from("<dl>");
-            for (term, definition) in items {
-                dl_html.push_str(&format!("<dt>{}</dt><dd>{}</dd>", term, definition));
-            }
-            dl_html.push_str("</dl>");
-            dl_html
-        })
-        .to_string();
+    // Remove wrapping <p> tags around video and map embeds
+    if let Cow::Owned(replaced) = WRAPPED_EMBED.replace_all(&result, "$1") {
+        result = replaced;
+    }
 
-    // Remove wrapping <p> tags around definition lists
-    let wrapped_dl = Regex::new(r"<p>\s*(<dl>.*?</dl>)\s*</p>").unwrap();
-    result = wrapped_dl.replace_all(&result, "$1").to_string();
+    // Remove wrapping <p> tags around block QR codes
+    if let Cow::Owned(replaced) = WRAPPED_QR.replace_all(&result, "$1") {
+        result = replaced;
+    }
 
-    // Apply custom link attributes: [text](url){id class}
-    result = apply_custom_link_attributes(&result);
+    // Restore definition lists (terms and definitions were rendered by comrak)
+    if let Cow::Owned(replaced) = DEFINITION_LIST.replace_all(&result, |caps: &Captures| {
+        let horizontal = &caps[2] == "horizontal";
+        let (dl, dt, dd) = if horizontal {
+            (
+                "<dl class=\"row\">",
+                "<dt class=\"col-sm-3\">",
+                "<dd class=\"col-sm-9\">",
+            )
+        } else {
+            ("<dl>", "<dt>", "<dd>")
+        };
 
-    // Add a visual warning marker for external links that use IDN or punycode hosts.
-    result = apply_idn_link_warnings(&result);
+        let mut dl_html = String::from(dl);
+        for item in DEFINITION_ITEM.captures_iter(&caps[3]) {
+            dl_html.push_str(&format!(
+                "{}{}</dt>{}{}</dd>",
+                dt,
+                item[1].trim(),
+                dd,
+                item[2].trim()
+            ));
+        }
+        dl_html.push_str("</dl>");
 
-    // Apply indeterminate task list markers before other HTML transforms
-    result = apply_tasklist_indeterminate(&result);
+        // Keep a paragraph tag that does not belong to the list
+        match (caps.get(1), caps.get(4)) {
+            (Some(_), None) => format!("<p>{}", dl_html),
+            (None, Some(_)) => format!("{}</p>", dl_html),
+            _ => dl_html,
+        }
+    }) {
+        result = replaced;
+    }
 
-    // Apply Bootstrap default classes, GFM alerts, and table cell alignment
-    result = apply_bootstrap_enhancements(&result, &header_map);
+    // Apply custom link attributes: [text](url){id class}
+    if let Cow::Owned(replaced) = apply_custom_link_attributes(&result) {
+        result = replaced;
+    }
 
     result
 }
 
 /// Apply indeterminate task list state to rendered checkboxes.
-fn apply_tasklist_indeterminate(html: &str) -> String {
-    let pattern =
-        Regex::new(r#"<input([^>]*\btype=\"checkbox\"[^>]*)/?>\s*\{\{TASK_INDETERMINATE\}\}"#)
-            .unwrap();
-
-    pattern
-        .replace_all(html, |caps: &Captures| {
-            let mut attrs = caps[1].to_string();
-            if !attrs.contains("data-task=") {
-                attrs.push_str(" data-task=\"indeterminate\"");
-            }
-            if !attrs.contains("aria-checked=") {
-                attrs.push_str(" aria-checked=\"mixed\"");
-            }
-            format!("<input{} />", attrs)
-        })
-        .to_string()
+fn apply_tasklist_indeterminate(html: &str) -> Cow<'_, str> {
+    TASK_INDETERMINATE_CHECKBOX.replace_all(html, |caps: &Captures| {
+        let mut attrs = caps[1].to_string();
+        if !attrs.contains("data-task=") {
+            attrs.push_str(" data-task=\"indeterminate\"");
+        }
+        if !attrs.contains("aria-checked=") {
+            attrs.push_str(" aria-checked=\"mixed\"");
+        }
+        format!("<input{} />", attrs)
+    })
 }
 
 /// Apply Bootstrap 5 enhancements to HTML
@@ -1129,91 +2026,152 @@ fn apply_tasklist_indeterminate(html: &str) -> String {
 /// - Add default `blockquote` class to all <blockquote> elements (except UMD-style)
 /// - Convert GFM alerts ([!NOTE], etc.) to Bootstrap alert components
 /// - Add JUSTIFY support for tables (w-100 class)
-fn apply_bootstrap_enhancements(html: &str, header_map: &HeaderIdMap) -> String {
+fn apply_bootstrap_enhancements(html: &str, options: &ParserOptions) -> String {
     let mut result = html.to_string();
 
     // Add default class to tables
-    let table_pattern = Regex::new(r"<table>").unwrap();
-    result = table_pattern
-        .replace_all(&result, "<table class=\"table\">")
-        .to_string();
+    if result.contains("<table>") {
+        result = result.replace("<table>", "<table class=\"table\">");
+    }
 
     // Add default class to blockquotes (check if it doesn't already have class="umd-blockquote")
-    let blockquote_pattern = Regex::new(r#"<blockquote>"#).unwrap();
-    result = blockquote_pattern
-        .replace_all(&result, "<blockquote class=\"blockquote\">")
-        .to_string();
+    if result.contains("<blockquote>") {
+        result = result.replace("<blockquote>", "<blockquote class=\"blockquote\">");
+    }
 
     // UMD blockquotes already have class="umd-blockquote", so they remain unchanged
 
     // Handle GFM alerts: > [!NOTE] etc.
-    // These are rendered as <blockquote class="blockquote"><p>[!NOTE] ...</p></blockquote>
-    let gfm_alert_pattern = Regex::new(
-        r#"<blockquote class="blockquote">\s*<p>\[!(NOTE|TIP|IMPORTANT|WARNING|CAUTION)\]\s*(.*?)</p>\s*</blockquote>"#
-    ).unwrap();
-
-    result = gfm_alert_pattern
-        .replace_all(&result, |caps: &Captures| {
-            let alert_type = &caps[1];
-            let content = &caps[2];
-
-            let (alert_class, icon_text) = match alert_type {
-                "NOTE" => ("alert-info", "Note"),
-                "TIP" => ("alert-success", "Tip"),
-                "IMPORTANT" => ("alert-primary", "Important"),
-                "WARNING" => ("alert-warning", "Warning"),
-                "CAUTION" => ("alert-danger", "Caution"),
-                _ => ("alert-info", "Note"),
-            };
-
-            format!(
-                "<div class=\"alert {}\" role=\"alert\"><strong>{}:</strong> {}</div>",
-                alert_class, icon_text, content
-            )
-        })
-        .to_string();
+    // These are rendered as <blockquote class="blockquote"><p>[!NOTE] ...</p>...</blockquote>
+
+    if let Cow::Owned(replaced) = GFM_ALERT.replace_all(&result, |caps: &Captures| {
+        let first = caps[2].trim();
+        let rest = caps[3].trim();
+        // The lazy match would cut a nested blockquote in half
+        if rest.contains("<blockquote") {
+            return caps[0].to_string();
+        }
+        render_gfm_alert(&caps[1], first, rest, options)
+    }) {
+        result = replaced;
+    }
 
-    // Restore UMD tables
-    // comrak wraps markers in <p> tags and strips newlines
-    for (marker, html) in &header_map.tables {
-        let marker_text = marker.trim();
-        let comrak_marker = format!("<p>{}</p>", marker_text);
-        result = result.replace(&comrak_marker, html);
+    // Nested quotes get depth classes for threaded message quoting
+    if let Cow::Owned(replaced) = apply_blockquote_depth(&result) {
+        result = replaced;
     }
 
+    // Move captions into the tables next to them
+    result = crate::extensions::table::caption::apply_table_captions(&result, options.caption_side);
+
     // Process table cell vertical alignment prefixes (for GFM tables only)
-    result = process_table_cell_alignment(&result);
+    if let Cow::Owned(replaced) = process_table_cell_alignment(&result) {
+        result = replaced;
+    }
 
     result
 }
 
+/// Add `depth-N` classes to Markdown blockquotes nested N levels deep
+/// (N >= 2); UMD blockquotes count towards the depth but are not changed
+fn apply_blockquote_depth(html: &str) -> Cow<'_, str> {
+    if html.matches("<blockquote").nth(1).is_none() {
+        return Cow::Borrowed(html);
+    }
+
+    let mut depth = 0usize;
+    BLOCKQUOTE_TAG.replace_all(html, |caps: &Captures| {
+        let tag = &caps[0];
+        if tag.starts_with("</") {
+            depth = depth.saturating_sub(1);
+            return tag.to_string();
+        }
+        depth += 1;
+        if depth >= 2 && tag == r#"<blockquote class="blockquote">"# {
+            format!(r#"<blockquote class="blockquote depth-{}">"#, depth)
+        } else {
+            tag.to_string()
+        }
+    })
+}
+
+/// Render a GFM alert from its first paragraph and the following blocks
+fn render_gfm_alert(alert_type: &str, first: &str, rest: &str, options: &ParserOptions) -> String {
+    let (locale, icons) = (&options.locale, &options.icons);
+    let (alert_class, label, icon) = match alert_type {
+        "TIP" => ("alert-success", &locale.tip, &icons.alert_tip),
+        "IMPORTANT" => ("alert-primary", &locale.important, &icons.alert_important),
+        "WARNING" => ("alert-warning", &locale.warning, &icons.alert_warning),
+        "CAUTION" => ("alert-danger", &locale.caution, &icons.alert_caution),
+        _ => ("alert-info", &locale.note, &icons.alert_note),
+    };
+    let icon = if options.alerts.icons {
+        format!("{} ", icon)
+    } else {
+        String::new()
+    };
+
+    let body = if options.alerts.heading {
+        let first = if first.is_empty() {
+            String::new()
+        } else {
+            format!("<p>{}</p>", first)
+        };
+        format!(
+            "<p class=\"alert-heading\">{}<strong>{}</strong></p>{}{}",
+            icon,
+            text_escape(label),
+            first,
+            rest
+        )
+    } else if rest.is_empty() {
+        format!("{}<strong>{}:</strong> {}", icon, text_escape(label), first)
+    } else {
+        format!(
+            "<p>{}<strong>{}:</strong> {}</p>\n{}",
+            icon,
+            text_escape(label),
+            first,
+            rest
+        )
+    };
+    format!(
+        "<div class=\"alert {}\" role=\"alert\">{}</div>",
+        alert_class, body
+    )
+}
+
 /// Process table cell alignment prefixes (TOP:, MIDDLE:, BOTTOM:, BASELINE:)
 ///
 /// Detects alignment prefixes in table cells and adds Bootstrap alignment classes.
 /// Note: GFM tables are handled by comrak without extensions.
 /// UMD tables have their own cell spanning and decoration support.
-fn process_table_cell_alignment(html: &str) -> String {
-    let mut result = html.to_string();
+fn process_table_cell_alignment(html: &str) -> Cow<'_, str> {
+    if !["TOP:", "MIDDLE:", "BOTTOM:", "BASELINE:"]
+        .iter()
+        .any(|prefix| html.contains(prefix))
+    {
+        return Cow::Borrowed(html);
+    }
+    let mut result = Cow::Borrowed(html);
 
     // Process <td> tags
-    let td_pattern = Regex::new(r"<td([^>]*)>(.*?)</td>").unwrap();
-    result = td_pattern
-        .replace_all(&result, |caps: &Captures| {
-            let existing_attrs = &caps[1];
-            let content = &caps[2];
-            process_cell_content("td", existing_attrs, content)
-        })
-        .to_string();
+    if let Cow::Owned(replaced) = TABLE_DATA_CELL.replace_all(&result, |caps: &Captures| {
+        let existing_attrs = &caps[1];
+        let content = &caps[2];
+        process_cell_content("td", existing_attrs, content)
+    }) {
+        result = Cow::Owned(replaced);
+    }
 
     // Process <th> tags
-    let th_pattern = Regex::new(r"<th([^>]*)>(.*?)</th>").unwrap();
-    result = th_pattern
-        .replace_all(&result, |caps: &Captures| {
-            let existing_attrs = &caps[1];
-            let content = &caps[2];
-            process_cell_content("th", existing_attrs, content)
-        })
-        .to_string();
+    if let Cow::Owned(replaced) = TABLE_HEADER_CELL.replace_all(&result, |caps: &Captures| {
+        let existing_attrs = &caps[1];
+        let content = &caps[2];
+        process_cell_content("th", existing_attrs, content)
+    }) {
+        result = Cow::Owned(replaced);
+    }
 
     result
 }
@@ -1290,6 +2248,98 @@ pub fn detect_ambiguous_syntax(input: &str) -> Vec<String> {
     warnings
 }
 
+/// Deduplicate heading anchor IDs
+///
+/// Custom header IDs (`{#id}`) may collide with each other, with
+/// auto-numbered IDs (`{#2}` vs. the second heading's `h-2`), or with IDs
+/// generated elsewhere such as footnotes. IDs not owned by heading anchors
+/// are reserved first; the first heading using an ID keeps it and later
+/// headings receive a `-1`, `-2`, ... suffix. Each rename is reported as a
+/// `duplicate-id` warning.
+///
+/// # Arguments
+///
+/// * `html` - HTML with heading anchors already inserted
+///
+/// # Returns
+///
+/// A tuple of (HTML with unique heading IDs, diagnostics for renamed IDs)
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::conflict_resolver::deduplicate_ids;
+///
+/// let a = r##"<a href="#h-x" aria-hidden="true" class="anchor" id="h-x"></a>"##;
+/// let html = format!("<h1>{a}A</h1><h2>{a}B</h2>");
+/// let (output, diagnostics) = deduplicate_ids(&html);
+/// assert!(output.contains(r#"id="h-x-1""#));
+/// assert_eq!(diagnostics.len(), 1);
+/// ```
+pub fn deduplicate_ids(html: &str) -> (Cow<'_, str>, Vec<Diagnostic>) {
+    let without_anchors = HEADING_ANCHOR.replace_all(html, "");
+    let mut used: HashSet<String> = ELEMENT_ID
+        .captures_iter(&without_anchors)
+        .map(|caps| caps[1].to_string())
+        .collect();
+    let mut diagnostics = Vec::new();
+    // Next suffix to try per ID, so repeated duplicates don't recount from 1
+    let mut next_suffix: HashMap<String, usize> = HashMap::new();
+
+    let result = HEADING_ANCHOR.replace_all(html, |caps: &Captures| {
+        let id = &caps[2];
+        if used.insert(id.to_string()) {
+            return caps[0].to_string();
+        }
+
+        let suffix = next_suffix.entry(id.to_string()).or_insert(1);
+        let unique = loop {
+            let candidate = format!("{}-{}", id, suffix);
+            *suffix += 1;
+            if used.insert(candidate.clone()) {
+                break candidate;
+            }
+        };
+        diagnostics.push(Diagnostic::warning(
+            "duplicate-id",
+            format!("Duplicate ID `{}` was renamed to `{}`", id, unique),
+        ));
+
+        format!(
+            "<a href=\"#{}\" aria-hidden=\"true\" class=\"anchor\" id=\"{}\"></a>",
+            unique, unique
+        )
+    });
+
+    if diagnostics.is_empty() {
+        return (Cow::Borrowed(html), diagnostics);
+    }
+    (result, diagnostics)
+}
+
+/// Report plugins rejected by `allowed_plugins` / `denied_plugins`
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::conflict_resolver::denied_plugin_diagnostics;
+///
+/// let html = "<p><span class=\"umd-plugin-denied\" data-plugin=\"ruby\">&amp;ruby;</span></p>";
+/// let diagnostics = denied_plugin_diagnostics(html);
+/// assert_eq!(diagnostics[0].code, "plugin-denied");
+/// ```
+pub fn denied_plugin_diagnostics(html: &str) -> Vec<Diagnostic> {
+    DENIED_PLUGIN
+        .captures_iter(html)
+        .map(|caps| {
+            Diagnostic::warning(
+                "plugin-denied",
+                format!("Plugin `{}` is not allowed and was left as text", &caps[1]),
+            )
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1347,6 +2397,29 @@ mod tests {
         assert_eq!(header_map.ids.get(&3), None); // No custom ID for third
     }
 
+    #[test]
+    fn test_custom_header_id_counts_all_headings() {
+        let input = "# Plain\n\n```\n# not a heading {#x}\n```\n\n## Custom {#custom}";
+        let (output, header_map) = preprocess_conflicts(input);
+        assert_eq!(header_map.ids.get(&1), None);
+        assert_eq!(header_map.ids.get(&2), Some(&"custom".to_string()));
+        assert!(output.contains("# not a heading {#x}"));
+    }
+
+    #[test]
+    fn test_heading_attribute_block() {
+        let (output, header_map) =
+            preprocess_conflicts("## Title {#intro .display-6 data-level=2}\n\n# Text {x}");
+        assert_eq!(header_map.ids.get(&1), Some(&"intro".to_string()));
+        assert!(output.starts_with("## Title\n"));
+        assert!(output.contains("# Text {x}"));
+
+        let html = postprocess_conflicts("<h2>Title</h2>", &header_map);
+        assert!(html.starts_with(
+            r##"<h2 class="display-6" data-level="2"><a href="#h-intro" aria-hidden="true" class="anchor" id="h-intro"></a>Title</h2>"##
+        ), "{}", html);
+    }
+
     #[test]
     fn test_apply_custom_header_ids() {
         let mut header_map = HeaderIdMap::new();
@@ -1370,6 +2443,131 @@ mod tests {
         assert!(output.contains("id=\"h-2\""));
     }
 
+    #[test]
+    fn test_deduplicate_custom_header_ids() {
+        let mut header_map = HeaderIdMap::new();
+        header_map.ids.insert(1, "intro".to_string());
+        header_map.ids.insert(2, "intro".to_string());
+        header_map.ids.insert(3, "intro".to_string());
+        let html = postprocess_conflicts("<h1>A</h1><h2>B</h2><h2>C</h2>", &header_map);
+
+        let (output, diagnostics) = deduplicate_ids(&html);
+        assert!(
+            output.contains(r##"href="#h-intro" aria-hidden="true" class="anchor" id="h-intro""##)
+        );
+        assert!(
+            output.contains(
+                r##"href="#h-intro-1" aria-hidden="true" class="anchor" id="h-intro-1""##
+            )
+        );
+        assert!(output.contains(r#"id="h-intro-2""#));
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].code, "duplicate-id");
+    }
+
+    #[test]
+    fn test_deduplicate_reserves_non_heading_ids() {
+        let mut header_map = HeaderIdMap::new();
+        header_map.ids.insert(1, "fn-1".to_string());
+        let html = postprocess_conflicts("<h1>Notes</h1>", &header_map);
+        let html = format!(r#"{}<li id="h-fn-1">note</li>"#, html);
+
+        let (output, diagnostics) = deduplicate_ids(&html);
+        assert!(output.contains(r#"<li id="h-fn-1">"#));
+        assert!(output.contains(r#"class="anchor" id="h-fn-1-1""#));
+        assert_eq!(diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn test_formatted_headings_get_ids() {
+        let mut header_map = HeaderIdMap::new();
+        header_map.ids.insert(2, "api".to_string());
+        let html = postprocess_conflicts(
+            r#"<h1>Use <em>this</em></h1><h2><code>parse()</code> and <a href="/x">x</a></h2>"#,
+            &header_map,
+        );
+        assert!(html.contains(
+            r##"<h1><a href="#h-1" aria-hidden="true" class="anchor" id="h-1"></a>Use <em>this</em></h1>"##
+        ));
+        assert!(html.contains(
+            r##"<h2><a href="#h-api" aria-hidden="true" class="anchor" id="h-api"></a><code>parse()</code> and"##
+        ));
+    }
+
+    #[test]
+    fn test_heading_offset_and_clamp() {
+        let mut header_map = HeaderIdMap::new();
+        header_map.ids.insert(2, "deep".to_string());
+        let options = ParserOptions {
+            heading_offset: 1,
+            max_heading_level: 4,
+            ..ParserOptions::default()
+        };
+        let html = postprocess_conflicts_with_options(
+            "<h1>Title</h1><h4>Deep</h4>",
+            &header_map,
+            &options,
+        );
+        assert!(html.starts_with(
+            r##"<h2><a href="#h-1" aria-hidden="true" class="anchor" id="h-1"></a>Title</h2>"##
+        ));
+        assert!(html.contains(
+            r##"<h4><a href="#h-deep" aria-hidden="true" class="anchor" id="h-deep"></a>Deep</h4>"##
+        ));
+    }
+
+    #[test]
+    fn test_number_headings() {
+        let options = ParserOptions {
+            number_headings: true,
+            ..ParserOptions::default()
+        };
+        let html = postprocess_conflicts_with_options(
+            "<h1>A</h1><h2>B</h2><h4>C</h4><h2>D</h2><h1>E</h1>",
+            &HeaderIdMap::new(),
+            &options,
+        );
+        let numbers: Vec<&str> = html
+            .split("<span class=\"umd-heading-number\">")
+            .skip(1)
+            .map(|rest| &rest[..rest.find("</span>").unwrap()])
+            .collect();
+        assert_eq!(numbers, ["1.", "1.1", "1.1.1", "1.2", "2."]);
+        assert!(
+            html.contains(r#"id="h-2"></a><span class="umd-heading-number">1.1</span> B</h2>"#)
+        );
+    }
+
+    #[test]
+    fn test_plugin_allow_and_deny_lists() {
+        let options = ParserOptions {
+            allowed_plugins: Some(vec!["color".to_string(), "br".to_string()]),
+            denied_plugins: vec!["br".to_string()],
+            ..ParserOptions::default()
+        };
+        let (input, header_map) = preprocess_conflicts("&color(red){x}; &br; &ruby(a){b};");
+        let html = postprocess_conflicts_with_options(&input, &header_map, &options);
+        assert!(
+            html.contains("<span class=\"text-red\">x</span>"),
+            "{}",
+            html
+        );
+        assert!(html.contains(
+            "<span class=\"umd-plugin-denied\" data-plugin=\"br\">&#38;br;</span> \
+             <span class=\"umd-plugin-denied\" data-plugin=\"ruby\">&#38;ruby(a){b};</span>"
+        ));
+        assert_eq!(denied_plugin_diagnostics(&html).len(), 2);
+    }
+
+    #[test]
+    fn test_deduplicate_unique_ids_unchanged() {
+        let header_map = HeaderIdMap::new();
+        let html = postprocess_conflicts("<h1>A</h1><h2>B</h2>", &header_map);
+        let (output, diagnostics) = deduplicate_ids(&html);
+        assert_eq!(output, html);
+        assert!(diagnostics.is_empty());
+    }
+
     #[test]
     fn test_detect_triple_emphasis_conflict() {
         let input = "***Markdown*** and '''UMD'''";
@@ -1419,6 +2617,61 @@ mod tests {
         assert!(output.contains("This is a note"));
     }
 
+    #[test]
+    fn test_blockquote_depth_classes() {
+        let header_map = HeaderIdMap::new();
+        let input = "<blockquote>\n<p>a</p>\n<blockquote>\n<blockquote>\n<p>c</p>\n</blockquote>\n</blockquote>\n</blockquote>\n<blockquote>\n<p>d</p>\n</blockquote>";
+        let output = postprocess_conflicts(input, &header_map);
+        assert_eq!(
+            output,
+            "<blockquote class=\"blockquote\">\n<p>a</p>\n<blockquote class=\"blockquote depth-2\">\n<blockquote class=\"blockquote depth-3\">\n<p>c</p>\n</blockquote>\n</blockquote>\n</blockquote>\n<blockquote class=\"blockquote\">\n<p>d</p>\n</blockquote>"
+        );
+    }
+
+    #[test]
+    fn test_gfm_alert_multiple_paragraphs() {
+        let header_map = HeaderIdMap::new();
+        let input = "<blockquote class=\"blockquote\">\n<p>[!TIP]\nFirst</p>\n<ul>\n<li>item</li>\n</ul>\n<p>Last</p>\n</blockquote>";
+        let output = postprocess_conflicts(input, &header_map);
+        assert!(output.contains(
+            "<div class=\"alert alert-success\" role=\"alert\"><p><strong>Tip:</strong> First</p>\n<ul>"
+        ));
+        assert!(output.contains("<p>Last</p></div>"));
+    }
+
+    #[test]
+    fn test_gfm_alert_heading_and_icon() {
+        let header_map = HeaderIdMap::new();
+        let options = ParserOptions {
+            alerts: crate::parser::AlertOptions {
+                heading: true,
+                icons: true,
+            },
+            ..ParserOptions::default()
+        };
+        let input = r#"<blockquote class="blockquote"><p>[!CAUTION]</p><p>Body</p></blockquote>"#;
+        let output = postprocess_conflicts_with_options(input, &header_map, &options);
+        assert!(output.contains(r#"<p class="alert-heading"><svg "#));
+        assert!(output.contains(r#"class="bi bi-exclamation-octagon-fill""#));
+        assert!(output.contains("</svg> <strong>Caution</strong></p><p>Body</p></div>"));
+    }
+
+    #[test]
+    fn test_localized_alert_labels() {
+        let header_map = HeaderIdMap::new();
+        let options = ParserOptions {
+            locale: Locale::ja(),
+            ..ParserOptions::default()
+        };
+        let input = r#"<blockquote class="blockquote"><p>[!WARNING] 注意事項</p></blockquote>"#;
+        let output = postprocess_conflicts_with_options(input, &header_map, &options);
+        assert!(output.contains("<strong>警告:</strong> 注意事項"));
+
+        let input = r#"<a href="https://xn--r8jz45g.jp/">例</a>"#;
+        let output = postprocess_conflicts_with_options(input, &header_map, &options);
+        assert!(output.contains(r#"aria-label="国際化ドメイン名の警告""#));
+    }
+
     #[test]
     fn test_gfm_alert_warning() {
         let header_map = HeaderIdMap::new();
@@ -1458,6 +2711,18 @@ mod tests {
         assert!(output.contains("</dl>"));
     }
 
+    #[test]
+    fn test_definition_list_inline_formatting_and_horizontal() {
+        let html = crate::parse(
+            ":**HTML**|Markup, see [spec](/html) and `<dl>`|h\n:CSS|\"Cascading\" *style*",
+        );
+        assert!(html.starts_with(r#"<dl class="row"><dt class="col-sm-3"><strong>HTML</strong></dt><dd class="col-sm-9">Markup, see <a href="/html">spec</a> and <code>&lt;dl&gt;</code></dd>"#));
+        assert!(html.contains(
+            r#"<dt class="col-sm-3">CSS</dt><dd class="col-sm-9">&quot;Cascading&quot; <em>style</em></dd></dl>"#
+        ));
+        assert!(!html.contains("<p>"));
+    }
+
     #[test]
     fn test_table_cell_vertical_alignment() {
         let header_map = HeaderIdMap::new();
@@ -1511,6 +2776,49 @@ mod tests {
         assert!(output.contains(r#"class="existing new""#));
     }
 
+    #[test]
+    fn test_attribute_blocks() {
+        let input = "<p>{.list-group}</p>\n<ul>\n<li>One {.list-group-item}</li>\n<li>Two {#two .a .b}\n<ul>\n<li>Nested {.c}</li>\n</ul>\n</li>\n<li>Plain {not attr}</li>\n</ul>";
+        assert_eq!(
+            apply_attribute_blocks(input),
+            "<ul class=\"list-group\">\n<li class=\"list-group-item\">One</li>\n<li id=\"two\" class=\"a b\">Two\n<ul>\n<li class=\"c\">Nested</li>\n</ul>\n</li>\n<li>Plain {not attr}</li>\n</ul>"
+        );
+
+        // `- a\n- b\n{.list-group}`: the block after the list is not applied
+        let trailing = "<ul>\n<li>a</li>\n<li>b\n{.list-group}</li>\n</ul>";
+        assert_eq!(apply_attribute_blocks(trailing), trailing);
+        let trailing = "<ul>\n<li>\n<p>a</p>\n</li>\n<li>\n<p>b\n{.list-group}</p>\n</li>\n</ul>";
+        assert_eq!(apply_attribute_blocks(trailing), trailing);
+
+        let loose = "<ol class=\"text-center\">\n<li>\n<p>Item {.x}</p>\n</li>\n</ol>";
+        assert_eq!(
+            apply_attribute_blocks(loose),
+            "<ol class=\"text-center\">\n<li class=\"x\">\n<p>Item</p>\n</li>\n</ol>"
+        );
+
+        let blocks = "<p class=\"text-center\">Lead\ntext {.lead data-x=&quot;a b&quot;}</p>\n<p>{#quote .border}</p>\n<blockquote class=\"blockquote\">\n<p>Q {onclick=x}</p>\n</blockquote>";
+        assert_eq!(
+            apply_attribute_blocks(blocks),
+            "<p class=\"text-center lead\" data-x=\"a b\">Lead\ntext</p>\n<blockquote class=\"blockquote border\" id=\"quote\">\n<p>Q {onclick=x}</p>\n</blockquote>"
+        );
+    }
+
+    #[test]
+    fn test_render_plugin_template_encoded() {
+        assert_eq!(
+            render_plugin_template("x", "a, <b>", "&lt;i&gt;", PluginContent::Escaped),
+            "<template class=\"umd-plugin umd-plugin-x\"><data value=\"0\">a</data><data value=\"1\">&lt;b&gt;</data>&lt;i&gt;</template>"
+        );
+        assert_eq!(
+            render_plugin_template("x", "a, <b>", "&lt;i&gt;", PluginContent::Encoded),
+            "<template class=\"umd-plugin umd-plugin-x\" data-umd-args=\"YQ==,PGI+\" data-umd-content=\"PGk+\"></template>"
+        );
+        assert_eq!(
+            render_plugin_template("x", "", "", PluginContent::Encoded),
+            "<template class=\"umd-plugin umd-plugin-x\"></template>"
+        );
+    }
+
     #[test]
     fn test_idn_warning_for_unicode_domain() {
         let header_map = HeaderIdMap::new();
@@ -1599,26 +2907,26 @@ pub fn apply_base_url_to_links(html: &str, base_url: &str) -> String {
     let mut result = html.to_string();
 
     // Replace href="/path" with href="/base_url/path"
-    let href_double = Regex::new(r#"((?:href|src|srcset)\s*=\s*)"(/[^"]*)""#).unwrap();
-    result = href_double
-        .replace_all(&result, |caps: &Captures| {
-            let attr = &caps[1];
-            let path = &caps[2];
-            let new_url = format!("{}{}", normalized_base, path);
-            format!("{}\"{}\"", attr, new_url)
-        })
-        .to_string();
+    if let Cow::Owned(replaced) = ROOT_PATH_ATTRIBUTE.replace_all(&result, |caps: &Captures| {
+        let attr = &caps[1];
+        let path = &caps[2];
+        let new_url = format!("{}{}", normalized_base, path);
+        format!("{}\"{}\"", attr, new_url)
+    }) {
+        result = replaced;
+    }
 
     // Replace href='/path' with href='/base_url/path' (single quotes)
-    let href_single = Regex::new(r"((?:href|src|srcset)\s*=\s*)'(/[^']*)'").unwrap();
-    result = href_single
-        .replace_all(&result, |caps: &Captures| {
+    if let Cow::Owned(replaced) =
+        ROOT_PATH_ATTRIBUTE_SINGLE_QUOTED.replace_all(&result, |caps: &Captures| {
             let attr = &caps[1];
             let path = &caps[2];
             let new_url = format!("{}{}", normalized_base, path);
             format!("{}'{}'", attr, new_url)
         })
-        .to_string();
+    {
+        result = replaced;
+    }
 
     result
 }
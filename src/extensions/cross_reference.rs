@@ -0,0 +1,283 @@
+//! Cross-references to headings
+//!
+//! Supports `&ref(id);` / `&ref(id){text};` and the `[#id]` shorthand, which
+//! link to a heading by its custom ID. When no link text is given the
+//! heading's current title is used, so references stay accurate when a
+//! section is renamed. Fragment-only links (`[text](#id)`) are validated
+//! against the IDs present in the document.
+//!
+//! References are resolved after heading IDs have been assigned and
+//! deduplicated, and after inline decorations have been rendered.
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use std::collections::{HashMap, HashSet};
+
+use crate::diagnostics::Diagnostic;
+
+/// Regex to detect the `[#id]` shorthand
+///
+/// Matches inside `[[#id]]` are skipped by checking the neighbouring
+/// characters, so adjacent references do not consume each other.
+static SHORTHAND_REF: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\[#(?P<id>[A-Za-z0-9_-]+)\]").unwrap());
+
+/// Regex to detect reference links emitted for `&ref`
+static REF_LINK: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r##"<a href="#[^"]*" class="umd-ref" data-ref="([^"]+)">(.*?)</a>"##).unwrap()
+});
+
+/// Regex to collect headings with their anchor IDs
+static HEADING: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?s)<h[1-6][^>]*><a [^>]*class="anchor" id="([^"]+)"></a>(.*?)</h[1-6]>"#)
+        .unwrap()
+});
+
+/// Regex to collect `id` attributes
+static ELEMENT_ID: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\sid="([^"]+)""#).unwrap());
+
+/// Regex to detect fragment-only links (heading anchors excluded)
+static FRAGMENT_LINK: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r##"<a href="#([^"]+)"(?P<rest>[^>]*)>"##).unwrap());
+
+/// Regex to strip element tags while keeping code placeholders (`<!--...-->`)
+static ELEMENT_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"</?[a-zA-Z][^>]*>").unwrap());
+
+/// Check whether a reference ID is safe to embed in attributes
+pub fn is_valid_ref_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_')
+}
+
+/// Render the placeholder link for a reference
+///
+/// The `href` points at the custom heading ID (`h-` prefixed); the text is
+/// filled in by [`resolve_cross_references`] when empty.
+pub fn render_ref_link(id: &str, text: &str) -> String {
+    format!(
+        "<a href=\"#h-{}\" class=\"umd-ref\" data-ref=\"{}\">{}</a>",
+        id, id, text
+    )
+}
+
+/// Find the document ID a reference points to
+///
+/// Custom heading IDs are rendered with an `h-` prefix, so `intro` resolves
+/// to `h-intro`. IDs that already exist verbatim (e.g. `h-3`, `fn-1`) are
+/// accepted as-is.
+fn resolve_target(id: &str, ids: &HashSet<String>) -> Option<String> {
+    let prefixed = format!("h-{}", id);
+    if ids.contains(&prefixed) {
+        Some(prefixed)
+    } else if ids.contains(id) {
+        Some(id.to_string())
+    } else {
+        None
+    }
+}
+
+/// Resolve cross-references and validate fragment links
+///
+/// # Arguments
+///
+/// * `html` - HTML with heading anchors assigned
+///
+/// # Returns
+///
+/// A tuple of (HTML with references resolved, diagnostics for unresolved targets)
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::cross_reference::{render_ref_link, resolve_cross_references};
+///
+/// let html = format!(
+///     "<h2><a href=\"#h-setup\" aria-hidden=\"true\" class=\"anchor\" id=\"h-setup\"></a>Setup</h2><p>{}</p>",
+///     render_ref_link("setup", "")
+/// );
+/// let (output, diagnostics) = resolve_cross_references(&html);
+/// assert!(output.contains(r##"<a href="#h-setup" class="umd-ref">Setup</a>"##));
+/// assert!(diagnostics.is_empty());
+/// ```
+pub fn resolve_cross_references(html: &str) -> (String, Vec<Diagnostic>) {
+    let mut diagnostics = Vec::new();
+
+    let ids: HashSet<String> = ELEMENT_ID
+        .captures_iter(html)
+        .map(|caps| caps[1].to_string())
+        .collect();
+    let titles: HashMap<String, String> = HEADING
+        .captures_iter(html)
+        .map(|caps| {
+            let title = ELEMENT_TAG.replace_all(&caps[2], "").trim().to_string();
+            (caps[1].to_string(), title)
+        })
+        .collect();
+
+    // [#id] shorthand is equivalent to &ref(id); when it names a heading.
+    // Other bracketed text (`issue [#42]`) is left as written.
+    let result = SHORTHAND_REF.replace_all(html, |caps: &Captures| {
+        let whole = caps.get(0).unwrap();
+        let is_heading =
+            resolve_target(&caps["id"], &ids).is_some_and(|target| titles.contains_key(&target));
+        if !is_heading
+            || html[..whole.start()].ends_with('[')
+            || html[whole.end()..].starts_with(']')
+        {
+            return whole.as_str().to_string();
+        }
+        render_ref_link(&caps["id"], "")
+    });
+
+    let result = REF_LINK.replace_all(&result, |caps: &Captures| {
+        let id = &caps[1];
+        let text = &caps[2];
+
+        match resolve_target(id, &ids) {
+            Some(target) => {
+                let text = if text.trim().is_empty() {
+                    titles.get(&target).map(String::as_str).unwrap_or(id)
+                } else {
+                    text
+                };
+                format!("<a href=\"#{}\" class=\"umd-ref\">{}</a>", target, text)
+            }
+            None => {
+                diagnostics.push(Diagnostic::warning(
+                    "unresolved-ref",
+                    format!("Cross-reference target `{}` was not found", id),
+                ));
+                let text = if text.trim().is_empty() { id } else { text };
+                format!("<span class=\"umd-ref umd-ref-unresolved\">{}</span>", text)
+            }
+        }
+    });
+
+    // Validate [text](#fragment) links; `#id` also matches a custom heading `h-id`
+    let result = FRAGMENT_LINK.replace_all(&result, |caps: &Captures| {
+        let fragment = &caps[1];
+        let rest = &caps["rest"];
+        if ids.contains(fragment) || rest.contains("class=\"anchor\"") {
+            return caps[0].to_string();
+        }
+        if let Some(target) = resolve_target(fragment, &ids) {
+            return format!("<a href=\"#{}\"{}>", target, rest);
+        }
+        diagnostics.push(Diagnostic::warning(
+            "broken-anchor",
+            format!(
+                "Link target `#{}` does not exist in this document",
+                fragment
+            ),
+        ));
+        caps[0].to_string()
+    });
+
+    (result.to_string(), diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heading(id: &str, title: &str) -> String {
+        format!(
+            "<h2><a href=\"#{}\" aria-hidden=\"true\" class=\"anchor\" id=\"{}\"></a>{}</h2>",
+            id, id, title
+        )
+    }
+
+    #[test]
+    fn test_ref_uses_heading_title() {
+        let html = format!(
+            "{}<p>See {}.</p>",
+            heading("h-install", "Install <em>now</em>"),
+            render_ref_link("install", "")
+        );
+        let (output, diagnostics) = resolve_cross_references(&html);
+        assert!(output.contains(r##"See <a href="#h-install" class="umd-ref">Install now</a>."##));
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_ref_keeps_explicit_text() {
+        let html = format!(
+            "{}<p>{}</p>",
+            heading("h-install", "Install"),
+            render_ref_link("install", "here")
+        );
+        let (output, _) = resolve_cross_references(&html);
+        assert!(output.contains(r##"<a href="#h-install" class="umd-ref">here</a>"##));
+    }
+
+    #[test]
+    fn test_shorthand_ref() {
+        let html = format!("{}<p>See [#api].</p>", heading("h-api", "API"));
+        let (output, _) = resolve_cross_references(&html);
+        assert!(output.contains(r##"<a href="#h-api" class="umd-ref">API</a>"##));
+    }
+
+    #[test]
+    fn test_shorthand_without_heading_is_text() {
+        let html = format!(
+            "{}<p>Fixed in issue [#42] and [#api].</p>",
+            heading("h-api", "API")
+        );
+        let (output, diagnostics) = resolve_cross_references(&html);
+        assert!(output.contains(
+            r##"<p>Fixed in issue [#42] and <a href="#h-api" class="umd-ref">API</a>.</p>"##
+        ));
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_adjacent_shorthand_refs() {
+        let html = format!(
+            "{}{}<p>See [#a] [#b], [#a],[#b] and [#a][#b]</p>",
+            heading("h-a", "A"),
+            heading("h-b", "B")
+        );
+        let (output, _) = resolve_cross_references(&html);
+        let (a, b) = (
+            r##"<a href="#h-a" class="umd-ref">A</a>"##,
+            r##"<a href="#h-b" class="umd-ref">B</a>"##,
+        );
+        assert!(output.contains(&format!(
+            "<p>See {a} {b}, {a},{b} and {a}{b}</p>",
+            a = a,
+            b = b
+        )));
+    }
+
+    #[test]
+    fn test_shorthand_ignores_double_brackets() {
+        let html = format!("{}<p>[[#api]]</p>", heading("h-api", "API"));
+        let (output, _) = resolve_cross_references(&html);
+        assert!(output.contains("<p>[[#api]]</p>"));
+    }
+
+    #[test]
+    fn test_unresolved_ref_is_reported() {
+        let html = format!("<p>{}</p>", render_ref_link("missing", ""));
+        let (output, diagnostics) = resolve_cross_references(&html);
+        assert!(output.contains(r#"<span class="umd-ref umd-ref-unresolved">missing</span>"#));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, "unresolved-ref");
+    }
+
+    #[test]
+    fn test_fragment_link_validation() {
+        let html = format!(
+            "{}<p><a href=\"#install\">A</a> <a href=\"#h-install\">B</a> <a href=\"#nope\">C</a></p>",
+            heading("h-install", "Install")
+        );
+        let (output, diagnostics) = resolve_cross_references(&html);
+        assert!(output.contains(r##"<a href="#h-install">A</a>"##));
+        assert!(output.contains(r##"<a href="#h-install">B</a>"##));
+        assert!(output.contains(r##"<a href="#nope">C</a>"##));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, "broken-anchor");
+    }
+}
@@ -0,0 +1,179 @@
+//! Detection of UMD-specific syntax
+//!
+//! Most documents are plain Markdown. [`has_umd_syntax`] is a cheap scan
+//! run during preprocessing; when it finds nothing, the passes that only
+//! handle UMD syntax (comments, custom IDs, decorations, plugins, UMD
+//! tables, definition lists, LukiWiki emphasis) are skipped. The remaining
+//! passes — comrak, heading anchors, Bootstrap classes, media, code blocks,
+//! footnotes — produce the same HTML as the full pipeline.
+//!
+//! The scan is conservative: anything that might be UMD syntax selects the
+//! full pipeline.
+
+use super::preprocessor::split_blockquote_prefix;
+use super::table::caption;
+use super::table::umd::is_umd_table;
+
+/// Substrings that only occur in UMD syntax (or that UMD passes rewrite)
+const UMD_TOKENS: &[&str] = &[
+    // Custom IDs, link / media attributes, plugin content
+    "{",
+    "}",
+    // LukiWiki emphasis, strikethrough, spoilers, task list state
+    "''",
+    "%%",
+    "||",
+    "[-]",
+    // Table directives
+    "@@",
+    // Line breaks, also rewritten without the closing `;`
+    "&br",
+    "&wbr",
+    // Comments (`//` is checked separately)
+    "/*",
+    // Block and cell decorations, placement prefixes, table captions
+    "COLOR(",
+    "SIZE(",
+    "WIDTH(",
+    "LANG(",
+    "TRUNCATE:",
+    "NOWRAP:",
+    "TOP:",
+    "MIDDLE:",
+    "BOTTOM:",
+    "BASELINE:",
+    "JUSTIFY:",
+    "RIGHT:",
+    "CENTER:",
+    "LEFT:",
+    "RTL:",
+    "LTR:",
+    "BDI:",
+    "Table:",
+];
+
+/// Whether `input` may contain UMD-specific syntax
+///
+/// Run on the preprocessed input right before
+/// [`preprocess_conflicts`](super::conflict_resolver::preprocess_conflicts);
+/// code is masked by then, so it never selects the full pipeline. `&name(`, `&name;` and `@name(`
+/// (plugins and inline decorations, but also HTML entities), `>` ... `<`
+/// blockquotes, `:term|` definition lists, UMD tables and whitespace-only
+/// lines the comment pass would empty all count as UMD syntax.
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::detect::has_umd_syntax;
+///
+/// assert!(!has_umd_syntax("# Title\n\nSome *Markdown* with a [link](/a).\n"));
+/// assert!(has_umd_syntax("&color(red){Red};"));
+/// assert!(has_umd_syntax("| A | B |\n| C | D |\n"));
+/// ```
+pub fn has_umd_syntax(input: &str) -> bool {
+    if input.contains('\r') || UMD_TOKENS.iter().any(|token| input.contains(token)) {
+        return true;
+    }
+    if has_marker(input, '&', &['(', ';']) || has_marker(input, '@', &['(']) {
+        return true;
+    }
+    // `//` starts a comment unless it follows a URL scheme
+    if input.match_indices('/').any(|(position, _)| {
+        input[position + 1..].starts_with('/') && !input[..position].ends_with(':')
+    }) {
+        return true;
+    }
+
+    // Blockquote markers are skipped, like the line-based extractors do
+    let lines: Vec<&str> = input
+        .lines()
+        .map(|line| split_blockquote_prefix(line).1)
+        .collect();
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index];
+        let trimmed = line.trim();
+        if trimmed.starts_with('|') {
+            // A run of table lines, as grouped by `extract_umd_tables`
+            let start = index;
+            while index < lines.len() && lines[index].trim().starts_with('|') {
+                index += 1;
+            }
+            let table = &lines[start..index];
+            if is_umd_table(table) || caption::caption_row(table[0]).is_some() {
+                return true;
+            }
+            continue;
+        }
+        if (trimmed.starts_with(':') && trimmed.contains('|'))
+            || trimmed.ends_with('<')
+            || (trimmed.is_empty() && !line.is_empty())
+        {
+            return true;
+        }
+        index += 1;
+    }
+    false
+}
+
+/// Whether `sigil` starts a name followed by one of `terminators`
+/// (`&name(`, `&name;`, `@name(`)
+fn has_marker(input: &str, sigil: char, terminators: &[char]) -> bool {
+    input.match_indices(sigil).any(|(position, _)| {
+        let rest = &input[position + 1..];
+        let name_len = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        name_len > 0 && rest[name_len..].starts_with(terminators)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_markdown() {
+        let input = "# Title\n\n- [x] Done\n- [ ] Todo\n\n> Quote\n\n\
+                     | A | B |\n|---|---|\n| 1 | 2 |\n\nSee https://example.com.[^1]\n\n\
+                     [^1]: Note\n";
+        assert!(!has_umd_syntax(input));
+    }
+
+    #[test]
+    fn test_umd_syntax() {
+        for input in [
+            "# Title {#custom}",
+            "[Link](/a){.btn}",
+            "''bold''",
+            "%%strike%%",
+            "||spoiler||",
+            "- [-] Partly done",
+            "Text // comment",
+            "file:///etc/hosts",
+            "CENTER: Centered",
+            "&br;",
+            "AT&amp;T",
+            "@toc()",
+            "@@colwidths(30,70)",
+            "> Quote <",
+            ":Term|Definition",
+            "| A | B |",
+            "> | A | B |",
+            "> :Term|Definition",
+            "|c Caption|\n| A |\n|---|\n| 1 |",
+            "| A |\n|---|\n| 1 |\n\nTable: Caption",
+            "Line\r\n",
+            "    code\n      \n    more",
+        ] {
+            assert!(has_umd_syntax(input), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn test_mail_address_and_ampersand() {
+        assert!(!has_umd_syntax(
+            "Mail @someone at user@example.com, AT&T\n\n"
+        ));
+    }
+}
@@ -0,0 +1,297 @@
+//! Email-safe rendering profile
+//!
+//! With `ParserOptions.profile = Profile::EmailSafe`, the output is prepared
+//! for newsletters and other mail, where scripts never run, `<style>` blocks
+//! are often dropped and media cannot play. On top of the
+//! [print mode](super::print) changes:
+//!
+//! - plugin `<template>` elements are removed and `<details>` become plain
+//!   blocks with a bold summary
+//! - revealed spoilers are prefixed with `Locale.spoiler`
+//! - YouTube links get a linked thumbnail; other video, audio and map embeds
+//!   are links
+//! - attributes that only scripts use (`data-*`, `tabindex`,
+//!   `aria-expanded`, `aria-controls`, `popover`, `command`, `commandfor`)
+//!   and Bootstrap Icons spans are removed
+//! - essential styles (alignment, colors, font sizes, badges, alerts, table
+//!   borders, quotes, image width) are inlined as `style` attributes; the
+//!   classes are kept for clients that load a stylesheet
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use super::class_map::{CLASS_ATTR, TAG};
+use super::print::apply_print_mode;
+use crate::html_builder::text_escape;
+use crate::parser::Locale;
+
+/// Regex to detect `<details>` / `<summary>` tags
+static DETAILS_OPEN: Lazy<Regex> = Lazy::new(|| Regex::new(r"<details\b[^>]*>").unwrap());
+static SUMMARY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?s)<summary\b[^>]*>(.*?)</summary>").unwrap());
+
+/// Regex to detect paragraphs left empty by removed templates
+static EMPTY_PARAGRAPH: Lazy<Regex> = Lazy::new(|| Regex::new(r"<p>\s*</p>\n?").unwrap());
+
+/// Regex to detect a YouTube link rendered instead of the player
+static YOUTUBE_LINK: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"<a href="https://www\.youtube\.com/watch\?v=([A-Za-z0-9_-]{11})" class="umd-embed-link umd-embed-youtube">([^<]*)</a>"#,
+    )
+    .unwrap()
+});
+
+/// Regex to detect Bootstrap Icons spans
+static ICON: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"<span class="bi [^"]*" aria-hidden="true"></span> ?"#).unwrap());
+
+/// Regex to detect one attribute inside a tag
+static ATTRIBUTE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"\s+([^\s"'>/=]+)(?:\s*=\s*("[^"]*"|'[^']*'|[^\s"'=<>`]+))?"#).unwrap()
+});
+
+/// Regex to detect the start of a tag: `<name`
+static TAG_NAME: Lazy<Regex> = Lazy::new(|| Regex::new(r"^<[a-zA-Z][a-zA-Z0-9-]*").unwrap());
+
+/// Attributes only meaningful to scripts
+const SCRIPT_ATTRIBUTES: &[&str] = &[
+    "tabindex",
+    "aria-expanded",
+    "aria-controls",
+    "popover",
+    "command",
+    "commandfor",
+];
+
+/// Bootstrap 5.3 color values of the color utility classes
+const COLORS: &[(&str, &str)] = &[
+    ("primary", "#0d6efd"),
+    ("secondary", "#6c757d"),
+    ("success", "#198754"),
+    ("danger", "#dc3545"),
+    ("warning", "#ffc107"),
+    ("info", "#0dcaf0"),
+    ("light", "#f8f9fa"),
+    ("dark", "#212529"),
+    ("blue", "#0d6efd"),
+    ("indigo", "#6610f2"),
+    ("purple", "#6f42c1"),
+    ("pink", "#d63384"),
+    ("red", "#dc3545"),
+    ("orange", "#fd7e14"),
+    ("yellow", "#ffc107"),
+    ("green", "#198754"),
+    ("teal", "#20c997"),
+    ("cyan", "#0dcaf0"),
+    ("white", "#ffffff"),
+    ("black", "#000000"),
+    ("muted", "#6c757d"),
+];
+
+/// Font sizes of `fs-1` ... `fs-6`
+const FONT_SIZES: &[&str] = &["2.5rem", "2rem", "1.75rem", "1.5rem", "1.25rem", "1rem"];
+
+/// Border of table cells
+const BORDER: &str = "1px solid #dee2e6";
+
+fn color(name: &str) -> Option<&'static str> {
+    COLORS
+        .iter()
+        .find(|(color, _)| *color == name)
+        .map(|(_, value)| *value)
+}
+
+/// Inline style declarations of one class
+fn class_style(class: &str) -> Option<String> {
+    let style = match class {
+        "text-start" => "text-align: left",
+        "text-center" => "text-align: center",
+        "text-end" => "text-align: right",
+        "text-justify" => "text-align: justify",
+        "align-top" => "vertical-align: top",
+        "align-middle" => "vertical-align: middle",
+        "align-bottom" => "vertical-align: bottom",
+        "align-baseline" => "vertical-align: baseline",
+        "fw-bold" => "font-weight: bold",
+        "fst-italic" => "font-style: italic",
+        "img-fluid" => "max-width: 100%; height: auto",
+        "table" => "border-collapse: collapse",
+        "blockquote" | "umd-blockquote" => {
+            "margin: 0 0 1rem; padding-left: 1rem; border-left: 4px solid #dee2e6"
+        }
+        "alert" => {
+            "margin: 0 0 1rem; padding: 0.75rem 1rem; border: 1px solid; border-radius: 0.375rem"
+        }
+        "badge" => {
+            "display: inline-block; padding: 0.35em 0.65em; font-size: 0.75em; font-weight: bold; line-height: 1; color: #ffffff; border-radius: 0.375rem"
+        }
+        _ => {
+            if let Some(size) = class.strip_prefix("fs-") {
+                let index = size.parse::<usize>().ok()?.checked_sub(1)?;
+                return FONT_SIZES
+                    .get(index)
+                    .map(|size| format!("font-size: {}", size));
+            }
+            if let Some(name) = class.strip_prefix("text-") {
+                return color(name).map(|value| format!("color: {}", value));
+            }
+            if let Some(name) = class.strip_prefix("bg-") {
+                return color(name).map(|value| format!("background-color: {}", value));
+            }
+            if let Some(name) = class.strip_prefix("border-") {
+                return color(name).map(|value| format!("border-color: {}", value));
+            }
+            return None;
+        }
+    };
+    Some(style.to_string())
+}
+
+/// Rewrite one opening tag: drop script attributes and inline class styles
+fn email_tag(tag: &str) -> String {
+    let Some(name) = TAG_NAME.find(tag).map(|name| name.as_str()) else {
+        return tag.to_string();
+    };
+    let (body, close) = match tag.strip_suffix("/>") {
+        Some(body) => (body, " />"),
+        None => (&tag[..tag.len() - 1], ">"),
+    };
+
+    let mut styles: Vec<String> = Vec::new();
+    if let Some(classes) = CLASS_ATTR.captures(tag) {
+        styles.extend(classes[1].split_whitespace().filter_map(class_style));
+    }
+    if matches!(name, "<td" | "<th") {
+        styles.push(format!("padding: 0.25rem 0.5rem; border: {}", BORDER));
+    }
+
+    let mut rewritten = name.to_string();
+    let mut style_written = false;
+    for caps in ATTRIBUTE.captures_iter(&body[name.len()..]) {
+        let attribute = &caps[1];
+        if attribute.starts_with("data-") || SCRIPT_ATTRIBUTES.contains(&attribute) {
+            continue;
+        }
+        if attribute == "style" && !styles.is_empty() {
+            // Authored styles win over the inlined class styles
+            let authored = caps.get(2).map_or("", |value| value.as_str());
+            let authored = authored.trim_matches(['"', '\'']).trim_end_matches(';');
+            styles.push(authored.to_string());
+            rewritten.push_str(&format!(" style=\"{}\"", styles.join("; ")));
+            style_written = true;
+            continue;
+        }
+        rewritten.push_str(caps.get(0).unwrap().as_str());
+    }
+    if !style_written && !styles.is_empty() {
+        rewritten.push_str(&format!(" style=\"{}\"", styles.join("; ")));
+    }
+    rewritten.push_str(close);
+    rewritten
+}
+
+/// Remove `<template>` elements, including nested ones
+fn remove_templates(html: &str) -> String {
+    const OPEN: &str = "<template";
+    const CLOSE: &str = "</template>";
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(OPEN) {
+        result.push_str(&rest[..start]);
+        let mut depth = 0usize;
+        let mut pos = start;
+        let mut end = rest.len();
+        while let Some(offset) = rest[pos..].find('<') {
+            pos += offset;
+            if rest[pos..].starts_with(OPEN) {
+                depth += 1;
+            } else if rest[pos..].starts_with(CLOSE) {
+                depth -= 1;
+                if depth == 0 {
+                    end = pos + CLOSE.len();
+                    break;
+                }
+            }
+            pos += 1;
+        }
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Apply the email-safe profile to rendered HTML
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::email::apply_email_profile;
+/// use umd::parser::Locale;
+///
+/// let html = "<p class=\"text-center\"><span class=\"spoiler\" role=\"button\" tabindex=\"0\" aria-expanded=\"false\">x</span></p>\n<p><template class=\"umd-plugin umd-plugin-x\"></template></p>\n";
+/// assert_eq!(
+///     apply_email_profile(html, &Locale::en()),
+///     "<p class=\"text-center\" style=\"text-align: center\"><span class=\"spoiler spoiler-revealed\">(spoiler) x</span></p>\n"
+/// );
+/// ```
+pub fn apply_email_profile(html: &str, locale: &Locale) -> String {
+    let result = apply_print_mode(html);
+    let result = remove_templates(&result);
+    let result = EMPTY_PARAGRAPH.replace_all(&result, "");
+    let result = DETAILS_OPEN.replace_all(&result, "<div>");
+    let result = result.replace("</details>", "</div>");
+    let result = SUMMARY.replace_all(&result, "<p><strong>$1</strong></p>");
+    let result = result.replace(
+        "<span class=\"spoiler spoiler-revealed\">",
+        &format!(
+            "<span class=\"spoiler spoiler-revealed\">{} ",
+            text_escape(&locale.spoiler)
+        ),
+    );
+    let result = YOUTUBE_LINK.replace_all(&result, |caps: &Captures| {
+        format!(
+            "<a href=\"https://www.youtube.com/watch?v={}\" class=\"umd-embed-link umd-embed-youtube\"><img src=\"https://i.ytimg.com/vi/{}/hqdefault.jpg\" alt=\"{}\" width=\"480\" class=\"img-fluid\" /></a>",
+            &caps[1],
+            &caps[1],
+            caps[2].replace('"', "&quot;")
+        )
+    });
+    let result = ICON.replace_all(&result, "");
+    TAG.replace_all(&result, |caps: &Captures| email_tag(&caps[0]))
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_templates_and_details() {
+        let html = "<p>A <template class=\"umd-plugin\"><template>x</template></template>B</p>\n<details><summary>More</summary><p>Body</p></details>";
+        assert_eq!(
+            apply_email_profile(html, &Locale::en()),
+            "<p>A B</p>\n<div><p><strong>More</strong></p><p>Body</p></div>"
+        );
+    }
+
+    #[test]
+    fn test_inline_styles() {
+        let html = "<span class=\"badge bg-success\" data-x=\"1\">OK</span><td class=\"text-end\" style=\"color: #FF0000\">1</td>";
+        assert_eq!(
+            apply_email_profile(html, &Locale::en()),
+            "<span class=\"badge bg-success\" style=\"display: inline-block; padding: 0.35em 0.65em; font-size: 0.75em; font-weight: bold; line-height: 1; color: #ffffff; border-radius: 0.375rem; background-color: #198754\">OK</span>\
+             <td class=\"text-end\" style=\"text-align: right; padding: 0.25rem 0.5rem; border: 1px solid #dee2e6; color: #FF0000\">1</td>"
+        );
+    }
+
+    #[test]
+    fn test_media() {
+        let html = "<figure class=\"w-100\">\n<video controls>\n  <source src=\"/a.mp4\" type=\"video/mp4\" />\n  <a href=\"/a.mp4\" download class=\"download-link video-fallback\" aria-label=\"Download a\"><span class=\"bi bi-camera-video-fill\" aria-hidden=\"true\"></span> a</a>\n</video>\n</figure>\n\
+                    <a href=\"https://www.youtube.com/watch?v=dQw4w9WgXcQ\" class=\"umd-embed-link umd-embed-youtube\">Talk</a>";
+        assert_eq!(
+            apply_email_profile(html, &Locale::en()),
+            "<figure class=\"w-100\">\n<a href=\"/a.mp4\" download class=\"download-link video-fallback\" aria-label=\"Download a\">a</a>\n</figure>\n\
+             <a href=\"https://www.youtube.com/watch?v=dQw4w9WgXcQ\" class=\"umd-embed-link umd-embed-youtube\"><img src=\"https://i.ytimg.com/vi/dQw4w9WgXcQ/hqdefault.jpg\" alt=\"Talk\" width=\"480\" class=\"img-fluid\" style=\"max-width: 100%; height: auto\" /></a>"
+        );
+    }
+}
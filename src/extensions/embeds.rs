@@ -0,0 +1,278 @@
+//! Video and map embed plugins
+//!
+//! With `ParserOptions.video_embeds`, `@youtube(id)`, `@vimeo(id)` and
+//! `@niconico(id)` are rendered as iframes instead of plugin templates:
+//!
+//! - YouTube uses the privacy-enhanced `youtube-nocookie.com` domain and
+//!   Vimeo the `dnt=1` (do not track) player
+//! - the iframe sits in a Bootstrap `ratio ratio-16x9` wrapper and is loaded
+//!   lazily
+//! - an optional second argument sets the iframe `title`; otherwise
+//!   `Locale.video_title` is used
+//!
+//! IDs that do not look like IDs of the service are left to the generic
+//! plugin template, so no URL is built from arbitrary input.
+//!
+//! With `ParserOptions.map_embed`, `@map(lat, lon[, zoom[, title]])` becomes
+//! an OpenStreetMap iframe or a static map `<img>` built from a URL template.
+//! Coordinates and zoom are validated the same way.
+//!
+//! In print mode iframes are replaced by a link to the video or map page.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::html_builder::{attr_escape, text_escape, url_attr};
+use crate::parser::{Locale, MapEmbed};
+
+/// Zoom level of `@map` without a zoom argument
+const DEFAULT_MAP_ZOOM: u8 = 15;
+
+/// Size in pixels of the area the OpenStreetMap bounding box is computed for
+const MAP_VIEW: (f64, f64) = (640.0, 480.0);
+
+/// YouTube video IDs: 11 URL-safe base64 characters
+static YOUTUBE_ID: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Za-z0-9_-]{11}$").unwrap());
+
+/// Vimeo video IDs: digits
+static VIMEO_ID: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\d+$").unwrap());
+
+/// niconico video IDs: `sm9`, `so123`, `nm456`
+static NICONICO_ID: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?:sm|so|nm)\d+$").unwrap());
+
+/// Service name, player URL and page URL for an embed plugin
+fn player(function: &str, id: &str) -> Option<(&'static str, String, String)> {
+    match function {
+        "youtube" if YOUTUBE_ID.is_match(id) => Some((
+            "YouTube",
+            format!("https://www.youtube-nocookie.com/embed/{}", id),
+            format!("https://www.youtube.com/watch?v={}", id),
+        )),
+        "vimeo" if VIMEO_ID.is_match(id) => Some((
+            "Vimeo",
+            format!("https://player.vimeo.com/video/{}?dnt=1", id),
+            format!("https://vimeo.com/{}", id),
+        )),
+        "niconico" if NICONICO_ID.is_match(id) => Some((
+            "niconico",
+            format!("https://embed.nicovideo.jp/watch/{}", id),
+            format!("https://www.nicovideo.jp/watch/{}", id),
+        )),
+        _ => None,
+    }
+}
+
+/// Link shown instead of an iframe in print mode
+fn print_link(kind: &str, href: &str, title: &str) -> String {
+    format!(
+        "<a href=\"{}\" class=\"umd-embed-link umd-embed-{}\">{}</a>",
+        url_attr(href),
+        kind,
+        text_escape(title)
+    )
+}
+
+/// Render `@youtube(id[, title])`, `@vimeo(id[, title])` or
+/// `@niconico(id[, title])` as an iframe
+///
+/// Returns `None` for other plugins and invalid IDs. With `print`, a link to
+/// the video page is returned instead of the iframe.
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::embeds::render_video_embed;
+/// use umd::parser::Locale;
+///
+/// let html = render_video_embed("youtube", "dQw4w9WgXcQ", &Locale::en(), false).unwrap();
+/// assert!(html.starts_with("<div class=\"ratio ratio-16x9 umd-embed umd-embed-youtube\">"));
+/// assert!(html.contains("src=\"https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ\""));
+/// assert!(html.contains("title=\"YouTube video\""));
+/// assert!(render_video_embed("youtube", "\"onload=x", &Locale::en(), false).is_none());
+/// ```
+pub fn render_video_embed(
+    function: &str,
+    args: &str,
+    locale: &Locale,
+    print: bool,
+) -> Option<String> {
+    let (id, title) = match args.split_once(',') {
+        Some((id, title)) => (id.trim(), Some(title.trim())),
+        None => (args.trim(), None),
+    };
+    let (service, src, page) = player(function, id)?;
+    let title = match title.filter(|title| !title.is_empty()) {
+        Some(title) => title.to_string(),
+        None => locale.video_title.replace("{name}", service),
+    };
+    if print {
+        return Some(print_link(function, &page, &title));
+    }
+    Some(format!(
+        "<div class=\"ratio ratio-16x9 umd-embed umd-embed-{}\"><iframe src=\"{}\" title=\"{}\" loading=\"lazy\" allow=\"encrypted-media; fullscreen; picture-in-picture\" referrerpolicy=\"strict-origin-when-cross-origin\" allowfullscreen></iframe></div>",
+        function,
+        src,
+        attr_escape(&title)
+    ))
+}
+
+/// Validated `@map` arguments
+struct MapArgs<'a> {
+    lat: f64,
+    lon: f64,
+    zoom: u8,
+    title: Option<&'a str>,
+}
+
+impl<'a> MapArgs<'a> {
+    fn parse(args: &'a str) -> Option<Self> {
+        let mut parts = args.splitn(4, ',').map(str::trim);
+        let lat: f64 = parts.next()?.parse().ok()?;
+        let lon: f64 = parts.next()?.parse().ok()?;
+        let zoom = match parts.next().filter(|zoom| !zoom.is_empty()) {
+            Some(zoom) => zoom.parse().ok().filter(|zoom| *zoom <= 19)?,
+            None => DEFAULT_MAP_ZOOM,
+        };
+        let title = parts.next().filter(|title| !title.is_empty());
+        ((-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon)).then_some(Self {
+            lat,
+            lon,
+            zoom,
+            title,
+        })
+    }
+
+    /// OpenStreetMap embed URL showing `MAP_VIEW` pixels around the point
+    fn openstreetmap_url(&self) -> String {
+        let degrees_per_pixel = 360.0 / (256.0 * 2f64.powi(i32::from(self.zoom)));
+        let half_lon = MAP_VIEW.0 / 2.0 * degrees_per_pixel;
+        let half_lat = MAP_VIEW.1 / 2.0 * degrees_per_pixel * self.lat.to_radians().cos();
+        format!(
+            "https://www.openstreetmap.org/export/embed.html?bbox={:.6},{:.6},{:.6},{:.6}&layer=mapnik&marker={},{}",
+            self.lon - half_lon,
+            self.lat - half_lat,
+            self.lon + half_lon,
+            self.lat + half_lat,
+            self.lat,
+            self.lon
+        )
+    }
+
+    /// OpenStreetMap page URL with a marker at the point
+    fn openstreetmap_page(&self) -> String {
+        format!(
+            "https://www.openstreetmap.org/?mlat={lat}&mlon={lon}#map={}/{lat}/{lon}",
+            self.zoom,
+            lat = self.lat,
+            lon = self.lon
+        )
+    }
+}
+
+/// Render `@map(lat, lon[, zoom[, title]])` with the configured provider
+///
+/// Returns `None` when map embeds are off or the arguments are not valid
+/// coordinates. With `print`, the OpenStreetMap iframe becomes a link to the
+/// map page; static images are kept.
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::embeds::render_map_embed;
+/// use umd::parser::{Locale, MapEmbed};
+///
+/// let provider = MapEmbed::StaticImage(
+///     "https://maps.example.com/static?center={lat},{lon}&zoom={zoom}".to_string(),
+/// );
+/// let html = render_map_embed("35.681, 139.767, 16, Tokyo Station", &provider, &Locale::en(), false);
+/// assert_eq!(
+///     html.as_deref(),
+///     Some("<img src=\"https://maps.example.com/static?center=35.681,139.767&amp;zoom=16\" alt=\"Tokyo Station\" class=\"img-fluid umd-map\" loading=\"lazy\" />")
+/// );
+/// assert!(render_map_embed("135, 0", &provider, &Locale::en(), false).is_none());
+/// ```
+pub fn render_map_embed(
+    args: &str,
+    provider: &MapEmbed,
+    locale: &Locale,
+    print: bool,
+) -> Option<String> {
+    let map = MapArgs::parse(args)?;
+    let title = attr_escape(map.title.unwrap_or(&locale.map_title)).into_owned();
+    match provider {
+        MapEmbed::Off => None,
+        MapEmbed::OpenStreetMap if print => Some(print_link(
+            "map",
+            &map.openstreetmap_page(),
+            map.title.unwrap_or(&locale.map_title),
+        )),
+        MapEmbed::OpenStreetMap => Some(format!(
+            "<div class=\"ratio ratio-4x3 umd-embed umd-embed-map\"><iframe src=\"{}\" title=\"{}\" loading=\"lazy\" referrerpolicy=\"strict-origin-when-cross-origin\"></iframe></div>",
+            url_attr(&map.openstreetmap_url()),
+            title
+        )),
+        MapEmbed::StaticImage(template) => {
+            let src = template
+                .replace("{lat}", &map.lat.to_string())
+                .replace("{lon}", &map.lon.to_string())
+                .replace("{zoom}", &map.zoom.to_string());
+            Some(format!(
+                "<img src=\"{}\" alt=\"{}\" class=\"img-fluid umd-map\" loading=\"lazy\" />",
+                url_attr(&src),
+                title
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_services_and_titles() {
+        let html =
+            render_video_embed("vimeo", "76979871, Launch & demo", &Locale::en(), false).unwrap();
+        assert!(html.contains("src=\"https://player.vimeo.com/video/76979871?dnt=1\""));
+        assert!(html.contains("title=\"Launch &amp; demo\""));
+
+        let html = render_video_embed("niconico", "sm9", &Locale::ja(), false).unwrap();
+        assert!(html.contains("src=\"https://embed.nicovideo.jp/watch/sm9\""));
+        assert!(html.contains("title=\"niconico の動画\""));
+    }
+
+    #[test]
+    fn test_openstreetmap() {
+        let html = render_map_embed(
+            "51.5, -0.12",
+            &MapEmbed::OpenStreetMap,
+            &Locale::ja(),
+            false,
+        )
+        .unwrap();
+        assert!(html.starts_with("<div class=\"ratio ratio-4x3 umd-embed umd-embed-map\">"));
+        assert!(html.contains("&amp;layer=mapnik&amp;marker=51.5,-0.12\""));
+        assert!(html.contains("title=\"地図\""));
+
+        let bbox = MapArgs::parse("0, 0, 1").unwrap().openstreetmap_url();
+        assert!(bbox.contains("bbox=-225.000000,-168.750000,225.000000,168.750000&"));
+    }
+
+    #[test]
+    fn test_map_arguments() {
+        assert!(MapArgs::parse("35, 139, 20").is_none());
+        assert!(MapArgs::parse("35, 181").is_none());
+        assert!(MapArgs::parse("35").is_none());
+        assert!(MapArgs::parse("north, 139").is_none());
+        assert_eq!(MapArgs::parse("35, 139, , Here").unwrap().zoom, 15);
+        assert!(render_map_embed("35, 139", &MapEmbed::Off, &Locale::en(), false).is_none());
+    }
+
+    #[test]
+    fn test_invalid_ids() {
+        assert!(render_video_embed("vimeo", "abc", &Locale::en(), false).is_none());
+        assert!(render_video_embed("niconico", "9", &Locale::en(), false).is_none());
+        assert!(render_video_embed("youtube", "short", &Locale::en(), false).is_none());
+        assert!(render_video_embed("feed", "dQw4w9WgXcQ", &Locale::en(), false).is_none());
+    }
+}
@@ -3,19 +3,25 @@
 //! Provides support for UMD-style emphasis using '' and '''
 //! - ''text'' → <b>text</b> (visual bold)
 //! - '''text''' → <i>text</i> (visual italic)
-//! - __text__ → <u>text</u> (underline, Discord-style - handled in preprocessor)
+//! - __text__ → <u>text</u> (underline, Discord-style - parsed by comrak)
+
+use std::borrow::Cow;
 
 use once_cell::sync::Lazy;
 use regex::Regex;
 
+use crate::parser::EmphasisTags;
+
 static UMD_BOLD: Lazy<Regex> = Lazy::new(|| {
-    // Match ''text'' but not '''text''' (at least 2 non-quote chars)
-    Regex::new(r"''([^']{2,})''").unwrap()
+    // Match ''text'' but not '''text'''; single apostrophes inside the
+    // text are allowed (''Tom's book'')
+    Regex::new(r"''((?:[^']|'[^'])+?)''").unwrap()
 });
 
 static UMD_ITALIC: Lazy<Regex> = Lazy::new(|| {
-    // Match '''text''' with at least one non-quote char
-    Regex::new(r"'''([^']+)'''").unwrap()
+    // Match '''text''' with at least one character; apostrophes and nested
+    // ''bold'' inside the text are allowed
+    Regex::new(r"'''((?:[^']|'[^']|''[^'])+?)'''").unwrap()
 });
 
 /// Apply UMD emphasis syntax to HTML
@@ -41,14 +47,36 @@ static UMD_ITALIC: Lazy<Regex> = Lazy::new(|| {
 /// assert!(output.contains("<b>bold</b>"));
 /// assert!(output.contains("<i>italic</i>"));
 /// ```
-pub fn apply_umd_emphasis(html: &str) -> String {
+pub fn apply_umd_emphasis(html: &str) -> Cow<'_, str> {
+    apply_umd_emphasis_with_tags(html, EmphasisTags::Presentational)
+}
+
+/// Apply UMD emphasis syntax with the given output tags
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::emphasis::apply_umd_emphasis_with_tags;
+/// use umd::parser::EmphasisTags;
+///
+/// let output = apply_umd_emphasis_with_tags("''Tom's book''", EmphasisTags::Semantic);
+/// assert_eq!(output, "<strong>Tom's book</strong>");
+/// ```
+pub fn apply_umd_emphasis_with_tags(html: &str, tags: EmphasisTags) -> Cow<'_, str> {
+    let (bold, italic) = match tags {
+        EmphasisTags::Presentational => ("<b>$1</b>", "<i>$1</i>"),
+        EmphasisTags::Semantic => ("<strong>$1</strong>", "<em>$1</em>"),
+    };
+
     // Process '''text''' (italic) first to avoid conflicts with ''text''
-    let result = UMD_ITALIC.replace_all(html, "<i>$1</i>");
+    let mut result = UMD_ITALIC.replace_all(html, italic);
 
     // Then process ''text'' (bold)
-    let result = UMD_BOLD.replace_all(&result, "<b>$1</b>");
+    if let Cow::Owned(replaced) = UMD_BOLD.replace_all(&result, bold) {
+        result = Cow::Owned(replaced);
+    }
 
-    result.to_string()
+    result
 }
 
 #[cfg(test)]
@@ -79,11 +107,11 @@ mod tests {
 
     #[test]
     fn test_discord_underline() {
-        // Note: __text__ is handled in preprocessor, not here
-        // This test verifies that apply_umd_emphasis doesn't break placeholder markers
-        let input = "This is {{UNDERLINE:underlined:UNDERLINE}} text.";
+        // Note: __text__ is parsed by comrak, not here
+        // This test verifies that apply_umd_emphasis leaves its <u> alone
+        let input = "This is <u>underlined</u> text.";
         let output = apply_umd_emphasis(input);
-        assert!(output.contains("{{UNDERLINE:underlined:UNDERLINE}}"));
+        assert_eq!(output, input);
     }
 
     #[test]
@@ -102,6 +130,26 @@ mod tests {
         assert!(output.contains("<i>italic</i>"));
     }
 
+    #[test]
+    fn test_apostrophes_inside_emphasis() {
+        for (input, expected) in [
+            ("''Tom's book''", "<b>Tom's book</b>"),
+            ("'''it's'''", "<i>it's</i>"),
+            ("'''a ''b'' c'''", "<i>a <b>b</b> c</i>"),
+            ("''a'' and ''b''", "<b>a</b> and <b>b</b>"),
+            ("''x''", "<b>x</b>"),
+            ("''''", "''''"),
+        ] {
+            assert_eq!(apply_umd_emphasis(input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_semantic_tags() {
+        let output = apply_umd_emphasis_with_tags("''bold'' '''italic'''", EmphasisTags::Semantic);
+        assert_eq!(output, "<strong>bold</strong> <em>italic</em>");
+    }
+
     #[test]
     fn test_no_false_matches() {
         let input = "Don't match this: 'single' or four";
@@ -0,0 +1,117 @@
+//! EPUB rendering profile
+//!
+//! With `ParserOptions.profile = Profile::Epub`, the output is ready to be
+//! placed in an EPUB content document:
+//!
+//! - classes are rewritten as with [`Framework::Semantic`], so no Bootstrap
+//!   class is left
+//! - footnotes become `<aside epub:type="footnote">` elements after the body,
+//!   and their references get `epub:type="noteref"`
+//! - `<picture>` wrappers are reduced to their `<img>`, and every media
+//!   figure gets a `<figcaption>` from the title or alt text
+//! - the output is XHTML unless `ParserOptions.html_syntax` is set
+//!
+//! The content document has to declare the `epub` prefix
+//! (`xmlns:epub="http://www.idpf.org/2007/ops"`).
+//!
+//! [`Framework::Semantic`]: crate::parser::Framework::Semantic
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use super::footnotes::footnote_items;
+
+/// Regex to detect a `<picture>` element and its `<img>`
+static PICTURE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?s)<picture\b[^>]*>.*?(<img\b[^>]*>).*?</picture>").unwrap());
+
+/// Regex to detect the loading hints of an `<img>`, which EPUB does not allow
+static LOADING_HINT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"\s(?:loading|decoding|fetchpriority)="[^"]*""#).unwrap());
+
+/// Regex to detect a figure wrapping block media
+static MEDIA_FIGURE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?s)<figure([^>]*)>\n(.*?)\n</figure>").unwrap());
+
+/// Regex to detect the title and alt attributes of media
+static TITLE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\stitle="([^"]+)""#).unwrap());
+static ALT: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\salt="([^"]+)""#).unwrap());
+
+/// Regex to detect the opening of a footnote reference link
+static NOTEREF: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r##"<a (href="#fn-[^"]+" id="fnref-[^"]+" data-footnote-ref)"##).unwrap()
+});
+
+/// Apply the EPUB profile to the body and the footnotes section
+///
+/// # Returns
+///
+/// The body HTML followed by one `<aside>` per footnote
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::epub::apply_epub_profile;
+///
+/// let body = "<p>A<sup class=\"footnote-ref\"><a href=\"#fn-1\" id=\"fnref-1\" data-footnote-ref>1</a></sup></p>";
+/// let footnotes = "<section class=\"footnotes\" data-footnotes>\n<ol>\n<li id=\"fn-1\">\n<p>Note</p>\n</li>\n</ol>\n</section>";
+/// assert_eq!(
+///     apply_epub_profile(body, Some(footnotes)),
+///     "<p>A<sup class=\"footnote-ref\"><a epub:type=\"noteref\" href=\"#fn-1\" id=\"fnref-1\" data-footnote-ref>1</a></sup></p>\n\
+///      <aside epub:type=\"footnote\" id=\"fn-1\">\n<p>Note</p>\n</aside>"
+/// );
+/// ```
+pub fn apply_epub_profile(html: &str, footnotes: Option<&str>) -> String {
+    let html = PICTURE.replace_all(html, |caps: &Captures| {
+        LOADING_HINT.replace_all(&caps[1], "").into_owned()
+    });
+    let html = MEDIA_FIGURE.replace_all(&html, |caps: &Captures| {
+        let content = &caps[2];
+        let caption = TITLE
+            .captures(content)
+            .or_else(|| ALT.captures(content))
+            .filter(|_| !content.contains("<figcaption"));
+        match caption {
+            Some(caption) => format!(
+                "<figure{}>\n{}\n<figcaption>{}</figcaption>\n</figure>",
+                &caps[1], content, &caption[1]
+            ),
+            None => caps[0].to_string(),
+        }
+    });
+    let mut html = NOTEREF
+        .replace_all(&html, "<a epub:type=\"noteref\" $1")
+        .into_owned();
+
+    for (label, content) in footnotes.map(footnote_items).unwrap_or_default() {
+        html.push_str(&format!(
+            "\n<aside epub:type=\"footnote\" id=\"fn-{}\">\n{}\n</aside>",
+            label, content
+        ));
+    }
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_media_figures() {
+        let html = "<figure data-width=\"full\">\n<picture title=\"Sunset\">\n  <source srcset=\"/a.png\" type=\"image/png\" />\n  <img src=\"/a.png\" alt=\"Sky\" loading=\"lazy\" title=\"Sunset\" />\n</picture>\n</figure>\n\
+                    <figure>\n<img src=\"/b.png\" alt=\"Sky\" />\n</figure>\n\
+                    <p>Inline <picture>\n  <img src=\"/i.png\" alt=\"\" loading=\"lazy\" />\n</picture></p>";
+        assert_eq!(
+            apply_epub_profile(html, None),
+            "<figure data-width=\"full\">\n<img src=\"/a.png\" alt=\"Sky\" title=\"Sunset\" />\n<figcaption>Sunset</figcaption>\n</figure>\n\
+             <figure>\n<img src=\"/b.png\" alt=\"Sky\" />\n<figcaption>Sky</figcaption>\n</figure>\n\
+             <p>Inline <img src=\"/i.png\" alt=\"\" /></p>"
+        );
+    }
+
+    #[test]
+    fn test_existing_captions_are_kept() {
+        let html = "<figure>\n<pre title=\"x\"></pre><figcaption>main.rs</figcaption>\n</figure>";
+        assert_eq!(apply_epub_profile(html, None), html);
+    }
+}
@@ -0,0 +1,185 @@
+//! External link marking
+//!
+//! Links to another origin than
+//! [`ParserOptions::site_origin`](crate::parser::ParserOptions::site_origin)
+//! get `class="external"`, the `rel` / `target` of [`ExternalLinkOptions`]
+//! and optionally a trailing icon. The pass runs once every link is final,
+//! so plain links, badge links, autolinks and media download links are
+//! treated alike. Only `http(s)://` and protocol-relative `//` URLs can be
+//! external; without a site origin all of them are.
+
+use std::borrow::Cow;
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use super::attributes::{Attributes, find_attribute};
+use crate::parser::ExternalLinkOptions;
+
+/// Regex to detect a link element with its start tag attributes and content
+static LINK_ELEMENT: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<a\s([^>]*)>(.*?)</a>").unwrap());
+
+/// Class added to external links
+pub const EXTERNAL_CLASS: &str = "external";
+
+/// Mark links to other origins
+///
+/// `icon` is appended inside the link when `options.icon` is set. An
+/// existing `rel` or `target` of the link is kept.
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::external_links::apply_external_links;
+/// use umd::parser::ExternalLinkOptions;
+///
+/// let html = r#"<a href="https://example.org/">A</a> <a href="https://example.com/b">B</a>"#;
+/// let options = ExternalLinkOptions::default();
+/// assert_eq!(
+///     apply_external_links(html, Some("https://example.com"), &options, ""),
+///     r#"<a href="https://example.org/" class="external" rel="noopener noreferrer">A</a> <a href="https://example.com/b">B</a>"#
+/// );
+/// ```
+pub fn apply_external_links<'a>(
+    html: &'a str,
+    site_origin: Option<&str>,
+    options: &ExternalLinkOptions,
+    icon: &str,
+) -> Cow<'a, str> {
+    if !html.contains("<a ") {
+        return Cow::Borrowed(html);
+    }
+    let site = site_origin.and_then(|site| origin(site, "https"));
+    let scheme = site
+        .as_deref()
+        .and_then(|site| site.split_once("://"))
+        .map_or("https", |(scheme, _)| scheme);
+
+    let mut block = Attributes {
+        classes: vec![EXTERNAL_CLASS.to_string()],
+        ..Attributes::default()
+    };
+    if let Some(rel) = &options.rel {
+        block.pairs.push(("rel".to_string(), rel.clone()));
+    }
+    if let Some(target) = &options.target {
+        block.pairs.push(("target".to_string(), target.clone()));
+    }
+
+    LINK_ELEMENT.replace_all(html, |caps: &Captures| {
+        let attrs = &caps[1];
+        let Some((start, end)) = find_attribute(attrs, "href") else {
+            return caps[0].to_string();
+        };
+        let external = origin(&attrs[start..end], scheme)
+            .is_some_and(|link| site.as_ref().is_none_or(|site| *site != link));
+        let marked = find_attribute(attrs, "class").is_some_and(|(start, end)| {
+            attrs[start..end]
+                .split_whitespace()
+                .any(|class| class == EXTERNAL_CLASS)
+        });
+        if !external || marked {
+            return caps[0].to_string();
+        }
+
+        let mut attrs = attrs.to_string();
+        block.merge_into(&mut attrs);
+        let icon = if options.icon && !icon.is_empty() {
+            format!(" {}", icon)
+        } else {
+            String::new()
+        };
+        format!("<a {}>{}{}</a>", attrs.trim_start(), &caps[2], icon)
+    })
+}
+
+/// Origin (`scheme://host[:port]`, lowercase, without a default port) of an
+/// `http(s)` URL; protocol-relative URLs use `scheme`
+fn origin(url: &str, scheme: &str) -> Option<String> {
+    let (scheme, rest) = match url.strip_prefix("//") {
+        Some(rest) => (scheme.to_string(), rest),
+        None => {
+            let (scheme, rest) = url.split_once("://")?;
+            (scheme.to_ascii_lowercase(), rest)
+        }
+    };
+    let default_port = match scheme.as_str() {
+        "http" => ":80",
+        "https" => ":443",
+        _ => return None,
+    };
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host)
+        .to_ascii_lowercase();
+    let host = host.strip_suffix(default_port).unwrap_or(&host);
+    (!host.is_empty()).then(|| format!("{}://{}", scheme, host))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_origin() {
+        assert_eq!(
+            origin("HTTPS://User@Example.com:443/a?b", "https").as_deref(),
+            Some("https://example.com")
+        );
+        assert_eq!(
+            origin("//cdn.example.com/x", "http").as_deref(),
+            Some("http://cdn.example.com")
+        );
+        assert_eq!(
+            origin("http://example.com:8080", "https").as_deref(),
+            Some("http://example.com:8080")
+        );
+        assert_eq!(origin("/docs", "https"), None);
+        assert_eq!(origin("mailto:a@example.com", "https"), None);
+        assert_eq!(origin("ftp://example.com", "https"), None);
+    }
+
+    #[test]
+    fn test_external_links() {
+        let options = ExternalLinkOptions {
+            icon: true,
+            target: Some("_blank".to_string()),
+            ..ExternalLinkOptions::default()
+        };
+        let icon = r#"<span class="bi bi-box-arrow-up-right" aria-hidden="true"></span>"#;
+        let html = concat!(
+            r#"<a href="https://example.org" class="badge bg-info" rel="me">A</a>"#,
+            r#"<a href="http://example.com/b">B</a>"#,
+            r#"<a href="//example.com/c">C</a>"#,
+            r##"<a href="#top">D</a>"##,
+            r#"<a href="https://example.org" class="external">E</a>"#,
+        );
+        let marked = apply_external_links(html, Some("https://example.com/docs/"), &options, icon);
+        assert_eq!(
+            marked,
+            format!(
+                concat!(
+                    r#"<a href="https://example.org" class="badge bg-info external" rel="me" target="_blank">A {icon}</a>"#,
+                    r#"<a href="http://example.com/b" class="external" rel="noopener noreferrer" target="_blank">B {icon}</a>"#,
+                    r#"<a href="//example.com/c">C</a>"#,
+                    r##"<a href="#top">D</a>"##,
+                    r#"<a href="https://example.org" class="external">E</a>"#,
+                ),
+                icon = icon
+            )
+        );
+
+        // Without a site origin every absolute link is external
+        let marked = apply_external_links(
+            r#"<a href="//example.com/c">C</a>"#,
+            None,
+            &ExternalLinkOptions::default(),
+            icon,
+        );
+        assert_eq!(
+            marked,
+            r#"<a href="//example.com/c" class="external" rel="noopener noreferrer">C</a>"#
+        );
+    }
+}
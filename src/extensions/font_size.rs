@@ -0,0 +1,134 @@
+//! Font size mapping shared by `SIZE()`, `&size()` and table cells
+//!
+//! - Bootstrap sizes (unitless): `2.5` → `fs-1`, `2` → `fs-2`, `1.75` → `fs-3`,
+//!   `1.5` → `fs-4`, `1.25` → `fs-5`, `0.875` → `fs-6`
+//! - keywords: `lead` → `lead`, `sm` → `small`, `lg` → `fs-5`, `xl` → `fs-4`
+//! - other numbers: inline style, in `rem` unless a unit (`rem`, `em`, `px`,
+//!   `%`) is given
+//! - other keywords: `umd-size-{keyword}` class, which embedders can map to
+//!   their own classes with
+//!   [`ParserOptions::font_sizes`](crate::parser::ParserOptions::font_sizes)
+//!
+//! Anything else is rejected, so a size can never inject other CSS.
+
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::class_map::apply_class_map;
+use crate::parser::ClassOptions;
+
+/// Regex to detect a length: number with an optional unit
+static LENGTH: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\d{1,3}(?:\.\d+)?(?:rem|em|px|%)?$").unwrap());
+
+/// Regex to detect a size keyword
+static KEYWORD: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[a-z][a-z0-9-]{0,31}$").unwrap());
+
+/// Prefix of the classes emitted for custom size keywords
+const CUSTOM_CLASS_PREFIX: &str = "umd-size-";
+
+/// Resolved font size
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FontSize {
+    /// CSS class (`fs-4`, `lead`, `umd-size-huge`, ...)
+    Class(String),
+    /// `font-size` value for an inline style (`3rem`, `14px`, ...)
+    Style(String),
+}
+
+/// Map a size argument to a class or an inline style value
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::font_size::{FontSize, map_font_size};
+///
+/// assert_eq!(map_font_size("1.5"), Some(FontSize::Class("fs-4".to_string())));
+/// assert_eq!(map_font_size("lead"), Some(FontSize::Class("lead".to_string())));
+/// assert_eq!(map_font_size("3"), Some(FontSize::Style("3rem".to_string())));
+/// assert_eq!(map_font_size("12px"), Some(FontSize::Style("12px".to_string())));
+/// assert_eq!(map_font_size("1;color:red"), None);
+/// ```
+pub fn map_font_size(value: &str) -> Option<FontSize> {
+    let value = value.trim();
+
+    let class = match value {
+        "2.5" => Some("fs-1"),
+        "2" | "2.0" => Some("fs-2"),
+        "1.75" => Some("fs-3"),
+        "1.5" => Some("fs-4"),
+        "1.25" => Some("fs-5"),
+        "0.875" => Some("fs-6"),
+        "lead" => Some("lead"),
+        "sm" => Some("small"),
+        "lg" => Some("fs-5"),
+        "xl" => Some("fs-4"),
+        _ => None,
+    };
+    if let Some(class) = class {
+        return Some(FontSize::Class(class.to_string()));
+    }
+
+    if LENGTH.is_match(value) {
+        let style = if value.ends_with(|c: char| c.is_ascii_digit()) {
+            format!("{}rem", value)
+        } else {
+            value.to_string()
+        };
+        return Some(FontSize::Style(style));
+    }
+
+    if KEYWORD.is_match(value) {
+        return Some(FontSize::Class(format!("{}{}", CUSTOM_CLASS_PREFIX, value)));
+    }
+
+    None
+}
+
+/// Replace `umd-size-{keyword}` classes with the embedder's mapping
+///
+/// # Arguments
+///
+/// * `html` - Rendered HTML
+/// * `sizes` - Keyword to class(es) map
+///
+/// # Returns
+///
+/// HTML with mapped size classes
+pub fn apply_custom_sizes(html: &str, sizes: &HashMap<String, String>) -> String {
+    let options = ClassOptions {
+        prefix: None,
+        map: sizes
+            .iter()
+            .map(|(keyword, class)| (format!("{}{}", CUSTOM_CLASS_PREFIX, keyword), class.clone()))
+            .collect(),
+    };
+    apply_class_map(html, &options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_keyword() {
+        assert_eq!(
+            map_font_size("huge"),
+            Some(FontSize::Class("umd-size-huge".to_string()))
+        );
+        let sizes = HashMap::from([("huge".to_string(), "text-9xl".to_string())]);
+        assert_eq!(
+            apply_custom_sizes(r#"<p class="umd-size-huge text-center">a</p>"#, &sizes),
+            r#"<p class="text-9xl text-center">a</p>"#
+        );
+    }
+
+    #[test]
+    fn test_rejects_css_injection() {
+        assert_eq!(map_font_size("2\" onclick=\"x"), None);
+        assert_eq!(map_font_size("1rem; color: red"), None);
+        assert_eq!(map_font_size("Huge"), None);
+    }
+}
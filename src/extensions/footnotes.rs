@@ -0,0 +1,419 @@
+//! Footnote section rendering
+//!
+//! comrak renders footnotes as a `<section class="footnotes">` list. This
+//! module renumbers them (for [`ParserOptions::footnotes`] `start` and for
+//! [`SiteContext`](crate::site::SiteContext) continuous numbering) and applies
+//! the heading, back-reference symbol and class options. With
+//! `FootnoteOptions.tooltips`, references also carry the plain text of their
+//! note for hover previews.
+//!
+//! [`ParserOptions::footnotes`]: crate::parser::ParserOptions::footnotes
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+
+use crate::html_builder::{attr_escape, text_escape};
+use crate::parser::FootnoteOptions;
+use crate::search_index::normalize_text;
+
+/// Regex to detect footnote references generated by comrak
+static FOOTNOTE_REF: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r##"<a href="#fn-([^"]+)" id="fnref-([^"]+)" data-footnote-ref([^>]*)>(\d+)</a>"##)
+        .unwrap()
+});
+
+/// Regex to detect back-reference links inside a footnote definition
+static BACKREF_LINK: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?s)<a [^>]*data-footnote-backref[^>]*>.*?</a>").unwrap());
+
+/// Regex to detect footnote definitions
+static FOOTNOTE_ITEM: Lazy<Regex> = Lazy::new(|| Regex::new(r#"<li id="fn-([^"]+)">"#).unwrap());
+
+/// Regex to detect footnote back-references
+static FOOTNOTE_BACKREF: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"href="\#fnref-([^"]+)" class="footnote-backref" data-footnote-backref data-footnote-backref-idx="(\d+)([^"]*)""#,
+    )
+    .unwrap()
+});
+
+/// Regex to detect the opening tag of the footnote list
+static FOOTNOTE_LIST: Lazy<Regex> = Lazy::new(|| Regex::new(r#"<ol(?: start="\d+")?>"#).unwrap());
+
+/// Regex to detect the opening tag of the footnotes section
+static FOOTNOTE_SECTION: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"<section class="footnotes"( data-footnotes)?>"#).unwrap());
+
+/// Regex to detect the back-reference symbol
+static BACKREF_SYMBOL: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(<a [^>]*data-footnote-backref[^>]*>)↩"#).unwrap());
+
+/// Regex to detect comrak's default back-reference label
+static FOOTNOTE_BACKREF_LABEL: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"aria-label="Back to reference (\d+)([^"]*)""#).unwrap());
+
+/// Opening tag of the footnotes section, up to its attributes
+const SECTION_OPEN: &str = "<section class=\"footnotes\"";
+
+/// Split the footnotes section generated by comrak from the body
+///
+/// comrak appends the section after the last block, so only a section that
+/// starts a line and runs to the end of the document is taken; the same
+/// markup elsewhere (inside code or raw HTML) stays in the body.
+///
+/// # Returns
+///
+/// A tuple of (body HTML, footnotes HTML)
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::footnotes::extract_footnotes;
+///
+/// let html = "<p>A<sup>1</sup></p>\n<section class=\"footnotes\" data-footnotes>\n<ol></ol>\n</section>\n";
+/// let (body, footnotes) = extract_footnotes(html);
+/// assert_eq!(body, "<p>A<sup>1</sup></p>\n\n");
+/// assert_eq!(
+///     footnotes.as_deref(),
+///     Some("<section class=\"footnotes\" data-footnotes>\n<ol></ol>\n</section>")
+/// );
+/// ```
+pub fn extract_footnotes(html: &str) -> (String, Option<String>) {
+    let section = html
+        .rmatch_indices(SECTION_OPEN)
+        .map(|(start, _)| start)
+        .filter(|&start| start == 0 || html[..start].ends_with('\n'))
+        .find_map(|start| {
+            let end = start + section_len(&html[start..])?;
+            html[end..].trim().is_empty().then_some((start, end))
+        });
+    match section {
+        Some((start, end)) => (
+            format!("{}{}", &html[..start], &html[end..]),
+            Some(html[start..end].to_string()),
+        ),
+        None => (html.to_string(), None),
+    }
+}
+
+/// Length of the `<section>` element starting at `html`, including nested
+/// sections and the closing tag
+fn section_len(html: &str) -> Option<usize> {
+    const CLOSE: &str = "</section>";
+    let mut depth = 0usize;
+    let mut pos = 0;
+    while let Some(offset) = html[pos..].find('<') {
+        pos += offset;
+        let rest = &html[pos..];
+        if rest.starts_with("<section")
+            && matches!(rest.as_bytes().get(8), Some(b'>' | b' ' | b'\t' | b'\n'))
+        {
+            depth += 1;
+        } else if rest.starts_with(CLOSE) {
+            depth = depth.checked_sub(1)?;
+            if depth == 0 {
+                return Some(pos + CLOSE.len());
+            }
+        }
+        pos += 1;
+    }
+    None
+}
+
+/// Apply [`FootnoteOptions`] to the body and the extracted footnotes section
+///
+/// # Returns
+///
+/// A tuple of (body HTML, footnotes HTML)
+pub(crate) fn apply_footnote_options(
+    html: String,
+    footnotes: Option<String>,
+    options: &FootnoteOptions,
+) -> (String, Option<String>) {
+    let Some(footnotes) = footnotes else {
+        return (html, None);
+    };
+    let (html, footnotes) = if options.start > 1 {
+        let (html, footnotes, _) = renumber_footnotes(&html, Some(&footnotes), options.start - 1);
+        (html, footnotes.unwrap_or_default())
+    } else {
+        (html, footnotes)
+    };
+    let html = if options.tooltips {
+        add_footnote_tooltips(&html, &footnotes)
+    } else {
+        html
+    };
+    (html, Some(customize_footnotes(&footnotes, options)))
+}
+
+/// Add the plain text of each note to its references as
+/// `data-footnote-content`
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::footnotes::add_footnote_tooltips;
+///
+/// let body = "<a href=\"#fn-1\" id=\"fnref-1\" data-footnote-ref>1</a>";
+/// let section = "<section class=\"footnotes\" data-footnotes>\n<ol>\n<li id=\"fn-1\">\n<p>A <em>note</em></p>\n</li>\n</ol>\n</section>";
+/// assert_eq!(
+///     add_footnote_tooltips(body, section),
+///     "<a href=\"#fn-1\" id=\"fnref-1\" data-footnote-ref data-footnote-content=\"A note\">1</a>"
+/// );
+/// ```
+pub fn add_footnote_tooltips(html: &str, footnotes: &str) -> String {
+    let mut notes: HashMap<&str, String> = HashMap::new();
+    for (label, content) in footnote_items(footnotes) {
+        let content = BACKREF_LINK.replace_all(content, "");
+        notes.insert(label, normalize_text(&content));
+    }
+
+    FOOTNOTE_REF
+        .replace_all(html, |caps: &Captures| match notes.get(&caps[1]) {
+            Some(text) if !caps[3].contains("data-footnote-content") => format!(
+                "<a href=\"#fn-{}\" id=\"fnref-{}\" data-footnote-ref{} data-footnote-content=\"{}\">{}</a>",
+                &caps[1],
+                &caps[2],
+                &caps[3],
+                attr_escape(text),
+                &caps[4]
+            ),
+            _ => caps[0].to_string(),
+        })
+        .into_owned()
+}
+
+/// Label (`fn-{label}`) and content of each footnote in a footnotes section
+///
+/// The content runs up to the next footnote, without the closing `</li>`.
+pub(crate) fn footnote_items(section: &str) -> Vec<(&str, &str)> {
+    let items: Vec<_> = FOOTNOTE_ITEM.captures_iter(section).collect();
+    let list_end = section.rfind("</ol>").unwrap_or(section.len());
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, caps)| {
+            let start = caps.get(0).unwrap().end();
+            let end = items
+                .get(index + 1)
+                .map_or(list_end, |next| next.get(0).unwrap().start());
+            let content = section[start..end.max(start)].trim();
+            let content = content.strip_suffix("</li>").unwrap_or(content).trim();
+            (caps.get(1).unwrap().as_str(), content)
+        })
+        .collect()
+}
+
+/// Apply heading, back-reference symbol and class options to the footnotes
+/// section
+///
+/// # Arguments
+///
+/// * `section` - The `<section class="footnotes">` HTML
+/// * `options` - Footnote rendering options
+///
+/// # Returns
+///
+/// Customized footnotes section
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::footnotes::customize_footnotes;
+/// use umd::parser::FootnoteOptions;
+///
+/// let section = "<section class=\"footnotes\" data-footnotes>\n<ol>\n</ol>\n</section>";
+/// let options = FootnoteOptions {
+///     heading: Some("References".to_string()),
+///     ..FootnoteOptions::default()
+/// };
+/// assert!(customize_footnotes(section, &options).contains("<h2 class=\"footnotes-heading\">References</h2>"));
+/// ```
+pub fn customize_footnotes(section: &str, options: &FootnoteOptions) -> String {
+    let mut result = section.to_string();
+
+    if options.backref_symbol != "↩" {
+        let symbol = text_escape(&options.backref_symbol);
+        result = BACKREF_SYMBOL
+            .replace_all(&result, |caps: &Captures| format!("{}{}", &caps[1], symbol))
+            .into_owned();
+    }
+
+    let class = options
+        .class
+        .as_deref()
+        .map(str::trim)
+        .filter(|class| !class.is_empty());
+    let heading = options.heading.as_deref().filter(|text| !text.is_empty());
+    if class.is_none() && heading.is_none() {
+        return result;
+    }
+
+    FOOTNOTE_SECTION
+        .replace(&result, |caps: &Captures| {
+            let mut open = match class {
+                Some(class) => format!(
+                    "<section class=\"footnotes {}\"{}>",
+                    attr_escape(class),
+                    caps.get(1).map_or("", |m| m.as_str())
+                ),
+                None => caps[0].to_string(),
+            };
+            if let Some(text) = heading {
+                let level = options.heading_level.clamp(1, 6);
+                open.push_str(&format!(
+                    "\n<h{} class=\"footnotes-heading\">{}</h{}>",
+                    level,
+                    text_escape(text),
+                    level
+                ));
+            }
+            open
+        })
+        .into_owned()
+}
+
+/// Renumber footnotes starting after `offset`
+///
+/// comrak names footnote IDs after their labels (`fn-note`, `fnref-note`),
+/// which collide when pages are combined. References and definitions are
+/// renamed to `fn-{n}` / `fnref-{n}` with `n` continuing from `offset`.
+///
+/// # Returns
+///
+/// A tuple of (body HTML, footnotes HTML, number of footnotes renumbered)
+pub(crate) fn renumber_footnotes(
+    html: &str,
+    footnotes: Option<&str>,
+    offset: usize,
+) -> (String, Option<String>, usize) {
+    let mut numbers: HashMap<String, usize> = HashMap::new();
+    for caps in FOOTNOTE_REF.captures_iter(html) {
+        let number: usize = caps[4].parse().unwrap_or(0);
+        numbers
+            .entry(caps[1].to_string())
+            .or_insert(number + offset);
+    }
+    if numbers.is_empty() {
+        return (html.to_string(), footnotes.map(str::to_string), 0);
+    }
+
+    let body = FOOTNOTE_REF
+        .replace_all(html, |caps: &Captures| {
+            let label = &caps[1];
+            let Some(number) = numbers.get(label) else {
+                return caps[0].to_string();
+            };
+            // Repeated references carry a suffix: fnref-note-2
+            let suffix = caps[2].strip_prefix(label).unwrap_or("");
+            format!(
+                "<a href=\"#fn-{}\" id=\"fnref-{}{}\" data-footnote-ref{}>{}</a>",
+                number, number, suffix, &caps[3], number
+            )
+        })
+        .to_string();
+
+    let footnotes = footnotes.map(|section| {
+        let section = FOOTNOTE_ITEM.replace_all(section, |caps: &Captures| match numbers
+            .get(&caps[1])
+        {
+            Some(number) => format!("<li id=\"fn-{}\">", number),
+            None => caps[0].to_string(),
+        });
+        let section = FOOTNOTE_BACKREF.replace_all(&section, |caps: &Captures| {
+            let reference = &caps[1];
+            let (label, suffix) = numbers
+                .keys()
+                .filter(|label| reference.starts_with(label.as_str()))
+                .max_by_key(|label| label.len())
+                .map(|label| (label.as_str(), &reference[label.len()..]))
+                .unwrap_or((reference, ""));
+            let Some(number) = numbers.get(label) else {
+                return caps[0].to_string();
+            };
+            let idx: usize = caps[2].parse().unwrap_or(0) + offset;
+            format!(
+                "href=\"#fnref-{}{}\" class=\"footnote-backref\" data-footnote-backref data-footnote-backref-idx=\"{}{}\"",
+                number, suffix, idx, &caps[3]
+            )
+        });
+        let section = FOOTNOTE_BACKREF_LABEL.replace_all(&section, |caps: &Captures| {
+            let aria: usize = caps[1].parse().unwrap_or(0) + offset;
+            format!("aria-label=\"Back to reference {}{}\"", aria, &caps[2])
+        });
+        let first = numbers.values().min().copied().unwrap_or(1);
+        if first > 1 {
+            FOOTNOTE_LIST
+                .replace(&section, format!("<ol start=\"{}\">", first))
+                .into_owned()
+        } else {
+            section.to_string()
+        }
+    });
+
+    (body, footnotes, numbers.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECTION: &str = "<section class=\"footnotes\" data-footnotes>\n<ol>\n<li id=\"fn-1\">\n<p>Note <a href=\"#fnref-1\" class=\"footnote-backref\" data-footnote-backref data-footnote-backref-idx=\"1\" aria-label=\"Back to content\">↩</a></p>\n</li>\n</ol>\n</section>";
+
+    #[test]
+    fn test_extract_footnotes_ignores_embedded_sections() {
+        let body = "<pre><code><section class=\"footnotes\">\n</code></pre>\n<div>\n<section class=\"footnotes\"></section>\n</div>\n";
+        let (html, footnotes) = extract_footnotes(&format!("{}{}\n", body, SECTION));
+        assert_eq!(html, format!("{}\n", body));
+        assert_eq!(footnotes.as_deref(), Some(SECTION));
+
+        let (html, footnotes) = extract_footnotes(body);
+        assert_eq!(html, body);
+        assert!(footnotes.is_none());
+    }
+
+    #[test]
+    fn test_customize_footnotes() {
+        let options = FootnoteOptions {
+            heading: Some("<Notes>".to_string()),
+            heading_level: 3,
+            backref_symbol: "^".to_string(),
+            class: Some("small text-muted".to_string()),
+            ..FootnoteOptions::default()
+        };
+        let output = customize_footnotes(SECTION, &options);
+        assert!(output.starts_with(
+            "<section class=\"footnotes small text-muted\" data-footnotes>\n<h3 class=\"footnotes-heading\">&lt;Notes&gt;</h3>\n<ol>"
+        ));
+        assert!(output.contains("aria-label=\"Back to content\">^</a>"));
+    }
+
+    #[test]
+    fn test_tooltips_survive_renumbering() {
+        let body = "<a href=\"#fn-1\" id=\"fnref-1\" data-footnote-ref>1</a>";
+        let options = FootnoteOptions {
+            tooltips: true,
+            ..FootnoteOptions::default()
+        };
+        let (html, _) =
+            apply_footnote_options(body.to_string(), Some(SECTION.to_string()), &options);
+        assert!(html.contains("data-footnote-content=\"Note\""));
+
+        let (html, _, count) = renumber_footnotes(&html, Some(SECTION), 4);
+        assert_eq!(count, 1);
+        assert_eq!(
+            html,
+            "<a href=\"#fn-5\" id=\"fnref-5\" data-footnote-ref data-footnote-content=\"Note\">5</a>"
+        );
+    }
+
+    #[test]
+    fn test_default_options_keep_section() {
+        assert_eq!(
+            customize_footnotes(SECTION, &FootnoteOptions::default()),
+            SECTION
+        );
+    }
+}
@@ -0,0 +1,296 @@
+//! CSS framework profiles
+//!
+//! Decorations are emitted with Bootstrap 5 classes. Other profiles rewrite
+//! those classes on the final HTML, so COLOR / SIZE / alignment parsing stays
+//! shared between all profiles:
+//!
+//! - [`Framework::Semantic`]: utility classes become data attributes
+//!   (`text-center` → `data-align="center"`, `fs-4` → `data-size="4"`,
+//!   `text-red` → `data-color="red"`, `text-opacity-50` →
+//!   `data-opacity="50"`, ...), component classes become
+//!   `umd-*` classes (`badge` → `umd-badge`) and pure layout helpers are
+//!   dropped
+//! - [`Framework::Custom`]: Bootstrap class names are replaced through a map
+//!
+//! Classes that are not Bootstrap's (`umd-*`, `spoiler`, `anchor`, icon
+//! classes, ...) are never touched.
+
+use std::collections::HashMap;
+
+use regex::Captures;
+
+use super::class_map::{CLASS_ATTR, TAG};
+use crate::parser::Framework;
+
+/// Component classes renamed in semantic mode
+const SEMANTIC_COMPONENTS: &[(&str, &str)] = &[
+    ("table", "umd-table"),
+    ("table-responsive", "umd-table-wrapper"),
+    ("badge", "umd-badge"),
+    ("alert", "umd-alert"),
+    ("alert-heading", "umd-alert-heading"),
+    ("clearfix", "umd-clearfix"),
+    ("ratio", "umd-ratio"),
+];
+
+/// Component classes renamed in semantic mode on one element only
+/// (`row` is also the grid of galleries)
+const SEMANTIC_ELEMENT_COMPONENTS: &[(&str, &str, &str)] = &[("dl", "row", "umd-dl-horizontal")];
+
+/// Layout helpers without semantic meaning
+const SEMANTIC_DROPPED: &[&str] = &[
+    "blockquote",
+    "img-fluid",
+    "rounded-pill",
+    "w-auto",
+    "me-0",
+    "me-3",
+    "ms-3",
+    "col-sm-3",
+    "col-sm-9",
+];
+
+/// Convert one Bootstrap class into a semantic class or data attribute
+fn semantic_class(element: &str, class: &str) -> (Option<String>, Option<(&'static str, String)>) {
+    let attr = |name: &'static str, value: &str| (None, Some((name, value.to_string())));
+
+    if let Some((_, _, renamed)) = SEMANTIC_ELEMENT_COMPONENTS
+        .iter()
+        .find(|(tag, name, _)| tag.eq_ignore_ascii_case(element) && *name == class)
+    {
+        return (Some((*renamed).to_string()), None);
+    }
+    if let Some((_, renamed)) = SEMANTIC_COMPONENTS.iter().find(|(name, _)| *name == class) {
+        return (Some((*renamed).to_string()), None);
+    }
+    if SEMANTIC_DROPPED.contains(&class) {
+        return (None, None);
+    }
+    match class {
+        "mx-auto" => return attr("data-placement", "center"),
+        "ms-auto" => return attr("data-placement", "end"),
+        "w-100" => return attr("data-width", "full"),
+        "float-start" => return attr("data-float", "start"),
+        "float-end" => return attr("data-float", "end"),
+        "text-truncate" => return attr("data-truncate", "true"),
+        "text-nowrap" => return attr("data-nowrap", "true"),
+        "caption-top" => return attr("data-caption", "top"),
+        "bg-gradient" => return attr("data-gradient", "true"),
+        _ => {}
+    }
+    if let Some(align) = class.strip_prefix("text-")
+        && matches!(align, "start" | "center" | "end" | "justify")
+    {
+        return attr("data-align", align);
+    }
+    if let Some(breakpoint) = class.strip_prefix("table-responsive-") {
+        return (
+            Some("umd-table-wrapper".to_string()),
+            Some(("data-responsive", breakpoint.to_string())),
+        );
+    }
+    if let Some(valign) = class.strip_prefix("align-") {
+        return attr("data-valign", valign);
+    }
+    if let Some(ratio) = class.strip_prefix("ratio-") {
+        return attr("data-ratio", ratio);
+    }
+    if let Some(size) = class.strip_prefix("fs-") {
+        return attr("data-size", size);
+    }
+    if let Some(opacity) = class.strip_prefix("text-opacity-") {
+        return attr("data-opacity", opacity);
+    }
+    if let Some(opacity) = class.strip_prefix("bg-opacity-") {
+        return attr("data-bg-opacity", opacity);
+    }
+    if let Some(alert) = class.strip_prefix("alert-") {
+        return attr("data-alert", alert);
+    }
+    if let Some(color) = class.strip_prefix("text-") {
+        return attr("data-color", color);
+    }
+    if let Some(color) = class.strip_prefix("bg-") {
+        return attr("data-bg", color);
+    }
+    (Some(class.to_string()), None)
+}
+
+fn semantic_tag(tag: &str) -> String {
+    let Some(caps) = CLASS_ATTR.captures(tag) else {
+        return tag.to_string();
+    };
+    let element = tag[1..]
+        .split(|ch: char| ch.is_ascii_whitespace() || ch == '>' || ch == '/')
+        .next()
+        .unwrap_or_default();
+    let (mut classes, mut attrs) = (Vec::new(), Vec::new());
+    for class in caps[1].split_whitespace() {
+        let (renamed, attr) = semantic_class(element, class);
+        if let Some(renamed) = renamed
+            && !classes.contains(&renamed)
+        {
+            classes.push(renamed);
+        }
+        attrs.extend(attr);
+    }
+
+    let mut replacement = String::new();
+    if !classes.is_empty() {
+        replacement.push_str(&format!(" class=\"{}\"", classes.join(" ")));
+    }
+    for (name, value) in attrs {
+        replacement.push_str(&format!(" {}=\"{}\"", name, value));
+    }
+    let whole = caps.get(0).unwrap();
+    format!(
+        "{}{}{}",
+        &tag[..whole.start()],
+        replacement,
+        &tag[whole.end()..]
+    )
+}
+
+fn custom_tag(tag: &str, map: &HashMap<String, String>) -> String {
+    CLASS_ATTR
+        .replace(tag, |caps: &Captures| {
+            let classes: Vec<&str> = caps[1]
+                .split_whitespace()
+                .flat_map(|class| match map.get(class) {
+                    Some(replacement) => replacement.split_whitespace().collect(),
+                    None => vec![class],
+                })
+                .collect();
+            if classes.is_empty() {
+                String::new()
+            } else {
+                format!(" class=\"{}\"", classes.join(" "))
+            }
+        })
+        .into_owned()
+}
+
+/// Rewrite Bootstrap classes for the selected framework profile
+///
+/// # Arguments
+///
+/// * `html` - Rendered HTML (Bootstrap 5 classes)
+/// * `framework` - Target profile
+///
+/// # Returns
+///
+/// HTML for the target profile
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::framework::apply_framework;
+/// use umd::parser::Framework;
+///
+/// let html = r#"<p class="text-center fs-4">x</p>"#;
+/// assert_eq!(
+///     apply_framework(html, &Framework::Semantic),
+///     r#"<p data-align="center" data-size="4">x</p>"#
+/// );
+/// ```
+pub fn apply_framework(html: &str, framework: &Framework) -> String {
+    match framework {
+        Framework::Bootstrap5 => html.to_string(),
+        Framework::Semantic => TAG
+            .replace_all(html, |caps: &Captures| semantic_tag(&caps[0]))
+            .into_owned(),
+        Framework::Custom(map) => TAG
+            .replace_all(html, |caps: &Captures| custom_tag(&caps[0], map))
+            .into_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_semantic_badge_and_colors() {
+        let html = r#"<span class="badge rounded-pill bg-primary">a</span><span class="text-red bg-yellow">b</span>"#;
+        assert_eq!(
+            apply_framework(html, &Framework::Semantic),
+            r#"<span class="umd-badge" data-bg="primary">a</span><span data-color="red" data-bg="yellow">b</span>"#
+        );
+    }
+
+    #[test]
+    fn test_semantic_color_utilities() {
+        let html = r#"<p class="text-primary text-opacity-50">a</p><span class="bg-primary bg-gradient bg-opacity-25">b</span>"#;
+        assert_eq!(
+            apply_framework(html, &Framework::Semantic),
+            r#"<p data-color="primary" data-opacity="50">a</p><span data-bg="primary" data-gradient="true" data-bg-opacity="25">b</span>"#
+        );
+    }
+
+    #[test]
+    fn test_semantic_keeps_umd_classes() {
+        let html = r#"<table class="table umd-table"><td class="align-top text-end">x</td></table><span class="spoiler">y</span>"#;
+        assert_eq!(
+            apply_framework(html, &Framework::Semantic),
+            r#"<table class="umd-table"><td data-valign="top" data-align="end">x</td></table><span class="spoiler">y</span>"#
+        );
+    }
+
+    #[test]
+    fn test_semantic_nowrap_cell() {
+        let html = r#"<td class="text-nowrap text-end">x</td>"#;
+        assert_eq!(
+            apply_framework(html, &Framework::Semantic),
+            r#"<td data-nowrap="true" data-align="end">x</td>"#
+        );
+    }
+
+    #[test]
+    fn test_semantic_caption_top() {
+        let html = r#"<table class="table caption-top"><caption>c</caption></table>"#;
+        assert_eq!(
+            apply_framework(html, &Framework::Semantic),
+            r#"<table class="umd-table" data-caption="top"><caption>c</caption></table>"#
+        );
+    }
+
+    #[test]
+    fn test_semantic_responsive_breakpoint() {
+        let html = r#"<div class="table-responsive-md"><table class="table"></table></div>"#;
+        assert_eq!(
+            apply_framework(html, &Framework::Semantic),
+            r#"<div class="umd-table-wrapper" data-responsive="md"><table class="umd-table"></table></div>"#
+        );
+    }
+
+    #[test]
+    fn test_semantic_float() {
+        let html =
+            r#"<figure class="float-start me-3"></figure><figure class="float-end ms-3"></figure>"#;
+        assert_eq!(
+            apply_framework(html, &Framework::Semantic),
+            r#"<figure data-float="start"></figure><figure data-float="end"></figure>"#
+        );
+    }
+
+    #[test]
+    fn test_semantic_row_is_renamed_on_definition_lists_only() {
+        let html = r#"<dl class="row"><dt class="col-sm-3">a</dt></dl><div class="umd-gallery row row-cols-1 g-2"></div>"#;
+        assert_eq!(
+            apply_framework(html, &Framework::Semantic),
+            r#"<dl class="umd-dl-horizontal"><dt>a</dt></dl><div class="umd-gallery row row-cols-1 g-2"></div>"#
+        );
+    }
+
+    #[test]
+    fn test_custom_map() {
+        let map = HashMap::from([(
+            "text-center".to_string(),
+            "text-center md:text-left".to_string(),
+        )]);
+        assert_eq!(
+            apply_framework(r#"<p class="text-center x">a</p>"#, &Framework::Custom(map)),
+            r#"<p class="text-center md:text-left x">a</p>"#
+        );
+    }
+}
@@ -0,0 +1,104 @@
+//! Generated element IDs (Mermaid diagrams, popovers)
+//!
+//! [`IdStrategy::Random`] uses UUIDs; [`IdStrategy::ContentHash`] derives
+//! the ID from the element's content, so the same document always renders
+//! the same HTML. Repeated tokens get a `-2`, `-3`, ... suffix.
+
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::parser::IdStrategy;
+
+/// Generator of document-unique ID tokens
+#[derive(Debug)]
+pub struct IdGenerator {
+    strategy: IdStrategy,
+    seen: HashMap<String, usize>,
+}
+
+impl IdGenerator {
+    /// Create a generator for one document
+    pub fn new(strategy: IdStrategy) -> Self {
+        Self {
+            strategy,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Strategy used by this generator
+    pub fn strategy(&self) -> IdStrategy {
+        self.strategy
+    }
+
+    /// Unique token of `len` hex digits (at most 16 for content hashes) for
+    /// an element with `content`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use umd::extensions::ids::IdGenerator;
+    /// use umd::parser::IdStrategy;
+    ///
+    /// let mut ids = IdGenerator::new(IdStrategy::ContentHash);
+    /// let first = ids.token("graph TD", 8);
+    /// assert_eq!(first.len(), 8);
+    /// assert_eq!(ids.token("graph TD", 8), format!("{}-2", first));
+    /// ```
+    pub fn token(&mut self, content: &str, len: usize) -> String {
+        let mut token = match self.strategy {
+            IdStrategy::Random => Uuid::new_v4().simple().to_string(),
+            IdStrategy::ContentHash => format!("{:016x}", simple_hash(content)),
+        };
+        token.truncate(len);
+
+        let count = self.seen.entry(token.clone()).or_insert(0);
+        *count += 1;
+        if *count == 1 {
+            token
+        } else {
+            format!("{}-{}", token, count)
+        }
+    }
+}
+
+/// Lightweight FNV-1a hash
+fn simple_hash(data: &str) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x100000001b3;
+
+    let mut hash = FNV_OFFSET_BASIS;
+    for byte in data.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simple_hash_consistency() {
+        let hash1 = simple_hash("test");
+        let hash2 = simple_hash("test");
+        assert_eq!(hash1, hash2);
+    }
+
+    #[test]
+    fn test_content_hash_tokens() {
+        let mut ids = IdGenerator::new(IdStrategy::ContentHash);
+        let a = ids.token("a", 16);
+        assert_eq!(a, format!("{:016x}", simple_hash("a")));
+        assert_ne!(ids.token("b", 16), a);
+        assert_eq!(ids.token("a", 16), format!("{}-2", a));
+        assert_eq!(IdGenerator::new(IdStrategy::ContentHash).token("a", 16), a);
+    }
+
+    #[test]
+    fn test_random_tokens() {
+        let mut ids = IdGenerator::new(IdStrategy::Random);
+        assert_ne!(ids.token("a", 32), ids.token("a", 32));
+    }
+}
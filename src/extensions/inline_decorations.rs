@@ -18,9 +18,16 @@
 //!
 //! Note: For underline, use Discord-style __text__ syntax instead
 
+use std::collections::HashMap;
+
 use once_cell::sync::Lazy;
 use regex::Regex;
 
+use super::color_utilities::{gradient_classes, opacity_class};
+use super::font_size::{FontSize, map_font_size};
+use crate::html_builder::{attr_escape, url_attr};
+use crate::sanitizer::{sanitize_css_color, split_args};
+
 // Badge pattern with optional link support
 static INLINE_BADGE: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"&badge\(([^)]+?)\)\{([^}]+?)\};").unwrap());
@@ -29,7 +36,7 @@ static INLINE_BADGE: Lazy<Regex> =
 static MARKDOWN_LINK: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[([^\]]+)\]\(([^)]+)\)").unwrap());
 
 static INLINE_COLOR: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"&color\(([^,)]*?)(?:,([^)]*?))?\)\{([^}]+?)\};").unwrap());
+    Lazy::new(|| Regex::new(r"&color\(((?:[^()]|\([^()]*\))*)\)\{([^}]+?)\};").unwrap());
 
 static INLINE_SIZE: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"&size\(([^)]+?)\)\{([^}]+?)\};").unwrap());
@@ -83,33 +90,16 @@ static DISCORD_SPOILER: Lazy<Regex> = Lazy::new(|| Regex::new(r"\|\|([^|]+)\|\|"
 static INLINE_SPOILER: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"&spoiler(?:\(([^)]+?)\)|\{([^}]+?)\});").unwrap());
 
-/// Map font size value to Bootstrap class or inline style
-fn map_font_size(value: &str) -> (bool, String) {
-    // Check if value has unit (rem, em, px, etc.)
-    if value.contains("rem") || value.contains("em") || value.contains("px") {
-        return (false, value.to_string()); // Return as inline style
-    }
-
-    // Map to Bootstrap fs-* classes (unitless values)
-    let class = match value {
-        "2.5" => "fs-1",
-        "2" | "2.0" => "fs-2",
-        "1.75" => "fs-3",
-        "1.5" => "fs-4",
-        "1.25" => "fs-5",
-        "0.875" => "fs-6",
-        _ => return (false, format!("{}rem", value)), // Custom value as inline style
-    };
-
-    (true, class.to_string())
-}
-
 /// Map color value to Bootstrap class or inline style
 /// Returns Some((is_class, value)) if valid, None if invalid
-/// Only accepts Bootstrap color names and HEX format (#RRGGBB or #RGB)
+/// Accepts Bootstrap color names and the CSS colors allowed by `sanitize_css_color`
 fn map_color(value: &str, is_background: bool) -> Option<(bool, String)> {
     let trimmed = value.trim();
 
+    if let Some(classes) = gradient_classes(trimmed) {
+        return Some((true, classes));
+    }
+
     // Bootstrap theme colors
     let bootstrap_colors = [
         // Theme colors
@@ -184,22 +174,8 @@ fn map_color(value: &str, is_background: bool) -> Option<(bool, String)> {
         }
     }
 
-    // Check if it's a HEX color (#RRGGBB or #RGB)
-    if trimmed.starts_with('#') && (trimmed.len() == 4 || trimmed.len() == 7) {
-        // Basic validation: check if all characters after # are hex digits
-        if trimmed[1..].chars().all(|c| c.is_ascii_hexdigit()) {
-            return Some((false, trimmed.to_string()));
-        }
-    }
-
-    // Future: Support rgb() and hsl() formats
-    // if trimmed.starts_with("rgb(") || trimmed.starts_with("rgba(") ||
-    //    trimmed.starts_with("hsl(") || trimmed.starts_with("hsla(") {
-    //     return Some((false, trimmed.to_string()));
-    // }
-
-    // Invalid color specification (e.g., HTML color names are not supported)
-    None
+    // HEX, rgb()/hsl() or CSS named color as inline style
+    sanitize_css_color(trimmed).map(|color| (false, color))
 }
 
 /// Map badge type to Bootstrap badge classes
@@ -235,6 +211,16 @@ pub fn apply_inline_decorations(html: &str) -> String {
 pub fn apply_inline_decorations_with_limit(
     html: &str,
     max_inline_nesting: Option<usize>,
+) -> String {
+    apply_inline_decorations_with_options(html, max_inline_nesting, true)
+}
+
+/// Apply inline decoration functions, optionally without the LukiWiki
+/// `%%text%%` strikethrough
+pub(crate) fn apply_inline_decorations_with_options(
+    html: &str,
+    max_inline_nesting: Option<usize>,
+    lukiwiki: bool,
 ) -> String {
     let mut result = html.to_string();
 
@@ -270,9 +256,11 @@ pub fn apply_inline_decorations_with_limit(
     }
 
     // Apply %%text%% → <s>text</s> (LukiWiki strikethrough)
-    result = LUKIWIKI_STRIKETHROUGH
-        .replace_all(&result, "<s>$1</s>")
-        .to_string();
+    if lukiwiki {
+        result = LUKIWIKI_STRIKETHROUGH
+            .replace_all(&result, "<s>$1</s>")
+            .to_string();
+    }
 
     // Apply || text || → <span class="spoiler">text</span> (Discord spoiler)
     result = DISCORD_SPOILER
@@ -301,19 +289,30 @@ pub fn apply_inline_decorations_with_limit(
             if let Some(link_caps) = MARKDOWN_LINK.captures(content) {
                 let text = link_caps.get(1).map_or("", |m| m.as_str());
                 let url = link_caps.get(2).map_or("", |m| m.as_str());
-                format!("<a href=\"{}\" class=\"{}\">{}</a>", url, badge_class, text)
+                format!(
+                    "<a href=\"{}\" class=\"{}\">{}</a>",
+                    url_attr(url),
+                    attr_escape(&badge_class),
+                    text
+                )
             } else {
-                format!("<span class=\"{}\">{}</span>", badge_class, content)
+                format!(
+                    "<span class=\"{}\">{}</span>",
+                    attr_escape(&badge_class),
+                    content
+                )
             }
         })
         .to_string();
 
-    // Apply &color(fg,bg){text}; with Bootstrap support
+    // Apply &color(fg,bg,opacity){text}; with Bootstrap support
     result = INLINE_COLOR
         .replace_all(&result, |caps: &regex::Captures| {
-            let fg = caps.get(1).map_or("", |m| m.as_str().trim());
-            let bg = caps.get(2).map_or("", |m| m.as_str().trim());
-            let text = caps.get(3).map_or("", |m| m.as_str());
+            let args = split_args(caps.get(1).map_or("", |m| m.as_str()));
+            let fg = args[0];
+            let bg = args.get(1).copied().unwrap_or("");
+            let opacity = args.get(2).copied().unwrap_or("");
+            let text = caps.get(2).map_or("", |m| m.as_str());
 
             let mut classes = Vec::new();
             let mut styles = Vec::new();
@@ -338,15 +337,17 @@ pub fn apply_inline_decorations_with_limit(
                 }
             }
 
+            classes.extend(opacity_class(opacity, &classes));
+
             if classes.is_empty() && styles.is_empty() {
                 text.to_string()
             } else {
                 let mut attrs = Vec::new();
                 if !classes.is_empty() {
-                    attrs.push(format!("class=\"{}\"", classes.join(" ")));
+                    attrs.push(format!("class=\"{}\"", attr_escape(&classes.join(" "))));
                 }
                 if !styles.is_empty() {
-                    attrs.push(format!("style=\"{}\"", styles.join("; ")));
+                    attrs.push(format!("style=\"{}\"", attr_escape(&styles.join("; "))));
                 }
                 format!("<span {}>{}</span>", attrs.join(" "), text)
             }
@@ -359,11 +360,16 @@ pub fn apply_inline_decorations_with_limit(
             let size = caps.get(1).map_or("", |m| m.as_str());
             let text = caps.get(2).map_or("", |m| m.as_str());
 
-            let (is_class, value) = map_font_size(size);
-            if is_class {
-                format!("<span class=\"{}\">{}</span>", value, text)
-            } else {
-                format!("<span style=\"font-size: {}\">{}</span>", value, text)
+            match map_font_size(size) {
+                Some(FontSize::Class(class)) => {
+                    format!("<span class=\"{}\">{}</span>", attr_escape(&class), text)
+                }
+                Some(FontSize::Style(size)) => format!(
+                    "<span style=\"font-size: {}\">{}</span>",
+                    attr_escape(&size),
+                    text
+                ),
+                None => text.to_string(),
             }
         })
         .to_string();
@@ -380,12 +386,24 @@ pub fn apply_inline_decorations_with_limit(
 
     // Apply &lang(locale){text};
     result = INLINE_LANG
-        .replace_all(&result, "<span lang=\"$1\">$2</span>;")
+        .replace_all(&result, |caps: &regex::Captures| {
+            format!(
+                "<span lang=\"{}\">{}</span>;",
+                attr_escape(&caps[1]),
+                &caps[2]
+            )
+        })
         .to_string();
 
     // Apply &abbr(text){description};
     result = INLINE_ABBR
-        .replace_all(&result, "<abbr title=\"$2\">$1</abbr>;")
+        .replace_all(&result, |caps: &regex::Captures| {
+            format!(
+                "<abbr title=\"{}\">{}</abbr>;",
+                attr_escape(&caps[2]),
+                &caps[1]
+            )
+        })
         .to_string();
 
     // Apply &ruby(reading){text};
@@ -416,10 +434,22 @@ pub fn apply_inline_decorations_with_limit(
 
     // Elements with attributes
     result = INLINE_TIME
-        .replace_all(&result, "<time datetime=\"$1\">$2</time>;")
+        .replace_all(&result, |caps: &regex::Captures| {
+            format!(
+                "<time datetime=\"{}\">{}</time>;",
+                attr_escape(&caps[1]),
+                &caps[2]
+            )
+        })
         .to_string();
     result = INLINE_DATA
-        .replace_all(&result, "<data value=\"$1\">$2</data>;")
+        .replace_all(&result, |caps: &regex::Captures| {
+            format!(
+                "<data value=\"{}\">{}</data>;",
+                attr_escape(&caps[1]),
+                &caps[2]
+            )
+        })
         .to_string();
 
     // Bidirectional text
@@ -427,7 +457,13 @@ pub fn apply_inline_decorations_with_limit(
         .replace_all(&result, "<bdi>$1</bdi>;")
         .to_string();
     result = INLINE_BDO
-        .replace_all(&result, "<bdo dir=\"$1\">$2</bdo>;")
+        .replace_all(
+            &result,
+            |caps: &regex::Captures| match crate::sanitizer::sanitize_dir(&caps[1]) {
+                Some(dir @ ("ltr" | "rtl")) => format!("<bdo dir=\"{}\">{}</bdo>;", dir, &caps[2]),
+                _ => format!("<bdi>{}</bdi>;", &caps[2]),
+            },
+        )
         .to_string();
 
     // Word break opportunity
@@ -478,6 +514,7 @@ fn neutralize_over_limit_inline_decorations(input: &str, max_inline_nesting: usi
     let mut stack: Vec<bool> = Vec::new();
     let mut limited_depth = 0usize;
     let mut output = String::with_capacity(input.len() + 16);
+    let block_ends = inline_block_ends(input);
 
     while i < bytes.len() {
         if let Some((name, next_index)) = parse_inline_block_start(input, i) {
@@ -485,7 +522,7 @@ fn neutralize_over_limit_inline_decorations(input: &str, max_inline_nesting: usi
             let over_limit = limited && limited_depth >= max_inline_nesting;
 
             if over_limit {
-                if let Some(end_index) = find_inline_block_end(input, i) {
+                if let Some(&end_index) = block_ends.get(&i) {
                     let escaped = html_escape::encode_safe(&input[i..end_index])
                         .replace('{', "&#123;")
                         .replace('}', "&#125;");
@@ -529,32 +566,35 @@ fn neutralize_over_limit_inline_decorations(input: &str, max_inline_nesting: usi
     output
 }
 
-fn find_inline_block_end(input: &str, start: usize) -> Option<usize> {
-    let mut i = parse_inline_block_start(input, start)?.1;
+/// End offsets (after `};`) of the inline blocks, keyed by their start
+///
+/// One pass over the input, so documents full of unclosed blocks are not
+/// rescanned for every block start.
+fn inline_block_ends(input: &str) -> HashMap<usize, usize> {
     let bytes = input.as_bytes();
-    let mut depth = 1usize;
+    let mut ends = HashMap::new();
+    let mut stack: Vec<usize> = Vec::new();
+    let mut i = 0usize;
 
     while i < bytes.len() {
-        if parse_inline_block_start(input, i).is_some() {
-            depth += 1;
-            i = parse_inline_block_start(input, i)?.1;
+        if let Some((_, next_index)) = parse_inline_block_start(input, i) {
+            stack.push(i);
+            i = next_index;
             continue;
         }
 
         if i + 1 < bytes.len() && bytes[i] == b'}' && bytes[i + 1] == b';' {
-            depth -= 1;
-            i += 2;
-            if depth == 0 {
-                return Some(i);
+            if let Some(start) = stack.pop() {
+                ends.insert(start, i + 2);
             }
+            i += 2;
             continue;
         }
 
-        let ch = input[i..].chars().next()?;
-        i += ch.len_utf8();
+        i += 1;
     }
 
-    None
+    ends
 }
 
 fn parse_inline_block_start(input: &str, start: usize) -> Option<(&str, usize)> {
@@ -582,12 +622,14 @@ fn parse_inline_block_start(input: &str, start: usize) -> Option<(&str, usize)>
         return None;
     }
 
+    // Arguments never contain `(`, which also keeps runs of unclosed
+    // `&name(` from rescanning the rest of the input
     let mut k = j + 1;
-    while k < bytes.len() && bytes[k] != b')' {
+    while k < bytes.len() && bytes[k] != b')' && bytes[k] != b'(' {
         k += 1;
     }
 
-    if k < bytes.len() && bytes.get(k + 1).copied() == Some(b'{') {
+    if bytes.get(k).copied() == Some(b')') && bytes.get(k + 1).copied() == Some(b'{') {
         return Some((name, k + 2));
     }
 
@@ -634,20 +676,22 @@ mod tests {
     }
 
     #[test]
-    fn test_map_color_invalid_html_name() {
-        // HTML color names like "white" or "black" are not in Bootstrap color list
-        // and should be rejected
-        let result = map_color("white", false);
-        assert!(
-            result.is_none(),
-            "HTML color name 'white' should be rejected"
+    fn test_map_color_css_formats() {
+        // CSS named colors outside the Bootstrap palette become inline styles
+        assert_eq!(
+            map_color("white", false),
+            Some((false, "white".to_string()))
         );
-
-        let result = map_color("black", false);
-        assert!(
-            result.is_none(),
-            "HTML color name 'black' should be rejected"
+        assert_eq!(
+            map_color("rgb(255 0 0 / 0.5)", false),
+            Some((false, "rgb(255 0 0 / 0.5)".to_string()))
+        );
+        assert_eq!(
+            map_color("hsl(210,50%,40%)", true),
+            Some((false, "hsl(210,50%,40%)".to_string()))
         );
+        assert_eq!(map_color("notacolor", false), None);
+        assert_eq!(map_color("rgb(0,0,0);x:y", false), None);
     }
 
     #[test]
@@ -681,8 +725,8 @@ mod tests {
 
     #[test]
     fn test_inline_color_invalid() {
-        // white and black are not in Bootstrap color list, so they should be rejected
-        let input = "&color(white,black){white on black};";
+        // Neither Bootstrap names nor valid CSS colors
+        let input = "&color(snow-white,blackish){white on black};";
         let output = apply_inline_decorations(input);
         // Invalid colors should be ignored, text remains as-is
         assert_eq!(
@@ -813,6 +857,13 @@ mod tests {
         assert!(output.contains("<bdo dir=\"rtl\">right-to-left</bdo>"));
     }
 
+    #[test]
+    fn test_bdo_invalid_direction_falls_back_to_bdi() {
+        let output = apply_inline_decorations("&bdo(up){text};");
+        assert!(output.contains("<bdi>text</bdi>"));
+        assert!(!output.contains("dir="));
+    }
+
     #[test]
     fn test_wbr() {
         let input = "Very&wbr;Long&wbr;Word";
@@ -0,0 +1,179 @@
+//! Source line anchors
+//!
+//! Review tools that attach comment threads to source lines need to find
+//! those lines in the rendered page. With `ParserOptions.line_anchors` set,
+//! an empty `<span id="L42"></span>` is placed before each top-level block,
+//! named after the source line the block starts on (counting frontmatter
+//! lines). Blocks closer than the interval to the previous anchor get none.
+//!
+//! [`insert_line_anchors`] runs on the source before preprocessing and
+//! writes each anchor as a placeholder paragraph, which comrak renders like
+//! any other paragraph; [`render_line_anchors`] turns those paragraphs into
+//! spans. Anchors are only placed where a paragraph can be inserted without
+//! changing the document structure: at unindented lines after a blank line,
+//! outside fenced code, multi-line plugins and comments, UMD blockquotes,
+//! and not inside a list (whose items may be followed by unindented tables
+//! or code fences) or before a `Table:` caption.
+
+use std::borrow::Cow;
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+/// Rendered placeholder paragraph of an anchor
+static LINE_ANCHOR: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"<p[^>]*>UMD_LINE_ANCHOR_(\d+)_END</p>").unwrap());
+
+/// Lines that continue a list above them even after a blank line: list
+/// items, and the tables, blockquotes, code fences, plugins and placement
+/// prefixes that [`preprocess_nested_blocks`] moves into the last item
+///
+/// [`preprocess_nested_blocks`]: super::nested_blocks::preprocess_nested_blocks
+static LIST_CONTINUATION: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^(?:(?:[-+*]|\d{1,9}[.)])(?:[ \t]|$)|[|>@]|```|~~~|(?:LEFT|CENTER|RIGHT|JUSTIFY):\s*$)",
+    )
+    .unwrap()
+});
+
+/// First line of a list item
+static LIST_ITEM: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?:[-+*]|\d{1,9}[.)])(?:[ \t]|$)").unwrap());
+
+/// Insert anchor placeholders before the top-level blocks of `input`
+///
+/// `interval` is the minimum number of lines between two anchors (`1`
+/// anchors every block) and `first_line` the source line number of the
+/// first line of `input`.
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::line_anchors::insert_line_anchors;
+///
+/// let output = insert_line_anchors("# A\n\nText\n\n- a\n\n- b", 1, 1);
+/// assert_eq!(
+///     output,
+///     "UMD_LINE_ANCHOR_1_END\n\n# A\n\nUMD_LINE_ANCHOR_3_END\n\nText\n\nUMD_LINE_ANCHOR_5_END\n\n- a\n\n- b"
+/// );
+/// ```
+pub fn insert_line_anchors(input: &str, interval: usize, first_line: usize) -> Cow<'_, str> {
+    if interval == 0 || input.trim().is_empty() {
+        return Cow::Borrowed(input);
+    }
+
+    let lines: Vec<&str> = input.split('\n').collect();
+    // A `>` line only opens a UMD blockquote when a `<` line follows it
+    let last_quote_close = lines.iter().rposition(|line| line.trim() == "<");
+
+    let mut output = String::with_capacity(input.len() + lines.len() * 8);
+    let mut fence: Option<(char, usize)> = None;
+    let mut in_plugin = false;
+    let mut in_comment = false;
+    let mut in_quote = false;
+    let mut after_blank = true;
+    let mut in_list = false;
+    let mut next_anchor = first_line;
+
+    for (index, line) in lines.iter().enumerate() {
+        if index > 0 {
+            output.push('\n');
+        }
+        let trimmed = line.trim();
+
+        if let Some((fence_char, fence_len)) = fence {
+            let run = trimmed.chars().take_while(|c| *c == fence_char).count();
+            if run >= fence_len && trimmed[run..].trim().is_empty() {
+                fence = None;
+            }
+        } else if in_plugin {
+            in_plugin = !line.contains("}}");
+        } else if in_comment {
+            in_comment = !line.contains("*/");
+        } else if in_quote {
+            in_quote = trimmed != "<";
+        } else if !trimmed.is_empty() {
+            let number = first_line + index;
+            if after_blank && !line.starts_with([' ', '\t']) {
+                let continues_list = in_list && LIST_CONTINUATION.is_match(line);
+                if number >= next_anchor && !continues_list && !line.starts_with("Table:") {
+                    output.push_str(&format!("UMD_LINE_ANCHOR_{}_END\n\n", number));
+                    next_anchor = number + interval;
+                }
+                in_list = continues_list || LIST_ITEM.is_match(line);
+                in_quote =
+                    line.starts_with('>') && last_quote_close.is_some_and(|close| close > index);
+            }
+
+            let fence_char = trimmed.chars().next().filter(|c| *c == '`' || *c == '~');
+            if let Some(ch) = fence_char {
+                let run = trimmed.chars().take_while(|c| *c == ch).count();
+                if run >= 3 {
+                    fence = Some((ch, run));
+                }
+            }
+            if let Some(open) = line.rfind("{{") {
+                in_plugin = !line[open..].contains("}}");
+            }
+            if let Some(open) = line.rfind("/*") {
+                in_comment = !line[open..].contains("*/");
+            }
+        }
+
+        output.push_str(line);
+        after_blank = trimmed.is_empty();
+    }
+
+    Cow::Owned(output)
+}
+
+/// Replace the rendered anchor placeholders with `<span id="L42"></span>`
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::line_anchors::render_line_anchors;
+///
+/// let html = render_line_anchors("<p>UMD_LINE_ANCHOR_3_END</p>\n<p>Text</p>");
+/// assert_eq!(html, "<span id=\"L3\"></span>\n<p>Text</p>");
+/// ```
+pub fn render_line_anchors(html: &str) -> Cow<'_, str> {
+    if !html.contains("UMD_LINE_ANCHOR_") {
+        return Cow::Borrowed(html);
+    }
+    LINE_ANCHOR.replace_all(html, |caps: &Captures| {
+        format!("<span id=\"L{}\"></span>", &caps[1])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interval() {
+        let input = "a\n\nb\n\nc\n\nd";
+        assert_eq!(
+            insert_line_anchors(input, 4, 10),
+            "UMD_LINE_ANCHOR_10_END\n\na\n\nb\n\nUMD_LINE_ANCHOR_14_END\n\nc\n\nd"
+        );
+        assert_eq!(insert_line_anchors(input, 0, 1), input);
+    }
+
+    #[test]
+    fn test_skips_nested_content() {
+        for input in [
+            "```\na\n\nb\n```",
+            "@note(){{\na\n\nb\n}}",
+            "/* a\n\nb */",
+            "> a\n\nb\n<",
+            "- a\n\n  b",
+            "- a\n\n- b\n\n| t |\n\n```\nc\n```",
+            "| a |\n\nTable: caption",
+        ] {
+            let output = insert_line_anchors(input, 1, 1);
+            assert_eq!(output.matches("UMD_LINE_ANCHOR_").count(), 1, "{}", input);
+            assert!(output.starts_with("UMD_LINE_ANCHOR_1_END\n\n"), "{}", input);
+        }
+    }
+}
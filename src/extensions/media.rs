@@ -3,8 +3,20 @@
 //! This module provides functionality to detect media files by extension
 //! and generate appropriate HTML5 media tags (video, audio, picture).
 
+use std::borrow::Cow;
 use std::path::Path;
 
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::attributes::{Attributes, parse_attributes};
+use crate::frontmatter::Frontmatter;
+use crate::html_builder::{attr_escape, text_escape, url_attr};
+use crate::parser::{
+    FetchPriority, Icons, ImageDecoding, ImageLoading, ImageOptions, Locale, MediaOptions,
+    ParserOptions,
+};
+
 /// Media type detected from file extension
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum MediaType {
@@ -96,9 +108,22 @@ pub fn detect_media_type(url: &str) -> Option<MediaType> {
 pub fn detect_media_type_with_hint(
     url: &str,
     allow_fragment_extension_hint: bool,
+) -> Option<MediaType> {
+    detect_mapped_media_type(url, &MediaOptions::default(), allow_fragment_extension_hint)
+}
+
+/// Media type of `url`, looking up `media.types` before the built-in table
+fn detect_mapped_media_type(
+    url: &str,
+    media: &MediaOptions,
+    allow_fragment_extension_hint: bool,
 ) -> Option<MediaType> {
     let ext = resolve_extension(url, allow_fragment_extension_hint)?;
-    media_type_from_extension(ext.as_str())
+    media
+        .types
+        .get(&ext)
+        .cloned()
+        .or_else(|| media_type_from_extension(ext.as_str()))
 }
 
 /// Get MIME type for a file extension
@@ -107,6 +132,7 @@ pub fn detect_media_type_with_hint(
 ///
 /// * `url` - The URL to analyze
 /// * `media_type` - The detected media type
+/// * `media` - MIME type overrides (`media.mime_types`)
 ///
 /// # Returns
 ///
@@ -114,9 +140,13 @@ pub fn detect_media_type_with_hint(
 fn get_mime_type_with_hint(
     url: &str,
     media_type: &MediaType,
+    media: &MediaOptions,
     allow_fragment_extension_hint: bool,
 ) -> String {
     let ext = resolve_extension(url, allow_fragment_extension_hint).unwrap_or_default();
+    if let Some(mime_type) = media.mime_types.get(&ext) {
+        return mime_type.clone();
+    }
 
     match media_type {
         MediaType::Video => match ext.as_str() {
@@ -170,7 +200,7 @@ fn get_mime_type_with_hint(
 /// # Arguments
 ///
 /// * `url` - The media URL
-/// * `alt` - Alt text (used for track label in video, ignored in audio)
+/// * `alt` - Alt text (used for the fallback link in video and audio)
 /// * `title` - Optional title attribute
 /// * `media_type` - The type of media
 ///
@@ -193,9 +223,106 @@ pub fn generate_media_html(
     alt: &str,
     title: Option<&str>,
     media_type: &MediaType,
-    icons: &crate::parser::Icons,
+    icons: &Icons,
 ) -> String {
-    generate_media_html_with_hint(url, alt, title, media_type, icons, false)
+    generate_media_html_with_hint(
+        url,
+        alt,
+        title,
+        media_type,
+        icons,
+        &Locale::default(),
+        false,
+    )
+}
+
+/// Attributes of a `{key=value ...}` block following a media image
+#[derive(Debug, Default)]
+struct MediaAttributes {
+    /// Alternative sources, in order of preference (`fallback=clip.mp4`)
+    fallback: Vec<String>,
+    /// WebVTT caption tracks (`captions=/subs/en.vtt lang=en label=English`)
+    captions: Vec<CaptionTrack>,
+    /// Loading hints written into the `<img>` of an image (not parsed from
+    /// the block; see [`image_loading_attrs`])
+    img_attrs: String,
+}
+
+/// A `<track kind="captions">` attached to a video
+#[derive(Debug, Default)]
+struct CaptionTrack {
+    src: String,
+    lang: Option<String>,
+    label: Option<String>,
+}
+
+/// Parse a media attribute block; `None` when it contains anything other
+/// than known `key=value` pairs (the block is then left as text)
+fn parse_media_attributes(spec: &str) -> Option<MediaAttributes> {
+    let spec = spec.replace("&quot;", "\"");
+    let mut attributes = MediaAttributes::default();
+    let mut tokens = Vec::new();
+    let mut rest = spec.trim();
+    while !rest.is_empty() {
+        let (key, after_key) = rest.split_once('=')?;
+        let key = key.trim();
+        let (value, after_value) = match after_key.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"')?;
+                (&quoted[..end], &quoted[end + 1..])
+            }
+            None => after_key
+                .split_once(char::is_whitespace)
+                .unwrap_or((after_key, "")),
+        };
+        tokens.push((key.to_string(), value.to_string()));
+        rest = after_value.trim_start();
+    }
+    if tokens.is_empty() {
+        return None;
+    }
+
+    for (key, value) in tokens {
+        match key.as_str() {
+            "fallback" => attributes.fallback.extend(
+                value
+                    .split('|')
+                    .filter(|source| !source.is_empty())
+                    .map(str::to_string),
+            ),
+            "captions" => attributes.captions.push(CaptionTrack {
+                src: value,
+                ..CaptionTrack::default()
+            }),
+            // `lang` and `label` describe the preceding `captions` track
+            "lang" => attributes.captions.last_mut()?.lang = Some(value),
+            "label" => attributes.captions.last_mut()?.label = Some(value),
+            _ => return None,
+        }
+    }
+    Some(attributes)
+}
+
+/// Sources of `clip.webm|clip.mp4` (`|` may be percent-encoded by comrak)
+///
+/// The URL is only split when every part is a file of `media_type`.
+fn split_sources<'a>(
+    url: &'a str,
+    media_type: &MediaType,
+    media: &MediaOptions,
+    allow_fragment_extension_hint: bool,
+) -> Vec<&'a str> {
+    let parts: Vec<&str> = url.split("%7C").flat_map(|part| part.split('|')).collect();
+    if parts.len() > 1
+        && parts.iter().all(|part| {
+            detect_mapped_media_type(part, media, allow_fragment_extension_hint).as_ref()
+                == Some(media_type)
+        })
+    {
+        parts
+    } else {
+        vec![url]
+    }
 }
 
 fn generate_media_html_with_hint(
@@ -203,77 +330,204 @@ fn generate_media_html_with_hint(
     alt: &str,
     title: Option<&str>,
     media_type: &MediaType,
-    icons: &crate::parser::Icons,
+    icons: &Icons,
+    locale: &Locale,
     allow_fragment_extension_hint: bool,
 ) -> String {
-    let mime_type = get_mime_type_with_hint(url, media_type, allow_fragment_extension_hint);
+    let mime_type = get_mime_type_with_hint(
+        url,
+        media_type,
+        &MediaOptions::default(),
+        allow_fragment_extension_hint,
+    );
+    let attributes = MediaAttributes {
+        img_attrs: image_loading_attrs(&ImageOptions::default(), None),
+        ..MediaAttributes::default()
+    };
+    generate_media_html_with_sources(
+        &[(url, mime_type)],
+        &attributes,
+        alt,
+        title,
+        media_type,
+        icons,
+        locale,
+    )
+}
+
+/// Split an `eager` / `lazy` keyword off the start or end of an image
+/// attribute block (`{eager}`, `{eager .hero}`, `{#logo lazy}`)
+fn split_loading_keyword(spec: &str) -> (Option<ImageLoading>, &str) {
+    let keyword = |token: &str| match token {
+        "eager" => Some(ImageLoading::Eager),
+        "lazy" => Some(ImageLoading::Lazy),
+        _ => None,
+    };
+    let spec = spec.trim();
+    let (first, rest) = spec.split_once(char::is_whitespace).unwrap_or((spec, ""));
+    if let Some(loading) = keyword(first) {
+        return (Some(loading), rest.trim_start());
+    }
+    let (rest, last) = spec.rsplit_once(char::is_whitespace).unwrap_or(("", spec));
+    match keyword(last) {
+        Some(loading) => (Some(loading), rest.trim_end()),
+        None => (None, spec),
+    }
+}
+
+/// `loading`, `decoding` and `fetchpriority` attributes of an `<img>`
+///
+/// `keyword` is the `{eager}` / `{lazy}` override of the image; an eager
+/// override also raises the fetch priority.
+fn image_loading_attrs(images: &ImageOptions, keyword: Option<ImageLoading>) -> String {
+    let loading = keyword.unwrap_or(images.loading);
+    let fetch_priority = match keyword {
+        Some(ImageLoading::Eager) => Some(FetchPriority::High),
+        _ => images.fetch_priority,
+    };
+    let mut attrs = String::from(match loading {
+        ImageLoading::Lazy => " loading=\"lazy\"",
+        ImageLoading::Eager => " loading=\"eager\"",
+    });
+    attrs.push_str(match images.decoding {
+        Some(ImageDecoding::Async) => " decoding=\"async\"",
+        Some(ImageDecoding::Sync) => " decoding=\"sync\"",
+        Some(ImageDecoding::Auto) => " decoding=\"auto\"",
+        None => "",
+    });
+    attrs.push_str(match fetch_priority {
+        Some(FetchPriority::High) => " fetchpriority=\"high\"",
+        Some(FetchPriority::Low) => " fetchpriority=\"low\"",
+        Some(FetchPriority::Auto) => " fetchpriority=\"auto\"",
+        None => "",
+    });
+    attrs
+}
+
+/// Media element with one `<source>` per `(url, mime type)` entry of
+/// `sources` (in order of preference); the last source is used for the
+/// fallback link and `<img>`
+///
+/// Videos get a `<track>` only for each caption source in `attributes`.
+fn generate_media_html_with_sources(
+    sources: &[(&str, String)],
+    attributes: &MediaAttributes,
+    alt: &str,
+    title: Option<&str>,
+    media_type: &MediaType,
+    icons: &Icons,
+    locale: &Locale,
+) -> String {
+    let captions = &attributes.captions;
+    let url = sources.last().map_or("", |(url, _)| *url);
+    let source_tags = |attribute: &str| {
+        sources
+            .iter()
+            .map(|(source, mime_type)| {
+                format!(
+                    "  <source {}=\"{}\" type=\"{}\" />\n",
+                    attribute,
+                    url_attr(source),
+                    mime_type
+                )
+            })
+            .collect::<String>()
+    };
+    let download_label =
+        |name: &str| attr_escape(&locale.download.replace("{name}", name)).to_string();
     let title_attr = title
-        .map(|t| format!(" title=\"{}\"", escape_html(t)))
+        .map(|t| format!(" title=\"{}\"", attr_escape(t)))
         .unwrap_or_default();
 
     match media_type {
         MediaType::Video => {
-            let track_label = escape_html(alt);
+            let tracks = captions
+                .iter()
+                .map(|track| {
+                    format!(
+                        "  <track kind=\"captions\" src=\"{}\"{}{} />\n",
+                        url_attr(&track.src),
+                        track
+                            .lang
+                            .as_ref()
+                            .map(|lang| format!(" srclang=\"{}\"", attr_escape(lang)))
+                            .unwrap_or_default(),
+                        track
+                            .label
+                            .as_ref()
+                            .map(|label| format!(" label=\"{}\"", attr_escape(label)))
+                            .unwrap_or_default()
+                    )
+                })
+                .collect::<String>();
             let display_text = if alt.is_empty() { url } else { alt };
             format!(
-                "<video controls{}>\n  <source src=\"{}\" type=\"{}\" />\n  <track kind=\"captions\" label=\"{}\" />\n  <a href=\"{}\" download class=\"download-link video-fallback\">{} {}</a>\n</video>",
+                "<video controls{}>\n{}{}  <a href=\"{}\" download class=\"download-link video-fallback\" aria-label=\"{}\">{} {}</a>\n</video>",
                 title_attr,
-                escape_html(url),
-                mime_type,
-                track_label,
-                escape_html(url),
+                source_tags("src"),
+                tracks,
+                attr_escape(url),
+                download_label(display_text),
                 icons.video,
-                escape_html(display_text)
+                text_escape(display_text)
             )
         }
         MediaType::Audio => {
             let display_text = if alt.is_empty() { url } else { alt };
             format!(
-                "<audio controls{}>\n  <source src=\"{}\" type=\"{}\" />\n  <a href=\"{}\" download class=\"download-link audio-fallback\">{} {}</a>\n</audio>",
+                "<audio controls{}>\n{}  <a href=\"{}\" download class=\"download-link audio-fallback\" aria-label=\"{}\">{} {}</a>\n</audio>",
                 title_attr,
-                escape_html(url),
-                mime_type,
-                escape_html(url),
+                source_tags("src"),
+                attr_escape(url),
+                download_label(display_text),
                 icons.audio,
-                escape_html(display_text)
+                text_escape(display_text)
             )
         }
         MediaType::Image => {
             let img_title = title
-                .map(|t| format!(" title=\"{}\"", escape_html(t)))
+                .map(|t| format!(" title=\"{}\"", attr_escape(t)))
                 .unwrap_or_default();
             format!(
-                "<picture{}>\n  <source srcset=\"{}\" type=\"{}\" />\n  <img src=\"{}\" alt=\"{}\" loading=\"lazy\" class=\"img-fluid\"{} />\n</picture>",
+                "<picture{}>\n{}  <img src=\"{}\" alt=\"{}\"{} class=\"img-fluid\"{} />\n</picture>",
                 title_attr,
-                escape_html(url),
-                mime_type,
-                escape_html(url),
-                escape_html(alt),
+                source_tags("srcset"),
+                attr_escape(url),
+                attr_escape(alt),
+                attributes.img_attrs,
                 img_title
             )
         }
         MediaType::Downloadable => {
             let display_text = if alt.is_empty() { url } else { alt };
             format!(
-                "<a href=\"{}\" download class=\"download-link\"{}>\n  {} {}\n</a>",
-                escape_html(url),
+                "<a href=\"{}\" download class=\"download-link\"{} aria-label=\"{}\">\n  {} {}\n</a>",
+                attr_escape(url),
                 title_attr,
+                download_label(display_text),
                 icons.download,
-                escape_html(display_text)
+                text_escape(display_text)
             )
         }
     }
 }
 
-/// Escape HTML special characters
-fn escape_html(input: &str) -> String {
-    input
-        .replace('&', "&amp;")
-        .replace('<', "&lt;")
-        .replace('>', "&gt;")
-        .replace('"', "&quot;")
-        .replace('\'', "&#x27;")
-}
+/// `<img>` tag with src and alt attributes, followed by an optional
+/// `{key=value ...}` attribute block
+static MEDIA_IMG: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"<img\s+src="([^"]+)"(?:\s+alt="([^"]*)")?(?:\s+title="([^"]*)")?\s*/>(?:\{([^{}\n]*)\})?"#,
+    )
+    .unwrap()
+});
+
+/// Paragraph consisting only of a media element
+static MEDIA_ONLY_PARAGRAPH: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?s)<p>\s*(<picture[\s\S]*?</picture>|<video[\s\S]*?</video>|<audio[\s\S]*?</audio>|<a href="[^"]+" download class="download-link[^"]*"[^>]*>[\s\S]*?</a>)\s*</p>"#,
+    )
+    .unwrap()
+});
 
 /// Transform image tags to media tags based on file extension
 ///
@@ -298,65 +552,237 @@ fn escape_html(input: &str) -> String {
 /// let result = transform_images_to_media(html, &Icons::default(), false);
 /// assert!(result.contains("<video"));
 /// ```
-pub fn transform_images_to_media(
-    html: &str,
-    icons: &crate::parser::Icons,
+pub fn transform_images_to_media<'a>(
+    html: &'a str,
+    icons: &Icons,
     allow_fragment_extension_hint: bool,
-) -> String {
-    use regex::Regex;
+) -> Cow<'a, str> {
+    transform_media(
+        html,
+        icons,
+        &Locale::default(),
+        &ImageOptions::default(),
+        &MediaOptions::default(),
+        allow_fragment_extension_hint,
+    )
+}
 
-    // Pattern to match <img> tags with src and alt attributes
-    let img_re =
-        Regex::new(r#"<img\s+src="([^"]+)"(?:\s+alt="([^"]*)")?(?:\s+title="([^"]*)")?\s*/>"#)
-            .unwrap();
+/// [`transform_images_to_media`] with the icons, locale, image loading
+/// hints, media type mappings and extension hint setting of `options`
+pub fn transform_images_to_media_with_options<'a>(
+    html: &'a str,
+    options: &ParserOptions,
+) -> Cow<'a, str> {
+    transform_media(
+        html,
+        &options.icons,
+        &options.locale,
+        &options.images,
+        &options.media,
+        options.allow_fragment_extension_hint,
+    )
+}
 
-    let transformed = img_re
-        .replace_all(html, |caps: &regex::Captures| {
-            let url = caps.get(1).map_or("", |m| m.as_str());
-            let alt = caps.get(2).map_or("", |m| m.as_str());
-            let title = caps.get(3).map(|m| m.as_str());
-
-            // Detect media type and generate appropriate HTML
-            if let Some(media_type) =
-                detect_media_type_with_hint(url, allow_fragment_extension_hint)
-            {
-                generate_media_html_with_hint(
-                    url,
-                    alt,
-                    title,
-                    &media_type,
-                    icons,
-                    allow_fragment_extension_hint,
-                )
+fn transform_media<'a>(
+    html: &'a str,
+    icons: &Icons,
+    locale: &Locale,
+    images: &ImageOptions,
+    media: &MediaOptions,
+    allow_fragment_extension_hint: bool,
+) -> Cow<'a, str> {
+    let transformed = MEDIA_IMG.replace_all(html, |caps: &regex::Captures| {
+        let url = caps.get(1).map_or("", |m| m.as_str());
+        let alt = caps.get(2).map_or("", |m| m.as_str());
+        let title = caps.get(3).map(|m| m.as_str());
+        let spec = caps.get(4).map(|m| m.as_str());
+
+        // `{eager}` / `{lazy}` only apply to images; an emptied block is dropped
+        let media_type = detect_mapped_media_type(url, media, allow_fragment_extension_hint);
+        let (keyword, spec) = match spec {
+            Some(spec) if media_type.as_ref().is_none_or(|t| *t == MediaType::Image) => {
+                match split_loading_keyword(spec) {
+                    (Some(keyword), rest) => (Some(keyword), Some(rest).filter(|r| !r.is_empty())),
+                    (None, _) => (None, Some(spec)),
+                }
+            }
+            _ => (None, spec),
+        };
+        let loading_attrs = image_loading_attrs(images, keyword);
+
+        // Detect media type and generate appropriate HTML
+        if let Some(media_type) = media_type {
+            let attributes = if media_type == MediaType::Downloadable {
+                None
             } else {
-                // Not a recognized media file, wrap in <picture> tag anyway
-                let title_attr = title
-                    .map(|t| format!(" title=\"{}\"", t))
-                    .unwrap_or_default();
-                let img_title = title
-                    .map(|t| format!(" title=\"{}\"", t))
-                    .unwrap_or_default();
-                format!(
-                    "<picture{}>\n  <img src=\"{}\" alt=\"{}\" loading=\"lazy\" class=\"img-fluid\"{} />\n</picture>",
-                    title_attr, url, alt, img_title
-                )
+                spec.and_then(parse_media_attributes)
+            };
+            // Otherwise a `{#id .class key=value}` block applies to an image
+            let block = match (spec, &attributes) {
+                (Some(spec), None) if media_type == MediaType::Image => parse_attributes(spec),
+                _ => None,
+            };
+            let trailing = match (spec, &attributes, &block) {
+                (Some(spec), None, None) => format!("{{{}}}", spec),
+                _ => String::new(),
+            };
+            let attributes = MediaAttributes {
+                img_attrs: loading_attrs,
+                ..attributes.unwrap_or_default()
+            };
+            let sources: Vec<(&str, String)> =
+                split_sources(url, &media_type, media, allow_fragment_extension_hint)
+                    .into_iter()
+                    .chain(attributes.fallback.iter().map(String::as_str))
+                    .map(|source| {
+                        (
+                            source,
+                            get_mime_type_with_hint(
+                                source,
+                                &media_type,
+                                media,
+                                allow_fragment_extension_hint,
+                            ),
+                        )
+                    })
+                    .collect();
+
+            let html = generate_media_html_with_sources(
+                &sources,
+                &attributes,
+                alt,
+                title,
+                &media_type,
+                icons,
+                locale,
+            );
+            match block {
+                Some(block) => merge_media_attributes(&html, &block),
+                None => html + &trailing,
             }
-        })
-        .to_string();
+        } else {
+            // Not a recognized media file, wrap in <picture> tag anyway
+            let title_attr = title
+                .map(|t| format!(" title=\"{}\"", attr_escape(t)))
+                .unwrap_or_default();
+            let html = format!(
+                "<picture{}>\n  <img src=\"{}\" alt=\"{}\"{} class=\"img-fluid\"{} />\n</picture>",
+                title_attr,
+                attr_escape(url),
+                attr_escape(alt),
+                loading_attrs,
+                title_attr
+            );
+            match spec {
+                Some(spec) => match parse_attributes(spec) {
+                    Some(block) => merge_media_attributes(&html, &block),
+                    None => html + &format!("{{{}}}", spec),
+                },
+                None => html,
+            }
+        }
+    });
 
     // Block media: if a paragraph consists only of a media element,
     // treat it as block-level output and wrap with <figure>.
     // Inline media inside text remains unchanged.
-    let media_only_paragraph = Regex::new(
-        r#"(?s)<p>\s*(<picture[\s\S]*?</picture>|<video[\s\S]*?</video>|<audio[\s\S]*?</audio>|<a href="[^"]+" download class="download-link[^"]*"[^>]*>[\s\S]*?</a>)\s*</p>"#,
-    )
-    .unwrap();
-
-    media_only_paragraph
+    if let Cow::Owned(wrapped) = MEDIA_ONLY_PARAGRAPH
         .replace_all(&transformed, |caps: &regex::Captures| {
             format!("<figure class=\"w-100\">\n{}\n</figure>", &caps[1])
         })
-        .to_string()
+    {
+        return Cow::Owned(wrapped);
+    }
+    transformed
+}
+
+/// Merge an attribute block into the `<img>` of a picture
+fn merge_media_attributes(html: &str, block: &Attributes) -> String {
+    let Some(start) = html.find("<img ") else {
+        return html.to_string();
+    };
+    let Some(name_len) = html[start..].find([' ', '>']) else {
+        return html.to_string();
+    };
+    let name_end = start + name_len;
+    let tag_end = name_end + html[name_end..].find('>').unwrap_or(0);
+    let attrs_end = if html[..tag_end].ends_with(" /") {
+        tag_end - 2
+    } else {
+        tag_end
+    };
+
+    let mut attrs = html[name_end..attrs_end].to_string();
+    block.merge_into(&mut attrs);
+    format!("{}{}{}", &html[..name_end], attrs, &html[attrs_end..])
+}
+
+/// An image referenced by a document, e.g. for `og:image` cards
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaRef {
+    /// Image URL (HTML entities decoded)
+    pub src: String,
+    /// Alternative text (empty when not given)
+    pub alt: String,
+    /// Title attribute, if any
+    pub title: Option<String>,
+}
+
+static IMG_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<img\s[^>]*>").unwrap());
+static IMG_ATTRIBUTE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"\s(src|alt|title)="([^"]*)""#).unwrap());
+
+/// Cover image of a document
+///
+/// A frontmatter `cover:` value takes precedence over the first `<img>` of
+/// the rendered HTML. A root-relative cover is resolved against `base_url`
+/// like the links in the body.
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::media::first_image;
+///
+/// let html = r#"<picture><img src="/a.png" alt="A" loading="lazy" /></picture>"#;
+/// let image = first_image(html, None, None).unwrap();
+/// assert_eq!(image.src, "/a.png");
+/// assert_eq!(image.alt, "A");
+/// ```
+pub fn first_image(
+    html: &str,
+    frontmatter: Option<&Frontmatter>,
+    base_url: Option<&str>,
+) -> Option<MediaRef> {
+    if let Some(cover) = frontmatter.and_then(|frontmatter| frontmatter.get_scalar("cover")) {
+        let src = match base_url {
+            Some(base_url) if cover.starts_with('/') && !cover.starts_with("//") => {
+                format!("{}{}", base_url.trim_end_matches('/'), cover)
+            }
+            _ => cover,
+        };
+        return Some(MediaRef {
+            src,
+            alt: String::new(),
+            title: None,
+        });
+    }
+
+    IMG_TAG.find_iter(html).find_map(|tag| {
+        let mut image = MediaRef {
+            src: String::new(),
+            alt: String::new(),
+            title: None,
+        };
+        for caps in IMG_ATTRIBUTE.captures_iter(tag.as_str()) {
+            let value = html_escape::decode_html_entities(&caps[2]).into_owned();
+            match &caps[1] {
+                "src" => image.src = value,
+                "alt" => image.alt = value,
+                _ => image.title = Some(value),
+            }
+        }
+        (!image.src.is_empty()).then_some(image)
+    })
 }
 
 #[cfg(test)]
@@ -531,10 +957,10 @@ mod tests {
         assert!(html.contains("title=\"Product demo\""));
         assert!(html.contains("src=\"video.mp4\""));
         assert!(html.contains("type=\"video/mp4\""));
-        assert!(html.contains("<track kind=\"captions\" label=\"Demo video\""));
+        assert!(!html.contains("<track"));
         // Check for download fallback
         assert!(
-            html.contains("<a href=\"video.mp4\" download class=\"download-link video-fallback\">")
+            html.contains("<a href=\"video.mp4\" download class=\"download-link video-fallback\" aria-label=\"Download Demo video\">")
         );
         assert!(html.contains(
             r#"<span class="bi bi-camera-video-fill" aria-hidden="true"></span> Demo video"#
@@ -556,7 +982,7 @@ mod tests {
         assert!(html.contains("type=\"audio/mpeg\""));
         // Check for download fallback
         assert!(
-            html.contains("<a href=\"audio.mp3\" download class=\"download-link audio-fallback\">")
+            html.contains("<a href=\"audio.mp3\" download class=\"download-link audio-fallback\" aria-label=\"Download Background music\">")
         );
         assert!(html.contains(
             r#"<span class="bi bi-music-note-beamed" aria-hidden="true"></span> Background music"#
@@ -698,6 +1124,17 @@ mod tests {
         assert!(!html.contains("bi-file-earmark-arrow-down-fill"));
     }
 
+    #[test]
+    fn test_localized_download_label() {
+        let options = ParserOptions {
+            locale: Locale::ja(),
+            ..ParserOptions::default()
+        };
+        let html = r#"<img src="report.pdf" alt="報告書" />"#;
+        let transformed = transform_images_to_media_with_options(html, &options);
+        assert!(transformed.contains(r#"aria-label="報告書 をダウンロード""#));
+    }
+
     #[test]
     fn test_transform_media_paragraph_to_figure() {
         let html = r#"<p><img src="image.png" alt="alt" title="Title" /></p>"#;
@@ -724,4 +1161,118 @@ mod tests {
         assert!(transformed.contains("<picture"));
         assert!(transformed.contains("type=\"image/png\""));
     }
+
+    #[test]
+    fn test_transform_multiple_sources() {
+        let icons = crate::parser::Icons::default();
+        let html = r#"<p><img src="clip.webm%7Cclip.mp4" alt="demo" /></p>"#;
+        let transformed = transform_images_to_media(html, &icons, false);
+        let webm = transformed
+            .find(r#"<source src="clip.webm" type="video/webm" />"#)
+            .unwrap();
+        let mp4 = transformed
+            .find(r#"<source src="clip.mp4" type="video/mp4" />"#)
+            .unwrap();
+        assert!(webm < mp4);
+        assert!(transformed.contains(r#"<a href="clip.mp4" download"#));
+
+        let html = r#"<p><img src="a.ogg" alt="song" />{fallback=&quot;a.mp3|a.m4a&quot;}</p>"#;
+        let transformed = transform_images_to_media(html, &icons, false);
+        assert!(transformed.contains(r#"<source src="a.mp3" type="audio/mpeg" />"#));
+        assert!(transformed.contains(r#"<source src="a.m4a" type="audio/mp4" />"#));
+        assert!(!transformed.contains("{fallback"));
+        assert!(transformed.contains(r#"<figure class="w-100">"#));
+
+        // Unknown attributes and mixed media types are left alone
+        let html = r#"<p><img src="a.png%7Cb.mp4" alt="x" />{width=3}</p>"#;
+        let transformed = transform_images_to_media(html, &icons, false);
+        assert!(transformed.contains(r#"<source src="a.png%7Cb.mp4""#));
+        assert!(transformed.contains("{width=3}"));
+    }
+
+    #[test]
+    fn test_transform_image_attribute_block() {
+        let icons = crate::parser::Icons::default();
+        let html = r#"<p>A <img src="a.png" alt="x" />{#logo .rounded title=Logo}</p>"#;
+        let transformed = transform_images_to_media(html, &icons, false);
+        assert!(transformed.contains(
+            r#"<img src="a.png" alt="x" loading="lazy" class="img-fluid rounded" id="logo" title="Logo" />"#
+        ));
+        assert!(!transformed.contains('{'));
+    }
+
+    #[test]
+    fn test_transform_image_loading() {
+        let html = r#"<p><img src="hero.jpg" alt="x" />{eager .hero} <img src="b" alt="" />{lazy} <img src="c.mp3" alt="" />{eager}</p>"#;
+        let transformed = transform_images_to_media(html, &Icons::default(), false);
+        assert!(transformed.contains(
+            r#"<img src="hero.jpg" alt="x" loading="eager" fetchpriority="high" class="img-fluid hero" />"#
+        ));
+        assert!(transformed.contains(r#"<img src="b" alt="" loading="lazy" class="img-fluid" />"#));
+        assert!(transformed.contains("</audio>{eager}"));
+
+        let options = ParserOptions {
+            images: ImageOptions {
+                loading: ImageLoading::Eager,
+                decoding: Some(ImageDecoding::Async),
+                fetch_priority: Some(FetchPriority::Low),
+            },
+            ..Default::default()
+        };
+        let html = r#"<p><img src="a.png" alt="x" />{#a lazy}</p>"#;
+        assert!(
+            transform_images_to_media_with_options(html, &options).contains(
+                r#"<img src="a.png" alt="x" loading="lazy" decoding="async" fetchpriority="low" class="img-fluid" id="a" />"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_transform_mapped_media_types() {
+        let mut options = ParserOptions::default();
+        options
+            .media
+            .types
+            .insert("heic".to_string(), MediaType::Image);
+        options
+            .media
+            .types
+            .insert("ipynb".to_string(), MediaType::Downloadable);
+        options
+            .media
+            .mime_types
+            .insert("heic".to_string(), "image/heic".to_string());
+        options
+            .media
+            .mime_types
+            .insert("mp4".to_string(), "video/mp4; codecs=avc1".to_string());
+
+        let html = r#"<p><img src="a.heic|b.jpg" alt="P" /> <img src="nb.ipynb" alt="N" /> <img src="c.mp4" alt="" /></p>"#;
+        let transformed = transform_images_to_media_with_options(html, &options);
+        assert!(transformed.contains(r#"<source srcset="a.heic" type="image/heic" />"#));
+        assert!(transformed.contains(r#"<source srcset="b.jpg" type="image/jpeg" />"#));
+        assert!(transformed.contains(r#"<a href="nb.ipynb" download class="download-link""#));
+        assert!(transformed.contains(r#"type="video/mp4; codecs=avc1""#));
+
+        let transformed = transform_images_to_media(html, &Icons::default(), false);
+        assert!(!transformed.contains("image/heic"));
+        assert!(transformed.contains(r#"<img src="nb.ipynb" alt="N""#));
+    }
+
+    #[test]
+    fn test_transform_caption_tracks() {
+        let icons = crate::parser::Icons::default();
+        let html = r#"<p><img src="clip.mp4" alt="demo" />{captions=/subs/en.vtt lang=en label=&quot;English US&quot;}</p>"#;
+        let transformed = transform_images_to_media(html, &icons, false);
+        assert!(transformed.contains(
+            r#"<track kind="captions" src="/subs/en.vtt" srclang="en" label="English US" />"#
+        ));
+        assert_eq!(transformed.matches("<track").count(), 1);
+
+        // `lang` without a preceding `captions` is not a media attribute
+        let html = r#"<p><img src="clip.mp4" alt="demo" />{lang=en}</p>"#;
+        let transformed = transform_images_to_media(html, &icons, false);
+        assert!(!transformed.contains("<track"));
+        assert!(transformed.contains("{lang=en}"));
+    }
 }
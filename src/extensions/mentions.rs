@@ -0,0 +1,188 @@
+//! Mention (`@username`) and hashtag (`#tag`) links
+//!
+//! Both are opt-in: mentions are linked only when
+//! [`ParserOptions::mention_base`](crate::parser::ParserOptions::mention_base)
+//! is set, hashtags only when
+//! [`ParserOptions::tag_base`](crate::parser::ParserOptions::tag_base) is set.
+//!
+//! Disambiguation rules:
+//!
+//! - `@name(...)`, `@name{...}` and `@name;` are plugin syntax and are never
+//!   treated as mentions
+//! - `@` / `#` must start a word, so mail addresses (`user@example.com`),
+//!   `C#`, entities (`&#39;`) and `[#id]` / `[[#id]]` references are ignored
+//! - headings need a space after `#` (`# Title`), so `#tag` at the start of a
+//!   line stays a hashtag paragraph
+//! - hashtags need at least one letter and may not look like a hex color
+//!   (`#123`, `#fff`, `#ff8800` are left alone)
+//! - links, code, plugin templates and SVG are skipped
+
+use std::borrow::Cow;
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use super::autolink::map_text_nodes;
+use crate::site::percent_encode;
+
+/// Regex to detect `@username` at a word boundary
+static MENTION: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?P<pre>^|[\s(（「『、。,])@(?P<name>[A-Za-z0-9_](?:[A-Za-z0-9_.-]*[A-Za-z0-9_])?)",
+    )
+    .unwrap()
+});
+
+/// Regex to detect `#tag` at a word boundary
+static HASHTAG: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?P<pre>^|[\s(（「『、。,])#(?P<tag>[\p{L}\p{N}_]+)").unwrap());
+
+/// Regex to detect hex color codes (`#rgb`, `#rgba`, `#rrggbb`, `#rrggbbaa`)
+static HEX_COLOR: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?:[0-9a-fA-F]{3,4}|[0-9a-fA-F]{6}|[0-9a-fA-F]{8})$").unwrap());
+
+/// Whether the text after a match turns it into plugin syntax or a longer token
+fn is_continued(text: &str, end: usize) -> bool {
+    let mut following = text[end..].chars();
+    match following.next() {
+        Some('(' | '{' | '[' | ';' | '@') => true,
+        Some('.') => following.next().is_some_and(|c| c.is_alphanumeric()),
+        _ => false,
+    }
+}
+
+fn link_mentions(text: &str, base: &str) -> String {
+    MENTION
+        .replace_all(text, |caps: &Captures| {
+            let end = caps.get(0).map_or(0, |m| m.end());
+            if is_continued(text, end) {
+                return caps[0].to_string();
+            }
+            let name = &caps["name"];
+            format!(
+                "{}<a class=\"mention\" href=\"{}/{}\">@{}</a>",
+                &caps["pre"],
+                base,
+                percent_encode(name),
+                name
+            )
+        })
+        .to_string()
+}
+
+fn link_hashtags(text: &str, base: &str) -> String {
+    HASHTAG
+        .replace_all(text, |caps: &Captures| {
+            let tag = &caps["tag"];
+            let end = caps.get(0).map_or(0, |m| m.end());
+            if is_continued(text, end)
+                || HEX_COLOR.is_match(tag)
+                || !tag.chars().any(char::is_alphabetic)
+            {
+                return caps[0].to_string();
+            }
+            format!(
+                "{}<a class=\"hashtag\" href=\"{}/{}\">#{}</a>",
+                &caps["pre"],
+                base,
+                percent_encode(tag),
+                tag
+            )
+        })
+        .to_string()
+}
+
+/// Link mentions and hashtags in text content
+///
+/// # Arguments
+///
+/// * `html` - The HTML to process (code sections should be protected)
+/// * `mention_base` - URL prefix for mentions; `None` disables mentions
+/// * `tag_base` - URL prefix for hashtags; `None` disables hashtags
+///
+/// # Returns
+///
+/// HTML with mentions and hashtags linked
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::mentions::apply_mentions;
+///
+/// let output = apply_mentions("<p>Thanks @alice! #release</p>", Some("/users"), Some("/tags"));
+/// assert_eq!(
+///     output,
+///     r#"<p>Thanks <a class="mention" href="/users/alice">@alice</a>! <a class="hashtag" href="/tags/release">#release</a></p>"#
+/// );
+/// ```
+pub fn apply_mentions<'a>(
+    html: &'a str,
+    mention_base: Option<&str>,
+    tag_base: Option<&str>,
+) -> Cow<'a, str> {
+    if mention_base.is_none() && tag_base.is_none() {
+        return Cow::Borrowed(html);
+    }
+    let mention_base = mention_base.map(|base| base.trim_end_matches('/'));
+    let tag_base = tag_base.map(|base| base.trim_end_matches('/'));
+
+    map_text_nodes(html, |text| {
+        let mut text = text.to_string();
+        if let Some(base) = mention_base {
+            text = link_mentions(&text, base);
+        }
+        if let Some(base) = tag_base {
+            text = link_hashtags(&text, base);
+        }
+        text
+    })
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(html: &str) -> String {
+        apply_mentions(html, Some("https://example.com/u/"), Some("/tags")).into_owned()
+    }
+
+    #[test]
+    fn test_disabled_by_default() {
+        let html = "<p>@alice #news</p>";
+        assert_eq!(apply_mentions(html, None, None), html);
+    }
+
+    #[test]
+    fn test_mention_linked() {
+        assert_eq!(
+            apply("<p>cc @alice.b, @bob_2.</p>"),
+            r#"<p>cc <a class="mention" href="https://example.com/u/alice.b">@alice.b</a>, <a class="mention" href="https://example.com/u/bob_2">@bob_2</a>.</p>"#
+        );
+    }
+
+    #[test]
+    fn test_plugin_syntax_and_emails_ignored() {
+        let html = "<p>@youtube(id) @box{x} @toc; mail user@example.com</p>";
+        assert_eq!(apply(html), html);
+    }
+
+    #[test]
+    fn test_hashtag_unicode() {
+        assert!(apply("<p>#日本語 タグ</p>").contains(
+            r#"<a class="hashtag" href="/tags/%E6%97%A5%E6%9C%AC%E8%AA%9E">#日本語</a>"#
+        ));
+    }
+
+    #[test]
+    fn test_hashtag_exclusions() {
+        let html = "<p>C# issue #123 color #fff &#39;x&#39; [[#anchor]] (#ff8800)</p>";
+        assert_eq!(apply(html), html);
+    }
+
+    #[test]
+    fn test_links_and_code_untouched() {
+        let html = r#"<p><a href="/x">@alice #tag</a> <code>@alice #tag</code></p>"#;
+        assert_eq!(apply(html), html);
+    }
+}
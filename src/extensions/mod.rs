@@ -3,17 +3,50 @@
 //! This module provides extended syntax support including Bootstrap 5 integration,
 //! semantic HTML elements, definition lists, and LukiWiki legacy compatibility.
 
+pub mod accessibility;
+pub mod anchors;
+pub mod aria;
+pub mod attachments;
+pub mod attributes;
+pub mod autolink;
 pub mod block_decorations;
+pub mod class_map;
 pub mod code_block;
+pub mod color_mode;
+pub mod color_utilities;
 pub mod conflict_resolver;
+pub mod cross_reference;
+pub mod detect;
+pub mod email;
+pub mod embeds;
 pub mod emphasis;
+pub mod epub;
+pub mod external_links;
+pub mod font_size;
+pub mod footnotes;
+pub mod framework;
+pub mod ids;
 pub mod inline_decorations;
+pub mod line_anchors;
 pub mod media;
+pub mod mentions;
 pub mod nested_blocks;
+pub mod output_format;
 pub mod plugin_markers;
+pub mod plugin_signatures;
 pub mod plugins;
 pub mod preprocessor;
+pub mod print;
+#[cfg(feature = "qr")]
+pub mod qr;
+pub mod serialization;
 pub mod table;
+pub mod toc;
+
+use std::borrow::Cow;
+
+use crate::diagnostics::Diagnostic;
+use crate::profiling::stage;
 
 /// Apply extended syntax transformations to HTML output
 ///
@@ -48,100 +81,370 @@ pub fn apply_extensions_with_headers(
     header_map: &conflict_resolver::HeaderIdMap,
     options: &crate::parser::ParserOptions,
 ) -> String {
+    apply_extensions_with_diagnostics(html, header_map, options).0
+}
+
+/// Apply extended syntax transformations and collect diagnostics
+///
+/// Same as [`apply_extensions_with_headers`], but also returns problems found
+/// while post-processing (duplicate heading IDs, unresolved cross-references).
+///
+/// # Arguments
+///
+/// * `html` - The HTML output from the Markdown parser
+/// * `header_map` - Map of custom header IDs
+/// * `options` - Parser configuration options
+///
+/// # Returns
+///
+/// A tuple of (transformed HTML, diagnostics)
+pub fn apply_extensions_with_diagnostics(
+    html: &str,
+    header_map: &conflict_resolver::HeaderIdMap,
+    options: &crate::parser::ParserOptions,
+) -> (String, Vec<Diagnostic>) {
+    apply_extensions_with_syntax(html, header_map, options, true)
+}
+
+/// [`apply_extensions_with_diagnostics`], skipping the passes that only
+/// handle UMD syntax unless `umd_syntax` (see [`detect::has_umd_syntax`])
+pub(crate) fn apply_extensions_with_syntax(
+    html: &str,
+    header_map: &conflict_resolver::HeaderIdMap,
+    options: &crate::parser::ParserOptions,
+    umd_syntax: bool,
+) -> (String, Vec<Diagnostic>) {
     let mut result = html.to_string();
+    let mut diagnostics = Vec::new();
 
     // Protect code blocks and inline code from transformation
-    let (protected, placeholders) = protect_code_sections(&result);
-    result = protected;
+    let mut placeholders = Vec::new();
+    result = stage("extensions.protect_code", || {
+        protect_code_sections(&result, &mut placeholders)
+    });
+
+    // Plugin markers still hold the arguments as written
+    if umd_syntax && !options.plugin_signatures.is_empty() {
+        diagnostics.extend(stage("extensions.plugin_signatures", || {
+            plugin_signatures::validate_plugins(&result, options)
+        }));
+    }
 
     // Apply transformations in order
     // Note: Plugins are handled in conflict_resolver::postprocess_conflicts
-    result = media::transform_images_to_media(
-        &result,
-        &options.icons,
-        options.allow_fragment_extension_hint,
-    );
-    result = conflict_resolver::postprocess_conflicts(&result, header_map);
-    result = emphasis::apply_umd_emphasis(&result);
-    result = block_decorations::apply_block_placement(&result); // Apply block placement first
-    result = block_decorations::apply_block_decorations(&result);
-    result = inline_decorations::apply_inline_decorations_with_limit(
-        &result,
-        options.max_inline_nesting.map(usize::from),
-    );
+    if let Cow::Owned(transformed) = stage("extensions.media", || {
+        media::transform_images_to_media_with_options(&result, options)
+    }) {
+        result = transformed;
+    }
+    result = stage("extensions.postprocess_conflicts", || {
+        conflict_resolver::postprocess_conflicts_with_syntax(
+            &result, header_map, options, umd_syntax,
+        )
+    });
+    if umd_syntax && (options.allowed_plugins.is_some() || !options.denied_plugins.is_empty()) {
+        diagnostics.extend(conflict_resolver::denied_plugin_diagnostics(&result));
+    }
+    // Inline plugins may have produced <code>/<kbd>/<samp>
+    result = stage("extensions.protect_code", || {
+        protect_code_sections(&result, &mut placeholders)
+    });
+    let (deduplicated, id_diagnostics) = stage("extensions.deduplicate_ids", || {
+        conflict_resolver::deduplicate_ids(&result)
+    });
+    if let Cow::Owned(deduplicated) = deduplicated {
+        result = deduplicated;
+    }
+    diagnostics.extend(id_diagnostics);
+    if umd_syntax
+        && options.lukiwiki_enabled()
+        && let Cow::Owned(emphasized) = stage("extensions.emphasis", || {
+            emphasis::apply_umd_emphasis_with_tags(&result, options.emphasis_tags)
+        })
+    {
+        result = emphasized;
+    }
+    if umd_syntax {
+        stage("extensions.block_decorations", || {
+            // Apply block placement first
+            if let Cow::Owned(placed) = block_decorations::apply_block_placement(&result) {
+                result = placed;
+                diagnostics.extend(block_decorations::float_diagnostics(&result));
+            }
+            if let Cow::Owned(decorated) = block_decorations::apply_block_decorations(&result) {
+                result = decorated;
+            } else {
+                // Undecorated HTML is only trimmed
+                result.truncate(result.trim_end().len());
+            }
+        });
+        result = stage("extensions.inline_decorations", || {
+            inline_decorations::apply_inline_decorations_with_options(
+                &result,
+                options.max_inline_nesting.map(usize::from),
+                options.lukiwiki_enabled(),
+            )
+        });
+        result = stage("extensions.protect_code", || {
+            protect_code_sections(&result, &mut placeholders)
+        });
+    } else {
+        result.truncate(result.trim_end().len());
+    }
+
+    result = stage("extensions.autolink", || {
+        autolink::apply_autolinks(&result, &options.autolink)
+    });
+
+    // Resolve cross-references once heading IDs and titles are final
+    let (resolved, ref_diagnostics) = stage("extensions.cross_references", || {
+        cross_reference::resolve_cross_references(&result)
+    });
+    result = resolved;
+    diagnostics.extend(ref_diagnostics);
+
+    // Mentions/hashtags run after `[#id]` references have been turned into links
+    if let Cow::Owned(linked) = stage("extensions.mentions", || {
+        mentions::apply_mentions(
+            &result,
+            options.mention_base.as_deref(),
+            options.tag_base.as_deref(),
+        )
+    }) {
+        result = linked;
+    }
 
     // Apply base URL resolution to links
     if let Some(base_url) = &options.base_url {
-        result = conflict_resolver::apply_base_url_to_links(&result, base_url);
+        result = stage("extensions.base_url", || {
+            conflict_resolver::apply_base_url_to_links(&result, base_url)
+        });
+    }
+
+    // Every link is final, including badges and media download links
+    if let Some(external_links) = &options.external_links
+        && let Cow::Owned(marked) = stage("extensions.external_links", || {
+            external_links::apply_external_links(
+                &result,
+                options.site_origin.as_deref(),
+                external_links,
+                &options.icons.external_link,
+            )
+        })
+    {
+        result = marked;
+    }
+
+    if options.a11y_audit {
+        diagnostics.extend(stage("extensions.a11y_audit", || {
+            accessibility::audit(&result)
+        }));
     }
 
     // Restore protected code sections
-    restore_code_sections(&result, &placeholders, &options.icons.color_swatch)
+    let mut result = stage("extensions.code_blocks", || {
+        restore_code_sections(&result, &placeholders, options)
+    });
+
+    // Column types are inferred from the final cell text
+    if options.sortable_tables
+        && !options.print_mode
+        && options.profile != crate::parser::Profile::EmailSafe
+        && let Cow::Owned(sortable) = stage("extensions.sortable_tables", || {
+            table::sorting::apply_sortable_tables(&result)
+        })
+    {
+        result = sortable;
+    }
+    if let Cow::Owned(wrapped) = stage("extensions.responsive_tables", || {
+        table::responsive::apply_responsive_tables(&result, options.responsive_tables)
+    }) {
+        result = wrapped;
+    }
+
+    // Heading labels are derived from the text including restored inline code
+    if let Cow::Owned(with_toc) = stage("extensions.toc", || {
+        toc::apply_toc(&result, &options.locale)
+    }) {
+        result = with_toc;
+    }
+    if let Cow::Owned(labelled) = stage("extensions.aria", || {
+        aria::apply_aria(&result, &options.aria)
+    }) {
+        result = labelled;
+    }
+    if let Cow::Owned(anchored) = stage("extensions.anchors", || {
+        anchors::apply_anchor_style(&result, &options.anchors)
+    }) {
+        result = anchored;
+    }
+    let result = if options.profile == crate::parser::Profile::EmailSafe {
+        stage("extensions.email", || {
+            email::apply_email_profile(&result, &options.locale)
+        })
+    } else if options.print_mode {
+        stage("extensions.print", || print::apply_print_mode(&result))
+    } else {
+        result
+    };
+    (result, diagnostics)
+}
+
+/// Inline elements whose content is shown verbatim
+const VERBATIM_INLINE_ELEMENTS: &[&str] = &["code", "kbd", "samp"];
+
+/// Length of the tag name if `html` starts with an opening tag named `name`
+fn opening_tag_len(html: &str, name: &str) -> Option<usize> {
+    let rest = html.strip_prefix('<')?;
+    let after = rest.get(..name.len())?;
+    if !after.eq_ignore_ascii_case(name) {
+        return None;
+    }
+    match rest[name.len()..].chars().next() {
+        Some('>' | ' ' | '\t' | '\n' | '/') => Some(1 + name.len()),
+        _ => None,
+    }
+}
+
+/// Length of the element starting at `html`, including nested elements of
+/// the same name and the closing tag
+fn element_len(html: &str, name: &str) -> Option<usize> {
+    let close = format!("</{}>", name);
+    let mut depth = 0usize;
+    let mut pos = 0;
+    while let Some(offset) = html[pos..].find('<') {
+        pos += offset;
+        if opening_tag_len(&html[pos..], name).is_some() {
+            depth += 1;
+            pos += 1;
+        } else if html[pos..].starts_with(&close) {
+            depth -= 1;
+            pos += close.len();
+            if depth == 0 {
+                return Some(pos);
+            }
+        } else {
+            pos += 1;
+        }
+    }
+    None
+}
+
+/// Length of the opening tag and of the whole element if `html` starts with
+/// a plugin `<template>`
+fn plugin_template_len(html: &str) -> Option<(usize, usize)> {
+    opening_tag_len(html, "template")?;
+    let tag_len = html.find('>')? + 1;
+    if !html[..tag_len].contains("umd-plugin") {
+        return None;
+    }
+    Some((tag_len, element_len(html, "template")?))
 }
 
 /// Protect code blocks and inline code from transformation
 ///
-/// Returns the HTML with code sections replaced by placeholders,
-/// and a vector of the original code sections.
-fn protect_code_sections(html: &str) -> (String, Vec<String>) {
-    use regex::Regex;
+/// Scans for `<pre>` blocks and `<code>` / `<kbd>` / `<samp>` elements
+/// (including attributes, newlines and nested markup) and the content of
+/// plugin templates, and replaces them with placeholders appended to
+/// `placeholders`. May be called again after passes
+/// that emit such elements; already protected sections are left alone.
+fn protect_code_sections(html: &str, placeholders: &mut Vec<String>) -> String {
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
 
-    let mut placeholders = Vec::new();
-    let mut result = html.to_string();
+    while let Some(pos) = rest.find('<') {
+        result.push_str(&rest[..pos]);
+        rest = &rest[pos..];
 
-    // Protect <pre><code>...</code></pre> blocks
-    let code_block_re = Regex::new(r"<pre><code[^>]*>[\s\S]*?</code></pre>").unwrap();
-    result = code_block_re
-        .replace_all(&result, |caps: &regex::Captures| {
-            let index = placeholders.len();
-            placeholders.push(caps[0].to_string());
-            format!("<!--CODE_BLOCK_{}-->", index)
-        })
-        .to_string();
-
-    // Protect <code>...</code> inline
-    let inline_code_re = Regex::new(r"<code[^>]*>[^<]*</code>").unwrap();
-    result = inline_code_re
-        .replace_all(&result, |caps: &regex::Captures| {
-            let index = placeholders.len();
-            placeholders.push(caps[0].to_string());
-            format!("<!--INLINE_CODE_{}-->", index)
-        })
-        .to_string();
+        // The opening tag of a plugin template stays visible to block
+        // placement; its arguments and content are plugin source
+        if let Some((tag_len, len)) = plugin_template_len(rest) {
+            let body = &rest[tag_len..len - "</template>".len()];
+            result.push_str(&rest[..tag_len]);
+            if body.is_empty() || body.starts_with("<!--") {
+                result.push_str(body);
+            } else {
+                result.push_str(&format!("<!--INLINE_ELEMENT_{}-->", placeholders.len()));
+                placeholders.push(body.to_string());
+            }
+            result.push_str("</template>");
+            rest = &rest[len..];
+            continue;
+        }
 
-    (result, placeholders)
+        let protected = if opening_tag_len(rest, "pre").is_some() {
+            element_len(rest, "pre").map(|len| (len, "CODE_BLOCK"))
+        } else {
+            VERBATIM_INLINE_ELEMENTS.iter().find_map(|name| {
+                opening_tag_len(rest, name)?;
+                let kind = if *name == "code" {
+                    "INLINE_CODE"
+                } else {
+                    "INLINE_ELEMENT"
+                };
+                element_len(rest, name).map(|len| (len, kind))
+            })
+        };
+
+        match protected {
+            Some((len, kind)) => {
+                result.push_str(&format!("<!--{}_{}-->", kind, placeholders.len()));
+                placeholders.push(rest[..len].to_string());
+                rest = &rest[len..];
+            }
+            None => {
+                result.push('<');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
 }
 
 /// Restore protected code sections
 fn restore_code_sections(
     html: &str,
     placeholders: &[String],
-    color_swatch_icon_html: &str,
+    options: &crate::parser::ParserOptions,
 ) -> String {
+    use once_cell::sync::Lazy;
     use regex::Regex;
 
-    let mut result = html.to_string();
+    static PLACEHOLDER_RE: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"<!--(CODE_BLOCK|INLINE_CODE|INLINE_ELEMENT)_(\d+)-->")
+            .expect("valid code placeholder regex")
+    });
 
-    // Restore code blocks
-    let placeholder_re = Regex::new(r"<!--(CODE_BLOCK|INLINE_CODE)_(\d+)-->").unwrap();
-    result = placeholder_re
-        .replace_all(&result, |caps: &regex::Captures| {
-            let section_type = caps.get(1).map(|m| m.as_str()).unwrap_or("");
-            let index: usize = caps[2].parse().unwrap();
-            let original = placeholders.get(index).map(|s| s.as_str()).unwrap_or("");
+    let mut result = Cow::Borrowed(html);
 
-            if section_type == "INLINE_CODE" {
-                enhance_inline_code_color_sample(original, color_swatch_icon_html)
-            } else {
-                original.to_string()
-            }
-        })
-        .to_string();
+    // Restore code blocks (sections protected later may contain earlier placeholders)
+    for _ in 0..=placeholders.len() {
+        if !PLACEHOLDER_RE.is_match(&result) {
+            break;
+        }
+        if let Cow::Owned(replaced) =
+            PLACEHOLDER_RE.replace_all(&result, |caps: &regex::Captures| {
+                let section_type = caps.get(1).map(|m| m.as_str()).unwrap_or("");
+                let index: usize = caps[2].parse().unwrap_or(usize::MAX);
+                let original = placeholders.get(index).map(|s| s.as_str()).unwrap_or("");
 
-    // Apply code block enhancements (syntax highlighting, Mermaid, filenames)
-    result = code_block::process_code_blocks(&result);
+                if section_type == "INLINE_CODE" {
+                    enhance_inline_code_color_sample(original, &options.icons.color_swatch)
+                } else {
+                    original.to_string()
+                }
+            })
+        {
+            result = Cow::Owned(replaced);
+        }
+    }
 
-    result
+    // Apply code block enhancements (syntax highlighting, Mermaid, filenames)
+    if let Cow::Owned(enhanced) = code_block::process_code_blocks_with_options(&result, options) {
+        return enhanced;
+    }
+    result.into_owned()
 }
 
 fn enhance_inline_code_color_sample(
@@ -241,4 +544,23 @@ mod tests {
         let output = apply_extensions_with_headers(input, &header_map, &options);
         assert!(output.contains(r#"<span class="my-swatch-icon" aria-hidden="true"></span>"#));
     }
+
+    #[test]
+    fn test_protect_code_sections_scanner() {
+        let html = "<p><code class=\"x\">a\n''b''</code> <kbd><kbd>Ctrl</kbd>+<kbd>C</kbd></kbd> <samp>[#h]</samp> <codex>''c''</codex></p><pre>''d''</pre>";
+        let mut placeholders = Vec::new();
+        let protected = protect_code_sections(html, &mut placeholders);
+        assert_eq!(
+            protected,
+            "<p><!--INLINE_CODE_0--> <!--INLINE_ELEMENT_1--> <!--INLINE_ELEMENT_2--> <codex>''c''</codex></p><!--CODE_BLOCK_3-->"
+        );
+        assert_eq!(placeholders[1], "<kbd><kbd>Ctrl</kbd>+<kbd>C</kbd></kbd>");
+    }
+
+    #[test]
+    fn test_samp_and_kbd_contents_are_verbatim() {
+        let output =
+            apply_extensions("<p>{{INLINE_PLUGIN:samp::WyNoXSAnJ3gnJw==:INLINE_PLUGIN}}</p>");
+        assert!(output.contains("<samp>[#h] ''x''</samp>"), "{}", output);
+    }
 }
@@ -3,18 +3,28 @@
 //! CommonMark requires block elements inside list items to be indented. UMD allows
 //! blocks like tables and code fences immediately after a list item, so we
 //! normalize those blocks by adding indentation before comrak parses them.
+//! Blocks indented by less than the item content are shifted the same way,
+//! and block plugin bodies keep their indentation relative to the plugin.
+
+use std::borrow::Cow;
 
 use once_cell::sync::Lazy;
 use regex::Regex;
 
-static LIST_MARKER: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"^(?P<indent>[ \t]*)(?P<marker>(?:[-+*])|(?:\d+\.))\s+.+$").unwrap());
+static LIST_MARKER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(?P<indent>[ \t]*)(?P<marker>(?:[-+*])|(?:\d+\.))(?P<space>\s+).+$").unwrap()
+});
 
 static PLACEMENT_PREFIX: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"^(LEFT|CENTER|RIGHT|JUSTIFY):\s*$").unwrap());
 
 /// Preprocess list items so nested block elements are indented properly.
-pub fn preprocess_nested_blocks(input: &str) -> String {
+pub fn preprocess_nested_blocks(input: &str) -> Cow<'_, str> {
+    // Without list items only the line endings would change
+    if !input.contains('\r') && !input.lines().any(|line| list_indent_width(line).is_some()) {
+        return Cow::Borrowed(input.strip_suffix('\n').unwrap_or(input));
+    }
+
     let lines: Vec<&str> = input.lines().collect();
     let mut output: Vec<String> = Vec::with_capacity(lines.len());
     let mut i = 0;
@@ -23,6 +33,8 @@ pub fn preprocess_nested_blocks(input: &str) -> String {
         let line = lines[i];
 
         if let Some(list_indent) = list_indent_width(line) {
+            // Innermost item so far: (marker indent, content column)
+            let mut item = (list_indent, list_content_width(line));
             output.push(line.to_string());
             i += 1;
 
@@ -34,24 +46,41 @@ pub fn preprocess_nested_blocks(input: &str) -> String {
                     continue;
                 }
 
+                // Closing line of a multi-line UMD blockquote around the list
+                if next_line.trim() == "<" {
+                    break;
+                }
+
                 if let Some(next_indent) = list_indent_width(next_line) {
                     if next_indent <= list_indent {
                         break;
                     }
+                    item = (next_indent, list_content_width(next_line));
                     output.push(next_line.to_string());
                     i += 1;
                     continue;
                 }
 
                 let next_indent = indent_width(next_line);
-                if next_indent > list_indent {
+                let target_indent = (item.0 + 4).max(item.1);
+
+                // Plugin bodies are consumed whole, so their lines are never
+                // read as list items
+                if is_block_plugin_line(next_line) {
+                    i = indent_plugin_block(&lines, i, &mut output, target_indent);
+                    continue;
+                }
+
+                // Blocks indented less than the item content would leave the
+                // item, so they are shifted like unindented ones
+                if next_indent >= item.1
+                    || (next_indent > list_indent && !is_block_start(next_line))
+                {
                     output.push(next_line.to_string());
                     i += 1;
                     continue;
                 }
 
-                let target_indent = list_indent + 4;
-
                 if is_table_line(next_line) {
                     i = indent_table_block(&lines, i, &mut output, target_indent);
                     continue;
@@ -67,11 +96,6 @@ pub fn preprocess_nested_blocks(input: &str) -> String {
                     continue;
                 }
 
-                if is_block_plugin_line(next_line) {
-                    i = indent_plugin_block(&lines, i, &mut output, target_indent);
-                    continue;
-                }
-
                 if is_block_placement_prefix(next_line)
                     && i + 1 < lines.len()
                     && (is_table_line(lines[i + 1]) || is_block_plugin_line(lines[i + 1]))
@@ -90,7 +114,31 @@ pub fn preprocess_nested_blocks(input: &str) -> String {
         }
     }
 
-    output.join("\n")
+    Cow::Owned(output.join("\n"))
+}
+
+/// Whether the indented line `lines[index]` continues a list item above it
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::nested_blocks::continues_list_item;
+///
+/// assert!(continues_list_item(&["- Item", "", "    | a |"], 2));
+/// assert!(!continues_list_item(&["- Item", "", "| a |"], 2));
+/// assert!(!continues_list_item(&["Text", "  | a |"], 1));
+/// ```
+pub fn continues_list_item(lines: &[&str], index: usize) -> bool {
+    let indent = lines.get(index).map_or(0, |line| indent_width(line));
+    if indent == 0 {
+        return false;
+    }
+    lines[..index]
+        .iter()
+        .rev()
+        .filter(|line| !line.trim().is_empty())
+        .find(|line| indent_width(line) < indent)
+        .is_some_and(|line| list_indent_width(line).is_some())
 }
 
 fn list_indent_width(line: &str) -> Option<usize> {
@@ -99,7 +147,18 @@ fn list_indent_width(line: &str) -> Option<usize> {
         .and_then(|caps| caps.name("indent").map(|m| indent_width(m.as_str())))
 }
 
-fn indent_width(input: &str) -> usize {
+/// Column where the content of a list item starts (`- a` → 2, `10. a` → 4)
+///
+/// Like CommonMark, more than four spaces after the marker count as one.
+fn list_content_width(line: &str) -> usize {
+    LIST_MARKER.captures(line).map_or(0, |caps| {
+        let space = caps.name("space").map_or(1, |m| indent_width(m.as_str()));
+        let space = if space > 4 { 1 } else { space };
+        indent_width(&caps["indent"]) + caps["marker"].len() + space
+    })
+}
+
+pub(crate) fn indent_width(input: &str) -> usize {
     input
         .chars()
         .take_while(|ch| ch.is_whitespace())
@@ -116,6 +175,22 @@ fn indent_to(line: &str, target_indent: usize) -> String {
     format!("{}{}", padding, line)
 }
 
+/// Remove up to `width` columns of leading whitespace
+pub(crate) fn dedent(line: &str, width: usize) -> &str {
+    let mut removed = 0;
+    let start = line
+        .char_indices()
+        .find(|&(_, ch)| {
+            if removed >= width || !(ch == ' ' || ch == '\t') {
+                return true;
+            }
+            removed += if ch == '\t' { 4 } else { 1 };
+            false
+        })
+        .map_or(line.len(), |(index, _)| index);
+    &line[start..]
+}
+
 fn is_table_line(line: &str) -> bool {
     line.trim_start().starts_with('|')
 }
@@ -124,9 +199,16 @@ fn is_blockquote_line(line: &str) -> bool {
     line.trim_start().starts_with('>')
 }
 
+/// Block plugin, or the marker a multi-line plugin body was protected as
 fn is_block_plugin_line(line: &str) -> bool {
     let trimmed = line.trim_start();
-    trimmed.starts_with('@')
+    trimmed.starts_with('@') || trimmed.starts_with("{{BLOCK_PLUGIN:")
+}
+
+/// Whether `line` opens a block that [`preprocess_nested_blocks`] moves into
+/// the list item
+fn is_block_start(line: &str) -> bool {
+    is_table_line(line) || is_blockquote_line(line) || is_code_fence_line(line).is_some()
 }
 
 fn is_block_placement_prefix(line: &str) -> bool {
@@ -206,7 +288,10 @@ fn indent_plugin_block(
     target_indent: usize,
 ) -> usize {
     let mut i = start;
-    let in_multiline = lines[i].contains("{{");
+    let in_multiline = lines[i]
+        .split_once("{{")
+        .is_some_and(|(_, rest)| !rest.contains("}}"));
+    let plugin_indent = indent_width(lines[i]);
 
     output.push(indent_to(lines[i], target_indent));
     i += 1;
@@ -215,8 +300,10 @@ fn indent_plugin_block(
         return i;
     }
 
+    // The body becomes a single marker line before comrak runs, so it keeps
+    // its indentation relative to the opening line instead of the list's
     while i < lines.len() {
-        output.push(indent_to(lines[i], target_indent));
+        output.push(dedent(lines[i], plugin_indent).to_string());
         if lines[i].contains("}}") {
             i += 1;
             break;
@@ -252,6 +339,37 @@ mod tests {
         assert!(output.contains("- Item\n    @note(info){text}"));
     }
 
+    #[test]
+    fn test_code_fence_under_numbered_item() {
+        // Indented by less than the item content, the fence would end the list
+        let input = "1. Item\n  ```\n  code\n  ```\n2. Next";
+        let output = preprocess_nested_blocks(input);
+        assert_eq!(output, "1. Item\n    ```\n    code\n    ```\n2. Next");
+
+        let input = "100. Item\n```\ncode\n```";
+        let output = preprocess_nested_blocks(input);
+        assert_eq!(output, "100. Item\n     ```\n     code\n     ```");
+    }
+
+    #[test]
+    fn test_plugin_body_inside_list() {
+        let input = "- Item\n@note{{\nText\n- not an item\n}}\n- Next";
+        let output = preprocess_nested_blocks(input);
+        assert_eq!(
+            output,
+            "- Item\n    @note{{\nText\n- not an item\n}}\n- Next"
+        );
+
+        // The body keeps its indentation relative to the opening line
+        let input = "- Item\n  @note{{\n    Text\n  }}";
+        let output = preprocess_nested_blocks(input);
+        assert_eq!(output, "- Item\n    @note{{\n  Text\n}}");
+
+        let input = "- Item\n@note(a){{one}}\nText }}";
+        let output = preprocess_nested_blocks(input);
+        assert_eq!(output, "- Item\n    @note(a){{one}}\nText }}");
+    }
+
     #[test]
     fn test_blockquote_inside_list() {
         let input = "- Item\n> Quote\n> Next";
@@ -259,6 +377,12 @@ mod tests {
         assert!(output.contains("- Item\n    > Quote\n    > Next"));
     }
 
+    #[test]
+    fn test_blockquote_terminator_ends_list() {
+        let input = "> Quote\n\n- Item\n<\n\n> Next <";
+        assert_eq!(preprocess_nested_blocks(input), input);
+    }
+
     #[test]
     fn test_nested_list_not_modified() {
         let input = "- Item\n  - Nested\n  - Nested 2";
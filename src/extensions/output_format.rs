@@ -0,0 +1,439 @@
+//! Layout of the final HTML
+//!
+//! [`OutputFormat::Compact`] keeps comrak's layout (one block per line).
+//! [`OutputFormat::Pretty`] puts every block element on its own line,
+//! indented by nesting depth, so git-backed wikis get stable, readable
+//! diffs. [`OutputFormat::Minified`] drops the whitespace between block
+//! elements and collapses the rest.
+//!
+//! Both only change whitespace a browser does not render: `<pre>`,
+//! `<textarea>`, `<script>`, `<style>` and plugin `<template>` elements are
+//! copied verbatim, and inline content keeps one space wherever it had
+//! whitespace.
+
+use std::borrow::Cow;
+
+use crate::parser::OutputFormat;
+
+/// Elements laid out as blocks; whitespace around them is not rendered
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "caption",
+    "col",
+    "colgroup",
+    "dd",
+    "details",
+    "dialog",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "script",
+    "section",
+    "style",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "tr",
+    "ul",
+];
+
+/// Elements whose content is copied verbatim
+const VERBATIM_ELEMENTS: &[&str] = &["pre", "script", "style", "template", "textarea"];
+
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Lay out `html` as `format`
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::output_format::format_html;
+/// use umd::parser::OutputFormat;
+///
+/// let html = "<ul>\n<li>One <em>two</em></li>\n</ul>\n";
+/// assert_eq!(
+///     format_html(html, OutputFormat::Pretty),
+///     "<ul>\n  <li>One <em>two</em></li>\n</ul>\n"
+/// );
+/// assert_eq!(
+///     format_html(html, OutputFormat::Minified),
+///     "<ul><li>One <em>two</em></li></ul>"
+/// );
+/// ```
+pub fn format_html(html: &str, format: OutputFormat) -> Cow<'_, str> {
+    match format {
+        OutputFormat::Compact => Cow::Borrowed(html),
+        OutputFormat::Pretty => Cow::Owned(pretty(&tokenize(html))),
+        OutputFormat::Minified => Cow::Owned(minify(&tokenize(html))),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    /// Opening tag (`void` for elements without content)
+    Open {
+        void: bool,
+    },
+    Close,
+    /// Element copied as a whole
+    Verbatim,
+    /// Comment, doctype or processing instruction
+    Other,
+    Text,
+}
+
+#[derive(Debug)]
+struct Token<'a> {
+    kind: TokenKind,
+    /// Lowercase element name (empty for text and comments)
+    name: String,
+    source: &'a str,
+}
+
+impl Token<'_> {
+    fn is_block(&self) -> bool {
+        matches!(
+            self.kind,
+            TokenKind::Open { .. } | TokenKind::Close | TokenKind::Verbatim
+        ) && BLOCK_ELEMENTS.contains(&self.name.as_str())
+    }
+}
+
+/// Split `html` into tags, verbatim elements and text
+fn tokenize(html: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut text_start = 0;
+    let mut pos = 0;
+    while let Some(offset) = html[pos..].find('<') {
+        pos += offset;
+        let Some((kind, name, len)) = scan_markup(&html[pos..]) else {
+            pos += 1;
+            continue;
+        };
+        if text_start < pos {
+            tokens.push(Token {
+                kind: TokenKind::Text,
+                name: String::new(),
+                source: &html[text_start..pos],
+            });
+        }
+        tokens.push(Token {
+            kind,
+            name,
+            source: &html[pos..pos + len],
+        });
+        pos += len;
+        text_start = pos;
+    }
+    if text_start < html.len() {
+        tokens.push(Token {
+            kind: TokenKind::Text,
+            name: String::new(),
+            source: &html[text_start..],
+        });
+    }
+    tokens
+}
+
+/// Kind, element name and length of the markup starting at `html`, if it
+/// is a tag or a comment
+fn scan_markup(html: &str) -> Option<(TokenKind, String, usize)> {
+    if html.starts_with("<!--") {
+        let len = html.find("-->").map_or(html.len(), |end| end + 3);
+        return Some((TokenKind::Other, String::new(), len));
+    }
+    if html.starts_with("<!") || html.starts_with("<?") {
+        let len = html.find('>')? + 1;
+        return Some((TokenKind::Other, String::new(), len));
+    }
+
+    let closing = html.starts_with("</");
+    let name_start = if closing { 2 } else { 1 };
+    let name_len = html[name_start..]
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+        .unwrap_or(html.len() - name_start);
+    if !html[name_start..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let name = html[name_start..name_start + name_len].to_ascii_lowercase();
+    let len = tag_len(html)?;
+
+    if closing {
+        return Some((TokenKind::Close, name, len));
+    }
+    if VERBATIM_ELEMENTS.contains(&name.as_str()) {
+        let end = html[len..]
+            .match_indices("</")
+            .map(|(start, _)| len + start)
+            .find(|&start| {
+                html[start + 2..]
+                    .get(..name.len())
+                    .is_some_and(|tag| tag.eq_ignore_ascii_case(&name))
+            })
+            .and_then(|start| Some(start + tag_len(&html[start..])?))
+            .unwrap_or(html.len());
+        return Some((TokenKind::Verbatim, name, end));
+    }
+    let void = VOID_ELEMENTS.contains(&name.as_str()) || html[..len].ends_with("/>");
+    Some((TokenKind::Open { void }, name, len))
+}
+
+/// Length of the tag starting at `html`, skipping quoted attribute values
+fn tag_len(html: &str) -> Option<usize> {
+    let mut quote = None;
+    for (index, c) in html.char_indices() {
+        match (quote, c) {
+            (Some(open), _) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some(index + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Replace every run of whitespace with one space
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut in_space = false;
+    for c in text.chars() {
+        if c.is_ascii_whitespace() {
+            if !in_space {
+                collapsed.push(' ');
+            }
+            in_space = true;
+        } else {
+            collapsed.push(c);
+            in_space = false;
+        }
+    }
+    collapsed
+}
+
+fn minify(tokens: &[Token<'_>]) -> String {
+    let mut html = String::new();
+    for (index, token) in tokens.iter().enumerate() {
+        if token.kind != TokenKind::Text {
+            html.push_str(token.source);
+            continue;
+        }
+        let mut text = collapse_whitespace(token.source);
+        let after_block = index == 0 || tokens[index - 1].is_block();
+        let before_block = tokens.get(index + 1).is_none_or(Token::is_block);
+        if before_block {
+            text.truncate(text.trim_ascii_end().len());
+        }
+        html.push_str(if after_block {
+            text.trim_ascii_start()
+        } else {
+            &text
+        });
+    }
+    html
+}
+
+/// Lines of a block element being laid out
+#[derive(Default)]
+struct Frame<'a> {
+    open: Option<&'a Token<'a>>,
+    /// Child lines with their indentation relative to this element
+    lines: Vec<(usize, String)>,
+    /// Inline content not yet ended by a block element
+    inline: String,
+    has_block_child: bool,
+}
+
+impl Frame<'_> {
+    fn flush_inline(&mut self) {
+        let text = self.inline.trim_ascii();
+        if !text.is_empty() {
+            self.lines.push((0, text.to_string()));
+        }
+        self.inline.clear();
+    }
+}
+
+fn pretty(tokens: &[Token<'_>]) -> String {
+    let mut stack = vec![Frame::default()];
+    for token in tokens {
+        let block = token.is_block();
+        match token.kind {
+            TokenKind::Open { void: false } if block => {
+                let parent = stack.last_mut().expect("root frame");
+                parent.flush_inline();
+                parent.has_block_child = true;
+                stack.push(Frame {
+                    open: Some(token),
+                    ..Frame::default()
+                });
+            }
+            TokenKind::Close
+                if block
+                    && stack[1..]
+                        .iter()
+                        .any(|frame| frame.open.is_some_and(|open| open.name == token.name)) =>
+            {
+                // Elements left open inside the closed one end with it
+                loop {
+                    let frame = stack.pop().expect("frame of an open element");
+                    let name = frame.open.map_or("", |open| open.name.as_str());
+                    let closed = if name == token.name {
+                        token.source.to_string()
+                    } else {
+                        String::new()
+                    };
+                    let lines = close_frame(frame, &closed);
+                    stack.last_mut().expect("root frame").lines.extend(lines);
+                    if name == token.name {
+                        break;
+                    }
+                }
+            }
+            _ if block => {
+                // Void blocks and verbatim `<pre>` / `<script>` / `<style>`
+                let frame = stack.last_mut().expect("root frame");
+                frame.flush_inline();
+                frame.lines.push((0, token.source.to_string()));
+                frame.has_block_child = true;
+            }
+            TokenKind::Text => {
+                let frame = stack.last_mut().expect("root frame");
+                frame.inline.push_str(&collapse_whitespace(token.source));
+            }
+            _ => {
+                let frame = stack.last_mut().expect("root frame");
+                frame.inline.push_str(token.source);
+            }
+        }
+    }
+
+    // Unclosed elements end with the document
+    while stack.len() > 1 {
+        let frame = stack.pop().expect("frame of an open element");
+        let lines = close_frame(frame, "");
+        stack.last_mut().expect("root frame").lines.extend(lines);
+    }
+    let mut root = stack.pop().expect("root frame");
+    root.flush_inline();
+
+    let mut html = String::new();
+    for (indent, line) in root.lines {
+        html.push_str(&"  ".repeat(indent));
+        html.push_str(&line);
+        html.push('\n');
+    }
+    html
+}
+
+/// Lines of a finished block element, with `close` as its closing tag
+fn close_frame(mut frame: Frame<'_>, close: &str) -> Vec<(usize, String)> {
+    let open = frame.open.map_or("", |open| open.source);
+    if !frame.has_block_child {
+        // Inline content only: the element stays on one line
+        let text = frame.inline.trim_ascii();
+        return vec![(0, format!("{}{}{}", open, text, close))];
+    }
+    frame.flush_inline();
+    let mut lines = vec![(0, open.to_string())];
+    lines.extend(
+        frame
+            .lines
+            .into_iter()
+            .map(|(indent, line)| (indent + 1, line)),
+    );
+    if !close.is_empty() {
+        lines.push((0, close.to_string()));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HTML: &str = "<h1>Title</h1>\n<blockquote class=\"blockquote\">\n<p>A\nquote</p>\n</blockquote>\n<ul>\n<li>\n<p>Item</p>\n<ul>\n<li>Nested <a href=\"/a\">link</a></li>\n</ul>\n</li>\n</ul>\n<pre><code>line 1\n  line 2\n</code></pre>\n<p>Text <template class=\"umd-plugin\">a\n  b</template> <strong>end</strong></p>\n";
+
+    #[test]
+    fn test_pretty() {
+        assert_eq!(
+            format_html(HTML, OutputFormat::Pretty),
+            "<h1>Title</h1>\n\
+             <blockquote class=\"blockquote\">\n  <p>A quote</p>\n</blockquote>\n\
+             <ul>\n  <li>\n    <p>Item</p>\n    <ul>\n      <li>Nested <a href=\"/a\">link</a></li>\n    </ul>\n  </li>\n</ul>\n\
+             <pre><code>line 1\n  line 2\n</code></pre>\n\
+             <p>Text <template class=\"umd-plugin\">a\n  b</template> <strong>end</strong></p>\n"
+        );
+    }
+
+    #[test]
+    fn test_minified() {
+        assert_eq!(
+            format_html(HTML, OutputFormat::Minified),
+            "<h1>Title</h1><blockquote class=\"blockquote\"><p>A quote</p></blockquote>\
+             <ul><li><p>Item</p><ul><li>Nested <a href=\"/a\">link</a></li></ul></li></ul>\
+             <pre><code>line 1\n  line 2\n</code></pre>\
+             <p>Text <template class=\"umd-plugin\">a\n  b</template> <strong>end</strong></p>"
+        );
+    }
+
+    #[test]
+    fn test_non_breaking_spaces_are_kept() {
+        // `@verse` indents lines with U+00A0, which browsers render
+        let html = "<p class=\"verse\">\u{a0}\u{a0}v<br />\n\u{a0}w\u{a0}</p>\n";
+        assert_eq!(
+            format_html(html, OutputFormat::Minified),
+            "<p class=\"verse\">\u{a0}\u{a0}v<br /> \u{a0}w\u{a0}</p>"
+        );
+        assert_eq!(
+            format_html(html, OutputFormat::Pretty),
+            "<p class=\"verse\">\u{a0}\u{a0}v<br /> \u{a0}w\u{a0}</p>\n"
+        );
+    }
+
+    #[test]
+    fn test_attributes_and_unbalanced_tags() {
+        let html = "<div title=\"a > b\">\n<p>x</p>\n</span>\n<section>\n<p>y";
+        assert_eq!(
+            format_html(html, OutputFormat::Pretty),
+            "<div title=\"a > b\">\n  <p>x</p>\n  </span>\n  <section>\n    <p>y\n"
+        );
+    }
+
+    #[test]
+    fn test_compact_is_unchanged() {
+        assert!(matches!(
+            format_html(HTML, OutputFormat::Compact),
+            Cow::Borrowed(HTML)
+        ));
+    }
+}
@@ -4,9 +4,45 @@
 //! that won't be affected by Markdown parsing.
 
 use base64::{Engine as _, engine::general_purpose};
+use once_cell::sync::Lazy;
 use regex::Regex;
+use std::borrow::Cow;
 use std::collections::HashSet;
 
+use super::nested_blocks::{dedent, indent_width};
+use super::preprocessor::split_blockquote_prefix;
+
+/// `&function{content};`
+static INLINE_PLUGIN_NOARGS_CONTENT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"&(\w+)\{((?:[^{}]|\{[^}]*\})*)\};").unwrap());
+
+/// `&function(args){content};`, args may nest one level of parentheses
+static INLINE_PLUGIN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"&(\w+)\(((?:[^()]|\([^()]*\))*)\)\{((?:[^{}]|\{[^}]*\})*)\};").unwrap()
+});
+
+/// `&function(args);`
+static INLINE_PLUGIN_ARGSONLY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"&(\w+)\(((?:[^()]|\([^()]*\))*)\);").unwrap());
+
+/// `&function;`
+static INLINE_PLUGIN_NOARGS: Lazy<Regex> = Lazy::new(|| Regex::new(r"&([a-zA-Z]\w*);").unwrap());
+
+/// `@function(args){{ content }}`
+static BLOCK_PLUGIN_MULTI: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"@(\w+)\(([^)]*)\)\{\{([\s\S]*?)\}\}").unwrap());
+
+/// `@function{{ content }}`
+static BLOCK_PLUGIN_MULTI_NOARGS: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"@(\w+)\{\{([\s\S]*?)\}\}").unwrap());
+
+/// `@function(args){content}`
+static BLOCK_PLUGIN_SINGLE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"@(\w+)\(([^)]*)\)\{([^}]*)\}").unwrap());
+
+/// `@function(args)`
+static BLOCK_PLUGIN_ARGSONLY: Lazy<Regex> = Lazy::new(|| Regex::new(r"@(\w+)\(([^)]*)\)").unwrap());
+
 /// HTML entities that should NOT be treated as plugins
 fn html_entities() -> HashSet<&'static str> {
     [
@@ -34,6 +70,19 @@ fn html_entities() -> HashSet<&'static str> {
     .collect()
 }
 
+/// Check whether a match position lies inside a URL token
+///
+/// Query strings such as `?a=1&lang;` or `&copy(2);` inside autolinked
+/// URLs must not be mistaken for inline plugins.
+fn is_inside_url(input: &str, position: usize) -> bool {
+    let before = &input[..position];
+    let token_start = before
+        .rfind(|c: char| c.is_whitespace() || matches!(c, '(' | '<' | '"' | '[' | '|'))
+        .map_or(0, |index| index + 1);
+    let token = &before[token_start..];
+    token.contains("://") || token.starts_with("www.") || token.starts_with("mailto:")
+}
+
 /// Protect inline plugin syntax by converting to markers
 ///
 /// Converts various inline plugin patterns into safe markers:
@@ -41,13 +90,15 @@ fn html_entities() -> HashSet<&'static str> {
 /// - `&function(args){content};` → marker with args and content
 /// - `&function(args);` → marker with args
 /// - `&function;` → marker (excluding HTML entities)
-pub fn protect_inline_plugins(input: &str) -> String {
-    let mut result = input.to_string();
+pub fn protect_inline_plugins(input: &str) -> Cow<'_, str> {
+    if !input.contains('&') {
+        return Cow::Borrowed(input);
+    }
+    let mut result = Cow::Borrowed(input);
 
     // Protect inline plugins with content but no args: &function{content};
-    let inline_plugin_noargs_content = Regex::new(r"&(\w+)\{((?:[^{}]|\{[^}]*\})*)\};").unwrap();
-    result = inline_plugin_noargs_content
-        .replace_all(&result, |caps: &regex::Captures| {
+    if let Cow::Owned(replaced) =
+        INLINE_PLUGIN_NOARGS_CONTENT.replace_all(&result, |caps: &regex::Captures| {
             let function = &caps[1];
             let content = &caps[2];
             let encoded_content = general_purpose::STANDARD.encode(content.as_bytes());
@@ -56,47 +107,55 @@ pub fn protect_inline_plugins(input: &str) -> String {
                 function, encoded_content
             )
         })
-        .to_string();
+    {
+        result = Cow::Owned(replaced);
+    }
 
     // Protect inline plugins: &function(args){content};
-    let inline_plugin = Regex::new(r"&(\w+)\(([^)]*)\)\{((?:[^{}]|\{[^}]*\})*)\};").unwrap();
-    result = inline_plugin
-        .replace_all(&result, |caps: &regex::Captures| {
-            let function = &caps[1];
-            let args = &caps[2];
-            let content = &caps[3];
-            let encoded_content = general_purpose::STANDARD.encode(content.as_bytes());
-            format!(
-                "{{{{INLINE_PLUGIN:{}:{}:{}:INLINE_PLUGIN}}}}",
-                function, args, encoded_content
-            )
-        })
-        .to_string();
+    // Args may contain one level of parentheses (`&color(rgb(0,0,0)){...};`)
+    if let Cow::Owned(replaced) = INLINE_PLUGIN.replace_all(&result, |caps: &regex::Captures| {
+        let function = &caps[1];
+        let args = &caps[2];
+        let content = &caps[3];
+        let encoded_content = general_purpose::STANDARD.encode(content.as_bytes());
+        format!(
+            "{{{{INLINE_PLUGIN:{}:{}:{}:INLINE_PLUGIN}}}}",
+            function, args, encoded_content
+        )
+    }) {
+        result = Cow::Owned(replaced);
+    }
 
     // Protect inline plugins (args only): &function(args);
-    let inline_plugin_argsonly = Regex::new(r"&(\w+)\(([^)]*)\);").unwrap();
-    result = inline_plugin_argsonly
-        .replace_all(&result, |caps: &regex::Captures| {
+    if let Cow::Owned(replaced) =
+        INLINE_PLUGIN_ARGSONLY.replace_all(&result, |caps: &regex::Captures| {
+            if is_inside_url(&result, caps.get(0).map_or(0, |m| m.start())) {
+                return caps[0].to_string();
+            }
             let function = &caps[1];
-            let args = &caps[2];
+            // Encoded like block plugin args, so URLs are not autolinked
+            let encoded_args = general_purpose::STANDARD.encode(caps[2].as_bytes());
             format!(
                 "{{{{INLINE_PLUGIN_ARGSONLY:{}:{}:INLINE_PLUGIN_ARGSONLY}}}}",
-                function, args
+                function, encoded_args
             )
         })
-        .to_string();
+    {
+        result = Cow::Owned(replaced);
+    }
 
     // Protect inline plugins (no args): &function;
     // Function name must start with a letter to avoid conflicts with HTML entities
-    let inline_plugin_noargs = Regex::new(r"&([a-zA-Z]\w*);").unwrap();
     let entities = html_entities();
 
-    result = inline_plugin_noargs
-        .replace_all(&result, |caps: &regex::Captures| {
+    if let Cow::Owned(replaced) =
+        INLINE_PLUGIN_NOARGS.replace_all(&result, |caps: &regex::Captures| {
             let function = &caps[1];
 
-            // Skip HTML entities
-            if entities.contains(function) {
+            // Skip HTML entities and query strings inside URLs
+            if entities.contains(function)
+                || is_inside_url(&result, caps.get(0).map_or(0, |m| m.start()))
+            {
                 return caps[0].to_string();
             }
 
@@ -105,39 +164,123 @@ pub fn protect_inline_plugins(input: &str) -> String {
                 function
             )
         })
-        .to_string();
+    {
+        result = Cow::Owned(replaced);
+    }
 
     result
 }
 
-/// Protect block plugin syntax by converting to markers
+/// Content of a multi-line block plugin without the `>` markers of the
+/// blockquote the plugin starts in and without the indentation of its
+/// opening line
 ///
-/// Converts various block plugin patterns into safe markers:
-/// - `@function(args){{ content }}` → marker with content
-/// - `@function(args){content}` → marker with content
-/// - `@function(args)` → marker with args
-pub fn protect_block_plugins(input: &str) -> String {
-    let mut result = input.to_string();
+/// `start` is the position of the `@` in `input`.
+fn unquote_plugin_content<'a>(input: &str, start: usize, content: &'a str) -> Cow<'a, str> {
+    let line_start = input[..start].rfind('\n').map_or(0, |index| index + 1);
+    let (quote, indent) = split_blockquote_prefix(&input[line_start..start]);
+    let indent = indent_width(indent);
+    if (quote.is_empty() && indent == 0) || !content.contains('\n') {
+        return Cow::Borrowed(content);
+    }
+    let lines: Vec<&str> = content
+        .split('\n')
+        .enumerate()
+        .map(|(index, line)| {
+            if index == 0 {
+                return line;
+            }
+            match split_blockquote_prefix(line) {
+                (line_quote, rest) if line_quote.trim_end() == quote.trim_end() => {
+                    dedent(rest, indent)
+                }
+                _ => line,
+            }
+        })
+        .collect();
+    Cow::Owned(lines.join("\n"))
+}
+
+/// Protect the bodies of multi-line block plugins by converting to markers
+///
+/// The body of `@function(args){{ ... }}` is plugin source and is handed to
+/// the plugin as written, so this runs before every line-based pass (list
+/// nesting, code filenames, headings, decorations, blockquotes, tables,
+/// definition lists, inline plugins). Only masked code is restored inside
+/// the body. `@comment` plugins are left to
+/// [`remove_comment_plugins`](super::preprocessor::remove_comment_plugins).
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::plugin_markers::protect_block_plugin_bodies;
+///
+/// let output = protect_block_plugin_bodies("@box(){{\n| a | b |\n}}\n| c |");
+/// assert_eq!(output, "{{BLOCK_PLUGIN:box::CnwgYSB8IGIgfAo=:BLOCK_PLUGIN}}\n| c |");
+/// ```
+pub fn protect_block_plugin_bodies(input: &str) -> Cow<'_, str> {
+    if !input.contains('@') || !input.contains("{{") {
+        return Cow::Borrowed(input);
+    }
+    let mut result = Cow::Borrowed(input);
 
     // Protect block plugins multiline: @function(args){{ content }}
-    let block_plugin_multi = Regex::new(r"@(\w+)\(([^)]*)\)\{\{([\s\S]*?)\}\}").unwrap();
-    result = block_plugin_multi
-        .replace_all(&result, |caps: &regex::Captures| {
+    if let Cow::Owned(replaced) =
+        BLOCK_PLUGIN_MULTI.replace_all(&result, |caps: &regex::Captures| {
             let function = &caps[1];
+            if function == "comment" {
+                return caps[0].to_string();
+            }
             let args = &caps[2];
-            let content = &caps[3];
+            let content =
+                unquote_plugin_content(&result, caps.get(0).map_or(0, |m| m.start()), &caps[3]);
             let encoded_content = general_purpose::STANDARD.encode(content.as_bytes());
             format!(
                 "{{{{BLOCK_PLUGIN:{}:{}:{}:BLOCK_PLUGIN}}}}",
                 function, args, encoded_content
             )
         })
-        .to_string();
+    {
+        result = Cow::Owned(replaced);
+    }
+
+    // Protect block plugins multiline without args: @function{{ content }}
+    if let Cow::Owned(replaced) =
+        BLOCK_PLUGIN_MULTI_NOARGS.replace_all(&result, |caps: &regex::Captures| {
+            if &caps[1] == "comment" {
+                return caps[0].to_string();
+            }
+            let content =
+                unquote_plugin_content(&result, caps.get(0).map_or(0, |m| m.start()), &caps[2]);
+            let encoded_content = general_purpose::STANDARD.encode(content.as_bytes());
+            format!(
+                "{{{{BLOCK_PLUGIN:{}::{}:BLOCK_PLUGIN}}}}",
+                &caps[1], encoded_content
+            )
+        })
+    {
+        result = Cow::Owned(replaced);
+    }
+
+    result
+}
+
+/// Protect block plugin syntax by converting to markers
+///
+/// Converts various block plugin patterns into safe markers:
+/// - `@function(args){{ content }}` → marker with content
+/// - `@function{{ content }}` → marker with content and empty args
+/// - `@function(args){content}` → marker with content
+/// - `@function(args)` → marker with args
+pub fn protect_block_plugins(input: &str) -> Cow<'_, str> {
+    if !input.contains('@') {
+        return Cow::Borrowed(input);
+    }
+    let mut result = protect_block_plugin_bodies(input);
 
     // Protect block plugins singleline: @function(args){content}
-    let block_plugin_single = Regex::new(r"@(\w+)\(([^)]*)\)\{([^}]*)\}").unwrap();
-    result = block_plugin_single
-        .replace_all(&result, |caps: &regex::Captures| {
+    if let Cow::Owned(replaced) =
+        BLOCK_PLUGIN_SINGLE.replace_all(&result, |caps: &regex::Captures| {
             let function = &caps[1];
             let args = &caps[2];
             let content = &caps[3];
@@ -147,12 +290,13 @@ pub fn protect_block_plugins(input: &str) -> String {
                 function, args, encoded_content
             )
         })
-        .to_string();
+    {
+        result = Cow::Owned(replaced);
+    }
 
     // Protect block plugins (args only, no content): @function(args)
-    let block_plugin_argsonly = Regex::new(r"@(\w+)\(([^)]*)\)").unwrap();
-    result = block_plugin_argsonly
-        .replace_all(&result, |caps: &regex::Captures| {
+    if let Cow::Owned(replaced) =
+        BLOCK_PLUGIN_ARGSONLY.replace_all(&result, |caps: &regex::Captures| {
             let function = &caps[1];
             let args = &caps[2];
             let encoded_args = general_purpose::STANDARD.encode(args.as_bytes());
@@ -161,7 +305,9 @@ pub fn protect_block_plugins(input: &str) -> String {
                 function, encoded_args
             )
         })
-        .to_string();
+    {
+        result = Cow::Owned(replaced);
+    }
 
     result
 }
@@ -170,6 +316,35 @@ pub fn protect_block_plugins(input: &str) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_block_plugin_in_blockquote() {
+        use base64::{Engine as _, engine::general_purpose};
+
+        let output = protect_block_plugins("> @note(a){{\n> Text\n>\n> - item\n> }}");
+        let encoded = general_purpose::STANDARD.encode("\nText\n\n- item\n");
+        assert_eq!(
+            output,
+            format!("> {{{{BLOCK_PLUGIN:note:a:{}:BLOCK_PLUGIN}}}}", encoded)
+        );
+    }
+
+    #[test]
+    fn test_block_plugin_bodies_are_protected_first() {
+        use base64::{Engine as _, engine::general_purpose};
+
+        let output =
+            protect_block_plugin_bodies("  @box(){{\n  | a |\n    - b\n  }}\n@comment{{\nx\n}}");
+        let encoded = general_purpose::STANDARD.encode("\n| a |\n  - b\n");
+        assert_eq!(
+            output,
+            format!(
+                "  {{{{BLOCK_PLUGIN:box::{}:BLOCK_PLUGIN}}}}\n@comment{{{{\nx\n}}}}",
+                encoded
+            )
+        );
+        assert_eq!(protect_block_plugin_bodies("@box(x)"), "@box(x)");
+    }
+
     #[test]
     fn test_protect_inline_plugin_with_content() {
         let input = "&test{content};";
@@ -192,6 +367,14 @@ mod tests {
         assert_eq!(input, output); // Should remain unchanged
     }
 
+    #[test]
+    fn test_skip_query_strings_in_urls() {
+        let input = "https://example.com/?a=1&lang; www.example.com/x&f(1); &br;";
+        let output = protect_inline_plugins(input);
+        assert!(output.starts_with("https://example.com/?a=1&lang; www.example.com/x&f(1); "));
+        assert!(output.contains("INLINE_PLUGIN_NOARGS:br:"));
+    }
+
     #[test]
     fn test_protect_block_plugin_multiline() {
         let input = "@test(args){{ content }}";
@@ -199,6 +382,13 @@ mod tests {
         assert!(output.contains("BLOCK_PLUGIN:test:args:"));
     }
 
+    #[test]
+    fn test_protect_block_plugin_multiline_without_args() {
+        let input = "@verse{{\nline\n}}";
+        let output = protect_block_plugins(input);
+        assert!(output.starts_with("{{BLOCK_PLUGIN:verse::"));
+    }
+
     #[test]
     fn test_protect_block_plugin_single_line() {
         let input = "@test(args){content}";
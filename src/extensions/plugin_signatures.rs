@@ -0,0 +1,236 @@
+//! Plugin argument validation
+//!
+//! Hosts describe the plugins they implement with
+//! [`PluginSignature`](crate::parser::PluginSignature) values in
+//! `ParserOptions.plugin_signatures`. Every `@name(...)` / `&name(...);`
+//! use of such a plugin is checked against its argument count and types, and
+//! mismatches are reported as `plugin-arguments` diagnostics. The plugin is
+//! rendered either way, so output does not depend on the signatures.
+//!
+//! Arguments are split on commas and trimmed, the same way they become the
+//! `<data>` elements of the plugin `<template>`.
+
+use base64::{Engine as _, engine::general_purpose};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::diagnostics::Diagnostic;
+use crate::parser::{ParserOptions, PluginArgType, PluginSignature};
+use crate::sanitizer::sanitize_url;
+
+/// Regex to detect protected plugin markers in the rendered HTML
+static PLUGIN_MARKER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"\{\{((?:INLINE|BLOCK)_PLUGIN(?:_ARGSONLY|_NOARGS)?):(\w+)(?::([\s\S]*?))??:(?:INLINE|BLOCK)_PLUGIN(?:_ARGSONLY|_NOARGS)?\}\}",
+    )
+    .unwrap()
+});
+
+/// Sigil and argument string of a plugin marker
+fn marker_args(kind: &str, rest: &str) -> (char, String) {
+    let raw = match kind {
+        // `args:base64 content`; the content never holds a colon
+        "INLINE_PLUGIN" | "BLOCK_PLUGIN" => rest.rsplit_once(':').map_or("", |(args, _)| args),
+        "BLOCK_PLUGIN_ARGSONLY" | "INLINE_PLUGIN_ARGSONLY" => {
+            return (
+                if kind.starts_with("BLOCK") { '@' } else { '&' },
+                general_purpose::STANDARD
+                    .decode(rest.as_bytes())
+                    .ok()
+                    .and_then(|bytes| String::from_utf8(bytes).ok())
+                    .unwrap_or_default(),
+            );
+        }
+        _ => rest,
+    };
+    let sigil = if kind.starts_with("BLOCK") { '@' } else { '&' };
+    (sigil, html_escape::decode_html_entities(raw).into_owned())
+}
+
+/// Whether `value` has the type `kind`
+fn matches_type(value: &str, kind: PluginArgType) -> bool {
+    match kind {
+        PluginArgType::Text => true,
+        PluginArgType::Integer => value.parse::<i64>().is_ok(),
+        PluginArgType::Number => value.parse::<f64>().is_ok_and(f64::is_finite),
+        PluginArgType::Url => {
+            !value.is_empty()
+                && !value.chars().any(char::is_whitespace)
+                && sanitize_url(value) == value
+        }
+    }
+}
+
+/// Human-readable parameter list, optional parameters in brackets:
+/// `(url[, count])`
+fn describe(signature: &PluginSignature) -> String {
+    let mut params = String::new();
+    for (index, param) in signature.params.iter().enumerate() {
+        let separator = if index == 0 { "" } else { ", " };
+        if index >= signature.min_args {
+            params.push_str(&format!("[{}{}]", separator, param.name));
+        } else {
+            params.push_str(separator);
+            params.push_str(&param.name);
+        }
+    }
+    if signature.max_args.is_none() {
+        params.push_str(if params.is_empty() { "..." } else { ", ..." });
+    }
+    format!("({})", params)
+}
+
+/// Check one plugin use against its signature
+fn check(signature: &PluginSignature, sigil: char, args: &str) -> Option<Diagnostic> {
+    let args: Vec<&str> = if args.trim().is_empty() {
+        Vec::new()
+    } else {
+        args.split(',').map(str::trim).collect()
+    };
+    let usage = format!("{}{}", sigil, signature.name);
+
+    let too_many = signature.max_args.is_some_and(|max| args.len() > max);
+    if args.len() < signature.min_args || too_many {
+        return Some(Diagnostic::warning(
+            "plugin-arguments",
+            format!(
+                "`{}` expects {}, got {} argument{}",
+                usage,
+                describe(signature),
+                args.len(),
+                if args.len() == 1 { "" } else { "s" }
+            ),
+        ));
+    }
+
+    signature
+        .params
+        .iter()
+        .zip(&args)
+        .find(|(param, value)| !matches_type(value, param.kind))
+        .map(|(param, value)| {
+            let expected = match param.kind {
+                PluginArgType::Text => "text",
+                PluginArgType::Integer => "an integer",
+                PluginArgType::Number => "a number",
+                PluginArgType::Url => "a URL",
+            };
+            Diagnostic::warning(
+                "plugin-arguments",
+                format!(
+                    "`{}` argument `{}` must be {}, got `{}`",
+                    usage, param.name, expected, value
+                ),
+            )
+        })
+}
+
+/// Validate plugin uses against `options.plugin_signatures`
+///
+/// Runs on the HTML rendered by comrak, while plugins are still protected
+/// markers, so plugins inside code are not checked. Plugins rejected by
+/// `allowed_plugins` / `denied_plugins` are skipped.
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::plugin_signatures::validate_plugins;
+/// use umd::parser::{ParserOptions, PluginArgType, PluginSignature};
+///
+/// let options = ParserOptions {
+///     plugin_signatures: vec![PluginSignature::new(
+///         "feed",
+///         &[("url", PluginArgType::Url), ("count", PluginArgType::Integer)],
+///     )],
+///     ..ParserOptions::default()
+/// };
+/// let html = "<p>{{BLOCK_PLUGIN_ARGSONLY:feed:aHR0cHM6Ly9leGFtcGxlLmNvbQ==:BLOCK_PLUGIN_ARGSONLY}}</p>";
+/// let diagnostics = validate_plugins(html, &options);
+/// assert_eq!(
+///     diagnostics[0].message,
+///     "`@feed` expects (url, count), got 1 argument"
+/// );
+/// ```
+pub fn validate_plugins(html: &str, options: &ParserOptions) -> Vec<Diagnostic> {
+    PLUGIN_MARKER
+        .captures_iter(html)
+        .filter_map(|caps| {
+            let name = &caps[2];
+            let signature = options
+                .plugin_signatures
+                .iter()
+                .find(|signature| signature.name == name)?;
+            if !options.is_plugin_allowed(name) {
+                return None;
+            }
+            let (sigil, args) = marker_args(&caps[1], caps.get(3).map_or("", |rest| rest.as_str()));
+            check(signature, sigil, &args)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed() -> PluginSignature {
+        PluginSignature::new(
+            "feed",
+            &[
+                ("url", PluginArgType::Url),
+                ("count", PluginArgType::Integer),
+            ],
+        )
+        .with_args(1, Some(2))
+    }
+
+    #[test]
+    fn test_argument_count() {
+        assert_eq!(describe(&feed()), "(url[, count])");
+        assert!(check(&feed(), '@', "https://example.com").is_none());
+        assert_eq!(
+            check(&feed(), '&', "").unwrap().message,
+            "`&feed` expects (url[, count]), got 0 arguments"
+        );
+        assert!(check(&feed(), '@', "/a, 1, 2").is_some());
+        let open = feed().with_args(0, None);
+        assert_eq!(describe(&open), "([url][, count], ...)");
+        assert!(check(&open, '@', "/a, 1, 2").is_none());
+    }
+
+    #[test]
+    fn test_argument_types() {
+        assert_eq!(
+            check(&feed(), '@', "https://example.com, ten")
+                .unwrap()
+                .message,
+            "`@feed` argument `count` must be an integer, got `ten`"
+        );
+        assert!(check(&feed(), '@', "javascript:alert(1)").is_some());
+        assert!(matches_type("-1.5", PluginArgType::Number));
+        assert!(!matches_type("NaN", PluginArgType::Number));
+    }
+
+    #[test]
+    fn test_markers_in_document_order() {
+        let options = ParserOptions {
+            plugin_signatures: vec![feed()],
+            ..ParserOptions::default()
+        };
+        let html = "<p>{{INLINE_PLUGIN:feed:https://a.example:Ym9keQ==:INLINE_PLUGIN}} \
+                    {{INLINE_PLUGIN_NOARGS:feed:INLINE_PLUGIN_NOARGS}} \
+                    {{INLINE_PLUGIN_ARGSONLY:feed:YSBi:INLINE_PLUGIN_ARGSONLY}} \
+                    {{INLINE_PLUGIN_ARGSONLY:other:INLINE_PLUGIN_ARGSONLY}}</p>";
+        let messages: Vec<String> = validate_plugins(html, &options)
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "`&feed` expects (url[, count]), got 0 arguments",
+                "`&feed` argument `url` must be a URL, got `a b`"
+            ]
+        );
+    }
+}
@@ -12,21 +12,7 @@
 use once_cell::sync::Lazy;
 use regex::Regex;
 
-/// Escape HTML special characters
-///
-/// # Arguments
-///
-/// * `input` - Text to escape
-///
-/// # Returns
-///
-/// HTML-escaped string
-fn escape_html_text(input: &str) -> String {
-    input
-        .replace('&', "&amp;")
-        .replace('<', "&lt;")
-        .replace('>', "&gt;")
-}
+use crate::html_builder::text_escape;
 
 /// Parse comma-separated args into a vector
 ///
@@ -57,7 +43,7 @@ fn render_args_as_data(args: &str) -> String {
     parse_args(args)
         .iter()
         .enumerate()
-        .map(|(i, arg)| format!("<data value=\"{}\">{}</data>", i, escape_html_text(arg)))
+        .map(|(i, arg)| format!("<data value=\"{}\">{}</data>", i, text_escape(arg)))
         .collect::<Vec<_>>()
         .join("")
 }
@@ -231,7 +217,7 @@ pub fn apply_plugin_syntax(html: &str) -> String {
             let content = caps.get(3).map_or("", |m| m.as_str());
 
             let args_html = render_args_as_data(args);
-            let escaped_content = escape_html_text(content);
+            let escaped_content = text_escape(content);
 
             if escaped_content.is_empty() {
                 format!(
@@ -255,7 +241,7 @@ pub fn apply_plugin_syntax(html: &str) -> String {
             let content = caps.get(3).map_or("", |m| m.as_str());
 
             let args_html = render_args_as_data(args);
-            let escaped_content = escape_html_text(content);
+            let escaped_content = text_escape(content);
 
             if escaped_content.is_empty() {
                 format!(
@@ -304,7 +290,7 @@ pub fn apply_plugin_syntax(html: &str) -> String {
             let content = caps.get(3).map_or("", |m| m.as_str());
 
             let args_html = render_args_as_data(args);
-            let escaped_content = escape_html_text(content);
+            let escaped_content = text_escape(content);
 
             if escaped_content.is_empty() {
                 format!(
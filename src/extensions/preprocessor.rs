@@ -2,17 +2,85 @@
 //!
 //! This module handles early-stage text processing before Markdown parsing.
 
+use std::borrow::Cow;
+
 use once_cell::sync::Lazy;
-use regex::Regex;
+use regex::{Captures, Regex};
+
+use crate::html_builder::text_escape;
 
-// Discord-style underline pattern: __text__
-static DISCORD_UNDERLINE: Lazy<Regex> = Lazy::new(|| Regex::new(r"__([^_]+)__").unwrap());
 static TASKLIST_INDETERMINATE: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"^([ \t]*(?:[-+*]|\d+\.)\s+)\[-\](\s|$)").unwrap());
 
 const CODEBLOCK_FILENAME_LANGLESS_MARKER: &str = "umd-nolang";
 const CODEBLOCK_FILENAME_META_PREFIX: &str = "umd-filename:";
 
+/// UMD markers a backslash turns into literal text (`\&color(red){x};`)
+const ESCAPABLE_MARKERS: &[&str] = &["||", "%%", "''", "__", "&", "@", "#"];
+
+/// Block decoration prefixes a backslash at line start turns into literal text
+static ESCAPABLE_BLOCK_PREFIX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^(?:(?:SIZE|COLOR|LANG)\(|(?:RTL|LTR|BDI|TRUNCATE|TOP|MIDDLE|BOTTOM|BASELINE|JUSTIFY|RIGHT|CENTER|LEFT):)",
+    )
+    .unwrap()
+});
+
+/// Noncharacter placed inside user text that looks like a pipeline marker,
+/// so no postprocessing pattern can match it
+const MARKER_GUARD: char = '\u{FDD0}';
+
+/// [`MARKER_GUARD`] as percent-encoded by comrak in link destinations
+const MARKER_GUARD_ENCODED: &str = "%EF%B7%90";
+
+/// User text with the shape of a pipeline marker: `{{NAME:` / `{{NAME}}`
+/// (unless it opens a plugin body, `@name(args){{`), `:NAME}}`, or the
+/// table, code, raw HTML and line anchor placeholders
+static MARKER_LOOKALIKE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(\))?\{\{([A-Z][A-Z0-9_]*(?::|\}\}))|:([A-Z][A-Z0-9_]*\}\})|UMD_(TABLE_MARKER|CODE_MASK|RAW_HTML|LINE_ANCHOR)_",
+    )
+    .unwrap()
+});
+
+/// Placeholder left by [`mask_code_sections`], optionally preceded by the
+/// `<code>` tag comrak renders around it
+static CODE_MASK_PLACEHOLDER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(<code[^>]*>)?UMD_CODE_MASK_(\d+)_END(\n)?").unwrap());
+
+/// `@comment(...){{ ... }}`, `@comment{{ ... }}`, `@comment(...){...}` and
+/// `@comment(...)`
+static COMMENT_BLOCK_PLUGIN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"@comment(?:\([^)]*\))?\{\{[\s\S]*?\}\}|@comment\([^)]*\)(?:\{[^}]*\})?").unwrap()
+});
+
+/// `&comment(...){...};`, `&comment{...};`, `&comment(...);` and `&comment;`
+static COMMENT_INLINE_PLUGIN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"&comment(?:\((?:[^()]|\([^()]*\))*\))?(?:\{(?:[^{}]|\{[^}]*\})*\})?;").unwrap()
+});
+
+/// `>` markers at the start of a blockquote line (`> `, `> > `, `>>`)
+static BLOCKQUOTE_PREFIX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?: {0,3}>[ \t]?)+").unwrap());
+
+/// Split a line into its blockquote markers and the quoted content
+///
+/// Line-based extractors (UMD tables, definition lists, block plugins) use
+/// the content, and keep the markers on the lines they emit so the result
+/// stays inside the blockquote.
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::preprocessor::split_blockquote_prefix;
+///
+/// assert_eq!(split_blockquote_prefix("> > | a |"), ("> > ", "| a |"));
+/// assert_eq!(split_blockquote_prefix("| a |"), ("", "| a |"));
+/// ```
+pub fn split_blockquote_prefix(line: &str) -> (&str, &str) {
+    let len = BLOCKQUOTE_PREFIX.find(line).map_or(0, |m| m.end());
+    line.split_at(len)
+}
+
 /// Remove comment syntax from input
 ///
 /// Removes single-line comments (`//`) and multi-line comments (`/* ... */`)
@@ -128,10 +196,40 @@ pub fn remove_comments(input: &str) -> String {
     result
 }
 
+/// Remove `@comment` / `&comment` plugins
+///
+/// Unlike other plugins, which are emitted as `<template>` elements for the
+/// host to render, comment plugins leave nothing in the output. A block
+/// comment spanning several lines is removed together with its lines.
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::preprocessor::remove_comment_plugins;
+///
+/// let output = remove_comment_plugins("A&comment(todo);B\n@comment(){{\nnote\n}}\nC");
+/// assert_eq!(output, "AB\n\nC");
+/// ```
+pub fn remove_comment_plugins(input: &str) -> Cow<'_, str> {
+    if !input.contains("comment") {
+        return Cow::Borrowed(input);
+    }
+    match COMMENT_BLOCK_PLUGIN.replace_all(input, "") {
+        Cow::Borrowed(_) => COMMENT_INLINE_PLUGIN.replace_all(input, ""),
+        Cow::Owned(removed) => {
+            Cow::Owned(COMMENT_INLINE_PLUGIN.replace_all(&removed, "").into_owned())
+        }
+    }
+}
+
 /// Convert indeterminate task list marker `[-]` to a placeholder.
 ///
 /// The placeholder is later converted to an indeterminate checkbox in HTML.
-pub fn preprocess_tasklist_indeterminate(input: &str) -> String {
+pub fn preprocess_tasklist_indeterminate(input: &str) -> Cow<'_, str> {
+    if !input.contains("[-]") && !input.contains('\r') {
+        return Cow::Borrowed(input);
+    }
+
     let ends_with_newline = input.ends_with('\n');
     let mut result = String::new();
     let mut in_code_block = false;
@@ -170,7 +268,7 @@ pub fn preprocess_tasklist_indeterminate(input: &str) -> String {
         result.pop();
     }
 
-    result
+    Cow::Owned(result)
 }
 
 /// Normalize fenced code block info string for filename syntax.
@@ -179,7 +277,11 @@ pub fn preprocess_tasklist_indeterminate(input: &str) -> String {
 /// `data-meta` when `render.full_info_string = true`.
 ///
 /// Also supports `:filename` by using an internal language marker (`umd-nolang`).
-pub fn preprocess_code_block_filenames(input: &str) -> String {
+pub fn preprocess_code_block_filenames(input: &str) -> Cow<'_, str> {
+    if !input.contains("```") && !input.contains("~~~") && !input.contains('\r') {
+        return Cow::Borrowed(input);
+    }
+
     let ends_with_newline = input.ends_with('\n');
     let mut result = String::new();
     let mut in_code_block = false;
@@ -227,7 +329,7 @@ pub fn preprocess_code_block_filenames(input: &str) -> String {
         result.pop();
     }
 
-    result
+    Cow::Owned(result)
 }
 
 fn parse_fence_open_line(trimmed_line: &str) -> Option<(usize, char, usize, &str)> {
@@ -268,6 +370,19 @@ fn is_fence_close_line(trimmed_line: &str, fence_char: char, fence_len: usize) -
 }
 
 fn normalize_code_fence_info(info: &str) -> String {
+    // A trailing attribute block (`rust:main.rs {.numbered}`) is kept as meta
+    if info.ends_with('}')
+        && let Some(open) = info.find('{')
+    {
+        let head = info[..open].trim_end();
+        let head = if head.is_empty() {
+            CODEBLOCK_FILENAME_LANGLESS_MARKER.to_string()
+        } else {
+            normalize_code_fence_info(head)
+        };
+        return format!("{} {}", head, &info[open..]);
+    }
+
     if info.is_empty() || info.contains(' ') {
         return info.to_string();
     }
@@ -292,46 +407,419 @@ fn normalize_code_fence_info(info: &str) -> String {
     info.to_string()
 }
 
+/// A code section hidden from the preprocessing passes
+#[derive(Debug)]
+enum MaskedCode {
+    /// Body of a fenced code block and the indentation of its opening fence
+    Block { body: String, indent: usize },
+    /// Content of an inline code span (between the backtick runs)
+    Inline(String),
+    /// Backslash-escaped UMD marker, restored as literal text
+    Literal(String),
+}
+
+/// Code sections removed by [`mask_code_sections`]
+#[derive(Debug, Default)]
+pub struct CodeMask {
+    sections: Vec<MaskedCode>,
+}
+
+impl CodeMask {
+    fn placeholder(&mut self, section: MaskedCode) -> String {
+        self.sections.push(section);
+        format!("UMD_CODE_MASK_{}_END", self.sections.len() - 1)
+    }
+
+    /// Whether no code section was masked
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+}
+
+/// Break up user text that looks like an internal pipeline marker
+///
+/// Preprocessing hands content to later passes through text markers such as
+/// `{{INLINE_PLUGIN:...}}`, `{{BLOCK_DECORATION_B64:...}}` or
+/// `UMD_TABLE_MARKER_0_END`. Typed into a document, they would be expanded
+/// by postprocessing as if the parser had produced them, smuggling decoded
+/// HTML past the sanitizer. Runs before any pass creates a marker and puts a
+/// noncharacter inside every lookalike; [`unguard_marker_lookalikes`] removes
+/// it from the final HTML, so the text reads as written.
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::preprocessor::{guard_marker_lookalikes, unguard_marker_lookalikes};
+///
+/// let guarded = guard_marker_lookalikes("{{UNDERLINE:x:UNDERLINE}}");
+/// assert_ne!(guarded, "{{UNDERLINE:x:UNDERLINE}}");
+/// assert_eq!(unguard_marker_lookalikes(&guarded), "{{UNDERLINE:x:UNDERLINE}}");
+/// ```
+pub fn guard_marker_lookalikes(input: &str) -> Cow<'_, str> {
+    if !input.contains("{{") && !input.contains("}}") && !input.contains("UMD_") {
+        return Cow::Borrowed(input);
+    }
+    MARKER_LOOKALIKE.replace_all(input, |caps: &Captures| {
+        if caps.get(1).is_some() {
+            caps[0].to_string()
+        } else if let Some(name) = caps.get(2) {
+            format!("{{{}{{{}", MARKER_GUARD, name.as_str())
+        } else if let Some(name) = caps.get(3) {
+            format!(":{}{}", MARKER_GUARD, name.as_str())
+        } else {
+            format!("UMD{}_{}_", MARKER_GUARD, &caps[4])
+        }
+    })
+}
+
+/// Remove the guards added by [`guard_marker_lookalikes`]
+pub fn unguard_marker_lookalikes(html: &str) -> Cow<'_, str> {
+    if !html.contains(MARKER_GUARD) && !html.contains(MARKER_GUARD_ENCODED) {
+        return Cow::Borrowed(html);
+    }
+    Cow::Owned(
+        html.replace(MARKER_GUARD, "")
+            .replace(MARKER_GUARD_ENCODED, ""),
+    )
+}
+
+/// Mask inline code spans in a run of non-fenced lines
+fn mask_inline_code(text: &str, mask: &mut CodeMask) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    // Start of the current line in `result`, and how far it was searched
+    let (mut line_start, mut scanned) = (0, 0);
+
+    while let Some(pos) = rest.find(['`', '\\']) {
+        result.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+
+        // Backslash escapes (`\``) never open a code span
+        if rest.starts_with('\\') {
+            if let Some(newline) = result[scanned..].rfind('\n') {
+                line_start = scanned + newline + 1;
+            }
+            scanned = result.len();
+            let line = result[line_start..].trim_start();
+            if let Some(marker) = escaped_marker(&rest[1..], line) {
+                result.push_str(&mask.placeholder(MaskedCode::Literal(marker.to_string())));
+                rest = &rest[1 + marker.len()..];
+                continue;
+            }
+            let escaped = rest[1..].chars().next().map_or(0, char::len_utf8);
+            result.push_str(&rest[..1 + escaped]);
+            rest = &rest[1 + escaped..];
+            continue;
+        }
+
+        let run = rest.bytes().take_while(|b| *b == b'`').count();
+        let after_open = &rest[run..];
+        let close = find_backtick_run(after_open, run);
+        match close {
+            Some(end) => {
+                result.push_str(&rest[..run]);
+                let content = &after_open[..end];
+                result.push_str(&mask.placeholder(MaskedCode::Inline(content.to_string())));
+                result.push_str(&rest[..run]);
+                rest = &after_open[end + run..];
+            }
+            None => {
+                result.push_str(&rest[..run]);
+                rest = after_open;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// UMD marker following a backslash, given the text before it on the line
+///
+/// In GFM table rows `\|` keeps its meaning of an escaped cell separator.
+fn escaped_marker<'a>(text: &'a str, line: &str) -> Option<&'a str> {
+    if line.is_empty()
+        && let Some(prefix) = ESCAPABLE_BLOCK_PREFIX.find(text)
+    {
+        return Some(prefix.as_str());
+    }
+    ESCAPABLE_MARKERS
+        .iter()
+        .filter(|marker| **marker != "||" || !line.starts_with('|'))
+        .find(|marker| text.starts_with(**marker))
+        .map(|marker| &text[..marker.len()])
+}
+
+/// Find a run of exactly `len` backticks, stopping at a blank line
+fn find_backtick_run(text: &str, len: usize) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'`' => {
+                let run = bytes[i..].iter().take_while(|b| **b == b'`').count();
+                if run == len {
+                    return Some(i);
+                }
+                i += run;
+            }
+            b'\n'
+                if text[i + 1..]
+                    .lines()
+                    .next()
+                    .is_some_and(|l| l.trim().is_empty()) =>
+            {
+                return None;
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// Mask the contents of fenced code blocks and inline code spans
+///
+/// Runs before every other preprocessing pass, so the UMD syntax handled
+/// there (task lists, comments, definition lists, tables, plugins,
+/// decorations) can never rewrite code. Fence lines are kept so filename
+/// syntax and the Markdown structure still work; the contents are replaced
+/// by placeholders that [`unmask_code_sections`] restores after parsing.
+///
+/// # Arguments
+///
+/// * `input` - The raw markup input
+///
+/// # Returns
+///
+/// Tuple of (masked input, masked sections)
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::preprocessor::{mask_code_sections, unmask_code_sections};
+///
+/// let (masked, mask) = mask_code_sections("Call `__init__` now");
+/// assert_eq!(masked, "Call `UMD_CODE_MASK_0_END` now");
+/// assert_eq!(
+///     unmask_code_sections("<p>Call <code>UMD_CODE_MASK_0_END</code> now</p>", &mask),
+///     "<p>Call <code>__init__</code> now</p>"
+/// );
+/// ```
+pub fn mask_code_sections(input: &str) -> (Cow<'_, str>, CodeMask) {
+    let mut mask = CodeMask::default();
+    if !input.contains(['`', '~', '\\']) {
+        return (Cow::Borrowed(input), mask);
+    }
+
+    let mut result = String::with_capacity(input.len());
+    let mut text = String::new();
+    let mut lines = input.split_inclusive('\n');
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_start();
+        let fence = parse_fence_open_line(trimmed.trim_end_matches(['\n', '\r']))
+            .filter(|(_, ch, _, info)| *ch == '~' || !info.contains('`'));
+        let Some((_, fence_char, fence_len, _)) = fence else {
+            text.push_str(line);
+            continue;
+        };
+
+        result.push_str(&mask_inline_code(&text, &mut mask));
+        text.clear();
+        result.push_str(line);
+
+        let indent_str = &line[..line.len() - trimmed.len()];
+        let mut body = String::new();
+        let mut close = None;
+        for line in lines.by_ref() {
+            if is_fence_close_line(line.trim(), fence_char, fence_len) {
+                close = Some(line);
+                break;
+            }
+            body.push_str(line);
+        }
+
+        if !body.is_empty() {
+            if !body.ends_with('\n') {
+                body.push('\n');
+            }
+            let indent = indent_str.chars().count();
+            result.push_str(indent_str);
+            result.push_str(&mask.placeholder(MaskedCode::Block { body, indent }));
+            result.push('\n');
+        }
+        if let Some(close) = close {
+            result.push_str(close);
+        }
+    }
+    result.push_str(&mask_inline_code(&text, &mut mask));
+
+    (Cow::Owned(result), mask)
+}
+
+/// Strip up to `indent` leading whitespace characters from every line
+fn strip_indent(body: &str, indent: usize) -> String {
+    body.split_inclusive('\n')
+        .map(|line| {
+            let strip: usize = line
+                .chars()
+                .take(indent)
+                .take_while(|c| *c == ' ' || *c == '\t')
+                .map(char::len_utf8)
+                .sum();
+            &line[strip..]
+        })
+        .collect()
+}
+
+/// Normalize inline code content the way CommonMark renders it
+fn normalize_code_span(content: &str) -> String {
+    let content = content.replace(['\r', '\n'], " ");
+    let stripped = content
+        .strip_prefix(' ')
+        .and_then(|c| c.strip_suffix(' '))
+        .filter(|_| !content.trim().is_empty());
+    stripped.unwrap_or(&content).to_string()
+}
+
+/// Restore code sections masked by [`mask_code_sections`]
+///
+/// Placeholders rendered inside `<code>` get the content as comrak would
+/// have rendered it; placeholders that ended up elsewhere (plugin content,
+/// UMD table cells) get the original text, escaped.
+///
+/// # Arguments
+///
+/// * `html` - HTML containing placeholders
+/// * `mask` - Sections returned by [`mask_code_sections`]
+///
+/// # Returns
+///
+/// HTML with code restored
+pub fn unmask_code_sections<'a>(html: &'a str, mask: &CodeMask) -> Cow<'a, str> {
+    unmask(html, mask, false)
+}
+
+/// Restore backslash-escaped UMD markers masked by [`mask_code_sections`]
+///
+/// Runs after all extensions so the restored markers are never transformed.
+///
+/// # Arguments
+///
+/// * `html` - HTML containing placeholders
+/// * `mask` - Sections returned by [`mask_code_sections`]
+///
+/// # Returns
+///
+/// HTML with escaped markers and any remaining code restored
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::preprocessor::{mask_code_sections, unmask_escaped_markers};
+///
+/// let (masked, mask) = mask_code_sections(r"\&color(red){x};");
+/// assert_eq!(masked, "UMD_CODE_MASK_0_ENDcolor(red){x};");
+/// assert_eq!(unmask_escaped_markers(&masked, &mask), "&amp;color(red){x};");
+/// ```
+pub fn unmask_escaped_markers<'a>(html: &'a str, mask: &CodeMask) -> Cow<'a, str> {
+    unmask(html, mask, true)
+}
+
+fn unmask<'a>(html: &'a str, mask: &CodeMask, literals: bool) -> Cow<'a, str> {
+    if mask.is_empty() {
+        return Cow::Borrowed(html);
+    }
+    CODE_MASK_PLACEHOLDER.replace_all(html, |caps: &Captures| {
+        let tag = caps.get(1).map_or("", |m| m.as_str());
+        let newline = caps.get(3).map_or("", |m| m.as_str());
+        let index: usize = caps[2].parse().unwrap_or(usize::MAX);
+        match mask.sections.get(index) {
+            Some(MaskedCode::Block { body, indent }) if !tag.is_empty() => {
+                format!("{}{}", tag, text_escape(&strip_indent(body, *indent)))
+            }
+            Some(MaskedCode::Block { body, .. }) => text_escape(body).into_owned(),
+            Some(MaskedCode::Inline(content)) if !tag.is_empty() => format!(
+                "{}{}{}",
+                tag,
+                text_escape(&normalize_code_span(content)),
+                newline
+            ),
+            Some(MaskedCode::Inline(content)) => {
+                format!("{}{}", text_escape(content), newline)
+            }
+            Some(MaskedCode::Literal(marker)) if literals => {
+                format!("{}{}{}", tag, text_escape(marker), newline)
+            }
+            Some(MaskedCode::Literal(_)) | None => caps[0].to_string(),
+        }
+    })
+}
+
 /// Process definition lists (:term|definition syntax)
 ///
 /// Converts consecutive lines starting with `:term|definition` into
-/// marker placeholders that will be converted to HTML later.
+/// marker placeholders that will be converted to HTML later. Terms and
+/// definitions stay in the Markdown text, so comrak renders their inline
+/// formatting. A trailing `|h` on any item selects the horizontal layout
+/// for the whole list.
 pub fn process_definition_lists(input: &str) -> String {
     let mut result = Vec::new();
     let mut lines = input.lines().peekable();
 
     while let Some(line) = lines.next() {
-        // Check if this line starts a definition list
-        if line.trim_start().starts_with(':') && line.contains('|') {
+        // Check if this line starts a definition list, possibly in a blockquote
+        let (quote, content) = split_blockquote_prefix(line);
+        if content.trim_start().starts_with(':') && content.contains('|') {
+            let indent = &line[..line.len() - content.trim_start().len()];
+            // Paragraph breaks stay inside the blockquote
+            let blank = quote.trim_end().to_string();
             let mut dl_items = Vec::new();
+            let mut horizontal = false;
 
             // Collect consecutive definition list items
-            let mut current_line = line;
+            let mut current_line = content;
             loop {
                 if let Some(stripped) = current_line.trim_start().strip_prefix(':') {
                     if let Some((term, definition)) = stripped.split_once('|') {
+                        let definition = match definition.trim_end().rsplit_once('|') {
+                            Some((definition, "h")) => {
+                                horizontal = true;
+                                definition
+                            }
+                            _ => definition,
+                        };
                         dl_items.push((term.trim().to_string(), definition.trim().to_string()));
                     }
                 }
 
                 // Check if next line is also a definition list item
-                match lines.peek() {
-                    Some(next_line)
-                        if next_line.trim_start().starts_with(':') && next_line.contains('|') =>
-                    {
-                        current_line = lines.next().unwrap();
-                    }
-                    _ => break,
+                match lines.next_if(|next_line| {
+                    let (next_quote, next_content) = split_blockquote_prefix(next_line);
+                    next_quote == quote
+                        && next_content.trim_start().starts_with(':')
+                        && next_content.contains('|')
+                }) {
+                    Some(next_line) => current_line = split_blockquote_prefix(next_line).1,
+                    None => break,
                 }
             }
 
-            // Create marker for the definition list
+            // Create markers for the definition list, as a paragraph of its own
             if !dl_items.is_empty() {
-                let items_json = serde_json::to_string(&dl_items).unwrap();
+                result.push(blank.clone());
                 result.push(format!(
-                    "{{{{DEFINITION_LIST:{}:DEFINITION_LIST}}}}",
-                    items_json
+                    "{}{{{{DEFINITION_LIST:{}:DEFINITION_LIST}}}}",
+                    indent,
+                    if horizontal { "horizontal" } else { "default" }
                 ));
+                for (term, definition) in dl_items {
+                    result.push(format!(
+                        "{}{{{{DEFINITION_TERM:{}:DEFINITION_TERM}}}}{{{{DEFINITION_DESC:{}:DEFINITION_DESC}}}}",
+                        indent, term, definition
+                    ));
+                }
+                result.push(format!("{}{{{{/DEFINITION_LIST}}}}", indent));
+                result.push(blank);
             }
         } else {
             result.push(line.to_string());
@@ -341,23 +829,6 @@ pub fn process_definition_lists(input: &str) -> String {
     result.join("\n")
 }
 
-/// Convert Discord-style underline (__text__) to placeholder before Markdown parsing
-///
-/// This prevents CommonMark from converting __text__ to <strong>
-pub fn preprocess_discord_underline(input: &str) -> String {
-    DISCORD_UNDERLINE
-        .replace_all(input, "{{UNDERLINE:$1:UNDERLINE}}")
-        .to_string()
-}
-
-/// Restore Discord-style underline placeholders to <u> tags
-///
-/// This should be called after Markdown parsing
-pub fn postprocess_discord_underline(html: &str) -> String {
-    html.replace("{{UNDERLINE:", "<u>")
-        .replace(":UNDERLINE}}", "</u>")
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -394,13 +865,51 @@ mod tests {
         assert!(output.contains("// code comment"));
     }
 
+    #[test]
+    fn test_remove_comment_plugins() {
+        assert_eq!(
+            remove_comment_plugins("a&comment{x {y}};b&comment(p(1)){z};c&comment;d"),
+            "abcd"
+        );
+        assert_eq!(
+            remove_comment_plugins("@comment(by alice){single}\n@comment(todo)\n@comment{{\n}}"),
+            "\n\n"
+        );
+        // Other plugins and words are kept
+        assert_eq!(
+            remove_comment_plugins("@comments(x) &commentary; comment"),
+            "@comments(x) &commentary; comment"
+        );
+    }
+
     #[test]
     fn test_definition_list() {
         let input = ":term1|definition1\n:term2|definition2\nregular text";
         let output = process_definition_lists(input);
-        assert!(output.contains("{{DEFINITION_LIST:"));
-        assert!(output.contains("DEFINITION_LIST}}"));
-        assert!(output.contains("regular text"));
+        assert!(output.contains("{{DEFINITION_LIST:default:DEFINITION_LIST}}"));
+        assert!(output.contains(
+            "{{DEFINITION_TERM:term1:DEFINITION_TERM}}{{DEFINITION_DESC:definition1:DEFINITION_DESC}}"
+        ));
+        assert!(output.contains("{{/DEFINITION_LIST}}\n\nregular text"));
+    }
+
+    #[test]
+    fn test_definition_list_horizontal_flag() {
+        let output = process_definition_lists(":a|b|c|h\n:d|e");
+        assert!(output.contains("{{DEFINITION_LIST:horizontal:DEFINITION_LIST}}"));
+        assert!(output.contains("{{DEFINITION_DESC:b|c:DEFINITION_DESC}}"));
+    }
+
+    #[test]
+    fn test_definition_list_in_blockquote() {
+        let output = process_definition_lists("> Intro\n> :a|b\n> :c|d\n> Outro");
+        assert_eq!(
+            output,
+            "> Intro\n>\n> {{DEFINITION_LIST:default:DEFINITION_LIST}}\n\
+             > {{DEFINITION_TERM:a:DEFINITION_TERM}}{{DEFINITION_DESC:b:DEFINITION_DESC}}\n\
+             > {{DEFINITION_TERM:c:DEFINITION_TERM}}{{DEFINITION_DESC:d:DEFINITION_DESC}}\n\
+             > {{/DEFINITION_LIST}}\n>\n> Outro"
+        );
     }
 
     #[test]
@@ -417,33 +926,6 @@ mod tests {
         assert!(output.contains("- [-] Maybe"));
     }
 
-    #[test]
-    fn test_preprocess_discord_underline() {
-        let input = "This is __underlined__ text.";
-        let output = preprocess_discord_underline(input);
-        assert!(output.contains("{{UNDERLINE:underlined:UNDERLINE}}"));
-        assert!(!output.contains("__underlined__"));
-    }
-
-    #[test]
-    fn test_postprocess_discord_underline() {
-        let input = "<p>This is {{UNDERLINE:underlined:UNDERLINE}} text.</p>";
-        let output = postprocess_discord_underline(input);
-        assert_eq!(output, "<p>This is <u>underlined</u> text.</p>");
-    }
-
-    #[test]
-    fn test_discord_underline_roundtrip() {
-        let input = "Text with __underline__ here.";
-        let preprocessed = preprocess_discord_underline(input);
-        let html = format!(
-            "<p>{}</p>",
-            preprocessed.replace("__underline__", "{{UNDERLINE:underline:UNDERLINE}}")
-        );
-        let output = postprocess_discord_underline(&html);
-        assert!(output.contains("<u>underline</u>"));
-    }
-
     #[test]
     fn test_preprocess_code_block_filename_with_language() {
         let input = "```rust:src/main.rs\nfn main() {}\n```";
@@ -458,10 +940,90 @@ mod tests {
         assert!(output.contains("``` umd-nolang umd-filename:config.yml"));
     }
 
+    #[test]
+    fn test_preprocess_code_block_attribute_block() {
+        let output = preprocess_code_block_filenames("```rust:main.rs {.numbered}\nx\n```");
+        assert!(output.contains("``` rust umd-filename:main.rs {.numbered}"));
+        let output = preprocess_code_block_filenames("```{#a}\nx\n```");
+        assert!(output.contains("``` umd-nolang {#a}"));
+    }
+
     #[test]
     fn test_preprocess_code_block_filename_ignores_inside_block() {
         let input = "```txt\nrust:main.rs\n```";
         let output = preprocess_code_block_filenames(input);
         assert!(output.contains("rust:main.rs"));
     }
+
+    #[test]
+    fn test_mask_code_sections() {
+        let input = "a `x` ``y ` z``\n\\`no`\n\n```rust:main.rs\n  __u__\n```\n~~~\nopen";
+        let (masked, mask) = mask_code_sections(input);
+        assert_eq!(
+            masked,
+            "a `UMD_CODE_MASK_0_END` ``UMD_CODE_MASK_1_END``\n\\`no`\n\n```rust:main.rs\nUMD_CODE_MASK_2_END\n```\n~~~\nUMD_CODE_MASK_3_END\n"
+        );
+        assert_eq!(
+            unmask_code_sections("<code>UMD_CODE_MASK_1_END</code>", &mask),
+            "<code>y ` z</code>"
+        );
+        assert_eq!(
+            unmask_code_sections(
+                "<pre><code class=\"language-rust\">UMD_CODE_MASK_2_END\n</code></pre>",
+                &mask
+            ),
+            "<pre><code class=\"language-rust\">  __u__\n</code></pre>"
+        );
+    }
+
+    #[test]
+    fn test_code_span_does_not_cross_paragraphs() {
+        let (masked, _) = mask_code_sections("a `b\n\nc` d");
+        assert_eq!(masked, "a `b\n\nc` d");
+    }
+
+    #[test]
+    fn test_escaped_markers() {
+        let (masked, mask) = mask_code_sections("\\CENTER: a \\@b `\\@c`\n| x \\|| y |");
+        assert_eq!(
+            masked,
+            "UMD_CODE_MASK_0_END a UMD_CODE_MASK_1_ENDb `UMD_CODE_MASK_2_END`\n| x \\|| y |"
+        );
+        assert_eq!(
+            unmask_code_sections(&masked, &mask),
+            "UMD_CODE_MASK_0_END a UMD_CODE_MASK_1_ENDb `\\@c`\n| x \\|| y |"
+        );
+        assert_eq!(
+            unmask_escaped_markers(&masked, &mask),
+            "CENTER: a @b `\\@c`\n| x \\|| y |"
+        );
+    }
+
+    #[test]
+    fn test_guard_marker_lookalikes() {
+        for input in [
+            "{{INLINE_PLUGIN:kbd::PHNjcmlwdD4=:INLINE_PLUGIN}}",
+            "{{UMD_BLOCKQUOTE_START}}",
+            "a :UNDERLINE}} b",
+            "UMD_TABLE_MARKER_0_END",
+            "UMD_CODE_MASK_0_END",
+        ] {
+            let guarded = guard_marker_lookalikes(input);
+            assert!(guarded.contains(MARKER_GUARD), "{}", guarded);
+            assert_eq!(unguard_marker_lookalikes(&guarded), input);
+        }
+
+        // Plugin bodies, lowercase braces and prose are left alone
+        for input in [
+            "@callout(info){{NOTE: x}}",
+            "{{name}}",
+            "{{Note: x}}",
+            "UMD_TABLE",
+        ] {
+            assert_eq!(guard_marker_lookalikes(input), input);
+        }
+
+        let encoded = format!("<a href=\"/{}x\">", MARKER_GUARD_ENCODED);
+        assert_eq!(unguard_marker_lookalikes(&encoded), "<a href=\"/x\">");
+    }
 }
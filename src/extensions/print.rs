@@ -0,0 +1,112 @@
+//! Print-friendly output
+//!
+//! With `ParserOptions.print_mode`, the final HTML is adjusted for PDF export
+//! and paper, where nothing can be clicked:
+//!
+//! - spoilers are revealed and lose their button role
+//! - `<details>` are open
+//! - alerts keep only a colored border (`alert-info` → `border-info`) and
+//!   are no longer live regions
+//! - popover triggers become plain text followed by the popover content
+//! - `<video>` / `<audio>` are replaced by their fallback link
+//!
+//! Video and map iframes are rendered as links directly by
+//! [`embeds`](super::embeds), and sorting hooks are not added.
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+/// Regex to detect the opening tag of a spoiler toggle
+static SPOILER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"<span class="spoiler" role="button" tabindex="0" aria-expanded="false"(?: aria-controls="[^"]*")?>"#,
+    )
+    .unwrap()
+});
+
+/// Regex to detect `<details>` opening tags
+static DETAILS: Lazy<Regex> = Lazy::new(|| Regex::new(r"<details(\s[^>]*)?>").unwrap());
+
+/// Regex to detect the opening tag of an alert
+static ALERT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"<div class="alert alert-(\w+)([^"]*)" role="alert"(?: aria-live="[^"]*")?>"#)
+        .unwrap()
+});
+
+/// Regex to detect a popover trigger and the opening tag of its content
+static POPOVER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?s)<button command="show-popover" commandfor="[^"]*">(.*?)</button><div id="([^"]*)" popover>"#,
+    )
+    .unwrap()
+});
+
+/// Regex to detect a media element and its fallback link
+static MEDIA: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?s)<(?:video|audio)\b[^>]*>.*?(<a href="[^"]*" download class="download-link (?:video|audio)-fallback"[^>]*>.*?</a>)\s*</(?:video|audio)>"#,
+    )
+    .unwrap()
+});
+
+/// Apply print-friendly rendering
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::print::apply_print_mode;
+///
+/// let html = "<div class=\"alert alert-warning\" role=\"alert\"><span class=\"spoiler\" role=\"button\" tabindex=\"0\" aria-expanded=\"false\">x</span></div>";
+/// assert_eq!(
+///     apply_print_mode(html),
+///     "<div class=\"alert border-warning\" role=\"note\"><span class=\"spoiler spoiler-revealed\">x</span></div>"
+/// );
+/// ```
+pub fn apply_print_mode(html: &str) -> String {
+    let result = SPOILER.replace_all(html, "<span class=\"spoiler spoiler-revealed\">");
+    let result = DETAILS.replace_all(&result, |caps: &Captures| {
+        let attrs = caps.get(1).map_or("", |attrs| attrs.as_str());
+        if attrs
+            .split_whitespace()
+            .any(|attr| attr == "open" || attr.starts_with("open="))
+        {
+            caps[0].to_string()
+        } else {
+            format!("<details{} open>", attrs)
+        }
+    });
+    let result = ALERT.replace_all(&result, "<div class=\"alert border-$1$2\" role=\"note\">");
+    let result = POPOVER.replace_all(
+        &result,
+        "<span class=\"umd-popover-trigger\">$1</span><div id=\"$2\" class=\"umd-popover-content\">",
+    );
+    MEDIA.replace_all(&result, "$1").into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_details_and_popovers() {
+        assert_eq!(
+            apply_print_mode("<details><summary>a</summary></details><details open>"),
+            "<details open><summary>a</summary></details><details open>"
+        );
+        assert_eq!(
+            apply_print_mode(
+                "<button command=\"show-popover\" commandfor=\"p1\">more</button><div id=\"p1\" popover><p>x</p></div>"
+            ),
+            "<span class=\"umd-popover-trigger\">more</span><div id=\"p1\" class=\"umd-popover-content\"><p>x</p></div>"
+        );
+    }
+
+    #[test]
+    fn test_media_fallback_links() {
+        let html = "<figure class=\"w-100\">\n<video controls>\n  <source src=\"a.mp4\" type=\"video/mp4\" />\n  <a href=\"a.mp4\" download class=\"download-link video-fallback\" aria-label=\"Download a\">a</a>\n</video>\n</figure>";
+        assert_eq!(
+            apply_print_mode(html),
+            "<figure class=\"w-100\">\n<a href=\"a.mp4\" download class=\"download-link video-fallback\" aria-label=\"Download a\">a</a>\n</figure>"
+        );
+    }
+}
@@ -0,0 +1,98 @@
+//! QR code plugin (`qr` feature)
+//!
+//! `&qr(url);` and `@qr(url, size)` are rendered as inline SVG, so print
+//! pages and posters need no frontend script. The SVG is drawn in module
+//! units (`viewBox`) with a quiet zone and scaled to `size` pixels
+//! (default [`DEFAULT_QR_SIZE`]); `Locale.qr_code` labels it for screen
+//! readers. Text that does not fit into a QR code is left to the generic
+//! plugin template.
+
+use qrcode::QrCode;
+use qrcode::render::svg;
+
+use crate::html_builder::attr_escape;
+use crate::parser::Locale;
+
+/// Width and height in pixels of a QR code without a size argument
+pub const DEFAULT_QR_SIZE: u32 = 128;
+
+/// Smallest and largest accepted size in pixels
+const QR_SIZE_RANGE: (u32, u32) = (32, 1024);
+
+/// Render `&qr` / `@qr` arguments (`text[, size]`) as an SVG QR code
+///
+/// The text is the whole argument unless its last comma-separated part is
+/// a number. Block codes are wrapped in `<div class="umd-qr">`.
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::qr::render_qr_code;
+/// use umd::parser::Locale;
+///
+/// let html = render_qr_code("https://example.com/?a=1,2, 200", true, &Locale::en()).unwrap();
+/// assert!(html.starts_with("<div class=\"umd-qr\"><svg "));
+/// assert!(html.contains(r#"width="200" height="200""#));
+/// assert!(html.contains(r#"aria-label="QR code: https://example.com/?a=1,2""#));
+/// ```
+pub fn render_qr_code(args: &str, block: bool, locale: &Locale) -> Option<String> {
+    let args = html_escape::decode_html_entities(args);
+    let (text, size) = match args.rsplit_once(',') {
+        Some((text, size)) if size.trim().parse::<u32>().is_ok() => {
+            let size = size.trim().parse::<u32>().ok()?;
+            (text.trim(), size.clamp(QR_SIZE_RANGE.0, QR_SIZE_RANGE.1))
+        }
+        _ => (args.trim(), DEFAULT_QR_SIZE),
+    };
+    if text.is_empty() {
+        return None;
+    }
+
+    let code = QrCode::new(text.as_bytes()).ok()?;
+    let image = code
+        .render::<svg::Color>()
+        .module_dimensions(1, 1)
+        .quiet_zone(true)
+        .build();
+    // Replace the XML declaration and pixel size of the rendered document
+    let (_, drawing) = image.split_once(" viewBox=")?;
+    let svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" class=\"umd-qr-code\" width=\"{}\" height=\"{}\" role=\"img\" aria-label=\"{}\" viewBox={}",
+        size,
+        size,
+        attr_escape(&locale.qr_code.replace("{text}", text)),
+        drawing
+    );
+    Some(if block {
+        format!("<div class=\"umd-qr\">{}</div>", svg)
+    } else {
+        svg
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qr_code() {
+        let locale = Locale::en();
+        let html = render_qr_code("https://example.com/?a=1&amp;b=<2>", false, &locale).unwrap();
+        assert!(html.starts_with(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" class="umd-qr-code" width="128" height="128" role="img" aria-label="QR code: https://example.com/?a=1&amp;b=&lt;2&gt;" viewBox="0 0 "#
+        ));
+        assert!(html.ends_with("\"/></svg>"));
+        assert!(!html.contains("<?xml"));
+
+        // Same text as the decoded entity, clamped size
+        let decoded = render_qr_code("https://example.com/?a=1&b=<2>, 8", false, &locale).unwrap();
+        assert!(decoded.contains(r#"width="32" height="32""#));
+        assert_eq!(
+            decoded.split_once("viewBox").unwrap().1,
+            html.split_once("viewBox").unwrap().1
+        );
+
+        assert!(render_qr_code(" , 100", true, &locale).is_none());
+        assert!(render_qr_code(&"x".repeat(8000), true, &locale).is_none());
+    }
+}
@@ -0,0 +1,154 @@
+//! HTML5 / XHTML serialization of the final HTML
+//!
+//! The passes emit void elements both ways (`<br>` from comrak, `<br />`
+//! from decorations and media). [`HtmlSyntax::Html5`] writes every void
+//! element without the slash; [`HtmlSyntax::Xhtml`] produces well-formed
+//! XML for EPUB and other XML pipelines:
+//!
+//! - void elements are self-closed (`<br />`)
+//! - attributes without a value get an empty one (`controls=""`), and
+//!   unquoted values are quoted
+//! - named character references other than the five XML ones become
+//!   numeric references (`&nbsp;` → `&#160;`)
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use std::borrow::Cow;
+
+use crate::parser::HtmlSyntax;
+
+/// Regex to detect opening and self-closing tags
+static START_TAG: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"<([a-zA-Z][a-zA-Z0-9-]*)((?:\s+[^\s"'>/=]+(?:\s*=\s*(?:"[^"]*"|'[^']*'|[^\s"'=<>`]+))?)*)\s*(/?)>"#,
+    )
+    .unwrap()
+});
+
+/// Regex to detect one attribute inside a tag
+static ATTRIBUTE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"([^\s"'>/=]+)(?:\s*=\s*("[^"]*"|'[^']*'|[^\s"'=<>`]+))?"#).unwrap());
+
+/// Regex to detect named character references
+static NAMED_REFERENCE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"&([a-zA-Z][a-zA-Z0-9]*);").unwrap());
+
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Named references defined by XML itself
+const XML_REFERENCES: &[&str] = &["amp", "lt", "gt", "quot", "apos"];
+
+/// Serialize `html` with the void element and attribute syntax of `syntax`
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::serialization::serialize;
+/// use umd::parser::HtmlSyntax;
+///
+/// let html = "<p>A<br>B<wbr />&nbsp;<video controls></video></p>";
+/// assert_eq!(
+///     serialize(html, HtmlSyntax::Html5),
+///     "<p>A<br>B<wbr>&nbsp;<video controls></video></p>"
+/// );
+/// assert_eq!(
+///     serialize(html, HtmlSyntax::Xhtml),
+///     "<p>A<br />B<wbr />&#160;<video controls=\"\"></video></p>"
+/// );
+/// ```
+pub fn serialize(html: &str, syntax: HtmlSyntax) -> Cow<'_, str> {
+    let tags = START_TAG.replace_all(html, |caps: &Captures| {
+        let name = &caps[1];
+        let void = VOID_ELEMENTS.contains(&name.to_ascii_lowercase().as_str());
+        match syntax {
+            HtmlSyntax::Html5 if void => format!("<{}{}>", name, &caps[2]),
+            HtmlSyntax::Html5 => caps[0].to_string(),
+            HtmlSyntax::Xhtml => {
+                let attributes = xhtml_attributes(&caps[2]);
+                let close = if void || !caps[3].is_empty() {
+                    " /"
+                } else {
+                    ""
+                };
+                format!("<{}{}{}>", name, attributes, close)
+            }
+        }
+    });
+    if syntax == HtmlSyntax::Html5 || !tags.contains('&') {
+        return tags;
+    }
+
+    match NAMED_REFERENCE.replace_all(&tags, |caps: &Captures| {
+        if XML_REFERENCES.contains(&&caps[1]) {
+            return caps[0].to_string();
+        }
+        let decoded = html_escape::decode_html_entities(&caps[0]);
+        if decoded == caps[0] {
+            // Unknown reference: keep it as text
+            return format!("&amp;{};", &caps[1]);
+        }
+        decoded
+            .chars()
+            .map(|c| format!("&#{};", u32::from(c)))
+            .collect()
+    }) {
+        Cow::Owned(replaced) => Cow::Owned(replaced),
+        Cow::Borrowed(_) => tags,
+    }
+}
+
+/// Attributes of a start tag with every value present and quoted
+fn xhtml_attributes(attributes: &str) -> String {
+    let mut serialized = String::with_capacity(attributes.len());
+    for caps in ATTRIBUTE.captures_iter(attributes) {
+        serialized.push(' ');
+        serialized.push_str(&caps[1]);
+        match caps.get(2).map(|value| value.as_str()) {
+            Some(value) if value.starts_with(['"', '\'']) => {
+                serialized.push('=');
+                serialized.push_str(value);
+            }
+            Some(value) => {
+                serialized.push_str("=\"");
+                serialized.push_str(value);
+                serialized.push('"');
+            }
+            None => serialized.push_str("=\"\""),
+        }
+    }
+    serialized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html5_keeps_foreign_self_closing_elements() {
+        let html = "<img src=\"a.png\" alt=\"A\" /><svg><path d=\"M 0 0\" /></svg>";
+        assert_eq!(
+            serialize(html, HtmlSyntax::Html5),
+            "<img src=\"a.png\" alt=\"A\"><svg><path d=\"M 0 0\" /></svg>"
+        );
+    }
+
+    #[test]
+    fn test_xhtml_attributes() {
+        let html = "<input type=checkbox checked disabled=\"\"><a href='/f' download>F</a><section class=\"footnotes\" data-footnotes>";
+        assert_eq!(
+            serialize(html, HtmlSyntax::Xhtml),
+            "<input type=\"checkbox\" checked=\"\" disabled=\"\" /><a href='/f' download=\"\">F</a><section class=\"footnotes\" data-footnotes=\"\">"
+        );
+    }
+
+    #[test]
+    fn test_xhtml_references() {
+        assert_eq!(
+            serialize("&lt;&amp;&hellip;&bogus;&#39;", HtmlSyntax::Xhtml),
+            "&lt;&amp;&#8230;&amp;bogus;&#39;"
+        );
+    }
+}
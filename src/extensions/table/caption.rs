@@ -0,0 +1,184 @@
+//! Table captions
+//!
+//! A `|c caption` row above a table, or a Pandoc-style `Table: caption` line
+//! below it (directly or after one blank line), becomes the table's
+//! `<caption>`. Both work for GFM and UMD tables.
+//!
+//! The caption text is left in the Markdown as a marker paragraph, so comrak
+//! renders its inline formatting; [`apply_table_captions`] then moves it into
+//! the adjacent table. A caption without a table next to it is shown as it
+//! was written.
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use crate::parser::CaptionSide;
+
+/// Regex to detect a rendered caption placed above its table
+static CAPTION_BEFORE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?s)<p>\{\{TABLE_CAPTION_BEFORE:(.*?):TABLE_CAPTION_BEFORE\}\}</p>(\s*)(<table\b[^>]*>)?",
+    )
+    .unwrap()
+});
+
+/// Regex to detect a rendered caption placed below its table
+static CAPTION_AFTER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?s)(</table>)?(\s*)<p>\{\{TABLE_CAPTION_AFTER:(.*?):TABLE_CAPTION_AFTER\}\}</p>")
+        .unwrap()
+});
+
+/// Regex to detect the class attribute of a table tag
+static CLASS_ATTR: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\sclass="([^"]*)""#).unwrap());
+
+/// Caption of a `|c caption` row, if `line` is one
+pub(crate) fn caption_row(line: &str) -> Option<&str> {
+    let caption = line.trim().strip_prefix("|c ")?;
+    let caption = caption.strip_suffix('|').unwrap_or(caption).trim();
+    (!caption.is_empty()).then_some(caption)
+}
+
+/// Caption of a `Table: caption` line, if `line` is one
+pub(crate) fn caption_line(line: &str) -> Option<&str> {
+    let caption = line.trim().strip_prefix("Table:")?.trim();
+    (!caption.is_empty()).then_some(caption)
+}
+
+/// Marker paragraph for a caption written above the table
+///
+/// `prefix` is the indentation of a table inside a list item, or the `>`
+/// markers of a table in a blockquote.
+pub(crate) fn marker_before(caption: &str, prefix: &str) -> String {
+    format!(
+        "{}\n{}{{{{TABLE_CAPTION_BEFORE:{}:TABLE_CAPTION_BEFORE}}}}\n{}",
+        prefix.trim_end(),
+        prefix,
+        caption,
+        prefix.trim_end()
+    )
+}
+
+/// Marker paragraph for a caption written below the table (see
+/// [`marker_before`] for `prefix`)
+pub(crate) fn marker_after(caption: &str, prefix: &str) -> String {
+    format!(
+        "{}\n{}{{{{TABLE_CAPTION_AFTER:{}:TABLE_CAPTION_AFTER}}}}\n{}",
+        prefix.trim_end(),
+        prefix,
+        caption,
+        prefix.trim_end()
+    )
+}
+
+/// Opening table tag with its `<caption>`
+fn captioned(table_tag: &str, caption: &str, side: CaptionSide) -> String {
+    let table_tag = match side {
+        CaptionSide::Bottom => table_tag.to_string(),
+        CaptionSide::Top => match CLASS_ATTR.captures(table_tag) {
+            Some(caps) => CLASS_ATTR
+                .replace(table_tag, format!(" class=\"{} caption-top\"", &caps[1]))
+                .to_string(),
+            None => table_tag.replacen("<table", "<table class=\"caption-top\"", 1),
+        },
+    };
+    format!("{}<caption>{}</caption>", table_tag, caption)
+}
+
+/// Move caption markers into their tables
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::table::caption::apply_table_captions;
+/// use umd::parser::CaptionSide;
+///
+/// let html = "<p>{{TABLE_CAPTION_BEFORE:Sales:TABLE_CAPTION_BEFORE}}</p>\n<table class=\"table\"><tr><td>1</td></tr></table>";
+/// assert_eq!(
+///     apply_table_captions(html, CaptionSide::Top),
+///     "<table class=\"table caption-top\"><caption>Sales</caption><tr><td>1</td></tr></table>"
+/// );
+/// ```
+pub fn apply_table_captions(html: &str, side: CaptionSide) -> String {
+    if !html.contains("{{TABLE_CAPTION_") {
+        return html.to_string();
+    }
+
+    let result = CAPTION_BEFORE.replace_all(html, |caps: &Captures| match caps.get(3) {
+        Some(table_tag) => captioned(table_tag.as_str(), &caps[1], side),
+        None => format!("<p>|c {}</p>{}", &caps[1], &caps[2]),
+    });
+
+    let mut output = String::with_capacity(result.len());
+    let mut last = 0;
+    for caps in CAPTION_AFTER.captures_iter(&result) {
+        let whole = caps.get(0).unwrap();
+        // Opening tag of the table the marker follows, unless it has a caption
+        let table_tag = result[..whole.start()]
+            .rfind("<table")
+            .filter(|start| *start >= last)
+            .map(|start| {
+                (
+                    start,
+                    start + result[start..].find('>').map_or(0, |end| end + 1),
+                )
+            })
+            .filter(|(_, tag_end)| !result[*tag_end..].starts_with("<caption>"));
+        match (caps.get(1), table_tag) {
+            (Some(_), Some((start, tag_end))) => {
+                output.push_str(&result[last..start]);
+                output.push_str(&captioned(&result[start..tag_end], &caps[3], side));
+                output.push_str(&result[tag_end..caps.get(1).unwrap().end()]);
+            }
+            _ => {
+                output.push_str(&result[last..whole.start()]);
+                output.push_str(caps.get(1).map_or("", |end| end.as_str()));
+                output.push_str(&caps[2]);
+                output.push_str(&format!("<p>Table: {}</p>", &caps[3]));
+            }
+        }
+        last = whole.end();
+    }
+    output.push_str(&result[last..]);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_caption_after_table() {
+        let html = "<table class=\"table\"><caption>Old</caption></table>\n<table class=\"table umd-table\"><tr><td>1</td></tr></table>\n<p>{{TABLE_CAPTION_AFTER:<em>Q1</em> sales:TABLE_CAPTION_AFTER}}</p>";
+        let output = apply_table_captions(html, CaptionSide::Bottom);
+        assert!(output.ends_with(
+            "<table class=\"table umd-table\"><caption><em>Q1</em> sales</caption><tr><td>1</td></tr></table>"
+        ));
+        assert!(output.starts_with("<table class=\"table\"><caption>Old</caption></table>"));
+    }
+
+    #[test]
+    fn test_one_caption_per_table() {
+        let html = "<table><caption>a</caption></table><p>{{TABLE_CAPTION_AFTER:b:TABLE_CAPTION_AFTER}}</p>";
+        assert_eq!(
+            apply_table_captions(html, CaptionSide::Bottom),
+            "<table><caption>a</caption></table><p>Table: b</p>"
+        );
+    }
+
+    #[test]
+    fn test_caption_without_table_is_kept() {
+        let html = "<p>{{TABLE_CAPTION_BEFORE:a:TABLE_CAPTION_BEFORE}}</p>\n<p>text</p>\n<p>{{TABLE_CAPTION_AFTER:b:TABLE_CAPTION_AFTER}}</p>";
+        assert_eq!(
+            apply_table_captions(html, CaptionSide::Top),
+            "<p>|c a</p>\n<p>text</p>\n<p>Table: b</p>"
+        );
+    }
+
+    #[test]
+    fn test_caption_syntax() {
+        assert_eq!(caption_row("|c Sales |"), Some("Sales"));
+        assert_eq!(caption_row("|c"), None);
+        assert_eq!(caption_line("Table: Sales"), Some("Sales"));
+        assert_eq!(caption_line("Tables: x"), None);
+    }
+}
@@ -3,5 +3,11 @@
 //! This module provides extended table functionality:
 //! - UMD-style tables with cell spanning and decorations
 //! - GFM tables are handled by comrak (no extension needed)
+//! - Sorting hooks (`data-sortable`, per-column `data-type`) for both
+//! - Captions (`|c caption` row above, `Table: caption` line below) for both
+//! - Scroll wrappers (`table-responsive`) for every table
 
+pub mod caption;
+pub mod responsive;
+pub mod sorting;
 pub mod umd;
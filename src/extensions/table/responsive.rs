@@ -0,0 +1,88 @@
+//! Scroll wrappers for wide tables
+//!
+//! With `ParserOptions.responsive_tables`, every table is wrapped in
+//! Bootstrap's `<div class="table-responsive">` (or a `table-responsive-{bp}`
+//! variant), so wide tables scroll instead of overflowing narrow layouts.
+
+use std::borrow::Cow;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::parser::ResponsiveTables;
+
+/// Regex to detect opening and closing table tags
+static TABLE_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<table\b[^>]*>|</table>").unwrap());
+
+/// Wrap every table not already inside a `table-responsive` wrapper
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::table::responsive::apply_responsive_tables;
+/// use umd::parser::ResponsiveTables;
+///
+/// assert_eq!(
+///     apply_responsive_tables("<table class=\"table\"></table>", ResponsiveTables::Md),
+///     "<div class=\"table-responsive-md\"><table class=\"table\"></table></div>"
+/// );
+/// ```
+pub fn apply_responsive_tables(html: &str, responsive: ResponsiveTables) -> Cow<'_, str> {
+    let class = match responsive {
+        ResponsiveTables::Off => return Cow::Borrowed(html),
+        ResponsiveTables::Always => "table-responsive",
+        ResponsiveTables::Sm => "table-responsive-sm",
+        ResponsiveTables::Md => "table-responsive-md",
+        ResponsiveTables::Lg => "table-responsive-lg",
+        ResponsiveTables::Xl => "table-responsive-xl",
+        ResponsiveTables::Xxl => "table-responsive-xxl",
+    };
+    if !html.contains("<table") {
+        return Cow::Borrowed(html);
+    }
+
+    let mut output = String::with_capacity(html.len());
+    let mut last = 0;
+    // Whether each open table got a wrapper
+    let mut wrapped: Vec<bool> = Vec::new();
+    for tag in TABLE_TAG.find_iter(html) {
+        output.push_str(&html[last..tag.start()]);
+        if tag.as_str() == "</table>" {
+            output.push_str(tag.as_str());
+            if wrapped.pop().unwrap_or(false) {
+                output.push_str("</div>");
+            }
+        } else {
+            let before = html[..tag.start()].trim_end();
+            let has_wrapper = before.rfind("<div").is_some_and(|start| {
+                let div = &before[start..];
+                div.ends_with('>')
+                    && !div[..div.len() - 1].contains('>')
+                    && div.contains("table-responsive")
+            });
+            if !has_wrapper {
+                output.push_str(&format!("<div class=\"{}\">", class));
+            }
+            wrapped.push(!has_wrapper);
+            output.push_str(tag.as_str());
+        }
+        last = tag.end();
+    }
+    output.push_str(&html[last..]);
+    Cow::Owned(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_existing_wrappers_and_nested_tables() {
+        let html = "<div class=\"table-responsive\"><table><tr><td><table></table></td></tr></table></div>\n<table></table>";
+        assert_eq!(
+            apply_responsive_tables(html, ResponsiveTables::Always),
+            "<div class=\"table-responsive\"><table><tr><td><div class=\"table-responsive\"><table></table></div></td></tr></table></div>\n<div class=\"table-responsive\"><table></table></div>"
+        );
+        assert_eq!(apply_responsive_tables(html, ResponsiveTables::Off), html);
+    }
+}
@@ -0,0 +1,228 @@
+//! Sorting hooks for tables
+//!
+//! With `ParserOptions.sortable_tables`, tables that have a header row get a
+//! `data-sortable` attribute, and every header cell a `data-type` of
+//! `number`, `date` or `text` inferred from the column's body cells, so
+//! client-side sorting libraries can attach without extra markup.
+//!
+//! A column is `number` or `date` only when all of its non-empty cells are;
+//! a column without content is `text`.
+
+use std::borrow::Cow;
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use crate::search_index::normalize_text;
+
+/// Regex to detect tables
+static TABLE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<table([^>]*)>(.*?)</table>").unwrap());
+
+/// Regex to detect table rows
+static ROW: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<tr\b[^>]*>(.*?)</tr>").unwrap());
+
+/// Regex to detect table cells
+static CELL: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?s)<(t[hd])\b([^>]*)>(.*?)</t[hd]>").unwrap());
+
+static COLSPAN: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\bcolspan="(\d+)""#).unwrap());
+static ROWSPAN: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\browspan="(\d+)""#).unwrap());
+
+/// Numbers with optional sign, thousands separators, decimals and `%`
+static NUMBER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[+\-−]?(?:\d{1,3}(?:,\d{3})+|\d+)?(?:\.\d+)?%?$").unwrap());
+
+/// ISO-like dates (`2024-01-31`, `2024/1/31`, optional time) and `2024年1月31日`
+static DATE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^(?:\d{4}[-/.]\d{1,2}[-/.]\d{1,2}(?:[ T]\d{1,2}:\d{2}(?::\d{2})?)?|\d{4}年\d{1,2}月\d{1,2}日)$",
+    )
+    .unwrap()
+});
+
+/// Column type inferred from the body cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnType {
+    Number,
+    Date,
+    Text,
+}
+
+impl ColumnType {
+    fn as_str(self) -> &'static str {
+        match self {
+            ColumnType::Number => "number",
+            ColumnType::Date => "date",
+            ColumnType::Text => "text",
+        }
+    }
+
+    fn of(text: &str) -> Self {
+        if text.chars().any(|c| c.is_ascii_digit()) && NUMBER.is_match(text) {
+            ColumnType::Number
+        } else if DATE.is_match(text) {
+            ColumnType::Date
+        } else {
+            ColumnType::Text
+        }
+    }
+}
+
+/// Cells of a row with the column each one starts at
+///
+/// `occupied` holds, per column, the number of following rows still covered
+/// by a `rowspan` from above.
+fn row_cells<'a>(row: &'a str, occupied: &mut Vec<usize>) -> Vec<(usize, Captures<'a>)> {
+    let mut cells = Vec::new();
+    let mut column = 0;
+    for caps in CELL.captures_iter(row) {
+        while occupied.get(column).is_some_and(|rows| *rows > 0) {
+            column += 1;
+        }
+        let span = |regex: &Regex| {
+            regex
+                .captures(&caps[2])
+                .and_then(|span| span[1].parse::<usize>().ok())
+                .unwrap_or(1)
+                .max(1)
+        };
+        let (colspan, rowspan) = (span(&COLSPAN), span(&ROWSPAN));
+        if occupied.len() < column + colspan {
+            occupied.resize(column + colspan, 0);
+        }
+        for rows in &mut occupied[column..column + colspan] {
+            *rows = (*rows).max(rowspan);
+        }
+        cells.push((column, caps));
+        column += colspan;
+    }
+    for rows in occupied.iter_mut() {
+        *rows = rows.saturating_sub(1);
+    }
+    cells
+}
+
+/// Infer column types of a table body and annotate its header row
+///
+/// Returns `None` when the table has no header row.
+fn annotate_table(attrs: &str, content: &str) -> Option<String> {
+    let rows: Vec<_> = ROW.find_iter(content).collect();
+    let header = rows.first()?;
+    let has_thead = content[..header.start()].contains("<thead");
+    let header_cells: Vec<_> = CELL.captures_iter(header.as_str()).collect();
+    if header_cells.is_empty() || !(has_thead || header_cells.iter().all(|cell| &cell[1] == "th")) {
+        return None;
+    }
+
+    let mut occupied = Vec::new();
+    let header_columns: Vec<usize> = row_cells(header.as_str(), &mut occupied)
+        .into_iter()
+        .map(|(column, _)| column)
+        .collect();
+
+    let mut types: Vec<Option<ColumnType>> = Vec::new();
+    for row in &rows[1..] {
+        for (column, caps) in row_cells(row.as_str(), &mut occupied) {
+            let text = normalize_text(&caps[3]);
+            if text.is_empty() {
+                continue;
+            }
+            if types.len() <= column {
+                types.resize(column + 1, None);
+            }
+            let cell_type = ColumnType::of(&text);
+            types[column] = match types[column] {
+                None => Some(cell_type),
+                Some(current) if current == cell_type => Some(current),
+                Some(_) => Some(ColumnType::Text),
+            };
+        }
+    }
+
+    let mut index = 0;
+    let header_html = CELL.replace_all(header.as_str(), |caps: &Captures| {
+        let column = header_columns[index];
+        index += 1;
+        let column_type = types
+            .get(column)
+            .copied()
+            .flatten()
+            .unwrap_or(ColumnType::Text);
+        format!(
+            "<{tag}{} data-type=\"{}\">{}</{tag}>",
+            &caps[2],
+            column_type.as_str(),
+            &caps[3],
+            tag = &caps[1]
+        )
+    });
+
+    Some(format!(
+        "<table{} data-sortable>{}{}{}</table>",
+        attrs,
+        &content[..header.start()],
+        header_html,
+        &content[header.end()..]
+    ))
+}
+
+/// Add sorting hooks to every table with a header row
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::table::sorting::apply_sortable_tables;
+///
+/// let html = "<table class=\"table\"><thead><tr><th>Name</th><th>Size</th></tr></thead>\
+///             <tbody><tr><td>a</td><td>1,024</td></tr></tbody></table>";
+/// let output = apply_sortable_tables(html);
+/// assert!(output.starts_with("<table class=\"table\" data-sortable>"));
+/// assert!(output.contains("<th data-type=\"text\">Name</th><th data-type=\"number\">Size</th>"));
+/// ```
+pub fn apply_sortable_tables(html: &str) -> Cow<'_, str> {
+    TABLE.replace_all(html, |caps: &Captures| {
+        annotate_table(&caps[1], &caps[2]).unwrap_or_else(|| caps[0].to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_column_types() {
+        assert_eq!(ColumnType::of("-1,234.5"), ColumnType::Number);
+        assert_eq!(ColumnType::of("12%"), ColumnType::Number);
+        assert_eq!(ColumnType::of("2024-01-31"), ColumnType::Date);
+        assert_eq!(ColumnType::of("2024年1月31日"), ColumnType::Date);
+        assert_eq!(ColumnType::of("v1.2"), ColumnType::Text);
+        assert_eq!(ColumnType::of("."), ColumnType::Text);
+    }
+
+    #[test]
+    fn test_mixed_columns_and_spans() {
+        let html = "<table class=\"table umd-table\"><tr><th>Date</th><th>Count</th><th>Note</th></tr>\
+                    <tr><td rowspan=\"2\">2024/1/2</td><td>3</td><td>x</td></tr>\
+                    <tr><td>4</td><td>5</td></tr>\
+                    <tr><td colspan=\"2\">total</td><td></td></tr></table>";
+        let output = apply_sortable_tables(html);
+        assert!(output.contains(
+            "<th data-type=\"text\">Date</th><th data-type=\"number\">Count</th><th data-type=\"text\">Note</th>"
+        ));
+
+        let html = html.replace(
+            "<td colspan=\"2\">total</td>",
+            "<td>2024/1/3</td><td>7</td>",
+        );
+        let output = apply_sortable_tables(&html);
+        assert!(output.contains(
+            "<th data-type=\"date\">Date</th><th data-type=\"number\">Count</th><th data-type=\"text\">Note</th>"
+        ));
+    }
+
+    #[test]
+    fn test_table_without_header_is_unchanged() {
+        let html = "<table class=\"table\"><tbody><tr><td>1</td></tr></tbody></table>";
+        assert_eq!(apply_sortable_tables(html), html);
+    }
+}
@@ -1,99 +1,189 @@
 //! Cell spanning support for UMD tables
 //!
-//! Provides colspan and rowspan functionality using special markers:
-//! - `|>` for horizontal spanning (colspan)
-//! - `|^` for vertical spanning (rowspan)
+//! Provides colspan and rowspan functionality using marker cells:
+//! - `|>|` joins the cell to its left (colspan)
+//! - `|^|` joins the cell above (rowspan)
+//!
+//! Every position covered by a span holds a marker in the source, so the
+//! raw cell index is the grid column. Spans are resolved on a virtual grid
+//! that records which cell owns each position; a marker that would make two
+//! cells overlap, or that has no cell to join, is kept as a literal cell.
 
 use super::parser::Cell;
 
-/// Process cell spanning (colspan and rowspan)
-pub fn process_cell_spanning(rows: &mut Vec<Vec<Cell>>) {
-    process_colspan(rows);
-    process_rowspan(rows);
+/// Content of a cell that joins the cell to its left
+const COLSPAN_MARKER: &str = ">";
+/// Content of a cell that joins the cell above
+const ROWSPAN_MARKER: &str = "^";
+
+/// Cells placed so far, with the grid column each one starts at
+struct Grid {
+    rows: Vec<Vec<Cell>>,
+    columns: Vec<Vec<usize>>,
+    /// Owning cell (row, index) of each grid position
+    owners: Vec<Vec<Option<(usize, usize)>>>,
 }
 
-/// Process colspan (horizontal spanning)
-fn process_colspan(rows: &mut Vec<Vec<Cell>>) {
-    for row in rows.iter_mut() {
-        let mut i = 0;
-        while i < row.len() {
-            if row[i].content.ends_with("|>") || row[i].content == "|>" {
-                // Remove marker
-                row[i].content = row[i].content.trim_end_matches("|>").trim().to_string();
-
-                // Count consecutive empty or |> cells
-                let mut span = 1;
-                let j = i + 1;
-                while j < row.len() && (row[j].content.is_empty() || row[j].content == "|>") {
-                    span += 1;
-                    row.remove(j); // Remove merged cell
-                }
-                row[i].colspan = span;
-            }
-            i += 1;
+impl Grid {
+    fn is_marker(cell: &Cell) -> bool {
+        cell.content == COLSPAN_MARKER || cell.content == ROWSPAN_MARKER
+    }
+
+    /// Extend the cell left of `(row, column)` over it
+    fn join_left(&mut self, row: usize, column: usize) -> Option<(usize, usize)> {
+        let (origin_row, index) = (*self.owners[row].get(column.checked_sub(1)?)?)?;
+        let start = self.columns[origin_row][index];
+        let cell = &mut self.rows[origin_row][index];
+        if column < start + cell.colspan {
+            // Already covered through a rowspan of a wide cell
+            return Some((origin_row, index));
+        }
+        if start + cell.colspan == column && origin_row == row {
+            cell.colspan += 1;
+            return Some((origin_row, index));
+        }
+        None
+    }
+
+    /// Extend the cell above `(row, column)` over it
+    ///
+    /// A cell spanning several columns only grows downwards when the
+    /// current row has markers under all of its columns.
+    fn join_above(&mut self, row: usize, column: usize, raw: &[Cell]) -> Option<(usize, usize)> {
+        let (origin_row, index) = (*self.owners.get(row.checked_sub(1)?)?.get(column)?)?;
+        let start = self.columns[origin_row][index];
+        let cell = &mut self.rows[origin_row][index];
+        if origin_row + cell.rowspan > row {
+            return Some((origin_row, index));
         }
+        let covered = raw
+            .get(start..start + cell.colspan)
+            .is_some_and(|cells| cells.iter().all(Self::is_marker));
+        if origin_row + cell.rowspan == row && start == column && covered {
+            cell.rowspan += 1;
+            return Some((origin_row, index));
+        }
+        None
     }
 }
 
-/// Process rowspan (vertical spanning)
-fn process_rowspan(rows: &mut Vec<Vec<Cell>>) {
-    let max_cols = rows.iter().map(|r| r.len()).max().unwrap_or(0);
-    for col in 0..max_cols {
-        let mut row_idx = 0;
-        while row_idx < rows.len() {
-            if col < rows[row_idx].len() && rows[row_idx][col].content == "|^" {
-                // Find source cell above
-                if row_idx > 0 && col < rows[row_idx - 1].len() {
-                    rows[row_idx - 1][col].rowspan += 1;
-                    rows[row_idx].remove(col);
-                }
-            }
-            row_idx += 1;
+/// Resolve span markers into colspan and rowspan
+pub fn process_cell_spanning(rows: &mut Vec<Vec<Cell>>) {
+    let mut grid = Grid {
+        rows: Vec::new(),
+        columns: Vec::new(),
+        owners: Vec::new(),
+    };
+
+    for (row, raw) in std::mem::take(rows).into_iter().enumerate() {
+        grid.rows.push(Vec::new());
+        grid.columns.push(Vec::new());
+        grid.owners.push(Vec::new());
+
+        for (column, cell) in raw.iter().enumerate() {
+            let owner = match cell.content.as_str() {
+                COLSPAN_MARKER => grid.join_left(row, column),
+                ROWSPAN_MARKER => grid.join_above(row, column, &raw),
+                _ => None,
+            };
+            let owner = owner.unwrap_or_else(|| {
+                grid.rows[row].push(cell.clone());
+                grid.columns[row].push(column);
+                (row, grid.rows[row].len() - 1)
+            });
+            grid.owners[row].push(Some(owner));
         }
     }
+
+    *rows = grid.rows;
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_colspan() {
-        let mut rows = vec![
-            vec![
-                Cell::new("A |>".to_string(), true),
-                Cell::new("".to_string(), true),
-            ],
-            vec![
-                Cell::new("C".to_string(), false),
-                Cell::new("D".to_string(), false),
-            ],
-        ];
-
+    /// Resolve a table of raw cell contents and describe the result as
+    /// `content(colspan x rowspan)` per row
+    fn layout(table: &[&[&str]]) -> Vec<String> {
+        let mut rows: Vec<Vec<Cell>> = table
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|content| Cell::new(content.to_string(), false))
+                    .collect()
+            })
+            .collect();
         process_cell_spanning(&mut rows);
+        rows.iter()
+            .map(|row| {
+                row.iter()
+                    .map(|cell| format!("{}({}x{})", cell.content, cell.colspan, cell.rowspan))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect()
+    }
 
-        assert_eq!(rows[0].len(), 1);
-        assert_eq!(rows[0][0].colspan, 2);
-        assert_eq!(rows[0][0].content, "A");
+    #[test]
+    fn test_colspan() {
+        assert_eq!(
+            layout(&[&["A", ">", ">", "B"], &["C", "D", "E", "F"]]),
+            ["A(3x1) B(1x1)", "C(1x1) D(1x1) E(1x1) F(1x1)"]
+        );
     }
 
     #[test]
     fn test_rowspan() {
-        let mut rows = vec![
-            vec![
-                Cell::new("A".to_string(), true),
-                Cell::new("B".to_string(), true),
-            ],
-            vec![
-                Cell::new("|^".to_string(), false),
-                Cell::new("D".to_string(), false),
-            ],
-        ];
+        assert_eq!(
+            layout(&[&["A", "B"], &["^", "D"], &["^", "E"]]),
+            ["A(1x3) B(1x1)", "D(1x1)", "E(1x1)"]
+        );
+    }
 
-        process_cell_spanning(&mut rows);
+    #[test]
+    fn test_block_span() {
+        let expected = ["A(2x2) B(1x1)", "C(1x1)", "D(1x1) E(1x1) F(1x1)"];
+        assert_eq!(
+            layout(&[&["A", ">", "B"], &["^", "^", "C"], &["D", "E", "F"]]),
+            expected
+        );
+        assert_eq!(
+            layout(&[&["A", ">", "B"], &["^", ">", "C"], &["D", "E", "F"]]),
+            expected
+        );
+    }
 
-        assert_eq!(rows[0][0].rowspan, 2);
-        assert_eq!(rows[1].len(), 1);
-        assert_eq!(rows[1][0].content, "D");
+    #[test]
+    fn test_rowspan_after_colspan_keeps_columns() {
+        // The colspan in the first row must not shift the `^` target
+        assert_eq!(
+            layout(&[&["A", ">", "B"], &["C", "D", "E"], &["F", "G", "^"]]),
+            ["A(2x1) B(1x1)", "C(1x1) D(1x1) E(1x2)", "F(1x1) G(1x1)"]
+        );
+        assert_eq!(
+            layout(&[
+                &["A", "B", "C", "X"],
+                &["^", "D", ">", "Y"],
+                &["^", "^", "^", "Z"]
+            ]),
+            ["A(1x3) B(1x1) C(1x1) X(1x1)", "D(2x2) Y(1x1)", "Z(1x1)"]
+        );
+    }
+
+    #[test]
+    fn test_overlapping_markers_stay_literal() {
+        // A wide cell cannot grow down under a single marker
+        assert_eq!(
+            layout(&[&["A", ">"], &["^", "B"]]),
+            ["A(2x1)", "^(1x1) B(1x1)"]
+        );
+        // A tall cell cannot grow sideways into a placed cell
+        assert_eq!(
+            layout(&[&["A", "B"], &["^", ">"]]),
+            ["A(1x2) B(1x1)", ">(1x1)"]
+        );
+        // Nothing to join
+        assert_eq!(layout(&[&[">", "A"], &["B", "C"]])[0], ">(1x1) A(1x1)");
+        assert_eq!(layout(&[&["^", "A"]])[0], "^(1x1) A(1x1)");
     }
 }
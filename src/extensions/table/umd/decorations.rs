@@ -1,26 +1,55 @@
 //! Cell decoration support for UMD tables
 //!
 //! Provides support for:
-//! - COLOR(fg,bg): Cell foreground and background colors
-//! - SIZE(value): Font size adjustments
+//! - COLOR(fg,bg,opacity): Cell foreground and background colors
+//! - SIZE(value): Font size adjustments (see `font_size`)
+//! - WIDTH(value): Cell width (`30%`, `120px`; unitless numbers are percent)
 //! - Alignment prefixes: TOP:, MIDDLE:, BOTTOM:, CENTER:, etc.
+//! - NOWRAP: Keep the cell content on one line
 
 use super::parser::Cell;
+use crate::extensions::color_utilities::{gradient_classes, opacity_class};
+use crate::extensions::font_size::{FontSize, map_font_size};
+use crate::sanitizer::{sanitize_css_color, split_args};
+use once_cell::sync::Lazy;
 use regex::Regex;
 
+/// Regex to detect a width: number with an optional unit
+static WIDTH: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\d{1,4}(?:\.\d+)?(?:%|px|em|rem|ch)?$").unwrap());
+
+/// Map a width argument to a CSS length, in percent unless a unit is given
+///
+/// Anything but a plain length is rejected, so a width can never inject
+/// other CSS.
+pub(super) fn map_width(value: &str) -> Option<String> {
+    let value = value.trim();
+    if !WIDTH.is_match(value) {
+        return None;
+    }
+    if value.ends_with(|c: char| c.is_ascii_digit()) {
+        Some(format!("{}%", value))
+    } else {
+        Some(value.to_string())
+    }
+}
+
+/// Cell prefix `COLOR(fg,bg,opacity):`
+static CELL_COLOR: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^COLOR\(((?:[^()]|\([^()]*\))*)\):\s*(.*)$").unwrap());
+
+/// Cell prefix `SIZE(value):`
+static CELL_SIZE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^SIZE\(([^)]+)\):\s*(.*)$").unwrap());
+
+/// Cell prefix `WIDTH(value):`
+static CELL_WIDTH: Lazy<Regex> = Lazy::new(|| Regex::new(r"^WIDTH\(([^)]+)\):\s*(.*)$").unwrap());
+
 /// Parse cell content for decorations and markers
 pub fn parse_cell_content(cell: &mut Cell) {
     let content = &cell.content;
 
-    // Check for colspan marker: |> - mark but don't remove yet
-    if content == "|>" || content.ends_with(" |>") {
-        // Keep the marker for process_cell_spanning
-        return;
-    }
-
-    // Check for rowspan marker: |^
-    if content == "|^" {
-        // Keep the marker for process_cell_spanning
+    // Keep colspan (`>`) and rowspan (`^`) markers for process_cell_spanning
+    if content == ">" || content == "^" {
         return;
     }
 
@@ -28,59 +57,67 @@ pub fn parse_cell_content(cell: &mut Cell) {
     let mut remaining = content.clone();
 
     // Check for header marker: ~
-    if remaining.starts_with('~') {
+    if let Some(rest) = remaining.strip_prefix('~') {
         cell.is_header = true;
-        remaining = remaining.strip_prefix('~').unwrap().trim().to_string();
+        remaining = rest.trim().to_string();
     }
 
     // Parse COLOR(fg,bg):
-    let color_pattern = Regex::new(r"^COLOR\(([^)]*)\):\s*(.*)$").unwrap();
-    if let Some(caps) = color_pattern.captures(&remaining) {
+    if let Some(caps) = CELL_COLOR.captures(&remaining) {
         let args = caps[1].to_string();
         remaining = caps[2].to_string();
 
-        let parts: Vec<&str> = args.split(',').collect();
-        let fg = parts.get(0).map_or("", |s| s.trim());
-        let bg = parts.get(1).map_or("", |s| s.trim());
+        let parts = split_args(&args);
+        let fg = parts[0];
+        let bg = parts.get(1).copied().unwrap_or("");
+        let opacity = parts.get(2).copied().unwrap_or("");
+        let first_color_class = cell.classes.len();
 
         if !fg.is_empty() && fg != "inherit" {
-            if is_bootstrap_color(fg) {
+            if let Some(classes) = gradient_classes(fg) {
+                cell.classes.push(classes);
+            } else if is_bootstrap_color(fg) {
                 cell.classes.push(format!("text-{}", fg));
-            } else {
-                cell.styles.push(format!("color: {}", fg));
+            } else if let Some(color) = sanitize_css_color(fg) {
+                cell.styles.push(format!("color: {}", color));
             }
         }
 
         if !bg.is_empty() && bg != "inherit" {
-            if is_bootstrap_color(bg) {
+            if let Some(classes) = gradient_classes(bg) {
+                cell.classes.push(classes);
+            } else if is_bootstrap_color(bg) {
                 cell.classes.push(format!("bg-{}", bg));
-            } else {
-                cell.styles.push(format!("background-color: {}", bg));
+            } else if let Some(color) = sanitize_css_color(bg) {
+                cell.styles.push(format!("background-color: {}", color));
             }
         }
+
+        if let Some(class) = opacity_class(opacity, &cell.classes[first_color_class..]) {
+            cell.classes.push(class);
+        }
     }
 
     // Parse SIZE(value):
-    let size_pattern = Regex::new(r"^SIZE\(([^)]+)\):\s*(.*)$").unwrap();
-    if let Some(caps) = size_pattern.captures(&remaining) {
+    if let Some(caps) = CELL_SIZE.captures(&remaining) {
         let value = caps[1].to_string();
         remaining = caps[2].to_string();
 
-        // Check if it's a Bootstrap size
-        if let Some(bs_class) = get_bootstrap_size_class(&value) {
-            cell.classes.push(bs_class);
-        } else {
-            // Use inline style
-            let size_value =
-                if value.contains("rem") || value.contains("em") || value.contains("px") {
-                    value.to_string()
-                } else {
-                    format!("{}rem", value)
-                };
-            cell.styles.push(format!("font-size: {}", size_value));
+        match map_font_size(&value) {
+            Some(FontSize::Class(class)) => cell.classes.push(class),
+            Some(FontSize::Style(size)) => cell.styles.push(format!("font-size: {}", size)),
+            None => {}
         }
     }
 
+    // Parse WIDTH(value):
+    if let Some(caps) = CELL_WIDTH.captures(&remaining) {
+        if let Some(width) = map_width(&caps[1]) {
+            cell.styles.push(format!("width: {}", width));
+        }
+        remaining = caps[2].to_string();
+    }
+
     // Parse alignment prefixes
     for (prefix, class) in &[
         ("TOP:", "align-top"),
@@ -91,17 +128,18 @@ pub fn parse_cell_content(cell: &mut Cell) {
         ("CENTER:", "text-center"),
         ("LEFT:", "text-start"),
         ("JUSTIFY:", "text-justify"),
+        ("NOWRAP:", "text-nowrap"),
     ] {
-        if remaining.starts_with(prefix) {
+        if let Some(rest) = remaining.strip_prefix(prefix) {
             cell.classes.push(class.to_string());
-            remaining = remaining.strip_prefix(prefix).unwrap().trim().to_string();
+            remaining = rest.trim().to_string();
         }
     }
 
     // Check for header marker after decoration prefixes: ~
-    if remaining.starts_with('~') {
+    if let Some(rest) = remaining.strip_prefix('~') {
         cell.is_header = true;
-        remaining = remaining.strip_prefix('~').unwrap().trim().to_string();
+        remaining = rest.trim().to_string();
     }
 
     cell.content = remaining;
@@ -145,33 +183,22 @@ fn is_bootstrap_color(color: &str) -> bool {
     )
 }
 
-/// Get Bootstrap size class for a given value
-fn get_bootstrap_size_class(value: &str) -> Option<String> {
-    let val: f32 = value.parse().ok()?;
-
-    let class = if val >= 2.5 {
-        "fs-1"
-    } else if val >= 2.0 {
-        "fs-2"
-    } else if val >= 1.75 {
-        "fs-3"
-    } else if val >= 1.5 {
-        "fs-4"
-    } else if val >= 1.25 {
-        "fs-5"
-    } else if val >= 0.875 {
-        "fs-6"
-    } else {
-        return None;
-    };
-
-    Some(class.to_string())
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_width_and_nowrap() {
+        let mut cell = Cell::new("WIDTH(30): CENTER: NOWRAP: Text".to_string(), false);
+        parse_cell_content(&mut cell);
+        assert_eq!(cell.content, "Text");
+        assert_eq!(cell.styles, vec!["width: 30%"]);
+        assert_eq!(cell.classes, vec!["text-center", "text-nowrap"]);
+
+        assert_eq!(map_width("12.5em").as_deref(), Some("12.5em"));
+        assert_eq!(map_width("10%;color:red"), None);
+    }
+
     #[test]
     fn test_color_decoration() {
         let mut cell = Cell::new("COLOR(red,blue): Text".to_string(), false);
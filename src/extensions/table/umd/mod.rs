@@ -2,7 +2,8 @@
 //!
 //! Provides support for UMD-style tables with extended features:
 //! - Cell spanning: `|>` for colspan, `|^` for rowspan
-//! - Cell decorations: COLOR(), SIZE(), alignment prefixes
+//! - Cell decorations: COLOR(), SIZE(), WIDTH(), alignment prefixes, NOWRAP:
+//! - Column widths: `@@colwidths(20,30,50)` on the line above the table
 //! - No mandatory header row (unlike GFM)
 
 mod cell_spanning;
@@ -10,4 +11,4 @@ mod decorations;
 mod parser;
 
 // Re-export main API
-pub use parser::{extract_umd_tables, parse_table};
+pub use parser::{extract_umd_tables, is_umd_table, parse_table, protect_colwidths};
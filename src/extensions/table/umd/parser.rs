@@ -2,6 +2,23 @@
 //!
 //! Parses UMD-style table syntax into structured cell data
 
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use crate::extensions::nested_blocks::continues_list_item;
+use crate::extensions::preprocessor::split_blockquote_prefix;
+use crate::extensions::table::caption;
+use crate::html_builder::{attr_escape, text_escape};
+
+/// Regex to detect `@@colwidths(...)` directive lines
+static COLWIDTHS_DIRECTIVE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?m)^((?: {0,3}>[ \t]?)*[ \t]*)@@colwidths\(([^)\n]*)\)[ \t]*$").unwrap()
+});
+
+/// Regex to detect a directive protected by [`protect_colwidths`]
+static COLWIDTHS_MARKER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{\{UMD_COLWIDTHS:([^\n]*?):UMD_COLWIDTHS\}\}").unwrap());
+
 /// Cell information
 #[derive(Debug, Clone)]
 pub struct Cell {
@@ -73,6 +90,8 @@ pub fn is_umd_table(lines: &[&str]) -> bool {
             || line.contains("CENTER:")
             || line.contains("RIGHT:")
             || line.contains("LEFT:")
+            || line.contains("WIDTH(")
+            || line.contains("NOWRAP:")
         {
             return true;
         }
@@ -82,6 +101,26 @@ pub fn is_umd_table(lines: &[&str]) -> bool {
     false
 }
 
+/// Parse a column spec row (`|<|=|>|c`) into per-column alignment classes
+///
+/// `<`, `=` and `>` align the column to the start, center and end; an empty
+/// cell leaves the column alone. Returns `None` for any other row.
+fn parse_column_spec(line: &str) -> Option<Vec<Option<&'static str>>> {
+    let spec = line.trim().strip_suffix('c')?.strip_prefix('|')?;
+    let spec = spec.strip_suffix('|').unwrap_or(spec);
+    let columns = spec
+        .split('|')
+        .map(|column| match column.trim() {
+            "<" => Some(Some("text-start")),
+            "=" => Some(Some("text-center")),
+            ">" => Some(Some("text-end")),
+            "" => Some(None),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    columns.iter().any(Option::is_some).then_some(columns)
+}
+
 /// Parse a UMD table and convert to HTML
 ///
 /// # Arguments
@@ -104,6 +143,19 @@ pub fn parse_table(table_text: &str) -> String {
         return table_text.to_string();
     }
 
+    // Column spec rows are not rendered; the last one wins
+    let mut column_alignment = Vec::new();
+    let lines: Vec<&str> = lines
+        .into_iter()
+        .filter(|line| match parse_column_spec(line) {
+            Some(spec) => {
+                column_alignment = spec;
+                false
+            }
+            None => true,
+        })
+        .collect();
+
     // Check if first row has 'h' suffix to determine if it's a header row
     let has_thead = lines
         .first()
@@ -123,49 +175,33 @@ pub fn parse_table(table_text: &str) -> String {
             line = &line[..line.len() - 1];
         }
 
-        // Parse cells more carefully to handle |> and |^ markers
-        let mut cells: Vec<Cell> = Vec::new();
-        let mut current_pos = 1; // Skip first |
-        let chars: Vec<char> = line.chars().collect();
-        let mut current_cell = String::new();
-
-        while current_pos < chars.len() {
-            if chars[current_pos] == '|' {
-                // Check if this is |> or |^ marker
-                if current_pos + 1 < chars.len() {
-                    let next_char = chars[current_pos + 1];
-                    if next_char == '>' || next_char == '^' {
-                        // This is a marker, include it in the cell
-                        current_cell.push('|');
-                        current_cell.push(next_char);
-                        current_pos += 2;
-                        continue;
-                    }
-                }
-
-                // Regular cell separator
-                let content = current_cell.trim().to_string();
-                let mut cell = Cell::new(content, false);
+        // Split into cells; a trailing `|` closes the row instead of opening
+        // an empty cell. `|>|` and `|^|` come out as marker cells.
+        let line = &line[1..];
+        let line = line.strip_suffix('|').unwrap_or(line);
+        let mut cells: Vec<Cell> = line
+            .split('|')
+            .map(|content| {
+                let mut cell = Cell::new(content.trim().to_string(), false);
                 super::decorations::parse_cell_content(&mut cell);
-                cells.push(cell);
-                current_cell.clear();
-                current_pos += 1;
-            } else {
-                current_cell.push(chars[current_pos]);
-                current_pos += 1;
+                cell
+            })
+            .collect();
+
+        // Column alignment applies to cells without their own alignment prefix
+        for (cell, class) in cells.iter_mut().zip(&column_alignment) {
+            if let Some(class) = class
+                && !cell.classes.iter().any(|existing| {
+                    matches!(
+                        existing.as_str(),
+                        "text-start" | "text-center" | "text-end" | "text-justify"
+                    )
+                })
+            {
+                cell.classes.push(class.to_string());
             }
         }
 
-        // Add last cell if exists
-        if !current_cell.trim().is_empty() || !cells.is_empty() {
-            let content = current_cell.trim().to_string();
-            let mut cell = Cell::new(content, false);
-            super::decorations::parse_cell_content(&mut cell);
-            cells.push(cell);
-        }
-
-        // Don't remove trailing empty cells - they may be part of colspan
-
         rows.push(cells);
     }
 
@@ -198,11 +234,17 @@ fn generate_table_html_with_header(rows: &[Vec<Cell>], has_thead: bool) -> Strin
                 let mut attrs = Vec::new();
 
                 if !cell.classes.is_empty() {
-                    attrs.push(format!(r#"class="{}""#, cell.classes.join(" ")));
+                    attrs.push(format!(
+                        r#"class="{}""#,
+                        attr_escape(&cell.classes.join(" "))
+                    ));
                 }
 
                 if !cell.styles.is_empty() {
-                    attrs.push(format!(r#"style="{}""#, cell.styles.join("; ")));
+                    attrs.push(format!(
+                        r#"style="{}""#,
+                        attr_escape(&cell.styles.join("; "))
+                    ));
                 }
 
                 if cell.colspan > 1 {
@@ -219,7 +261,10 @@ fn generate_table_html_with_header(rows: &[Vec<Cell>], has_thead: bool) -> Strin
                     format!(" {}", attrs.join(" "))
                 };
 
-                html.push_str(&format!("<{tag}{attrs_str}>{}</{tag}>", cell.content));
+                html.push_str(&format!(
+                    "<{tag}{attrs_str}>{}</{tag}>",
+                    text_escape(&cell.content)
+                ));
             }
             html.push_str("</tr>");
         }
@@ -240,11 +285,17 @@ fn generate_table_html_with_header(rows: &[Vec<Cell>], has_thead: bool) -> Strin
                 let mut attrs = Vec::new();
 
                 if !cell.classes.is_empty() {
-                    attrs.push(format!(r#"class="{}""#, cell.classes.join(" ")));
+                    attrs.push(format!(
+                        r#"class="{}""#,
+                        attr_escape(&cell.classes.join(" "))
+                    ));
                 }
 
                 if !cell.styles.is_empty() {
-                    attrs.push(format!(r#"style="{}""#, cell.styles.join("; ")));
+                    attrs.push(format!(
+                        r#"style="{}""#,
+                        attr_escape(&cell.styles.join("; "))
+                    ));
                 }
 
                 if cell.colspan > 1 {
@@ -261,7 +312,10 @@ fn generate_table_html_with_header(rows: &[Vec<Cell>], has_thead: bool) -> Strin
                     format!(" {}", attrs.join(" "))
                 };
 
-                html.push_str(&format!("<{tag}{attrs_str}>{}</{tag}>", cell.content));
+                html.push_str(&format!(
+                    "<{tag}{attrs_str}>{}</{tag}>",
+                    text_escape(&cell.content)
+                ));
             }
             html.push_str("</tr>");
         }
@@ -272,67 +326,148 @@ fn generate_table_html_with_header(rows: &[Vec<Cell>], has_thead: bool) -> Strin
     html
 }
 
+/// Protect `@@colwidths(...)` directives from plugin parsing
+///
+/// Must run before plugin protection; [`extract_umd_tables`] applies each
+/// directive to the UMD table that directly follows it and restores the
+/// others as text.
+pub fn protect_colwidths(input: &str) -> String {
+    if !input.contains("@@colwidths(") {
+        return input.to_string();
+    }
+    COLWIDTHS_DIRECTIVE
+        .replace_all(input, "${1}{{UMD_COLWIDTHS:$2:UMD_COLWIDTHS}}")
+        .to_string()
+}
+
+/// `<colgroup>` for the widths of a `@@colwidths(...)` directive
+///
+/// Empty or invalid widths leave their column unsized.
+fn render_colgroup(args: &str) -> String {
+    let mut html = String::from("<colgroup>");
+    for width in args.split(',') {
+        match super::decorations::map_width(width) {
+            Some(width) => html.push_str(&format!("<col style=\"width: {}\" />", width)),
+            None => html.push_str("<col />"),
+        }
+    }
+    html.push_str("</colgroup>");
+    html
+}
+
 /// Detect and extract UMD tables from input text
 ///
 /// Returns a tuple of (processed_text, table_map)
-/// where table_map contains markers and their corresponding HTML
+/// where table_map contains markers and their corresponding HTML.
+/// Caption rows and lines around GFM and UMD tables are turned into caption
+/// markers (see [`caption`](crate::extensions::table::caption)).
 pub fn extract_umd_tables(input: &str) -> (String, Vec<(String, String)>) {
-    let mut result = input.to_string();
+    let mut output: Vec<String> = Vec::new();
     let mut tables = Vec::new();
-    let mut table_counter = 0;
 
-    // Find all potential tables (consecutive lines starting with |)
-    let mut in_table = false;
-    let mut table_lines = Vec::new();
+    // Find all potential tables (consecutive lines starting with |), also
+    // inside blockquotes, where the rows share the same `>` markers
     let lines: Vec<&str> = input.lines().collect();
+    let mut index = 0;
+    while index < lines.len() {
+        let (quote, content) = split_blockquote_prefix(lines[index]);
+        if !content.trim().starts_with('|') {
+            output.push(lines[index].to_string());
+            index += 1;
+            continue;
+        }
+        // Content of line `at` when it is in the same blockquote
+        let quoted = |at: usize| {
+            lines.get(at).and_then(|line| {
+                let (line_quote, content) = split_blockquote_prefix(line);
+                (line_quote.trim_end() == quote.trim_end()).then_some(content)
+            })
+        };
+        let start = index;
+        let mut rows = Vec::new();
+        while let Some(row) = quoted(index).filter(|row| row.trim().starts_with('|')) {
+            rows.push(row);
+            index += 1;
+        }
+        let mut table_lines = &rows[..];
+
+        // Markers keep the `>` markers of a table in a blockquote, or the
+        // indentation of a table continuing a list item, so neither is split
+        let line = lines[start];
+        let list_indent = (quote.is_empty() && continues_list_item(&lines, start))
+            .then(|| &line[..line.len() - line.trim_start().len()]);
+        let prefix = list_indent.unwrap_or(quote);
+
+        // `|c caption` row above the table
+        let caption_before = match table_lines {
+            [first, _, ..] => caption::caption_row(first),
+            _ => None,
+        };
+        if let Some(caption) = caption_before {
+            table_lines = &table_lines[1..];
+            output.push(caption::marker_before(caption, prefix));
+        }
 
-    for line in lines.iter() {
-        let trimmed = line.trim();
-
-        if trimmed.starts_with('|') {
-            if !in_table {
-                in_table = true;
-                table_lines.clear();
+        // `Table: caption` line below the table, directly or after a blank line
+        let caption_at = match quoted(index) {
+            Some(line) if line.trim().is_empty() => index + 1,
+            _ => index,
+        };
+        let caption_after = quoted(caption_at).and_then(caption::caption_line);
+
+        if is_umd_table(table_lines) {
+            // Directive line directly above the table
+            let directive = output
+                .last()
+                .filter(|_| caption_before.is_none())
+                .and_then(|previous| COLWIDTHS_MARKER.captures(previous))
+                .map(|caps| caps[1].to_string());
+
+            let mut html = parse_table(&table_lines.join("\n"));
+            if let Some(widths) = directive {
+                output.pop();
+                if let Some(end) = html.find('>') {
+                    html.insert_str(end + 1, &render_colgroup(&widths));
+                }
             }
-            table_lines.push(*line);
-        } else {
-            if in_table && !table_lines.is_empty() {
-                // End of table
-                let table_text = table_lines.join("\n");
-
-                // Check if it's UMD format
-                let table_lines_refs: Vec<&str> = table_text.lines().collect();
-                if is_umd_table(&table_lines_refs) {
-                    // Parse and replace with marker
-                    let html = parse_table(&table_text);
-                    // Use a marker with newlines to make comrak treat it as block-level
-                    let marker = format!("\n\nUMD_TABLE_MARKER_{}_END\n\n", table_counter);
-                    tables.push((marker.clone(), html));
-
-                    // Replace in result
-                    result = result.replace(&table_text, &marker);
-                    table_counter += 1;
+
+            // Use a marker with newlines to make comrak treat it as block-level;
+            // in a list item a bare indented line keeps a tight list tight,
+            // unless caption paragraphs have to sit next to it
+            let marker = format!("UMD_TABLE_MARKER_{}_END", tables.len());
+            let captioned = caption_before.is_some() || caption_after.is_some();
+            output.push(match list_indent {
+                _ if !quote.is_empty() => {
+                    let blank = quote.trim_end();
+                    format!("{}\n{}{}\n{}", blank, quote, marker, blank)
                 }
+                Some(indent) if !captioned => format!("{}{}", indent, marker),
+                indent => format!("\n\n{}{}\n\n", indent.unwrap_or_default(), marker),
+            });
+            tables.push((marker, html));
+        } else {
+            let first_row = start + usize::from(caption_before.is_some());
+            output.extend(lines[first_row..index].iter().map(|line| line.to_string()));
+        }
 
-                table_lines.clear();
-                in_table = false;
-            }
+        if let Some(caption) = caption_after {
+            output.push(caption::marker_after(caption, prefix));
+            index = caption_at + 1;
         }
     }
 
-    // Handle table at end of file
-    if in_table && !table_lines.is_empty() {
-        let table_text = table_lines.join("\n");
-        let table_lines_refs: Vec<&str> = table_text.lines().collect();
-        if is_umd_table(&table_lines_refs) {
-            let html = parse_table(&table_text);
-            // Use a marker with newlines
-            let marker = format!("\n\nUMD_TABLE_MARKER_{}_END\n\n", table_counter);
-            tables.push((marker.clone(), html));
-            result = result.replace(&table_text, &marker);
-        }
+    let mut result = output.join("\n");
+    if input.ends_with('\n') {
+        result.push('\n');
     }
 
+    // Directives without a UMD table below them stay as written
+    let result = COLWIDTHS_MARKER
+        .replace_all(&result, |caps: &Captures| {
+            format!("@@colwidths({})", &caps[1])
+        })
+        .to_string();
+
     (result, tables)
 }
 
@@ -400,6 +535,78 @@ mod tests {
         assert!(html.contains(r#"colspan="2""#));
     }
 
+    #[test]
+    fn test_column_alignment() {
+        assert_eq!(parse_column_spec("| A | B |c"), None);
+        assert_eq!(parse_column_spec("|||c"), None);
+
+        let input = "| ~A | ~B | ~C |h\n|<|=|>|c\n| 1 | LEFT: 2 | 3 |";
+        let html = parse_table(input);
+        assert!(html.contains(
+            r#"<th class="text-start">A</th><th class="text-center">B</th><th class="text-end">C</th>"#
+        ));
+        assert!(html.contains(
+            r#"<td class="text-start">1</td><td class="text-start">2</td><td class="text-end">3</td>"#
+        ));
+        assert!(!html.contains("|c"));
+    }
+
+    #[test]
+    fn test_colwidths_directive() {
+        let input =
+            protect_colwidths("@@colwidths(20, 30px, bad)\n| A | B | C |\n\n@@colwidths(5)\n");
+        let (result, tables) = extract_umd_tables(&input);
+        assert_eq!(tables.len(), 1);
+        assert!(tables[0].1.starts_with(
+            r#"<table class="table umd-table"><colgroup><col style="width: 20%" /><col style="width: 30px" /><col /></colgroup><tbody>"#
+        ));
+        assert!(!result.contains("@@colwidths(20"));
+        assert!(result.contains("@@colwidths(5)"));
+    }
+
+    #[test]
+    fn test_caption_markers() {
+        let (result, tables) =
+            extract_umd_tables("|c Sales\n| A | B |\n|---|---|\n\n| C | D |\n\nTable: Totals\n");
+        assert_eq!(tables.len(), 1);
+        assert!(result.starts_with(
+            "\n{{TABLE_CAPTION_BEFORE:Sales:TABLE_CAPTION_BEFORE}}\n\n| A | B |\n|---|---|\n"
+        ));
+        assert!(result.ends_with(
+            "UMD_TABLE_MARKER_0_END\n\n\n\n{{TABLE_CAPTION_AFTER:Totals:TABLE_CAPTION_AFTER}}\n\n"
+        ));
+    }
+
+    #[test]
+    fn test_table_inside_list_item() {
+        let (result, tables) = extract_umd_tables("- Item\n    | A | B |\n- Next\n");
+        assert_eq!(tables.len(), 1);
+        assert_eq!(result, "- Item\n    UMD_TABLE_MARKER_0_END\n- Next\n");
+
+        let (result, _) = extract_umd_tables("- Item\n    |c Cap\n    | A |\n");
+        assert_eq!(
+            result,
+            "- Item\n\n    {{TABLE_CAPTION_BEFORE:Cap:TABLE_CAPTION_BEFORE}}\n\n\n\n    UMD_TABLE_MARKER_0_END\n\n\n"
+        );
+    }
+
+    #[test]
+    fn test_table_inside_blockquote() {
+        let (result, tables) = extract_umd_tables("> Quote\n> | A | B |\n> > | C |\n");
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0].0, "UMD_TABLE_MARKER_0_END");
+        assert_eq!(
+            result,
+            "> Quote\n>\n> UMD_TABLE_MARKER_0_END\n>\n> >\n> > UMD_TABLE_MARKER_1_END\n> >\n"
+        );
+
+        let (result, _) = extract_umd_tables("> | A |\n>\n> Table: Cap");
+        assert_eq!(
+            result,
+            ">\n> UMD_TABLE_MARKER_0_END\n>\n>\n> {{TABLE_CAPTION_AFTER:Cap:TABLE_CAPTION_AFTER}}\n>"
+        );
+    }
+
     #[test]
     fn test_parse_with_decoration() {
         let input = "| COLOR(red): ~A | B |h";
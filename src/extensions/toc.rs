@@ -0,0 +1,330 @@
+//! Native table of contents (`@toc`)
+//!
+//! `@toc` is rendered as an empty `<nav class="umd-toc">` placeholder while
+//! plugins are restored, and filled in once heading IDs are final, so the
+//! links match the deduplicated anchors.
+//!
+//! Arguments (comma separated, all optional):
+//!
+//! - `@toc(3)` - headings up to level 3
+//! - `@toc(2,4)` - headings from level 2 to level 4 (`min=2, max=4` also work)
+//! - `@toc(section=#install)` - only the headings below the `install` heading
+//! - `@toc(numbered)` - prefix entries with hierarchical numbers (`1.2.3`)
+//!
+//! With `ParserOptions.number_headings`, every entry shows the number of its
+//! heading instead.
+
+use std::borrow::Cow;
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use crate::html_builder::attr_escape;
+use crate::parser::Locale;
+
+/// Regex to detect TOC placeholders emitted by [`render_toc_placeholder`]
+static TOC_PLACEHOLDER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"<nav class="umd-toc" data-toc="([^"]*)"></nav>"#).unwrap());
+
+/// Regex to collect headings with their anchor IDs
+static HEADING: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?s)<h([1-6])[^>]*><a href="[^"]*"[^>]*class="anchor" id="([^"]+)"[^>]*></a>(.*?)</h[1-6]>"#,
+    )
+    .unwrap()
+});
+
+/// Regex to detect the number added by `number_headings`
+static HEADING_NUMBER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"^<span class="umd-heading-number">([^<]*)</span> "#).unwrap());
+
+static TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]+>").unwrap());
+
+/// Parsed `@toc` arguments
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TocArgs {
+    min: u8,
+    max: u8,
+    section: Option<String>,
+    numbered: bool,
+}
+
+impl TocArgs {
+    fn parse(args: &str) -> Self {
+        let mut toc = TocArgs {
+            min: 1,
+            max: 6,
+            section: None,
+            numbered: false,
+        };
+        let mut levels = Vec::new();
+        for arg in args.split(',').map(str::trim).filter(|arg| !arg.is_empty()) {
+            match arg
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+            {
+                Some(("min", value)) => toc.min = value.parse().unwrap_or(toc.min),
+                Some(("max", value)) => toc.max = value.parse().unwrap_or(toc.max),
+                Some(("section", value)) => {
+                    toc.section = Some(value.trim_start_matches('#').to_string())
+                }
+                Some(_) => {}
+                None if arg == "numbered" => toc.numbered = true,
+                None => levels.extend(arg.parse::<u8>().ok()),
+            }
+        }
+        match levels.as_slice() {
+            [max] => toc.max = *max,
+            [min, max, ..] => (toc.min, toc.max) = (*min, *max),
+            [] => {}
+        }
+        toc.min = toc.min.clamp(1, 6);
+        toc.max = toc.max.clamp(toc.min, 6);
+        toc
+    }
+}
+
+/// A heading listed in the table of contents
+struct Entry {
+    level: u8,
+    id: String,
+    /// Section number rendered into the heading, if any
+    number: Option<String>,
+    text: String,
+}
+
+/// Placeholder for `@toc(args)`, filled in by [`apply_toc`]
+pub fn render_toc_placeholder(args: &str) -> String {
+    format!(
+        "<nav class=\"umd-toc\" data-toc=\"{}\"></nav>",
+        attr_escape(args)
+    )
+}
+
+/// Fill `@toc` placeholders with the document's headings
+///
+/// Headings must already carry their final anchor IDs. A table of contents
+/// without matching headings is removed.
+///
+/// # Examples
+///
+/// ```
+/// use umd::extensions::toc::{apply_toc, render_toc_placeholder};
+/// use umd::parser::Locale;
+///
+/// let html = format!(
+///     "{}<h2><a href=\"#h-1\" aria-hidden=\"true\" class=\"anchor\" id=\"h-1\"></a>Setup</h2>",
+///     render_toc_placeholder("")
+/// );
+/// let output = apply_toc(&html, &Locale::default());
+/// assert!(output.contains(r##"<a class="nav-link" href="#h-1">Setup</a>"##));
+/// ```
+pub fn apply_toc<'a>(html: &'a str, locale: &Locale) -> Cow<'a, str> {
+    if !TOC_PLACEHOLDER.is_match(html) {
+        return Cow::Borrowed(html);
+    }
+
+    let headings: Vec<Entry> = HEADING
+        .captures_iter(html)
+        .map(|caps| {
+            let number = HEADING_NUMBER.captures(&caps[3]);
+            let content = match &number {
+                Some(number) => &caps[3][number[0].len()..],
+                None => &caps[3],
+            };
+            Entry {
+                level: caps[1].parse().unwrap_or(1),
+                id: caps[2].to_string(),
+                number: number.map(|number| number[1].to_string()),
+                text: TAG
+                    .replace_all(content, "")
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            }
+        })
+        .collect();
+
+    TOC_PLACEHOLDER.replace_all(html, |caps: &Captures| {
+        let args = TocArgs::parse(&html_escape::decode_html_entities(&caps[1]));
+        render_toc(&select_headings(&headings, &args), &args, locale)
+    })
+}
+
+/// Headings within the requested section and level range
+fn select_headings<'a>(headings: &'a [Entry], args: &TocArgs) -> Vec<&'a Entry> {
+    let scope: &[Entry] = match &args.section {
+        Some(section) => {
+            let prefixed = format!("h-{}", section);
+            let Some(start) = headings
+                .iter()
+                .position(|entry| entry.id == prefixed || entry.id == *section)
+            else {
+                return Vec::new();
+            };
+            let level = headings[start].level;
+            let rest = &headings[start + 1..];
+            let end = rest
+                .iter()
+                .position(|entry| entry.level <= level)
+                .unwrap_or(rest.len());
+            &rest[..end]
+        }
+        None => headings,
+    };
+    scope
+        .iter()
+        .filter(|entry| (args.min..=args.max).contains(&entry.level))
+        .collect()
+}
+
+/// Render entries as nested `<ol>` lists
+///
+/// A level jump (h2 → h4) nests one list deeper; a heading shallower than
+/// every open list continues the outermost one.
+fn render_toc(entries: &[&Entry], args: &TocArgs, locale: &Locale) -> String {
+    if entries.is_empty() {
+        return String::new();
+    }
+
+    let mut html = format!(
+        "<nav class=\"umd-toc\" aria-label=\"{}\">\n<ol class=\"nav flex-column\">",
+        attr_escape(&locale.toc)
+    );
+    // Levels of the open lists, and the item count in each of them
+    let mut levels: Vec<u8> = Vec::new();
+    let mut counters: Vec<usize> = Vec::new();
+
+    for entry in entries {
+        match levels.last().copied() {
+            None => {
+                levels.push(entry.level);
+                counters.push(0);
+            }
+            Some(last) if entry.level > last => {
+                html.push_str("\n<ol class=\"nav flex-column ps-3\">");
+                levels.push(entry.level);
+                counters.push(0);
+            }
+            Some(_) => {
+                html.push_str("</li>");
+                while levels.len() > 1 && levels[levels.len() - 2] >= entry.level {
+                    levels.pop();
+                    counters.pop();
+                    html.push_str("</ol></li>");
+                }
+                if let Some(last) = levels.last_mut() {
+                    *last = (*last).min(entry.level);
+                }
+            }
+        }
+        if let Some(count) = counters.last_mut() {
+            *count += 1;
+        }
+
+        let number = match &entry.number {
+            Some(number) => Some(number.clone()),
+            None if args.numbered => Some(
+                counters
+                    .iter()
+                    .map(usize::to_string)
+                    .collect::<Vec<_>>()
+                    .join("."),
+            ),
+            None => None,
+        };
+        let number = number
+            .map(|number| format!("<span class=\"umd-toc-number\">{}</span> ", number))
+            .unwrap_or_default();
+        html.push_str(&format!(
+            "\n<li class=\"nav-item\"><a class=\"nav-link\" href=\"#{}\">{}{}</a>",
+            entry.id, number, entry.text
+        ));
+    }
+
+    html.push_str("</li>");
+    for _ in 1..levels.len() {
+        html.push_str("</ol></li>");
+    }
+    html.push_str("\n</ol>\n</nav>");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heading(level: u8, id: &str, text: &str) -> String {
+        format!(
+            "<h{level}><a href=\"#{id}\" aria-hidden=\"true\" class=\"anchor\" id=\"{id}\"></a>{text}</h{level}>\n"
+        )
+    }
+
+    fn document(args: &str) -> String {
+        let mut html = render_toc_placeholder(args);
+        html.push('\n');
+        html.push_str(&heading(1, "h-1", "Guide"));
+        html.push_str(&heading(2, "h-install", "Install <em>now</em>"));
+        html.push_str(&heading(3, "h-3", "Linux"));
+        html.push_str(&heading(2, "h-4", "Usage"));
+        html
+    }
+
+    #[test]
+    fn test_args() {
+        assert_eq!(TocArgs::parse("3").max, 3);
+        let args = TocArgs::parse("2, 4, numbered");
+        assert_eq!((args.min, args.max, args.numbered), (2, 4, true));
+        let args = TocArgs::parse("section=#install, max=9");
+        assert_eq!(args.section.as_deref(), Some("install"));
+        assert_eq!(args.max, 6);
+    }
+
+    #[test]
+    fn test_nested_toc() {
+        let output = apply_toc(&document(""), &Locale::default()).into_owned();
+        let toc = &output[..output.find("</nav>").unwrap()];
+        assert!(toc.starts_with(
+            "<nav class=\"umd-toc\" aria-label=\"Table of contents\">\n<ol class=\"nav flex-column\">\n<li class=\"nav-item\"><a class=\"nav-link\" href=\"#h-1\">Guide</a>\n<ol class=\"nav flex-column ps-3\">"
+        ));
+        assert!(toc.contains(r##"<a class="nav-link" href="#h-install">Install now</a>"##));
+        assert!(toc.contains(r##"<a class="nav-link" href="#h-3">Linux</a></li></ol></li>"##));
+        assert!(toc.ends_with(
+            r##"<a class="nav-link" href="#h-4">Usage</a></li></ol></li>
+</ol>
+"##
+        ));
+    }
+
+    #[test]
+    fn test_section_and_numbering() {
+        let output =
+            apply_toc(&document("section=#install, numbered"), &Locale::default()).into_owned();
+        let toc = &output[..output.find("</nav>").unwrap()];
+        assert!(toc.contains(r#"<span class="umd-toc-number">1</span> Linux"#));
+        assert!(!toc.contains("Usage"));
+
+        let output = apply_toc(&document("2,2,numbered"), &Locale::default()).into_owned();
+        assert!(output.contains(r#"<span class="umd-toc-number">2</span> Usage"#));
+        assert!(!output.contains(r##"class="nav-link" href="#h-3""##));
+    }
+
+    #[test]
+    fn test_heading_numbers() {
+        let html = document("").replace(
+            "class=\"anchor\" id=\"h-3\"></a>",
+            "class=\"anchor\" id=\"h-3\"></a><span class=\"umd-heading-number\">1.1.1</span> ",
+        );
+        let output = apply_toc(&html, &Locale::default());
+        assert!(output.contains(
+            r##"<a class="nav-link" href="#h-3"><span class="umd-toc-number">1.1.1</span> Linux</a>"##
+        ));
+        assert!(output.contains(r##"<a class="nav-link" href="#h-4">Usage</a>"##));
+    }
+
+    #[test]
+    fn test_empty_toc_is_removed() {
+        let output = apply_toc(&document("section=missing"), &Locale::default()).into_owned();
+        assert!(!output.contains("umd-toc"));
+    }
+}
@@ -0,0 +1,138 @@
+//! C ABI bindings (`ffi` feature)
+//!
+//! Lets PHP (`FFI`), Python (`ctypes`/`cffi`) and other native hosts call
+//! the parser from the `cdylib` build instead of shelling out to the CLI or
+//! loading the WASM module. The declarations are in `include/umd.h`.
+//!
+//! - [`umd_parse`] returns the same HTML as the WASM `parse` function, with
+//!   options as a JSON string using the WASM keys
+//! - every string returned by [`umd_parse`] must be released with
+//!   [`umd_free`], never with the host's `free`
+//! - all strings are NUL-terminated UTF-8
+//!
+//! A panic inside the parser is caught and reported as `NULL`, since it must
+//! not unwind across the C boundary.
+
+use std::ffi::{CStr, CString, c_char};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::ptr;
+
+use crate::parse_with_options_json;
+
+/// Version of the library as a static NUL-terminated string
+const VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "\0");
+
+/// Borrow a C string as UTF-8, `None` for `NULL` or invalid UTF-8
+///
+/// # Safety
+///
+/// `value` must be `NULL` or point to a NUL-terminated string that outlives
+/// the returned reference.
+unsafe fn utf8<'a>(value: *const c_char) -> Option<&'a str> {
+    if value.is_null() {
+        return None;
+    }
+    // SAFETY: non-null and NUL-terminated per the caller's contract
+    unsafe { CStr::from_ptr(value) }.to_str().ok()
+}
+
+/// Parse Universal Markdown and return HTML
+///
+/// `options_json` may be `NULL` or empty for the default options. Invalid
+/// JSON falls back to the defaults, like the WASM API.
+///
+/// Returns a newly allocated string to be released with [`umd_free`], or
+/// `NULL` when `input` is `NULL` or not UTF-8, or when parsing panicked.
+///
+/// # Safety
+///
+/// `input` and `options_json` must each be `NULL` or point to a valid
+/// NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn umd_parse(
+    input: *const c_char,
+    options_json: *const c_char,
+) -> *mut c_char {
+    // SAFETY: forwarded from the caller's contract
+    let Some(input) = (unsafe { utf8(input) }) else {
+        return ptr::null_mut();
+    };
+    // SAFETY: forwarded from the caller's contract
+    let options_json = unsafe { utf8(options_json) };
+
+    catch_unwind(AssertUnwindSafe(|| {
+        parse_with_options_json(input, options_json)
+    }))
+    .ok()
+    .and_then(|html| CString::new(html).ok())
+    .map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// Release a string returned by [`umd_parse`]
+///
+/// `NULL` is ignored.
+///
+/// # Safety
+///
+/// `value` must be `NULL` or a pointer returned by [`umd_parse`] that has
+/// not been released yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn umd_free(value: *mut c_char) {
+    if !value.is_null() {
+        // SAFETY: allocated by `CString::into_raw` in `umd_parse`
+        drop(unsafe { CString::from_raw(value) });
+    }
+}
+
+/// Version of the library, e.g. `"0.1.1"`
+///
+/// The string is static and must not be released.
+#[unsafe(no_mangle)]
+pub extern "C" fn umd_version() -> *const c_char {
+    VERSION.as_ptr().cast()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &CStr, options_json: Option<&CStr>) -> Option<String> {
+        let options_json = options_json.map_or(ptr::null(), CStr::as_ptr);
+        unsafe {
+            let html = umd_parse(input.as_ptr(), options_json);
+            if html.is_null() {
+                return None;
+            }
+            let result = CStr::from_ptr(html).to_str().unwrap().to_string();
+            umd_free(html);
+            Some(result)
+        }
+    }
+
+    #[test]
+    fn test_parse_with_options() {
+        let html = parse(c"# Title\n\n**bold**", None).unwrap();
+        assert!(html.contains("<strong>bold</strong>"), "{}", html);
+
+        let html = parse(c"[docs](/docs)", Some(c"{\"baseUrl\":\"/app\"}")).unwrap();
+        assert!(html.contains("href=\"/app/docs\""), "{}", html);
+
+        let html = parse(c"[docs](/docs)", Some(c"{not json")).unwrap();
+        assert!(html.contains("href=\"/docs\""), "{}", html);
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert_eq!(parse(c"\xff", None), None);
+        unsafe {
+            assert!(umd_parse(ptr::null(), ptr::null()).is_null());
+            umd_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn test_version() {
+        let version = unsafe { CStr::from_ptr(umd_version()) };
+        assert_eq!(version.to_str(), Ok(env!("CARGO_PKG_VERSION")));
+    }
+}
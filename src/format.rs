@@ -0,0 +1,519 @@
+//! Canonical source formatting
+//!
+//! [`format`] rewrites Universal Markdown source into one canonical layout
+//! without changing what it renders, so git-backed wikis get diffs that
+//! only show real edits:
+//!
+//! - line endings become `\n`, trailing whitespace is removed (a hard line
+//!   break keeps exactly two spaces), runs of blank lines collapse to one
+//!   and the file ends with a single newline
+//! - `*` and `+` bullets become `-`; a list that directly follows another
+//!   list with a different bullet keeps a different bullet, so the two
+//!   lists stay apart
+//! - setext headings (`Title` / `===`) become ATX headings, and ATX headings
+//!   lose extra spaces and closing `#`s
+//! - GFM tables get padded columns and a `| --- |` separator row keeping
+//!   the column alignment
+//! - block decoration prefixes are separated by one space
+//!   (`COLOR(red):CENTER:text` → `COLOR(red): CENTER: text`)
+//!
+//! Frontmatter, fenced code, multi-line plugin bodies, `/* */` comments and
+//! lines indented as code are copied verbatim, and UMD tables (`|>|`,
+//! cell decorations, tables without a separator row) are left as written.
+//! Formatting is idempotent: formatting the output again changes nothing.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::extensions::table::umd::is_umd_table;
+use crate::frontmatter::extract_frontmatter;
+
+/// Bullet list item: indentation, bullet and the rest of the line
+static BULLET_ITEM: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^([ \t]*)([-+*])([ \t]+\S.*)$").unwrap());
+
+/// Ordered list item
+static ORDERED_ITEM: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[ \t]*\d{1,9}[.)](?:[ \t]|$)").unwrap());
+
+/// Thematic break (`***`, `- - -`, `___`)
+static THEMATIC_BREAK: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^ {0,3}(?:(?:\*[ \t]*){3,}|(?:-[ \t]*){3,}|(?:_[ \t]*){3,})$").unwrap()
+});
+
+/// Setext heading underline
+static SETEXT_UNDERLINE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^ {0,3}(=+|-+)[ \t]*$").unwrap());
+
+/// GFM table separator row (`| --- | :-: |`)
+static TABLE_SEPARATOR: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\|?[ \t]*:?-+:?[ \t]*(?:\|[ \t]*:?-+:?[ \t]*)*\|?[ \t]*$").unwrap());
+
+/// One block decoration prefix at the start of a line, with the spaces
+/// after it (the prefixes of `preprocess_conflicts`)
+static DECORATION_PREFIX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^(SIZE\([^)]+\)|COLOR\((?:[^()\n]|\([^()\n]*\))*\)|LANG\([^)]*\)|RTL|LTR|BDI|TRUNCATE|TOP|MIDDLE|BOTTOM|BASELINE|JUSTIFY|RIGHT|CENTER|LEFT):[ \t]*",
+    )
+    .unwrap()
+});
+
+/// Format Universal Markdown source canonically
+///
+/// # Arguments
+///
+/// * `input` - The Universal Markdown source text
+///
+/// # Returns
+///
+/// The formatted source, ending with a single newline (empty for a blank
+/// document)
+///
+/// # Examples
+///
+/// ```
+/// use umd::format;
+///
+/// let input = "Title\n=====\n\n\n* one  \n* two\n\n|a|b|\n|:-|-:|\n|1|22|\n";
+/// let formatted = format(input);
+/// assert_eq!(
+///     formatted,
+///     "# Title\n\n- one\n- two\n\n| a   |   b |\n| :-- | --: |\n| 1   |  22 |\n"
+/// );
+/// assert_eq!(format(&formatted), formatted);
+/// ```
+pub fn format(input: &str) -> String {
+    let input = input.replace("\r\n", "\n");
+    let (_, body) = extract_frontmatter(&input);
+    let frontmatter = &input[..input.len() - body.len()];
+
+    let lines: Vec<&str> = body.lines().collect();
+    let mut output: Vec<String> = Vec::with_capacity(lines.len());
+    let mut fence: Option<(char, usize)> = None;
+    let mut in_plugin = false;
+    let mut in_comment = false;
+    // Open bullet lists: (indentation, bullet as written, bullet in the output)
+    let mut bullets: Vec<(usize, char, char)> = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim();
+
+        if let Some((fence_char, fence_len)) = fence {
+            let run = trimmed.chars().take_while(|c| *c == fence_char).count();
+            if run >= fence_len && trimmed[run..].trim().is_empty() {
+                fence = None;
+            }
+            output.push(line.to_string());
+            i += 1;
+            continue;
+        }
+        if in_plugin || in_comment {
+            in_plugin = in_plugin && !line.contains("}}");
+            in_comment = in_comment && !line.contains("*/");
+            output.push(line.to_string());
+            i += 1;
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            let run = lines[i..]
+                .iter()
+                .take_while(|line| line.trim().is_empty())
+                .count();
+            let next = lines.get(i + run);
+            // Blank lines inside indented code are part of the code
+            let in_indented_code = output.last().is_some_and(|prev| indent_width(prev) >= 4)
+                && next.is_some_and(|next| indent_width(next) >= 4);
+            if next.is_some() && !output.is_empty() {
+                let kept = if in_indented_code { run } else { 1 };
+                output.extend(std::iter::repeat_n(String::new(), kept));
+            }
+            i += run;
+            continue;
+        }
+
+        let indent = indent_width(line);
+        let after_blank = output.last().is_none_or(|prev| prev.is_empty());
+        if indent >= 4 {
+            // Indented code, or list content whose trailing spaces may matter
+            output.push(line.to_string());
+            i += 1;
+            continue;
+        }
+
+        if indent == 0 && after_blank && !BULLET_ITEM.is_match(line) && !ORDERED_ITEM.is_match(line)
+        {
+            bullets.clear();
+        }
+
+        let fence_char = trimmed.chars().next().filter(|c| *c == '`' || *c == '~');
+        if let Some(ch) = fence_char {
+            let run = trimmed.chars().take_while(|c| *c == ch).count();
+            if run >= 3 {
+                fence = Some((ch, run));
+                output.push(line.trim_end().to_string());
+                i += 1;
+                continue;
+            }
+        }
+
+        if let Some(end) = gfm_table_end(&lines, i) {
+            output.extend(format_table(&lines[i..end]));
+            i = end;
+            continue;
+        }
+
+        if after_blank
+            && let Some(next) = lines.get(i + 1)
+            && let Some(caps) = SETEXT_UNDERLINE.captures(next)
+            && is_plain_paragraph_line(line)
+        {
+            let level = if caps[1].starts_with('=') { "#" } else { "##" };
+            output.push(format!("{} {}", level, trimmed));
+            i += 2;
+            continue;
+        }
+
+        let formatted = if let Some(heading) = format_atx_heading(line) {
+            heading
+        } else if let Some(decorated) = format_decoration_prefixes(line) {
+            decorated
+        } else if let Some(item) = format_bullet(line, &mut bullets) {
+            item
+        } else {
+            if ORDERED_ITEM.is_match(line) {
+                bullets.retain(|(bullet_indent, _, _)| *bullet_indent < indent);
+            }
+            line.to_string()
+        };
+
+        if let Some(open) = formatted.rfind("{{") {
+            in_plugin = !formatted[open..].contains("}}");
+        }
+        if let Some(open) = formatted.rfind("/*") {
+            in_comment = !formatted[open..].contains("*/");
+        }
+
+        // Trailing spaces only make a hard break before a continuation line
+        let hard_break = formatted.ends_with("  ")
+            && lines
+                .get(i + 1)
+                .is_some_and(|next| !next.trim().is_empty() && !starts_block(next));
+        let mut formatted = formatted.trim_end().to_string();
+        if hard_break {
+            formatted.push_str("  ");
+        }
+        output.push(formatted);
+        i += 1;
+    }
+
+    // The frontmatter pattern takes the blank lines after it along
+    let mut result = frontmatter.trim_end().to_string();
+    if !result.is_empty() {
+        result.push('\n');
+    }
+    if !output.is_empty() {
+        if !result.is_empty() {
+            result.push('\n');
+        }
+        result.push_str(&output.join("\n"));
+        result.push('\n');
+    }
+    result
+}
+
+/// Column of the first non-blank character (tabs count as four)
+fn indent_width(line: &str) -> usize {
+    line.chars()
+        .take_while(|ch| *ch == ' ' || *ch == '\t')
+        .map(|ch| if ch == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+/// Whether `line` starts a new block rather than continuing a paragraph
+fn starts_block(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    indent_width(line) < 4
+        && (BULLET_ITEM.is_match(line)
+            || format_atx_heading(line).is_some()
+            || trimmed.starts_with("```")
+            || trimmed.starts_with("~~~"))
+}
+
+/// Whether `line` can be a one-line setext heading paragraph
+fn is_plain_paragraph_line(line: &str) -> bool {
+    let trimmed = line.trim();
+    indent_width(line) < 4
+        && !trimmed.starts_with([
+            '#', '>', '|', '@', '-', '*', '+', '=', '<', '`', '~', ':', '[',
+        ])
+        && !ORDERED_ITEM.is_match(line)
+        && !trimmed.contains("{{")
+        && !trimmed.contains("/*")
+        && !DECORATION_PREFIX.is_match(trimmed)
+}
+
+/// `## Title ##` → `## Title`
+fn format_atx_heading(line: &str) -> Option<String> {
+    let trimmed = line.trim_start();
+    let level = trimmed.chars().take_while(|c| *c == '#').count();
+    let rest = &trimmed[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        return None;
+    }
+    let mut text = rest.trim();
+    let without_closing = text.trim_end_matches('#');
+    if without_closing.is_empty() {
+        text = "";
+    } else if without_closing.ends_with([' ', '\t']) {
+        text = without_closing.trim_end();
+    }
+    let hashes = "#".repeat(level);
+    Some(if text.is_empty() {
+        hashes
+    } else {
+        format!("{} {}", hashes, text)
+    })
+}
+
+/// `COLOR(red):CENTER:text` → `COLOR(red): CENTER: text`
+fn format_decoration_prefixes(line: &str) -> Option<String> {
+    let mut rest = line;
+    let mut prefixes = Vec::new();
+    while let Some(caps) = DECORATION_PREFIX.captures(rest) {
+        prefixes.push(format!("{}:", &caps[1]));
+        rest = &rest[caps[0].len()..];
+    }
+    if prefixes.is_empty() {
+        return None;
+    }
+    let mut formatted = prefixes.join(" ");
+    if !rest.trim().is_empty() {
+        formatted.push(' ');
+        formatted.push_str(rest);
+    }
+    Some(formatted)
+}
+
+/// Rewrite the bullet of a list item, keeping adjacent lists apart
+fn format_bullet(line: &str, bullets: &mut Vec<(usize, char, char)>) -> Option<String> {
+    if THEMATIC_BREAK.is_match(line) {
+        return None;
+    }
+    let caps = BULLET_ITEM.captures(line)?;
+    let indent = indent_width(&caps[1]);
+    let bullet = caps[2].chars().next()?;
+
+    bullets.retain(|(open_indent, _, _)| *open_indent <= indent);
+    let output = match bullets.last_mut() {
+        Some(open) if open.0 == indent && open.1 == bullet => open.2,
+        Some(open) if open.0 == indent => {
+            // A different bullet starts a new list
+            let output = if open.2 == '-' { '*' } else { '-' };
+            *open = (indent, bullet, output);
+            output
+        }
+        _ => {
+            bullets.push((indent, bullet, '-'));
+            '-'
+        }
+    };
+
+    let formatted = format!("{}{}{}", &caps[1], output, &caps[3]);
+    // `- - -` would turn the item into a thematic break
+    Some(if THEMATIC_BREAK.is_match(&formatted) {
+        line.to_string()
+    } else {
+        formatted
+    })
+}
+
+/// End of the GFM table starting at `lines[start]`, if it is one
+fn gfm_table_end(lines: &[&str], start: usize) -> Option<usize> {
+    let header = lines[start];
+    let separator = lines.get(start + 1)?;
+    if !header.starts_with('|') || !TABLE_SEPARATOR.is_match(separator) {
+        return None;
+    }
+    if split_row(header).len() != split_row(separator).len() {
+        return None;
+    }
+    let end = start
+        + lines[start..]
+            .iter()
+            .take_while(|line| line.starts_with('|'))
+            .count();
+    (!is_umd_table(&lines[start..end])).then_some(end)
+}
+
+/// Cells of a table row, trimmed, with escaped pipes and code spans kept
+fn split_row(line: &str) -> Vec<String> {
+    let row = line.trim();
+    let mut rest = row.strip_prefix('|').unwrap_or(row);
+    let mut cells = vec![String::new()];
+    while let Some(ch) = rest.chars().next() {
+        let len = match ch {
+            '\\' => rest.chars().take(2).map(char::len_utf8).sum(),
+            '`' => code_span_len(rest),
+            _ => ch.len_utf8(),
+        };
+        if ch == '|' {
+            cells.push(String::new());
+        } else {
+            cells.last_mut().unwrap().push_str(&rest[..len]);
+        }
+        rest = &rest[len..];
+    }
+    // A closing pipe leaves an empty last cell
+    if cells.len() > 1 && cells.last().is_some_and(|cell| cell.trim().is_empty()) {
+        cells.pop();
+    }
+    cells.iter().map(|cell| cell.trim().to_string()).collect()
+}
+
+/// Byte length of the code span opened by the backtick run at the start of
+/// `text`, or of the run alone when no run of the same length closes it
+fn code_span_len(text: &str) -> usize {
+    let run_len = |text: &str| text.len() - text.trim_start_matches('`').len();
+    let ticks = run_len(text);
+    let mut offset = ticks;
+    while let Some(start) = text[offset..].find('`') {
+        let start = offset + start;
+        let run = run_len(&text[start..]);
+        if run == ticks {
+            return start + run;
+        }
+        offset = start + run;
+    }
+    ticks
+}
+
+/// Pad the columns of a GFM table
+fn format_table(lines: &[&str]) -> Vec<String> {
+    let header = split_row(lines[0]);
+    let alignments: Vec<(bool, bool)> = split_row(lines[1])
+        .iter()
+        .map(|cell| (cell.starts_with(':'), cell.ends_with(':')))
+        .collect();
+    let mut rows: Vec<Vec<String>> = vec![header];
+    rows.extend(lines[2..].iter().map(|line| split_row(line)));
+
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let mut widths = vec![3; columns];
+    for row in &rows {
+        for (column, cell) in row.iter().enumerate() {
+            widths[column] = widths[column].max(display_width(cell));
+        }
+    }
+
+    let render = |cells: Vec<String>| {
+        let cells: Vec<String> = cells
+            .into_iter()
+            .enumerate()
+            .map(|(column, cell)| {
+                let padding = widths[column] - display_width(&cell);
+                match alignments.get(column) {
+                    Some((true, true)) => format!(
+                        "{}{}{}",
+                        " ".repeat(padding / 2),
+                        cell,
+                        " ".repeat(padding - padding / 2)
+                    ),
+                    Some((false, true)) => format!("{}{}", " ".repeat(padding), cell),
+                    _ => format!("{}{}", cell, " ".repeat(padding)),
+                }
+            })
+            .collect();
+        format!("| {} |", cells.join(" | "))
+    };
+
+    let mut output = Vec::with_capacity(lines.len());
+    let mut rows = rows.into_iter();
+    output.push(render(rows.next().unwrap_or_default()));
+    let separator: Vec<String> = (0..alignments.len())
+        .map(|column| {
+            let (left, right) = alignments[column];
+            let dashes = widths[column] - usize::from(left) - usize::from(right);
+            format!(
+                "{}{}{}",
+                if left { ":" } else { "" },
+                "-".repeat(dashes),
+                if right { ":" } else { "" }
+            )
+        })
+        .collect();
+    output.push(format!("| {} |", separator.join(" | ")));
+    output.extend(rows.map(render));
+    output
+}
+
+/// Width of `text` in a monospace editor: East Asian wide characters and
+/// emoji take two columns
+fn display_width(text: &str) -> usize {
+    text.graphemes(true)
+        .map(|grapheme| {
+            let wide = grapheme.chars().next().is_some_and(|ch| {
+                matches!(ch as u32,
+                    0x1100..=0x115F
+                    | 0x2E80..=0x303E
+                    | 0x3041..=0x33FF
+                    | 0x3400..=0x4DBF
+                    | 0x4E00..=0x9FFF
+                    | 0xA000..=0xA4CF
+                    | 0xAC00..=0xD7A3
+                    | 0xF900..=0xFAFF
+                    | 0xFE30..=0xFE4F
+                    | 0xFF00..=0xFF60
+                    | 0xFFE0..=0xFFE6
+                    | 0x1F300..=0x1F64F
+                    | 0x1F900..=0x1F9FF
+                    | 0x20000..=0x3FFFD)
+            });
+            if wide { 2 } else { 1 }
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adjacent_lists_stay_apart() {
+        assert_eq!(format("* a\n* b\n+ c\n- d\n"), "- a\n- b\n* c\n- d\n");
+        assert_eq!(format("- a\n  * b\n"), "- a\n  - b\n");
+        assert_eq!(format("* * *\n"), "* * *\n");
+    }
+
+    #[test]
+    fn test_headings() {
+        assert_eq!(format("#   Title ##\n"), "# Title\n");
+        assert_eq!(format("# C#\n"), "# C#\n");
+        assert_eq!(format("Sub {#id}\n---\n"), "## Sub {#id}\n");
+        // A list item or a second paragraph line is not a one-line heading
+        assert_eq!(format("- a\n---\n"), "- a\n---\n");
+        assert_eq!(format("a\nb\n===\n"), "a\nb\n===\n");
+    }
+
+    #[test]
+    fn test_verbatim_regions() {
+        let input = "---\ntitle: x  \n---\n\n```\n* a  \n\n\n```\n@note(){{\n* b\n\n\n}}\n\n    code  \n\n\n    more\n";
+        assert_eq!(format(input), input);
+    }
+
+    #[test]
+    fn test_umd_table_is_kept() {
+        let input = "|a|>|\n|---|---|\n|b|c|\n";
+        assert_eq!(format(input), input);
+    }
+
+    #[test]
+    fn test_wide_table_cells() {
+        assert_eq!(
+            format("|名前|x|\n|-|-|\n|a|b|\n"),
+            "| 名前 | x   |\n| ---- | --- |\n| a    | b   |\n"
+        );
+    }
+}
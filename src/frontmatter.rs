@@ -24,6 +24,255 @@ pub struct Frontmatter {
     pub content: String,
 }
 
+impl Frontmatter {
+    /// Look up a top-level scalar value by key
+    ///
+    /// Only simple `key: value` (YAML) and `key = value` (TOML) lines are
+    /// recognized; nested tables, lists and multi-line values are ignored.
+    /// Surrounding single or double quotes are stripped from the value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use umd::frontmatter::extract_frontmatter;
+    ///
+    /// let (fm, _) = extract_frontmatter("---\ntitle: \"Hello\"\n---\n\nBody");
+    /// assert_eq!(fm.unwrap().get_scalar("title").as_deref(), Some("Hello"));
+    /// ```
+    pub fn get_scalar(&self, key: &str) -> Option<String> {
+        let separator = match self.format {
+            FrontmatterFormat::Yaml => ':',
+            FrontmatterFormat::Toml => '=',
+        };
+
+        self.content.lines().find_map(|line| {
+            // Indented lines belong to nested structures
+            if line.starts_with(char::is_whitespace) {
+                return None;
+            }
+            let (name, value) = line.split_once(separator)?;
+            if name.trim() != key {
+                return None;
+            }
+            let value = unquote(value.trim());
+            if value.is_empty() {
+                None
+            } else {
+                Some(value.to_string())
+            }
+        })
+    }
+
+    /// Top-level scalar values in document order
+    ///
+    /// Keys whose value is nested or spans several lines are skipped, as in
+    /// [`get_scalar`](Self::get_scalar).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use umd::frontmatter::extract_frontmatter;
+    ///
+    /// let (fm, _) = extract_frontmatter("---\ntitle: Hello\ntags:\n  - a\ndraft: true\n---\n");
+    /// assert_eq!(
+    ///     fm.unwrap().scalars(),
+    ///     vec![
+    ///         ("title".to_string(), "Hello".to_string()),
+    ///         ("draft".to_string(), "true".to_string()),
+    ///     ]
+    /// );
+    /// ```
+    pub fn scalars(&self) -> Vec<(String, String)> {
+        entries(self)
+            .into_iter()
+            .filter(|entry| !entry.table && entry.lines.len() == 1)
+            .filter_map(|entry| {
+                let key = entry.key?;
+                let value = self.get_scalar(&key)?;
+                Some((key, value))
+            })
+            .collect()
+    }
+
+    /// Merge site-level defaults beneath this frontmatter
+    ///
+    /// Precedence is decided per top-level key: a key present in the
+    /// document is kept as written (including nested values), and a key only
+    /// present in `defaults` is added. TOML tables (`[author]`) count as one
+    /// key. The result keeps the document's format; when the formats differ,
+    /// only single-line scalar defaults are carried over.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use umd::frontmatter::{Frontmatter, FrontmatterFormat, extract_frontmatter};
+    ///
+    /// let defaults = Frontmatter {
+    ///     format: FrontmatterFormat::Yaml,
+    ///     content: "layout: post\nauthor: Site".to_string(),
+    /// };
+    /// let (fm, _) = extract_frontmatter("---\nauthor: Alice\n---\n");
+    /// let merged = fm.unwrap().merge_defaults(&defaults);
+    /// assert_eq!(merged.get_scalar("author").as_deref(), Some("Alice"));
+    /// assert_eq!(merged.get_scalar("layout").as_deref(), Some("post"));
+    /// ```
+    pub fn merge_defaults(&self, defaults: &Frontmatter) -> Frontmatter {
+        let document = entries(self);
+        let inherited: Vec<Entry> = entries(defaults)
+            .into_iter()
+            .filter(|entry| {
+                entry.key.as_ref().is_some_and(|key| {
+                    !document
+                        .iter()
+                        .any(|existing| existing.key.as_ref() == Some(key))
+                })
+            })
+            .collect();
+
+        // Plain keys must precede the first TOML table, or they would be
+        // read as part of it
+        let mut lines: Vec<String> = Vec::new();
+        for entry in document.iter().filter(|entry| !entry.table) {
+            lines.extend(entry.lines.iter().map(|line| line.to_string()));
+        }
+        for entry in inherited.iter().filter(|entry| !entry.table) {
+            if defaults.format == self.format {
+                lines.extend(entry.lines.iter().map(|line| line.to_string()));
+            } else if let Some(line) = convert_scalar(entry, &self.format) {
+                lines.push(line);
+            }
+        }
+        for entry in document.iter().filter(|entry| entry.table) {
+            lines.extend(entry.lines.iter().map(|line| line.to_string()));
+        }
+        if defaults.format == self.format {
+            for entry in inherited.iter().filter(|entry| entry.table) {
+                lines.extend(entry.lines.iter().map(|line| line.to_string()));
+            }
+        }
+
+        Frontmatter {
+            format: self.format.clone(),
+            content: lines.join("\n"),
+        }
+    }
+}
+
+/// Strip one pair of surrounding single or double quotes
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+        .unwrap_or(value)
+}
+
+/// A top-level frontmatter entry with its continuation lines
+struct Entry<'a> {
+    /// Key (`None` for comments before the first key)
+    key: Option<String>,
+    /// Whether the entry is a TOML `[table]` section
+    table: bool,
+    lines: Vec<&'a str>,
+}
+
+static TOML_TABLE_HEADER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\[\[?\s*([^\[\]]+?)\s*\]\]?\s*(?:#.*)?$").unwrap());
+static TOML_KEY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"^\s*([A-Za-z0-9_\-."']+?)\s*="#).unwrap());
+
+/// Split frontmatter into top-level entries
+///
+/// YAML entries start at an unindented `key:` line; TOML entries start at a
+/// `key =` line, and a `[table]` header swallows everything up to the next
+/// header.
+fn entries(frontmatter: &Frontmatter) -> Vec<Entry<'_>> {
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut in_table = false;
+
+    for line in frontmatter.content.lines() {
+        let start = match frontmatter.format {
+            FrontmatterFormat::Yaml => {
+                if line.starts_with(|c: char| c.is_whitespace() || c == '#' || c == '-') {
+                    None
+                } else {
+                    line.split_once(':')
+                        .map(|(key, _)| (unquote(key.trim()).to_string(), false))
+                }
+            }
+            FrontmatterFormat::Toml => {
+                if let Some(caps) = TOML_TABLE_HEADER.captures(line) {
+                    in_table = true;
+                    Some((unquote(&caps[1]).to_string(), true))
+                } else if in_table {
+                    None
+                } else {
+                    TOML_KEY
+                        .captures(line)
+                        .map(|caps| (unquote(&caps[1]).to_string(), false))
+                }
+            }
+        };
+
+        match (start, entries.last_mut()) {
+            (Some((key, table)), _) => entries.push(Entry {
+                key: Some(key),
+                table,
+                lines: vec![line],
+            }),
+            (None, Some(entry)) => entry.lines.push(line),
+            (None, None) => entries.push(Entry {
+                key: None,
+                table: false,
+                lines: vec![line],
+            }),
+        }
+    }
+    entries
+}
+
+/// Rewrite a single-line scalar entry in another format
+fn convert_scalar(entry: &Entry, format: &FrontmatterFormat) -> Option<String> {
+    let key = entry.key.as_deref()?;
+    let [line] = entry.lines.as_slice() else {
+        return None;
+    };
+    match format {
+        FrontmatterFormat::Toml => {
+            let value = unquote(line.split_once(':')?.1.trim());
+            if value.is_empty() {
+                return None;
+            }
+            let bare = value == "true"
+                || value == "false"
+                || value.parse::<i64>().is_ok()
+                || value.parse::<f64>().is_ok();
+            let value = if bare {
+                value.to_string()
+            } else {
+                format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+            };
+            let key = if key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            {
+                key.to_string()
+            } else {
+                format!("\"{}\"", key.replace('"', "\\\""))
+            };
+            Some(format!("{} = {}", key, value))
+        }
+        FrontmatterFormat::Yaml => {
+            let value = line.split_once('=')?.1.trim();
+            // Inline tables are not valid YAML
+            if value.is_empty() || value.starts_with('{') {
+                return None;
+            }
+            Some(format!("{}: {}", key, value))
+        }
+    }
+}
+
 static YAML_FRONTMATTER: Lazy<Regex> = Lazy::new(|| {
     // Match YAML frontmatter: ---\n...content...\n---
     Regex::new(r"^---\s*\n([\s\S]*?)\n---\s*\n").unwrap()
@@ -140,6 +389,40 @@ mod tests {
         assert!(content.contains("**Bold**"));
     }
 
+    #[test]
+    fn test_get_scalar() {
+        let (fm, _) = extract_frontmatter("---\ntitle: 'Quoted'\ntags:\n  - title: nested\n---\n");
+        let fm = fm.unwrap();
+        assert_eq!(fm.get_scalar("title").as_deref(), Some("Quoted"));
+        assert_eq!(fm.get_scalar("tags"), None);
+
+        let (fm, _) = extract_frontmatter("+++\ntitle = \"Toml\"\n+++\n");
+        assert_eq!(fm.unwrap().get_scalar("title").as_deref(), Some("Toml"));
+    }
+
+    #[test]
+    fn test_merge_defaults() {
+        let defaults = Frontmatter {
+            format: FrontmatterFormat::Yaml,
+            content: "layout: post\nauthor: Site\ntags:\n  - default".to_string(),
+        };
+        let (fm, _) = extract_frontmatter("---\nauthor: Alice\ntags:\n  - rust\n---\n");
+        let merged = fm.unwrap().merge_defaults(&defaults);
+        assert_eq!(merged.format, FrontmatterFormat::Yaml);
+        assert_eq!(merged.get_scalar("author").as_deref(), Some("Alice"));
+        assert_eq!(merged.get_scalar("layout").as_deref(), Some("post"));
+        assert!(merged.content.contains("  - rust"));
+        assert!(!merged.content.contains("default"));
+
+        // Plain keys stay above TOML tables; YAML scalars are converted
+        let (fm, _) = extract_frontmatter("+++\ntitle = \"T\"\n[extra]\nx = 1\n+++\n");
+        let merged = fm.unwrap().merge_defaults(&defaults);
+        assert_eq!(
+            merged.content,
+            "title = \"T\"\nlayout = \"post\"\nauthor = \"Site\"\n[extra]\nx = 1"
+        );
+    }
+
     #[test]
     fn test_frontmatter_must_be_at_start() {
         let input = "Some text\n---\ntitle: Test\n---\n\nMore content";
@@ -0,0 +1,111 @@
+//! HTML output helpers
+//!
+//! Every emitter that interpolates user content into generated markup goes
+//! through these helpers:
+//!
+//! - [`text_escape`] for element content
+//! - [`attr_escape`] for double-quoted attribute values
+//! - [`url_attr`] for `href` / `src` values (dangerous schemes blocked first)
+//!
+//! Values often pass through the sanitizer before reaching an emitter, so
+//! existing character references (`&amp;`, `&#39;`, ...) are kept as-is
+//! instead of being escaped a second time. A bare `&` is always escaped.
+
+use std::borrow::Cow;
+
+use crate::sanitizer::{is_html_entity, sanitize_url};
+
+fn escape(value: &str, quotes: bool) -> Cow<'_, str> {
+    let needs_escape =
+        |ch: char| matches!(ch, '&' | '<' | '>') || (quotes && matches!(ch, '"' | '\''));
+    if !value.contains(needs_escape) {
+        return Cow::Borrowed(value);
+    }
+
+    let mut result = String::with_capacity(value.len() + 16);
+    let mut chars = value.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '&' if is_html_entity(&mut chars.clone()) => result.push('&'),
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' if quotes => result.push_str("&quot;"),
+            '\'' if quotes => result.push_str("&#39;"),
+            _ => result.push(ch),
+        }
+    }
+    Cow::Owned(result)
+}
+
+/// Escapes a value for use as element content
+///
+/// # Examples
+///
+/// ```
+/// use umd::html_builder::text_escape;
+///
+/// assert_eq!(text_escape("<script>"), "&lt;script&gt;");
+/// assert_eq!(text_escape("A & B &amp; C"), "A &amp; B &amp; C");
+/// ```
+pub fn text_escape(value: &str) -> Cow<'_, str> {
+    escape(value, false)
+}
+
+/// Escapes a value for use inside a double-quoted attribute
+///
+/// # Examples
+///
+/// ```
+/// use umd::html_builder::attr_escape;
+///
+/// assert_eq!(
+///     attr_escape(r#"2024" onclick="alert(1)"#),
+///     "2024&quot; onclick=&quot;alert(1)"
+/// );
+/// ```
+pub fn attr_escape(value: &str) -> Cow<'_, str> {
+    escape(value, true)
+}
+
+/// Prepares a URL for an `href` / `src` attribute
+///
+/// Dangerous schemes are replaced by `#blocked-url` (see
+/// [`sanitize_url`]) before attribute escaping.
+///
+/// # Examples
+///
+/// ```
+/// use umd::html_builder::url_attr;
+///
+/// assert_eq!(url_attr("javascript:alert(1)"), "#blocked-url");
+/// assert_eq!(url_attr("/search?q=\"x\""), "/search?q=&quot;x&quot;");
+/// ```
+pub fn url_attr(value: &str) -> String {
+    attr_escape(&sanitize_url(value)).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_borrowed_when_clean() {
+        assert!(matches!(attr_escape("plain text"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_entities_preserved() {
+        assert_eq!(
+            attr_escape("&lt;b&gt; &#39; &#x27;"),
+            "&lt;b&gt; &#39; &#x27;"
+        );
+        assert_eq!(text_escape("&unknown"), "&amp;unknown");
+    }
+
+    #[test]
+    fn test_quotes_only_escaped_in_attributes() {
+        assert_eq!(text_escape(r#"say "hi" 'x'"#), r#"say "hi" 'x'"#);
+        assert_eq!(attr_escape("'x'"), "&#39;x&#39;");
+    }
+}
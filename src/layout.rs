@@ -0,0 +1,214 @@
+//! Page layouts with slots
+//!
+//! Hosts register page templates by name in [`Layouts`]; a document picks
+//! one with `layout: article` in its frontmatter (a layout registered as
+//! `default` is used otherwise) and [`Layouts::render`] fills the slots of
+//! the template with the rendered page:
+//!
+//! | Slot | Content |
+//! |------|---------|
+//! | `{{slot:content}}` | Body HTML |
+//! | `{{slot:toc}}` | Table of contents (as `@toc()`; empty without headings) |
+//! | `{{slot:footnotes}}` | Footnotes section (empty without footnotes) |
+//! | `{{slot:title}}` | Frontmatter `title`, else the first heading (escaped text) |
+//!
+//! Templates are trusted host HTML and are not sanitized. Slots are filled
+//! in one pass, so slot-like text in the document is never expanded. An
+//! unknown layout name or slot is reported as a diagnostic.
+
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use crate::diagnostics::Diagnostic;
+use crate::extensions::toc::{apply_toc, render_toc_placeholder};
+use crate::html_builder::text_escape;
+use crate::parser::ParserOptions;
+use crate::{ParseResult, parse_with_frontmatter_opts};
+
+/// Slot placeholder in a template
+static SLOT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{\{\s*slot:([A-Za-z][\w-]*)\s*\}\}").unwrap());
+
+/// Name of the layout used when the frontmatter names none
+pub const DEFAULT_LAYOUT: &str = "default";
+
+/// Registered page templates
+#[derive(Debug, Clone, Default)]
+pub struct Layouts {
+    templates: HashMap<String, String>,
+}
+
+impl Layouts {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register (or replace) the template of layout `name`
+    pub fn register(&mut self, name: &str, template: &str) {
+        self.templates
+            .insert(name.to_string(), template.to_string());
+    }
+
+    /// Template of layout `name`
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.templates.get(name).map(String::as_str)
+    }
+
+    /// Render a document into its layout
+    ///
+    /// `html` of the result is the filled template; the footnotes are moved
+    /// into it when the template has a `footnotes` slot. Without a layout
+    /// the result is the same as [`parse_with_frontmatter_opts`].
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The Universal Markdown source text
+    /// * `options` - Parser configuration options
+    ///
+    /// # Returns
+    ///
+    /// ParseResult with the page HTML
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use umd::layout::Layouts;
+    /// use umd::parser::ParserOptions;
+    ///
+    /// let mut layouts = Layouts::new();
+    /// layouts.register(
+    ///     "article",
+    ///     "<article><h1>{{slot:title}}</h1>{{slot:toc}}{{slot:content}}</article>",
+    /// );
+    /// let input = "---\nlayout: article\ntitle: Notes\n---\n\n## Setup\n\nText.";
+    /// let result = layouts.render(input, &ParserOptions::default());
+    /// assert!(result.html.starts_with("<article><h1>Notes</h1><nav class=\"umd-toc\""));
+    /// assert!(result.html.ends_with("<p>Text.</p></article>"));
+    /// ```
+    pub fn render(&self, input: &str, options: &ParserOptions) -> ParseResult {
+        let mut result = parse_with_frontmatter_opts(input, options);
+        let name = result
+            .frontmatter
+            .as_ref()
+            .and_then(|frontmatter| frontmatter.get_scalar("layout"));
+        let template = match &name {
+            Some(name) => match self.get(name) {
+                Some(template) => template,
+                None => {
+                    result.diagnostics.push(Diagnostic::warning(
+                        "unknown-layout",
+                        format!("Layout `{}` is not registered", name),
+                    ));
+                    return result;
+                }
+            },
+            None => match self.get(DEFAULT_LAYOUT) {
+                Some(template) => template,
+                None => return result,
+            },
+        };
+
+        let footnotes = if SLOT
+            .captures_iter(template)
+            .any(|caps| &caps[1] == "footnotes")
+        {
+            result.footnotes.take().unwrap_or_default()
+        } else {
+            String::new()
+        };
+        let mut unknown = Vec::new();
+        let html = SLOT.replace_all(template, |caps: &Captures| match &caps[1] {
+            "content" => result.html.clone(),
+            "toc" => table_of_contents(&result.html, options),
+            "footnotes" => footnotes.clone(),
+            "title" => text_escape(&page_title(&result)).into_owned(),
+            slot => {
+                unknown.push(slot.to_string());
+                String::new()
+            }
+        });
+        result.html = html.into_owned();
+        for slot in unknown {
+            result.diagnostics.push(Diagnostic::warning(
+                "unknown-slot",
+                format!("Layout slot `{}` is not supported", slot),
+            ));
+        }
+        result
+    }
+}
+
+/// Table of contents of rendered body HTML
+fn table_of_contents(html: &str, options: &ParserOptions) -> String {
+    let placeholder = render_toc_placeholder("");
+    let filled = apply_toc(&format!("{}\n{}", placeholder, html), &options.locale).into_owned();
+    filled
+        .strip_suffix(html)
+        .map_or(String::new(), |toc| toc.trim_end().to_string())
+}
+
+/// Frontmatter title, else the title of the first heading
+fn page_title(result: &ParseResult) -> String {
+    result
+        .frontmatter
+        .as_ref()
+        .and_then(|frontmatter| frontmatter.get_scalar("title"))
+        .or_else(|| {
+            result
+                .sections
+                .iter()
+                .find(|section| section.level > 0 && !section.title.is_empty())
+                .map(|section| section.title.clone())
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_layout_and_footnotes() {
+        let mut layouts = Layouts::new();
+        let options = ParserOptions::default();
+        let input = "# <Title>\n\nText[^1] {{slot:title}}\n\n[^1]: Note";
+        assert_eq!(
+            layouts.render(input, &options).html,
+            parse_with_frontmatter_opts(input, &options).html
+        );
+
+        layouts.register(
+            DEFAULT_LAYOUT,
+            "<main>{{slot:content}}</main>\n<aside>{{ slot:footnotes }}</aside>\
+             <title>{{slot:title}}</title>",
+        );
+        let result = layouts.render(input, &options);
+        assert!(result.footnotes.is_none());
+        assert!(
+            result.html.contains("{{slot:title}}</p>"),
+            "{}",
+            result.html
+        );
+        assert!(result.html.contains("<aside><section"), "{}", result.html);
+        assert!(result.html.ends_with("<title>&lt;Title&gt;</title>"));
+        assert!(result.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_unknown_layout_and_slot() {
+        let mut layouts = Layouts::new();
+        layouts.register("page", "{{slot:sidebar}}{{slot:toc}}{{slot:content}}");
+        let options = ParserOptions::default();
+
+        let result = layouts.render("---\nlayout: page\n---\n\nText", &options);
+        assert_eq!(result.html, "<p>Text</p>");
+        assert_eq!(result.diagnostics[0].code, "unknown-slot");
+
+        let result = layouts.render("---\nlayout: missing\n---\n\nText", &options);
+        assert_eq!(result.html, "<p>Text</p>");
+        assert_eq!(result.diagnostics[0].code, "unknown-layout");
+    }
+}
@@ -40,25 +40,83 @@
 //! const html = parse('# Hello World');
 //! ```
 
-use serde::Deserialize;
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
+use profiling::stage;
+
+pub mod cache;
+pub mod compliance;
+pub mod diagnostics;
+pub mod diff;
 pub mod extensions;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod format;
 pub mod frontmatter;
+pub mod html_builder;
+pub mod layout;
+pub mod lint;
+pub mod outline;
 pub mod parser;
+pub mod profiling;
 pub mod sanitizer;
+pub mod search_index;
+pub mod sections;
+pub mod site;
+pub mod standalone;
+
+pub use diagnostics::{Diagnostic, Severity};
+pub use diff::{BlockChange, BlockDiff};
+pub use format::format;
+pub use lint::{LintIssue, LintOptions};
+pub use outline::{DocumentOutline, outline};
+pub use profiling::StageTiming;
+pub use search_index::{IndexToken, IndexTokenKind};
+pub use sections::Section;
+pub use standalone::{StandaloneOptions, render_standalone};
 
 /// Parse result with optional frontmatter and footnotes
 #[derive(Debug, Clone)]
 pub struct ParseResult {
-    /// The rendered HTML content (body only, footnotes are separate)
+    /// The rendered HTML content (body only, footnotes are separate unless
+    /// placed inline with [`parser::FootnotePlacement::Inline`])
     pub html: String,
     /// Optional frontmatter data
     pub frontmatter: Option<frontmatter::Frontmatter>,
     /// Footnotes HTML (if any footnotes are present)
     pub footnotes: Option<String>,
+    /// Problems found while parsing (duplicate IDs, ...)
+    pub diagnostics: Vec<Diagnostic>,
+    /// Palette stylesheet for `ColorMode::Adaptive` (`None` when no HEX
+    /// color was used or the mode is `Fixed`)
+    pub color_palette: Option<String>,
+    /// Cover image: the frontmatter `cover:` value, else the first image of
+    /// the body
+    pub first_image: Option<extensions::media::MediaRef>,
+    /// Body HTML split at top-level headings, sharing the rendered anchor IDs
+    pub sections: Vec<Section>,
+}
+
+/// Error returned by [`parse_fallible`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// An internal error aborted parsing; the message describes the panic
+    Internal(String),
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::Internal(message) => write!(f, "internal parser error: {}", message),
+        }
+    }
 }
 
+impl std::error::Error for ParseError {}
+
 /// Parse Universal Markdown and convert to HTML
 ///
 /// This function extracts frontmatter (if present) and parses the content.
@@ -99,6 +157,122 @@ struct WasmIconsOptions {
     audio: Option<String>,
     download: Option<String>,
     color_swatch: Option<String>,
+    external_link: Option<String>,
+    alert_note: Option<String>,
+    alert_tip: Option<String>,
+    alert_important: Option<String>,
+    alert_warning: Option<String>,
+    alert_caution: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WasmImageOptions {
+    loading: Option<String>,
+    decoding: Option<String>,
+    fetch_priority: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WasmMediaOptions {
+    types: Option<std::collections::HashMap<String, String>>,
+    mime_types: Option<std::collections::HashMap<String, String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WasmAutolinkOptions {
+    bare_domains: Option<bool>,
+    allow: Option<Vec<String>>,
+    deny: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WasmExternalLinkOptions {
+    icon: Option<bool>,
+    rel: Option<String>,
+    target: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WasmAriaOptions {
+    heading_anchor_label: Option<String>,
+    footnote_backref_label: Option<String>,
+    alert_live: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WasmPluginParam {
+    name: String,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WasmPluginSignature {
+    name: String,
+    params: Option<Vec<WasmPluginParam>>,
+    min_args: Option<usize>,
+    max_args: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WasmHtmlAllowList {
+    tags: Option<Vec<String>>,
+    attributes: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WasmComrakExtensions {
+    header_ids: Option<String>,
+    tasklist: Option<bool>,
+    single_tilde_strikethrough: Option<bool>,
+    subscript: Option<bool>,
+    superscript: Option<bool>,
+    description_lists: Option<bool>,
+    multiline_block_quotes: Option<bool>,
+    underline: Option<bool>,
+    wikilinks: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WasmAnchorOptions {
+    style: Option<String>,
+    symbol: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WasmAlertOptions {
+    heading: Option<bool>,
+    icons: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WasmFootnoteOptions {
+    heading: Option<String>,
+    heading_level: Option<u8>,
+    start: Option<usize>,
+    backref_symbol: Option<String>,
+    class: Option<String>,
+    tooltips: Option<bool>,
+    placement: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WasmClassOptions {
+    prefix: Option<String>,
+    map: Option<std::collections::HashMap<String, String>>,
 }
 
 #[derive(Debug, Default, Deserialize)]
@@ -107,61 +281,597 @@ struct WasmParseOptions {
     gfm_extensions: Option<bool>,
     umd_extensions: Option<bool>,
     max_heading_level: Option<u8>,
+    heading_offset: Option<u8>,
+    number_headings: Option<bool>,
+    sortable_tables: Option<bool>,
+    caption_side: Option<String>,
+    responsive_tables: Option<String>,
+    video_embeds: Option<bool>,
+    map_embed: Option<String>,
+    print_mode: Option<bool>,
     max_inline_nesting: Option<u8>,
     base_url: Option<String>,
     allow_fragment_extension_hint: Option<bool>,
     icons: Option<WasmIconsOptions>,
+    images: Option<WasmImageOptions>,
+    media: Option<WasmMediaOptions>,
+    autolink: Option<WasmAutolinkOptions>,
+    attachment_url: Option<String>,
+    site_origin: Option<String>,
+    external_links: Option<WasmExternalLinkOptions>,
+    mention_base: Option<String>,
+    tag_base: Option<String>,
+    aria: Option<WasmAriaOptions>,
+    color_mode: Option<String>,
+    framework: Option<String>,
+    classes: Option<WasmClassOptions>,
+    font_sizes: Option<std::collections::HashMap<String, String>>,
+    dialect: Option<String>,
+    emphasis_tags: Option<String>,
+    ids: Option<String>,
+    footnotes: Option<WasmFootnoteOptions>,
+    locale: Option<String>,
+    alerts: Option<WasmAlertOptions>,
+    anchors: Option<WasmAnchorOptions>,
+    allowed_plugins: Option<Vec<String>>,
+    denied_plugins: Option<Vec<String>>,
+    plugin_signatures: Option<Vec<WasmPluginSignature>>,
+    plugin_content: Option<String>,
+    trusted_html: Option<WasmHtmlAllowList>,
+    comrak: Option<WasmComrakExtensions>,
+    output_format: Option<String>,
+    html_syntax: Option<String>,
+    profile: Option<String>,
+    line_anchors: Option<usize>,
 }
 
-fn parse_with_options_json(input: &str, options_json: Option<&str>) -> String {
+/// Parser options from JSON with the keys of the WASM API
+///
+/// Keys are camelCase (`baseUrl`, `outputFormat`, ...) as documented in
+/// `docs/runtime-features.md`. Missing keys keep their defaults; unknown
+/// keys and unrecognized values are ignored.
+///
+/// # Errors
+///
+/// Returns the JSON error when `json` is not an object of the expected shape
+///
+/// # Examples
+///
+/// ```
+/// use umd::options_from_json;
+///
+/// let options = options_from_json(r#"{"baseUrl":"/app","printMode":true}"#).unwrap();
+/// assert_eq!(options.base_url.as_deref(), Some("/app"));
+/// assert!(options.print_mode);
+/// let options = options_from_json(r#"{"images":{"loading":"eager","fetchPriority":"high"}}"#).unwrap();
+/// assert_eq!(options.images.loading, umd::parser::ImageLoading::Eager);
+/// assert!(options_from_json("[1]").is_err());
+/// ```
+pub fn options_from_json(json: &str) -> Result<parser::ParserOptions, serde_json::Error> {
+    let raw = serde_json::from_str::<WasmParseOptions>(json)?;
     let mut options = parser::ParserOptions::default();
-
-    if let Some(raw_json) = options_json {
-        let trimmed = raw_json.trim();
-        if !trimmed.is_empty() {
-            if let Ok(raw) = serde_json::from_str::<WasmParseOptions>(trimmed) {
-                if let Some(value) = raw.gfm_extensions {
-                    options.gfm_extensions = value;
-                }
-                if let Some(value) = raw.umd_extensions {
-                    options.umd_extensions = value;
-                }
-                if let Some(value) = raw.max_heading_level {
-                    options.max_heading_level = value;
-                }
-                if let Some(value) = raw.max_inline_nesting {
-                    options.max_inline_nesting = Some(value);
-                }
-                if let Some(value) = raw.base_url {
-                    options.base_url = Some(value);
-                }
-                if let Some(value) = raw.allow_fragment_extension_hint {
-                    options.allow_fragment_extension_hint = value;
-                }
-                if let Some(icons) = raw.icons {
-                    if let Some(value) = icons.video {
-                        options.icons.video = value;
-                    }
-                    if let Some(value) = icons.audio {
-                        options.icons.audio = value;
-                    }
-                    if let Some(value) = icons.download {
-                        options.icons.download = value;
-                    }
-                    if let Some(value) = icons.color_swatch {
-                        options.icons.color_swatch = value;
-                    }
-                }
+    if let Some(value) = raw.gfm_extensions {
+        options.gfm_extensions = value;
+    }
+    if let Some(value) = raw.umd_extensions {
+        options.umd_extensions = value;
+    }
+    if let Some(value) = raw.max_heading_level {
+        options.max_heading_level = value;
+    }
+    if let Some(value) = raw.heading_offset {
+        options.heading_offset = value;
+    }
+    if let Some(value) = raw.number_headings {
+        options.number_headings = value;
+    }
+    if let Some(value) = raw.sortable_tables {
+        options.sortable_tables = value;
+    }
+    if let Some(value) = raw.video_embeds {
+        options.video_embeds = value;
+    }
+    if let Some(value) = raw.print_mode {
+        options.print_mode = value;
+    }
+    match raw.map_embed.as_deref() {
+        Some("off") => options.map_embed = parser::MapEmbed::Off,
+        Some("osm") => options.map_embed = parser::MapEmbed::OpenStreetMap,
+        Some(template) if template.starts_with("https://") => {
+            options.map_embed = parser::MapEmbed::StaticImage(template.to_string())
+        }
+        _ => {}
+    }
+    match raw.caption_side.as_deref() {
+        Some("top") => options.caption_side = parser::CaptionSide::Top,
+        Some("bottom") => options.caption_side = parser::CaptionSide::Bottom,
+        _ => {}
+    }
+    if let Some(value) = raw.responsive_tables.as_deref() {
+        options.responsive_tables = match value {
+            "always" => parser::ResponsiveTables::Always,
+            "sm" => parser::ResponsiveTables::Sm,
+            "md" => parser::ResponsiveTables::Md,
+            "lg" => parser::ResponsiveTables::Lg,
+            "xl" => parser::ResponsiveTables::Xl,
+            "xxl" => parser::ResponsiveTables::Xxl,
+            _ => parser::ResponsiveTables::Off,
+        };
+    }
+    if let Some(value) = raw.max_inline_nesting {
+        options.max_inline_nesting = Some(value);
+    }
+    if let Some(value) = raw.line_anchors {
+        options.line_anchors = (value > 0).then_some(value);
+    }
+    if let Some(value) = raw.base_url {
+        options.base_url = Some(value);
+    }
+    if let Some(value) = raw.allow_fragment_extension_hint {
+        options.allow_fragment_extension_hint = value;
+    }
+    if let Some(icons) = raw.icons {
+        if let Some(value) = icons.video {
+            options.icons.video = value;
+        }
+        if let Some(value) = icons.audio {
+            options.icons.audio = value;
+        }
+        if let Some(value) = icons.download {
+            options.icons.download = value;
+        }
+        if let Some(value) = icons.color_swatch {
+            options.icons.color_swatch = value;
+        }
+        if let Some(value) = icons.external_link {
+            options.icons.external_link = value;
+        }
+        if let Some(value) = icons.alert_note {
+            options.icons.alert_note = value;
+        }
+        if let Some(value) = icons.alert_tip {
+            options.icons.alert_tip = value;
+        }
+        if let Some(value) = icons.alert_important {
+            options.icons.alert_important = value;
+        }
+        if let Some(value) = icons.alert_warning {
+            options.icons.alert_warning = value;
+        }
+        if let Some(value) = icons.alert_caution {
+            options.icons.alert_caution = value;
+        }
+    }
+    if let Some(images) = raw.images {
+        match images.loading.as_deref() {
+            Some("lazy") => options.images.loading = parser::ImageLoading::Lazy,
+            Some("eager") => options.images.loading = parser::ImageLoading::Eager,
+            _ => {}
+        }
+        match images.decoding.as_deref() {
+            Some("async") => options.images.decoding = Some(parser::ImageDecoding::Async),
+            Some("sync") => options.images.decoding = Some(parser::ImageDecoding::Sync),
+            Some("auto") => options.images.decoding = Some(parser::ImageDecoding::Auto),
+            _ => {}
+        }
+        match images.fetch_priority.as_deref() {
+            Some("high") => options.images.fetch_priority = Some(parser::FetchPriority::High),
+            Some("low") => options.images.fetch_priority = Some(parser::FetchPriority::Low),
+            Some("auto") => options.images.fetch_priority = Some(parser::FetchPriority::Auto),
+            _ => {}
+        }
+    }
+    if let Some(media) = raw.media {
+        // Extensions may be given as `.heic` or `HEIC`
+        let extension = |ext: &str| ext.trim_start_matches('.').to_lowercase();
+        for (ext, media_type) in media.types.unwrap_or_default() {
+            let media_type = match media_type.as_str() {
+                "video" => extensions::media::MediaType::Video,
+                "audio" => extensions::media::MediaType::Audio,
+                "image" => extensions::media::MediaType::Image,
+                "downloadable" => extensions::media::MediaType::Downloadable,
+                _ => continue,
+            };
+            options.media.types.insert(extension(&ext), media_type);
+        }
+        for (ext, mime_type) in media.mime_types.unwrap_or_default() {
+            options.media.mime_types.insert(extension(&ext), mime_type);
+        }
+    }
+    if let Some(autolink) = raw.autolink {
+        if let Some(value) = autolink.bare_domains {
+            options.autolink.bare_domains = value;
+        }
+        if let Some(value) = autolink.allow {
+            options.autolink.allow = value;
+        }
+        if let Some(value) = autolink.deny {
+            options.autolink.deny = value;
+        }
+    }
+    if let Some(value) = raw.attachment_url {
+        options.attachment_url = Some(value);
+    }
+    if let Some(value) = raw.site_origin {
+        options.site_origin = Some(value);
+    }
+    if let Some(external_links) = raw.external_links {
+        let mut value = parser::ExternalLinkOptions::default();
+        if let Some(icon) = external_links.icon {
+            value.icon = icon;
+        }
+        // An empty string omits the attribute
+        if let Some(rel) = external_links.rel {
+            value.rel = (!rel.is_empty()).then_some(rel);
+        }
+        if let Some(target) = external_links.target {
+            value.target = (!target.is_empty()).then_some(target);
+        }
+        options.external_links = Some(value);
+    }
+    if let Some(value) = raw.mention_base {
+        options.mention_base = Some(value);
+    }
+    if let Some(value) = raw.tag_base {
+        options.tag_base = Some(value);
+    }
+    // Before `aria`, which may override the localized labels
+    if let Some(tag) = raw.locale.as_deref() {
+        if let Some(locale) = parser::Locale::from_tag(tag) {
+            options.locale = locale;
+        }
+        if tag.to_ascii_lowercase().starts_with("ja") {
+            options.aria = parser::AriaOptions::ja();
+        }
+    }
+    if let Some(aria) = raw.aria {
+        // An empty string disables the corresponding attribute
+        if let Some(value) = aria.heading_anchor_label {
+            options.aria.heading_anchor_label = Some(value).filter(|v| !v.is_empty());
+        }
+        if let Some(value) = aria.footnote_backref_label {
+            options.aria.footnote_backref_label = Some(value).filter(|v| !v.is_empty());
+        }
+        if let Some(value) = aria.alert_live {
+            options.aria.alert_live = Some(value).filter(|v| !v.is_empty());
+        }
+    }
+    match raw.color_mode.as_deref() {
+        Some("fixed") => options.color_mode = parser::ColorMode::Fixed,
+        Some("adaptive") => options.color_mode = parser::ColorMode::Adaptive,
+        _ => {}
+    }
+    match raw.dialect.as_deref() {
+        Some("commonmark") => options.dialect = parser::Dialect::CommonMark,
+        Some("gfm") => options.dialect = parser::Dialect::Gfm,
+        Some("umd") => options.dialect = parser::Dialect::Umd,
+        Some("lukiwiki") => options.dialect = parser::Dialect::LukiWiki,
+        _ => {}
+    }
+    match raw.emphasis_tags.as_deref() {
+        Some("presentational") => options.emphasis_tags = parser::EmphasisTags::Presentational,
+        Some("semantic") => options.emphasis_tags = parser::EmphasisTags::Semantic,
+        _ => {}
+    }
+    if let Some(footnotes) = raw.footnotes {
+        if let Some(value) = footnotes.heading {
+            options.footnotes.heading = Some(value);
+        }
+        if let Some(value) = footnotes.heading_level {
+            options.footnotes.heading_level = value;
+        }
+        if let Some(value) = footnotes.start {
+            options.footnotes.start = value;
+        }
+        if let Some(value) = footnotes.backref_symbol {
+            options.footnotes.backref_symbol = value;
+        }
+        if let Some(value) = footnotes.class {
+            options.footnotes.class = Some(value);
+        }
+        if let Some(value) = footnotes.tooltips {
+            options.footnotes.tooltips = value;
+        }
+        match footnotes.placement.as_deref() {
+            Some("separate") => options.footnotes.placement = parser::FootnotePlacement::Separate,
+            Some("inline") => options.footnotes.placement = parser::FootnotePlacement::Inline,
+            _ => {}
+        }
+    }
+    if let Some(alerts) = raw.alerts {
+        if let Some(value) = alerts.heading {
+            options.alerts.heading = value;
+        }
+        if let Some(value) = alerts.icons {
+            options.alerts.icons = value;
+        }
+    }
+    if let Some(anchors) = raw.anchors {
+        match anchors.style.as_deref() {
+            Some("empty") => options.anchors.style = parser::AnchorStyle::Empty,
+            Some("permalink") => options.anchors.style = parser::AnchorStyle::Permalink,
+            Some("wrap") => options.anchors.style = parser::AnchorStyle::Wrap,
+            Some("id") => options.anchors.style = parser::AnchorStyle::IdOnly,
+            Some("none") => options.anchors.style = parser::AnchorStyle::None,
+            _ => {}
+        }
+        if let Some(value) = anchors.symbol {
+            options.anchors.symbol = value;
+        }
+    }
+    if let Some(value) = raw.allowed_plugins {
+        options.allowed_plugins = Some(value);
+    }
+    if let Some(value) = raw.denied_plugins {
+        options.denied_plugins = value;
+    }
+    if let Some(signatures) = raw.plugin_signatures {
+        options.plugin_signatures = signatures
+            .into_iter()
+            .map(|signature| {
+                let params: Vec<(&str, parser::PluginArgType)> = signature
+                    .params
+                    .iter()
+                    .flatten()
+                    .map(|param| {
+                        let kind = match param.kind.as_deref() {
+                            Some("integer") => parser::PluginArgType::Integer,
+                            Some("number") => parser::PluginArgType::Number,
+                            Some("url") => parser::PluginArgType::Url,
+                            _ => parser::PluginArgType::Text,
+                        };
+                        (param.name.as_str(), kind)
+                    })
+                    .collect();
+                let parsed = parser::PluginSignature::new(&signature.name, &params);
+                let min_args = signature.min_args.unwrap_or(parsed.min_args);
+                let max_args = signature.max_args.or(parsed.max_args);
+                parsed.with_args(min_args, max_args)
+            })
+            .collect();
+    }
+    match raw.plugin_content.as_deref() {
+        Some("escaped") => options.plugin_content = parser::PluginContent::Escaped,
+        Some("encoded") => options.plugin_content = parser::PluginContent::Encoded,
+        _ => {}
+    }
+    if let Some(trusted) = raw.trusted_html {
+        let defaults = parser::HtmlAllowList::default();
+        options.trust_level = parser::TrustLevel::Trusted(parser::HtmlAllowList {
+            tags: trusted.tags.unwrap_or(defaults.tags),
+            attributes: trusted.attributes.unwrap_or(defaults.attributes),
+        });
+    }
+    if let Some(comrak) = raw.comrak {
+        if let Some(value) = comrak.header_ids {
+            options.comrak.header_ids = Some(value);
+        }
+        if let Some(value) = comrak.tasklist {
+            options.comrak.tasklist = value;
+        }
+        if let Some(value) = comrak.single_tilde_strikethrough {
+            options.comrak.single_tilde_strikethrough = value;
+        }
+        if let Some(value) = comrak.subscript {
+            options.comrak.subscript = value;
+        }
+        if let Some(value) = comrak.superscript {
+            options.comrak.superscript = value;
+        }
+        if let Some(value) = comrak.description_lists {
+            options.comrak.description_lists = value;
+        }
+        if let Some(value) = comrak.multiline_block_quotes {
+            options.comrak.multiline_block_quotes = value;
+        }
+        if let Some(value) = comrak.underline {
+            options.comrak.underline = value;
+        }
+        match comrak.wikilinks.as_deref() {
+            Some("off") => options.comrak.wikilinks = parser::WikiLinks::Off,
+            Some("title-after-pipe") => {
+                options.comrak.wikilinks = parser::WikiLinks::TitleAfterPipe
             }
+            Some("title-before-pipe") => {
+                options.comrak.wikilinks = parser::WikiLinks::TitleBeforePipe
+            }
+            _ => {}
+        }
+    }
+    match raw.ids.as_deref() {
+        Some("random") => options.ids = parser::IdStrategy::Random,
+        Some("hash") => options.ids = parser::IdStrategy::ContentHash,
+        _ => {}
+    }
+    match raw.framework.as_deref() {
+        Some("bootstrap5") => options.framework = parser::Framework::Bootstrap5,
+        Some("semantic") => options.framework = parser::Framework::Semantic,
+        _ => {}
+    }
+    match raw.output_format.as_deref() {
+        Some("compact") => options.output_format = parser::OutputFormat::Compact,
+        Some("pretty") => options.output_format = parser::OutputFormat::Pretty,
+        Some("minified") => options.output_format = parser::OutputFormat::Minified,
+        _ => {}
+    }
+    match raw.html_syntax.as_deref() {
+        Some("html5") => options.html_syntax = Some(parser::HtmlSyntax::Html5),
+        Some("xhtml") => options.html_syntax = Some(parser::HtmlSyntax::Xhtml),
+        _ => {}
+    }
+    match raw.profile.as_deref() {
+        Some("web") => options.profile = parser::Profile::Web,
+        Some("epub") => options.profile = parser::Profile::Epub,
+        Some("email-safe") => options.profile = parser::Profile::EmailSafe,
+        _ => {}
+    }
+    if let Some(classes) = raw.classes {
+        if let Some(value) = classes.prefix {
+            options.classes.prefix = Some(value);
         }
+        if let Some(value) = classes.map {
+            options.classes.map = value;
+        }
+    }
+    if let Some(value) = raw.font_sizes {
+        options.font_sizes = value;
     }
+    Ok(options)
+}
 
-    let result = parse_with_frontmatter_opts(input, &options);
-    if let Some(footnotes) = result.footnotes {
+/// Options of the WASM API; missing, empty or invalid JSON gives the defaults
+fn wasm_options(options_json: Option<&str>) -> parser::ParserOptions {
+    options_json
+        .map(str::trim)
+        .filter(|json| !json.is_empty())
+        .and_then(|json| options_from_json(json).ok())
+        .unwrap_or_default()
+}
+
+/// HTML returned by the WASM API: body, footnotes and the palette
+fn wasm_html(result: ParseResult) -> String {
+    let html = if let Some(footnotes) = result.footnotes {
         format!("{}\n{}", result.html, footnotes)
     } else {
         result.html
+    };
+    // The palette is inlined since the WASM API only returns HTML
+    match result.color_palette {
+        Some(css) => format!(
+            "<style class=\"umd-color-palette\">\n{}</style>\n{}",
+            css, html
+        ),
+        None => html,
+    }
+}
+
+pub(crate) fn parse_with_options_json(input: &str, options_json: Option<&str>) -> String {
+    wasm_html(parse_with_frontmatter_opts(
+        input,
+        &wasm_options(options_json),
+    ))
+}
+
+/// `parseWithLayout` with the layouts as JSON (`{"name": "template"}`)
+pub(crate) fn parse_with_layout_json(
+    input: &str,
+    layouts_json: Option<&str>,
+    options_json: Option<&str>,
+) -> String {
+    let mut layouts = layout::Layouts::new();
+    let templates = layouts_json
+        .and_then(|json| {
+            serde_json::from_str::<std::collections::HashMap<String, String>>(json).ok()
+        })
+        .unwrap_or_default();
+    for (name, template) in &templates {
+        layouts.register(name, template);
+    }
+    wasm_html(layouts.render(input, &wasm_options(options_json)))
+}
+
+/// Structured result of `parseMarkdownFull`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WasmFullResult {
+    html: String,
+    /// Top-level frontmatter scalars
+    frontmatter: Option<serde_json::Map<String, serde_json::Value>>,
+    footnotes: Option<String>,
+    toc: Vec<WasmTocEntry>,
+    diagnostics: Vec<WasmDiagnostic>,
+    stats: WasmStats,
+    color_palette: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct WasmTocEntry {
+    level: u8,
+    title: String,
+    id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct WasmDiagnostic {
+    severity: String,
+    code: &'static str,
+    message: String,
+    line: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct WasmStats {
+    /// Words of the rendered text (plugin output excluded)
+    words: usize,
+    /// Non-whitespace grapheme clusters of the rendered text
+    characters: usize,
+    headings: usize,
+    footnotes: usize,
+}
+
+pub(crate) fn parse_full_json(input: &str, options_json: Option<&str>) -> String {
+    use unicode_segmentation::UnicodeSegmentation;
+
+    let result = parse_with_frontmatter_opts(input, &wasm_options(options_json));
+
+    let toc: Vec<_> = result
+        .sections
+        .iter()
+        .filter(|section| section.level > 0)
+        .map(|section| WasmTocEntry {
+            level: section.level,
+            title: section.title.clone(),
+            id: section.id.clone(),
+        })
+        .collect();
+    let mut html = result.html.clone();
+    if let Some(footnotes) = &result.footnotes {
+        html.push('\n');
+        html.push_str(footnotes);
+    }
+    let (mut words, mut characters) = (0, 0);
+    for token in search_index::tokens_from_html(&html) {
+        words += token.text.unicode_words().count();
+        characters += token
+            .text
+            .graphemes(true)
+            .filter(|grapheme| !grapheme.trim().is_empty())
+            .count();
     }
+    let stats = WasmStats {
+        words,
+        characters,
+        headings: toc.len(),
+        footnotes: result.footnotes.as_deref().map_or(0, |section| {
+            extensions::footnotes::footnote_items(section).len()
+        }),
+    };
+
+    let full = WasmFullResult {
+        frontmatter: result.frontmatter.map(|frontmatter| {
+            frontmatter
+                .scalars()
+                .into_iter()
+                .map(|(key, value)| (key, serde_json::Value::String(value)))
+                .collect()
+        }),
+        footnotes: result.footnotes,
+        toc,
+        diagnostics: result
+            .diagnostics
+            .into_iter()
+            .map(|diagnostic| WasmDiagnostic {
+                severity: diagnostic.severity.to_string(),
+                code: diagnostic.code,
+                message: diagnostic.message,
+                line: diagnostic.line,
+            })
+            .collect(),
+        stats,
+        color_palette: result.color_palette,
+        html: result.html,
+    };
+    serde_json::to_string(&full).unwrap_or_else(|_| "null".to_string())
 }
 
 /// Parse Universal Markdown and return HTML with frontmatter
@@ -195,6 +905,8 @@ pub fn parse_with_frontmatter(input: &str) -> ParseResult {
 ///
 /// This function extracts frontmatter and returns it separately from the HTML content,
 /// with support for custom parser options (e.g., base_url for URL resolution).
+/// When `options.cache` is set, a cached result for the same input and
+/// options is returned without parsing.
 ///
 /// # Arguments
 ///
@@ -217,78 +929,514 @@ pub fn parse_with_frontmatter(input: &str) -> ParseResult {
 /// assert!(result.html.contains(r#"href="/app/docs""#));
 /// ```
 pub fn parse_with_frontmatter_opts(input: &str, options: &parser::ParserOptions) -> ParseResult {
+    let Some(cache) = &options.cache else {
+        return parse_uncached(input, options);
+    };
+    let key = cache::CacheKey::new(input, options);
+    if let Some(result) = cache.get(&key) {
+        return result;
+    }
+    let result = parse_uncached(input, options);
+    cache.put(key, &result);
+    result
+}
+
+fn parse_uncached(input: &str, options: &parser::ParserOptions) -> ParseResult {
+    let mut result = parse_unmapped(input, options);
+    finish_result(&mut result, options);
+    result
+}
+
+/// Parse Universal Markdown without ever panicking
+///
+/// Same as [`parse_with_frontmatter_opts`], but an internal error is
+/// returned as [`ParseError`] instead of unwinding into the caller. Native
+/// builds catch panics; the WASM build aborts on panic, so embedders should
+/// still report any `ParseError` seen natively for the same input.
+///
+/// # Arguments
+///
+/// * `input` - The Universal Markdown source text
+/// * `options` - Parser configuration options
+///
+/// # Returns
+///
+/// ParseResult, or the internal error that stopped parsing
+///
+/// # Examples
+///
+/// ```
+/// use umd::{parse_fallible, parser::ParserOptions};
+///
+/// let result = parse_fallible("&color(red){x};", &ParserOptions::default()).unwrap();
+/// assert!(result.html.contains("text-red"));
+/// ```
+pub fn parse_fallible(
+    input: &str,
+    options: &parser::ParserOptions,
+) -> Result<ParseResult, ParseError> {
+    // A panic happens before anything is stored in `options.cache`
+    let parse = std::panic::AssertUnwindSafe(|| parse_with_frontmatter_opts(input, options));
+    std::panic::catch_unwind(parse).map_err(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        ParseError::Internal(message)
+    })
+}
+
+/// Parse Universal Markdown and report each finished pipeline stage
+///
+/// Same output as [`parse_with_frontmatter_opts`]. `progress` is called
+/// with the name of every stage as it finishes, in the order of
+/// [`parse_with_timings`], so editors can show progress for large
+/// documents. A result served from `options.cache` reports no stages.
+///
+/// # Examples
+///
+/// ```
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+/// use umd::{parse_with_progress, parser::ParserOptions};
+///
+/// let stages = Rc::new(RefCell::new(Vec::new()));
+/// let seen = Rc::clone(&stages);
+/// let result = parse_with_progress("**bold**", &ParserOptions::default(), move |stage| {
+///     seen.borrow_mut().push(stage)
+/// });
+/// assert!(result.html.contains("<strong>bold</strong>"));
+/// assert_eq!(stages.borrow().last(), Some(&"sections"));
+/// ```
+pub fn parse_with_progress(
+    input: &str,
+    options: &parser::ParserOptions,
+    progress: impl FnMut(&'static str) + 'static,
+) -> ParseResult {
+    profiling::observe(progress, || parse_with_frontmatter_opts(input, options))
+}
+
+/// Parse Universal Markdown and measure each pipeline stage
+///
+/// Same output as [`parse_with_frontmatter_opts`], plus the wall-clock time
+/// of every stage in the order they ran: preprocessing passes, `sanitize`,
+/// `comrak`, each `extensions.*` pass and the final passes. Stages skipped
+/// by the options are not listed. With the `trace` feature the same stages
+/// are also emitted as `tracing` spans.
+///
+/// Not available on WASM, which has no monotonic clock in `std`.
+///
+/// # Examples
+///
+/// ```
+/// use umd::{parse_with_timings, parser::ParserOptions};
+///
+/// let (result, timings) = parse_with_timings("**bold**", &ParserOptions::default());
+/// assert!(result.html.contains("<strong>bold</strong>"));
+/// assert!(timings.iter().any(|timing| timing.stage == "comrak"));
+/// ```
+#[cfg(not(target_arch = "wasm32"))]
+pub fn parse_with_timings(
+    input: &str,
+    options: &parser::ParserOptions,
+) -> (ParseResult, Vec<StageTiming>) {
+    profiling::record(|| parse_with_frontmatter_opts(input, options))
+}
+
+/// Parse without applying [`parser::Framework`] / [`parser::ClassOptions`]
+///
+/// Post-processing that matches built-in class names (wiki links, footnote
+/// renumbering, search indexing) runs on this output.
+pub(crate) fn parse_unmapped(input: &str, options: &parser::ParserOptions) -> ParseResult {
     // Step 0: Extract frontmatter
-    let (frontmatter_data, content) = frontmatter::extract_frontmatter(input);
+    let (frontmatter_data, content) =
+        stage("frontmatter", || frontmatter::extract_frontmatter(input));
+    let frontmatter_data = match (frontmatter_data, &options.default_frontmatter) {
+        (Some(document), Some(defaults)) => Some(document.merge_defaults(defaults)),
+        (None, Some(defaults)) => Some(defaults.clone()),
+        (document, None) => document,
+    };
+
+    if !options.umd_enabled() {
+        return parse_markdown(&content, frontmatter_data, options);
+    }
+    let first_line = input[..input.len() - content.len()].matches('\n').count() + 1;
+
+    // Step 0.4: Marker-like text typed by the author must not reach the
+    // passes that expand markers
+    let content = stage("guard_markers", || {
+        extensions::preprocessor::guard_marker_lookalikes(&content)
+    });
+
+    // Step 0.45: Anchor placeholders before top-level blocks, numbered
+    // after the source lines including the frontmatter
+    let content = match options.line_anchors {
+        Some(interval) => Cow::Owned(stage("line_anchors", || {
+            extensions::line_anchors::insert_line_anchors(&content, interval, first_line)
+                .into_owned()
+        })),
+        None => content,
+    };
+
+    // Step 0.5: Mask code so no preprocessing pass can rewrite it
+    let (content, code_mask) = stage("mask_code", || {
+        extensions::preprocessor::mask_code_sections(&content)
+    });
+
+    // Step 0.6: Block plugin bodies are plugin source; protect them before
+    // any line-based pass (list nesting, tables, decorations) rewrites them
+    let content = stage("plugin_bodies", || {
+        extensions::plugin_markers::protect_block_plugin_bodies(&content)
+    });
 
     // Step 1: Pre-process list items to allow nested block elements
-    let content = extensions::nested_blocks::preprocess_nested_blocks(&content);
+    let content = stage("nested_blocks", || {
+        extensions::nested_blocks::preprocess_nested_blocks(&content)
+    });
 
     // Step 2: Pre-process indeterminate task list markers
-    let content = extensions::preprocessor::preprocess_tasklist_indeterminate(&content);
-
-    // Step 3: Pre-process Discord-style underline (__text__) to prevent CommonMark conversion
-    let content = extensions::preprocessor::preprocess_discord_underline(&content);
+    let content = stage("tasklist", || {
+        extensions::preprocessor::preprocess_tasklist_indeterminate(&content)
+    });
 
     // Step 3.5: Normalize fenced code block filename syntax (```lang:file)
-    let content = extensions::preprocessor::preprocess_code_block_filenames(&content);
+    let content = stage("code_block_filenames", || {
+        extensions::preprocessor::preprocess_code_block_filenames(&content)
+    });
+
+    // Step 3.8: Plain Markdown skips the passes that only handle UMD syntax
+    let umd_syntax = stage("detect", || extensions::detect::has_umd_syntax(&content));
 
     // Step 4: Pre-process to resolve syntax conflicts and extract custom header IDs
-    let (preprocessed, header_map) = extensions::conflict_resolver::preprocess_conflicts(&content);
+    let (preprocessed, header_map) = if umd_syntax {
+        stage("preprocess_conflicts", || {
+            extensions::conflict_resolver::preprocess_conflicts(&content)
+        })
+    } else {
+        // The line-based preprocessing drops the final newline
+        let content = content.strip_suffix('\n').unwrap_or(&content);
+        (
+            content.to_string(),
+            extensions::conflict_resolver::HeaderIdMap::new(),
+        )
+    };
 
     // Step 4.5: Remove ASCII control characters (U+0000-U+001F except TAB/LF/CR, and U+007F)
     // from non-code-block regions. Plugin content is already base64-encoded by Step 4,
     // so plugin markers are inherently safe. Plugin authors are responsible for any
     // further sanitization of their plugin's content.
-    let preprocessed = sanitizer::remove_ascii_control_chars_from_markup(&preprocessed);
+    let preprocessed = stage("control_chars", || {
+        sanitizer::remove_ascii_control_chars_from_markup(&preprocessed)
+    });
 
     // Step 5: Sanitize input
-    let sanitized = sanitizer::sanitize(&preprocessed);
+    let sanitized = stage("sanitize", || match &options.trust_level {
+        parser::TrustLevel::Untrusted => sanitizer::sanitize(&preprocessed),
+        parser::TrustLevel::Trusted(allow_list) => {
+            sanitizer::sanitize_trusted(&preprocessed, allow_list)
+        }
+    });
 
     // Step 6: Parse with comrak-based parser
-    let html = parser::parse_to_html(&sanitized, options);
+    let html = stage("comrak", || parser::parse_to_html(&sanitized, options));
+
+    // Step 7.5: Restore masked code (extensions protect it from here on)
+    let html = stage("unmask_code", || {
+        extensions::preprocessor::unmask_code_sections(&html, &code_mask)
+    });
 
-    // Step 7: Restore Discord-style underline placeholders to <u> tags
-    let html = extensions::preprocessor::postprocess_discord_underline(&html);
+    // Step 7.6: Take trusted raw HTML tags out of reach of the extension passes
+    let (html, trusted_tags) = stage("protect_trusted_html", || {
+        let (protected, tags) = sanitizer::protect_trusted_tags(&html);
+        (protected.into_owned(), tags)
+    });
 
     // Step 8: Apply extended syntax and custom header IDs (includes post-processing)
-    let final_html = extensions::apply_extensions_with_headers(&html, &header_map, options);
+    // (heading IDs are deduplicated and cross-references resolved here)
+    let (mut final_html, diagnostics) =
+        extensions::apply_extensions_with_syntax(&html, &header_map, options, umd_syntax);
+    if let Cow::Owned(anchored) = extensions::line_anchors::render_line_anchors(&final_html) {
+        final_html = anchored;
+    }
+    // Escaped markers, and code carried through plugin content or UMD table
+    // cells, are restored last
+    if let Cow::Owned(restored) = stage("unmask_escaped_markers", || {
+        extensions::preprocessor::unmask_escaped_markers(&final_html, &code_mask)
+    }) {
+        final_html = restored;
+    }
+    if let Cow::Owned(unguarded) = stage("unguard_markers", || {
+        extensions::preprocessor::unguard_marker_lookalikes(&final_html)
+    }) {
+        final_html = unguarded;
+    }
+    if let Cow::Owned(restored) = stage("restore_trusted_html", || {
+        sanitizer::restore_trusted_tags(&final_html, &trusted_tags)
+    }) {
+        final_html = restored;
+    }
+
+    // Step 8.5: Swap colors for palette variables / adaptive classes
+    let (final_html, color_palette) = match options.color_mode {
+        parser::ColorMode::Fixed => (final_html, None),
+        parser::ColorMode::Adaptive => stage("color_mode", || {
+            extensions::color_mode::apply_adaptive_colors(&final_html)
+        }),
+    };
 
     // Step 9: Extract footnotes from HTML
-    let (body_html, footnotes_html) = extract_footnotes(&final_html);
+    let (body_html, footnotes_html) = stage("footnotes", || {
+        let (body_html, footnotes_html) = extensions::footnotes::extract_footnotes(&final_html);
+        extensions::footnotes::apply_footnote_options(body_html, footnotes_html, &options.footnotes)
+    });
+
+    let first_image = stage("first_image", || {
+        extensions::media::first_image(
+            &body_html,
+            frontmatter_data.as_ref(),
+            options.base_url.as_deref(),
+        )
+    });
 
     ParseResult {
         html: body_html,
         frontmatter: frontmatter_data,
         footnotes: footnotes_html,
+        diagnostics,
+        color_palette,
+        first_image,
+        sections: Vec::new(),
     }
 }
 
-/// Extract footnotes section from HTML
-///
-/// Comrak generates footnotes as a `<section class="footnotes">` element.
-/// This function separates the footnotes from the main content.
-///
-/// # Arguments
-///
-/// * `html` - The complete HTML with potential footnotes
-///
-/// # Returns
+/// Render CommonMark / GFM without any UMD preprocessing or extensions
 ///
-/// A tuple of (body HTML, optional footnotes HTML)
-fn extract_footnotes(html: &str) -> (String, Option<String>) {
-    use regex::Regex;
+/// Raw HTML is omitted by comrak instead of being escaped by the sanitizer,
+/// so `<autolinks>` and `>` blockquotes follow the spec.
+fn parse_markdown(
+    content: &str,
+    frontmatter: Option<frontmatter::Frontmatter>,
+    options: &parser::ParserOptions,
+) -> ParseResult {
+    let content = stage("control_chars", || {
+        sanitizer::remove_ascii_control_chars_from_markup(content)
+    });
+    let mut html = stage("comrak", || parser::parse_to_html(&content, options));
+    if let Cow::Owned(adjusted) = extensions::conflict_resolver::adjust_heading_levels(
+        &html,
+        options.heading_offset,
+        options.max_heading_level,
+    ) {
+        html = adjusted;
+    }
+    if let Some(base_url) = &options.base_url {
+        html = extensions::conflict_resolver::apply_base_url_to_links(&html, base_url);
+    }
+    let diagnostics = if options.a11y_audit {
+        extensions::accessibility::audit(&html)
+    } else {
+        Vec::new()
+    };
+    let (body_html, footnotes_html) = stage("footnotes", || {
+        let (body_html, footnotes_html) = extensions::footnotes::extract_footnotes(&html);
+        extensions::footnotes::apply_footnote_options(body_html, footnotes_html, &options.footnotes)
+    });
 
-    // Match the footnotes section generated by comrak
-    let footnote_pattern =
-        Regex::new(r#"(?s)<section class="footnotes"[^>]*>.*?</section>"#).unwrap();
+    let first_image = extensions::media::first_image(
+        &body_html,
+        frontmatter.as_ref(),
+        options.base_url.as_deref(),
+    );
 
-    if let Some(matched) = footnote_pattern.find(html) {
-        let footnotes = matched.as_str().to_string();
-        let body = footnote_pattern.replace(html, "").to_string();
-        (body, Some(footnotes))
-    } else {
-        (html.to_string(), None)
+    ParseResult {
+        html: body_html,
+        frontmatter,
+        footnotes: footnotes_html,
+        diagnostics,
+        color_palette: None,
+        first_image,
+        sections: Vec::new(),
+    }
+}
+
+/// Final steps shared by every public entry point
+///
+/// Applies the class options, the profile, the footnote placement, the HTML
+/// syntax and the output format, then splits the final body into sections.
+pub(crate) fn finish_result(result: &mut ParseResult, options: &parser::ParserOptions) {
+    stage("class_options", || apply_class_options(result, options));
+    if options.profile == parser::Profile::Epub {
+        stage("profile", || {
+            let footnotes = result.footnotes.take();
+            let html = extensions::epub::apply_epub_profile(&result.html, footnotes.as_deref());
+            result.html = html;
+        });
+    }
+    if options.footnotes.placement == parser::FootnotePlacement::Inline
+        && let Some(footnotes) = result.footnotes.take()
+    {
+        result.html = format!("{}\n{}", result.html, footnotes);
+    }
+    if let Some(syntax) = options.effective_html_syntax() {
+        stage("serialization", || {
+            let html = extensions::serialization::serialize(&result.html, syntax).into_owned();
+            result.html = html;
+            if let Some(footnotes) = &mut result.footnotes {
+                *footnotes = extensions::serialization::serialize(footnotes, syntax).into_owned();
+            }
+        });
+    }
+    if options.output_format != parser::OutputFormat::Compact {
+        stage("output_format", || {
+            let format = options.output_format;
+            let html = extensions::output_format::format_html(&result.html, format).into_owned();
+            result.html = html;
+            if let Some(footnotes) = &mut result.footnotes {
+                *footnotes = extensions::output_format::format_html(footnotes, format).into_owned();
+            }
+        });
+    }
+    result.sections = stage("sections", || sections::split_sections(&result.html));
+}
+
+/// Rewrite class names of the body and footnotes
+///
+/// Applies the framework profile first, then the class prefix / map.
+fn apply_class_options(result: &mut ParseResult, options: &parser::ParserOptions) {
+    let framework = match options.profile {
+        parser::Profile::Epub => &parser::Framework::Semantic,
+        parser::Profile::Web | parser::Profile::EmailSafe => &options.framework,
+    };
+    if *framework == parser::Framework::Bootstrap5
+        && options.classes.is_identity()
+        && options.font_sizes.is_empty()
+    {
+        return;
     }
+    let rewrite = |html: &str| {
+        let html = extensions::font_size::apply_custom_sizes(html, &options.font_sizes);
+        let html = extensions::framework::apply_framework(&html, framework);
+        extensions::class_map::apply_class_map(&html, &options.classes)
+    };
+    let html = rewrite(&result.html);
+    result.html = html;
+    result.footnotes = result.footnotes.as_deref().map(rewrite);
+}
+
+/// Build a search-index token stream from Universal Markdown
+///
+/// Yields heading-weighted plain-text segments (title, headings, body, code)
+/// suitable for a full-text search engine. Plugin output is excluded and
+/// segments containing inline decorations are flagged.
+///
+/// # Arguments
+///
+/// * `input` - The Universal Markdown source text
+///
+/// # Returns
+///
+/// Tokens in document order
+///
+/// # Examples
+///
+/// ```
+/// use umd::{IndexTokenKind, index_tokens};
+///
+/// let tokens = index_tokens("# Install\n\nRun the installer.");
+/// assert_eq!(tokens[0].kind, IndexTokenKind::Heading(1));
+/// assert_eq!(tokens[1].text, "Run the installer.");
+/// ```
+pub fn index_tokens(input: &str) -> Vec<IndexToken> {
+    search_index::index_tokens_with_options(input, &parser::ParserOptions::default())
+}
+
+/// Compare two revisions of a document block by block
+///
+/// Both sources are rendered and their top-level blocks matched, so a
+/// history view can show which headings, paragraphs, tables or plugins were
+/// changed, inserted or removed, with the rendered HTML of each side. See
+/// [`diff::diff_with_options`] for custom parser options.
+///
+/// # Arguments
+///
+/// * `old_src` - The previous Universal Markdown source text
+/// * `new_src` - The current Universal Markdown source text
+///
+/// # Returns
+///
+/// Every block of both revisions in document order
+///
+/// # Examples
+///
+/// ```
+/// use umd::{BlockChange, diff};
+///
+/// let diffs = diff("# Title\n\nOld text.\n", "# Title\n\nNew text.\n\n- item\n");
+/// assert_eq!(diffs[0].change, BlockChange::Unchanged);
+/// assert_eq!(diffs[1].change, BlockChange::Changed);
+/// assert_eq!(diffs[1].old_html.as_deref(), Some("<p>Old text.</p>"));
+/// assert_eq!(diffs[1].new_html.as_deref(), Some("<p>New text.</p>"));
+/// assert_eq!(diffs[2].change, BlockChange::Inserted);
+/// ```
+pub fn diff(old_src: &str, new_src: &str) -> Vec<BlockDiff> {
+    diff::diff_with_options(old_src, new_src, &parser::ParserOptions::default())
+}
+
+/// Check a document against the default style rules
+///
+/// See [`lint::lint_with_options`] for the rules and their configuration,
+/// and [`lint::apply_fixes`] to apply the suggested fixes.
+///
+/// # Arguments
+///
+/// * `input` - The Universal Markdown source text
+///
+/// # Returns
+///
+/// Issues ordered by source line
+///
+/// # Examples
+///
+/// ```
+/// use umd::lint;
+///
+/// let issues = lint("# Title\n\n![](photo.jpg)\n");
+/// assert_eq!(issues[0].diagnostic.code, "lint-img-alt");
+/// assert_eq!(issues[0].diagnostic.line, Some(3));
+/// ```
+pub fn lint(input: &str) -> Vec<LintIssue> {
+    lint::lint_with_options(input, &LintOptions::default())
+}
+
+/// Run CommonMark spec examples with all extensions off
+///
+/// Takes examples in the format of the official `spec.json` and reports the
+/// pass rate and the failing examples. Use
+/// [`compliance::run_spec`] with [`parser::Dialect::Gfm`] for the GFM
+/// extension examples.
+///
+/// # Arguments
+///
+/// * `spec_json` - Examples in the format of the official `spec.json`
+///
+/// # Returns
+///
+/// Compliance report, or the JSON error when `spec_json` is malformed
+///
+/// # Examples
+///
+/// ```
+/// use umd::commonmark_compliance;
+///
+/// let spec = r##"[{"markdown": "# foo\n", "html": "<h1>foo</h1>\n", "example": 1, "section": "ATX headings"}]"##;
+/// let report = commonmark_compliance(spec).unwrap();
+/// assert_eq!(report.pass_rate(), 1.0);
+/// ```
+pub fn commonmark_compliance(
+    spec_json: &str,
+) -> Result<compliance::ComplianceReport, serde_json::Error> {
+    compliance::run_spec(spec_json, parser::Dialect::CommonMark)
 }
 
 /// WASM-exposed API for parsing Universal Markdown
@@ -300,11 +1448,54 @@ fn extract_footnotes(html: &str) -> (String, Option<String>) {
 /// Supported options:
 /// - `gfmExtensions`: boolean
 /// - `umdExtensions`: boolean
-/// - `maxHeadingLevel`: number
+/// - `maxHeadingLevel`: number (deeper headings are clamped to it)
+/// - `headingOffset`: number (levels every heading is shifted down)
+/// - `numberHeadings`: boolean (prefix headings with `1.`, `1.1`, ...)
+/// - `sortableTables`: boolean (`data-sortable` / `data-type` table hooks)
+/// - `captionSide`: `"bottom"` (default) or `"top"` (table captions)
+/// - `responsiveTables`: `"off"` (default), `"always"`, `"sm"`, `"md"`, `"lg"`, `"xl"` or `"xxl"`
+/// - `videoEmbeds`: boolean (`@youtube` / `@vimeo` / `@niconico` iframes)
+/// - `mapEmbed`: `"off"` (default), `"osm"` (OpenStreetMap iframe) or an `https://` static map URL
+///   template with `{lat}`, `{lon}` and `{zoom}`
+/// - `printMode`: boolean (print / PDF friendly output)
 /// - `maxInlineNesting`: number (recommended: 3-5)
 /// - `baseUrl`: string
 /// - `allowFragmentExtensionHint`: boolean
-/// - `icons`: object with `video`, `audio`, `download`, `colorSwatch`
+/// - `icons`: object with `video`, `audio`, `download`, `colorSwatch`, `alertNote`, `alertTip`,
+///   `alertImportant`, `alertWarning`, `alertCaution`
+/// - `autolink`: object with `bareDomains` (boolean), `allow` and `deny` (string arrays)
+/// - `mentionBase`: string (enables `@username` links)
+/// - `tagBase`: string (enables `#tag` links)
+/// - `aria`: object with `headingAnchorLabel`, `footnoteBackrefLabel`, `alertLive` (strings; empty disables)
+/// - `colorMode`: `"fixed"` (default) or `"adaptive"` (the palette is prepended as a `<style>` element)
+/// - `framework`: `"bootstrap5"` (default) or `"semantic"`
+/// - `classes`: object with `prefix` (string) and `map` (object of class name to replacement)
+/// - `fontSizes`: object of size keyword to class(es)
+/// - `dialect`: `"commonmark"`, `"gfm"`, `"umd"` or `"lukiwiki"` (default)
+/// - `emphasisTags`: `"presentational"` (default, `''` / `'''` as `<b>` / `<i>`) or `"semantic"`
+///   (`<strong>` / `<em>`)
+/// - `ids`: `"random"` (default) or `"hash"` (content-based, deterministic IDs)
+/// - `footnotes`: object with `heading`, `headingLevel`, `start`, `backrefSymbol`, `class`, `tooltips`
+///   and `placement` (`"separate"` (default) or `"inline"`; inline footnotes end the returned HTML)
+/// - `alerts`: object with `heading` and `icons` (booleans)
+/// - `anchors`: object with `style` (`"empty"` (default), `"permalink"`, `"wrap"`, `"id"` or `"none"`) and `symbol`
+/// - `allowedPlugins` / `deniedPlugins`: string arrays of plugin names
+/// - `pluginSignatures`: array of `{ name, params: [{ name, type }], minArgs, maxArgs }`
+///   (`type`: `"text"` (default), `"integer"`, `"number"` or `"url"`; argument counts default to
+///   the number of params)
+/// - `pluginContent`: `"escaped"` (default) or `"encoded"` (empty plugin templates with base64
+///   `data-umd-args` / `data-umd-content`)
+/// - `trustedHtml`: object with `tags` and `attributes` (string arrays, each defaulting to the
+///   built-in allow-list); raw HTML tags on the list pass through, other HTML is still escaped.
+///   Only for documents from trusted editors
+/// - `comrak`: object with `headerIds` (anchor ID prefix, `commonmark` / `gfm` dialects only),
+///   `tasklist`, `singleTildeStrikethrough`, `subscript`, `superscript`, `descriptionLists`,
+///   `multilineBlockQuotes`, `underline` (booleans) and `wikilinks` (`"off"` (default),
+///   `"title-after-pipe"` or `"title-before-pipe"`)
+/// - `locale`: `"en"` (default) or `"ja"` (generated labels; `"ja"` also localizes the `aria` defaults)
+/// - `outputFormat`: `"compact"` (default), `"pretty"` (indented blocks) or `"minified"`
+/// - `lineAnchors`: number (`<span id="L42"></span>` before top-level blocks, at least this many
+///   source lines apart; `0` disables)
 ///
 /// # Arguments
 ///
@@ -335,6 +1526,112 @@ pub fn parse_wasm(input: &str, options_json: Option<String>) -> String {
     parse_with_options_json(input, options_json.as_deref())
 }
 
+/// WASM entry point returning a structured result
+///
+/// Parses once and returns everything a frontend needs for a page, so
+/// metadata does not require a second parse:
+///
+/// - `html`: body HTML (without footnotes)
+/// - `frontmatter`: top-level frontmatter scalars, or `null`
+/// - `footnotes`: footnotes HTML, or `null`
+/// - `toc`: `{ level, title, id }` of each top-level heading
+/// - `diagnostics`: `{ severity, code, message, line }`
+/// - `stats`: `{ words, characters, headings, footnotes }`
+/// - `colorPalette`: palette stylesheet for `colorMode: "adaptive"`, or `null`
+///
+/// `options` is an options object or its JSON string, with the same keys as
+/// [`parse_wasm`].
+///
+/// # Examples (JavaScript)
+///
+/// ```javascript
+/// import { parseMarkdownFull } from 'umd';
+///
+/// const { html, frontmatter, toc } = parseMarkdownFull(source, { baseUrl: '/app' });
+/// document.title = frontmatter?.title ?? toc[0]?.title ?? '';
+/// ```
+#[wasm_bindgen(js_name = parseMarkdownFull)]
+pub fn parse_markdown_full(input: &str, options: JsValue) -> JsValue {
+    let options_json = js_json(&options);
+    js_sys::JSON::parse(&parse_full_json(input, options_json.as_deref())).unwrap_or(JsValue::NULL)
+}
+
+/// WASM entry point rendering a page into its layout
+///
+/// `layouts` maps layout names to templates (an object or its JSON
+/// string); the document picks one with `layout:` in its frontmatter, or
+/// gets the `default` layout. Slots are described in [`layout`]. Returns
+/// the filled template, followed by the footnotes when the template has no
+/// `{{slot:footnotes}}`. `options` has the same keys as [`parse_wasm`].
+///
+/// # Examples (JavaScript)
+///
+/// ```javascript
+/// import { parseWithLayout } from 'umd';
+///
+/// const html = parseWithLayout(source, {
+///   article: '<article><h1>{{slot:title}}</h1>{{slot:toc}}{{slot:content}}</article>',
+/// }, JSON.stringify({ baseUrl: '/app' }));
+/// ```
+#[wasm_bindgen(js_name = parseWithLayout)]
+pub fn parse_with_layout_wasm(
+    input: &str,
+    layouts: JsValue,
+    options_json: Option<String>,
+) -> String {
+    parse_with_layout_json(input, js_json(&layouts).as_deref(), options_json.as_deref())
+}
+
+/// JSON text of a JS value that is either a string or an object
+fn js_json(value: &JsValue) -> Option<String> {
+    value.as_string().or_else(|| {
+        value
+            .is_object()
+            .then(|| js_sys::JSON::stringify(value).ok())
+            .flatten()
+            .and_then(|json| json.as_string())
+    })
+}
+
+/// WASM entry point reporting progress per pipeline stage
+///
+/// Same output as [`parse_wasm`]. `on_progress(stage, index)` is called as
+/// each stage finishes (`index` counts from 0); its return value and any
+/// exception it throws are ignored.
+///
+/// This reports progress only: the parse is synchronous and never yields
+/// to the event loop between stages, so the calling thread is blocked until
+/// it returns. Parse large documents in a Web Worker that forwards the
+/// progress with `postMessage` to keep the editor's main thread free.
+///
+/// # Examples (JavaScript)
+///
+/// ```javascript
+/// // worker.js
+/// import init, { parseWithProgress } from 'umd';
+///
+/// self.onmessage = async ({ data }) => {
+///   await init();
+///   const html = parseWithProgress(data.source, JSON.stringify(data.options), (stage, index) =>
+///     self.postMessage({ type: 'progress', stage, index }));
+///   self.postMessage({ type: 'done', html });
+/// };
+/// ```
+#[wasm_bindgen(js_name = parseWithProgress)]
+pub fn parse_with_progress_wasm(
+    input: &str,
+    options_json: Option<String>,
+    on_progress: js_sys::Function,
+) -> String {
+    let mut index = 0u32;
+    let progress = move |stage: &'static str| {
+        let _ = on_progress.call2(&JsValue::NULL, &JsValue::from_str(stage), &index.into());
+        index += 1;
+    };
+    let options = wasm_options(options_json.as_deref());
+    wasm_html(parse_with_progress(input, &options, progress))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -354,6 +1651,410 @@ mod tests {
         assert!(output.contains("&lt;script&gt;"));
     }
 
+    #[test]
+    fn test_duplicate_header_ids_are_reported() {
+        let input = "# One {#same}\n\n# Two {#same}";
+        let result = parse_with_frontmatter(input);
+        assert!(result.html.contains(r#"id="h-same""#));
+        assert!(result.html.contains(r#"id="h-same-1""#));
+        assert_eq!(result.diagnostics.len(), 1);
+        assert_eq!(result.diagnostics[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_cross_reference_to_heading() {
+        let input =
+            "See &ref(setup); and [#setup].\n\n## Initial Setup {#setup}\n\n[bad](#nowhere)";
+        let result = parse_with_frontmatter(input);
+        assert!(
+            result
+                .html
+                .contains(r##"See <a href="#h-setup" class="umd-ref">Initial Setup</a> and <a href="#h-setup" class="umd-ref">Initial Setup</a>."##),
+            "{}",
+            result.html
+        );
+        assert_eq!(result.diagnostics.len(), 1);
+        assert_eq!(result.diagnostics[0].code, "broken-anchor");
+    }
+
+    #[test]
+    fn test_shorthand_reference_needs_a_heading() {
+        let result = parse_with_frontmatter("Fixed in issue [#42].");
+        assert_eq!(result.html.trim(), "<p>Fixed in issue [#42].</p>");
+        assert!(result.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_a11y_audit_is_opt_in() {
+        let input = "# Title\n\n### Skipped\n\n![](chart.png) [here](/docs)";
+        assert!(parse_with_frontmatter(input).diagnostics.is_empty());
+
+        let options = parser::ParserOptions {
+            a11y_audit: true,
+            ..Default::default()
+        };
+        let result = parse_with_frontmatter_opts(input, &options);
+        let codes: Vec<_> = result.diagnostics.iter().map(|d| d.code).collect();
+        assert_eq!(
+            codes,
+            ["a11y-img-alt", "a11y-heading-skip", "a11y-link-text"]
+        );
+    }
+
+    #[test]
+    fn test_autolink_bare_domain_and_query_string() {
+        let output = parse("Visit example.com or https://example.com/?a=1&y; now");
+        assert!(output.contains(r#"<a href="https://example.com">example.com</a>"#));
+        assert!(!output.contains("umd-plugin-y"), "{}", output);
+    }
+
+    #[test]
+    fn test_parse_with_options_json_autolink_deny() {
+        let output = parse_with_options_json(
+            "example.com and www.example.org",
+            Some(r#"{"autolink":{"deny":["example.com","example.org"]}}"#),
+        );
+        assert!(!output.contains("<a "), "{}", output);
+    }
+
+    #[test]
+    fn test_parse_with_options_json_mentions_and_hashtags() {
+        let input =
+            "# Title\n\n#release notes by @alice\n\n@callout(info){{ body }}\n\n`@bob #code`";
+        let output =
+            parse_with_options_json(input, Some(r#"{"mentionBase":"/users","tagBase":"/tags"}"#));
+        assert!(output.contains(r#"<a class="hashtag" href="/tags/release">#release</a>"#));
+        assert!(output.contains(r#"<a class="mention" href="/users/alice">@alice</a>"#));
+        assert!(output.contains("Title</h1>"), "{}", output);
+        assert!(!output.contains("/users/callout"), "{}", output);
+        assert!(!output.contains("/users/bob"), "{}", output);
+        assert!(!parse(input).contains("class=\"mention\""));
+    }
+
+    #[test]
+    fn test_aria_labels() {
+        let output = parse("## Setup\n\nSee ||secret||[^1]\n\n[^1]: Note");
+        assert!(output.contains(r#"class="anchor" id="h-1" aria-label="Link to section: Setup""#));
+        assert!(output.contains(r#"aria-controls="umd-spoiler-1""#));
+        assert!(output.contains(r#"aria-label="Back to content""#));
+
+        let output = parse_with_options_json(
+            "## Setup",
+            Some(r#"{"aria":{"headingAnchorLabel":"{title} へのリンク"}}"#),
+        );
+        assert!(
+            output.contains(r#"aria-label="Setup へのリンク""#),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_class_options() {
+        let input = "See ||secret||[^1]\n\n[^1]: Note";
+        let output = parse_with_options_json(
+            input,
+            Some(r#"{"classes":{"prefix":"u-","map":{"spoiler":"blur"}}}"#),
+        );
+        assert!(
+            output.contains(r#"<span class="blur" role="button""#),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains(r#"class="u-spoiler-content""#),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains(r#"<section class="u-footnotes""#),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_gfm_alert_options() {
+        let input = "> [!WARNING]\n> Mind the gap.\n>\n> Second paragraph.";
+        let output = parse(input);
+        assert!(
+            output.contains(
+                "<div class=\"alert alert-warning\" role=\"alert\"><p><strong>Warning:</strong> Mind the gap.</p>\n<p>Second paragraph.</p></div>"
+            ),
+            "{}",
+            output
+        );
+
+        let output =
+            parse_with_options_json(input, Some(r#"{"alerts":{"heading":true,"icons":true}}"#));
+        assert!(
+            output.contains(r#"<p class="alert-heading"><svg "#),
+            "{}",
+            output
+        );
+        assert!(output.contains("<strong>Warning</strong></p><p>Mind the gap.</p>"));
+    }
+
+    #[test]
+    fn test_footnote_options() {
+        let output = parse_with_options_json(
+            "One[^a] two[^b].\n\n[^a]: First\n[^b]: Second",
+            Some(
+                r#"{"footnotes":{"heading":"References","start":3,"backrefSymbol":"Back","class":"small"}}"#,
+            ),
+        );
+        assert!(
+            output.contains(r##"<a href="#fn-3" id="fnref-3" data-footnote-ref>3</a>"##),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains(r##"<a href="#fn-4" id="fnref-4" data-footnote-ref>4</a>"##),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains(
+                "<section class=\"footnotes small\" data-footnotes>\n<h2 class=\"footnotes-heading\">References</h2>\n<ol start=\"3\">"
+            ),
+            "{}",
+            output
+        );
+        assert!(output.contains(r#"<li id="fn-4">"#), "{}", output);
+        assert!(output.contains(">Back</a>"), "{}", output);
+    }
+
+    #[test]
+    fn test_output_format() {
+        let input = "> Quote\n\n- One\n- Two[^1]\n\n[^1]: Note";
+        let output = parse_with_options_json(input, Some(r#"{"outputFormat":"minified"}"#));
+        assert!(
+            output.starts_with(
+                "<blockquote class=\"blockquote\"><p>Quote</p></blockquote><ul><li>One</li><li>Two"
+            ),
+            "{}",
+            output
+        );
+        // Body and footnotes, joined by a newline
+        assert_eq!(output.lines().count(), 2, "{}", output);
+
+        let output = parse_with_options_json(input, Some(r#"{"outputFormat":"pretty"}"#));
+        assert!(
+            output.starts_with(
+                "<blockquote class=\"blockquote\">\n  <p>Quote</p>\n</blockquote>\n<ul>\n  <li>One</li>\n"
+            ),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("\n  <ol>\n    <li id=\"fn-1\">"),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_html_syntax() {
+        let input = "A&br;B\n\n![Clip](/a.mp4)[^1]\n\n[^1]: Note";
+        let output = parse_with_options_json(input, Some(r#"{"htmlSyntax":"html5"}"#));
+        assert!(output.contains("A<br>B"), "{}", output);
+        assert!(
+            output.contains("<source src=\"/a.mp4\" type=\"video/mp4\">"),
+            "{}",
+            output
+        );
+
+        let output = parse_with_options_json(input, Some(r#"{"htmlSyntax":"xhtml"}"#));
+        assert!(output.contains("A<br />B"), "{}", output);
+        assert!(output.contains("<video controls=\"\">"), "{}", output);
+        assert!(output.contains(" download=\"\""), "{}", output);
+        assert!(output.contains(" data-footnotes=\"\""), "{}", output);
+    }
+
+    #[test]
+    fn test_epub_profile() {
+        let input = "CENTER: Text\n\nNote[^1]\n\n![Sky](/a.png \"Sunset\")\n\n[^1]: Note";
+        let output = parse_with_options_json(input, Some(r#"{"profile":"epub"}"#));
+        assert!(
+            output.starts_with("<p data-align=\"center\">Text</p>"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("<a epub:type=\"noteref\" href=\"#fn-1\""),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("<img src=\"/a.png\" alt=\"Sky\" title=\"Sunset\" />\n<figcaption>Sunset</figcaption>"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("\n<aside epub:type=\"footnote\" id=\"fn-1\">\n<p>Note "),
+            "{}",
+            output
+        );
+        assert!(!output.contains("<section"), "{}", output);
+    }
+
+    #[test]
+    fn test_email_safe_profile() {
+        let input =
+            "CENTER: ||Answer||\n\n@youtube(dQw4w9WgXcQ, Talk)\n\n@toc()\n\n![Clip](/a.mp4)";
+        let output = parse_with_options_json(
+            input,
+            Some(r#"{"profile":"email-safe","videoEmbeds":true}"#),
+        );
+        assert!(
+            output.starts_with(
+                "<p class=\"text-center\" style=\"text-align: center\"><span class=\"spoiler spoiler-revealed\">(spoiler) <span class=\"spoiler-content\""
+            ),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains(
+                "<img src=\"https://i.ytimg.com/vi/dQw4w9WgXcQ/hqdefault.jpg\" alt=\"Talk\""
+            ),
+            "{}",
+            output
+        );
+        assert!(output.contains(">Clip</a>"), "{}", output);
+        for removed in [
+            "<template",
+            "<iframe",
+            "<video",
+            "aria-controls",
+            "tabindex",
+        ] {
+            assert!(!output.contains(removed), "{}", output);
+        }
+    }
+
+    #[test]
+    fn test_footnote_placement() {
+        let input = "```\n<section class=\"footnotes\">\n```\n\nText[^1]\n\n[^1]: Note";
+        let separate = parse_with_frontmatter(input);
+        assert!(!separate.html.contains("<section"), "{}", separate.html);
+        let footnotes = separate.footnotes.expect("footnotes");
+        assert!(footnotes.contains("<li id=\"fn-1\">"), "{}", footnotes);
+
+        let mut options = parser::ParserOptions::default();
+        options.footnotes.placement = parser::FootnotePlacement::Inline;
+        let inline = parse_with_frontmatter_opts(input, &options);
+        assert!(inline.footnotes.is_none());
+        assert_eq!(inline.html, parse(input));
+    }
+
+    #[test]
+    fn test_deterministic_ids() {
+        let input = "&popover(Info){Some **detail**};\n\n&popover(Info){Some **detail**};\n\n```mermaid\ngraph TD\nA-->B\n```";
+        let output = parse(input);
+        assert_eq!(output, parse(input));
+        let id = regex::Regex::new(r#"id="(umd-popover-[0-9a-f]{16})""#)
+            .unwrap()
+            .captures(&output)
+            .map(|caps| caps[1].to_string())
+            .expect("popover id");
+        assert!(output.contains(&format!(r#"id="{}-2""#, id)), "{}", output);
+        assert!(output.contains(r#"id="mermaid-"#), "{}", output);
+
+        let options = parser::ParserOptions {
+            ids: parser::IdStrategy::Random,
+            ..parser::ParserOptions::default()
+        };
+        assert_ne!(
+            parse_with_frontmatter_opts(input, &options).html,
+            parse_with_frontmatter_opts(input, &options).html
+        );
+    }
+
+    #[test]
+    fn test_parse_fallible_accepts_malformed_input() {
+        let options = parser::ParserOptions::default();
+        for input in [
+            "&color(red){&size(2){&badge(x){",
+            "|>|>|\n|~|^|\n|h",
+            "@x(a){{\n```\n}}",
+            ":a|b\n:|\n:c",
+            "COLOR(\u{0}): 🎉 &ruby(漢字",
+        ] {
+            assert!(parse_fallible(input, &options).is_ok(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_font_size_keywords() {
+        let input = "SIZE(lead): Intro\n\n&size(huge){Big}; &size(sm){small};";
+        let output = parse_with_options_json(
+            input,
+            Some(r#"{"fontSizes":{"huge":"text-9xl font-bold"}}"#),
+        );
+        assert!(output.contains(r#"class="lead""#), "{}", output);
+        assert!(
+            output.contains(r#"<span class="text-9xl font-bold">Big</span>"#),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains(r#"<span class="small">small</span>"#),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_semantic_framework() {
+        let input = "CENTER: &color(red){a}; &badge(primary){b};";
+        let output = parse_with_options_json(input, Some(r#"{"framework":"semantic"}"#));
+        assert!(output.contains(r#"data-align="center""#), "{}", output);
+        assert!(
+            output.contains(r#"<span data-color="red">a</span>"#),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains(r#"<span class="umd-badge" data-bg="primary">b</span>"#),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_adaptive_color_mode() {
+        let input = "COLOR(#000): a &color(primary){b};\n\n`#ff0000`";
+        let output = parse_with_options_json(input, Some(r#"{"colorMode":"adaptive"}"#));
+        assert!(
+            output.starts_with("<style class=\"umd-color-palette\">"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("--umd-color-000000: #ffffff;"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains(r#"style="color: var(--umd-color-000000)""#),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains(r#"class="text-primary-emphasis""#),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains(r#"style="background-color: #ff0000;""#),
+            "{}",
+            output
+        );
+
+        let result = parse_with_frontmatter("COLOR(#000): a");
+        assert!(result.color_palette.is_none());
+    }
+
     #[test]
     fn test_parse_with_options_json_base_url() {
         let input = "[docs](/guide)";
@@ -389,4 +2090,162 @@ mod tests {
 
         assert_eq!(output_from_json, expected_html);
     }
+
+    #[test]
+    fn test_parse_full_json() {
+        let input =
+            "---\ntitle: Guide\n---\n\n# Intro\n\nHello [world](/w)[^1]\n\n## Setup\n\n[^1]: Note";
+        let output = parse_full_json(input, Some(r#"{"baseUrl":"/app"}"#));
+        let value: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert!(value["html"].as_str().unwrap().contains("href=\"/app/w\""));
+        assert!(!value["html"].as_str().unwrap().contains("Note"));
+        assert_eq!(value["frontmatter"]["title"], "Guide");
+        assert!(value["footnotes"].as_str().unwrap().contains("Note"));
+        assert_eq!(value["toc"][0]["title"], "Intro");
+        assert_eq!(value["toc"][1]["level"], 2);
+        assert!(value["diagnostics"].is_array());
+        assert_eq!(value["stats"]["headings"], 2);
+        assert_eq!(value["stats"]["footnotes"], 1);
+        assert_eq!(value["stats"]["words"], 5);
+        assert!(value["colorPalette"].is_null());
+
+        let value: serde_json::Value = serde_json::from_str(&parse_full_json("", None)).unwrap();
+        assert!(value["frontmatter"].is_null());
+        assert_eq!(value["stats"]["words"], 0);
+    }
+
+    #[test]
+    fn test_first_image() {
+        let result = parse_with_frontmatter(
+            "Text\n\n![Logo](/img/logo.png?a=1&b=2 \"Site\")\n\n![x](b.png)",
+        );
+        let image = result.first_image.unwrap();
+        assert_eq!(image.src, "/img/logo.png?a=1&b=2");
+        assert_eq!(image.alt, "Logo");
+        assert_eq!(image.title.as_deref(), Some("Site"));
+
+        let options = parser::ParserOptions {
+            base_url: Some("/blog/".to_string()),
+            ..Default::default()
+        };
+        let result =
+            parse_with_frontmatter_opts("---\ncover: /img/cover.jpg\n---\n\n![x](b.png)", &options);
+        assert_eq!(result.first_image.unwrap().src, "/blog/img/cover.jpg");
+
+        assert!(parse_with_frontmatter("No images").first_image.is_none());
+    }
+
+    #[test]
+    fn test_sections() {
+        let result =
+            parse_with_frontmatter("Intro\n\n# Setup {#setup}\n\nA\n\n## Next *step*\n\nB");
+        let sections = &result.sections;
+        assert_eq!(sections.len(), 3);
+        assert_eq!(sections[0].level, 0);
+        assert_eq!(sections[0].html, "<p>Intro</p>");
+        assert_eq!(sections[1].level, 1);
+        assert_eq!(sections[1].title, "Setup");
+        assert_eq!(sections[1].id.as_deref(), Some("h-setup"));
+        assert!(sections[1].html.ends_with("<p>A</p>"));
+        assert_eq!(sections[2].title, "Next step");
+        assert!(result.html.contains(&sections[2].html));
+    }
+
+    #[test]
+    fn test_anchor_style_options() {
+        let input = "# Setup {#setup}\n\nSee [#setup].";
+        let output = parse_with_options_json(
+            input,
+            Some(r#"{"anchors":{"style":"permalink","symbol":"¶"}}"#),
+        );
+        assert!(
+            output.contains(r##"Setup <a href="#h-setup" class="anchor" id="h-setup""##),
+            "{}",
+            output
+        );
+        assert!(output.contains(">¶</a></h1>"), "{}", output);
+
+        let options = parser::ParserOptions {
+            anchors: parser::AnchorOptions {
+                style: parser::AnchorStyle::IdOnly,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let result = parse_with_frontmatter_opts(input, &options);
+        assert!(
+            result.html.starts_with(r#"<h1 id="h-setup">Setup</h1>"#),
+            "{}",
+            result.html
+        );
+        assert!(result.html.contains(r##"href="#h-setup""##));
+        assert_eq!(result.sections[0].id.as_deref(), Some("h-setup"));
+        let tokens = search_index::index_tokens_with_options(input, &options);
+        assert_eq!(tokens[0].anchor.as_deref(), Some("h-setup"));
+    }
+
+    #[test]
+    fn test_formatted_heading_anchor() {
+        let html = parse("## Call `parse()`  *now* {#call}");
+        assert!(
+            html.contains(
+                r##"<a href="#h-call" class="anchor" id="h-call" aria-label="Link to section: Call parse() now"></a>Call <code>parse()</code>"##
+            ),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn test_parse_with_layout_json() {
+        let layouts = r#"{"default":"<main>{{slot:content}}</main>"}"#;
+        let output = parse_with_layout_json("Text[^1]\n\n[^1]: Note", Some(layouts), None);
+        assert!(output.starts_with("<main><p>Text"), "{}", output);
+        assert!(output.contains("</main>\n<section"), "{}", output);
+        assert_eq!(
+            parse_with_layout_json("Text", Some("not json"), None),
+            "<p>Text</p>"
+        );
+    }
+
+    #[test]
+    fn test_line_anchors() {
+        let input =
+            "---\ntitle: Notes\n---\n# Notes\n\nFirst\nparagraph\n\n- a\n\n- b\n\n| x |\n\nLast";
+        let output = parse_with_options_json(input, Some(r#"{"lineAnchors":1}"#));
+        assert!(
+            output.starts_with("<span id=\"L4\"></span>\n<h1"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("<span id=\"L6\"></span>\n<p>First"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("<span id=\"L9\"></span>\n<ul>"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("<span id=\"L15\"></span>\n<p>Last</p>"),
+            "{}",
+            output
+        );
+        assert_eq!(output.matches("<span id=").count(), 4, "{}", output);
+        assert!(output.contains("<td>x</td>"), "{}", output);
+
+        let output = parse_with_options_json(input, Some(r#"{"lineAnchors":10}"#));
+        assert_eq!(output.matches("<span id=").count(), 2, "{}", output);
+        assert!(output.contains("<span id=\"L15\"></span>"), "{}", output);
+
+        // Text shaped like the placeholder stays text
+        let output = parse_with_options_json("UMD_LINE_ANCHOR_1_END", Some(r#"{"lineAnchors":1}"#));
+        assert!(
+            output.contains("<p>UMD_LINE_ANCHOR_1_END</p>"),
+            "{}",
+            output
+        );
+    }
 }
@@ -0,0 +1,506 @@
+//! Style linter
+//!
+//! [`lint_with_options`] checks a document against configurable style rules
+//! and reports each problem as a [`Diagnostic`] with its source line. Where
+//! the fix is unambiguous the issue carries a [`Fix`], and [`apply_fixes`]
+//! rewrites the source with all of them.
+//!
+//! | Code | Rule | Fix |
+//! |------|------|-----|
+//! | `lint-heading-depth` | Heading deeper than `max_heading_depth` | Lowers the `#` count |
+//! | `lint-img-alt` | Image without alternative text | - |
+//! | `lint-raw-url` | Bare URL in text | Wraps it in `<...>` |
+//! | `lint-emphasis-style` | `*em*` and `_em_` mixed | Swaps the markers |
+//! | `lint-table-header` | Table without header cells | - |
+//! | `lint-trailing-whitespace` | Trailing spaces or tabs (not a hard break) | Removes them |
+//!
+//! Block rules run on the rendered document: it is parsed with line anchors
+//! before every top-level block (see [`crate::extensions::line_anchors`]),
+//! split with [`top_level_blocks`] and each block is attributed to the
+//! anchor before it, so headings, images and tables produced by any syntax
+//! (setext headings, UMD tables, `&ref` images) are checked alike. Image and
+//! table checks are those of the [accessibility audit](crate::extensions::accessibility).
+//! Text rules run on the source lines outside frontmatter, code, multi-line
+//! plugins and comments.
+
+use std::ops::Range;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::diagnostics::Diagnostic;
+use crate::extensions::accessibility::audit;
+use crate::frontmatter::extract_frontmatter;
+use crate::parser::{IdStrategy, ParserOptions};
+use crate::sections::top_level_blocks;
+
+/// Line anchor rendered before a block
+static LINE_ANCHOR: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"^<span id="L(\d+)"></span>$"#).unwrap());
+
+/// Heading block
+static HEADING_BLOCK: Lazy<Regex> = Lazy::new(|| Regex::new(r"^<h([1-6])\b").unwrap());
+
+/// ATX heading line
+static ATX_HEADING: Lazy<Regex> = Lazy::new(|| Regex::new(r"^ {0,3}(#{1,6})(?:[ \t]|$)").unwrap());
+
+/// Text whose URLs are not bare: inline code, link and image targets,
+/// angle-bracket autolinks and plugin arguments
+static NOT_TEXT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"`+[^`]*`+|\]\([^)]*\)|<[^>\s]+>|[&@][A-Za-z_][\w-]*\([^)]*\)").unwrap()
+});
+
+/// Bare URL
+static RAW_URL: Lazy<Regex> = Lazy::new(|| Regex::new(r#"https?://[^\s<>()\[\]{}"'`|]+"#).unwrap());
+
+/// Link reference definition (`[label]: https://...`)
+static LINK_DEFINITION: Lazy<Regex> = Lazy::new(|| Regex::new(r"^ {0,3}\[[^\]]+\]:").unwrap());
+
+/// `*em*` candidate; flanking characters are checked separately
+static ASTERISK_EMPHASIS: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\*[^*\s](?:[^*]*?[^*\s])?\*").unwrap());
+
+/// `_em_` candidate; flanking characters are checked separately
+static UNDERSCORE_EMPHASIS: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"_[^_\s](?:[^_]*?[^_\s])?_").unwrap());
+
+/// Marker for single emphasis (`*em*` / `_em_`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmphasisStyle {
+    /// Whichever marker the document uses first
+    Consistent,
+    /// `*em*`
+    Asterisk,
+    /// `_em_`
+    Underscore,
+}
+
+/// Lint rule configuration
+///
+/// Every rule except the heading depth is enabled by default.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintOptions {
+    /// Deepest allowed heading level (`Some(3)` reports `####`)
+    pub max_heading_depth: Option<u8>,
+    /// Report images without alternative text
+    pub require_alt_text: bool,
+    /// Report bare URLs in text
+    pub no_raw_urls: bool,
+    /// Required single emphasis marker (`None` allows both)
+    pub emphasis_style: Option<EmphasisStyle>,
+    /// Report tables without header cells
+    pub require_table_header: bool,
+    /// Report trailing whitespace that is not a hard line break
+    pub no_trailing_whitespace: bool,
+}
+
+impl Default for LintOptions {
+    fn default() -> Self {
+        Self {
+            max_heading_depth: None,
+            require_alt_text: true,
+            no_raw_urls: true,
+            emphasis_style: Some(EmphasisStyle::Consistent),
+            require_table_header: true,
+            no_trailing_whitespace: true,
+        }
+    }
+}
+
+/// Replacement of part of a source line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fix {
+    /// 1-based source line
+    pub line: usize,
+    /// Byte range within the line (without the line ending)
+    pub range: Range<usize>,
+    /// Text replacing the range
+    pub replacement: String,
+}
+
+/// A rule violation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintIssue {
+    /// Rule code, message and source line
+    pub diagnostic: Diagnostic,
+    /// Suggested fix, when one is unambiguous
+    pub fix: Option<Fix>,
+}
+
+impl LintIssue {
+    fn new(code: &'static str, message: impl Into<String>, line: usize, fix: Option<Fix>) -> Self {
+        Self {
+            diagnostic: Diagnostic::warning(code, message).with_line(line),
+            fix,
+        }
+    }
+}
+
+/// Lint a document
+///
+/// # Arguments
+///
+/// * `input` - The Universal Markdown source text
+/// * `options` - Rules to check
+///
+/// # Returns
+///
+/// Issues ordered by source line
+///
+/// # Examples
+///
+/// ```
+/// use umd::lint::{LintOptions, apply_fixes, lint_with_options};
+///
+/// let input = "# Title\n\nSee https://example.com \n";
+/// let issues = lint_with_options(input, &LintOptions::default());
+/// let codes: Vec<_> = issues.iter().map(|issue| issue.diagnostic.code).collect();
+/// assert_eq!(codes, ["lint-raw-url", "lint-trailing-whitespace"]);
+/// assert_eq!(issues[0].diagnostic.line, Some(3));
+/// assert_eq!(
+///     apply_fixes(input, &issues),
+///     "# Title\n\nSee <https://example.com>\n"
+/// );
+/// ```
+pub fn lint_with_options(input: &str, options: &LintOptions) -> Vec<LintIssue> {
+    let lines: Vec<&str> = input
+        .split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .collect();
+    let mut issues = lint_blocks(input, &lines, options);
+    lint_text(input, &lines, options, &mut issues);
+    issues.sort_by_key(|issue| issue.diagnostic.line);
+    issues
+}
+
+/// Apply the fixes of `issues` to `input`
+///
+/// A fix overlapping one already applied to the same line is skipped;
+/// linting the result again reports it if it still applies.
+pub fn apply_fixes(input: &str, issues: &[LintIssue]) -> String {
+    let mut fixes: Vec<&Fix> = issues
+        .iter()
+        .filter_map(|issue| issue.fix.as_ref())
+        .collect();
+    // Right to left, so earlier ranges on a line stay valid
+    fixes.sort_by_key(|fix| (fix.line, std::cmp::Reverse(fix.range.start)));
+
+    let mut lines: Vec<String> = input.split('\n').map(str::to_string).collect();
+    let mut applied_from: Option<(usize, usize)> = None;
+    for fix in fixes {
+        let Some(line) = lines.get_mut(fix.line.wrapping_sub(1)) else {
+            continue;
+        };
+        let content_len = line.strip_suffix('\r').unwrap_or(line).len();
+        let overlaps =
+            applied_from.is_some_and(|(number, start)| number == fix.line && fix.range.end > start);
+        if overlaps
+            || fix.range.end > content_len
+            || !line.is_char_boundary(fix.range.start)
+            || !line.is_char_boundary(fix.range.end)
+        {
+            continue;
+        }
+        line.replace_range(fix.range.clone(), &fix.replacement);
+        applied_from = Some((fix.line, fix.range.start));
+    }
+    lines.join("\n")
+}
+
+/// Rules checked on the rendered top-level blocks
+fn lint_blocks(input: &str, lines: &[&str], options: &LintOptions) -> Vec<LintIssue> {
+    let parser_options = ParserOptions {
+        ids: IdStrategy::ContentHash,
+        line_anchors: Some(1),
+        ..Default::default()
+    };
+    let result = crate::parse_with_frontmatter_opts(input, &parser_options);
+
+    let mut issues = Vec::new();
+    let mut line = 1;
+    for block in top_level_blocks(&result.html) {
+        if let Some(caps) = LINE_ANCHOR.captures(block) {
+            line = caps[1].parse().unwrap_or(line);
+            continue;
+        }
+
+        if let Some(max_depth) = options.max_heading_depth
+            && let Some(caps) = HEADING_BLOCK.captures(block)
+        {
+            let level: u8 = caps[1].parse().unwrap_or(1);
+            if level > max_depth {
+                let fix = lines
+                    .get(line - 1)
+                    .and_then(|source| ATX_HEADING.captures(source))
+                    .and_then(|caps| caps.get(1))
+                    .filter(|hashes| hashes.len() == usize::from(level))
+                    .map(|hashes| Fix {
+                        line,
+                        range: hashes.range(),
+                        replacement: "#".repeat(usize::from(max_depth.max(1))),
+                    });
+                issues.push(LintIssue::new(
+                    "lint-heading-depth",
+                    format!("Heading level h{} is deeper than h{}", level, max_depth),
+                    line,
+                    fix,
+                ));
+            }
+        }
+
+        for diagnostic in audit(block) {
+            let code = match diagnostic.code {
+                "a11y-img-alt" if options.require_alt_text => "lint-img-alt",
+                "a11y-table-header" if options.require_table_header => "lint-table-header",
+                _ => continue,
+            };
+            issues.push(LintIssue::new(code, diagnostic.message, line, None));
+        }
+    }
+    issues
+}
+
+/// Rules checked on the source lines
+fn lint_text(input: &str, lines: &[&str], options: &LintOptions, issues: &mut Vec<LintIssue>) {
+    let (_, body) = extract_frontmatter(input);
+    let first_body_line = input[..input.len() - body.len()].matches('\n').count();
+
+    let mut emphasis_style = options.emphasis_style;
+    let mut fence: Option<(char, usize)> = None;
+    let mut in_plugin = false;
+    let mut in_comment = false;
+
+    for (index, source) in lines.iter().enumerate().skip(first_body_line) {
+        let number = index + 1;
+        let trimmed = source.trim();
+
+        if let Some((fence_char, fence_len)) = fence {
+            let run = trimmed.chars().take_while(|c| *c == fence_char).count();
+            if run >= fence_len && trimmed[run..].trim().is_empty() {
+                fence = None;
+            }
+            continue;
+        }
+        if in_plugin || in_comment {
+            in_plugin = in_plugin && !source.contains("}}");
+            in_comment = in_comment && !source.contains("*/");
+            continue;
+        }
+        let fence_char = trimmed.chars().next().filter(|c| *c == '`' || *c == '~');
+        if let Some(ch) = fence_char {
+            let run = trimmed.chars().take_while(|c| *c == ch).count();
+            if run >= 3 {
+                fence = Some((ch, run));
+                continue;
+            }
+        }
+        if let Some(open) = source.rfind("{{") {
+            in_plugin = !source[open..].contains("}}");
+        }
+        if let Some(open) = source.rfind("/*") {
+            in_comment = !source[open..].contains("*/");
+        }
+
+        // Byte positions stay valid: masked text is replaced by spaces
+        let text = NOT_TEXT.replace_all(source, |caps: &regex::Captures| " ".repeat(caps[0].len()));
+        if options.no_raw_urls && !LINK_DEFINITION.is_match(source) {
+            check_raw_urls(&text, number, issues);
+        }
+        let text = RAW_URL.replace_all(&text, |caps: &regex::Captures| " ".repeat(caps[0].len()));
+        if let Some(style) = emphasis_style.as_mut() {
+            check_emphasis(source, &text, style, number, issues);
+        }
+        if options.no_trailing_whitespace {
+            check_trailing_whitespace(source, lines.get(index + 1), number, issues);
+        }
+    }
+}
+
+fn check_trailing_whitespace(
+    source: &str,
+    next: Option<&&str>,
+    number: usize,
+    issues: &mut Vec<LintIssue>,
+) {
+    let content_len = source.trim_end_matches([' ', '\t']).len();
+    let trailing = &source[content_len..];
+    let hard_break =
+        trailing == "  " && content_len > 0 && next.is_some_and(|next| !next.trim().is_empty());
+    if trailing.is_empty() || hard_break {
+        return;
+    }
+    issues.push(LintIssue::new(
+        "lint-trailing-whitespace",
+        "Trailing whitespace",
+        number,
+        Some(Fix {
+            line: number,
+            range: content_len..source.len(),
+            replacement: String::new(),
+        }),
+    ));
+}
+
+fn check_raw_urls(text: &str, number: usize, issues: &mut Vec<LintIssue>) {
+    for found in RAW_URL.find_iter(text) {
+        // Sentence punctuation after a URL is not part of it
+        let url = found
+            .as_str()
+            .trim_end_matches(['.', ',', ';', ':', '!', '?']);
+        issues.push(LintIssue::new(
+            "lint-raw-url",
+            format!("Bare URL `{}`; write it as `<{}>` or a link", url, url),
+            number,
+            Some(Fix {
+                line: number,
+                range: found.start()..found.start() + url.len(),
+                replacement: format!("<{}>", url),
+            }),
+        ));
+    }
+}
+
+fn check_emphasis(
+    source: &str,
+    text: &str,
+    style: &mut EmphasisStyle,
+    number: usize,
+    issues: &mut Vec<LintIssue>,
+) {
+    let is_word = |ch: Option<char>| ch.is_some_and(|ch| ch.is_alphanumeric() || ch == '_');
+    let mut found: Vec<(Range<usize>, EmphasisStyle, bool)> = Vec::new();
+    for candidate in ASTERISK_EMPHASIS.find_iter(text) {
+        let before = text[..candidate.start()].chars().next_back();
+        let after = text[candidate.end()..].chars().next();
+        if before != Some('*') && after != Some('*') && before != Some('\\') {
+            // `_` cannot emphasize inside a word
+            let swappable = !is_word(before) && !is_word(after);
+            found.push((candidate.range(), EmphasisStyle::Asterisk, swappable));
+        }
+    }
+    for candidate in UNDERSCORE_EMPHASIS.find_iter(text) {
+        let before = text[..candidate.start()].chars().next_back();
+        let after = text[candidate.end()..].chars().next();
+        if !is_word(before) && !is_word(after) && before != Some('\\') {
+            found.push((candidate.range(), EmphasisStyle::Underscore, true));
+        }
+    }
+    found.sort_by_key(|(range, _, _)| range.start);
+
+    for (range, used, swappable) in found {
+        if *style == EmphasisStyle::Consistent {
+            *style = used;
+            continue;
+        }
+        if used == *style {
+            continue;
+        }
+        let (marker, expected) = match used {
+            EmphasisStyle::Underscore => ('_', '*'),
+            _ => ('*', '_'),
+        };
+        let fix = swappable.then(|| Fix {
+            line: number,
+            range: range.clone(),
+            replacement: format!(
+                "{}{}{}",
+                expected,
+                &source[range.start + 1..range.end - 1],
+                expected
+            ),
+        });
+        issues.push(LintIssue::new(
+            "lint-emphasis-style",
+            format!(
+                "Emphasis uses `{}`; this document uses `{}`",
+                marker, expected
+            ),
+            number,
+            fix,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(input: &str, options: &LintOptions) -> Vec<(&'static str, Option<usize>)> {
+        lint_with_options(input, options)
+            .iter()
+            .map(|issue| (issue.diagnostic.code, issue.diagnostic.line))
+            .collect()
+    }
+
+    #[test]
+    fn test_block_rules() {
+        let options = LintOptions {
+            max_heading_depth: Some(2),
+            ..Default::default()
+        };
+        let input = "---\ntitle: x\n---\n\n# A\n\n### Deep\n\n![](a.png)\n\n|a|b|\n|c|d|\n";
+        assert_eq!(
+            codes(input, &options),
+            [
+                ("lint-heading-depth", Some(7)),
+                ("lint-img-alt", Some(9)),
+                ("lint-table-header", Some(11)),
+            ]
+        );
+        let fixed = apply_fixes(input, &lint_with_options(input, &options));
+        assert!(fixed.contains("\n## Deep\n"), "{}", fixed);
+    }
+
+    #[test]
+    fn test_text_rules_skip_code_and_links() {
+        let input = "```\ntext  \t\nhttps://a.example _x_ *y*\n```\n\n\
+                     [site](https://a.example) <https://b.example> `https://c.example`\n\
+                     @youtube(https://d.example)\n\n[ref]: https://e.example\n\n\
+                     Line with break  \nnext snake_case_name\n";
+        assert!(codes(input, &LintOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn test_emphasis_style() {
+        let input = "*a* and _b_\n\nx*y*z and _c_\n";
+        let issues = lint_with_options(input, &LintOptions::default());
+        let lines: Vec<_> = issues.iter().map(|issue| issue.diagnostic.line).collect();
+        assert_eq!(lines, [Some(1), Some(3)]);
+        assert_eq!(
+            apply_fixes(input, &issues),
+            "*a* and *b*\n\nx*y*z and *c*\n"
+        );
+
+        let options = LintOptions {
+            emphasis_style: Some(EmphasisStyle::Underscore),
+            ..Default::default()
+        };
+        let issues = lint_with_options(input, &options);
+        assert_eq!(issues.len(), 2);
+        // Intraword `*` cannot become `_`
+        assert!(issues[1].fix.is_none());
+        assert_eq!(
+            apply_fixes(input, &issues),
+            "_a_ and _b_\n\nx*y*z and _c_\n"
+        );
+    }
+
+    #[test]
+    fn test_disabled_rules() {
+        let options = LintOptions {
+            require_alt_text: false,
+            no_raw_urls: false,
+            emphasis_style: None,
+            require_table_header: false,
+            no_trailing_whitespace: false,
+            ..Default::default()
+        };
+        assert!(
+            codes(
+                "###### h\n\n![](a.png) https://x.example *a* _b_ \n\n|a|\n",
+                &options
+            )
+            .is_empty()
+        );
+    }
+}
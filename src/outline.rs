@@ -0,0 +1,372 @@
+//! Document outline
+//!
+//! [`outline`] reads the heading structure of a document straight from the
+//! source, without rendering it: the heading tree for sidebar navigation,
+//! plus word counts and the kinds of content (tables, code, lists, images)
+//! under each heading for content-quality dashboards.
+//!
+//! Headings are ATX headings and one-line setext headings at the top level
+//! of the document. Headings in blockquotes and lists do not appear in the
+//! tree but are counted for the `h-N` IDs, which follow the numbering of
+//! the renderer (a `{#id}` attribute gives `h-id`; repeated IDs get a `-1`,
+//! `-2`, ... suffix). Words are counted with Unicode word boundaries, so
+//! each CJK ideograph counts as one word; code, comments, frontmatter,
+//! link targets, plugin names and block decoration prefixes are not counted.
+
+use std::collections::HashSet;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::extensions::attributes::split_trailing_attributes;
+use crate::frontmatter::extract_frontmatter;
+
+/// ATX heading: hashes and text (closing hashes included)
+static ATX_HEADING: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^ {0,3}(#{1,6})(?:[ \t]+(.*?))?[ \t]*$").unwrap());
+
+/// Heading inside blockquotes (`> ## Title`)
+static QUOTED_HEADING: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^ {0,3}(?:>[ \t]?)+ {0,3}#{1,6}(?:[ \t]|$)").unwrap());
+
+/// Setext heading underline
+static SETEXT_UNDERLINE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^ {0,3}(=+|-+)[ \t]*$").unwrap());
+
+/// List item
+static LIST_ITEM: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[ \t]*(?:[-+*]|\d{1,9}[.)])[ \t]+\S").unwrap());
+
+/// Markup that is not prose: link targets, plugin and inline decoration
+/// names with their arguments, block decoration prefixes
+static NOT_PROSE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"\]\([^)]*\)|[&@]{1,2}[A-Za-z_][\w-]*(?:\((?:[^()]|\([^()]*\))*\))?|(?:SIZE\([^)]+\)|COLOR\((?:[^()\n]|\([^()\n]*\))*\)|LANG\([^)]*\)|RTL|LTR|BDI|TRUNCATE|TOP|MIDDLE|BOTTOM|BASELINE|JUSTIFY|RIGHT|CENTER|LEFT):",
+    )
+    .unwrap()
+});
+
+/// Inline markup around heading text
+static INLINE_MARKUP: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"!?\[([^\]]*)\]\([^)]*\)|&[A-Za-z_][\w-]*(?:\([^)]*\))?\{([^{}]*)\};|\*\*|~~|''+|%%|__|`",
+    )
+    .unwrap()
+});
+
+/// What the content of a section contains
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SectionContent {
+    /// Words of prose (headings of subsections excluded)
+    pub word_count: usize,
+    /// A table (GFM or UMD)
+    pub has_table: bool,
+    /// A fenced code block
+    pub has_code: bool,
+    /// A list
+    pub has_list: bool,
+    /// A Markdown image
+    pub has_image: bool,
+}
+
+/// A heading and the subsections below it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutlineHeading {
+    /// Heading level (1-6)
+    pub level: u8,
+    /// Plain-text title
+    pub title: String,
+    /// Anchor ID of the rendered heading
+    pub id: String,
+    /// 1-based source line (counting frontmatter lines)
+    pub line: usize,
+    /// Content up to the next heading
+    pub content: SectionContent,
+    /// Headings nested below this one
+    pub children: Vec<OutlineHeading>,
+}
+
+impl OutlineHeading {
+    /// Words of this section and all its subsections, titles excluded
+    pub fn total_word_count(&self) -> usize {
+        self.content.word_count
+            + self
+                .children
+                .iter()
+                .map(OutlineHeading::total_word_count)
+                .sum::<usize>()
+    }
+}
+
+/// Heading tree of a document
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocumentOutline {
+    /// Content before the first heading
+    pub intro: SectionContent,
+    /// Top-level headings (each with its nested headings)
+    pub headings: Vec<OutlineHeading>,
+    /// Words of prose in the whole document, titles excluded
+    pub word_count: usize,
+}
+
+/// Read the heading tree of a document
+///
+/// # Arguments
+///
+/// * `input` - The Universal Markdown source text
+///
+/// # Returns
+///
+/// The outline; a heading nests under the closest preceding heading of a
+/// lower level
+///
+/// # Examples
+///
+/// ```
+/// use umd::outline::outline;
+///
+/// let doc = outline("# Guide\n\nIntro text.\n\n## Setup {#setup}\n\n```sh\nmake\n```\n\n## Usage\n");
+/// let guide = &doc.headings[0];
+/// assert_eq!(guide.title, "Guide");
+/// assert_eq!(guide.id, "h-1");
+/// assert_eq!(guide.content.word_count, 2);
+/// assert_eq!(guide.children[0].id, "h-setup");
+/// assert!(guide.children[0].content.has_code);
+/// assert_eq!(guide.children[1].line, 11);
+/// ```
+pub fn outline(input: &str) -> DocumentOutline {
+    let (_, body) = extract_frontmatter(input);
+    let first_line = input[..input.len() - body.len()].matches('\n').count() + 1;
+    let lines: Vec<&str> = body.lines().collect();
+
+    let mut intro = SectionContent::default();
+    let mut flat: Vec<OutlineHeading> = Vec::new();
+    let mut ids = HashSet::new();
+    let mut heading_counter = 0;
+    let mut fence: Option<(char, usize)> = None;
+    let mut in_plugin = false;
+    let mut in_comment = false;
+    let mut after_blank = true;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim();
+        let content = flat
+            .last_mut()
+            .map_or(&mut intro, |heading| &mut heading.content);
+
+        let unquoted = trimmed.trim_start_matches(['>', ' ', '\t']);
+
+        if let Some((fence_char, fence_len)) = fence {
+            let run = unquoted.chars().take_while(|c| *c == fence_char).count();
+            if run >= fence_len && unquoted[run..].trim().is_empty() {
+                fence = None;
+            }
+            i += 1;
+            continue;
+        }
+        if in_comment {
+            in_comment = !line.contains("*/");
+            i += 1;
+            continue;
+        }
+        if in_plugin {
+            in_plugin = !line.contains("}}");
+            content.word_count += count_words(line);
+            i += 1;
+            continue;
+        }
+
+        let fence_char = unquoted.chars().next().filter(|c| *c == '`' || *c == '~');
+        if let Some(ch) = fence_char {
+            let run = unquoted.chars().take_while(|c| *c == ch).count();
+            if run >= 3 {
+                fence = Some((ch, run));
+                content.has_code = true;
+                after_blank = false;
+                i += 1;
+                continue;
+            }
+        }
+
+        let heading = if let Some(caps) = ATX_HEADING.captures(line) {
+            Some((
+                caps[1].len() as u8,
+                caps.get(2).map_or("", |text| text.as_str()),
+                1,
+            ))
+        } else if after_blank
+            && !trimmed.is_empty()
+            && !line.starts_with([' ', '\t'])
+            && !trimmed.starts_with(['>', '|', '@', '-', '*', '+', '<', ':', '['])
+            && !trimmed.contains("{{")
+            && let Some(caps) = lines
+                .get(i + 1)
+                .and_then(|next| SETEXT_UNDERLINE.captures(next))
+        {
+            let level = if caps[1].starts_with('=') { 1 } else { 2 };
+            Some((level, trimmed, 2))
+        } else {
+            None
+        };
+
+        if let Some((level, text, consumed)) = heading {
+            heading_counter += 1;
+            let (title, id) = heading_title(text);
+            let id = unique_id(
+                &mut ids,
+                format!("h-{}", id.unwrap_or_else(|| heading_counter.to_string())),
+            );
+            flat.push(OutlineHeading {
+                level,
+                title,
+                id,
+                line: first_line + i,
+                content: SectionContent::default(),
+                children: Vec::new(),
+            });
+            after_blank = false;
+            i += consumed;
+            continue;
+        }
+
+        if QUOTED_HEADING.is_match(line) {
+            heading_counter += 1;
+            ids.insert(format!("h-{}", heading_counter));
+        }
+        if unquoted.starts_with('|') {
+            content.has_table = true;
+        }
+        if LIST_ITEM.is_match(unquoted) {
+            content.has_list = true;
+        }
+        if line.contains("![") {
+            content.has_image = true;
+        }
+        if let Some(open) = line.rfind("/*") {
+            in_comment = !line[open..].contains("*/");
+        }
+        let prose = match line.find("/*") {
+            Some(open) => &line[..open],
+            None => line,
+        };
+        content.word_count += count_words(prose);
+        if let Some(open) = line.rfind("{{") {
+            in_plugin = !line[open..].contains("}}");
+        }
+
+        after_blank = trimmed.is_empty();
+        i += 1;
+    }
+
+    let word_count = intro.word_count
+        + flat
+            .iter()
+            .map(|heading| heading.content.word_count)
+            .sum::<usize>();
+    let mut flat = flat.into_iter().peekable();
+    DocumentOutline {
+        intro,
+        headings: nest(&mut flat, 0),
+        word_count,
+    }
+}
+
+/// Build the headings deeper than `parent_level` into a tree
+fn nest(
+    flat: &mut std::iter::Peekable<impl Iterator<Item = OutlineHeading>>,
+    parent_level: u8,
+) -> Vec<OutlineHeading> {
+    let mut headings = Vec::new();
+    while let Some(mut heading) = flat.next_if(|heading| heading.level > parent_level) {
+        heading.children = nest(flat, heading.level);
+        headings.push(heading);
+    }
+    headings
+}
+
+/// Plain-text title and custom ID of a heading
+fn heading_title(text: &str) -> (String, Option<String>) {
+    // Closing hashes are dropped when a space precedes them
+    let without_closing = text.trim_end_matches('#');
+    let text = if without_closing.is_empty() || without_closing.ends_with([' ', '\t']) {
+        without_closing.trim_end()
+    } else {
+        text
+    };
+    let (text, id) = match split_trailing_attributes(text) {
+        Some((text, attributes)) => (text, attributes.id),
+        None => (text, None),
+    };
+    let title = INLINE_MARKUP.replace_all(text, |caps: &regex::Captures| {
+        caps.get(1)
+            .or_else(|| caps.get(2))
+            .map_or(String::new(), |inner| inner.as_str().to_string())
+    });
+    (title.split_whitespace().collect::<Vec<_>>().join(" "), id)
+}
+
+/// `id`, or `id-1`, `id-2`, ... when it is already taken
+fn unique_id(ids: &mut HashSet<String>, id: String) -> String {
+    let mut unique = id.clone();
+    let mut suffix = 0;
+    while ids.contains(&unique) {
+        suffix += 1;
+        unique = format!("{}-{}", id, suffix);
+    }
+    ids.insert(unique.clone());
+    unique
+}
+
+fn count_words(line: &str) -> usize {
+    NOT_PROSE.replace_all(line, " ").unicode_words().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nesting() {
+        let doc = outline("Intro\n\n### Deep\n\n# A\n\n## B\n\n#### C\n\n## D\n\n# E\n");
+        let shape: Vec<_> = doc
+            .headings
+            .iter()
+            .map(|heading| (heading.title.as_str(), heading.children.len()))
+            .collect();
+        assert_eq!(shape, [("Deep", 0), ("A", 2), ("E", 0)]);
+        assert_eq!(doc.headings[1].children[0].children[0].title, "C");
+        assert_eq!(doc.intro.word_count, 1);
+    }
+
+    #[test]
+    fn test_ids_follow_renderer_numbering() {
+        let doc = outline(
+            "---\ntitle: x\n---\n\nTitle\n=====\n\n> # Quoted\n\n## **Bold** [link](/x) {#same}\n\n## Again {#same}\n",
+        );
+        let ids: Vec<_> = doc.headings[0]
+            .children
+            .iter()
+            .map(|heading| heading.id.as_str())
+            .collect();
+        assert_eq!(doc.headings[0].id, "h-1");
+        assert_eq!(doc.headings[0].line, 5);
+        assert_eq!(ids, ["h-same", "h-same-1"]);
+        assert_eq!(doc.headings[0].children[0].title, "Bold link");
+    }
+
+    #[test]
+    fn test_section_content() {
+        let doc = outline(
+            "# A\n\nCOLOR(red): 日本語 text [site](https://example.com)\n\n|a|b|\n\n- item\n\n/* hidden words */\n\n```\n# not a heading\n```\n\n# B\n\n![alt](x.png)\n",
+        );
+        let a = &doc.headings[0].content;
+        assert_eq!(a.word_count, 8);
+        assert!(a.has_table && a.has_list && a.has_code && !a.has_image);
+        assert_eq!(doc.headings.len(), 2);
+        assert!(doc.headings[1].content.has_image);
+        assert_eq!(doc.word_count, 9);
+        assert_eq!(doc.headings[0].total_word_count(), 8);
+    }
+}
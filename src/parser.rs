@@ -3,9 +3,16 @@
 //! This module provides the core parsing functionality using comrak as the base
 //! Markdown parser, with extensions for Universal Markdown-specific syntax.
 
-use comrak::options::{ListStyleType, Plugins};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use comrak::nodes::{AstNode, NodeValue};
+use comrak::options::{ListStyleType, Plugins, URLRewriter};
 use comrak::{Arena, Options, format_html_with_plugins, parse_document};
 
+use crate::cache::Cache;
+use crate::extensions::media::MediaType;
+
 /// Icon markup configuration
 ///
 /// Values can include raw HTML (e.g. `<span class="bi bi-camera-video-fill"></span>`).
@@ -25,6 +32,28 @@ pub struct Icons {
     /// Icon markup rendered inside the inline color swatch.
     /// Default: `<span class="bi bi-palette-fill" aria-hidden="true"></span>`
     pub color_swatch: String,
+    /// Icon appended to external links (used when `ExternalLinkOptions.icon` is set).
+    /// Default: `<span class="bi bi-box-arrow-up-right" aria-hidden="true"></span>`
+    pub external_link: String,
+    /// Icon of `[!NOTE]` alerts (used when `AlertOptions.icons` is set).
+    /// Default: inline Bootstrap Icons `info-circle-fill` SVG
+    pub alert_note: String,
+    /// Icon of `[!TIP]` alerts. Default: inline `lightbulb-fill` SVG
+    pub alert_tip: String,
+    /// Icon of `[!IMPORTANT]` alerts. Default: inline `exclamation-circle-fill` SVG
+    pub alert_important: String,
+    /// Icon of `[!WARNING]` alerts. Default: inline `exclamation-triangle-fill` SVG
+    pub alert_warning: String,
+    /// Icon of `[!CAUTION]` alerts. Default: inline `exclamation-octagon-fill` SVG
+    pub alert_caution: String,
+}
+
+/// Inline 16x16 Bootstrap Icons SVG (decorative, `aria-hidden`)
+fn bootstrap_svg_icon(name: &str, path: &str) -> String {
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="16" fill="currentColor" class="bi bi-{}" viewBox="0 0 16 16" aria-hidden="true"><path d="{}"/></svg>"#,
+        name, path
+    )
 }
 
 impl Default for Icons {
@@ -39,10 +68,771 @@ impl Default for Icons {
                     .to_string(),
             color_swatch: r#"<span class="bi bi-palette-fill" aria-hidden="true"></span>"#
                 .to_string(),
+            external_link: r#"<span class="bi bi-box-arrow-up-right" aria-hidden="true"></span>"#
+                .to_string(),
+            alert_note: bootstrap_svg_icon(
+                "info-circle-fill",
+                "M8 16A8 8 0 1 0 8 0a8 8 0 0 0 0 16m.93-9.412-1 4.705c-.07.34.029.533.304.533.194 0 .487-.07.686-.246l-.088.416c-.287.346-.92.598-1.465.598-.703 0-1.002-.422-.808-1.319l.738-3.468c.064-.293.006-.399-.287-.47l-.451-.081.082-.381 2.29-.287zM8 5.5a1 1 0 1 1 0-2 1 1 0 0 1 0 2",
+            ),
+            alert_tip: bootstrap_svg_icon(
+                "lightbulb-fill",
+                "M2 6a6 6 0 1 1 10.174 4.31c-.203.196-.359.4-.453.619l-.762 1.769A.5.5 0 0 1 10.5 13h-5a.5.5 0 0 1-.46-.302l-.761-1.77a2 2 0 0 0-.453-.618A5.98 5.98 0 0 1 2 6m3 8.5a.5.5 0 0 1 .5-.5h5a.5.5 0 0 1 0 1l-.224.447a1 1 0 0 1-.894.553H6.618a1 1 0 0 1-.894-.553L5.5 15a.5.5 0 0 1-.5-.5",
+            ),
+            alert_important: bootstrap_svg_icon(
+                "exclamation-circle-fill",
+                "M16 8A8 8 0 1 1 0 8a8 8 0 0 1 16 0M8 4a.905.905 0 0 0-.9.995l.35 3.507a.552.552 0 0 0 1.1 0l.35-3.507A.905.905 0 0 0 8 4m.002 6a1 1 0 1 0 0 2 1 1 0 0 0 0-2",
+            ),
+            alert_warning: bootstrap_svg_icon(
+                "exclamation-triangle-fill",
+                "M8.982 1.566a1.13 1.13 0 0 0-1.96 0L.165 13.233c-.457.778.091 1.767.98 1.767h13.713c.889 0 1.438-.99.98-1.767zM8 5c.535 0 .954.462.9.995l-.35 3.507a.552.552 0 0 1-1.1 0L7.1 5.995A.905.905 0 0 1 8 5m.002 6a1 1 0 1 1 0 2 1 1 0 0 1 0-2",
+            ),
+            alert_caution: bootstrap_svg_icon(
+                "exclamation-octagon-fill",
+                "M11.46.146A.5.5 0 0 0 11.107 0H4.893a.5.5 0 0 0-.353.146L.146 4.54A.5.5 0 0 0 0 4.893v6.214a.5.5 0 0 0 .146.353l4.394 4.394a.5.5 0 0 0 .353.146h6.214a.5.5 0 0 0 .353-.146l4.394-4.394a.5.5 0 0 0 .146-.353V4.893a.5.5 0 0 0-.146-.353zM8 4c.535 0 .954.462.9.995l-.35 3.507a.552.552 0 0 1-1.1 0L7.1 4.995A.905.905 0 0 1 8 4m.002 6a1 1 0 1 1 0 2 1 1 0 0 1 0-2",
+            ),
+        }
+    }
+}
+
+/// Autolink configuration
+///
+/// Host entries match the host itself and all of its subdomains
+/// (`example.com` also matches `docs.example.com`).
+#[derive(Debug, Clone)]
+pub struct AutolinkOptions {
+    /// Link bare domains such as `example.com/docs` (in addition to the GFM
+    /// autolinks for `www.`, `https://` and mail addresses).
+    /// Default: `true`
+    pub bare_domains: bool,
+    /// Hosts allowed to be autolinked. Empty means every host is allowed.
+    pub allow: Vec<String>,
+    /// Hosts never autolinked; wins over `allow`.
+    pub deny: Vec<String>,
+}
+
+impl Default for AutolinkOptions {
+    fn default() -> Self {
+        Self {
+            bare_domains: true,
+            allow: Vec::new(),
+            deny: Vec::new(),
+        }
+    }
+}
+
+/// ARIA configuration for generated markup
+///
+/// Labels are plain text; they are attribute-escaped when emitted.
+#[derive(Debug, Clone)]
+pub struct AriaOptions {
+    /// `aria-label` for heading anchor links; `{title}` is replaced by the
+    /// heading text. `None` keeps the anchors `aria-hidden`.
+    /// Default: `Some("Link to section: {title}")`
+    pub heading_anchor_label: Option<String>,
+    /// `aria-label` for footnote back-references. `None` keeps comrak's
+    /// "Back to reference N". Default: `Some("Back to content")`
+    pub footnote_backref_label: Option<String>,
+    /// `aria-live` politeness for GFM alerts (`off`, `polite` or `assertive`).
+    /// Default: `None` (the implicit politeness of `role="alert"`)
+    pub alert_live: Option<String>,
+}
+
+impl Default for AriaOptions {
+    fn default() -> Self {
+        Self {
+            heading_anchor_label: Some("Link to section: {title}".to_string()),
+            footnote_backref_label: Some("Back to content".to_string()),
+            alert_live: None,
+        }
+    }
+}
+
+impl AriaOptions {
+    /// Japanese labels (to go with [`Locale::ja`])
+    pub fn ja() -> Self {
+        Self {
+            heading_anchor_label: Some("{title} へのリンク".to_string()),
+            footnote_backref_label: Some("本文へ戻る".to_string()),
+            alert_live: None,
+        }
+    }
+}
+
+/// Generated UI strings
+///
+/// Text the parser emits on its own (alert labels, media link labels,
+/// fallback messages), so that it matches the document language.
+/// Labels are plain text; they are escaped when emitted.
+///
+/// # Examples
+///
+/// ```
+/// use umd::parser::{Locale, ParserOptions};
+///
+/// let options = ParserOptions {
+///     locale: Locale::ja(),
+///     ..ParserOptions::default()
+/// };
+/// assert_eq!(options.locale.warning, "警告");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Locale {
+    /// Label of `[!NOTE]` alerts. Default: `"Note"`
+    pub note: String,
+    /// Label of `[!TIP]` alerts. Default: `"Tip"`
+    pub tip: String,
+    /// Label of `[!IMPORTANT]` alerts. Default: `"Important"`
+    pub important: String,
+    /// Label of `[!WARNING]` alerts. Default: `"Warning"`
+    pub warning: String,
+    /// Label of `[!CAUTION]` alerts. Default: `"Caution"`
+    pub caution: String,
+    /// `aria-label` of media fallback and download links; `{name}` is
+    /// replaced by the link text. Default: `"Download {name}"`
+    pub download: String,
+    /// Caption of Mermaid diagrams that could not be rendered.
+    /// Default: `"Diagram could not be rendered"`
+    pub mermaid_error: String,
+    /// `aria-label` of the warning icon on links to internationalized
+    /// domains. Default: `"Internationalized domain warning"`
+    pub idn_warning: String,
+    /// `aria-label` of the `@toc` navigation. Default: `"Table of contents"`
+    pub toc: String,
+    /// `title` of `@youtube` / `@vimeo` / `@niconico` iframes without a title
+    /// argument; `{name}` is replaced by the service name. Default:
+    /// `"{name} video"`
+    pub video_title: String,
+    /// `title` / `alt` of `@map` embeds without a title argument.
+    /// Default: `"Map"`
+    pub map_title: String,
+    /// `aria-label` of `&qr` / `@qr` codes; `{text}` is replaced by the
+    /// encoded text. Default: `"QR code: {text}"`
+    pub qr_code: String,
+    /// Label prepended to spoilers in the email-safe profile.
+    /// Default: `"(spoiler)"`
+    pub spoiler: String,
+}
+
+impl Locale {
+    /// English strings (default)
+    pub fn en() -> Self {
+        Self {
+            note: "Note".to_string(),
+            tip: "Tip".to_string(),
+            important: "Important".to_string(),
+            warning: "Warning".to_string(),
+            caution: "Caution".to_string(),
+            download: "Download {name}".to_string(),
+            mermaid_error: "Diagram could not be rendered".to_string(),
+            idn_warning: "Internationalized domain warning".to_string(),
+            toc: "Table of contents".to_string(),
+            video_title: "{name} video".to_string(),
+            map_title: "Map".to_string(),
+            qr_code: "QR code: {text}".to_string(),
+            spoiler: "(spoiler)".to_string(),
+        }
+    }
+
+    /// Japanese strings
+    pub fn ja() -> Self {
+        Self {
+            note: "注記".to_string(),
+            tip: "ヒント".to_string(),
+            important: "重要".to_string(),
+            warning: "警告".to_string(),
+            caution: "注意".to_string(),
+            download: "{name} をダウンロード".to_string(),
+            mermaid_error: "図を表示できませんでした".to_string(),
+            idn_warning: "国際化ドメイン名の警告".to_string(),
+            toc: "目次".to_string(),
+            video_title: "{name} の動画".to_string(),
+            map_title: "地図".to_string(),
+            qr_code: "QR コード: {text}".to_string(),
+            spoiler: "（ネタバレ）".to_string(),
+        }
+    }
+
+    /// Built-in strings for a language tag (`en`, `ja`, `ja-JP`, ...)
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let language = tag.split(['-', '_']).next().unwrap_or_default();
+        match language.to_ascii_lowercase().as_str() {
+            "en" => Some(Self::en()),
+            "ja" => Some(Self::ja()),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self::en()
+    }
+}
+
+/// GFM alert (`> [!NOTE]`) rendering
+#[derive(Debug, Clone, Default)]
+pub struct AlertOptions {
+    /// Render the label as a `<p class="alert-heading">` above the body
+    /// instead of an inline `<strong>Note:</strong>` prefix. Default: `false`
+    pub heading: bool,
+    /// Embed the alert type icon (`Icons.alert_*`) before the label, like
+    /// GitHub does. Default: `false`
+    pub icons: bool,
+}
+
+/// Marking of links to other sites (see [`crate::extensions::external_links`])
+#[derive(Debug, Clone)]
+pub struct ExternalLinkOptions {
+    /// Append `Icons.external_link` inside the link. Default: `false`
+    pub icon: bool,
+    /// `rel` attribute. Default: `Some("noopener noreferrer")`
+    pub rel: Option<String>,
+    /// `target` attribute, e.g. `"_blank"`. Default: `None`
+    pub target: Option<String>,
+}
+
+impl Default for ExternalLinkOptions {
+    fn default() -> Self {
+        Self {
+            icon: false,
+            rel: Some("noopener noreferrer".to_string()),
+            target: None,
         }
     }
 }
 
+/// How the link anchor of each heading is rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AnchorStyle {
+    /// Empty `<a class="anchor">` before the heading text (default)
+    #[default]
+    Empty,
+    /// `<a class="anchor">` showing `AnchorOptions.symbol` after the text
+    Permalink,
+    /// The heading text wrapped in the `<a class="anchor">` link
+    Wrap,
+    /// `id` on the heading element, without a link
+    IdOnly,
+    /// Neither a link nor an `id`
+    None,
+}
+
+/// Where table captions are shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CaptionSide {
+    /// Below the table (the browser and Bootstrap default)
+    #[default]
+    Bottom,
+    /// Above the table, through Bootstrap's `caption-top` class
+    Top,
+}
+
+/// Scroll wrapper around tables
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResponsiveTables {
+    /// No wrapper (default)
+    #[default]
+    Off,
+    /// `<div class="table-responsive">`: scroll at every width
+    Always,
+    /// `<div class="table-responsive-sm">`: scroll below 576px
+    Sm,
+    /// `<div class="table-responsive-md">`: scroll below 768px
+    Md,
+    /// `<div class="table-responsive-lg">`: scroll below 992px
+    Lg,
+    /// `<div class="table-responsive-xl">`: scroll below 1200px
+    Xl,
+    /// `<div class="table-responsive-xxl">`: scroll below 1400px
+    Xxl,
+}
+
+/// Type of a plugin argument
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PluginArgType {
+    /// Any text (default)
+    #[default]
+    Text,
+    /// Whole number, e.g. `10` or `-1`
+    Integer,
+    /// Decimal number, e.g. `1.5`
+    Number,
+    /// URL without whitespace and with an allowed scheme
+    Url,
+}
+
+/// A named plugin parameter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginParam {
+    /// Name shown in diagnostics
+    pub name: String,
+    /// Expected type
+    pub kind: PluginArgType,
+}
+
+/// Expected usage of a plugin, checked at parse time
+///
+/// ```
+/// use umd::parser::{PluginArgType, PluginSignature};
+///
+/// // `@feed(url)` or `@feed(url, count)`
+/// let feed = PluginSignature::new(
+///     "feed",
+///     &[("url", PluginArgType::Url), ("count", PluginArgType::Integer)],
+/// )
+/// .with_args(1, Some(2));
+/// assert_eq!(feed.min_args, 1);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginSignature {
+    /// Plugin name (`feed` for `@feed(...)` and `&feed(...);`)
+    pub name: String,
+    /// Parameters in order; arguments past the last one are not type-checked
+    pub params: Vec<PluginParam>,
+    /// Fewest arguments accepted
+    pub min_args: usize,
+    /// Most arguments accepted, `None` for no limit
+    pub max_args: Option<usize>,
+}
+
+impl PluginSignature {
+    /// Signature whose parameters are all required
+    pub fn new(name: &str, params: &[(&str, PluginArgType)]) -> Self {
+        Self {
+            name: name.to_string(),
+            params: params
+                .iter()
+                .map(|(name, kind)| PluginParam {
+                    name: name.to_string(),
+                    kind: *kind,
+                })
+                .collect(),
+            min_args: params.len(),
+            max_args: Some(params.len()),
+        }
+    }
+
+    /// Set the accepted argument count range
+    pub fn with_args(mut self, min_args: usize, max_args: Option<usize>) -> Self {
+        self.min_args = min_args;
+        self.max_args = max_args;
+        self
+    }
+}
+
+/// Rendering of `@map(lat, lon[, zoom[, title]])`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum MapEmbed {
+    /// Generic plugin template (default)
+    #[default]
+    Off,
+    /// OpenStreetMap iframe in a 4:3 wrapper
+    OpenStreetMap,
+    /// Static map `<img>`; `{lat}`, `{lon}` and `{zoom}` in the URL template
+    /// are replaced by the plugin arguments
+    StaticImage(String),
+}
+
+/// Heading anchor rendering
+#[derive(Debug, Clone)]
+pub struct AnchorOptions {
+    /// Anchor style. Default: `AnchorStyle::Empty`
+    pub style: AnchorStyle,
+    /// Link text of `AnchorStyle::Permalink`. Default: `"#"`
+    pub symbol: String,
+}
+
+impl Default for AnchorOptions {
+    fn default() -> Self {
+        Self {
+            style: AnchorStyle::default(),
+            symbol: "#".to_string(),
+        }
+    }
+}
+
+/// Footnote section rendering
+#[derive(Debug, Clone)]
+pub struct FootnoteOptions {
+    /// Heading text placed at the top of the footnotes section
+    /// (e.g. "References"). Default: `None` (no heading)
+    pub heading: Option<String>,
+    /// Level of the heading (1-6). Default: `2`
+    pub heading_level: u8,
+    /// Number of the first footnote. Default: `1`
+    pub start: usize,
+    /// Text of the back-reference links. Default: `"↩"`
+    pub backref_symbol: String,
+    /// Extra class(es) on the footnotes `<section>`. Default: `None`
+    pub class: Option<String>,
+    /// Add the plain text of each note to its references as
+    /// `data-footnote-content`, for hover previews. Default: `false`
+    pub tooltips: bool,
+    /// Where the footnotes section goes. Default: `Separate`
+    pub placement: FootnotePlacement,
+}
+
+/// Where the footnotes section is placed in the result
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FootnotePlacement {
+    /// In [`ParseResult::footnotes`](crate::ParseResult::footnotes), apart
+    /// from the body (default)
+    #[default]
+    Separate,
+    /// At the end of the body HTML; `ParseResult::footnotes` is `None`
+    Inline,
+}
+
+impl Default for FootnoteOptions {
+    fn default() -> Self {
+        Self {
+            heading: None,
+            heading_level: 2,
+            start: 1,
+            backref_symbol: "↩".to_string(),
+            class: None,
+            tooltips: false,
+            placement: FootnotePlacement::Separate,
+        }
+    }
+}
+
+/// Class name configuration for generated markup
+///
+/// Applied to every `class` attribute in the output, including the
+/// Bootstrap utility classes emitted by decorations.
+#[derive(Debug, Clone, Default)]
+pub struct ClassOptions {
+    /// Prefix prepended to every class name not found in `map`
+    /// (`language-*` and `syntect-*` code classes are never prefixed).
+    /// Default: `None`
+    pub prefix: Option<String>,
+    /// Class name replacements. A value may contain several space-separated
+    /// classes; an empty value removes the class.
+    pub map: HashMap<String, String>,
+}
+
+impl ClassOptions {
+    /// Whether the options leave class names unchanged
+    pub fn is_identity(&self) -> bool {
+        self.prefix.as_deref().is_none_or(str::is_empty) && self.map.is_empty()
+    }
+}
+
+/// CSS framework profile for decoration classes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Framework {
+    /// Bootstrap 5 utility and component classes (default)
+    #[default]
+    Bootstrap5,
+    /// Framework-agnostic HTML: utility classes become data attributes
+    /// (`data-align`, `data-size`, `data-color`, ...)
+    Semantic,
+    /// Bootstrap class names replaced through a custom map
+    Custom(HashMap<String, String>),
+}
+
+/// Markup dialect accepted by the parser
+///
+/// Each dialect is a superset of the previous one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Dialect {
+    /// Plain CommonMark: no GFM or UMD syntax, raw HTML omitted
+    CommonMark,
+    /// CommonMark with GFM tables, strikethrough, autolinks, task lists and
+    /// footnotes
+    Gfm,
+    /// GFM with UMD extensions, without the LukiWiki legacy emphasis
+    /// (`''bold''`, `'''italic'''`, `%%strike%%`)
+    Umd,
+    /// UMD with LukiWiki legacy emphasis (default)
+    #[default]
+    LukiWiki,
+}
+
+/// Tags emitted for LukiWiki `''bold''` / `'''italic'''` emphasis
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EmphasisTags {
+    /// `<b>` / `<i>`: visual styling only (default)
+    #[default]
+    Presentational,
+    /// `<strong>` / `<em>`: importance and stress emphasis
+    Semantic,
+}
+
+/// How generated element IDs (Mermaid diagrams, popovers) are chosen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdStrategy {
+    /// Random UUID-based IDs (default)
+    Random,
+    /// IDs derived from the element content, so the same input always
+    /// renders the same HTML (default in this crate's unit tests)
+    ContentHash,
+}
+
+impl Default for IdStrategy {
+    fn default() -> Self {
+        if cfg!(test) {
+            IdStrategy::ContentHash
+        } else {
+            IdStrategy::Random
+        }
+    }
+}
+
+/// Layout of the rendered HTML
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// comrak's layout, one block per line (default)
+    #[default]
+    Compact,
+    /// Block elements on their own lines, indented by nesting depth
+    Pretty,
+    /// No whitespace between block elements
+    Minified,
+}
+
+/// Serialization of void elements and attributes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HtmlSyntax {
+    /// HTML5 syntax: `<br>`, `<img src="...">`
+    Html5,
+    /// Well-formed XHTML: `<br />`, `controls=""`, numeric character
+    /// references
+    Xhtml,
+}
+
+/// Target medium of the rendered HTML
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Profile {
+    /// Web pages styled with the selected framework (default)
+    #[default]
+    Web,
+    /// EPUB content documents: semantic classes, footnotes as
+    /// `epub:type="footnote"` asides, captioned figures and XHTML
+    /// (see [`crate::extensions::epub`])
+    Epub,
+    /// Newsletters and other mail: no templates or script attributes,
+    /// visible spoilers, linked media and inline styles
+    /// (see [`crate::extensions::email`])
+    EmailSafe,
+}
+
+/// How raw HTML written in the document is treated
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum TrustLevel {
+    /// Every raw HTML tag is escaped (default)
+    #[default]
+    Untrusted,
+    /// Tags on the allow-list pass through with their allowed attributes;
+    /// everything else is still escaped (see
+    /// [`sanitize_trusted`](crate::sanitizer::sanitize_trusted)). Only for
+    /// documents written by trusted editors
+    Trusted(HtmlAllowList),
+}
+
+/// Raw HTML tags and attributes passed through by [`TrustLevel::Trusted`]
+///
+/// Tags that run scripts or restyle the page (`script`, `style`, `object`,
+/// `meta`, ...) and `on*` / `style` attributes are never passed through,
+/// even when listed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HtmlAllowList {
+    /// Lowercase tag names
+    pub tags: Vec<String>,
+    /// Lowercase attribute names; an entry ending in `*` (`data-*`) allows
+    /// every name with that prefix. `href` / `src` values are checked with
+    /// [`sanitize_url`](crate::sanitizer::sanitize_url)
+    pub attributes: Vec<String>,
+}
+
+impl Default for HtmlAllowList {
+    /// Inline text markup and `<iframe>` embeds
+    fn default() -> Self {
+        let list = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        Self {
+            tags: list(&[
+                "sup", "sub", "small", "mark", "kbd", "abbr", "ins", "del", "s", "u", "br", "wbr",
+                "span", "iframe",
+            ]),
+            attributes: list(&[
+                "class",
+                "id",
+                "title",
+                "lang",
+                "dir",
+                "src",
+                "width",
+                "height",
+                "allow",
+                "allowfullscreen",
+                "loading",
+                "referrerpolicy",
+            ]),
+        }
+    }
+}
+
+/// Wikilink syntax parsed by comrak
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WikiLinks {
+    /// `[[...]]` is left to UMD (default)
+    #[default]
+    Off,
+    /// `[[url|title]]`
+    TitleAfterPipe,
+    /// `[[title|url]]`
+    TitleBeforePipe,
+}
+
+/// comrak extensions passed through to the underlying parser
+///
+/// These constructs are parsed by comrak itself rather than by UMD passes.
+/// Everything except `tasklist` is off by default.
+///
+/// # Examples
+///
+/// ```
+/// use umd::parser::{ComrakExtensions, ParserOptions};
+///
+/// let options = ParserOptions {
+///     comrak: ComrakExtensions {
+///         superscript: true,
+///         ..ComrakExtensions::default()
+///     },
+///     ..ParserOptions::default()
+/// };
+/// let html = umd::parse_with_frontmatter_opts("e = mc^2^", &options).html;
+/// assert!(html.contains("mc<sup>2</sup>"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComrakExtensions {
+    /// Prefix of comrak's heading anchors (`<a class="anchor" id="{prefix}slug">`).
+    /// Only used by the `CommonMark` and `Gfm` dialects; UMD dialects
+    /// generate their own heading IDs. Default: `None`
+    pub header_ids: Option<String>,
+    /// GFM task list items (`- [x] done`). Default: `true`
+    pub tasklist: bool,
+    /// Strikethrough written with single tildes (`~text~`) as well as
+    /// `~~text~~`. Has no effect when `subscript` is on. Default: `true`
+    pub single_tilde_strikethrough: bool,
+    /// `~subscript~`; `~~text~~` stays strikethrough. Default: `false`
+    pub subscript: bool,
+    /// `^superscript^`. Default: `false`
+    pub superscript: bool,
+    /// Description lists (a term line followed by `: details`). Default: `false`
+    pub description_lists: bool,
+    /// Block quotes fenced with `>>>` lines. Default: `false`
+    pub multiline_block_quotes: bool,
+    /// `__underline__` as `<u>` (always on in UMD dialects). Default: `false`
+    pub underline: bool,
+    /// Wikilinks. Default: `WikiLinks::Off`
+    pub wikilinks: WikiLinks,
+}
+
+impl Default for ComrakExtensions {
+    fn default() -> Self {
+        Self {
+            header_ids: None,
+            tasklist: true,
+            single_tilde_strikethrough: true,
+            subscript: false,
+            superscript: false,
+            description_lists: false,
+            multiline_block_quotes: false,
+            underline: false,
+            wikilinks: WikiLinks::Off,
+        }
+    }
+}
+
+/// How plugin arguments and content are written into `<template>` elements
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PluginContent {
+    /// `<data>` children for the arguments followed by the escaped content
+    /// (default)
+    #[default]
+    Escaped,
+    /// Empty templates carrying the arguments and content as base64 in
+    /// `data-umd-args` / `data-umd-content`, for frontends that cannot be
+    /// trusted to treat the template children as text
+    Encoded,
+}
+
+/// `loading` attribute of rendered images
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImageLoading {
+    /// `loading="lazy"`: fetched when scrolled near (default)
+    #[default]
+    Lazy,
+    /// `loading="eager"`: fetched right away
+    Eager,
+}
+
+/// `decoding` attribute of rendered images
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageDecoding {
+    /// `decoding="async"`: decode off the main thread
+    Async,
+    /// `decoding="sync"`: decode together with the surrounding content
+    Sync,
+    /// `decoding="auto"`: the browser decides
+    Auto,
+}
+
+/// `fetchpriority` attribute of rendered images
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FetchPriority {
+    /// `fetchpriority="high"`
+    High,
+    /// `fetchpriority="low"`
+    Low,
+    /// `fetchpriority="auto"`
+    Auto,
+}
+
+/// Loading hints on the `<img>` of rendered images
+///
+/// A single image is made eager with `{eager}` after it
+/// (`![Hero](hero.jpg){eager}`), which also gives it `fetchpriority="high"`;
+/// `{lazy}` makes it lazy when the default is eager.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImageOptions {
+    /// `loading` attribute. Default: `ImageLoading::Lazy`
+    pub loading: ImageLoading,
+    /// `decoding` attribute; omitted when `None` (default)
+    pub decoding: Option<ImageDecoding>,
+    /// `fetchpriority` attribute; omitted when `None` (default)
+    pub fetch_priority: Option<FetchPriority>,
+}
+
+/// File extensions recognized as media, on top of the built-in table
+///
+/// Extensions are lowercase and written without the dot. The icons of
+/// video, audio and download links are set in [`Icons`].
+#[derive(Debug, Clone, Default)]
+pub struct MediaOptions {
+    /// Media type of extra extensions (`heic` as `MediaType::Image`,
+    /// `ipynb` as `MediaType::Downloadable`); takes precedence over the
+    /// built-in table
+    pub types: HashMap<String, MediaType>,
+    /// MIME type written into `<source type="...">` for an extension;
+    /// takes precedence over the built-in mapping
+    pub mime_types: HashMap<String, String>,
+}
+
+/// Color handling for light / dark themes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorMode {
+    /// Colors are emitted as authored (default)
+    #[default]
+    Fixed,
+    /// HEX colors become palette variables with dark-mode alternates
+    /// ([`ParseResult::color_palette`](crate::ParseResult::color_palette)) and
+    /// Bootstrap theme colors use their `-emphasis` / `-subtle` variants
+    Adaptive,
+}
+
 /// Parser configuration for Universal Markdown
 #[derive(Debug, Clone)]
 pub struct ParserOptions {
@@ -50,8 +840,38 @@ pub struct ParserOptions {
     pub gfm_extensions: bool,
     /// Enable Universal Markdown-specific extensions
     pub umd_extensions: bool,
-    /// Maximum heading level (1-5 for Universal Markdown, 1-6 for standard Markdown)
+    /// Deepest heading level in the output; deeper headings are clamped to
+    /// it (after `heading_offset` is applied). Default: `6`
     pub max_heading_level: u8,
+    /// Number of levels every heading is shifted down, e.g. `1` when the
+    /// page template already renders the `<h1>`. Default: `0`
+    pub heading_offset: u8,
+    /// Prefix headings with hierarchical numbers (`1.`, `1.1`, `1.1.1`)
+    /// written into the HTML. Default: `false`
+    pub number_headings: bool,
+    /// Mark tables with a header row as `data-sortable` and give their header
+    /// cells a `data-type` (`number`, `date`, `text`) inferred from the
+    /// column. Default: `false`
+    pub sortable_tables: bool,
+    /// Side of the table that captions are shown on. Default:
+    /// `CaptionSide::Bottom`
+    pub caption_side: CaptionSide,
+    /// Wrap every table in a `table-responsive` scroll container. Tables
+    /// already wrapped by `@table(responsive)` are left alone. Default:
+    /// `ResponsiveTables::Off`
+    pub responsive_tables: ResponsiveTables,
+    /// Render `@youtube(id)`, `@vimeo(id)` and `@niconico(id)` as
+    /// privacy-enhanced, lazily loaded iframes in a 16:9 wrapper instead of
+    /// plugin templates. Enable only when the host permits iframes.
+    /// Default: `false`
+    pub video_embeds: bool,
+    /// Render `@map(lat, lon[, zoom[, title]])` as an OpenStreetMap iframe or
+    /// a static map image. Default: `MapEmbed::Off`
+    pub map_embed: MapEmbed,
+    /// Render for print / PDF export: spoilers revealed, `<details>` open,
+    /// border-only alerts, popover content inline, and media and embeds
+    /// replaced by links. Default: `false`
+    pub print_mode: bool,
     /// Base URL for resolving absolute paths (e.g., "/umd-core", "https://example.com/app")
     /// If set, absolute paths (starting with "/") will be prefixed with this base URL
     pub base_url: Option<String>,
@@ -67,6 +887,135 @@ pub struct ParserOptions {
     pub max_inline_nesting: Option<u8>,
     /// Icon configuration (media fallback links and inline code enhancements)
     pub icons: Icons,
+    /// `loading`, `decoding` and `fetchpriority` of images. Default: lazy
+    /// loading without the other two attributes
+    pub images: ImageOptions,
+    /// Extra media file extensions and MIME types
+    pub media: MediaOptions,
+    /// Autolink configuration (bare domains, allow/deny lists)
+    pub autolink: AutolinkOptions,
+    /// URL template of `&attach(file.pdf){label};` links; `{name}` is
+    /// replaced by the percent-encoded file name (`/files/{name}`).
+    /// `&attach` is an ordinary plugin when `None` (default)
+    pub attachment_url: Option<String>,
+    /// Origin of the site (`https://example.com`); links to any other
+    /// origin are external. Default: `None` (every `http(s)` link is external)
+    pub site_origin: Option<String>,
+    /// Mark external links with `class="external"`, `rel` / `target` and an
+    /// optional icon. Default: `None` (links are left alone)
+    pub external_links: Option<ExternalLinkOptions>,
+    /// URL prefix for `@username` mentions (`{mention_base}/username`).
+    /// Mentions are not linked when `None` (default).
+    pub mention_base: Option<String>,
+    /// URL prefix for `#tag` hashtags (`{tag_base}/tag`).
+    /// Hashtags are not linked when `None` (default).
+    pub tag_base: Option<String>,
+    /// Report accessibility problems (missing alt text, heading level jumps,
+    /// non-descriptive link text, ...) as diagnostics. Default: `false`
+    pub a11y_audit: bool,
+    /// ARIA labels for heading anchors, footnotes and alerts
+    pub aria: AriaOptions,
+    /// Light / dark color handling. Default: `ColorMode::Fixed`
+    pub color_mode: ColorMode,
+    /// CSS framework profile (applied before `classes`)
+    pub framework: Framework,
+    /// Class prefix and remapping for emitted class names
+    pub classes: ClassOptions,
+    /// Classes for custom size keywords: `SIZE(huge):` emits `umd-size-huge`,
+    /// which is replaced by `font_sizes["huge"]` when present
+    pub font_sizes: HashMap<String, String>,
+    /// Markup dialect. Default: `Dialect::LukiWiki` (all syntax)
+    pub dialect: Dialect,
+    /// Tags for LukiWiki `''bold''` / `'''italic'''`. Default:
+    /// `EmphasisTags::Presentational` (`<b>` / `<i>`)
+    pub emphasis_tags: EmphasisTags,
+    /// Footnote section heading, numbering, back-reference symbol and class
+    pub footnotes: FootnoteOptions,
+    /// Generated element IDs. Default: `IdStrategy::Random`
+    /// (`IdStrategy::ContentHash` in unit tests)
+    pub ids: IdStrategy,
+    /// GFM alert heading markup and icons
+    pub alerts: AlertOptions,
+    /// Generated UI strings (alert labels, media link labels, ...).
+    /// Default: `Locale::en()`
+    pub locale: Locale,
+    /// Site-level frontmatter merged beneath each document's frontmatter
+    /// (see [`Frontmatter::merge_defaults`](crate::frontmatter::Frontmatter::merge_defaults)).
+    /// Default: `None`
+    pub default_frontmatter: Option<crate::frontmatter::Frontmatter>,
+    /// Heading anchor rendering. Default: empty anchor before the text
+    pub anchors: AnchorOptions,
+    /// Names of the `@name` / `&name` plugins that may be used, including
+    /// built-in ones such as `color` or `detail`. `None` (default) allows all
+    pub allowed_plugins: Option<Vec<String>>,
+    /// Names of plugins that may not be used; takes precedence over
+    /// `allowed_plugins`. Rejected plugins are shown as their escaped source
+    /// text and reported as `plugin-denied` diagnostics
+    pub denied_plugins: Vec<String>,
+    /// Expected arguments of host plugins. Plugins used with the wrong
+    /// number or type of arguments are still rendered, and reported as
+    /// `plugin-arguments` diagnostics. Default: empty
+    pub plugin_signatures: Vec<PluginSignature>,
+    /// Plugin template markup. `PluginContent::Encoded` keeps markup out of
+    /// the template entirely. Default: `PluginContent::Escaped`
+    pub plugin_content: PluginContent,
+    /// Raw HTML handling. `TrustLevel::Trusted` passes the tags of its
+    /// allow-list through comrak's unsafe mode. Default: `TrustLevel::Untrusted`
+    pub trust_level: TrustLevel,
+    /// Syntax parsed by comrak itself (task lists, superscript, wikilinks, ...)
+    pub comrak: ComrakExtensions,
+    /// Store of rendered documents consulted before parsing, keyed by the
+    /// input and the other options (see [`crate::cache`]). Default: `None`
+    pub cache: Option<Arc<dyn Cache>>,
+    /// Layout of the body and footnotes HTML (see
+    /// [`crate::extensions::output_format`]). Default: `OutputFormat::Compact`
+    pub output_format: OutputFormat,
+    /// Serialize the output as HTML5 or XHTML (see
+    /// [`crate::extensions::serialization`]). Default: `None` (each pass's
+    /// own markup, which mixes `<br>` and `<br />`)
+    pub html_syntax: Option<HtmlSyntax>,
+    /// Target medium; `Epub` overrides `framework`, `footnotes.placement`
+    /// and the default `html_syntax`, `EmailSafe` implies `print_mode`.
+    /// Default: `Profile::Web`
+    pub profile: Profile,
+    /// Place `<span id="L42"></span>` before top-level blocks, named after
+    /// their source line, at least this many lines apart (see
+    /// [`crate::extensions::line_anchors`]). UMD dialects only. Default: `None`
+    pub line_anchors: Option<usize>,
+}
+
+impl ParserOptions {
+    /// Whether GFM syntax is parsed (`gfm_extensions` and the dialect)
+    pub fn gfm_enabled(&self) -> bool {
+        self.gfm_extensions && self.dialect != Dialect::CommonMark
+    }
+
+    /// Whether UMD syntax is parsed (`umd_extensions` and the dialect)
+    pub fn umd_enabled(&self) -> bool {
+        self.umd_extensions && matches!(self.dialect, Dialect::Umd | Dialect::LukiWiki)
+    }
+
+    /// Whether LukiWiki legacy emphasis (`''`, `'''`, `%%`) is parsed
+    pub fn lukiwiki_enabled(&self) -> bool {
+        self.umd_enabled() && self.dialect == Dialect::LukiWiki
+    }
+
+    /// HTML syntax of the output: `html_syntax`, or XHTML for the EPUB profile
+    pub fn effective_html_syntax(&self) -> Option<HtmlSyntax> {
+        match self.profile {
+            Profile::Epub => self.html_syntax.or(Some(HtmlSyntax::Xhtml)),
+            Profile::Web | Profile::EmailSafe => self.html_syntax,
+        }
+    }
+
+    /// Whether the plugin `name` passes `allowed_plugins` and `denied_plugins`
+    pub fn is_plugin_allowed(&self, name: &str) -> bool {
+        !self.denied_plugins.iter().any(|denied| denied == name)
+            && self
+                .allowed_plugins
+                .as_ref()
+                .is_none_or(|allowed| allowed.iter().any(|plugin| plugin == name))
+    }
 }
 
 impl Default for ParserOptions {
@@ -74,11 +1023,52 @@ impl Default for ParserOptions {
         Self {
             gfm_extensions: true,
             umd_extensions: true,
-            max_heading_level: 5,
+            max_heading_level: 6,
+            heading_offset: 0,
+            number_headings: false,
+            sortable_tables: false,
+            caption_side: CaptionSide::Bottom,
+            responsive_tables: ResponsiveTables::Off,
+            video_embeds: false,
+            map_embed: MapEmbed::Off,
+            print_mode: false,
             base_url: None,
             allow_fragment_extension_hint: false,
             max_inline_nesting: Some(5),
             icons: Icons::default(),
+            images: ImageOptions::default(),
+            media: MediaOptions::default(),
+            autolink: AutolinkOptions::default(),
+            attachment_url: None,
+            site_origin: None,
+            external_links: None,
+            mention_base: None,
+            tag_base: None,
+            a11y_audit: false,
+            aria: AriaOptions::default(),
+            color_mode: ColorMode::default(),
+            framework: Framework::default(),
+            classes: ClassOptions::default(),
+            font_sizes: HashMap::new(),
+            dialect: Dialect::default(),
+            emphasis_tags: EmphasisTags::default(),
+            footnotes: FootnoteOptions::default(),
+            ids: IdStrategy::default(),
+            alerts: AlertOptions::default(),
+            locale: Locale::default(),
+            default_frontmatter: None,
+            anchors: AnchorOptions::default(),
+            allowed_plugins: None,
+            denied_plugins: Vec::new(),
+            plugin_signatures: Vec::new(),
+            plugin_content: PluginContent::Escaped,
+            trust_level: TrustLevel::Untrusted,
+            comrak: ComrakExtensions::default(),
+            cache: None,
+            output_format: OutputFormat::Compact,
+            html_syntax: None,
+            profile: Profile::Web,
+            line_anchors: None,
         }
     }
 }
@@ -109,22 +1099,47 @@ pub fn parse_to_html(input: &str, options: &ParserOptions) -> String {
     let mut comrak_options = Options::default();
 
     // Enable extensions
-    if options.gfm_extensions {
+    if options.gfm_enabled() {
         comrak_options.extension.strikethrough = true;
-        comrak_options.extension.tagfilter = true; // Disallow dangerous HTML tags
+        // Disallow dangerous HTML tags (trusted raw HTML is filtered by the sanitizer)
+        comrak_options.extension.tagfilter = options.trust_level == TrustLevel::Untrusted;
         comrak_options.extension.table = true;
         comrak_options.extension.autolink = true;
-        comrak_options.extension.tasklist = true;
+        comrak_options.extension.tasklist = options.comrak.tasklist;
         comrak_options.extension.footnotes = true; // Enable footnotes
-        comrak_options.extension.header_id_prefix = None; // Disable automatic IDs, we'll add them ourselves
     }
 
+    // Opt-in comrak syntax
+    let extensions = &options.comrak;
+    if !options.umd_enabled() {
+        // UMD dialects add heading IDs themselves
+        comrak_options.extension.header_id_prefix = extensions.header_ids.clone();
+    }
+    comrak_options.extension.subscript = extensions.subscript;
+    comrak_options.extension.superscript = extensions.superscript;
+    comrak_options.extension.description_lists = extensions.description_lists;
+    comrak_options.extension.multiline_block_quotes = extensions.multiline_block_quotes;
+    // Discord-style `__text__` underline is UMD syntax
+    comrak_options.extension.underline = extensions.underline || options.umd_enabled();
+    comrak_options.extension.wikilinks_title_after_pipe =
+        extensions.wikilinks == WikiLinks::TitleAfterPipe;
+    comrak_options.extension.wikilinks_title_before_pipe =
+        extensions.wikilinks == WikiLinks::TitleBeforePipe;
+
     // Render options
     comrak_options.render.hardbreaks = false;
     comrak_options.render.github_pre_lang = false; // Keep language on <code class="language-*"> (no lang on <pre>)
     comrak_options.render.full_info_string = true;
     comrak_options.render.width = 0;
-    comrak_options.render.r#unsafe = false; // Don't render raw HTML
+    // Raw HTML only reaches comrak unescaped when it passed the allow-list
+    comrak_options.render.r#unsafe = options.trust_level != TrustLevel::Untrusted;
+    if options.trust_level != TrustLevel::Untrusted {
+        // Unsafe mode also skips comrak's check of link and image URLs
+        let rewriter: Arc<dyn URLRewriter> =
+            Arc::new(|url: &str| crate::sanitizer::sanitize_url(url).into_owned());
+        comrak_options.extension.link_url_rewriter = Some(rewriter.clone());
+        comrak_options.extension.image_url_rewriter = Some(rewriter);
+    }
     comrak_options.render.escape = false;
     comrak_options.render.list_style = ListStyleType::Dash;
 
@@ -133,6 +1148,9 @@ pub fn parse_to_html(input: &str, options: &ParserOptions) -> String {
 
     // Parse markdown to AST
     let root = parse_document(&arena, input, &comrak_options);
+    if options.gfm_enabled() && !extensions.single_tilde_strikethrough && !extensions.subscript {
+        revert_single_tilde_strikethrough(&arena, root);
+    }
 
     // Render to HTML
     let mut html = String::new();
@@ -142,6 +1160,34 @@ pub fn parse_to_html(input: &str, options: &ParserOptions) -> String {
     html
 }
 
+/// Turn strikethrough written with single tildes (`~text~`) back into text
+///
+/// comrak accepts one or two tildes; the delimiter width is recovered from
+/// the source position of the first child.
+fn revert_single_tilde_strikethrough<'a>(arena: &'a Arena<'a>, root: &'a AstNode<'a>) {
+    let single: Vec<_> = root
+        .descendants()
+        .filter(|node| {
+            let ast = node.data.borrow();
+            matches!(ast.value, NodeValue::Strikethrough)
+                && node.first_child().is_some_and(|child| {
+                    let start = child.data.borrow().sourcepos.start;
+                    start.line == ast.sourcepos.start.line
+                        && start.column == ast.sourcepos.start.column + 1
+                })
+        })
+        .collect();
+
+    for node in single {
+        node.insert_before(arena.alloc(NodeValue::Text("~".into()).into()));
+        while let Some(child) = node.first_child() {
+            node.insert_before(child);
+        }
+        node.insert_before(arena.alloc(NodeValue::Text("~".into()).into()));
+        node.detach();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -249,6 +1295,95 @@ mod tests {
         assert!(html.contains("disabled"));
     }
 
+    #[test]
+    fn test_comrak_extensions() {
+        let mut options = ParserOptions {
+            comrak: ComrakExtensions {
+                header_ids: Some("user-".to_string()),
+                tasklist: false,
+                single_tilde_strikethrough: true,
+                subscript: false,
+                superscript: true,
+                description_lists: true,
+                multiline_block_quotes: true,
+                underline: true,
+                wikilinks: WikiLinks::TitleBeforePipe,
+            },
+            ..ParserOptions::default()
+        };
+        let html = parse_to_html(
+            "# Title\n\nx^2^ __u__ [[label|page]]\n\nTerm\n\n: Details\n\n>>>\nquote\n>>>\n\n- [x] done",
+            &options,
+        );
+        assert!(!html.contains("id=\"user-title\""), "{}", html);
+        assert!(html.contains("x<sup>2</sup> <u>u</u>"), "{}", html);
+        assert!(
+            html.contains(r#"<a href="page" data-wikilink="true">label</a>"#),
+            "{}",
+            html
+        );
+        assert!(html.contains("<dt>Term</dt>"), "{}", html);
+        assert!(html.contains("<blockquote>\n<p>quote</p>"), "{}", html);
+        assert!(html.contains("<li>[x] done</li>"), "{}", html);
+
+        options.dialect = Dialect::Gfm;
+        let html = parse_to_html("# Title", &options);
+        assert!(
+            html.contains(r#"class="anchor" id="user-title""#),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn test_single_tilde_strikethrough() {
+        let input = "~a~ ~~b~~ `~c~` H~2~O";
+        let html = parse_to_html(input, &ParserOptions::default());
+        assert!(html.contains("<del>a</del> <del>b</del> <code>~c~</code> H<del>2</del>O"));
+
+        let mut options = ParserOptions::default();
+        options.comrak.single_tilde_strikethrough = false;
+        let html = parse_to_html(input, &options);
+        assert!(
+            html.contains("~a~ <del>b</del> <code>~c~</code> H~2~O"),
+            "{}",
+            html
+        );
+        let html = parse_to_html("~**a** b~ ~~~x~~~", &options);
+        assert!(html.contains("~<strong>a</strong> b~"), "{}", html);
+
+        options.comrak.subscript = true;
+        let html = parse_to_html(input, &options);
+        assert!(
+            html.contains("<sub>a</sub> <del>b</del> <code>~c~</code> H<sub>2</sub>O"),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn test_underline() {
+        for (input, expected) in [
+            ("__a_b__", "<p><u>a_b</u></p>"),
+            ("__two\nlines__", "<p><u>two\nlines</u></p>"),
+            ("__`a_b`__", "<p><u><code>a_b</code></u></p>"),
+            ("`__code__`", "<p><code>__code__</code></p>"),
+            ("snake__case__name", "<p>snake__case__name</p>"),
+            (
+                "**strong** __u__",
+                "<p><strong>strong</strong> <u>u</u></p>",
+            ),
+        ] {
+            assert_eq!(crate::parse(input).trim(), expected, "{}", input);
+        }
+        // Plain Markdown keeps `__text__` as strong emphasis
+        let options = ParserOptions {
+            dialect: Dialect::Gfm,
+            ..ParserOptions::default()
+        };
+        assert!(parse_to_html("__a__", &options).contains("<strong>a</strong>"));
+    }
+
     #[test]
     fn test_video_media() {
         let input = "![Demo video](https://example.com/video.mp4)";
@@ -257,7 +1392,7 @@ mod tests {
         assert!(html.contains("<video controls"));
         assert!(html.contains("src=\"https://example.com/video.mp4\""));
         assert!(html.contains("type=\"video/mp4\""));
-        assert!(html.contains("<track kind=\"captions\" label=\"Demo video\""));
+        assert!(!html.contains("<track"));
     }
 
     #[test]
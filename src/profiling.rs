@@ -0,0 +1,128 @@
+//! Per-stage timings and tracing spans
+//!
+//! Every pipeline stage (preprocessing, sanitizing, comrak, each extension
+//! pass) runs through [`stage`]. With the `trace` feature, each stage is a
+//! `tracing` span named `umd_stage` with a `stage` field, so any subscriber
+//! can report hotspots. [`parse_with_timings`](crate::parse_with_timings)
+//! records the duration of every stage without a subscriber.
+//!
+//! Stages are not nested, so the durations add up to the time spent in the
+//! pipeline. [`parse_with_progress`](crate::parse_with_progress) reports each
+//! finished stage to a callback instead, for progress indicators.
+
+use std::cell::RefCell;
+use std::time::Duration;
+
+/// Time spent in one pipeline stage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StageTiming {
+    /// Stage name, e.g. `sanitize`, `comrak` or `extensions.inline_decorations`
+    pub stage: &'static str,
+    /// Wall-clock time spent in the stage
+    pub duration: Duration,
+}
+
+/// Callback receiving the name of each finished stage
+type Progress = Box<dyn FnMut(&'static str)>;
+
+thread_local! {
+    /// Timings of the parse running on this thread, while it is recorded
+    static TIMINGS: RefCell<Option<Vec<StageTiming>>> = const { RefCell::new(None) };
+
+    /// Progress callback of the parse running on this thread
+    static PROGRESS: RefCell<Option<Progress>> = const { RefCell::new(None) };
+}
+
+/// Run one pipeline stage
+///
+/// Enters a tracing span with the `trace` feature and records the duration
+/// when called inside [`record`].
+pub(crate) fn stage<T>(name: &'static str, run: impl FnOnce() -> T) -> T {
+    #[cfg(feature = "trace")]
+    let _span = tracing::debug_span!("umd_stage", stage = name).entered();
+
+    let value = if TIMINGS.with(|timings| timings.borrow().is_some()) {
+        // `Instant` is only touched while recording: it panics on wasm32
+        let start = std::time::Instant::now();
+        let value = run();
+        let duration = start.elapsed();
+        TIMINGS.with(|timings| {
+            if let Some(timings) = timings.borrow_mut().as_mut() {
+                timings.push(StageTiming {
+                    stage: name,
+                    duration,
+                });
+            }
+        });
+        value
+    } else {
+        run()
+    };
+    report_progress(name);
+    value
+}
+
+/// Call the progress callback, if any, with a finished stage
+fn report_progress(name: &'static str) {
+    // Taken out while it runs, so a callback that parses again neither
+    // re-enters itself nor reports the inner parse
+    let Some(mut progress) = PROGRESS.with(|progress| progress.borrow_mut().take()) else {
+        return;
+    };
+    progress(name);
+    PROGRESS.with(|slot| *slot.borrow_mut() = Some(progress));
+}
+
+/// Run `parse` and collect the timings of the stages it runs
+///
+/// Recording is per thread, so parses on other threads are not included.
+pub(crate) fn record<T>(parse: impl FnOnce() -> T) -> (T, Vec<StageTiming>) {
+    let outer = TIMINGS.with(|timings| timings.borrow_mut().replace(Vec::new()));
+    let value = parse();
+    let recorded = TIMINGS.with(|timings| std::mem::replace(&mut *timings.borrow_mut(), outer));
+    (value, recorded.unwrap_or_default())
+}
+
+/// Run `parse` and report every stage it finishes to `progress`
+///
+/// Reporting is per thread, like [`record`].
+pub(crate) fn observe<T>(
+    progress: impl FnMut(&'static str) + 'static,
+    parse: impl FnOnce() -> T,
+) -> T {
+    let outer = PROGRESS.with(|slot| slot.borrow_mut().replace(Box::new(progress)));
+    let value = parse();
+    PROGRESS.with(|slot| *slot.borrow_mut() = outer);
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stages_are_recorded_only_inside_record() {
+        assert_eq!(stage("outside", || 1), 1);
+        let (value, timings) = record(|| stage("a", || 2) + stage("b", || 3));
+        assert_eq!(value, 5);
+        let stages: Vec<&str> = timings.iter().map(|timing| timing.stage).collect();
+        assert_eq!(stages, ["a", "b"]);
+        assert!(TIMINGS.with(|timings| timings.borrow().is_none()));
+    }
+
+    #[test]
+    fn test_progress_is_reported_after_each_stage() {
+        use std::rc::Rc;
+
+        let stages = Rc::new(RefCell::new(Vec::new()));
+        let seen = Rc::clone(&stages);
+        let value = observe(
+            move |name| seen.borrow_mut().push(name),
+            || stage("a", || observe(|_| panic!("inner"), || 2)) + stage("b", || 3),
+        );
+        assert_eq!(value, 5);
+        assert_eq!(*stages.borrow(), ["a", "b"]);
+        stage("outside", || ());
+        assert_eq!(stages.borrow().len(), 2);
+    }
+}
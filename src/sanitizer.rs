@@ -6,6 +6,13 @@
 
 use std::borrow::Cow;
 
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use crate::html_builder::attr_escape;
+use crate::parser::HtmlAllowList;
+use crate::sections::VOID_ELEMENTS;
+
 /// Sanitizes a URL by blocking dangerous schemes
 ///
 /// # Arguments
@@ -50,12 +57,19 @@ use std::borrow::Cow;
 ///
 /// assert_eq!(sanitize_url("https://example.com"), "https://example.com");
 /// assert_eq!(sanitize_url("javascript:alert(1)"), "#blocked-url");
+/// assert_eq!(sanitize_url("&#106;ava\tscript:alert(1)"), "#blocked-url");
 /// assert_eq!(sanitize_url("data:text/html,<script>alert(1)</script>"), "#blocked-url");
 /// assert_eq!(sanitize_url("spotify:track:123"), "spotify:track:123"); // Custom app schemes allowed
 /// ```
 pub fn sanitize_url(url: &str) -> Cow<'_, str> {
     let normalized = remove_disallowed_blank_chars(url);
-    let url_lower = normalized.trim().to_lowercase();
+    // Compare the scheme the browser sees: character references decoded and
+    // whitespace / control characters (`java&#x09;script:`) removed
+    let url_lower: String = html_escape::decode_html_entities(&normalized)
+        .chars()
+        .filter(|ch| !ch.is_whitespace() && !ch.is_control())
+        .flat_map(char::to_lowercase)
+        .collect();
 
     // Check for dangerous schemes (case-insensitive)
     // TODO: Consider adding ParserOptions.allow_file_scheme configuration
@@ -71,6 +85,325 @@ pub fn sanitize_url(url: &str) -> Cow<'_, str> {
     normalized
 }
 
+/// Normalizes a text direction value for the `dir` attribute
+///
+/// Only the keywords defined by HTML (`ltr`, `rtl`, `auto`) are kept, so
+/// user-supplied directions (`&bdo(rtl){...};`, `RTL:`) can be emitted as
+/// attributes verbatim.
+///
+/// # Examples
+///
+/// ```
+/// use umd::sanitizer::sanitize_dir;
+///
+/// assert_eq!(sanitize_dir(" RTL "), Some("rtl"));
+/// assert_eq!(sanitize_dir("auto"), Some("auto"));
+/// assert_eq!(sanitize_dir("rtl\" onclick=\"x"), None);
+/// ```
+pub fn sanitize_dir(value: &str) -> Option<&'static str> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "ltr" => Some("ltr"),
+        "rtl" => Some("rtl"),
+        "auto" => Some("auto"),
+        _ => None,
+    }
+}
+
+/// Checks a `key=value` pair of an attribute block against the attribute
+/// whitelist
+///
+/// Allowed are `title`, `role`, `data-*` and `aria-*` (any value; it is
+/// escaped on output), `lang` (a language tag) and `dir` (see
+/// [`sanitize_dir`]). Event handlers, `style`, URL attributes and any other
+/// name are rejected.
+///
+/// # Examples
+///
+/// ```
+/// use umd::sanitizer::sanitize_attribute;
+///
+/// assert_eq!(sanitize_attribute("data-step", "2").as_deref(), Some("2"));
+/// assert_eq!(sanitize_attribute("dir", "RTL").as_deref(), Some("rtl"));
+/// assert_eq!(sanitize_attribute("lang", "en\" x=\"y"), None);
+/// assert_eq!(sanitize_attribute("onclick", "alert(1)"), None);
+/// assert_eq!(sanitize_attribute("style", "color: red"), None);
+/// ```
+pub fn sanitize_attribute<'a>(name: &str, value: &'a str) -> Option<Cow<'a, str>> {
+    let custom = |prefix: &str| {
+        name.strip_prefix(prefix).is_some_and(|rest| {
+            !rest.is_empty()
+                && rest
+                    .bytes()
+                    .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+        })
+    };
+    match name {
+        "title" | "role" => Some(Cow::Borrowed(value)),
+        "dir" => sanitize_dir(value).map(Cow::Borrowed),
+        "lang" => (!value.is_empty()
+            && value.len() <= 35
+            && value
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-'))
+        .then_some(Cow::Borrowed(value)),
+        _ if custom("data-") || custom("aria-") => Some(Cow::Borrowed(value)),
+        _ => None,
+    }
+}
+
+/// CSS named colors (CSS Color Module Level 4) plus `transparent` and `currentcolor`
+const CSS_NAMED_COLORS: &[&str] = &[
+    "aliceblue",
+    "antiquewhite",
+    "aqua",
+    "aquamarine",
+    "azure",
+    "beige",
+    "bisque",
+    "black",
+    "blanchedalmond",
+    "blue",
+    "blueviolet",
+    "brown",
+    "burlywood",
+    "cadetblue",
+    "chartreuse",
+    "chocolate",
+    "coral",
+    "cornflowerblue",
+    "cornsilk",
+    "crimson",
+    "currentcolor",
+    "cyan",
+    "darkblue",
+    "darkcyan",
+    "darkgoldenrod",
+    "darkgray",
+    "darkgreen",
+    "darkgrey",
+    "darkkhaki",
+    "darkmagenta",
+    "darkolivegreen",
+    "darkorange",
+    "darkorchid",
+    "darkred",
+    "darksalmon",
+    "darkseagreen",
+    "darkslateblue",
+    "darkslategray",
+    "darkslategrey",
+    "darkturquoise",
+    "darkviolet",
+    "deeppink",
+    "deepskyblue",
+    "dimgray",
+    "dimgrey",
+    "dodgerblue",
+    "firebrick",
+    "floralwhite",
+    "forestgreen",
+    "fuchsia",
+    "gainsboro",
+    "ghostwhite",
+    "gold",
+    "goldenrod",
+    "gray",
+    "green",
+    "greenyellow",
+    "grey",
+    "honeydew",
+    "hotpink",
+    "indianred",
+    "indigo",
+    "ivory",
+    "khaki",
+    "lavender",
+    "lavenderblush",
+    "lawngreen",
+    "lemonchiffon",
+    "lightblue",
+    "lightcoral",
+    "lightcyan",
+    "lightgoldenrodyellow",
+    "lightgray",
+    "lightgreen",
+    "lightgrey",
+    "lightpink",
+    "lightsalmon",
+    "lightseagreen",
+    "lightskyblue",
+    "lightslategray",
+    "lightslategrey",
+    "lightsteelblue",
+    "lightyellow",
+    "lime",
+    "limegreen",
+    "linen",
+    "magenta",
+    "maroon",
+    "mediumaquamarine",
+    "mediumblue",
+    "mediumorchid",
+    "mediumpurple",
+    "mediumseagreen",
+    "mediumslateblue",
+    "mediumspringgreen",
+    "mediumturquoise",
+    "mediumvioletred",
+    "midnightblue",
+    "mintcream",
+    "mistyrose",
+    "moccasin",
+    "navajowhite",
+    "navy",
+    "oldlace",
+    "olive",
+    "olivedrab",
+    "orange",
+    "orangered",
+    "orchid",
+    "palegoldenrod",
+    "palegreen",
+    "paleturquoise",
+    "palevioletred",
+    "papayawhip",
+    "peachpuff",
+    "peru",
+    "pink",
+    "plum",
+    "powderblue",
+    "purple",
+    "rebeccapurple",
+    "red",
+    "rosybrown",
+    "royalblue",
+    "saddlebrown",
+    "salmon",
+    "sandybrown",
+    "seagreen",
+    "seashell",
+    "sienna",
+    "silver",
+    "skyblue",
+    "slateblue",
+    "slategray",
+    "slategrey",
+    "snow",
+    "springgreen",
+    "steelblue",
+    "tan",
+    "teal",
+    "thistle",
+    "tomato",
+    "transparent",
+    "turquoise",
+    "violet",
+    "wheat",
+    "white",
+    "whitesmoke",
+    "yellow",
+    "yellowgreen",
+];
+
+/// Regex for `rgb()` / `rgba()` in comma or space syntax
+static CSS_RGB: Lazy<Regex> = Lazy::new(|| {
+    let channel = r"\d{1,3}(?:\.\d+)?%?";
+    let alpha = r"(?:\d*\.\d+|\d{1,3})%?";
+    Regex::new(&format!(
+        r"^rgba?\(\s*{c}\s*(?:,\s*{c}\s*,\s*{c}\s*(?:,\s*{a}\s*)?|\s{c}\s+{c}\s*(?:/\s*{a}\s*)?)\)$",
+        c = channel,
+        a = alpha
+    ))
+    .unwrap()
+});
+
+/// Regex for `hsl()` / `hsla()` in comma or space syntax
+static CSS_HSL: Lazy<Regex> = Lazy::new(|| {
+    let hue = r"-?\d{1,3}(?:\.\d+)?(?:deg)?";
+    let percent = r"\d{1,3}(?:\.\d+)?%";
+    let alpha = r"(?:\d*\.\d+|\d{1,3})%?";
+    Regex::new(&format!(
+        r"^hsla?\(\s*{h}\s*(?:,\s*{p}\s*,\s*{p}\s*(?:,\s*{a}\s*)?|\s{p}\s+{p}\s*(?:/\s*{a}\s*)?)\)$",
+        h = hue,
+        p = percent,
+        a = alpha
+    ))
+    .unwrap()
+});
+
+/// Validates a CSS color value for use in a `style` attribute
+///
+/// Accepted forms:
+///
+/// - HEX: `#rgb`, `#rgba`, `#rrggbb`, `#rrggbbaa`
+/// - `rgb()` / `rgba()` and `hsl()` / `hsla()`, comma or space separated
+/// - CSS named colors, `transparent` and `currentcolor`
+///
+/// The grammar is strict (digits, `%`, `deg`, `.`, `,`, `/` and whitespace
+/// inside functions), so the value cannot end the declaration and inject
+/// other properties. Function and color names are returned lowercased.
+///
+/// # Examples
+///
+/// ```
+/// use umd::sanitizer::sanitize_css_color;
+///
+/// assert_eq!(sanitize_css_color("#FF8800").as_deref(), Some("#FF8800"));
+/// assert_eq!(sanitize_css_color("RGB(255 0 0 / 50%)").as_deref(), Some("rgb(255 0 0 / 50%)"));
+/// assert_eq!(sanitize_css_color("hsl(210, 50%, 40%)").as_deref(), Some("hsl(210, 50%, 40%)"));
+/// assert_eq!(sanitize_css_color("RebeccaPurple").as_deref(), Some("rebeccapurple"));
+/// assert_eq!(sanitize_css_color("red; background: url(x)"), None);
+/// ```
+pub fn sanitize_css_color(value: &str) -> Option<String> {
+    let trimmed = value.trim();
+
+    if let Some(hex) = trimmed.strip_prefix('#') {
+        return (matches!(hex.len(), 3 | 4 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+            .then(|| trimmed.to_string());
+    }
+
+    let lower = trimmed.to_ascii_lowercase();
+    if CSS_NAMED_COLORS.contains(&lower.as_str())
+        || CSS_RGB.is_match(&lower)
+        || CSS_HSL.is_match(&lower)
+    {
+        return Some(lower);
+    }
+    None
+}
+
+/// Splits decoration arguments at commas outside parentheses
+///
+/// Keeps color functions intact: `rgb(255,0,0), #fff` yields
+/// `["rgb(255,0,0)", "#fff"]`. Parts are trimmed.
+///
+/// # Examples
+///
+/// ```
+/// use umd::sanitizer::split_args;
+///
+/// assert_eq!(split_args("rgb(255,0,0), #fff"), ["rgb(255,0,0)", "#fff"]);
+/// assert_eq!(split_args(",red"), ["", "red"]);
+/// ```
+pub fn split_args(args: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, ch) in args.char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(args[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(args[start..].trim());
+    parts
+}
+
 /// Sanitizes input text by escaping HTML tags while preserving HTML entities
 /// and removing disallowed invisible blank-like characters.
 ///
@@ -98,6 +431,82 @@ pub fn sanitize_url(url: &str) -> Cow<'_, str> {
 /// assert_eq!(output, "Hello&nbsp;World &lt;tag&gt;");
 /// ```
 pub fn sanitize(input: &str) -> Cow<'_, str> {
+    sanitize_lines(input, escape_html_into)
+}
+
+/// [`sanitize`] for trusted authors: raw HTML tags on `allow_list` pass
+/// through, everything else is escaped
+///
+/// Passed tags are re-serialized: names are lowercased, attribute values
+/// are double-quoted and attribute-escaped, and `href` / `src` values
+/// go through [`sanitize_url`]. A tag is escaped as a whole when it has an
+/// attribute outside the allow-list, a blocked URL, or a value holding
+/// UMD marker braces (`{{` / `}}`) that later passes would expand. An end
+/// tag passes only when it closes a passed start tag. Start tags carry a
+/// `data-umd-raw` marker for [`protect_trusted_tags`].
+///
+/// # Examples
+///
+/// ```
+/// use umd::parser::HtmlAllowList;
+/// use umd::sanitizer::sanitize_trusted;
+///
+/// let allow_list = HtmlAllowList::default();
+/// assert_eq!(
+///     sanitize_trusted("x<SUP class=n>2</sup> <b>y</b>", &allow_list),
+///     "x<sup data-umd-raw class=\"n\">2</sup> &lt;b&gt;y&lt;/b&gt;"
+/// );
+/// assert_eq!(
+///     sanitize_trusted("<span onclick=\"x()\">a</span>", &allow_list),
+///     "&lt;span onclick=\"x()\"&gt;a&lt;/span&gt;"
+/// );
+/// assert_eq!(
+///     sanitize_trusted("<iframe src=\"javascript:alert(1)\"></iframe>", &allow_list),
+///     "&lt;iframe src=\"javascript:alert(1)\"&gt;&lt;/iframe&gt;"
+/// );
+/// ```
+pub fn sanitize_trusted<'a>(input: &'a str, allow_list: &HtmlAllowList) -> Cow<'a, str> {
+    // Elements opened by passed start tags, across lines
+    let mut open: Vec<String> = Vec::new();
+    sanitize_lines(input, |source, result| {
+        let mut rest = source;
+        while let Some(start) = rest.find('<') {
+            escape_html_into(&rest[..start], result);
+            let passed = allowed_tag(&rest[start..], allow_list).filter(|(_, tag)| {
+                match tag.strip_prefix("</") {
+                    Some(end) => {
+                        let name = end.trim_end_matches('>');
+                        let Some(depth) = open.iter().rposition(|open| open == name) else {
+                            return false;
+                        };
+                        open.truncate(depth);
+                    }
+                    None => {
+                        let name = tag[1..].split(' ').next().unwrap_or_default();
+                        if !tag.ends_with(" />") && !VOID_ELEMENTS.contains(&name) {
+                            open.push(name.to_string());
+                        }
+                    }
+                }
+                true
+            });
+            match passed {
+                Some((len, tag)) => {
+                    result.push_str(&tag);
+                    rest = &rest[start + len..];
+                }
+                None => {
+                    result.push_str("&lt;");
+                    rest = &rest[start + 1..];
+                }
+            }
+        }
+        escape_html_into(rest, result);
+    })
+}
+
+/// Run `escape` on each line of `input`, keeping blockquote markers
+fn sanitize_lines(input: &str, mut escape: impl FnMut(&str, &mut String)) -> Cow<'_, str> {
     let normalized = remove_disallowed_blank_chars(input);
     let source = normalized.as_ref();
 
@@ -107,6 +516,254 @@ pub fn sanitize(input: &str) -> Cow<'_, str> {
     }
 
     let mut result = String::with_capacity(source.len() + 32);
+    for line in source.split_inclusive('\n') {
+        // Blockquote markers (`> `, `> > `, `- > `) stay Markdown syntax
+        let (prefix, rest) = line.split_at(blockquote_prefix_len(line));
+        result.push_str(prefix);
+        escape(rest, &mut result);
+    }
+
+    Cow::Owned(result)
+}
+
+/// Attribute marking the tags passed by [`sanitize_trusted`] until
+/// [`protect_trusted_tags`] takes them out of the rendered HTML
+const TRUSTED_TAG_MARKER: &str = " data-umd-raw";
+
+/// Start tag passed by [`sanitize_trusted`], as rendered by comrak
+static TRUSTED_TAG: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"<([a-z][a-z0-9]*) data-umd-raw([^>]*)>").unwrap());
+
+/// Placeholder of a protected trusted start tag
+static TRUSTED_TAG_PLACEHOLDER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"UMD_RAW_HTML_(\d+)_END").unwrap());
+
+/// Replace the start tags passed by [`sanitize_trusted`] with placeholders
+///
+/// Run right after comrak, so the extension passes (spoilers, autolinks,
+/// emphasis, ...) cannot rewrite text inside their attribute values.
+/// [`restore_trusted_tags`] puts them back.
+///
+/// # Examples
+///
+/// ```
+/// use umd::sanitizer::{protect_trusted_tags, restore_trusted_tags};
+///
+/// let (html, tags) = protect_trusted_tags(r#"<p><span data-umd-raw title="a ||b||">x</span></p>"#);
+/// assert_eq!(html, "<p>UMD_RAW_HTML_0_ENDx</span></p>");
+/// assert_eq!(
+///     restore_trusted_tags(&html, &tags),
+///     r#"<p><span title="a ||b||">x</span></p>"#
+/// );
+/// ```
+pub fn protect_trusted_tags(html: &str) -> (Cow<'_, str>, Vec<String>) {
+    let mut tags = Vec::new();
+    if !html.contains(TRUSTED_TAG_MARKER) {
+        return (Cow::Borrowed(html), tags);
+    }
+    let protected = TRUSTED_TAG.replace_all(html, |caps: &Captures| {
+        tags.push(format!("<{}{}>", &caps[1], &caps[2]));
+        format!("UMD_RAW_HTML_{}_END", tags.len() - 1)
+    });
+    (protected, tags)
+}
+
+/// Restore the tags protected by [`protect_trusted_tags`]
+///
+/// Placeholders that ended up inside another tag (a heading's
+/// `aria-label`, for example) are dropped instead of restored.
+pub fn restore_trusted_tags<'a>(html: &'a str, tags: &[String]) -> Cow<'a, str> {
+    if tags.is_empty() {
+        return Cow::Borrowed(html);
+    }
+    let mut result = String::with_capacity(html.len());
+    let mut last = 0;
+    for caps in TRUSTED_TAG_PLACEHOLDER.captures_iter(html) {
+        let placeholder = caps.get(0).unwrap();
+        let before = &html[..placeholder.start()];
+        let inside_tag = before.rfind('<') > before.rfind('>');
+        result.push_str(&html[last..placeholder.start()]);
+        if !inside_tag && let Ok(index) = caps[1].parse::<usize>() {
+            result.push_str(tags.get(index).map_or("", String::as_str));
+        }
+        last = placeholder.end();
+    }
+    result.push_str(&html[last..]);
+    Cow::Owned(result)
+}
+
+/// Tags never passed through by [`sanitize_trusted`], whatever the allow-list
+const DENIED_TAGS: &[&str] = &[
+    "script",
+    "style",
+    "template",
+    "title",
+    "textarea",
+    "xmp",
+    "noembed",
+    "noframes",
+    "plaintext",
+    "noscript",
+    "object",
+    "embed",
+    "applet",
+    "base",
+    "meta",
+    "link",
+    "frame",
+    "frameset",
+    "form",
+    "svg",
+    "math",
+];
+
+/// Attributes whose value is a URL
+const URL_ATTRIBUTES: &[&str] = &["href", "src", "cite", "poster", "action", "formaction"];
+
+/// Length and normalized markup of the allowed tag at the start of `source`
+fn allowed_tag(source: &str, allow_list: &HtmlAllowList) -> Option<(usize, String)> {
+    let name_len = |text: &str| {
+        text.find(|ch: char| !ch.is_ascii_alphanumeric())
+            .unwrap_or(text.len())
+    };
+    let skip_blank = |text: &str| text.len() - text.trim_start().len();
+
+    let (closing, after) = match source[1..].strip_prefix('/') {
+        Some(after) => (true, after),
+        None => (false, &source[1..]),
+    };
+    let len = name_len(after);
+    let name = after[..len].to_ascii_lowercase();
+    if !after.starts_with(|ch: char| ch.is_ascii_alphabetic())
+        || DENIED_TAGS.contains(&name.as_str())
+        || !allow_list.tags.contains(&name)
+    {
+        return None;
+    }
+
+    let mut rest = &after[len..];
+    let mut tag = if closing {
+        format!("</{}", name)
+    } else {
+        format!("<{}{}", name, TRUSTED_TAG_MARKER)
+    };
+    loop {
+        rest = &rest[skip_blank(rest)..];
+        if let Some(after) = rest.strip_prefix('>') {
+            tag.push('>');
+            return Some((source.len() - after.len(), tag));
+        }
+        if let Some(after) = rest.strip_prefix("/>").filter(|_| !closing) {
+            tag.push_str(" />");
+            return Some((source.len() - after.len(), tag));
+        }
+        if closing {
+            return None;
+        }
+
+        let attr_len = rest
+            .find(|ch: char| !(ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | ':' | '.')))
+            .unwrap_or(rest.len());
+        if attr_len == 0 {
+            return None;
+        }
+        let attr = rest[..attr_len].to_ascii_lowercase();
+        rest = &rest[attr_len..];
+        let allowed = !attr.starts_with("on")
+            && attr != "style"
+            && attr != TRUSTED_TAG_MARKER.trim_start()
+            && allow_list
+                .attributes
+                .iter()
+                .any(|entry| match entry.strip_suffix('*') {
+                    Some(prefix) => attr.starts_with(prefix),
+                    None => *entry == attr,
+                });
+        if !allowed {
+            return None;
+        }
+
+        let after_blank = &rest[skip_blank(rest)..];
+        let Some(value_start) = after_blank.strip_prefix('=') else {
+            // Boolean attribute
+            tag.push(' ');
+            tag.push_str(&attr);
+            continue;
+        };
+        let value_start = &value_start[skip_blank(value_start)..];
+        let (value, after_value) = match value_start.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let end = value_start[1..].find(quote)? + 1;
+                (&value_start[1..end], &value_start[end + 1..])
+            }
+            _ => {
+                let end = value_start
+                    .find(|ch: char| {
+                        ch.is_whitespace() || matches!(ch, '"' | '\'' | '=' | '<' | '>' | '`')
+                    })
+                    .unwrap_or(value_start.len());
+                if end == 0 {
+                    return None;
+                }
+                value_start.split_at(end)
+            }
+        };
+        if value.contains("{{") || value.contains("}}") {
+            return None;
+        }
+        let value = if URL_ATTRIBUTES.contains(&attr.as_str()) {
+            let url = sanitize_url(value);
+            if url == "#blocked-url" {
+                return None;
+            }
+            attr_escape(&url).into_owned()
+        } else {
+            attr_escape(value).into_owned()
+        };
+        tag.push_str(&format!(" {}=\"{}\"", attr, value));
+        rest = after_value;
+    }
+}
+
+/// Length of the leading blockquote markers of a line, including an
+/// optional list item marker before them
+///
+/// A lone `>` cannot start a tag, so keeping it unescaped is safe.
+fn blockquote_prefix_len(line: &str) -> usize {
+    let bytes = line.as_bytes();
+    let skip_blank = |mut i: usize| {
+        while bytes.get(i).is_some_and(|b| *b == b' ' || *b == b'\t') {
+            i += 1;
+        }
+        i
+    };
+
+    let mut i = skip_blank(0);
+    // Optional list item marker (`-`, `*`, `+`, `1.`, `1)`) followed by a blank
+    let digits = bytes[i..].iter().take_while(|b| b.is_ascii_digit()).count();
+    let marker_len = match bytes.get(i + digits) {
+        Some(b'-' | b'*' | b'+') if digits == 0 => 1,
+        Some(b'.' | b')') if (1..=9).contains(&digits) => digits + 1,
+        _ => 0,
+    };
+    if marker_len > 0
+        && bytes
+            .get(i + marker_len)
+            .is_some_and(|b| *b == b' ' || *b == b'\t')
+    {
+        i = skip_blank(i + marker_len);
+    }
+
+    let mut end = 0;
+    while bytes.get(i) == Some(&b'>') {
+        end = i + 1;
+        i = skip_blank(end);
+    }
+    end
+}
+
+/// Escape `<`, `>` and `&` (except in entities) of `source` into `result`
+fn escape_html_into(source: &str, result: &mut String) {
     let mut chars = source.chars().peekable();
 
     while let Some(ch) = chars.next() {
@@ -126,8 +783,6 @@ pub fn sanitize(input: &str) -> Cow<'_, str> {
             _ => result.push(ch),
         }
     }
-
-    Cow::Owned(result)
 }
 
 fn remove_disallowed_blank_chars(input: &str) -> Cow<'_, str> {
@@ -257,7 +912,7 @@ pub fn remove_ascii_control_chars_from_markup(input: &str) -> std::borrow::Cow<'
 /// - Named entities: &name; (e.g., &nbsp;, &lt;, &gt;, &amp;, &quot;, &apos;)
 /// - Decimal entities: &#123;
 /// - Hexadecimal entities: &#x7B;
-fn is_html_entity(chars: &mut std::iter::Peekable<std::str::Chars>) -> bool {
+pub(crate) fn is_html_entity(chars: &mut std::iter::Peekable<std::str::Chars>) -> bool {
     let mut entity = String::new();
     let mut temp_chars = chars.clone();
 
@@ -434,6 +1089,17 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_blockquote_markers_preserved() {
+        assert_eq!(sanitize("> a <b>"), "> a &lt;b&gt;");
+        assert_eq!(
+            sanitize("> > nested\n>\n- > in list"),
+            "> > nested\n>\n- > in list"
+        );
+        assert_eq!(sanitize("1. > quote"), "1. > quote");
+        assert_eq!(sanitize("a > b\n->x"), "a &gt; b\n-&gt;x");
+    }
+
     #[test]
     fn test_entity_validation() {
         assert!(is_valid_entity("nbsp"));
@@ -563,4 +1229,65 @@ mod tests {
         let result = remove_ascii_control_chars_from_markup(input);
         assert!(result.contains("hello\x01world"));
     }
+
+    #[test]
+    fn test_sanitize_trusted() {
+        let allow_list = HtmlAllowList::default();
+        for (input, expected) in [
+            (
+                "<SUP Class='n'>2</sup>",
+                r#"<sup data-umd-raw class="n">2</sup>"#,
+            ),
+            (
+                r#"<iframe src="https://example.com/?a=1&b=2" allowfullscreen></iframe>"#,
+                r#"<iframe data-umd-raw src="https://example.com/?a=1&amp;b=2" allowfullscreen></iframe>"#,
+            ),
+            ("<script>x</script>", "&lt;script&gt;x&lt;/script&gt;"),
+            ("<b>x</b>", "&lt;b&gt;x&lt;/b&gt;"),
+            (
+                "<span onclick=\"x\">t</span>",
+                "&lt;span onclick=\"x\"&gt;t&lt;/span&gt;",
+            ),
+            (
+                "<span style=\"color:red\">t</span>",
+                "&lt;span style=\"color:red\"&gt;t&lt;/span&gt;",
+            ),
+            (
+                "<iframe src=\"javascript:alert(1)\"></iframe>",
+                "&lt;iframe src=\"javascript:alert(1)\"&gt;&lt;/iframe&gt;",
+            ),
+            (
+                "<iframe srcdoc=\"<b>x</b>\"></iframe>",
+                "&lt;iframe srcdoc=\"&lt;b&gt;x&lt;/b&gt;\"&gt;&lt;/iframe&gt;",
+            ),
+            (
+                "<span>a<span onclick=\"x\">b</span></span></sup>",
+                "<span data-umd-raw>a&lt;span onclick=\"x\"&gt;b</span>&lt;/span&gt;&lt;/sup&gt;",
+            ),
+            (
+                "<sup>a<br>b<br />c</sup>",
+                "<sup data-umd-raw>a<br data-umd-raw>b<br data-umd-raw />c</sup>",
+            ),
+            (
+                "<span data-umd-raw>t</span>",
+                "&lt;span data-umd-raw&gt;t&lt;/span&gt;",
+            ),
+            (
+                "<span title=\"{{HEADER}}\">t</span>",
+                "&lt;span title=\"{{HEADER}}\"&gt;t&lt;/span&gt;",
+            ),
+        ] {
+            assert_eq!(sanitize_trusted(input, &allow_list), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_restore_trusted_tags_inside_attribute() {
+        let (html, tags) = protect_trusted_tags("A <sup data-umd-raw>2</sup>");
+        let html = format!("<h2 aria-label=\"{}\">{}</h2>", html, html);
+        assert_eq!(
+            restore_trusted_tags(&html, &tags),
+            "<h2 aria-label=\"A 2</sup>\">A <sup>2</sup></h2>"
+        );
+    }
 }
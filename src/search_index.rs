@@ -0,0 +1,293 @@
+//! Search-index token stream
+//!
+//! Produces plain-text segments from a Universal Markdown document for
+//! feeding into full-text search engines. Each segment carries a weight
+//! derived from where the text appeared (document title, heading level,
+//! body, code), so indexers can rank heading matches above body matches.
+//!
+//! Plugin output (`<template class="umd-plugin ...">`) is excluded because
+//! its content is only meaningful after external plugin execution. Segments
+//! that contain inline decorations (`&color`, `&badge`, ...) are kept but
+//! flagged via [`IndexToken::decorated`].
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use crate::parser::ParserOptions;
+
+/// Weight assigned to the frontmatter title
+pub const TITLE_WEIGHT: u8 = 10;
+/// Weight assigned to body text and code
+pub const BODY_WEIGHT: u8 = 1;
+
+/// Kind of indexed segment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexTokenKind {
+    /// Document title taken from frontmatter (`title`)
+    Title,
+    /// Heading text with its level (1-6)
+    Heading(u8),
+    /// Paragraphs, list items, table cells and other prose
+    Body,
+    /// Fenced or indented code block contents
+    Code,
+}
+
+/// A weighted plain-text segment for search indexing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexToken {
+    /// Where the text came from
+    pub kind: IndexTokenKind,
+    /// Plain text with markup removed and entities decoded
+    pub text: String,
+    /// Ranking weight (higher is more important)
+    pub weight: u8,
+    /// Anchor ID of the heading (for headings) or the enclosing section
+    pub anchor: Option<String>,
+    /// Whether the segment contained inline decoration markup
+    pub decorated: bool,
+}
+
+static PLUGIN_TEMPLATE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?s)<template class="umd-plugin[^"]*"[^>]*>.*?</template>"#).unwrap()
+});
+
+/// Tables of contents repeat the heading titles
+static TOC_NAV: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?s)<nav class="umd-toc"[^>]*>.*?</nav>"#).unwrap());
+
+static SVG_ELEMENT: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<svg\b.*?</svg>").unwrap());
+
+static SEGMENT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?s)<h(?P<level>[1-6])\b(?P<attrs>[^>]*)>(?P<heading>.*?)</h[1-6]>|<pre\b[^>]*>(?P<code>.*?)</pre>",
+    )
+    .unwrap()
+});
+
+static ANCHOR_ID: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"<a [^>]*class="anchor"[^>]*id="([^"]+)"[^>]*>"#).unwrap());
+
+static HEADING_ID: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\sid="([^"]+)""#).unwrap());
+
+static BLOCK_BOUNDARY: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)</?(?:p|li|ul|ol|tr|td|th|thead|tbody|table|caption|blockquote|div|figure|figcaption|dl|dt|dd|section|aside|details|summary|hr|br)\b[^>]*>",
+    )
+    .unwrap()
+});
+
+static TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]*>").unwrap());
+
+static WHITESPACE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\s+").unwrap());
+
+/// Weight for a heading of the given level (h1 = 7 … h6 = 2)
+fn heading_weight(level: u8) -> u8 {
+    8u8.saturating_sub(level).max(2)
+}
+
+/// Inline decorations are rendered as `<span>` wrappers
+fn has_decoration(fragment: &str) -> bool {
+    fragment.contains("<span")
+}
+
+fn strip_tags(fragment: &str) -> String {
+    let text = TAG.replace_all(fragment, "");
+    html_escape::decode_html_entities(&text).into_owned()
+}
+
+pub(crate) fn normalize_text(fragment: &str) -> String {
+    WHITESPACE
+        .replace_all(&strip_tags(fragment), " ")
+        .trim()
+        .to_string()
+}
+
+fn push_body_tokens(fragment: &str, anchor: &Option<String>, tokens: &mut Vec<IndexToken>) {
+    for block in BLOCK_BOUNDARY.split(fragment) {
+        let text = normalize_text(block);
+        if text.is_empty() {
+            continue;
+        }
+        tokens.push(IndexToken {
+            kind: IndexTokenKind::Body,
+            text,
+            weight: BODY_WEIGHT,
+            anchor: anchor.clone(),
+            decorated: has_decoration(block),
+        });
+    }
+}
+
+/// Convert rendered UMD HTML into index tokens
+///
+/// # Arguments
+///
+/// * `html` - HTML produced by the UMD pipeline
+///
+/// # Returns
+///
+/// Tokens in document order (without a title token)
+pub fn tokens_from_html(html: &str) -> Vec<IndexToken> {
+    let html = PLUGIN_TEMPLATE.replace_all(html, "");
+    let html = TOC_NAV.replace_all(&html, "");
+    let html = SVG_ELEMENT.replace_all(&html, "");
+
+    let mut tokens = Vec::new();
+    let mut anchor: Option<String> = None;
+    let mut last_end = 0;
+
+    for caps in SEGMENT.captures_iter(&html) {
+        let whole = caps.get(0).unwrap();
+        push_body_tokens(&html[last_end..whole.start()], &anchor, &mut tokens);
+        last_end = whole.end();
+
+        if let Some(inner) = caps.name("heading") {
+            let level: u8 = caps["level"].parse().unwrap_or(6);
+            // The ID sits on the heading itself with `AnchorStyle::IdOnly`
+            anchor = ANCHOR_ID
+                .captures(inner.as_str())
+                .or_else(|| HEADING_ID.captures(&caps["attrs"]))
+                .map(|c: Captures| c[1].to_string());
+            let text = normalize_text(inner.as_str());
+            if !text.is_empty() {
+                tokens.push(IndexToken {
+                    kind: IndexTokenKind::Heading(level),
+                    text,
+                    weight: heading_weight(level),
+                    anchor: anchor.clone(),
+                    decorated: has_decoration(inner.as_str()),
+                });
+            }
+        } else if let Some(code) = caps.name("code") {
+            let text = strip_tags(code.as_str()).trim_end().to_string();
+            if !text.trim().is_empty() {
+                tokens.push(IndexToken {
+                    kind: IndexTokenKind::Code,
+                    text,
+                    weight: BODY_WEIGHT,
+                    anchor: anchor.clone(),
+                    decorated: false,
+                });
+            }
+        }
+    }
+    push_body_tokens(&html[last_end..], &anchor, &mut tokens);
+
+    tokens
+}
+
+/// Build a search-index token stream with custom parser options
+///
+/// The document is rendered through the regular pipeline first, so heading
+/// anchors match the IDs in the HTML output. Footnotes are indexed as body
+/// text after the main content.
+///
+/// # Arguments
+///
+/// * `input` - The Universal Markdown source text
+/// * `options` - Parser configuration options
+///
+/// # Returns
+///
+/// Tokens in document order, starting with the frontmatter title if present
+pub fn index_tokens_with_options(input: &str, options: &ParserOptions) -> Vec<IndexToken> {
+    let result = crate::parse_unmapped(input, options);
+
+    let mut tokens = Vec::new();
+    if let Some(title) = result
+        .frontmatter
+        .as_ref()
+        .and_then(|fm| fm.get_scalar("title"))
+    {
+        tokens.push(IndexToken {
+            kind: IndexTokenKind::Title,
+            text: title,
+            weight: TITLE_WEIGHT,
+            anchor: None,
+            decorated: false,
+        });
+    }
+
+    tokens.extend(tokens_from_html(&result.html));
+    if let Some(footnotes) = &result.footnotes {
+        tokens.extend(tokens_from_html(footnotes));
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(input: &str) -> Vec<IndexToken> {
+        index_tokens_with_options(input, &ParserOptions::default())
+    }
+
+    #[test]
+    fn test_title_and_headings_are_weighted() {
+        let result =
+            tokens("---\ntitle: Guide\n---\n# Intro {#intro}\n\nHello world.\n\n### Detail");
+
+        assert_eq!(result[0].kind, IndexTokenKind::Title);
+        assert_eq!(result[0].text, "Guide");
+        assert_eq!(result[0].weight, TITLE_WEIGHT);
+
+        assert_eq!(result[1].kind, IndexTokenKind::Heading(1));
+        assert_eq!(result[1].text, "Intro");
+        assert_eq!(result[1].anchor.as_deref(), Some("h-intro"));
+        assert!(result[1].weight > result[2].weight);
+
+        assert_eq!(result[2].kind, IndexTokenKind::Body);
+        assert_eq!(result[2].text, "Hello world.");
+        assert_eq!(result[2].anchor.as_deref(), Some("h-intro"));
+
+        assert_eq!(result[3].kind, IndexTokenKind::Heading(3));
+        assert!(result[1].weight > result[3].weight);
+    }
+
+    #[test]
+    fn test_code_blocks_are_separate() {
+        let result = tokens("Text\n\n```\nlet a = 1 < 2;\n```");
+        let code = result
+            .iter()
+            .find(|t| t.kind == IndexTokenKind::Code)
+            .expect("code token");
+        assert!(code.text.contains("let a = 1"));
+        assert!(
+            result
+                .iter()
+                .all(|t| t.kind != IndexTokenKind::Body || !t.text.contains("let a"))
+        );
+    }
+
+    #[test]
+    fn test_plugins_are_excluded() {
+        let result = tokens("Before\n\n@toc(2)\n\nAfter &highlight(yellow){secret};");
+        assert!(result.iter().all(|t| !t.text.contains('2')));
+        assert!(result.iter().all(|t| !t.text.contains("secret")));
+        assert!(result.iter().any(|t| t.text == "Before"));
+    }
+
+    #[test]
+    fn test_toc_is_excluded() {
+        let result = tokens("@toc\n\n# Title\n\nBody");
+        assert_eq!(result.iter().filter(|t| t.text == "Title").count(), 1);
+    }
+
+    #[test]
+    fn test_decorations_are_flagged() {
+        let result = tokens("Plain text\n\nSome &color(red){red}; text");
+        assert!(!result[0].decorated);
+        assert!(result[1].decorated);
+        assert_eq!(result[1].text, "Some red text");
+    }
+
+    #[test]
+    fn test_list_items_and_entities() {
+        let result = tokens("- Tom &amp; Jerry\n- Second");
+        let texts: Vec<&str> = result.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(texts, vec!["Tom & Jerry", "Second"]);
+    }
+}
@@ -0,0 +1,174 @@
+//! Splitting rendered HTML into heading sections
+//!
+//! Hosts that paginate long manuals, lazy-load parts of a page or render
+//! per-section edit links get the document as a list of [`Section`]s.
+//! Only headings at the top level of the document split it; headings inside
+//! blockquotes, lists, tables or plugin output stay within their section.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::search_index::normalize_text;
+
+/// A part of the document starting at a heading
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    /// Heading level (1-6); `0` for content before the first heading
+    pub level: u8,
+    /// Plain-text heading title (empty for content before the first heading)
+    pub title: String,
+    /// Anchor ID of the heading, as rendered by the anchor pass
+    pub id: Option<String>,
+    /// HTML of the heading and the content up to the next top-level heading
+    pub html: String,
+}
+
+static TAG: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"<(/?)([A-Za-z][A-Za-z0-9-]*)\b[^>]*?(/?)>").unwrap());
+
+static HEADING: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?s)^<h([1-6])\b([^>]*)>(.*?)</h[1-6]>").unwrap());
+
+static ID_ATTRIBUTE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\sid="([^"]+)""#).unwrap());
+
+/// Elements without an end tag
+pub(crate) const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Split rendered body HTML at its top-level headings
+///
+/// # Examples
+///
+/// ```
+/// use umd::sections::split_sections;
+///
+/// let sections = split_sections("<p>Intro</p>\n<h2 id=\"a\">A</h2>\n<p>Body</p>");
+/// assert_eq!(sections.len(), 2);
+/// assert_eq!(sections[0].level, 0);
+/// assert_eq!(sections[1].id.as_deref(), Some("a"));
+/// assert_eq!(sections[1].html, "<h2 id=\"a\">A</h2>\n<p>Body</p>");
+/// ```
+pub fn split_sections(html: &str) -> Vec<Section> {
+    let mut starts = Vec::new();
+    let mut depth = 0usize;
+    for caps in TAG.captures_iter(html) {
+        let name = caps[2].to_ascii_lowercase();
+        if &caps[3] == "/" || VOID_ELEMENTS.contains(&name.as_str()) {
+            continue;
+        }
+        if &caps[1] == "/" {
+            depth = depth.saturating_sub(1);
+            continue;
+        }
+        if depth == 0 && HEADING.is_match(&html[caps.get(0).unwrap().start()..]) {
+            starts.push(caps.get(0).unwrap().start());
+        }
+        depth += 1;
+    }
+
+    let mut sections = Vec::new();
+    let preamble = &html[..starts.first().copied().unwrap_or(html.len())];
+    if !preamble.trim().is_empty() {
+        sections.push(Section {
+            level: 0,
+            title: String::new(),
+            id: None,
+            html: preamble.trim().to_string(),
+        });
+    }
+    for (index, &start) in starts.iter().enumerate() {
+        let end = starts.get(index + 1).copied().unwrap_or(html.len());
+        let fragment = &html[start..end];
+        let caps = HEADING.captures(fragment).unwrap();
+        let id = ID_ATTRIBUTE
+            .captures(&caps[2])
+            .or_else(|| ID_ATTRIBUTE.captures(&caps[3]))
+            .map(|id| id[1].to_string());
+        sections.push(Section {
+            level: caps[1].parse().unwrap_or(1),
+            title: normalize_text(&caps[3]),
+            id,
+            html: fragment.trim().to_string(),
+        });
+    }
+    sections
+}
+
+/// Top-level elements of rendered HTML, in order
+///
+/// Text outside any element is returned as its own trimmed block; the
+/// whitespace between blocks is dropped.
+///
+/// # Examples
+///
+/// ```
+/// use umd::sections::top_level_blocks;
+///
+/// let blocks = top_level_blocks("<p>A<br />B</p>\n<hr />\n<ul>\n<li>x</li>\n</ul>\n");
+/// assert_eq!(blocks, ["<p>A<br />B</p>", "<hr />", "<ul>\n<li>x</li>\n</ul>"]);
+/// ```
+pub fn top_level_blocks(html: &str) -> Vec<&str> {
+    let mut blocks = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut text_start = 0;
+    for caps in TAG.captures_iter(html) {
+        let tag = caps.get(0).unwrap();
+        if depth == 0 {
+            let text = html[text_start..tag.start()].trim();
+            if !text.is_empty() {
+                blocks.push(text);
+            }
+            start = tag.start();
+        }
+        let name = caps[2].to_ascii_lowercase();
+        if &caps[3] == "/" || VOID_ELEMENTS.contains(&name.as_str()) {
+            if depth == 0 {
+                blocks.push(tag.as_str());
+                text_start = tag.end();
+            }
+            continue;
+        }
+        if &caps[1] == "/" {
+            depth = depth.saturating_sub(1);
+            if depth == 0 {
+                let block = &html[start..tag.end()];
+                // A stray end tag is not a block of its own
+                if block != tag.as_str() {
+                    blocks.push(block);
+                }
+                text_start = tag.end();
+            }
+            continue;
+        }
+        depth += 1;
+    }
+    let rest = if depth == 0 {
+        html[text_start..].trim()
+    } else {
+        html[start..].trim()
+    };
+    if !rest.is_empty() {
+        blocks.push(rest);
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_headings_do_not_split() {
+        let html = "<h1>A</h1>\n<blockquote>\n<h2>Quoted</h2>\n</blockquote>\n<p>x<br />y</p>\n<h2><a href=\"#h-b\" class=\"anchor\" id=\"h-b\"></a>B &amp; C</h2>\n";
+        let sections = split_sections(html);
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].title, "A");
+        assert!(sections[0].html.contains("Quoted"));
+        assert_eq!(sections[1].level, 2);
+        assert_eq!(sections[1].title, "B & C");
+        assert_eq!(sections[1].id.as_deref(), Some("h-b"));
+    }
+}
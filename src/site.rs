@@ -0,0 +1,486 @@
+//! Multi-document site rendering context
+//!
+//! A [`SiteContext`] is shared across several `parse` calls so that static
+//! site generators can resolve links between pages and build a global index.
+//! It accumulates:
+//!
+//! - heading slugs (anchor IDs) and titles for every parsed page
+//! - an interwiki map for `[[Prefix:Name]]` links
+//! - a running footnote offset, so footnote numbers and IDs stay unique when
+//!   pages are concatenated into a single document
+//! - a cache of resolved `[[...]]` link targets
+//!
+//! # Wiki link syntax
+//!
+//! - `[[Page]]` - link to another page
+//! - `[[Page#anchor]]` - link to a heading on another page (`#anchor` may be a
+//!   custom header ID without the `h-` prefix)
+//! - `[[#anchor]]` - link to a heading on the current page
+//! - `[[label>Page]]` - LukiWiki-style link with custom text
+//! - `[[Prefix:Name]]` - interwiki link (prefix registered in `interwiki`)
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::diagnostics::Diagnostic;
+use crate::extensions::footnotes::renumber_footnotes;
+use crate::parser::ParserOptions;
+use crate::search_index::{IndexToken, IndexTokenKind, tokens_from_html};
+use crate::{ParseResult, finish_result, parse_unmapped};
+
+/// Regex to detect `[[...]]` wiki links
+static WIKILINK: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[\[([^\[\]\n]+?)\]\]").unwrap());
+
+/// Regex to detect code and plugin regions that must not be rewritten
+static PROTECTED_REGION: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?s)<pre\b.*?</pre>|<code\b.*?</code>|<template\b.*?</template>").unwrap()
+});
+
+/// A heading collected from a parsed page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageHeading {
+    /// Heading level (1-6)
+    pub level: u8,
+    /// Anchor ID as rendered (e.g. `h-install`, `h-3`)
+    pub id: String,
+    /// Plain-text title
+    pub title: String,
+}
+
+/// Information recorded for each parsed page
+#[derive(Debug, Clone)]
+pub struct PageInfo {
+    /// Page name used in `[[links]]`
+    pub name: String,
+    /// Title from frontmatter (`title`), if any
+    pub title: Option<String>,
+    /// Headings in document order
+    pub headings: Vec<PageHeading>,
+    /// Search-index tokens for the page
+    pub tokens: Vec<IndexToken>,
+}
+
+impl PageInfo {
+    /// Find a heading by anchor, accepting custom IDs without the `h-` prefix
+    pub fn find_heading(&self, anchor: &str) -> Option<&PageHeading> {
+        let prefixed = format!("h-{}", anchor);
+        self.headings
+            .iter()
+            .find(|h| h.id == prefixed)
+            .or_else(|| self.headings.iter().find(|h| h.id == anchor))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ResolvedLink {
+    href: String,
+    missing: bool,
+}
+
+/// Shared state for rendering multiple documents
+#[derive(Debug, Clone)]
+pub struct SiteContext {
+    /// URL pattern for page links; `{page}` is replaced with the
+    /// percent-encoded page name. Default: `/{page}`
+    pub page_url_pattern: String,
+    /// Interwiki prefixes mapped to URL templates. `$1` in the template is
+    /// replaced with the percent-encoded name; without `$1` the name is appended.
+    pub interwiki: HashMap<String, String>,
+    /// Renumber footnotes continuously across pages
+    pub continuous_footnotes: bool,
+    pages: BTreeMap<String, PageInfo>,
+    footnote_offset: usize,
+    link_cache: HashMap<String, ResolvedLink>,
+}
+
+impl Default for SiteContext {
+    fn default() -> Self {
+        Self {
+            page_url_pattern: "/{page}".to_string(),
+            interwiki: HashMap::new(),
+            continuous_footnotes: true,
+            pages: BTreeMap::new(),
+            footnote_offset: 0,
+            link_cache: HashMap::new(),
+        }
+    }
+}
+
+impl SiteContext {
+    /// Create an empty site context
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register an interwiki prefix
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use umd::site::SiteContext;
+    ///
+    /// let mut site = SiteContext::new();
+    /// site.add_interwiki("wp", "https://en.wikipedia.org/wiki/$1");
+    /// let result = site.parse_page("Home", "See [[wp:Rust]].", &Default::default());
+    /// assert!(result.html.contains(r#"href="https://en.wikipedia.org/wiki/Rust""#));
+    /// ```
+    pub fn add_interwiki(&mut self, prefix: &str, url_template: &str) {
+        self.interwiki
+            .insert(prefix.to_string(), url_template.to_string());
+        self.link_cache.clear();
+    }
+
+    /// Parse a page without rendering links, only recording its headings
+    ///
+    /// Use this as a first pass so that forward links (to pages parsed later)
+    /// resolve during the rendering pass.
+    pub fn collect_page(&mut self, name: &str, input: &str, options: &ParserOptions) {
+        let result = parse_unmapped(input, options);
+        self.register_page(name, &result);
+    }
+
+    /// Parse a page and resolve `[[links]]` against the site
+    ///
+    /// The page is (re-)registered before links are resolved, so links to
+    /// the page's own headings work in a single pass.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Page name used by other pages to link here
+    /// * `input` - The Universal Markdown source text
+    /// * `options` - Parser configuration options
+    ///
+    /// # Returns
+    ///
+    /// ParseResult with wiki links resolved and footnotes renumbered
+    pub fn parse_page(&mut self, name: &str, input: &str, options: &ParserOptions) -> ParseResult {
+        let mut result = parse_unmapped(input, options);
+        self.register_page(name, &result);
+
+        let (html, mut link_diagnostics) = self.resolve_wikilinks(name, &result.html, options);
+        result.html = html;
+        result.diagnostics.append(&mut link_diagnostics);
+
+        if self.continuous_footnotes {
+            let (html, footnotes, count) = renumber_footnotes(
+                &result.html,
+                result.footnotes.as_deref(),
+                self.footnote_offset,
+            );
+            result.html = html;
+            result.footnotes = footnotes;
+            self.footnote_offset += count;
+        }
+
+        finish_result(&mut result, options);
+        result
+    }
+
+    /// Look up a parsed page
+    pub fn page(&self, name: &str) -> Option<&PageInfo> {
+        self.pages.get(name)
+    }
+
+    /// All parsed pages, ordered by name
+    pub fn pages(&self) -> impl Iterator<Item = &PageInfo> {
+        self.pages.values()
+    }
+
+    /// Number of footnotes emitted so far
+    pub fn footnote_offset(&self) -> usize {
+        self.footnote_offset
+    }
+
+    /// Global search index across all pages as (page name, token) pairs
+    pub fn index(&self) -> impl Iterator<Item = (&str, &IndexToken)> {
+        self.pages
+            .values()
+            .flat_map(|page| page.tokens.iter().map(move |t| (page.name.as_str(), t)))
+    }
+
+    fn register_page(&mut self, name: &str, result: &ParseResult) {
+        let tokens = tokens_from_html(&result.html);
+        let headings = tokens
+            .iter()
+            .filter_map(|token| match (token.kind, &token.anchor) {
+                (IndexTokenKind::Heading(level), Some(id)) => Some(PageHeading {
+                    level,
+                    id: id.clone(),
+                    title: token.text.clone(),
+                }),
+                _ => None,
+            })
+            .collect();
+        let title = result
+            .frontmatter
+            .as_ref()
+            .and_then(|fm| fm.get_scalar("title"));
+
+        self.pages.insert(
+            name.to_string(),
+            PageInfo {
+                name: name.to_string(),
+                title,
+                headings,
+                tokens,
+            },
+        );
+        self.link_cache.clear();
+    }
+
+    fn page_url(&self, page: &str) -> String {
+        self.page_url_pattern
+            .replace("{page}", &percent_encode(page))
+    }
+
+    fn resolve_target(&mut self, current_page: &str, target: &str) -> ResolvedLink {
+        let cache_key = format!("{}\u{0}{}", current_page, target);
+        if let Some(cached) = self.link_cache.get(&cache_key) {
+            return cached.clone();
+        }
+
+        let resolved = if let Some((prefix, name)) = target
+            .split_once(':')
+            .filter(|(prefix, _)| self.interwiki.contains_key(*prefix))
+        {
+            let template = &self.interwiki[prefix];
+            let encoded = percent_encode(name);
+            let href = if template.contains("$1") {
+                template.replace("$1", &encoded)
+            } else {
+                format!("{}{}", template, encoded)
+            };
+            ResolvedLink {
+                href,
+                missing: false,
+            }
+        } else {
+            let (page_name, anchor) = match target.split_once('#') {
+                Some((page, anchor)) => (page, Some(anchor)),
+                None => (target, None),
+            };
+            let page_name = if page_name.is_empty() {
+                current_page
+            } else {
+                page_name
+            };
+            let page_href = if page_name == current_page && anchor.is_some() {
+                String::new()
+            } else {
+                self.page_url(page_name)
+            };
+
+            match (self.pages.get(page_name), anchor) {
+                (Some(page), Some(anchor)) => match page.find_heading(anchor) {
+                    Some(heading) => ResolvedLink {
+                        href: format!("{}#{}", page_href, heading.id),
+                        missing: false,
+                    },
+                    None => ResolvedLink {
+                        href: format!("{}#h-{}", page_href, percent_encode(anchor)),
+                        missing: true,
+                    },
+                },
+                (Some(_), None) => ResolvedLink {
+                    href: page_href,
+                    missing: false,
+                },
+                (None, anchor) => ResolvedLink {
+                    href: match anchor {
+                        Some(anchor) => format!("{}#h-{}", page_href, percent_encode(anchor)),
+                        None => page_href,
+                    },
+                    missing: true,
+                },
+            }
+        };
+
+        self.link_cache.insert(cache_key, resolved.clone());
+        resolved
+    }
+
+    fn resolve_wikilinks(
+        &mut self,
+        current_page: &str,
+        html: &str,
+        options: &ParserOptions,
+    ) -> (String, Vec<Diagnostic>) {
+        let mut diagnostics = Vec::new();
+        let mut output = String::with_capacity(html.len());
+        let mut last_end = 0;
+
+        let protected: Vec<(usize, usize)> = PROTECTED_REGION
+            .find_iter(html)
+            .map(|m| (m.start(), m.end()))
+            .chain(std::iter::once((html.len(), html.len())))
+            .collect();
+
+        for (start, end) in protected {
+            let segment = &html[last_end..start];
+            let replaced = WIKILINK.replace_all(segment, |caps: &Captures| {
+                // Link text is already HTML-escaped; `>` arrives as `&gt;`
+                let inner = caps[1].replace("&gt;", ">");
+                let (label, target) = match inner.rsplit_once('>') {
+                    Some((label, target)) => (Some(label.trim()), target.trim()),
+                    None => (None, inner.trim()),
+                };
+                let decoded_target = html_escape::decode_html_entities(target).into_owned();
+
+                let resolved = self.resolve_target(current_page, &decoded_target);
+                let label = label.unwrap_or(target).replace('>', "&gt;");
+                let class = if resolved.missing {
+                    diagnostics.push(Diagnostic::warning(
+                        "unresolved-wikilink",
+                        format!("Wiki link target `{}` was not found", decoded_target),
+                    ));
+                    "umd-wikilink umd-wikilink-missing"
+                } else {
+                    "umd-wikilink"
+                };
+
+                let link = format!(
+                    "<a href=\"{}\" class=\"{}\">{}</a>",
+                    html_escape::encode_double_quoted_attribute(&resolved.href),
+                    class,
+                    label
+                );
+                match &options.base_url {
+                    Some(base_url) => {
+                        crate::extensions::conflict_resolver::apply_base_url_to_links(
+                            &link, base_url,
+                        )
+                    }
+                    None => link,
+                }
+            });
+            output.push_str(&replaced);
+            output.push_str(&html[start..end]);
+            last_end = end;
+        }
+
+        (output, diagnostics)
+    }
+}
+
+/// Percent-encode a page name for use in a URL path segment
+pub(crate) fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            b' ' => encoded.push('_'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> ParserOptions {
+        ParserOptions::default()
+    }
+
+    #[test]
+    fn test_page_links_and_headings() {
+        let mut site = SiteContext::new();
+        site.collect_page("Guide", "# Guide\n\n## Install {#install}", &options());
+
+        let result = site.parse_page(
+            "Home",
+            "See [[Guide]], [[Guide#install]] and [[setup>Guide#install]].",
+            &options(),
+        );
+        assert!(
+            result
+                .html
+                .contains(r#"<a href="/Guide" class="umd-wikilink">Guide</a>"#)
+        );
+        assert!(
+            result
+                .html
+                .contains(r##"<a href="/Guide#h-install" class="umd-wikilink">Guide#install</a>"##)
+        );
+        assert!(
+            result
+                .html
+                .contains(r##"<a href="/Guide#h-install" class="umd-wikilink">setup</a>"##)
+        );
+        assert!(result.diagnostics.is_empty());
+
+        let guide = site.page("Guide").unwrap();
+        assert_eq!(guide.headings.len(), 2);
+        assert_eq!(guide.headings[1].id, "h-install");
+        assert_eq!(guide.headings[1].title, "Install");
+    }
+
+    #[test]
+    fn test_missing_page_is_reported() {
+        let mut site = SiteContext::new();
+        let result = site.parse_page("Home", "[[Nowhere]]", &options());
+        assert!(result.html.contains("umd-wikilink-missing"));
+        assert_eq!(result.diagnostics.len(), 1);
+        assert_eq!(result.diagnostics[0].code, "unresolved-wikilink");
+    }
+
+    #[test]
+    fn test_same_page_anchor_and_url_pattern() {
+        let mut site = SiteContext::new();
+        site.page_url_pattern = "/wiki/{page}.html".to_string();
+        let result = site.parse_page(
+            "My Page",
+            "# Top {#top}\n\n[[#top]] [[My Page]]",
+            &options(),
+        );
+        assert!(
+            result
+                .html
+                .contains(r##"<a href="#h-top" class="umd-wikilink">#top</a>"##)
+        );
+        assert!(result.html.contains(r#"href="/wiki/My_Page.html""#));
+    }
+
+    #[test]
+    fn test_wikilinks_in_code_are_untouched() {
+        let mut site = SiteContext::new();
+        let result = site.parse_page("Home", "`[[Guide]]`\n\n```\n[[Guide]]\n```", &options());
+        assert!(!result.html.contains("umd-wikilink"));
+    }
+
+    #[test]
+    fn test_footnote_offsets_accumulate() {
+        let mut site = SiteContext::new();
+        let first = site.parse_page("A", "One[^a].\n\n[^a]: First", &options());
+        let second = site.parse_page("B", "Two[^a].\n\n[^a]: Second", &options());
+
+        assert!(
+            first
+                .html
+                .contains(r##"<a href="#fn-1" id="fnref-1" data-footnote-ref>1</a>"##)
+        );
+        assert!(
+            second
+                .html
+                .contains(r##"<a href="#fn-2" id="fnref-2" data-footnote-ref>2</a>"##)
+        );
+        let notes = second.footnotes.unwrap();
+        assert!(notes.contains(r#"<ol start="2">"#));
+        assert!(notes.contains(r#"<li id="fn-2">"#));
+        assert!(notes.contains(r##"href="#fnref-2""##));
+        assert_eq!(site.footnote_offset(), 2);
+    }
+
+    #[test]
+    fn test_global_index() {
+        let mut site = SiteContext::new();
+        site.parse_page("A", "# Alpha", &options());
+        site.parse_page("B", "# Beta", &options());
+        let pages: Vec<&str> = site.index().map(|(page, _)| page).collect();
+        assert_eq!(pages, vec!["A", "B"]);
+    }
+}
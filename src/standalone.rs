@@ -0,0 +1,252 @@
+//! Complete HTML documents
+//!
+//! [`render_standalone`] wraps the rendered body in a full document, for
+//! CLI tools, preview servers and static exports that do not have a page
+//! template of their own:
+//!
+//! - the title comes from the frontmatter `title`, then the first heading,
+//!   then [`StandaloneOptions::title`]
+//! - `lang` and the `description` meta tag come from the frontmatter
+//! - the stylesheet is the Bootstrap CDN build, a small embedded stylesheet
+//!   for the classes the parser emits, a custom link, or nothing
+//! - the `ColorMode::Adaptive` palette and the footnotes are included
+//!
+//! With XHTML output (`html_syntax` or the EPUB profile), the document gets
+//! an XML declaration and the XHTML namespace.
+
+use crate::extensions::serialization::serialize;
+use crate::html_builder::{attr_escape, text_escape, url_attr};
+use crate::parse_with_frontmatter_opts;
+use crate::parser::{HtmlSyntax, ParserOptions, Profile};
+
+/// Bootstrap 5 stylesheet on the jsDelivr CDN
+const BOOTSTRAP_CSS: &str =
+    "https://cdn.jsdelivr.net/npm/bootstrap@5.3.3/dist/css/bootstrap.min.css";
+
+/// Bootstrap Icons stylesheet on the jsDelivr CDN
+const BOOTSTRAP_ICONS_CSS: &str =
+    "https://cdn.jsdelivr.net/npm/bootstrap-icons@1.11.3/font/bootstrap-icons.min.css";
+
+/// Minimal styles for the classes the parser emits
+const EMBEDDED_CSS: &str = "\
+body{margin:0;font-family:system-ui,-apple-system,\"Segoe UI\",sans-serif;line-height:1.6;color:#212529}
+main{max-width:48rem;margin:0 auto;padding:1.5rem}
+img,video{max-width:100%;height:auto}
+pre{overflow:auto;padding:1rem;background:#f8f9fa;border-radius:.375rem}
+code{font-size:.875em}
+table{border-collapse:collapse;margin-bottom:1rem}
+th,td{padding:.25rem .5rem;border:1px solid #dee2e6}
+blockquote,.blockquote,.umd-blockquote{margin:0 0 1rem;padding-left:1rem;border-left:4px solid #dee2e6}
+figure{margin:0 0 1rem}
+figcaption{color:#6c757d;font-size:.875em}
+.alert{margin-bottom:1rem;padding:.75rem 1rem;border:1px solid #dee2e6;border-radius:.375rem}
+.alert-info{background:#cff4fc}.alert-success{background:#d1e7dd}.alert-warning{background:#fff3cd}.alert-danger{background:#f8d7da}
+.badge{display:inline-block;padding:.35em .65em;font-size:.75em;font-weight:700;line-height:1;color:#fff;background:#6c757d;border-radius:.375rem}
+.text-start{text-align:left}.text-center{text-align:center}.text-end{text-align:right}.text-justify{text-align:justify}
+.spoiler:not(.spoiler-revealed) .spoiler-content{background:currentColor}
+.spoiler:hover .spoiler-content,.spoiler:focus .spoiler-content{background:none}
+.visually-hidden{position:absolute;width:1px;height:1px;overflow:hidden;clip:rect(0,0,0,0)}
+";
+
+/// Stylesheet of a standalone document
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Stylesheet {
+    /// Bootstrap 5 and Bootstrap Icons from the jsDelivr CDN (default)
+    #[default]
+    BootstrapCdn,
+    /// A small embedded stylesheet covering the parser's own classes
+    Embedded,
+    /// A `<link>` to the given stylesheet URL
+    Link(String),
+    /// No stylesheet
+    None,
+}
+
+/// Options of [`render_standalone`]
+#[derive(Debug, Clone)]
+pub struct StandaloneOptions {
+    /// Options the body is parsed with
+    pub parser: ParserOptions,
+    /// Stylesheet linked or embedded in `<head>`. Default: `Stylesheet::BootstrapCdn`
+    pub stylesheet: Stylesheet,
+    /// Title used when neither the frontmatter nor a heading provides one.
+    /// Default: `"Untitled"`
+    pub title: String,
+    /// `lang` of the document when the frontmatter has no `lang`.
+    /// Default: `"en"`
+    pub lang: String,
+}
+
+impl Default for StandaloneOptions {
+    fn default() -> Self {
+        Self {
+            parser: ParserOptions::default(),
+            stylesheet: Stylesheet::default(),
+            title: "Untitled".to_string(),
+            lang: "en".to_string(),
+        }
+    }
+}
+
+/// Render Universal Markdown as a complete HTML document
+///
+/// # Arguments
+///
+/// * `input` - The Universal Markdown source text
+/// * `options` - Document and parser options
+///
+/// # Returns
+///
+/// The document, starting with the doctype
+///
+/// # Examples
+///
+/// ```
+/// use umd::{StandaloneOptions, render_standalone};
+///
+/// let html = render_standalone("---\ntitle: Notes\n---\n\n# Intro", &StandaloneOptions::default());
+/// assert!(html.starts_with("<!DOCTYPE html>\n<html lang=\"en\">"));
+/// assert!(html.contains("<title>Notes</title>"));
+/// assert!(html.contains("bootstrap.min.css"));
+/// ```
+pub fn render_standalone(input: &str, options: &StandaloneOptions) -> String {
+    let result = parse_with_frontmatter_opts(input, &options.parser);
+    let frontmatter = |key: &str| {
+        result
+            .frontmatter
+            .as_ref()
+            .and_then(|frontmatter| frontmatter.get_scalar(key))
+    };
+    let title = frontmatter("title")
+        .or_else(|| {
+            result
+                .sections
+                .iter()
+                .find(|section| section.level > 0 && !section.title.is_empty())
+                .map(|section| section.title.clone())
+        })
+        .unwrap_or_else(|| options.title.clone());
+    let lang = frontmatter("lang").unwrap_or_else(|| options.lang.clone());
+
+    let mut head = String::from(
+        "<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n",
+    );
+    head.push_str(&format!("<title>{}</title>\n", text_escape(&title)));
+    if let Some(description) = frontmatter("description") {
+        head.push_str(&format!(
+            "<meta name=\"description\" content=\"{}\">\n",
+            attr_escape(&description)
+        ));
+    }
+    let link = |href: &str| format!("<link rel=\"stylesheet\" href=\"{}\">\n", url_attr(href));
+    match &options.stylesheet {
+        Stylesheet::BootstrapCdn => {
+            head.push_str(&link(BOOTSTRAP_CSS));
+            head.push_str(&link(BOOTSTRAP_ICONS_CSS));
+        }
+        Stylesheet::Embedded => head.push_str(&format!("<style>\n{}</style>\n", EMBEDDED_CSS)),
+        Stylesheet::Link(href) => head.push_str(&link(href)),
+        Stylesheet::None => {}
+    }
+    if let Some(palette) = &result.color_palette {
+        head.push_str(&format!("<style>\n{}\n</style>\n", palette.trim_end()));
+    }
+
+    let syntax = options.parser.effective_html_syntax();
+    let mut document = String::new();
+    let mut html_attributes = format!(" lang=\"{}\"", attr_escape(&lang));
+    if syntax == Some(HtmlSyntax::Xhtml) {
+        document.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        head = serialize(&head, HtmlSyntax::Xhtml).into_owned();
+        html_attributes = format!(
+            " xmlns=\"http://www.w3.org/1999/xhtml\"{} xml:lang=\"{}\"",
+            html_attributes,
+            attr_escape(&lang)
+        );
+        if options.parser.profile == Profile::Epub {
+            html_attributes.push_str(" xmlns:epub=\"http://www.idpf.org/2007/ops\"");
+        }
+    }
+    let main = if options.stylesheet == Stylesheet::BootstrapCdn {
+        "<main class=\"container py-4\">"
+    } else {
+        "<main>"
+    };
+
+    document.push_str(&format!(
+        "<!DOCTYPE html>\n<html{}>\n<head>\n{}</head>\n<body>\n{}\n{}\n",
+        html_attributes,
+        head,
+        main,
+        result.html.trim_end()
+    ));
+    if let Some(footnotes) = &result.footnotes {
+        document.push_str(footnotes.trim_end());
+        document.push('\n');
+    }
+    document.push_str("</main>\n</body>\n</html>\n");
+    document
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_title_fallbacks() {
+        let mut options = StandaloneOptions {
+            stylesheet: Stylesheet::Embedded,
+            ..StandaloneOptions::default()
+        };
+        let html = render_standalone("Intro\n\n## Setup & use", &options);
+        assert!(html.contains("<title>Setup &amp; use</title>"), "{}", html);
+        assert!(html.contains("<style>\nbody{"), "{}", html);
+        assert!(html.contains("\n<main>\n<p>Intro</p>"), "{}", html);
+
+        options.title = "Draft".to_string();
+        let html = render_standalone("No heading", &options);
+        assert!(html.contains("<title>Draft</title>"), "{}", html);
+    }
+
+    #[test]
+    fn test_frontmatter_and_footnotes() {
+        let input = "---\nlang: ja\ndescription: \"A <short> note\"\n---\n\nText[^1]\n\n[^1]: Note";
+        let options = StandaloneOptions {
+            stylesheet: Stylesheet::Link("/site.css".to_string()),
+            ..StandaloneOptions::default()
+        };
+        let html = render_standalone(input, &options);
+        assert!(html.contains("<html lang=\"ja\">"), "{}", html);
+        assert!(
+            html.contains("<meta name=\"description\" content=\"A &lt;short&gt; note\">"),
+            "{}",
+            html
+        );
+        assert!(
+            html.contains("<link rel=\"stylesheet\" href=\"/site.css\">"),
+            "{}",
+            html
+        );
+        assert!(
+            html.contains("</section>\n</main>\n</body>\n</html>\n"),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn test_xhtml_document() {
+        let mut options = StandaloneOptions::default();
+        options.parser.profile = Profile::Epub;
+        let html = render_standalone("# Title", &options);
+        assert!(
+            html.starts_with(
+                "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<!DOCTYPE html>\n<html xmlns=\"http://www.w3.org/1999/xhtml\" lang=\"en\" xml:lang=\"en\""
+            ),
+            "{}",
+            html
+        );
+        assert!(html.contains(" xmlns:epub="), "{}", html);
+        assert!(html.contains("<meta charset=\"utf-8\" />"), "{}", html);
+    }
+}
@@ -0,0 +1,454 @@
+//! Quote / angle bracket injection regression tests
+//!
+//! Every decoration that interpolates user input into generated markup must
+//! keep the input inside its attribute value or text node.
+//!
+//! [`test_injection_matrix`] feeds each payload through every decoration,
+//! plugin, table, link and media path under each output profile, and checks
+//! the output tag by tag: no event handler attribute, no `<script>`, no
+//! malformed attribute list and no `javascript:` URL, even when encoded.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use umd::{options_from_json, parse, parse_with_frontmatter_opts};
+
+/// Assert that no injected attribute or element escaped into the markup
+fn assert_contained(input: &str) -> String {
+    let html = parse(input);
+    let injected = Regex::new(r#"<[^>]*\s(onclick|onerror|x)=""#).unwrap();
+    assert!(
+        !injected.is_match(&html),
+        "Injected attribute in output for {:?}: {}",
+        input,
+        html
+    );
+    assert!(
+        !html.contains("<script"),
+        "Injected element in output for {:?}: {}",
+        input,
+        html
+    );
+    html
+}
+
+#[test]
+fn test_inline_decoration_arguments() {
+    for input in [
+        r#"&time(2024" onclick="x){a};"#,
+        r#"&data(1" onclick="x){a};"#,
+        r#"&lang(en" onclick="x){a};"#,
+        r#"&abbr(T" onclick="x){HTML};"#,
+        r#"&badge(primary" onclick="x){a};"#,
+        r#"&color(red" onclick="x){a};"#,
+        r#"&size(2" onclick="x){a};"#,
+        r#"&ruby(r" x="y){a};"#,
+        r#"&bdo(rtl" x="y){a};"#,
+    ] {
+        assert_contained(input);
+    }
+
+    let html = assert_contained(r#"&time(2024" onclick="x){a};"#);
+    assert!(html.contains(r#"datetime="2024&quot; onclick=&quot;x""#));
+}
+
+#[test]
+fn test_inline_decoration_content() {
+    for input in [
+        "&sup(<script>x</script>);",
+        "&sub(<script>x</script>);",
+        "&kbd{<script>x</script>};",
+        "&badge(primary){<script>x</script>};",
+        "&time(2024-01-01){<script>x</script>};",
+    ] {
+        let html = assert_contained(input);
+        assert!(html.contains("&lt;script&gt;"), "{}", html);
+    }
+}
+
+#[test]
+fn test_badge_link_url() {
+    let html = assert_contained("&badge(primary){[x](javascript:alert(1))};");
+    assert!(html.contains(r##"href="#blocked-url""##), "{}", html);
+}
+
+#[test]
+fn test_block_decoration_arguments() {
+    assert_contained(r#"COLOR(red" onclick="x): block"#);
+    assert_contained(r#"SIZE(2" onclick="x): block"#);
+    assert_contained(r#"LANG(en" onclick="x): block"#);
+    assert_contained("| COLOR(red\" onclick=\"x): a | b |\n| c | d |");
+}
+
+#[test]
+fn test_size_style_injection() {
+    for input in [
+        "SIZE(1;color:red): block",
+        "&size(1rem;background:url(x)){a};",
+        "| SIZE(1;color:red): a | b |\n| c | d |",
+    ] {
+        let html = assert_contained(input);
+        assert!(!html.contains("color:red"), "{}", html);
+        assert!(!html.contains("background:url"), "{}", html);
+    }
+}
+
+#[test]
+fn test_plugin_arguments() {
+    assert_contained(r#"@youtube(x" onerror="y)"#);
+    assert_contained("&mark(<script>x</script>);");
+}
+
+#[test]
+fn test_umd_table_cells() {
+    for input in [
+        "| <b>x</b> |",
+        "| a |> | <i>x</i> |",
+        "| COLOR(red): \"><script>x</script> |",
+        "| a |h\n| <img src=x onerror=y> |",
+        "| &badge(info){<script>x</script>}; | &icon(\"><img src=x onerror=y>); |",
+    ] {
+        let html = assert_contained(input);
+        assert!(html.contains("&lt;"), "{}", html);
+    }
+}
+
+#[test]
+fn test_encoded_dangerous_schemes() {
+    for url in ["&#106;avascript:alert(1)", "java&#x09;script:alert(1)"] {
+        let html = assert_contained(&format!("&badge(primary){{[x]({})}};", url));
+        assert!(html.contains(r##"href="#blocked-url""##), "{}", html);
+    }
+}
+
+#[test]
+fn test_table_plugin_classes() {
+    let html = assert_contained("@table(striped){{| a |}}");
+    assert!(
+        html.contains(r#"<table class="table umd-table table-striped">"#),
+        "{}",
+        html
+    );
+}
+
+#[test]
+fn test_marker_spoofing() {
+    for (input, literal) in [
+        (
+            "{{BLOCK_DECORATION_B64:PHNjcmlwdD4=:BLOCK_DECORATION_B64}}",
+            "{{BLOCK_DECORATION_B64:PHNjcmlwdD4=:BLOCK_DECORATION_B64}}",
+        ),
+        (
+            "{{UMD_BLOCKQUOTE:<b>x</b>:UMD_BLOCKQUOTE}}",
+            "{{UMD_BLOCKQUOTE:&lt;b&gt;x&lt;/b&gt;:UMD_BLOCKQUOTE}}",
+        ),
+        ("{{UMD_BLOCKQUOTE_START}}", "{{UMD_BLOCKQUOTE_START}}"),
+        ("a {{UNDERLINE:x:UNDERLINE}} b", "{{UNDERLINE:x:UNDERLINE}}"),
+        (
+            "{{INLINE_PLUGIN:kbd::PHNjcmlwdD4=:INLINE_PLUGIN}}",
+            "{{INLINE_PLUGIN:kbd::PHNjcmlwdD4=:INLINE_PLUGIN}}",
+        ),
+        (
+            "{{BLOCK_PLUGIN:foo:x:PGI+:BLOCK_PLUGIN}}",
+            "{{BLOCK_PLUGIN:foo:x:PGI+:BLOCK_PLUGIN}}",
+        ),
+        (
+            "UMD_TABLE_MARKER_0_END\n\n| a |",
+            "<p>UMD_TABLE_MARKER_0_END</p>",
+        ),
+        ("`x` UMD_CODE_MASK_0_END", "UMD_CODE_MASK_0_END"),
+    ] {
+        let html = assert_contained(input);
+        assert!(html.contains(literal), "{:?}: {}", input, html);
+        assert!(!html.contains('\u{FDD0}'), "{}", html);
+        assert!(!html.contains("<template"), "{}", html);
+        assert!(!html.contains("<blockquote"), "{}", html);
+    }
+
+    // Real markers still work next to spoofed ones
+    let html = parse("__real__ {{UNDERLINE:x:UNDERLINE}}\n\n| a |");
+    assert!(
+        html.contains("<u>real</u> {{UNDERLINE:x:UNDERLINE}}"),
+        "{}",
+        html
+    );
+    assert_eq!(html.matches("<table").count(), 1, "{}", html);
+}
+
+#[test]
+fn test_encoded_plugin_content() {
+    use base64::{Engine as _, engine::general_purpose};
+
+    static TEMPLATE: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(concat!(
+            r#"<template class="umd-plugin umd-plugin-foo""#,
+            r#"( data-umd-args="([^"]*)")?( data-umd-content="([^"]*)")?>(.*?)</template>"#
+        ))
+        .unwrap()
+    });
+    let decode =
+        |value: &str| String::from_utf8(general_purpose::STANDARD.decode(value).unwrap()).unwrap();
+
+    let options = options_from_json(r#"{"pluginContent":"encoded"}"#).unwrap();
+    for source in [
+        r#"" onmouseover="alert(1)"#,
+        r#""><script>alert(1)</script>"#,
+        "<img src=x onerror=alert(1)>",
+        "&#34; onmouseover=&#34;alert(1)",
+        "&lt;b&gt; & 日本語",
+    ] {
+        for input in [
+            format!("&foo({}){{{}}};", source, source),
+            format!("@foo({})", source),
+            format!("@foo(x){{{{{}}}}}", source),
+        ] {
+            let html = parse_with_frontmatter_opts(&input, &options).html;
+            let caps = TEMPLATE
+                .captures(&html)
+                .unwrap_or_else(|| panic!("No plugin template for {:?}: {}", input, html));
+            // Nothing a frontend could inject: the template has no children
+            assert_eq!(&caps[5], "", "{:?}: {}", input, html);
+            // The host gets the text back, character references decoded
+            let payload = html_escape::decode_html_entities(source);
+            let args: Vec<String> = caps.get(2).map_or(vec![], |args| {
+                args.as_str().split(',').map(decode).collect()
+            });
+            let content = caps.get(4).map(|content| decode(content.as_str()));
+            if input.starts_with('&') {
+                assert_eq!(args.join(","), payload, "{}", html);
+                assert_eq!(content.as_deref(), Some(payload.as_ref()), "{}", html);
+            } else if input.contains("{{") {
+                assert_eq!(
+                    content.as_deref().map(str::trim),
+                    Some(payload.as_ref()),
+                    "{}",
+                    html
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_trusted_raw_html() {
+    let trusted = options_from_json(r#"{"trustedHtml":{"tags":["span","sup"]}}"#).unwrap();
+    let cases = [
+        (
+            "x<sup>2</sup> <span title=\"a ||b|| %%c%% example.com @user\">t</span>",
+            r#"<p>x<sup>2</sup> <span title="a ||b|| %%c%% example.com @user">t</span></p>"#,
+        ),
+        (
+            "<span title='\" onmouseover=\"alert(1)'>t</span>",
+            r#"<p><span title="&quot; onmouseover=&quot;alert(1)">t</span></p>"#,
+        ),
+        (
+            "<span onclick=\"alert(1)\">t</span>",
+            "<p>&lt;span onclick=&quot;alert(1)&quot;&gt;t&lt;/span&gt;</p>",
+        ),
+        ("<b>x</b>", "<p>&lt;b&gt;x&lt;/b&gt;</p>"),
+        (
+            "[x](javascript:alert(1)) <javascript:alert(1)>",
+            r##"<p><a href="#blocked-url">x</a> &lt;javascript:alert(1)&gt;</p>"##,
+        ),
+        ("UMD_RAW_HTML_0_END", "<p>UMD_RAW_HTML_0_END</p>"),
+    ];
+    for (input, expected) in cases {
+        let html = parse_with_frontmatter_opts(input, &trusted).html;
+        assert_eq!(html.trim(), expected, "{}", input);
+    }
+
+    // Untrusted (the default) still escapes every tag
+    let html = parse("x<sup>2</sup>");
+    assert!(html.contains("&lt;sup&gt;2&lt;/sup&gt;"), "{}", html);
+}
+
+const PAYLOADS: &[&str] = &[
+    r#"" onmouseover="alert(1)"#,
+    r#"' onmouseover='alert(1)"#,
+    r#""><script>alert(1)</script>"#,
+    "x onmouseover=alert(1)",
+    "&quot; onmouseover=&quot;alert(1)",
+    "&#34; onmouseover=&#34;alert(1)",
+    "`onmouseover=alert(1)",
+    "javascript:alert(1)",
+    "&#106;avascript:alert(1)",
+    "java&#x09;script:alert(1)",
+];
+
+/// Input templates; `P` is replaced by each payload
+const TEMPLATES: &[&str] = &[
+    // Attribute blocks
+    "- P {.x}",
+    "{.x}\n- P",
+    "# P {.x}",
+    "# x {title=\"P\"}",
+    "x {data-a=\"P\"}",
+    "{aria-label=\"P\"}\n> x",
+    "![x](a.png){title=\"P\"}",
+    "```rust {data-a=\"P\"}\nx\n```",
+    // Inline decorations
+    "&time(P){x};",
+    "&time(2024){P};",
+    "&data(P){x};",
+    "&lang(P){x};",
+    "&abbr(P){x};",
+    "&abbr(x){P};",
+    "&badge(P){x};",
+    "&badge(primary){[x](P)};",
+    "&badge(primary){[x](/a \"P\")};",
+    "&color(P){x};",
+    "&color(red,P){x};",
+    "&color(red){[x](P)};",
+    "&size(P){x};",
+    "&ruby(P){x};",
+    "&bdo(P){x};",
+    "&bdi(P);",
+    "&dfn(P){x};",
+    "&kbd(P){x};",
+    "&sup(P);",
+    "&hover(P){x};",
+    "&popover(P){x};",
+    "&popover(x){P};",
+    "&ref(P);",
+    "&ref(P){x};",
+    "&math(P);",
+    "&spoiler{P};",
+    "&fn(P);",
+    "&mark(P);",
+    "&foo(P){x};",
+    // Block plugins
+    "@youtube(P)",
+    "@vimeo(P)",
+    "@niconico(P)",
+    "@map(P)",
+    "@card(P)",
+    "@callout(P){{x}}",
+    "@detail(P){{x}}",
+    "@popover(P){{x}}",
+    "@gallery(P){{x}}",
+    "@math(P)",
+    "@toc(P)",
+    "@table(P){{| a |}}",
+    "@foo(P)",
+    "@foo(x){{P}}",
+    // Block decorations
+    "COLOR(P): x",
+    "COLOR(red,P): x",
+    "SIZE(P): x",
+    "LANG(P): x",
+    // Tables
+    "| P |",
+    "| a | P |\n| b | c |",
+    "| a |h\n| P |",
+    "| COLOR(P): a | b |\n| c | d |",
+    "| SIZE(P): a |",
+    "| LANG(P): a |",
+    "| a |\n|---|\n| P |",
+    "@@colwidths(P)\n| a |",
+    // Headings, links and media
+    "# T {#P}",
+    "[x](P)",
+    "[x](/a \"P\")",
+    "[x](/a){#P}",
+    "[x](/a){.P}",
+    "![P](/a.png)",
+    "![x](P)",
+    "![x](/a.png \"P\")",
+    "[x](https://youtube.com/watch?v=P)",
+    "https://example.com/P",
+    "<P>",
+    // References, lists and code
+    "x[^1]\n\n[^1]: P",
+    "x[^1]\n\n[^1]: [x](P)",
+    ":P|x",
+    "> [!NOTE] P",
+    "> P <",
+    "```P\nx\n```",
+    "```rust title=P\nx\n```",
+    "`P`",
+    "@P",
+    "#P",
+    "---\ntitle: P\ncover: P\n---\n\nx",
+];
+
+/// Options JSON of each profile the matrix runs under
+const PROFILES: &[&str] = &[
+    "{}",
+    r#"{"printMode":true}"#,
+    r#"{"profile":"email-safe"}"#,
+    r#"{"profile":"epub"}"#,
+    r#"{"framework":"semantic"}"#,
+    r#"{"baseUrl":"/app","mentionBase":"/u/","tagBase":"/t/"}"#,
+    r#"{"colorMode":"adaptive","footnotes":{"tooltips":true},"anchors":{"style":"wrap"}}"#,
+    r#"{"pluginContent":"encoded"}"#,
+];
+
+static TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<([a-zA-Z][a-zA-Z0-9:-]*)([^>]*)>").unwrap());
+
+static ATTRIBUTE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"^\s*([^\s"'>/=`]+)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'=<>`]+)))?"#).unwrap()
+});
+
+const URL_ATTRIBUTES: &[&str] = &[
+    "href",
+    "src",
+    "action",
+    "formaction",
+    "poster",
+    "cite",
+    "data",
+];
+
+/// Describe the first injected construct in `html`, if any
+fn find_injection(html: &str) -> Option<String> {
+    if html.contains("<script") {
+        return Some("<script> element".to_string());
+    }
+    for tag in TAG.captures_iter(html) {
+        let mut rest = tag[2].trim_end_matches('/');
+        while !rest.trim().is_empty() {
+            let Some(attribute) = ATTRIBUTE.captures(rest) else {
+                return Some(format!("malformed attributes in {}", &tag[0]));
+            };
+            let name = attribute[1].to_ascii_lowercase();
+            if name.starts_with("on") {
+                return Some(format!("event handler in {}", &tag[0]));
+            }
+            let value = (2..=4)
+                .find_map(|group| attribute.get(group))
+                .map_or("", |value| value.as_str());
+            let url: String = html_escape::decode_html_entities(value)
+                .chars()
+                .filter(|ch| !ch.is_whitespace() && !ch.is_control())
+                .collect();
+            if URL_ATTRIBUTES.contains(&name.as_str())
+                && url.to_ascii_lowercase().starts_with("javascript:")
+            {
+                return Some(format!("javascript: URL in {}", &tag[0]));
+            }
+            rest = &rest[attribute[0].len()..];
+        }
+    }
+    None
+}
+
+#[test]
+fn test_injection_matrix() {
+    let profiles: Vec<_> = PROFILES
+        .iter()
+        .map(|json| options_from_json(json).unwrap())
+        .collect();
+    let mut failures = Vec::new();
+    for template in TEMPLATES {
+        for payload in PAYLOADS {
+            let input = template.replace('P', payload);
+            for (json, options) in PROFILES.iter().zip(&profiles) {
+                let result = parse_with_frontmatter_opts(&input, options);
+                let html = format!("{}\n{}", result.html, result.footnotes.unwrap_or_default());
+                if let Some(problem) = find_injection(&html) {
+                    failures.push(format!("{:?} with {}: {}", input, json, problem));
+                }
+            }
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
@@ -124,3 +124,25 @@ fn test_relative_link_has_no_warning_marker() {
     assert!(!result.html.contains("umd-idn-warning-link"));
     assert!(!result.html.contains("data-idn-warning"));
 }
+
+#[test]
+fn test_base_url_links_are_internal_to_site_origin() {
+    let input = "[docs](/docs) [other](https://example.org/) `https://example.org/`";
+    let opts = ParserOptions {
+        base_url: Some("https://example.com/app".to_string()),
+        site_origin: Some("https://example.com".to_string()),
+        external_links: Some(umd::parser::ExternalLinkOptions::default()),
+        ..Default::default()
+    };
+
+    let result = parse_with_frontmatter_opts(input, &opts);
+    assert!(
+        result
+            .html
+            .contains(r#"<a href="https://example.com/app/docs">docs</a>"#)
+    );
+    assert!(result.html.contains(
+        r#"<a href="https://example.org/" class="external" rel="noopener noreferrer">other</a>"#
+    ));
+    assert!(result.html.contains("<code>https://example.org/</code>"));
+}
@@ -11,6 +11,15 @@ fn test_bootstrap_table_default_class() {
     assert!(output.contains(r#"<table class="table">"#));
 }
 
+#[test]
+fn test_bootstrap_list_group_attributes() {
+    let input = "{.list-group}\n- One {.list-group-item}\n- Two {.list-group-item .active}";
+    let output = parse(input);
+    assert!(output.contains(r#"<ul class="list-group">"#), "{}", output);
+    assert!(output.contains(r#"<li class="list-group-item">One</li>"#));
+    assert!(output.contains(r#"<li class="list-group-item active">Two</li>"#));
+}
+
 #[test]
 fn test_bootstrap_blockquote_default_class() {
     // Note: Due to sanitization, Markdown blockquote syntax (>) is escaped.
@@ -147,6 +156,36 @@ fn test_block_alignment() {
     let input = "CENTER: Centered text";
     let output = parse(input);
     assert!(output.contains(r#"class="text-center""#));
+    assert!(!output.contains("<p><p"), "{}", output);
+}
+
+#[test]
+fn test_block_decoration_targets_following_block() {
+    let output = parse("CENTER:\n## Title");
+    assert!(output.contains(r#"<h2 class="text-center">"#), "{}", output);
+    assert!(!output.contains("CENTER:"), "{}", output);
+
+    let output = parse("COLOR(red): LANG(en):\n- a\n- b");
+    assert!(
+        output.contains(r#"<ul class="text-red" lang="en">"#),
+        "{}",
+        output
+    );
+
+    let output = parse("RIGHT:\n> quote <");
+    assert!(
+        output.contains(r#"<blockquote class="umd-blockquote text-end">quote</blockquote>"#),
+        "{}",
+        output
+    );
+    assert!(!output.contains("<p><blockquote"), "{}", output);
+
+    let output = parse("COLOR(,warning):\n| a | b |\n|---|---|\n| 1 | 2 |");
+    assert!(
+        output.contains(r#"<table class="table bg-warning">"#),
+        "{}",
+        output
+    );
 }
 
 #[test]
@@ -175,6 +214,60 @@ fn test_block_placement_justify_for_umd_table() {
     assert!(!output.contains("<p>JUSTIFY:</p>"));
 }
 
+#[test]
+fn test_plugins_and_decorations_in_umd_table_cells() {
+    let input =
+        "| &icon(star); | @clear() |h\n| COLOR(red): &color(blue){x}; | &badge(info){New}; |";
+    let output = parse(input);
+    assert!(!output.contains("PLUGIN"), "output: {}", output);
+    assert!(
+        output.contains("<td><template class=\"umd-plugin umd-plugin-icon\">"),
+        "output: {}",
+        output
+    );
+    assert!(
+        output.contains("<div class=\"clearfix\"></div>"),
+        "output: {}",
+        output
+    );
+    assert!(
+        output.contains("<td class=\"text-red\"><span class=\"text-blue\">x</span></td>"),
+        "output: {}",
+        output
+    );
+    assert!(
+        output.contains("<span class=\"badge bg-info\">New</span>"),
+        "output: {}",
+        output
+    );
+}
+
+#[test]
+fn test_float_media_placement_and_clear_diagnostic() {
+    let options = umd::parser::ParserOptions::default();
+    let input = "FLOAT-LEFT:\n![Logo](logo.png)\nText wraps around the logo.\n\nMore text.";
+    let result = umd::parse_with_frontmatter_opts(input, &options);
+    assert!(
+        result
+            .html
+            .starts_with("<figure class=\"float-start me-3\">\n<picture>"),
+        "output: {}",
+        result.html
+    );
+    assert!(
+        result
+            .html
+            .contains("</figure>\n<p>Text wraps around the logo.</p>"),
+        "output: {}",
+        result.html
+    );
+    assert_eq!(result.diagnostics.len(), 1);
+    assert_eq!(result.diagnostics[0].code, "float-without-clear");
+
+    let result = umd::parse_with_frontmatter_opts(&format!("{}\n\n@clear()", input), &options);
+    assert!(result.diagnostics.is_empty(), "{:?}", result.diagnostics);
+}
+
 #[test]
 fn test_block_placement_center_for_block_plugin() {
     let input = "CENTER:\n@callout(info)";
@@ -196,6 +289,91 @@ fn test_compound_prefixes() {
     assert!(output.contains("text-center"));
 }
 
+#[test]
+fn test_block_lang_prefix() {
+    let input = "LANG(ja): 日本語の段落\n\nCENTER: LANG(en): English";
+    let output = parse(input);
+    assert!(
+        output.contains(r#"lang="ja">日本語の段落"#),
+        "output: {}",
+        output
+    );
+    assert!(output.contains(r#"class="text-center" lang="en">English"#));
+    assert!(!output.contains("LANG("));
+}
+
+#[test]
+fn test_block_direction_prefixes() {
+    let output = parse("RTL: مرحبا\n\nLTR: Hello &bdo(rtl){abc};");
+    assert!(
+        output.contains(r#"class="text-end" dir="rtl">مرحبا"#),
+        "output: {}",
+        output
+    );
+    assert!(output.contains(r#"dir="ltr">Hello"#), "output: {}", output);
+    assert!(
+        output.contains(r#"<bdo dir="rtl">abc</bdo>"#),
+        "output: {}",
+        output
+    );
+}
+
+#[test]
+fn test_css_color_formats() {
+    let output = parse("&color(rgb(255 0 0),hsl(210, 50%, 40%)){a};");
+    assert!(
+        output.contains(r#"style="color: rgb(255 0 0); background-color: hsl(210, 50%, 40%)">a"#),
+        "output: {}",
+        output
+    );
+
+    let output = parse("COLOR(rebeccapurple): Text");
+    assert!(
+        output.contains(r#"style="color: rebeccapurple""#),
+        "output: {}",
+        output
+    );
+
+    // Table cells used to emit any value verbatim
+    let output = parse("| COLOR(navy): a | COLOR(red;x:1): b |\n| c | d |");
+    assert!(
+        output.contains(r#"<td style="color: navy">a</td>"#),
+        "output: {}",
+        output
+    );
+    assert!(output.contains("<td>b</td>"), "output: {}", output);
+}
+
+#[test]
+fn test_color_opacity_and_gradient() {
+    let output = parse("COLOR(primary, , 50): Text");
+    assert!(
+        output.contains(r#"class="text-primary text-opacity-50""#),
+        "output: {}",
+        output
+    );
+
+    let output = parse("&color(gradient-primary){a}; &color(,warning,75){b};");
+    assert!(
+        output.contains(r#"<span class="bg-primary bg-gradient">a</span>"#),
+        "output: {}",
+        output
+    );
+    assert!(
+        output.contains(r#"<span class="bg-warning bg-opacity-75">b</span>"#),
+        "output: {}",
+        output
+    );
+
+    // Unsupported opacity levels are ignored
+    let output = parse("| COLOR(info,,33): x | y |\n| a | b |");
+    assert!(
+        output.contains(r#"<td class="text-info">x</td>"#),
+        "output: {}",
+        output
+    );
+}
+
 #[test]
 fn test_table_cell_vertical_alignment_top() {
     let input = "| TOP: Header |\n|-------------|\n| Cell        |";
@@ -423,6 +601,62 @@ fn test_popover_inline_support() {
     assert!(output.contains("短い補足説明です"), "output: {}", output);
 }
 
+#[test]
+fn test_verse_block() {
+    let input = "@verse{{\n古池や\n  蛙飛び込む\n- 水の音\n\n*二連目*\n}}";
+    let output = parse(input);
+
+    assert_eq!(
+        output,
+        "<p class=\"verse\">古池や<br />\n\u{a0}\u{a0}蛙飛び込む<br />\n- 水の音</p>\n<p class=\"verse\"><em>二連目</em></p>"
+    );
+}
+
+#[test]
+fn test_toc_block() {
+    let input = "@toc(2)\n\n# Guide\n\n## Setup {#setup}\n\n### Linux\n\n## Setup {#setup}";
+    let output = parse(input);
+
+    assert!(
+        output.starts_with(
+            "<nav class=\"umd-toc\" aria-label=\"Table of contents\">\n<ol class=\"nav flex-column\">"
+        ),
+        "output: {}",
+        output
+    );
+    // Links follow the deduplicated heading IDs
+    assert!(output.contains(r##"<a class="nav-link" href="#h-setup">Setup</a>"##));
+    assert!(output.contains(r##"<a class="nav-link" href="#h-setup-1">Setup</a>"##));
+    assert!(!output.contains(r##"class="nav-link" href="#h-3""##));
+    assert!(!output.contains("<template"), "output: {}", output);
+}
+
+#[test]
+fn test_gallery_block() {
+    let input = "@gallery(2){{\n![Cat](/img/cat.jpg \"Tama\") ![Dog](/img/dog.jpg)\n}}";
+    let output = parse(input);
+
+    assert!(
+        output.starts_with(
+            r#"<div class="umd-gallery row row-cols-1 row-cols-md-2 g-2" id="gallery-"#
+        ),
+        "output: {}",
+        output
+    );
+    assert!(
+        output.contains(r#"<div class="col"><a href="/img/cat.jpg" data-gallery="gallery-"#),
+        "output: {}",
+        output
+    );
+    assert!(
+        output.contains(r#"title="Tama"><img src="/img/cat.jpg" alt="Cat" loading="lazy" class="img-fluid" /></a></div>"#),
+        "output: {}",
+        output
+    );
+    assert_eq!(output.matches("class=\"col\"").count(), 2);
+    assert!(!output.contains("<template"), "output: {}", output);
+}
+
 #[test]
 fn test_popover_block_support() {
     let input = "@popover(詳細を表示){{\n**重要**\n\n- 項目1\n- 項目2\n}}";
@@ -516,3 +750,32 @@ fn test_inline_code_non_color_does_not_add_swatch() {
     );
     assert!(!output.contains("inline-code-color"), "output: {}", output);
 }
+
+#[test]
+fn test_attachment_links() {
+    let input = "See &attach(spec v2.pdf){Spec}; and &attach(data.csv);\n\n&attach(../etc/passwd);";
+    assert!(umd::parse(input).contains("<template class=\"umd-plugin umd-plugin-attach\">"));
+
+    let options = umd::parser::ParserOptions {
+        attachment_url: Some("/files/{name}".to_string()),
+        ..Default::default()
+    };
+    let output = umd::parse_with_frontmatter_opts(input, &options).html;
+    assert!(
+        output.contains(
+            "<a href=\"/files/spec%20v2.pdf\" download class=\"download-link attachment\" data-attachment=\"spec v2.pdf\" aria-label=\"Download Spec\">"
+        ),
+        "output: {}",
+        output
+    );
+    assert!(
+        output.contains("data.csv <span class=\"download-size\" data-size=\"\"></span></a>"),
+        "output: {}",
+        output
+    );
+    assert!(
+        output.contains("<template class=\"umd-plugin umd-plugin-attach\">"),
+        "output: {}",
+        output
+    );
+}
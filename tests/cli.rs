@@ -0,0 +1,88 @@
+//! Tests of the `umd` binary (`cargo test --features cli`)
+#![cfg(feature = "cli")]
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn run(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_umd"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("umd binary");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_stdin_to_html() {
+    let output = run(&["--base-url", "/app", "--toc"], "# Title\n\n[docs](/docs)");
+    assert!(output.status.success());
+    let html = String::from_utf8(output.stdout).unwrap();
+    assert!(html.starts_with("<nav class=\"umd-toc\""), "{}", html);
+    assert!(html.contains("href=\"/app/docs\""), "{}", html);
+}
+
+#[test]
+fn test_frontmatter_json_and_options_file() {
+    let dir = std::env::temp_dir().join(format!("umd-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("page.umd");
+    let options = dir.join("options.json");
+    std::fs::write(&input, "---\ntitle: \"Hello\"\n---\n\n> Quote").unwrap();
+    std::fs::write(&options, r#"{"outputFormat":"minified"}"#).unwrap();
+
+    let output = run(&[input.to_str().unwrap(), "--frontmatter-json"], "");
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "{\"title\":\"Hello\"}\n"
+    );
+
+    let output = run(
+        &[
+            input.to_str().unwrap(),
+            "--options-file",
+            options.to_str().unwrap(),
+        ],
+        "",
+    );
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "<blockquote class=\"blockquote\"><p>Quote</p></blockquote>\n"
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_fmt_subcommand() {
+    let output = run(&["fmt"], "Title\n=====\n\n\n* a\n* b  \n");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "# Title\n\n- a\n- b\n"
+    );
+
+    let output = run(&["fmt", "--toc"], "");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_invalid_arguments() {
+    let output = run(&["--unknown"], "");
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = run(&["--options-file", "/nonexistent/options.json"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .starts_with("umd: /nonexistent")
+    );
+}
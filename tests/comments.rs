@@ -142,3 +142,15 @@ fn test_url_with_double_slash_not_comment() {
     // URLの//はコメントではない（hの直後だから）
     assert!(result.html.contains("https://example.com"));
 }
+
+#[test]
+fn test_comment_plugins_are_dropped() {
+    let input = "# 見出し\n@comment(レビュー){{\n**未公開のメモ**\n}}\n本文&comment(確認);の続き\n\n`&comment(コード);`";
+    let result = parse_with_frontmatter(input);
+
+    assert!(!result.html.contains("未公開のメモ"));
+    assert!(!result.html.contains("確認"));
+    assert!(!result.html.contains("<template"));
+    assert!(result.html.contains("<p>本文の続き</p>"));
+    assert!(result.html.contains("<code>&amp;comment(コード);</code>"));
+}
@@ -3,7 +3,8 @@
 //! This test suite verifies that the parser reasonably complies with CommonMark specification.
 //! Target: 75%+ pass rate
 
-use umd::parser::{ParserOptions, parse_to_html};
+use umd::parse_with_frontmatter_opts;
+use umd::parser::{Dialect, ParserOptions, parse_to_html};
 
 /// Test CommonMark heading levels
 #[test]
@@ -247,3 +248,60 @@ fn main() {}
     assert!(html.contains("fn main() {}"));
     assert!(html.contains("<hr"));
 }
+
+fn parse_dialect(input: &str, dialect: Dialect) -> String {
+    let options = ParserOptions {
+        dialect,
+        ..ParserOptions::default()
+    };
+    parse_with_frontmatter_opts(input, &options).html
+}
+
+/// Test that the CommonMark dialect leaves UMD and GFM syntax alone
+#[test]
+fn test_commonmark_dialect() {
+    let input = "# Title\n\n50%% off, ''quoted'', &color(red){x}; ||x||\n\n> quote <\n\n<https://example.com> ~~del~~\n\n<b>raw</b>";
+    let html = parse_dialect(input, Dialect::CommonMark);
+    assert!(html.contains("<h1>Title</h1>"), "{}", html);
+    assert!(
+        html.contains("50%% off, ''quoted'', &amp;color(red){x}; ||x||"),
+        "{}",
+        html
+    );
+    assert!(html.contains("<blockquote>\n<p>quote &lt;</p>"), "{}", html);
+    assert!(
+        html.contains(r#"<a href="https://example.com">https://example.com</a> ~~del~~"#),
+        "{}",
+        html
+    );
+    assert!(!html.contains("<b>raw</b>"), "{}", html);
+}
+
+/// Test that the GFM dialect adds GFM syntax only
+#[test]
+fn test_gfm_dialect() {
+    let html = parse_dialect("~~del~~ %%kept%%\n\n| a |\n|---|\n| 1 |", Dialect::Gfm);
+    assert!(html.contains("<del>del</del> %%kept%%"), "{}", html);
+    assert!(html.contains("<table>"), "{}", html);
+}
+
+/// Test that the UMD dialect drops LukiWiki legacy emphasis only
+#[test]
+fn test_umd_dialect() {
+    let input = "50%% off and 20%% more, ''quoted'' &color(red){x};";
+    let html = parse_dialect(input, Dialect::Umd);
+    assert!(
+        html.contains("50%% off and 20%% more, ''quoted''"),
+        "{}",
+        html
+    );
+    assert!(
+        html.contains(r#"<span class="text-red">x</span>"#),
+        "{}",
+        html
+    );
+
+    let html = parse_dialect(input, Dialect::LukiWiki);
+    assert!(html.contains("<s> off and 20</s>"), "{}", html);
+    assert!(html.contains("<b>quoted</b>"), "{}", html);
+}
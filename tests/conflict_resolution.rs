@@ -11,6 +11,20 @@ fn test_umd_blockquote() {
     assert!(output.contains("This is a UMD-style blockquote"));
 }
 
+#[test]
+fn test_multiline_umd_blockquote() {
+    let input = "Intro\n\n> First line\nwith **bold**\n\n- one\n- two\n<\n\n> single <";
+    let output = parse(input);
+    assert!(
+        output.contains("<blockquote class=\"umd-blockquote\">\n<p>First line\nwith <strong>bold</strong></p>\n<ul>"),
+        "{}",
+        output
+    );
+    assert!(output.contains("</ul>\n</blockquote>"), "{}", output);
+    assert!(output.contains("<blockquote class=\"umd-blockquote\">single</blockquote>"));
+    assert!(!output.contains("&lt;"), "{}", output);
+}
+
 #[test]
 fn test_emphasis_coexistence() {
     let input = "**Markdown bold** and UMD bold";
@@ -63,9 +77,9 @@ fn test_color_decoration() {
 
 #[test]
 fn test_plugin_syntax_preserved() {
-    let input = "@toc(){{ }}";
+    let input = "@card(){{ }}";
     let output = parse(input);
-    assert!(output.contains(r#"class="umd-plugin umd-plugin-toc""#));
+    assert!(output.contains(r#"class="umd-plugin umd-plugin-card""#));
 }
 
 #[test]
@@ -96,6 +110,91 @@ fn test_inline_code_not_processed() {
     assert!(output.contains("<code>"));
 }
 
+#[test]
+fn test_umd_syntax_inside_inline_code() {
+    let output = parse("Define `__init__`, `||secret||`, `%%gone%%` and `&color(red){x};` here");
+    assert!(output.contains("<code>__init__</code>"), "{}", output);
+    assert!(output.contains("<code>||secret||</code>"), "{}", output);
+    assert!(output.contains("<code>%%gone%%</code>"), "{}", output);
+    assert!(
+        output.contains("<code>&amp;color(red){x};</code>"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn test_escaped_umd_markers() {
+    let output = parse(
+        "\\&color(red){x}; \\||text|| \\%%kept%% \\''kept'' \\@plugin(x)\n\n\\COLOR(red): literal",
+    );
+    assert!(output.contains("&amp;color(red){x};"), "{}", output);
+    assert!(output.contains("||text||"), "{}", output);
+    assert!(output.contains("%%kept%%"), "{}", output);
+    assert!(output.contains("''kept''"), "{}", output);
+    assert!(output.contains("@plugin(x)"), "{}", output);
+    assert!(output.contains("<p>COLOR(red): literal</p>"), "{}", output);
+    assert!(!output.contains("UMD_CODE_MASK"), "{}", output);
+    assert!(!output.contains("text-red"), "{}", output);
+}
+
+#[test]
+fn test_umd_syntax_inside_code_block() {
+    let input = "```\n__init__ a < b\n||x|| %%y%%\n@plugin(x){{ y }}\n:term|definition\n- [-] task\n// kept\n```\n\nafter";
+    let output = parse(input);
+    assert!(
+        output.contains(
+            "<pre>__init__ a &lt; b\n||x|| %%y%%\n@plugin(x){{ y }}\n:term|definition\n- [-] task\n// kept\n</pre>"
+        ),
+        "{}",
+        output
+    );
+    assert!(output.contains("<p>after</p>"), "{}", output);
+}
+
+#[test]
+fn test_code_inside_block_plugin_content() {
+    let output = parse("@details(t){{\n```\nz\n```\n}}");
+    assert!(output.contains("```\nz\n```"), "{}", output);
+    assert!(!output.contains("UMD_CODE_MASK"), "{}", output);
+}
+
+#[test]
+fn test_table_inside_block_plugin_content() {
+    let input = "@box(){{\n| ''a'' | &icon(star); |h\n|>| CENTER: b |\nRIGHT: c\n}}\n\n| d |";
+    let output = parse(input);
+    assert!(
+        output.contains(
+            "<template class=\"umd-plugin umd-plugin-box\">\n\
+             | ''a'' | &amp;icon(star); |h\n|&gt;| CENTER: b |\nRIGHT: c\n</template>"
+        ),
+        "{}",
+        output
+    );
+    assert!(output.contains("<td>d</td>"), "{}", output);
+}
+
+#[test]
+fn test_code_and_lists_inside_block_plugin_content() {
+    let input = "@box(){{\n```rust:main.rs\nx }}\n```\n}}\n\n- item\n\n  @box(){{\n  - a\n  - [/] b\n  # H {#id}\n  }}\n\n# Title";
+    let output = parse(input);
+    assert!(
+        output.contains(
+            "<template class=\"umd-plugin umd-plugin-box\">\n- a\n- [/] b\n# H {#id}\n</template></p>\n</li>\n</ul>"
+        ),
+        "{}",
+        output
+    );
+    assert!(
+        output.contains(
+            "<template class=\"umd-plugin umd-plugin-box\">\n```rust:main.rs\nx }}\n```\n</template>"
+        ),
+        "{}",
+        output
+    );
+    assert!(output.contains("id=\"h-1\""), "{}", output);
+}
+
 #[test]
 fn test_no_false_positive_warnings() {
     let input = "# Heading\n\n**Bold** text";
@@ -120,3 +219,36 @@ fn test_multiline_content() {
     // blue is now a Bootstrap color, so it should output a class
     assert!(output.contains(r#"class="text-blue""#));
 }
+
+#[test]
+fn test_lukiwiki_emphasis_tags() {
+    let input = "''Tom's book'' and '''it's''' **md**";
+    let output = parse(input);
+    assert!(
+        output.contains("<b>Tom's book</b> and <i>it's</i> <strong>md</strong>"),
+        "{}",
+        output
+    );
+
+    let options = umd::options_from_json(r#"{"emphasisTags":"semantic"}"#).unwrap();
+    let output = umd::parse_with_frontmatter_opts(input, &options).html;
+    assert!(
+        output.contains("<strong>Tom's book</strong> and <em>it's</em> <strong>md</strong>"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn test_inline_plugin_url_args_are_not_autolinked() {
+    let output = parse("Feed &feed(https://example.com/rss?a=1&b=2); and &sup(a<b);");
+    assert!(
+        output.contains(
+            r#"<template class="umd-plugin umd-plugin-feed"><data value="0">https://example.com/rss?a=1&amp;b=2</data></template>"#
+        ),
+        "{}",
+        output
+    );
+    assert!(!output.contains("<a href"), "{}", output);
+    assert!(output.contains("<sup>a&lt;b</sup>"), "{}", output);
+}
@@ -0,0 +1,97 @@
+//! Canonical formatter tests
+//!
+//! Every golden fixture and a few hand-written documents are formatted
+//! with `umd::format()`. Formatting must be idempotent and must not change
+//! the rendered HTML.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use umd::parser::{IdStrategy, ParserOptions};
+
+fn render(input: &str) -> String {
+    let options = ParserOptions {
+        ids: IdStrategy::ContentHash,
+        ..Default::default()
+    };
+    umd::parse_with_frontmatter_opts(input, &options).html
+}
+
+fn assert_canonical(name: &str, input: &str) {
+    let formatted = umd::format(input);
+    assert_eq!(
+        umd::format(&formatted),
+        formatted,
+        "{}: formatting is not idempotent",
+        name
+    );
+    assert_eq!(
+        render(&formatted),
+        render(input),
+        "{}: formatting changed the output",
+        name
+    );
+}
+
+#[test]
+fn test_golden_files_are_unchanged_by_formatting() {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/golden");
+    let mut inputs: Vec<PathBuf> = fs::read_dir(fixtures)
+        .expect("fixtures directory")
+        .map(|entry| entry.expect("fixture entry").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "umd"))
+        .collect();
+    inputs.sort();
+    assert!(!inputs.is_empty(), "no golden fixtures found");
+
+    for path in &inputs {
+        let input = fs::read_to_string(path).expect("fixture input");
+        assert_canonical(&path.file_name().unwrap().to_string_lossy(), &input);
+    }
+}
+
+#[test]
+fn test_messy_document() {
+    let input = "---\r\ntitle: Notes\r\n---\r\n\r\n\r\nNotes   \r\n=====\r\n\r\n\
+        Line one  \r\nline two\t\r\n\r\n\r\n\r\n\
+        * a\r\n* b\r\n    + nested\r\n+ c\r\n\r\n\
+        |Name|Qty|\r\n|:--|--:|\r\n|りんご|3|\r\n|pear|12|\r\n\r\n\
+        COLOR(red):CENTER:Alert\r\n\r\n\
+        ##   Done ###\r\n\r\n\r\n";
+    let formatted = umd::format(input);
+    assert_eq!(
+        formatted,
+        "---\ntitle: Notes\n---\n\n# Notes\n\n\
+         Line one  \nline two\n\n\
+         - a\n- b\n    + nested\n* c\n\n\
+         | Name   | Qty |\n| :----- | --: |\n| りんご |   3 |\n| pear   |  12 |\n\n\
+         COLOR(red): CENTER: Alert\n\n\
+         ## Done\n"
+    );
+    assert_canonical("messy document", input);
+}
+
+#[test]
+fn test_table_pipes_in_code_spans() {
+    // An unclosed backtick does not hide the following pipes
+    let input = "|a|b|\n|-|-|\n|`x|y`|2|\n|``p|`q``|`open|\n";
+    assert_eq!(
+        umd::format(input),
+        "| a        | b     |\n| -------- | ----- |\n| `x|y`    | 2     |\n| ``p|`q`` | `open |\n"
+    );
+    assert_canonical("code spans in table", input);
+}
+
+#[test]
+fn test_code_and_plugins_are_verbatim() {
+    let input =
+        "```rust\nfn main() {   \n\n\n}\n```\n\n@code(){{\n* keep  \n\n\n}}\n\n/*\n+ hidden\n*/\n";
+    assert_eq!(umd::format(input), input);
+    assert_canonical("verbatim", input);
+}
+
+#[test]
+fn test_empty_document() {
+    assert_eq!(umd::format(""), "");
+    assert_eq!(umd::format("\n\n  \n"), "");
+}
@@ -0,0 +1,85 @@
+//! Golden-file snapshot tests
+//!
+//! Every `tests/fixtures/golden/*.umd` file is parsed with `parse()` and
+//! compared with the `.html` file of the same name. To accept new output
+//! (after an intended change, or for a new fixture) regenerate the snapshots
+//! and review the diff:
+//!
+//! ```sh
+//! cargo test --test golden --features bless
+//! ```
+//!
+//! `UMD_BLESS=1` does the same without the feature.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/golden")
+}
+
+fn bless() -> bool {
+    cfg!(feature = "bless") || std::env::var_os("UMD_BLESS").is_some()
+}
+
+/// First differing line, for a readable failure message
+fn first_difference(expected: &str, actual: &str) -> String {
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    let mut line = 1;
+    loop {
+        match (expected_lines.next(), actual_lines.next()) {
+            (Some(e), Some(a)) if e == a => line += 1,
+            (None, None) => return "trailing whitespace differs".to_string(),
+            (e, a) => {
+                return format!(
+                    "line {}\n  expected: {}\n  actual:   {}",
+                    line,
+                    e.unwrap_or("<end of file>"),
+                    a.unwrap_or("<end of file>")
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_golden_files() {
+    let mut inputs: Vec<PathBuf> = fs::read_dir(fixtures_dir())
+        .expect("fixtures directory")
+        .map(|entry| entry.expect("fixture entry").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "umd"))
+        .collect();
+    inputs.sort();
+    assert!(!inputs.is_empty(), "no golden fixtures found");
+
+    let mut failures = Vec::new();
+    for input_path in &inputs {
+        let input = fs::read_to_string(input_path).expect("fixture input");
+        let actual = umd::parse(&input);
+        let expected_path = input_path.with_extension("html");
+
+        if bless() {
+            fs::write(&expected_path, &actual).expect("write snapshot");
+            continue;
+        }
+
+        let name = input_path.file_name().unwrap().to_string_lossy();
+        match fs::read_to_string(&expected_path) {
+            Ok(expected) if expected == actual => {}
+            Ok(expected) => failures.push(format!(
+                "{}: {}",
+                name,
+                first_difference(&expected, &actual)
+            )),
+            Err(_) => failures.push(format!("{}: missing .html snapshot", name)),
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} golden file(s) differ (rerun with `--features bless` to accept):\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}
@@ -0,0 +1,54 @@
+//! Memory stability tests
+//!
+//! Runs in its own test binary: the counting allocator sees every
+//! allocation of the process, so no other test may run alongside.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicIsize, Ordering};
+
+use umd::parse;
+
+/// Bytes currently allocated through the global allocator
+static LIVE_BYTES: AtomicIsize = AtomicIsize::new(0);
+
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LIVE_BYTES.fetch_add(layout.size() as isize, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE_BYTES.fetch_sub(layout.size() as isize, Ordering::Relaxed);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        LIVE_BYTES.fetch_add(new_size as isize - layout.size() as isize, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn test_block_decorations_do_not_leak() {
+    let input = (0..500)
+        .map(|i| format!("TRUNCATE: line {i}\n\nCOLOR(red): TRUNCATE: CENTER: line {i}\n"))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    // Warm up lazily initialized regexes and tables
+    drop(parse(&input));
+    let before = LIVE_BYTES.load(Ordering::Relaxed);
+    for _ in 0..3 {
+        let html = parse(&input);
+        assert_eq!(html.matches("text-truncate").count(), 1000);
+    }
+    let grown = LIVE_BYTES.load(Ordering::Relaxed) - before;
+
+    // 1000 decorated lines per parse; a leaked line would be far above this
+    assert!(grown < 8 * 1024, "{} bytes still allocated", grown);
+}
@@ -0,0 +1,76 @@
+//! Time-budget tests for adversarial input
+//!
+//! Each input repeats a fragment that used to make a pass rescan the rest
+//! of the document for every occurrence. With linear scanning they parse in
+//! well under the budget even in debug builds; a quadratic regression takes
+//! minutes.
+
+use std::time::{Duration, Instant};
+
+use umd::parse;
+
+/// Generous enough for slow CI machines running unoptimized builds
+const BUDGET: Duration = Duration::from_secs(10);
+
+/// Number of times each fragment is repeated
+const REPEAT: usize = 10_000;
+
+fn assert_within_budget(fragment: &str) {
+    let input = fragment.repeat(REPEAT);
+    let start = Instant::now();
+    let html = parse(&input);
+    let elapsed = start.elapsed();
+    assert!(!html.is_empty());
+    assert!(
+        elapsed < BUDGET,
+        "{:?} x {} took {:?}",
+        fragment,
+        REPEAT,
+        elapsed
+    );
+}
+
+#[test]
+fn test_unclosed_inline_decorations() {
+    for fragment in ["&color(red){", "&size(1){", "&ruby(a){", "&size(&size{){"] {
+        assert_within_budget(fragment);
+    }
+}
+
+#[test]
+fn test_duplicate_heading_ids() {
+    assert_within_budget("# a {#b}\n");
+    assert_within_budget("## same\n");
+}
+
+#[test]
+fn test_list_item_attribute_blocks() {
+    for fragment in ["- a {.b}\n", "{.a}\n- b\n\n", "- [-] a\n"] {
+        assert_within_budget(fragment);
+    }
+}
+
+#[test]
+fn test_backslash_escapes_on_one_line() {
+    assert_within_budget("\\\\&a ");
+    assert_within_budget("\\&a ");
+}
+
+#[test]
+fn test_unclosed_delimiters() {
+    for fragment in [
+        "&f{{a}",
+        "&f(a){{a}",
+        "@a(x){{",
+        "[a](https://e.com) {",
+        "``a`",
+        "||a",
+        "%%a",
+        "''a",
+        "[[a",
+        "{{A:",
+        "<a ",
+    ] {
+        assert_within_budget(fragment);
+    }
+}